@@ -0,0 +1,172 @@
+// Switch of the active workdir (same as "<workdir> set-active", also known as asui).
+//
+// The state is the workdirs/active symlink, pointing to workdirs/{workdir}.
+//
+// Delegated to the suibase-daemon (setActiveWorkdir) when running, so its status is
+// updated right away. Otherwise, the symlink is changed here (the daemon, when later
+// started, finds the same state as if the script did it).
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::Error;
+use crate::suibase_daemon_api;
+use crate::suibase_root::SuibaseRoot;
+
+const ACTIVE_SYMLINK: &str = "active";
+
+// Workdir not managed by the suibase-daemon (always switched directly).
+const CARGOBIN_WORKDIR: &str = "cargobin";
+
+// Serialize the direct switches done by this process. The rename of the symlink is
+// atomic, so concurrent switches from other processes are "last one wins".
+static DIRECT_SWITCH_LOCK: Mutex<()> = Mutex::new(());
+
+// Same check as is_workdir_ok in __globals.sh (the "create" was done).
+fn is_workdir_ok(workdir_path: &Path) -> bool {
+    workdir_path.is_dir()
+        && workdir_path.join("sui-exec").is_file()
+        && workdir_path.join("workdir-exec").is_file()
+        && workdir_path
+            .join("config")
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+// Name of the workdir the symlink points to (None when there is no symlink).
+pub(crate) fn active_workdir(workdirs_path: &Path) -> Option<String> {
+    let target = std::fs::read_link(workdirs_path.join(ACTIVE_SYMLINK)).ok()?;
+    Some(target.file_name()?.to_string_lossy().to_string())
+}
+
+// Returns false when the workdir was already active.
+pub(crate) fn set_active_workdir(
+    root: &mut SuibaseRoot,
+    workdir_name: &str,
+) -> Result<bool, Error> {
+    if !root.is_installed() {
+        if root.is_workdirs_missing() {
+            return Err(Error::WorkdirsNotExists {
+                path: root.workdirs_path().to_string(),
+            });
+        }
+        return Err(Error::NotInstalled);
+    }
+    if workdir_name.is_empty() {
+        return Err(Error::WorkdirNameEmpty);
+    }
+    if workdir_name == ACTIVE_SYMLINK {
+        return Err(Error::UnsupportedForWorkdir {
+            workdir: workdir_name.to_string(),
+            what: "set_active_workdir".to_string(),
+        });
+    }
+
+    let workdirs_path = Path::new(root.workdirs_path()).to_path_buf();
+    let workdir_path = workdirs_path.join(workdir_name);
+    if !workdir_path.is_dir() {
+        return Err(Error::WorkdirNotExists);
+    }
+    if !is_workdir_ok(&workdir_path) {
+        return Err(Error::WorkdirInitializationIncomplete {
+            workdir: workdir_name.to_string(),
+        });
+    }
+
+    // The daemon manages only ~/suibase (not an overridden path).
+    if workdir_name != CARGOBIN_WORKDIR && !root.has_suibase_path_override() {
+        match suibase_daemon_api::set_active_workdir(workdir_name) {
+            Ok(is_changed) => return Ok(is_changed),
+            Err(Error::DaemonNotRunning) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    set_active_symlink(&workdirs_path, workdir_name)
+}
+
+// Same result as set_active_symlink_force in __globals.sh, but the symlink is replaced
+// with a rename (never missing for a concurrent reader).
+fn set_active_symlink(workdirs_path: &Path, workdir_name: &str) -> Result<bool, Error> {
+    let _guard = DIRECT_SWITCH_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if active_workdir(workdirs_path).as_deref() == Some(workdir_name) {
+        return Ok(false);
+    }
+
+    let symlink_path = workdirs_path.join(ACTIVE_SYMLINK);
+    let tmp_path = workdirs_path.join(format!(".{}.{}.tmp", ACTIVE_SYMLINK, std::process::id()));
+    let _ = std::fs::remove_file(&tmp_path);
+    std::os::unix::fs::symlink(workdirs_path.join(workdir_name), &tmp_path)
+        .map_err(|_| Error::WorkdirAccessError)?;
+    std::fs::rename(&tmp_path, &symlink_path).map_err(|_| {
+        let _ = std::fs::remove_file(&tmp_path);
+        Error::WorkdirAccessError
+    })?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // Minimal workdir, as left by "<workdir> create".
+    fn create_workdir(workdirs_path: &Path, workdir: &str) {
+        let workdir_path = workdirs_path.join(workdir);
+        fs::create_dir_all(workdir_path.join("config-default")).unwrap();
+        fs::write(workdir_path.join("sui-exec"), "").unwrap();
+        fs::write(workdir_path.join("workdir-exec"), "").unwrap();
+        std::os::unix::fs::symlink(
+            workdir_path.join("config-default"),
+            workdir_path.join("config"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_set_active_workdir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workdirs_path = tmp.path().join("workdirs");
+        create_workdir(&workdirs_path, "localnet");
+        create_workdir(&workdirs_path, "testnet");
+        fs::create_dir_all(workdirs_path.join("devnet")).unwrap();
+        let mut root = SuibaseRoot::with_suibase_path(tmp.path());
+
+        assert_eq!(active_workdir(&workdirs_path), None);
+        for workdir in ["localnet", "testnet", "localnet"] {
+            assert!(set_active_workdir(&mut root, workdir).unwrap());
+            assert_eq!(active_workdir(&workdirs_path).as_deref(), Some(workdir));
+            assert_eq!(
+                fs::read_link(workdirs_path.join("active")).unwrap(),
+                workdirs_path.join(workdir)
+            );
+        }
+        assert!(!set_active_workdir(&mut root, "localnet").unwrap());
+
+        // Concurrent switches (the last one wins).
+        std::thread::scope(|s| {
+            for workdir in ["testnet", "localnet", "testnet", "localnet"] {
+                let mut root = SuibaseRoot::with_suibase_path(tmp.path());
+                s.spawn(move || set_active_workdir(&mut root, workdir).unwrap());
+            }
+        });
+        let active = active_workdir(&workdirs_path).unwrap();
+        assert!(active == "localnet" || active == "testnet");
+
+        // The selection is unchanged on error.
+        assert!(matches!(
+            set_active_workdir(&mut root, "devnet"),
+            Err(Error::WorkdirInitializationIncomplete { .. })
+        ));
+        assert!(matches!(
+            set_active_workdir(&mut root, "mainnet"),
+            Err(Error::WorkdirNotExists)
+        ));
+        assert!(matches!(
+            set_active_workdir(&mut root, "active"),
+            Err(Error::UnsupportedForWorkdir { .. })
+        ));
+        assert_eq!(active_workdir(&workdirs_path), Some(active));
+    }
+}
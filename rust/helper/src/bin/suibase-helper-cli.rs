@@ -0,0 +1,177 @@
+// suibase-helper-cli
+//
+// The Helper accessors for non-Rust consumers (shell scripts, Python, TypeScript...).
+//
+//   suibase-helper-cli [--workdir <name>] <command> [<arg>]
+//
+//   workdir                           Name of the workdir ("active" resolved).
+//   rpc-url                           RPC URL of the workdir.
+//   package-id <package_name>         Last published id of a package.
+//   client-address <address_name>     e.g. "active", "sb-1-ed25519".
+//   published-objects <object_type>   e.g. "demo::Counter::Counter".
+//   keystore-path                     Pathname of the workdir keystore.
+//
+// The workdir defaults to "active".
+//
+// Prints a single JSON object to stdout (see cli_output.rs for each command), also on
+// failure. The exit code is 0 on success, otherwise the one of the error (distinct for
+// every Error variant, see error.rs).
+//
+// Must never panic: a consumer would otherwise get no JSON at all.
+use std::io::Write;
+use std::process::ExitCode;
+
+use serde::Serialize;
+use suibase::{
+    ClientAddressOutput, ErrorOutput, Helper, KeystorePathOutput, PackageIdOutput,
+    PublishedObjectsOutput, RpcUrlOutput, WorkdirOutput, EXIT_CODE_INTERNAL,
+};
+
+const USAGE: &str = "usage: suibase-helper-cli [--workdir <name>] <command> [<arg>]
+commands:
+  workdir
+  rpc-url
+  package-id <package_name>
+  client-address <address_name>
+  published-objects <object_type>
+  keystore-path";
+
+// Last resort when even an ErrorOutput cannot be serialized.
+const INTERNAL_ERROR_JSON: &str =
+    r#"{"error":"Internal","message":"JSON serialization failed","exit_code":3}"#;
+
+enum Command {
+    Help,
+    Workdir,
+    RpcUrl,
+    PackageId(String),
+    ClientAddress(String),
+    PublishedObjects(String),
+    KeystorePath,
+}
+
+// Returns the workdir name and the command.
+fn parse_args(args: &[String]) -> Result<(String, Command), ErrorOutput> {
+    let mut workdir = "active".to_string();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok((workdir, Command::Help));
+        } else if arg == "--workdir" {
+            match iter.next() {
+                Some(name) => workdir = name.clone(),
+                None => return Err(ErrorOutput::usage("missing value for --workdir")),
+            }
+        } else if let Some(name) = arg.strip_prefix("--workdir=") {
+            workdir = name.to_string();
+        } else if arg.starts_with("--") {
+            return Err(ErrorOutput::usage(&format!("unknown option {}", arg)));
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let name = match positional.next() {
+        Some(name) => name,
+        None => return Err(ErrorOutput::usage("missing command")),
+    };
+    let arg = positional.next();
+    if positional.next().is_some() {
+        return Err(ErrorOutput::usage(&format!(
+            "too many arguments for {}",
+            name
+        )));
+    }
+
+    let command = match (name.as_str(), arg) {
+        ("workdir", None) => Command::Workdir,
+        ("rpc-url", None) => Command::RpcUrl,
+        ("keystore-path", None) => Command::KeystorePath,
+        ("package-id", Some(arg)) => Command::PackageId(arg),
+        ("client-address", Some(arg)) => Command::ClientAddress(arg),
+        ("published-objects", Some(arg)) => Command::PublishedObjects(arg),
+        ("workdir" | "rpc-url" | "keystore-path", Some(_)) => {
+            return Err(ErrorOutput::usage(&format!("{} takes no argument", name)));
+        }
+        ("package-id" | "client-address" | "published-objects", None) => {
+            return Err(ErrorOutput::usage(&format!("{} needs an argument", name)));
+        }
+        _ => return Err(ErrorOutput::usage(&format!("unknown command {}", name))),
+    };
+    Ok((workdir, command))
+}
+
+fn to_json<T: Serialize>(output: &T) -> Result<String, ErrorOutput> {
+    serde_json::to_string(output).map_err(|e| ErrorOutput::internal(&e.to_string()))
+}
+
+fn error_line(e: &ErrorOutput) -> (String, i32) {
+    let line = to_json(e).unwrap_or_else(|_| INTERNAL_ERROR_JSON.to_string());
+    (line, e.exit_code)
+}
+
+// The line to print on success.
+fn run(args: Vec<String>) -> Result<String, ErrorOutput> {
+    let (workdir_name, command) = parse_args(&args)?;
+    if let Command::Help = command {
+        return Ok(USAGE.to_string());
+    }
+
+    let sbh = Helper::new();
+    sbh.select_workdir(&workdir_name)?;
+    let workdir = sbh.workdir()?;
+
+    match command {
+        Command::Help | Command::Workdir => to_json(&WorkdirOutput { workdir }),
+        Command::RpcUrl => to_json(&RpcUrlOutput {
+            rpc_url: sbh.rpc_url()?,
+            workdir,
+        }),
+        Command::PackageId(package_name) => to_json(&PackageIdOutput {
+            package_id: sbh.package_id(&package_name)?.to_string(),
+            workdir,
+            package_name,
+        }),
+        Command::ClientAddress(address_name) => to_json(&ClientAddressOutput {
+            address: sbh.client_address(&address_name)?.to_string(),
+            workdir,
+            address_name,
+        }),
+        Command::PublishedObjects(object_type) => to_json(&PublishedObjectsOutput {
+            object_ids: sbh
+                .published_new_objects(&object_type)?
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
+            workdir,
+            object_type,
+        }),
+        Command::KeystorePath => to_json(&KeystorePathOutput {
+            keystore_path: sbh.keystore_pathname()?,
+            workdir,
+        }),
+    }
+}
+
+fn main() -> ExitCode {
+    // A panic is reported as an "Internal" JSON error instead (see below).
+    std::panic::set_hook(Box::new(|_| {}));
+
+    // args_os because std::env::args() panics on a non UTF-8 argument.
+    let args: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    let (line, exit_code) = match std::panic::catch_unwind(|| run(args)) {
+        Ok(Ok(line)) => (line, 0),
+        Ok(Err(e)) => error_line(&e),
+        Err(_) => error_line(&ErrorOutput::internal("unexpected failure")),
+    };
+
+    // Not println!(), which panics when stdout is closed (e.g. piped into head).
+    let _ = writeln!(std::io::stdout().lock(), "{}", line);
+    ExitCode::from(u8::try_from(exit_code).unwrap_or(EXIT_CODE_INTERNAL as u8))
+}
@@ -0,0 +1,97 @@
+// JSON output of the suibase-helper-cli.
+//
+// Every command prints one of these objects (a single line) to stdout, including on
+// failure (ErrorOutput), so a non-Rust consumer only has to parse stdout and check
+// the exit code.
+//
+// Public so Rust consumers deserialize the output with the same types.
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, EXIT_CODE_INTERNAL, EXIT_CODE_USAGE};
+
+// "suibase-helper-cli workdir"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkdirOutput {
+    pub workdir: String, // Resolved name (never "active").
+}
+
+// "suibase-helper-cli rpc-url"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcUrlOutput {
+    pub workdir: String,
+    pub rpc_url: String,
+}
+
+// "suibase-helper-cli package-id <package_name>"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageIdOutput {
+    pub workdir: String,
+    pub package_name: String,
+    pub package_id: String,
+}
+
+// "suibase-helper-cli client-address <address_name>"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientAddressOutput {
+    pub workdir: String,
+    pub address_name: String,
+    pub address: String,
+}
+
+// "suibase-helper-cli published-objects <object_type>"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedObjectsOutput {
+    pub workdir: String,
+    pub object_type: String,
+    pub object_ids: Vec<String>,
+}
+
+// "suibase-helper-cli keystore-path"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystorePathOutput {
+    pub workdir: String,
+    pub keystore_path: String,
+}
+
+// Any failure. `error` is the Error variant name, or "Usage"/"Internal" for the
+// failures that are not from the Helper. `exit_code` is also the process exit code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorOutput {
+    pub error: String,
+    pub message: String,
+    pub exit_code: i32,
+}
+
+impl ErrorOutput {
+    pub fn usage(message: &str) -> Self {
+        Self {
+            error: "Usage".to_string(),
+            message: message.to_string(),
+            exit_code: EXIT_CODE_USAGE,
+        }
+    }
+
+    pub fn internal(message: &str) -> Self {
+        Self {
+            error: "Internal".to_string(),
+            message: message.to_string(),
+            exit_code: EXIT_CODE_INTERNAL,
+        }
+    }
+}
+
+impl From<&Error> for ErrorOutput {
+    fn from(e: &Error) -> Self {
+        Self {
+            error: e.name().to_string(),
+            message: e.to_string(),
+            exit_code: e.exit_code(),
+        }
+    }
+}
+
+impl From<Error> for ErrorOutput {
+    fn from(e: Error) -> Self {
+        Self::from(&e)
+    }
+}
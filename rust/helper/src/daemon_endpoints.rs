@@ -0,0 +1,189 @@
+// Endpoints listened on by the suibase-daemon (see Helper::daemon_endpoints).
+//
+// The daemon maintains ~/suibase/workdirs/common/daemon-endpoints.json with every
+// listening endpoint (API, explorer, proxy of each workdir), its pid and a generation
+// incremented on every change.
+//
+// The file is stale when the daemon marked it so on a graceful shutdown, or when its
+// pid is no longer running (e.g. after a crash). A stale file is still returned by
+// daemon_endpoints(), but never used to derive an URL.
+
+use std::path::Path;
+
+use serde_json::Value as JsonValue;
+
+// Relative to ~/suibase/workdirs.
+pub(crate) const DAEMON_ENDPOINTS_FILE: &str = "common/daemon-endpoints.json";
+
+// Schema version of the file supported by this helper.
+const DAEMON_ENDPOINTS_VERSION: u64 = 1;
+
+pub(crate) const ENDPOINT_PURPOSE_API: &str = "api";
+pub(crate) const ENDPOINT_PURPOSE_PROXY: &str = "proxy";
+pub(crate) const ENDPOINT_PURPOSE_WS: &str = "ws";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonEndpoint {
+    pub purpose: String,         // e.g. "api", "explorer" or "proxy"
+    pub workdir: Option<String>, // None for the daemon-wide endpoints.
+    pub url: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonEndpoints {
+    pub pid: u32,
+    pub generation: u64,
+    pub updated_at: String, // RFC 3339
+    pub stale: bool,        // Marked stale by the daemon, or its pid is not running.
+    pub endpoints: Vec<DaemonEndpoint>,
+}
+
+impl DaemonEndpoints {
+    // URL of an endpoint, when the file is not stale.
+    pub(crate) fn fresh_url(&self, workdir: Option<&str>, purpose: &str) -> Option<&str> {
+        if self.stale {
+            return None;
+        }
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.purpose == purpose && endpoint.workdir.as_deref() == workdir)
+            .map(|endpoint| endpoint.url.as_str())
+    }
+
+    pub(crate) fn fresh_port(&self, workdir: Option<&str>, purpose: &str) -> Option<u16> {
+        if self.stale {
+            return None;
+        }
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.purpose == purpose && endpoint.workdir.as_deref() == workdir)
+            .map(|endpoint| endpoint.port)
+    }
+}
+
+// A process signal 0 only checks that the pid exists.
+pub(crate) fn is_pid_alive(pid: u32) -> bool {
+    if pid == 0 {
+        return false;
+    }
+    if Path::new("/proc/self").exists() {
+        return Path::new(&format!("/proc/{}", pid)).exists();
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// None when the content is not usable (e.g. another schema version).
+pub(crate) fn parse_daemon_endpoints(
+    content: &str,
+    is_alive: impl Fn(u32) -> bool,
+) -> Option<DaemonEndpoints> {
+    let json: JsonValue = serde_json::from_str(content).ok()?;
+    if json["version"].as_u64() != Some(DAEMON_ENDPOINTS_VERSION) {
+        return None;
+    }
+    let pid = u32::try_from(json["pid"].as_u64()?).ok()?;
+    let endpoints = json["endpoints"]
+        .as_array()?
+        .iter()
+        .filter_map(|endpoint| {
+            Some(DaemonEndpoint {
+                purpose: endpoint["purpose"].as_str()?.to_string(),
+                workdir: endpoint["workdir"].as_str().map(|s| s.to_string()),
+                url: endpoint["url"].as_str()?.to_string(),
+                port: u16::try_from(endpoint["port"].as_u64()?).ok()?,
+            })
+        })
+        .collect();
+    Some(DaemonEndpoints {
+        pid,
+        generation: json["generation"].as_u64().unwrap_or_default(),
+        updated_at: json["updated_at"].as_str().unwrap_or_default().to_string(),
+        stale: json["stale"].as_bool().unwrap_or(true) || !is_alive(pid),
+        endpoints,
+    })
+}
+
+pub(crate) fn load_daemon_endpoints(workdirs_path: &str) -> Option<DaemonEndpoints> {
+    if workdirs_path.is_empty() {
+        return None;
+    }
+    let content =
+        std::fs::read_to_string(Path::new(workdirs_path).join(DAEMON_ENDPOINTS_FILE)).ok()?;
+    parse_daemon_endpoints(&content, is_pid_alive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{
+        "version": 1,
+        "pid": 4242,
+        "generation": 7,
+        "updated_at": "2024-05-01T12:00:00+00:00",
+        "stale": false,
+        "endpoints": [
+            { "purpose": "api", "url": "http://localhost:44399", "port": 44399 },
+            { "purpose": "proxy", "workdir": "localnet",
+              "url": "http://localhost:44341", "port": 44341 }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_daemon_endpoints() {
+        let endpoints = parse_daemon_endpoints(FIXTURE, |_| true).unwrap();
+        assert!(!endpoints.stale);
+        assert_eq!(endpoints.generation, 7);
+        assert_eq!(
+            endpoints.fresh_url(Some("localnet"), ENDPOINT_PURPOSE_PROXY),
+            Some("http://localhost:44341")
+        );
+        assert_eq!(
+            endpoints.fresh_port(None, ENDPOINT_PURPOSE_API),
+            Some(44399)
+        );
+        assert_eq!(
+            endpoints.fresh_url(Some("testnet"), ENDPOINT_PURPOSE_PROXY),
+            None
+        );
+        assert_eq!(
+            endpoints.fresh_url(Some("localnet"), ENDPOINT_PURPOSE_WS),
+            None
+        );
+
+        // After a crash (pid no longer running): returned, but not used.
+        let endpoints = parse_daemon_endpoints(FIXTURE, |pid| pid != 4242).unwrap();
+        assert!(endpoints.stale);
+        assert_eq!(endpoints.endpoints.len(), 2);
+        assert_eq!(
+            endpoints.fresh_url(Some("localnet"), ENDPOINT_PURPOSE_PROXY),
+            None
+        );
+
+        // Marked stale on a graceful shutdown.
+        let content = FIXTURE.replace("\"stale\": false", "\"stale\": true");
+        assert!(parse_daemon_endpoints(&content, |_| true).unwrap().stale);
+
+        let content = FIXTURE.replace("\"version\": 1", "\"version\": 2");
+        assert!(parse_daemon_endpoints(&content, |_| true).is_none());
+        assert!(parse_daemon_endpoints("{\"version\": 1,", |_| true).is_none());
+    }
+
+    #[test]
+    fn test_is_pid_alive() {
+        assert!(is_pid_alive(std::process::id()));
+        assert!(!is_pid_alive(0));
+
+        // A child that exited (and was reaped) is not running anymore.
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!is_pid_alive(pid));
+    }
+}
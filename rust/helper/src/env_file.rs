@@ -0,0 +1,311 @@
+// Generation of a file with the latest published ids of a workdir, so that an app
+// stays in sync after every "localnet publish" (no copy-paste of ids).
+//
+// Formats (names are converted to identifiers, e.g. "my-pkg" -> "MY_PKG"):
+//
+//   Dotenv   MY_PKG_PACKAGE_ID=0x...
+//            MY_PKG_MODULE_TYPE_IDS=0x...,0x...
+//
+//   Json     {"workdir":"localnet","packages":{"my-pkg":"0x..."},
+//             "objects":{"my-pkg::module::Type":["0x..."]}}
+//
+//   Rust     pub const MY_PKG_PACKAGE_ID: &str = "0x...";
+//            pub const MY_PKG_MODULE_TYPE_IDS: &[&str] = &["0x..."];
+//            (for an include!)
+//
+// The Json format can be loaded back with read_env_file().
+//
+// The file is replaced atomically, and left untouched on any error (e.g. one of
+// the packages was never published).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+use crate::suibase_root::SuibaseRoot;
+use crate::suibase_workdir::SuibaseWorkdir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvFormat {
+    Dotenv,
+    Json,
+    Rust,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublishedIds {
+    pub workdir: String,
+    pub packages: HashMap<String, String>, // Key is the package name.
+    pub objects: HashMap<String, Vec<String>>, // Key is the "package::module::type".
+}
+
+pub(crate) fn collect_published_ids(
+    root: &mut SuibaseRoot,
+    wd: &SuibaseWorkdir,
+    package_names: &[&str],
+    object_types: &[&str],
+) -> Result<PublishedIds, Error> {
+    let mut ids = PublishedIds {
+        workdir: wd.get_name()?,
+        ..Default::default()
+    };
+    for package_name in package_names {
+        let package_id = wd.package_object_id(root, package_name)?;
+        ids.packages
+            .insert(package_name.to_string(), package_id.to_string());
+    }
+    for object_type in object_types {
+        let object_ids = wd.published_new_object_ids(root, object_type)?;
+        ids.objects.insert(
+            object_type.to_string(),
+            object_ids.iter().map(|id| id.to_string()).collect(),
+        );
+    }
+    Ok(ids)
+}
+
+// Upper case, with any character not valid in an identifier replaced by '_'.
+pub(crate) fn env_identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c.to_ascii_uppercase());
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_matches('_');
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", identifier)
+    } else {
+        identifier.to_string()
+    }
+}
+
+pub(crate) fn render_env_file(ids: &PublishedIds, format: EnvFormat) -> String {
+    // Sorted, so a re-generation without changes gives the same file.
+    let packages: BTreeMap<&String, &String> = ids.packages.iter().collect();
+    let objects: BTreeMap<&String, &Vec<String>> = ids.objects.iter().collect();
+
+    let mut out = String::new();
+    match format {
+        EnvFormat::Dotenv => {
+            out.push_str(&format!("# Generated by suibase for {}\n", ids.workdir));
+            for (name, id) in packages {
+                out.push_str(&format!("{}_PACKAGE_ID={}\n", env_identifier(name), id));
+            }
+            for (object_type, object_ids) in objects {
+                out.push_str(&format!(
+                    "{}_IDS={}\n",
+                    env_identifier(object_type),
+                    object_ids.join(",")
+                ));
+            }
+        }
+        EnvFormat::Json => {
+            let json = serde_json::json!({
+                "workdir": ids.workdir,
+                "packages": packages,
+                "objects": objects,
+            });
+            // Serializing a Value never fails.
+            out = serde_json::to_string_pretty(&json).unwrap_or_default();
+            out.push('\n');
+        }
+        EnvFormat::Rust => {
+            out.push_str(&format!("// Generated by suibase for {}\n", ids.workdir));
+            for (name, id) in packages {
+                out.push_str(&format!(
+                    "pub const {}_PACKAGE_ID: &str = \"{}\";\n",
+                    env_identifier(name),
+                    id
+                ));
+            }
+            for (object_type, object_ids) in objects {
+                let quoted: Vec<String> =
+                    object_ids.iter().map(|id| format!("\"{}\"", id)).collect();
+                out.push_str(&format!(
+                    "pub const {}_IDS: &[&str] = &[{}];\n",
+                    env_identifier(object_type),
+                    quoted.join(", ")
+                ));
+            }
+        }
+    }
+    out
+}
+
+// Written in a temporary file of the same directory, then renamed.
+pub(crate) fn write_env_file(
+    ids: &PublishedIds,
+    path: &Path,
+    format: EnvFormat,
+) -> Result<(), Error> {
+    let write_error = |e: std::io::Error| Error::EnvFileWriteError {
+        path: path.to_string_lossy().to_string(),
+        msg: e.to_string(),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| write_error(std::io::ErrorKind::InvalidInput.into()))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    std::fs::write(&tmp_path, render_env_file(ids, format)).map_err(write_error)?;
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(write_error(e));
+    }
+    Ok(())
+}
+
+pub(crate) fn read_env_file(path: &Path) -> Result<PublishedIds, Error> {
+    let read_error = |msg: String| Error::EnvFileReadError {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| read_error(e.to_string()))?;
+    let json: JsonValue = serde_json::from_str(&contents).map_err(|e| read_error(e.to_string()))?;
+
+    let workdir = json["workdir"]
+        .as_str()
+        .ok_or_else(|| read_error("missing workdir".to_string()))?;
+    let mut ids = PublishedIds {
+        workdir: workdir.to_string(),
+        ..Default::default()
+    };
+    if let Some(packages) = json["packages"].as_object() {
+        for (name, id) in packages {
+            let id = id
+                .as_str()
+                .ok_or_else(|| read_error(format!("invalid package id for {}", name)))?;
+            ids.packages.insert(name.clone(), id.to_string());
+        }
+    }
+    if let Some(objects) = json["objects"].as_object() {
+        for (object_type, object_ids) in objects {
+            let object_ids = object_ids
+                .as_array()
+                .map(|array| array.iter().filter_map(|id| id.as_str()))
+                .ok_or_else(|| read_error(format!("invalid object ids for {}", object_type)))?;
+            ids.objects.insert(
+                object_type.clone(),
+                object_ids.map(|id| id.to_string()).collect(),
+            );
+        }
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const PACKAGE_ID: &str = "0x6f36a2a2b1b4ffbe7c9f3b9a4e8cfd50d4c37c36b1cfa4b5e1e2ac8e8b9c8feb";
+    const OBJECT_ID: &str = "0x0000000000000000000000000000000000000000000000000000000000000abc";
+
+    fn demo_ids() -> PublishedIds {
+        let mut ids = PublishedIds {
+            workdir: "localnet".to_string(),
+            ..Default::default()
+        };
+        ids.packages
+            .insert("my-demo".to_string(), PACKAGE_ID.to_string());
+        ids.objects.insert(
+            "my-demo::counter::Counter".to_string(),
+            vec![OBJECT_ID.to_string()],
+        );
+        ids
+    }
+
+    #[test]
+    fn test_env_identifier() {
+        assert_eq!(env_identifier("demo"), "DEMO");
+        assert_eq!(env_identifier("my-demo"), "MY_DEMO");
+        assert_eq!(
+            env_identifier("demo::counter::Counter"),
+            "DEMO_COUNTER_COUNTER"
+        );
+        assert_eq!(env_identifier("2fast"), "_2FAST");
+        assert_eq!(env_identifier("-"), "_");
+    }
+
+    #[test]
+    fn test_render_dotenv_and_rust() {
+        let ids = demo_ids();
+        let dotenv = render_env_file(&ids, EnvFormat::Dotenv);
+        assert!(dotenv.contains(&format!("MY_DEMO_PACKAGE_ID={}\n", PACKAGE_ID)));
+        assert!(dotenv.contains(&format!("MY_DEMO_COUNTER_COUNTER_IDS={}\n", OBJECT_ID)));
+
+        let rust = render_env_file(&ids, EnvFormat::Rust);
+        assert!(rust.contains(&format!(
+            "pub const MY_DEMO_PACKAGE_ID: &str = \"{}\";\n",
+            PACKAGE_ID
+        )));
+        assert!(rust.contains(&format!(
+            "pub const MY_DEMO_COUNTER_COUNTER_IDS: &[&str] = &[\"{}\"];\n",
+            OBJECT_ID
+        )));
+    }
+
+    #[test]
+    fn test_json_write_and_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("published.json");
+        let ids = demo_ids();
+        write_env_file(&ids, &path, EnvFormat::Json).unwrap();
+        assert!(!tmp.path().join("published.json.tmp").exists());
+        assert_eq!(read_env_file(&path).unwrap(), ids);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            read_env_file(&path),
+            Err(Error::EnvFileReadError { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_package_never_published() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workdir = tmp.path().join("workdirs/localnet");
+        fs::create_dir_all(workdir.join(".state")).unwrap();
+        fs::write(workdir.join(".state/name"), "localnet").unwrap();
+        fs::write(workdir.join(".state/user_request"), "stop").unwrap();
+        let publication = workdir.join("published-data/demo/1");
+        fs::create_dir_all(&publication).unwrap();
+        fs::write(
+            publication.join("package-id.json"),
+            format!("[\"{}\"]", PACKAGE_ID),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            &publication,
+            workdir.join("published-data/demo/most-recent"),
+        )
+        .unwrap();
+
+        let mut root = SuibaseRoot::with_suibase_path(tmp.path());
+        let mut wd = SuibaseWorkdir::new();
+        wd.init_from_existing(&mut root, "localnet").unwrap();
+
+        let ids = collect_published_ids(&mut root, &wd, &["demo"], &[]).unwrap();
+        assert_eq!(ids.workdir, "localnet");
+        assert_eq!(ids.packages["demo"], PACKAGE_ID);
+
+        // A previously generated file is kept as-is on error.
+        let path = tmp.path().join(".env");
+        write_env_file(&ids, &path, EnvFormat::Dotenv).unwrap();
+        let res = collect_published_ids(&mut root, &wd, &["demo", "never-published"], &[]);
+        assert!(matches!(
+            res,
+            Err(Error::PublishedDataAccessErrorSymlinkNotFound { .. })
+        ));
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("DEMO_PACKAGE_ID="));
+    }
+}
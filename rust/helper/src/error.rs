@@ -9,6 +9,12 @@ pub enum Error {
     #[error("suibase: Not installed. Need to run ~/suibase/install")]
     NotInstalled,
 
+    #[error("suibase: Installed version `{found:?}` is older than `{required:?}`. Need to run ~/suibase/update")]
+    IncompatibleSuibase { found: String, required: String },
+
+    #[error("suibase: Missing workdirs directory `{path:?}`. Need to run ~/suibase/install again")]
+    WorkdirsNotExists { path: String },
+
     #[error("suibase: Workdir not selected. Successful call to `select_workdir` needed")]
     WorkdirNotSelected,
 
@@ -70,9 +76,21 @@ pub enum Error {
     #[error("suibase: Invalid object_type parameter")]
     ObjectTypeInvalidFormat,
 
+    #[error("suibase: Invalid transaction digest (empty string)")]
+    TransactionDigestEmpty,
+
+    #[error("suibase: Invalid object id `{id:?}`")]
+    ObjectIdInvalid { id: String },
+
+    #[error("suibase: Invalid address `{address:?}`")]
+    AddressInvalid { address: String },
+
     #[error("suibase: Not finding address name'{address_name:?}'")]
     AddressNameNotFound { address_name: String },
 
+    #[error("suibase: {what} not supported by the {workdir} workdir")]
+    UnsupportedForWorkdir { workdir: String, what: String },
+
     /*****************************/
     // Suibase filesystem related errors
     //
@@ -90,6 +108,9 @@ pub enum Error {
     #[error("suibase: Active workdir .state/name not set. Try to 'update' the workdir")]
     WorkdirStateNameNotSet,
 
+    #[error("suibase: Missing package-id.json `{path:?}`. Was the package `{package_name:?}` published with success?")]
+    PackageIdFileNotFound { package_name: String, path: String },
+
     #[error("suibase: Invalid package-id.json format")]
     PackageIdJsonInvalidFormat,
 
@@ -137,6 +158,86 @@ pub enum Error {
     #[error("suibase: Could not read link file `{path:?}`")]
     WorkdirStateLinkReadError { path: String },
 
+    #[error("suibase: No build artifacts for package `{package_name:?}` in `{path:?}`")]
+    PackageBuildNotFound { package_name: String, path: String },
+
+    #[error("suibase: Could not read build metadata `{path:?}`: {msg}")]
+    PackageBuildReadError { path: String, msg: String },
+
+    /*****************************/
+    // App files related errors (see generate_env_file)
+    /*****************************/
+    #[error("suibase: Could not write `{path:?}`: {msg}")]
+    EnvFileWriteError { path: String, msg: String },
+
+    #[error("suibase: Could not read `{path:?}`: {msg}")]
+    EnvFileReadError { path: String, msg: String },
+
+    #[error("suibase: Could not write profile `{path:?}`: {msg}")]
+    ProfileWriteError { path: String, msg: String },
+
+    #[error("suibase: Could not read profile `{path:?}`: {msg}")]
+    ProfileReadError { path: String, msg: String },
+
+    /*****************************/
+    // Keystore related errors (see verify_keystore and import_key)
+    /*****************************/
+    #[error("suibase: Could not read keystore `{path:?}`: {msg}")]
+    KeystoreReadError { path: String, msg: String },
+
+    #[error("suibase: Could not write keystore `{path:?}`: {msg}")]
+    KeystoreWriteError { path: String, msg: String },
+
+    #[error("suibase: Keystore locked by another import. Delete `{path:?}` if stale")]
+    KeystoreLocked { path: String },
+
+    #[error("suibase: Invalid private key ({msg})")]
+    PrivateKeyInvalid { msg: String },
+
+    #[error("suibase: Key import into {workdir} must be explicitly allowed")]
+    KeyImportNotAllowed { workdir: String },
+
+    /*****************************/
+    // Sui network related errors
+    /*****************************/
+    #[error("suibase: RPC URL `{url:?}` not supported. Expecting the workdir proxy (http://...)")]
+    RpcUrlNotSupported { url: String },
+
+    #[error("suibase: Sui RPC `{method:?}` request failed: {msg}")]
+    RpcRequestError { method: String, msg: String },
+
+    #[error("suibase: Could not sign transaction with `{address:?}`: {msg}")]
+    TransactionSignError { address: String, msg: String },
+
+    #[error("suibase: Transaction `{digest:?}` failed: {msg}")]
+    TransactionFailed { digest: String, msg: String },
+
+    #[error("suibase: Transaction `{digest:?}` not found. Was it executed on the selected workdir?")]
+    TransactionNotFound { digest: String },
+
+    #[error("suibase: Object `{id:?}` not found. Was it created on the selected workdir?")]
+    ObjectNotFound { id: String },
+
+    #[error("suibase: Object `{id:?}` was deleted")]
+    ObjectDeleted { id: String },
+
+    #[error("suibase: Timeout waiting for object `{id:?}`. Last content: {last_content}")]
+    ObjectWaitTimeout { id: String, last_content: String },
+
+    /*****************************/
+    // Suibase daemon related errors
+    /*****************************/
+    #[error(
+        "suibase: suibase-daemon not running. Did you do 'localnet start' (or any other workdir)?"
+    )]
+    DaemonNotRunning,
+
+    #[error("suibase: suibase-daemon `{method:?}` request failed: {msg}")]
+    DaemonRequestError { method: String, msg: String },
+
+    #[error("suibase: {workdir} is DOWN — run '{workdir} start'")]
+    WorkdirDown { workdir: String },
+
     /*****************************/
     // Suibase internal errors
     // Likely a bug in  code.
@@ -153,3 +254,99 @@ pub enum Error {
     #[error("suibase: Invalid state name (empty string)")]
     StateNameEmpty,
 }
+
+// Exit codes of the suibase-helper-cli (see cli_output.rs).
+//
+// Each variant has its own code, so a script can react without parsing the message.
+// Codes are never reused: a new variant gets the next unused code.
+pub const EXIT_CODE_USAGE: i32 = 2; // Bad command line.
+pub const EXIT_CODE_INTERNAL: i32 = 3; // Unexpected failure (e.g. JSON serialization).
+
+impl Error {
+    /// The variant name (e.g. "WorkdirNotExists").
+    pub fn name(&self) -> &'static str {
+        self.name_and_exit_code().0
+    }
+
+    /// Process exit code of the suibase-helper-cli for this error (always >= 10).
+    pub fn exit_code(&self) -> i32 {
+        self.name_and_exit_code().1
+    }
+
+    fn name_and_exit_code(&self) -> (&'static str, i32) {
+        match self {
+            Error::NotInstalled => ("NotInstalled", 10),
+            Error::IncompatibleSuibase { .. } => ("IncompatibleSuibase", 11),
+            Error::WorkdirsNotExists { .. } => ("WorkdirsNotExists", 12),
+            Error::WorkdirNotSelected => ("WorkdirNotSelected", 13),
+            Error::WorkdirAccessError => ("WorkdirAccessError", 14),
+            Error::WorkdirNotExists => ("WorkdirNotExists", 15),
+            Error::SuibaseKeystoreNotExists { .. } => ("SuibaseKeystoreNotExists", 16),
+            Error::PublishedDataNotFound { .. } => ("PublishedDataNotFound", 17),
+            Error::MissingLinkDefinition => ("MissingLinkDefinition", 18),
+            Error::MissingAtLeastOneLinkDefinition => ("MissingAtLeastOneLinkDefinition", 19),
+            Error::MissingLinkField { .. } => ("MissingLinkField", 20),
+            Error::ConfigAccessError { .. } => ("ConfigAccessError", 21),
+            Error::ConfigReadError { .. } => ("ConfigReadError", 22),
+            Error::ConfigActiveAddressParseError { .. } => ("ConfigActiveAddressParseError", 23),
+            Error::WorkdirNameEmpty => ("WorkdirNameEmpty", 24),
+            Error::PackageNameEmpty => ("PackageNameEmpty", 25),
+            Error::AddressNameEmpty => ("AddressNameEmpty", 26),
+            Error::ObjectTypeMissingField => ("ObjectTypeMissingField", 27),
+            Error::ObjectTypeInvalidFormat => ("ObjectTypeInvalidFormat", 28),
+            Error::TransactionDigestEmpty => ("TransactionDigestEmpty", 29),
+            Error::ObjectIdInvalid { .. } => ("ObjectIdInvalid", 30),
+            Error::AddressNameNotFound { .. } => ("AddressNameNotFound", 31),
+            Error::UnsupportedForWorkdir { .. } => ("UnsupportedForWorkdir", 32),
+            Error::WorkdirStateNameAccessFailed => ("WorkdirStateNameAccessFailed", 33),
+            Error::WorkdirStateDNSAccessFailed { .. } => ("WorkdirStateDNSAccessFailed", 34),
+            Error::WorkdirStateNameNotSet => ("WorkdirStateNameNotSet", 35),
+            Error::PackageIdFileNotFound { .. } => ("PackageIdFileNotFound", 36),
+            Error::PackageIdJsonInvalidFormat => ("PackageIdJsonInvalidFormat", 37),
+            Error::PackageIdInvalidHex { .. } => ("PackageIdInvalidHex", 38),
+            Error::PublishedNewObjectReadError { .. } => ("PublishedNewObjectReadError", 39),
+            Error::PublishedNewObjectParseError { .. } => ("PublishedNewObjectParseError", 40),
+            Error::WorkdirInitializationIncomplete { .. } => {
+                ("WorkdirInitializationIncomplete", 41)
+            }
+            Error::WorkdirStateDNSReadError { .. } => ("WorkdirStateDNSReadError", 42),
+            Error::WorkdirStateDNSParseError { .. } => ("WorkdirStateDNSParseError", 43),
+            Error::PublishedDataAccessError { .. } => ("PublishedDataAccessError", 44),
+            Error::PublishedDataAccessErrorInvalidSymlink { .. } => {
+                ("PublishedDataAccessErrorInvalidSymlink", 45)
+            }
+            Error::PublishedDataAccessErrorSymlinkNotFound { .. } => {
+                ("PublishedDataAccessErrorSymlinkNotFound", 46)
+            }
+            Error::PublishedNewObjectAccessError { .. } => ("PublishedNewObjectAccessError", 47),
+            Error::WorkdirStateLinkReadError { .. } => ("WorkdirStateLinkReadError", 48),
+            Error::EnvFileWriteError { .. } => ("EnvFileWriteError", 49),
+            Error::EnvFileReadError { .. } => ("EnvFileReadError", 50),
+            Error::RpcUrlNotSupported { .. } => ("RpcUrlNotSupported", 51),
+            Error::RpcRequestError { .. } => ("RpcRequestError", 52),
+            Error::TransactionSignError { .. } => ("TransactionSignError", 53),
+            Error::TransactionFailed { .. } => ("TransactionFailed", 54),
+            Error::TransactionNotFound { .. } => ("TransactionNotFound", 55),
+            Error::DaemonNotRunning => ("DaemonNotRunning", 56),
+            Error::DaemonRequestError { .. } => ("DaemonRequestError", 57),
+            Error::WorkdirDown { .. } => ("WorkdirDown", 58),
+            Error::WorkdirNameNotSet => ("WorkdirNameNotSet", 59),
+            Error::WorkdirPathNotSet => ("WorkdirPathNotSet", 60),
+            Error::FileNameEmpty => ("FileNameEmpty", 61),
+            Error::StateNameEmpty => ("StateNameEmpty", 62),
+            Error::KeystoreReadError { .. } => ("KeystoreReadError", 63),
+            Error::KeystoreWriteError { .. } => ("KeystoreWriteError", 64),
+            Error::KeystoreLocked { .. } => ("KeystoreLocked", 65),
+            Error::PrivateKeyInvalid { .. } => ("PrivateKeyInvalid", 66),
+            Error::KeyImportNotAllowed { .. } => ("KeyImportNotAllowed", 67),
+            Error::PackageBuildNotFound { .. } => ("PackageBuildNotFound", 68),
+            Error::PackageBuildReadError { .. } => ("PackageBuildReadError", 69),
+            Error::ObjectNotFound { .. } => ("ObjectNotFound", 70),
+            Error::ObjectDeleted { .. } => ("ObjectDeleted", 71),
+            Error::ObjectWaitTimeout { .. } => ("ObjectWaitTimeout", 72),
+            Error::AddressInvalid { .. } => ("AddressInvalid", 73),
+            Error::ProfileWriteError { .. } => ("ProfileWriteError", 74),
+            Error::ProfileReadError { .. } => ("ProfileReadError", 75),
+        }
+    }
+}
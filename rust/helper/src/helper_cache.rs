@@ -0,0 +1,357 @@
+// Opt-in memoization of the lookups of the selected workdir (see Helper::new_cached).
+//
+// An entry is keyed by workdir name, lookup and argument. It is loaded again when:
+//   - older than the CachePolicy ttl.
+//   - with CachePolicy watch, a file it was loaded from changed. The files are
+//     "stamped" (metadata and symlink target) before the load, and compared on
+//     every hit. A republished package switches the "most-recent" symlink, so its
+//     new id is never missed.
+//   - Helper::invalidate_cache() is called.
+//
+// Errors are not cached (e.g. a package not yet published).
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::Error;
+
+/// How the cached lookups are invalidated. See Helper::set_cache_policy().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Entries older than this are loaded again. None for no expiry.
+    pub ttl: Option<Duration>,
+
+    /// Load again when a file the entry depends on changed on disk.
+    pub watch: bool,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            ttl: None,
+            watch: true,
+        }
+    }
+}
+
+// Identify the state of a file (or directory) without reading it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    link_target: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+// File system access for the cache validation (replaced in tests).
+pub(crate) trait CacheFs: Send {
+    // None when the path does not exist.
+    fn stamp(&self, path: &Path) -> Option<FileStamp>;
+}
+
+pub(crate) struct StdCacheFs;
+
+impl CacheFs for StdCacheFs {
+    fn stamp(&self, path: &Path) -> Option<FileStamp> {
+        let link_target = std::fs::read_link(path).ok();
+        // Follow the symlink (if any), a dangling one is same as not existing.
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileStamp {
+            link_target,
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    workdir: String,
+    lookup: &'static str,
+    arg: String,
+}
+
+impl CacheKey {
+    pub(crate) fn new(workdir: &str, lookup: &'static str, arg: &str) -> Self {
+        Self {
+            workdir: workdir.to_string(),
+            lookup,
+            arg: arg.to_string(),
+        }
+    }
+}
+
+struct CacheEntry {
+    value: Box<dyn Any + Send>,
+    loaded_at: Instant,
+    watched: Vec<(PathBuf, Option<FileStamp>)>, // Empty when not watching.
+}
+
+pub(crate) struct HelperCache {
+    policy: CachePolicy,
+    fs: Box<dyn CacheFs>,
+    entries: HashMap<CacheKey, CacheEntry>,
+}
+
+impl HelperCache {
+    pub(crate) fn new(policy: CachePolicy) -> Self {
+        Self::with_fs(policy, Box::new(StdCacheFs))
+    }
+
+    pub(crate) fn with_fs(policy: CachePolicy, fs: Box<dyn CacheFs>) -> Self {
+        Self {
+            policy,
+            fs,
+            entries: HashMap::new(),
+        }
+    }
+
+    // The entries were validated with the previous policy, so start over.
+    pub(crate) fn set_policy(&mut self, policy: CachePolicy) {
+        self.policy = policy;
+        self.invalidate();
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        self.entries.clear();
+    }
+
+    // Cached value for 'key', otherwise the result of 'load' (cached on success).
+    //
+    // 'watched' are the files used by 'load'.
+    pub(crate) fn get_or_load<T, F>(
+        &mut self,
+        key: CacheKey,
+        watched: Vec<PathBuf>,
+        load: F,
+    ) -> Result<T, Error>
+    where
+        T: Clone + Send + 'static,
+        F: FnOnce() -> Result<T, Error>,
+    {
+        if let Some(entry) = self.entries.get(&key) {
+            if self.is_valid(entry) {
+                if let Some(value) = entry.value.downcast_ref::<T>() {
+                    return Ok(value.clone());
+                }
+            }
+        }
+
+        // Stamp *before* the load, so a change while loading is detected on next call.
+        let watched = if self.policy.watch {
+            watched
+                .into_iter()
+                .map(|path| {
+                    let stamp = self.fs.stamp(&path);
+                    (path, stamp)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let value = match load() {
+            Ok(value) => value,
+            Err(e) => {
+                self.entries.remove(&key);
+                return Err(e);
+            }
+        };
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value: Box::new(value.clone()),
+                loaded_at: Instant::now(),
+                watched,
+            },
+        );
+        Ok(value)
+    }
+
+    fn is_valid(&self, entry: &CacheEntry) -> bool {
+        if let Some(ttl) = self.policy.ttl {
+            if entry.loaded_at.elapsed() >= ttl {
+                return false;
+            }
+        }
+        entry
+            .watched
+            .iter()
+            .all(|(path, stamp)| self.fs.stamp(path) == *stamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suibase_helper_impl::SuibaseHelperImpl;
+    use std::cell::Cell;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    // Count the stamps, and optionally report every file as modified at each call.
+    struct CountingFs {
+        stamps: Arc<AtomicUsize>,
+        always_modified: bool,
+    }
+
+    impl CacheFs for CountingFs {
+        fn stamp(&self, path: &Path) -> Option<FileStamp> {
+            let n = self.stamps.fetch_add(1, Ordering::SeqCst);
+            let mut stamp = StdCacheFs.stamp(path)?;
+            if self.always_modified {
+                stamp.len = n as u64;
+            }
+            Some(stamp)
+        }
+    }
+
+    fn counting_cache(
+        policy: CachePolicy,
+        always_modified: bool,
+    ) -> (HelperCache, Arc<AtomicUsize>) {
+        let stamps = Arc::new(AtomicUsize::new(0));
+        let fs = CountingFs {
+            stamps: stamps.clone(),
+            always_modified,
+        };
+        (HelperCache::with_fs(policy, Box::new(fs)), stamps)
+    }
+
+    #[test]
+    fn test_get_or_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("file");
+        fs::write(&file, "1").unwrap();
+        let key = || CacheKey::new("localnet", "test", "arg");
+        let loads = Cell::new(0);
+        let load = |cache: &mut HelperCache| -> Result<String, Error> {
+            cache.get_or_load(key(), vec![file.clone()], || {
+                loads.set(loads.get() + 1);
+                Ok(fs::read_to_string(&file).unwrap())
+            })
+        };
+
+        // Without watch, a hit does no IO at all.
+        let policy = CachePolicy {
+            ttl: None,
+            watch: false,
+        };
+        let (mut cache, stamps) = counting_cache(policy, false);
+        for _ in 0..1000 {
+            assert_eq!(load(&mut cache).unwrap(), "1");
+        }
+        assert_eq!(loads.get(), 1);
+        assert_eq!(stamps.load(Ordering::SeqCst), 0);
+
+        // Manual invalidation.
+        cache.invalidate();
+        load(&mut cache).unwrap();
+        assert_eq!(loads.get(), 2);
+
+        // With watch, only the metadata is checked on a hit.
+        let (mut cache, stamps) = counting_cache(CachePolicy::default(), false);
+        loads.set(0);
+        for _ in 0..10 {
+            load(&mut cache).unwrap();
+        }
+        assert_eq!(loads.get(), 1);
+        assert_eq!(stamps.load(Ordering::SeqCst), 10);
+
+        // ...and a modified file is loaded again.
+        let (mut cache, _) = counting_cache(CachePolicy::default(), true);
+        loads.set(0);
+        for _ in 0..3 {
+            load(&mut cache).unwrap();
+        }
+        assert_eq!(loads.get(), 3);
+
+        // Expired.
+        let policy = CachePolicy {
+            ttl: Some(Duration::ZERO),
+            watch: false,
+        };
+        let (mut cache, _) = counting_cache(policy, false);
+        loads.set(0);
+        load(&mut cache).unwrap();
+        load(&mut cache).unwrap();
+        assert_eq!(loads.get(), 2);
+
+        // Errors are not cached.
+        let (mut cache, _) = counting_cache(CachePolicy::default(), false);
+        let res: Result<String, Error> =
+            cache.get_or_load(key(), vec![], || Err(Error::NotInstalled));
+        assert!(res.is_err());
+        assert_eq!(load(&mut cache).unwrap(), "1");
+    }
+
+    const PACKAGE_ID_1: &str = "0x00000000000000000000000000000000000000000000000000000000000000a1";
+    const PACKAGE_ID_2: &str = "0x00000000000000000000000000000000000000000000000000000000000000a2";
+
+    // Publication 'n' of the package "demo", made the most recent.
+    fn publish(root: &Path, n: u32, package_id: &str) {
+        let package = root.join("workdirs/localnet/published-data/demo");
+        let publication = package.join(n.to_string());
+        fs::create_dir_all(&publication).unwrap();
+        fs::write(
+            publication.join("package-id.json"),
+            format!("[\"{}\"]", package_id),
+        )
+        .unwrap();
+        let most_recent = package.join("most-recent");
+        let _ = fs::remove_file(&most_recent);
+        std::os::unix::fs::symlink(&publication, &most_recent).unwrap();
+    }
+
+    fn localnet(root: &Path) -> SuibaseHelperImpl {
+        let state = root.join("workdirs/localnet/.state");
+        fs::create_dir_all(&state).unwrap();
+        fs::write(state.join("name"), "localnet").unwrap();
+        fs::write(state.join("user_request"), "stop").unwrap();
+        let mut sbh = SuibaseHelperImpl::with_suibase_path(root);
+        sbh.set_cache_policy(CachePolicy::default());
+        sbh.select_workdir("localnet").unwrap();
+        sbh
+    }
+
+    #[test]
+    fn test_republished_package() {
+        let tmp = tempfile::tempdir().unwrap();
+        publish(tmp.path(), 1, PACKAGE_ID_1);
+        let mut sbh = localnet(tmp.path());
+        for _ in 0..3 {
+            let id = sbh.package_object_id("demo").unwrap();
+            assert_eq!(id.to_string(), PACKAGE_ID_1);
+        }
+
+        // Never the stale id once republished.
+        publish(tmp.path(), 2, PACKAGE_ID_2);
+        let id = sbh.package_object_id("demo").unwrap();
+        assert_eq!(id.to_string(), PACKAGE_ID_2);
+    }
+
+    #[test]
+    fn test_concurrent_calls() {
+        let tmp = tempfile::tempdir().unwrap();
+        publish(tmp.path(), 1, PACKAGE_ID_1);
+        let sbh = crate::Helper(Arc::new(Mutex::new(localnet(tmp.path()))));
+
+        // The Helper calls, interleaved with invalidations.
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let sbh = &sbh;
+                scope.spawn(move || {
+                    for j in 0..200 {
+                        assert_eq!(sbh.package_id("demo").unwrap().to_string(), PACKAGE_ID_1);
+                        if (i + j) % 50 == 0 {
+                            sbh.invalidate_cache();
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(sbh.package_id("demo").unwrap().to_string(), PACKAGE_ID_1);
+    }
+}
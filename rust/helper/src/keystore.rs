@@ -0,0 +1,572 @@
+// Consistency of the workdir keystore with its client.yaml, and import of private keys.
+//
+// The file keystore (e.g. config/sui.keystore) is a JSON array of base64 encoded
+// "flag || private key". When it exists, the aliases file next to it (sui.aliases)
+// has one alias per key.
+//
+// An import appends to the keystore while holding a "<keystore>.lock" file (created
+// exclusively, so only between users of this helper, the sui client ignores it). A
+// copy of the keystore prior to the import is kept as "<keystore>.bak".
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{EncodeDecodeBase64, SuiKeyPair};
+
+use crate::error::Error;
+
+// Wait on another import for at most this long.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY: Duration = Duration::from_millis(20);
+
+// Flag of an Ed25519 key (see the keystore format).
+const ED25519_FLAG: u8 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeystoreReport {
+    pub keystore_path: String,
+    pub active_address: Option<String>, // None when not set in client.yaml.
+    pub key_addresses: Vec<String>,     // Of every valid key, in the keystore order.
+    pub missing_keys: Vec<String>,      // Used by the workdir, but no key to sign.
+    pub unused_keys: Vec<String>,       // Key of an address not used by the workdir.
+    pub invalid_entries: u64,           // Keystore entries that could not be decoded.
+}
+
+impl KeystoreReport {
+    // True when every address used by the workdir can sign.
+    pub fn is_ok(&self) -> bool {
+        self.missing_keys.is_empty() && self.invalid_entries == 0
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path: OsString = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn read_error(path: &Path, msg: String) -> Error {
+    Error::KeystoreReadError {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    }
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> Error {
+    Error::KeystoreWriteError {
+        path: path.to_string_lossy().to_string(),
+        msg: e.to_string(),
+    }
+}
+
+// The entries of a keystore or aliases file (both are a JSON array).
+fn read_json_array(path: &Path) -> Result<Vec<JsonValue>, Error> {
+    let contents = fs::read_to_string(path).map_err(|e| read_error(path, e.to_string()))?;
+    match serde_json::from_str(&contents) {
+        Ok(JsonValue::Array(entries)) => Ok(entries),
+        Ok(_) => Err(read_error(path, "not a JSON array".to_string())),
+        Err(e) => Err(read_error(path, e.to_string())),
+    }
+}
+
+// Address of each keystore entry (None when it could not be decoded).
+fn key_addresses(entries: &[JsonValue]) -> Vec<Option<SuiAddress>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let keypair = SuiKeyPair::decode_base64(entry.as_str()?).ok()?;
+            Some(SuiAddress::from(&keypair.public()))
+        })
+        .collect()
+}
+
+// Default active address of the sui client when client.yaml has none: the first key,
+// with the keys ordered by address (None for an empty keystore).
+//
+// An invalid entry is an error, same as for the sui client.
+pub(crate) fn first_address(keystore_path: &Path) -> Result<Option<SuiAddress>, Error> {
+    let keys = key_addresses(&read_json_array(keystore_path)?);
+    if keys.iter().any(Option::is_none) {
+        return Err(read_error(keystore_path, "invalid key entry".to_string()));
+    }
+    Ok(keys.into_iter().flatten().min())
+}
+
+// Cross-check the addresses used by the workdir (the active one and the suibase named
+// ones) with the keys of the keystore.
+pub(crate) fn verify(
+    keystore_path: &Path,
+    active_address: Option<SuiAddress>,
+    named_addresses: &[SuiAddress],
+) -> Result<KeystoreReport, Error> {
+    let keys = key_addresses(&read_json_array(keystore_path)?);
+    let key_set: BTreeSet<SuiAddress> = keys.iter().flatten().copied().collect();
+
+    let mut used = Vec::new();
+    for address in active_address.iter().chain(named_addresses) {
+        if !used.contains(address) {
+            used.push(*address);
+        }
+    }
+
+    let to_strings = |addresses: Vec<&SuiAddress>| -> Vec<String> {
+        addresses.into_iter().map(|a| a.to_string()).collect()
+    };
+    Ok(KeystoreReport {
+        keystore_path: keystore_path.to_string_lossy().to_string(),
+        active_address: active_address.map(|address| address.to_string()),
+        key_addresses: to_strings(keys.iter().flatten().collect()),
+        missing_keys: to_strings(used.iter().filter(|a| !key_set.contains(a)).collect()),
+        unused_keys: to_strings(
+            keys.iter()
+                .flatten()
+                .filter(|address| !used.contains(address))
+                .collect(),
+        ),
+        invalid_entries: keys.iter().filter(|key| key.is_none()).count() as u64,
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Accepts a bech32 "suiprivkey1..." (as exported by "sui keytool export"), or hex of
+// either "flag || private key" (33 bytes) or an Ed25519 private key (32 bytes).
+//
+// The key is never part of the error (could end up in a log).
+pub(crate) fn parse_private_key(private_key: &str) -> Result<SuiKeyPair, Error> {
+    let invalid = |msg: &str| Error::PrivateKeyInvalid {
+        msg: msg.to_string(),
+    };
+    let private_key = private_key.trim();
+    if private_key.starts_with("suiprivkey") {
+        return SuiKeyPair::decode(private_key).map_err(|_| invalid("bech32 decoding failed"));
+    }
+    let hex = private_key.strip_prefix("0x").unwrap_or(private_key);
+    let bytes = decode_hex(hex).ok_or_else(|| invalid("neither bech32 nor hex"))?;
+    let flagged = match bytes.len() {
+        32 => [&[ED25519_FLAG], bytes.as_slice()].concat(),
+        33 => bytes,
+        _ => return Err(invalid("expecting 32 or 33 bytes")),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(flagged);
+    SuiKeyPair::decode_base64(&encoded).map_err(|_| invalid("unsupported key scheme or value"))
+}
+
+// Exclusive access to the keystore among the users of this helper. A lock file left by
+// a crashed process must be deleted manually (see Error::KeystoreLocked).
+struct KeystoreLock {
+    path: PathBuf,
+}
+
+impl KeystoreLock {
+    fn acquire(keystore_path: &Path, timeout: Duration) -> Result<Self, Error> {
+        let path = with_suffix(keystore_path, ".lock");
+        let start = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if start.elapsed() >= timeout {
+                        return Err(Error::KeystoreLocked {
+                            path: path.to_string_lossy().to_string(),
+                        });
+                    }
+                    std::thread::sleep(LOCK_RETRY);
+                }
+                Err(e) => return Err(write_error(&path, e)),
+            }
+        }
+    }
+}
+
+impl Drop for KeystoreLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Replace 'path' in one step (never seen partially written), keeping its permissions.
+fn write_replace(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = with_suffix(path, ".tmp");
+    let _ = fs::remove_file(&tmp);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        // Private keys. Not readable by others, even temporarily.
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::set_permissions(&tmp, fs::metadata(path)?.permissions())?;
+    fs::rename(&tmp, path)
+}
+
+fn to_pretty_json(entries: &[JsonValue]) -> Vec<u8> {
+    // Serializing a Vec of JSON values can't fail.
+    serde_json::to_vec_pretty(entries).unwrap_or_default()
+}
+
+// Append the key to the keystore (and an alias for it, when the aliases file exists).
+//
+// Nothing is written when the keystore already has the key.
+pub(crate) fn import_key(keystore_path: &Path, private_key: &str) -> Result<SuiAddress, Error> {
+    import_key_with_timeout(keystore_path, private_key, LOCK_TIMEOUT)
+}
+
+fn import_key_with_timeout(
+    keystore_path: &Path,
+    private_key: &str,
+    lock_timeout: Duration,
+) -> Result<SuiAddress, Error> {
+    let keypair = parse_private_key(private_key)?;
+    let address = SuiAddress::from(&keypair.public());
+
+    let _lock = KeystoreLock::acquire(keystore_path, lock_timeout)?;
+    let mut entries = read_json_array(keystore_path)?;
+    if key_addresses(&entries).contains(&Some(address)) {
+        return Ok(address);
+    }
+    let aliases_path = keystore_path.with_extension("aliases");
+    let mut aliases = if aliases_path.exists() {
+        Some(read_json_array(&aliases_path)?)
+    } else {
+        None
+    };
+
+    fs::copy(keystore_path, with_suffix(keystore_path, ".bak"))
+        .map_err(|e| write_error(keystore_path, e))?;
+    entries.push(JsonValue::String(keypair.encode_base64()));
+    write_replace(keystore_path, &to_pretty_json(&entries))
+        .map_err(|e| write_error(keystore_path, e))?;
+
+    if let Some(aliases) = aliases.as_mut() {
+        // e.g. "imported-1a2b3c4d", from the address (so unique).
+        let alias = format!("imported-{}", &address.to_string()[2..10]);
+        aliases.push(serde_json::json!({
+            "alias": alias,
+            "public_key_base64": keypair.public().encode_base64(),
+        }));
+        write_replace(&aliases_path, &to_pretty_json(aliases))
+            .map_err(|e| write_error(&aliases_path, e))?;
+    }
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suibase_helper_impl::SuibaseHelperImpl;
+
+    // Ed25519 private key of 32 times 'n' (as hex), and its keypair.
+    fn test_key(n: u8) -> (String, SuiKeyPair) {
+        let hex: String = std::iter::repeat(format!("{:02x}", n)).take(32).collect();
+        let keypair = parse_private_key(&hex).unwrap();
+        (hex, keypair)
+    }
+
+    fn address_of(keypair: &SuiKeyPair) -> SuiAddress {
+        SuiAddress::from(&keypair.public())
+    }
+
+    // A workdir with a keystore of 'keys' (plus 'extra' raw entries), an aliases file
+    // and a client.yaml with the 'active' address.
+    fn create_workdir(
+        root: &Path,
+        name: &str,
+        keys: &[&SuiKeyPair],
+        extra: &[&str],
+        active: Option<SuiAddress>,
+    ) -> PathBuf {
+        let workdir = root.join("workdirs").join(name);
+        let state = workdir.join(".state");
+        let config = workdir.join("config");
+        fs::create_dir_all(&state).unwrap();
+        fs::create_dir_all(&config).unwrap();
+        fs::write(state.join("name"), name).unwrap();
+        fs::write(state.join("user_request"), "stop").unwrap();
+
+        let keystore = config.join("sui.keystore");
+        let mut entries: Vec<JsonValue> = keys
+            .iter()
+            .map(|key| JsonValue::String(key.encode_base64()))
+            .collect();
+        entries.extend(extra.iter().map(|entry| JsonValue::from(*entry)));
+        fs::write(&keystore, to_pretty_json(&entries)).unwrap();
+        let aliases: Vec<JsonValue> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                serde_json::json!({
+                    "alias": format!("key-{}", i),
+                    "public_key_base64": key.public().encode_base64(),
+                })
+            })
+            .collect();
+        fs::write(config.join("sui.aliases"), to_pretty_json(&aliases)).unwrap();
+
+        let active = match active {
+            Some(address) => format!("\"{}\"", address),
+            None => "~".to_string(),
+        };
+        fs::write(
+            config.join("client.yaml"),
+            format!(
+                "---\nkeystore:\n  File: {}\nenvs: []\nactive_env: {}\nactive_address: {}\n",
+                keystore.display(),
+                name,
+                active
+            ),
+        )
+        .unwrap();
+        keystore
+    }
+
+    fn select(root: &Path, name: &str) -> SuibaseHelperImpl {
+        let mut sbh = SuibaseHelperImpl::with_suibase_path(root);
+        sbh.select_workdir(name).unwrap();
+        sbh
+    }
+
+    #[test]
+    fn test_parse_private_key() {
+        let (hex, keypair) = test_key(1);
+        assert_eq!(
+            address_of(&parse_private_key(&format!(" 0x{} ", hex)).unwrap()),
+            address_of(&keypair)
+        );
+        let bech32 = keypair.encode().unwrap();
+        assert_eq!(
+            address_of(&parse_private_key(&bech32).unwrap()),
+            address_of(&keypair)
+        );
+
+        // Flag of another scheme (secp256k1).
+        let secp256k1 = parse_private_key(&format!("01{}", hex)).unwrap();
+        assert!(matches!(secp256k1, SuiKeyPair::Secp256k1(_)));
+
+        for bad in [
+            "",
+            "0x",
+            "zz",
+            &hex[2..],
+            &format!("09{}", hex),
+            "suiprivkey1bad",
+        ] {
+            let res = parse_private_key(bad);
+            assert!(
+                matches!(res, Err(Error::PrivateKeyInvalid { .. })),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_keystore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, key_1) = test_key(1);
+        let (_, key_2) = test_key(2);
+        let (_, key_3) = test_key(3);
+        create_workdir(
+            tmp.path(),
+            "localnet",
+            &[&key_1, &key_2],
+            &["not-a-key"],
+            Some(address_of(&key_3)),
+        );
+        fs::write(
+            tmp.path().join("workdirs/localnet/.state/dns"),
+            format!(
+                "{{\"known\":{{\"sb-1-ed25519\":{{\"address\":\"{}\"}}}}}}",
+                address_of(&key_1)
+            ),
+        )
+        .unwrap();
+
+        let mut sbh = select(tmp.path(), "localnet");
+        let report = sbh.verify_keystore().unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.active_address, Some(address_of(&key_3).to_string()));
+        assert_eq!(
+            report.key_addresses,
+            [
+                address_of(&key_1).to_string(),
+                address_of(&key_2).to_string()
+            ]
+        );
+        assert_eq!(report.missing_keys, [address_of(&key_3).to_string()]);
+        assert_eq!(report.unused_keys, [address_of(&key_2).to_string()]);
+        assert_eq!(report.invalid_entries, 1);
+
+        // Without an active address (and dns), every key is unused.
+        create_workdir(tmp.path(), "devnet", &[&key_1], &[], None);
+        let report = select(tmp.path(), "devnet").verify_keystore().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.active_address, None);
+        assert_eq!(report.unused_keys, [address_of(&key_1).to_string()]);
+    }
+
+    #[test]
+    fn test_import_key() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, key_1) = test_key(1);
+        let (hex_2, key_2) = test_key(2);
+        let keystore = create_workdir(
+            tmp.path(),
+            "localnet",
+            &[&key_1],
+            &[],
+            Some(address_of(&key_2)),
+        );
+        let original = fs::read(&keystore).unwrap();
+
+        let mut sbh = select(tmp.path(), "localnet");
+        assert!(!sbh.verify_keystore().unwrap().is_ok());
+        assert_eq!(sbh.import_key(&hex_2, false).unwrap(), address_of(&key_2));
+        let report = sbh.verify_keystore().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.key_addresses.len(), 2);
+        assert_eq!(fs::read(with_suffix(&keystore, ".bak")).unwrap(), original);
+        assert!(!with_suffix(&keystore, ".lock").exists());
+        let aliases = read_json_array(&keystore.with_extension("aliases")).unwrap();
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(
+            aliases[1]["public_key_base64"],
+            key_2.public().encode_base64()
+        );
+
+        // Importing again changes nothing.
+        let keystore_contents = fs::read(&keystore).unwrap();
+        assert_eq!(sbh.import_key(&hex_2, false).unwrap(), address_of(&key_2));
+        assert_eq!(fs::read(&keystore).unwrap(), keystore_contents);
+
+        // Concurrent imports are serialized by the lock.
+        std::thread::scope(|scope| {
+            for n in 10..18 {
+                let keystore = &keystore;
+                scope.spawn(move || import_key(keystore, &test_key(n).0).unwrap());
+            }
+        });
+        let entries = read_json_array(&keystore).unwrap();
+        assert_eq!(entries.len(), 10);
+        let aliases = read_json_array(&keystore.with_extension("aliases")).unwrap();
+        assert_eq!(aliases.len(), 10);
+
+        // Held by another process (or a stale lock file).
+        let lock = KeystoreLock::acquire(&keystore, LOCK_TIMEOUT).unwrap();
+        let res = import_key_with_timeout(&keystore, &test_key(3).0, Duration::from_millis(50));
+        assert!(matches!(res, Err(Error::KeystoreLocked { .. })));
+        drop(lock);
+        assert!(import_key_with_timeout(&keystore, &test_key(3).0, LOCK_TIMEOUT).is_ok());
+
+        assert!(matches!(
+            sbh.import_key("0x1234", false),
+            Err(Error::PrivateKeyInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_active_address_from_files() {
+        use sui_keys::keystore::{AccountKeystore, FileBasedKeystore};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, key_1) = test_key(1);
+        let (_, key_2) = test_key(2);
+        let (_, key_3) = test_key(3);
+        let first = [&key_1, &key_2, &key_3]
+            .iter()
+            .map(|key| address_of(key))
+            .min()
+            .unwrap();
+        let active =
+            |sbh: &mut SuibaseHelperImpl| sbh.client_sui_address("active").map(SuiAddress::from);
+
+        // Set in client.yaml.
+        create_workdir(
+            tmp.path(),
+            "localnet",
+            &[&key_1, &key_2],
+            &[],
+            Some(address_of(&key_3)),
+        );
+        let mut sbh = select(tmp.path(), "localnet");
+        assert_eq!(active(&mut sbh).unwrap(), address_of(&key_3));
+
+        // Not set ("~"), then the first key like the sui client.
+        let keystore = create_workdir(tmp.path(), "devnet", &[&key_3, &key_2, &key_1], &[], None);
+        let mut sbh = select(tmp.path(), "devnet");
+        assert_eq!(active(&mut sbh).unwrap(), first);
+        let sui_keystore = FileBasedKeystore::new(&keystore).unwrap();
+        assert_eq!(sui_keystore.addresses().first().copied(), Some(first));
+
+        // Keystore only (client.yaml not created yet).
+        let config = tmp.path().join("workdirs/devnet/config");
+        fs::remove_file(config.join("client.yaml")).unwrap();
+        assert_eq!(active(&mut sbh).unwrap(), first);
+
+        // Malformed client.yaml, or an invalid active_address.
+        fs::write(config.join("client.yaml"), "active_address: [\n").unwrap();
+        assert!(matches!(
+            active(&mut sbh),
+            Err(Error::ConfigReadError { .. })
+        ));
+        fs::write(config.join("client.yaml"), "active_address: \"0xzz\"\n").unwrap();
+        assert!(matches!(
+            active(&mut sbh),
+            Err(Error::ConfigActiveAddressParseError { .. })
+        ));
+
+        // An invalid key fails. sui-keys decodes the keys the same way (with
+        // SuiKeyPair::decode_base64), so the fallback fails too: only the error
+        // path of set_sui_keys_fallback is exercised here, not a recovered address.
+        create_workdir(tmp.path(), "testnet", &[&key_2], &["not-a-key"], None);
+        let mut sbh = select(tmp.path(), "testnet");
+        assert!(matches!(
+            active(&mut sbh),
+            Err(Error::KeystoreReadError { .. })
+        ));
+        sbh.set_sui_keys_fallback(true);
+        assert!(matches!(
+            active(&mut sbh),
+            Err(Error::ConfigActiveAddressParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_import_key_mainnet() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (hex_1, key_1) = test_key(1);
+        let (_, key_2) = test_key(2);
+        create_workdir(tmp.path(), "mainnet", &[&key_2], &[], None);
+
+        let mut sbh = select(tmp.path(), "mainnet");
+        assert!(matches!(
+            sbh.import_key(&hex_1, false),
+            Err(Error::KeyImportNotAllowed { .. })
+        ));
+        assert_eq!(sbh.import_key(&hex_1, true).unwrap(), address_of(&key_1));
+    }
+}
@@ -30,15 +30,62 @@
 //! You may have to adjust the number "../" depending on where your Cargo.toml is located relative to ~/suibase.
 
 mod error;
-pub use crate::error::Error;
+pub use crate::error::{Error, EXIT_CODE_INTERNAL, EXIT_CODE_USAGE};
 
+mod active_workdir;
+mod cli_output;
+mod daemon_endpoints;
+mod env_file;
+mod helper_cache;
+#[cfg(feature = "sui-types")]
+mod keystore;
+#[cfg(feature = "sui-types")]
+mod move_call;
+mod object_wait;
+mod package_build;
+mod sui_ids;
+mod suibase_daemon_api;
 mod suibase_helper_impl;
+// Same resolution of the ~/suibase location as the suibase-daemon (SUIBASE_HOME...).
+// Its expand_home() is only used by the daemon.
+#[allow(dead_code)]
+#[path = "../../suibase/crates/common/src/utils/suibase_home.rs"]
+mod suibase_home;
 mod suibase_root;
 mod suibase_workdir;
+mod tx_lookup;
+mod workdir_status;
+
+pub use crate::cli_output::{
+    ClientAddressOutput, ErrorOutput, KeystorePathOutput, PackageIdOutput, PublishedObjectsOutput,
+    RpcUrlOutput, WorkdirOutput,
+};
+pub use crate::daemon_endpoints::{DaemonEndpoint, DaemonEndpoints};
+pub use crate::env_file::{EnvFormat, PublishedIds};
+pub use crate::helper_cache::CachePolicy;
+#[cfg(feature = "sui-types")]
+pub use crate::keystore::KeystoreReport;
+#[cfg(feature = "sui-types")]
+pub use crate::move_call::{MoveCallResult, MOVE_CALL_GAS_BUDGET};
+pub use crate::package_build::PackageBuildInfo;
+pub use crate::sui_ids::{SuibaseAddress, SuibaseObjectId, SUI_ID_LENGTH};
+pub use crate::suibase_daemon_api::{
+    GasCoinBucket, GasInventory, MergeGasCoinsResult, ProfileConflict, ProfileExport,
+    ProfileImport, ProfileImportOptions, SuiBinaryProvenance,
+};
+pub use crate::suibase_root::{
+    Compatibility, InstallationStatus, MIN_SUIBASE_VERSION, TESTED_SUIBASE_VERSION,
+};
+pub use crate::tx_lookup::TxStatus;
+pub use crate::workdir_status::{WorkdirService, WorkdirStatus};
 
 use crate::suibase_helper_impl::SuibaseHelperImpl;
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(feature = "sui-types")]
 use sui_types::base_types::{ObjectID, SuiAddress};
 
 #[cfg(feature = "build-with-uniffi")]
@@ -77,12 +124,96 @@ impl Helper {
         Helper(Arc::new(Mutex::new(SuibaseHelperImpl::new())))
     }
 
+    /// Same as new(), but the lookups of the selected workdir are cached in memory
+    /// with the default CachePolicy.
+    ///
+    /// Cached: keystore_pathname(), package_object_id(), published_new_object_ids()
+    /// and client_sui_address() (and the calls derived from these).
+    ///
+    /// Useful when a test suite does many of these calls. By default, an entry is
+    /// loaded again when any of its files changed on disk (e.g. the package was
+    /// republished).
+    pub fn new_cached() -> Self {
+        let helper = Self::new();
+        helper.set_cache_policy(CachePolicy::default());
+        helper
+    }
+
+    /// Enable the cache (see new_cached), or change how its entries are invalidated.
+    ///
+    /// With `watch: false`, a cached call does no file IO at all, but may return a
+    /// stale value until the `ttl` expires or invalidate_cache() is called.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use suibase::{CachePolicy, Helper};
+    /// let sbh = Helper::new();
+    /// sbh.set_cache_policy(CachePolicy { ttl: Some(Duration::from_secs(10)), watch: false });
+    /// ```
+    pub fn set_cache_policy(&self, policy: CachePolicy) {
+        self.0.lock().unwrap().set_cache_policy(policy)
+    }
+
+    /// Forget all the cached lookups (no effect when the cache is not enabled).
+    pub fn invalidate_cache(&self) {
+        self.0.lock().unwrap().invalidate_cache()
+    }
+
+    /// The "active" address is resolved from the workdir files only (client.yaml, then
+    /// the first key of the keystore), without the sui binary.
+    ///
+    /// When enabled, a keystore that the helper cannot read is loaded again with the
+    /// sui-keys crate (as done by the sui client). Off by default.
+    ///
+    /// Without the "sui-types" feature the keystore is not read at all, so the active
+    /// address must be set in client.yaml.
+    pub fn set_sui_keys_fallback(&self, enabled: bool) {
+        self.0.lock().unwrap().set_sui_keys_fallback(enabled)
+    }
+
     /// Check first if suibase is installed, otherwise
     /// most of the other calls will fail in some ways.
+    ///
+    /// This is a cheap check. Use check_compatibility() to also verify that
+    /// the installed suibase is not too old for this helper.
     pub fn is_installed(&self) -> Result<bool, Error> {
         self.0.lock().unwrap().is_installed()
     }
 
+    /// Stricter than is_installed(). Identify what is missing when the installation
+    /// is only partially initialized (e.g. interrupted install or update).
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::{Helper, InstallationStatus};
+    /// let sbh = Helper::new();
+    /// if let InstallationStatus::PartiallyInitialized { missing } = sbh.installation_status()? {
+    ///    println!("Missing {:?}. Try ~/suibase/update", missing);
+    /// }
+    /// ```
+    pub fn installation_status(&self) -> Result<InstallationStatus, Error> {
+        self.0.lock().unwrap().installation_status()
+    }
+
+    /// Verify that the installed ~/suibase version is supported by this helper.
+    ///
+    /// Returns Error::IncompatibleSuibase when older than MIN_SUIBASE_VERSION
+    /// (or when too old to even have a version). An installation newer than
+    /// TESTED_SUIBASE_VERSION is likely OK, but is reported as NewerThanTested.
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::{Compatibility, Helper};
+    /// let sbh = Helper::new();
+    /// if let Compatibility::NewerThanTested { found } = sbh.check_compatibility()? {
+    ///    println!("suibase {} not tested with this helper", found);
+    /// }
+    /// ```
+    pub fn check_compatibility(&self) -> Result<Compatibility, Error> {
+        self.0.lock().unwrap().check_compatibility()
+    }
+
     /// Select an existing workdir by name.
     ///
     /// Possible values are:
@@ -94,10 +225,37 @@ impl Helper {
     ///       to be done for "localnet". The selection does not change even if the user
     ///       externally change the active after this call.
     ///
+    /// Note: "cargobin" uses the sui client config of the user (~/.sui/sui_config, unless
+    ///       its config symlink is changed). Only the "active" address, the keystore and
+    ///       the RPC/Websocket URLs of the active env are available for it.
+    ///
     pub fn select_workdir(&self, workdir_name: &str) -> Result<(), Error> {
         self.0.lock().unwrap().select_workdir(workdir_name)
     }
 
+    /// Change the active workdir, same as doing "<workdir> set-active" (e.g. "testnet set-active").
+    ///
+    /// Returns false when it was already the active one. The workdir must be initialized
+    /// ("create" or "start" was done once), but not necessarily started.
+    ///
+    /// Done by the suibase-daemon when running (its status is updated right away).
+    /// Concurrent switches are serialized (the last one wins).
+    ///
+    /// Note: Does not change the workdir selected by this API. Call select_workdir("active")
+    ///       after to also select it.
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.set_active_workdir("testnet")?;
+    /// sbh.select_workdir("active")?;
+    /// assert_eq!(sbh.workdir()?, "testnet");
+    /// ```
+    pub fn set_active_workdir(&self, workdir_name: &str) -> Result<bool, Error> {
+        self.0.lock().unwrap().set_active_workdir(workdir_name)
+    }
+
     /// Get the name of the selected workdir.
     pub fn workdir(&self) -> Result<String, Error> {
         self.0.lock().unwrap().workdir()
@@ -110,19 +268,88 @@ impl Helper {
         self.0.lock().unwrap().keystore_pathname()
     }
 
+    /// Cross-check the keystore with the addresses used by the selected workdir: the
+    /// client.yaml active address and the suibase named addresses (e.g. "sb-1-ed25519").
+    ///
+    /// Reports both the used addresses without a key (`missing_keys`) and the keys
+    /// of an unused address (`unused_keys`). The keystore is not modified.
+    #[cfg(feature = "sui-types")]
+    pub fn verify_keystore(&self) -> Result<KeystoreReport, Error> {
+        self.0.lock().unwrap().verify_keystore()
+    }
+
+    /// Add a private key to the file keystore of the selected workdir, and return
+    /// its address. Same as "sui keytool import", but without a mnemonic.
+    ///
+    /// `private_key` is either bech32 ("suiprivkey1...") or hex, of an Ed25519 key
+    /// (32 bytes) or of the flag byte followed by the key (33 bytes).
+    ///
+    /// Nothing is written when the key is already in the keystore. Otherwise, the
+    /// keystore prior to the import is copied to "<keystore>.bak".
+    ///
+    /// Refused on mainnet unless `allow_mainnet` is true.
+    #[cfg(feature = "sui-types")]
+    pub fn import_key(&self, private_key: &str, allow_mainnet: bool) -> Result<SuiAddress, Error> {
+        let addr = self
+            .0
+            .lock()
+            .unwrap()
+            .import_key(private_key, allow_mainnet)?;
+        Ok(addr.into())
+    }
+
+    /// Same as import_key(), returning a SuibaseAddress.
+    ///
+    /// The keys are decoded with sui-types, so this also needs the "sui-types" feature.
+    #[cfg(feature = "sui-types")]
+    pub fn import_key_string(
+        &self,
+        private_key: &str,
+        allow_mainnet: bool,
+    ) -> Result<SuibaseAddress, Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .import_key(private_key, allow_mainnet)
+    }
+
     /// Get the ObjectID of the last successfully published "package_name".
     ///
     /// package_name is the "name" field specified in the "Move.toml".
     ///
     /// Related path: ~/suibase/workdirs/<workdir_name>/published-data/<package_name>/
+    ///
+    /// Fails with `Error::UnsupportedForWorkdir` for "cargobin" (no published-data).
+    #[cfg(feature = "sui-types")]
     pub fn package_object_id(&self, package_name: &str) -> Result<ObjectID, Error> {
+        let id = self.0.lock().unwrap().package_object_id(package_name)?;
+        Ok(id.into())
+    }
+
+    /// Same as package_object_id(), without sui-types (see SuibaseObjectId).
+    pub fn package_id(&self, package_name: &str) -> Result<SuibaseObjectId, Error> {
         self.0.lock().unwrap().package_object_id(package_name)
     }
 
-    /// Alternative for string-based API.
-    pub fn package_id(&self, package_name: &str) -> Result<String, Error> {
-        let id = self.package_object_id(package_name)?;
-        Ok(id.to_string())
+    /// Build metadata of the last published "package_name".
+    ///
+    /// Useful to find which source and compiler produced the on-chain package (e.g. when
+    /// a bytecode verification fails).
+    ///
+    /// The compiler version, source path and git commit are recorded by `<workdir> publish`
+    /// and are None for an older publication (the source path may still be found from
+    /// the build flags).
+    ///
+    /// Related path: ~/suibase/workdirs/<workdir_name>/published-data/<package_name>/
+    pub fn package_build_info(&self, package_name: &str) -> Result<PackageBuildInfo, Error> {
+        self.0.lock().unwrap().package_build_info(package_name)
+    }
+
+    /// Names of the modules of the last published "package_name" (sorted).
+    ///
+    /// Related path: ~/suibase/workdirs/<workdir_name>/published-data/<package_name>/
+    pub fn package_modules(&self, package_name: &str) -> Result<Vec<String>, Error> {
+        self.0.lock().unwrap().package_modules(package_name)
     }
 
     /// Get the ObjectID of the objects that were created when the package was published.
@@ -143,14 +370,19 @@ impl Helper {
     /// The object_type is "acme::Tools::Anvil"
     ///
     /// Related path: ~/suibase/workdirs/<workdir_name>/published-data/<package_name>/
+    #[cfg(feature = "sui-types")]
     pub fn published_new_object_ids(&self, object_type: &str) -> Result<Vec<ObjectID>, Error> {
-        self.0.lock().unwrap().published_new_object_ids(object_type)
+        let res = self
+            .0
+            .lock()
+            .unwrap()
+            .published_new_object_ids(object_type)?;
+        Ok(res.into_iter().map(ObjectID::from).collect())
     }
 
-    /// Alternative to published_new_object_ids() for string-based API.
-    pub fn published_new_objects(&self, object_type: &str) -> Result<Vec<String>, Error> {
-        let res = self.published_new_object_ids(object_type)?;
-        Ok(res.iter().map(|c| c.to_string()).collect())
+    /// Same as published_new_object_ids(), without sui-types (see SuibaseObjectId).
+    pub fn published_new_objects(&self, object_type: &str) -> Result<Vec<SuibaseObjectId>, Error> {
+        self.0.lock().unwrap().published_new_object_ids(object_type)
     }
 
     /// Get an address by name.
@@ -163,23 +395,475 @@ impl Helper {
     /// Examples: "active", "sb-1-ed25519", "sb-3-scp256r1", "sb-5-scp256k1" ...
     ///
     /// Choosing "active" is same as doing "sui client active-address" for the selected workdir.
+    /// It is resolved from the workdir files, so the sui binary is not needed (see
+    /// set_sui_keys_fallback()).
+    ///
+    /// Only "active" is supported for "cargobin" (`Error::UnsupportedForWorkdir` otherwise).
+    #[cfg(feature = "sui-types")]
     pub fn client_sui_address(&self, address_name: &str) -> Result<SuiAddress, Error> {
+        let addr = self.0.lock().unwrap().client_sui_address(address_name)?;
+        Ok(addr.into())
+    }
+
+    /// Same as client_sui_address(), without sui-types (see SuibaseAddress).
+    pub fn client_address(&self, address_name: &str) -> Result<SuibaseAddress, Error> {
         self.0.lock().unwrap().client_sui_address(address_name)
     }
 
-    /// Alternative to client_sui_address() for string-based API.
-    pub fn client_address(&self, address_name: &str) -> Result<String, Error> {
-        let addr = self.client_sui_address(address_name)?;
-        Ok(addr.to_string())
+    /// Endpoints listened on by the suibase-daemon (API, explorer, proxy of each workdir).
+    ///
+    /// From ~/suibase/workdirs/common/daemon-endpoints.json, maintained by the daemon.
+    /// `stale` is true when the daemon was stopped or is no longer running (e.g. crashed).
+    ///
+    /// `None` when the daemon never ran (or with an incompatible suibase version).
+    pub fn daemon_endpoints(&self) -> Result<Option<DaemonEndpoints>, Error> {
+        self.0.lock().unwrap().daemon_endpoints()
     }
 
     /// Get a RPC URL for the selected workdir.
+    ///
+    /// This is the proxy of the workdir when the suibase-daemon is running (see
+    /// daemon_endpoints), otherwise the primary link of the workdir.
+    ///
+    /// For "cargobin", this is the RPC of the active env in its client.yaml.
     pub fn rpc_url(&self) -> Result<String, Error> {
         self.0.lock().unwrap().rpc_url()
     }
 
     /// Get a Websocket URL for the selected workdir.
+    ///
+    /// A "ws" endpoint of the suibase-daemon is preferred when listed in
+    /// daemon_endpoints (the proxy has none yet), otherwise the primary link.
     pub fn ws_url(&self) -> Result<String, Error> {
         self.0.lock().unwrap().ws_url()
     }
+
+    /// Get if the services ("node", "faucet" and "proxy") of the selected workdir are up.
+    ///
+    /// Uses the suibase-daemon status when the daemon is running. Otherwise, the
+    /// fullnode and the proxy are probed directly (the faucet is then not reported).
+    ///
+    /// `up` is for the node (the multi-link RPC for a remote network) and
+    /// `latency_ms` is the round-trip of the status request.
+    ///
+    /// rpc_url() does not check any of this, so a stopped localnet is otherwise
+    /// found only later by a connection error.
+    pub fn workdir_status(&self) -> Result<WorkdirStatus, Error> {
+        self.0.lock().unwrap().workdir_status()
+    }
+
+    /// Wait up to `timeout` for the selected workdir to be up (see workdir_status).
+    ///
+    /// Fails with `Error::WorkdirDown` (e.g. "localnet is DOWN — run 'localnet start'")
+    /// instead of letting the caller fail later on a connection error.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// sbh.ensure_workdir_ready(Duration::from_secs(10))?;
+    /// let rpc_url = sbh.rpc_url()?;
+    /// ```
+    pub fn ensure_workdir_ready(&self, timeout: Duration) -> Result<WorkdirStatus, Error> {
+        // The lock is released between each check.
+        workdir_status::wait_until_up(timeout, || self.workdir_status())
+    }
+
+    /// Get a link to an object (e.g. "0x2") in the sui-explorer served by the suibase-daemon.
+    ///
+    /// The link selects the network of the selected workdir, and uses the port actually
+    /// used by the daemon (may differ from `sui_explorer_port` when already in use).
+    ///
+    /// Requires the suibase-daemon to be running.
+    pub fn explorer_url_for_object(&self, object_id: &str) -> Result<String, Error> {
+        self.0.lock().unwrap().explorer_url_for_object(object_id)
+    }
+
+    /// Get the SUI coins inventory of an address (coin count, total balance, largest
+    /// coin and a histogram of the coin sizes).
+    ///
+    /// `address` defaults to the active address of the selected workdir.
+    ///
+    /// Requires the suibase-daemon to be running. Result is cached ~10 seconds.
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// let inventory = sbh.gas_inventory(None)?;
+    /// if inventory.merge_suggested {
+    ///    sbh.merge_gas_coins(None, None, false)?;
+    /// }
+    /// ```
+    pub fn gas_inventory(&self, address: Option<String>) -> Result<GasInventory, Error> {
+        self.0.lock().unwrap().gas_inventory(address)
+    }
+
+    /// Merge the SUI coins of an address, with up to `max_coins_per_tx` coins per
+    /// transaction (default 100). Transactions are signed with the workdir keystore.
+    ///
+    /// `address` defaults to the active address of the selected workdir.
+    ///
+    /// Refused on mainnet unless `confirm` is true.
+    pub fn merge_gas_coins(
+        &self,
+        address: Option<String>,
+        max_coins_per_tx: Option<u32>,
+        confirm: bool,
+    ) -> Result<MergeGasCoinsResult, Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .merge_gas_coins(address, max_coins_per_tx, confirm)
+    }
+
+    /// Get where the sui binary of the selected workdir comes from: built from a
+    /// repo set with `set-sui-repo` ("repo-build"), a downloaded precompiled release
+    /// ("downloaded") or built from the default repo ("default-build").
+    ///
+    /// Also has the modification time (RFC3339) and the version of the binary.
+    ///
+    /// Requires the suibase-daemon to be running.
+    pub fn sui_binary_provenance(&self) -> Result<SuiBinaryProvenance, Error> {
+        self.0.lock().unwrap().sui_binary_provenance()
+    }
+
+    /// Build an unsigned transaction calling `package_name::module::function`.
+    ///
+    /// `package_name` resolves to the last published package of the selected workdir
+    /// (see package_object_id). The signer and gas owner is the active address.
+    ///
+    /// Arguments are plain strings: addresses, object IDs and integers are passed
+    /// as-is (e.g. "0x6", "42"), "true"/"false" are booleans and a JSON array
+    /// (e.g. "[1,2,3]") is a vector.
+    ///
+    /// Returns the JSON of the unsafe_moveCall RPC (`txBytes`, `gas` and `inputObjects`),
+    /// which can be signed and executed with the sui client or any SDK.
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// let counter_id = sbh.published_new_objects("demo::Counter::Counter")?[0];
+    /// let args = vec![counter_id.to_string()];
+    /// let tx = sbh.build_move_call("demo", "Counter", "increment", vec![], args)?;
+    /// println!("txBytes: {}", tx["txBytes"]);
+    /// ```
+    #[cfg(feature = "sui-types")]
+    pub fn build_move_call(
+        &self,
+        package_name: &str,
+        module: &str,
+        function: &str,
+        type_args: Vec<String>,
+        args: Vec<String>,
+    ) -> Result<serde_json::Value, Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .build_move_call(package_name, module, function, &type_args, &args)
+    }
+
+    /// Alternative to build_move_call() for string-based API.
+    #[cfg(feature = "sui-types")]
+    pub fn build_move_call_json(
+        &self,
+        package_name: &str,
+        module: &str,
+        function: &str,
+        type_args: Vec<String>,
+        args: Vec<String>,
+    ) -> Result<String, Error> {
+        let tx = self.build_move_call(package_name, module, function, type_args, args)?;
+        Ok(tx.to_string())
+    }
+
+    /// Same as build_move_call(), but also sign the transaction with the active address
+    /// (workdir keystore) and execute it through the workdir proxy.
+    ///
+    /// Returns once the transaction is executed, with its digest and the IDs of the
+    /// created/mutated objects.
+    #[cfg(feature = "sui-types")]
+    pub fn execute_move_call(
+        &self,
+        package_name: &str,
+        module: &str,
+        function: &str,
+        type_args: Vec<String>,
+        args: Vec<String>,
+    ) -> Result<MoveCallResult, Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .execute_move_call(package_name, module, function, &type_args, &args)
+    }
+
+    /// Get the ObjectID of the objects created by a transaction, grouped by their
+    /// full type tag (e.g. "0x2::coin::Coin<0x2::sui::SUI>").
+    ///
+    /// Unlike published_new_object_ids(), works for any transaction (not only the
+    /// publication of a package). The lookup is done through the workdir proxy.
+    ///
+    /// A transaction just executed may not be indexed yet, so a digest not found
+    /// is retried for ~2 seconds before returning Error::TransactionNotFound.
+    ///
+    /// # Example
+    /// ```
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// let created = sbh.tx_created_objects("5xyz...")?;
+    /// for (object_type, ids) in created {
+    ///    println!("{}: {:?}", object_type, ids);
+    /// }
+    /// ```
+    #[cfg(feature = "sui-types")]
+    pub fn tx_created_objects(
+        &self,
+        digest: &str,
+    ) -> Result<HashMap<String, Vec<ObjectID>>, Error> {
+        let created = self.0.lock().unwrap().tx_created_objects(digest)?;
+        Ok(created
+            .into_iter()
+            .map(|(object_type, ids)| (object_type, ids.into_iter().map(ObjectID::from).collect()))
+            .collect())
+    }
+
+    /// Same as tx_created_objects(), without sui-types (see SuibaseObjectId).
+    pub fn tx_created_objects_strings(
+        &self,
+        digest: &str,
+    ) -> Result<HashMap<String, Vec<SuibaseObjectId>>, Error> {
+        self.0.lock().unwrap().tx_created_objects(digest)
+    }
+
+    /// Get if a transaction succeeded, and its error when it failed (e.g. a MoveAbort).
+    ///
+    /// Same lookup (and retry) as tx_created_objects().
+    pub fn tx_status(&self, digest: &str) -> Result<TxStatus, Error> {
+        self.0.lock().unwrap().tx_status(digest)
+    }
+
+    /// Wait up to `timeout` for an object to match `predicate`, and return the object
+    /// (`objectId`, `version`, `digest`, `type` and `content`).
+    ///
+    /// The predicate gets the `content` of the object, as returned by sui_getObject
+    /// (e.g. `content["fields"]["count"]`, u64 fields are strings). The object is polled
+    /// through the workdir proxy, every 100ms at first and backing off up to 2 seconds.
+    ///
+    /// Fails with:
+    ///  * `Error::ObjectWaitTimeout` with the last content observed (for debugging).
+    ///  * `Error::ObjectNotFound` when the object never existed during the wait.
+    ///  * `Error::ObjectDeleted` as soon as the object is found deleted.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// let counter_id = sbh.published_new_objects("demo::Counter::Counter")?[0].to_string();
+    /// sbh.wait_for_object(&counter_id, Duration::from_secs(30), |content| {
+    ///     content["fields"]["count"].as_str() == Some("3")
+    /// })?;
+    /// ```
+    pub fn wait_for_object<F>(
+        &self,
+        object_id: &str,
+        timeout: Duration,
+        predicate: F,
+    ) -> Result<serde_json::Value, Error>
+    where
+        F: Fn(&serde_json::Value) -> bool,
+    {
+        // The lock is not held while polling.
+        let rpc_url = self.0.lock().unwrap().client_rpc_url()?;
+        object_wait::wait_for_object(&rpc_url, object_id, timeout, predicate)
+    }
+
+    /// Same as wait_for_object(), until the version of the object is at least
+    /// `min_version` (e.g. the version after an expected transaction).
+    pub fn wait_for_object_version(
+        &self,
+        object_id: &str,
+        min_version: u64,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, Error> {
+        let rpc_url = self.0.lock().unwrap().client_rpc_url()?;
+        object_wait::wait_for_object_version(&rpc_url, object_id, min_version, timeout)
+    }
+
+    /// Alternative to wait_for_object_version() for string-based API.
+    pub fn wait_for_object_version_json(
+        &self,
+        object_id: &str,
+        min_version: u64,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        let object = self.wait_for_object_version(object_id, min_version, timeout)?;
+        Ok(object.to_string())
+    }
+
+    /// Write the package id of the last publication of each package into a file.
+    ///
+    /// Intended to be called after every publication (e.g. on localnet), so the app
+    /// loads the new ids instead of having them copy-pasted in its code or Move.toml.
+    ///
+    /// The format is one of:
+    ///  * `EnvFormat::Dotenv`: `MY_PACKAGE_PACKAGE_ID=0x...`
+    ///  * `EnvFormat::Json`: can be loaded back with read_env_file().
+    ///  * `EnvFormat::Rust`: `pub const MY_PACKAGE_PACKAGE_ID: &str = "0x...";` (for an include!)
+    ///
+    /// Names are converted to identifiers (upper case, '_' for invalid characters).
+    ///
+    /// The file is replaced atomically. It is left untouched on error (e.g. one
+    /// of the packages was never published on the selected workdir).
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    /// use suibase::{EnvFormat, Helper};
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// sbh.generate_env_file(&["demo"], Path::new(".env"), EnvFormat::Dotenv)?;
+    /// ```
+    pub fn generate_env_file(
+        &self,
+        package_names: &[&str],
+        path: &Path,
+        format: EnvFormat,
+    ) -> Result<(), Error> {
+        self.generate_env_file_with_objects(package_names, &[], path, format)
+    }
+
+    /// Same as generate_env_file(), with also the ids of the objects created when the
+    /// packages were published (see published_new_object_ids() for the object_type format).
+    ///
+    /// Each object type is written as `PACKAGE_MODULE_TYPE_IDS` (comma separated with Dotenv).
+    pub fn generate_env_file_with_objects(
+        &self,
+        package_names: &[&str],
+        object_types: &[&str],
+        path: &Path,
+        format: EnvFormat,
+    ) -> Result<(), Error> {
+        let ids = self
+            .0
+            .lock()
+            .unwrap()
+            .published_ids(package_names, object_types)?;
+        env_file::write_env_file(&ids, path, format)
+    }
+
+    /// Alternative to generate_env_file_with_objects() for string-based API.
+    pub fn generate_env_file_strings(
+        &self,
+        package_names: Vec<String>,
+        object_types: Vec<String>,
+        path: &str,
+        format: EnvFormat,
+    ) -> Result<(), Error> {
+        let package_names: Vec<&str> = package_names.iter().map(|name| name.as_str()).collect();
+        let object_types: Vec<&str> = object_types.iter().map(|name| name.as_str()).collect();
+        self.generate_env_file_with_objects(&package_names, &object_types, Path::new(path), format)
+    }
+
+    /// Load a file generated with `EnvFormat::Json`.
+    ///
+    /// Does not require suibase (e.g. the file can be shipped with the app).
+    pub fn read_env_file(&self, path: &Path) -> Result<PublishedIds, Error> {
+        env_file::read_env_file(path)
+    }
+
+    /// Alternative to read_env_file() for string-based API.
+    pub fn read_env_file_strings(&self, path: &str) -> Result<PublishedIds, Error> {
+        self.read_env_file(Path::new(path))
+    }
+
+    /// Write into a single file the setup of the selected workdir, to reproduce it
+    /// elsewhere with import_profile() (e.g. a new machine or a teammate).
+    ///
+    /// The bundle has the user suibase.yaml, the published-data and a manifest of the
+    /// environment (suibase and sui versions, packages, addresses).
+    ///
+    /// No private key is included (see export_profile_with_keys()).
+    ///
+    /// Requires the suibase-daemon to be running.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    /// use suibase::Helper;
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// sbh.export_profile(Path::new("localnet-profile.json"))?;
+    /// ```
+    pub fn export_profile(&self, path: &Path) -> Result<ProfileExport, Error> {
+        self.export_profile_with_keys(path, &[])
+    }
+
+    /// Same as export_profile(), with also the keystore entries of `keys` (aliases or
+    /// addresses). These are private keys: the bundle must then be kept secret.
+    pub fn export_profile_with_keys(
+        &self,
+        path: &Path,
+        keys: &[&str],
+    ) -> Result<ProfileExport, Error> {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        self.0.lock().unwrap().export_profile(path, &keys)
+    }
+
+    /// Alternative to export_profile_with_keys() for string-based API.
+    pub fn export_profile_strings(
+        &self,
+        path: &str,
+        keys: Vec<String>,
+    ) -> Result<ProfileExport, Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .export_profile(Path::new(path), &keys)
+    }
+
+    /// Apply a bundle of export_profile() into the selected workdir.
+    ///
+    /// Nothing is written when a conflict is found (a link with the same alias but a
+    /// different definition, or a package already published with another id), unless
+    /// `options.force` is set. Use `options.dry_run` to get the report first.
+    ///
+    /// The private keys of the bundle are ignored unless `options.import_keys` is set,
+    /// and never replace a key with the same alias.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    /// use suibase::{Helper, ProfileImportOptions};
+    /// let sbh = Helper::new();
+    /// sbh.select_workdir("localnet")?;
+    /// let options = ProfileImportOptions { dry_run: true, ..Default::default() };
+    /// let report = sbh.import_profile(Path::new("localnet-profile.json"), options)?;
+    /// for conflict in report.conflicts {
+    ///     println!("{} {}: {}", conflict.kind, conflict.name, conflict.detail);
+    /// }
+    /// ```
+    pub fn import_profile(
+        &self,
+        path: &Path,
+        options: ProfileImportOptions,
+    ) -> Result<ProfileImport, Error> {
+        self.0.lock().unwrap().import_profile(path, &options)
+    }
+
+    /// Alternative to import_profile() for string-based API.
+    pub fn import_profile_strings(
+        &self,
+        path: &str,
+        options: ProfileImportOptions,
+    ) -> Result<ProfileImport, Error> {
+        self.import_profile(Path::new(path), options)
+    }
 }
@@ -0,0 +1,221 @@
+// Move calls of a package published with suibase.
+//
+// The transaction is built by the Sui RPC (unsafe_moveCall) of the workdir proxy,
+// signed locally with the workdir keystore and then submitted to the same proxy.
+
+use std::path::PathBuf;
+
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use shared_crypto::intent::Intent;
+use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, Keystore};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::transaction::TransactionData;
+
+use crate::error::Error;
+use crate::suibase_daemon_api::rpc_call;
+
+// Paid by the signer (MIST). Unused gas is refunded.
+pub const MOVE_CALL_GAS_BUDGET: u64 = 100_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveCallResult {
+    pub digest: String,
+    pub created_object_ids: Vec<String>,
+    pub mutated_object_ids: Vec<String>, // Includes the gas coin.
+}
+
+// Convert a plain string argument to the JSON expected by the Sui RPC.
+//
+// The RPC converts each value according to the function signature, so addresses,
+// object IDs and integers are all passed as strings (a u64 may not fit in a JSON
+// number). Only booleans and vectors (JSON arrays) need a conversion.
+pub(crate) fn encode_arg(arg: &str) -> JsonValue {
+    let trimmed = arg.trim();
+    match trimmed {
+        "true" => JsonValue::Bool(true),
+        "false" => JsonValue::Bool(false),
+        _ if trimmed.starts_with('[') => match serde_json::from_str::<JsonValue>(trimmed) {
+            Ok(array @ JsonValue::Array(_)) => array,
+            _ => JsonValue::String(arg.to_string()),
+        },
+        _ if trimmed.starts_with("0x") || trimmed.chars().all(|c| c.is_ascii_digit()) => {
+            JsonValue::String(trimmed.to_string())
+        }
+        _ => JsonValue::String(arg.to_string()),
+    }
+}
+
+pub(crate) fn move_call_params(
+    signer: &SuiAddress,
+    package_id: &ObjectID,
+    module: &str,
+    function: &str,
+    type_args: &[String],
+    args: &[String],
+) -> JsonValue {
+    let args: Vec<JsonValue> = args.iter().map(|arg| encode_arg(arg)).collect();
+    serde_json::json!([
+        signer.to_string(),
+        package_id.to_string(),
+        module,
+        function,
+        type_args,
+        args,
+        JsonValue::Null, // The node picks a gas coin of the signer.
+        MOVE_CALL_GAS_BUDGET.to_string(),
+    ])
+}
+
+// Unsigned transaction as returned by unsafe_moveCall ("txBytes", "gas", "inputObjects").
+pub(crate) fn build_move_call(
+    rpc_url: &str,
+    signer: &SuiAddress,
+    package_id: &ObjectID,
+    module: &str,
+    function: &str,
+    type_args: &[String],
+    args: &[String],
+) -> Result<JsonValue, Error> {
+    let params = move_call_params(signer, package_id, module, function, type_args, args);
+    rpc_call(rpc_url, "unsafe_moveCall", params)
+}
+
+pub(crate) fn sign_and_execute(
+    rpc_url: &str,
+    keystore_pathname: &str,
+    signer: &SuiAddress,
+    unsigned_tx: &JsonValue,
+) -> Result<MoveCallResult, Error> {
+    let sign_error = |msg: String| Error::TransactionSignError {
+        address: signer.to_string(),
+        msg,
+    };
+    let tx_bytes = unsigned_tx["txBytes"]
+        .as_str()
+        .ok_or_else(|| sign_error("missing txBytes".to_string()))?;
+    let raw_tx = base64::engine::general_purpose::STANDARD
+        .decode(tx_bytes)
+        .map_err(|e| sign_error(e.to_string()))?;
+    let tx_data: TransactionData =
+        bcs::from_bytes(&raw_tx).map_err(|e| sign_error(e.to_string()))?;
+
+    let keystore = FileBasedKeystore::new(&PathBuf::from(keystore_pathname))
+        .map(Keystore::File)
+        .map_err(|e| sign_error(e.to_string()))?;
+    let signature = keystore
+        .sign_secure(signer, &tx_data, Intent::sui_transaction())
+        .map_err(|e| sign_error(e.to_string()))?;
+
+    let options = serde_json::json!({ "showEffects": true, "showObjectChanges": true });
+    let result = rpc_call(
+        rpc_url,
+        "sui_executeTransactionBlock",
+        serde_json::json!([tx_bytes, [signature], options, "WaitForLocalExecution"]),
+    )?;
+    parse_execute_result(&result)
+}
+
+pub(crate) fn parse_execute_result(result: &JsonValue) -> Result<MoveCallResult, Error> {
+    let digest = result["digest"].as_str().unwrap_or_default().to_string();
+    if digest.is_empty() {
+        return Err(Error::RpcRequestError {
+            method: "sui_executeTransactionBlock".to_string(),
+            msg: format!("missing digest in {}", result),
+        });
+    }
+
+    let status = &result["effects"]["status"];
+    if let Some(status_str) = status["status"].as_str() {
+        if status_str != "success" {
+            return Err(Error::TransactionFailed {
+                digest,
+                msg: status["error"].as_str().unwrap_or(status_str).to_string(),
+            });
+        }
+    }
+
+    let object_ids = |change_type: &str| -> Vec<String> {
+        result["objectChanges"]
+            .as_array()
+            .map(|changes| {
+                changes
+                    .iter()
+                    .filter(|change| change["type"].as_str() == Some(change_type))
+                    .filter_map(|change| change["objectId"].as_str())
+                    .map(|id| id.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Ok(MoveCallResult {
+        created_object_ids: object_ids("created"),
+        mutated_object_ids: object_ids("mutated"),
+        digest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_encode_arg() {
+        assert_eq!(encode_arg("0x2"), JsonValue::String("0x2".to_string()));
+        assert_eq!(
+            encode_arg("18446744073709551615"),
+            JsonValue::String("18446744073709551615".to_string())
+        );
+        assert_eq!(encode_arg(" true"), JsonValue::Bool(true));
+        assert_eq!(encode_arg("[1, 2]"), serde_json::json!([1, 2]));
+        assert_eq!(
+            encode_arg("[not json"),
+            JsonValue::String("[not json".to_string())
+        );
+        assert_eq!(
+            encode_arg(" hello "),
+            JsonValue::String(" hello ".to_string())
+        );
+
+        let signer = SuiAddress::from_str("0x7").unwrap();
+        let package_id = ObjectID::from_hex_literal("0x9").unwrap();
+        let params = move_call_params(
+            &signer,
+            &package_id,
+            "Counter",
+            "increment",
+            &[],
+            &["0x5".to_string(), "false".to_string()],
+        );
+        assert_eq!(params[2], "Counter");
+        assert_eq!(params[5], serde_json::json!(["0x5", false]));
+        assert_eq!(params[7], MOVE_CALL_GAS_BUDGET.to_string());
+    }
+
+    #[test]
+    fn test_parse_execute_result() {
+        let result = serde_json::json!({
+            "digest": "5xyz",
+            "effects": { "status": { "status": "success" } },
+            "objectChanges": [
+                { "type": "mutated", "objectId": "0x5" },
+                { "type": "created", "objectId": "0x6" },
+                { "type": "mutated", "objectId": "0x7" }
+            ]
+        });
+        let call = parse_execute_result(&result).unwrap();
+        assert_eq!(call.digest, "5xyz");
+        assert_eq!(call.created_object_ids, vec!["0x6"]);
+        assert_eq!(call.mutated_object_ids, vec!["0x5", "0x7"]);
+
+        let result = serde_json::json!({
+            "digest": "5xyz",
+            "effects": { "status": { "status": "failure", "error": "MoveAbort" } }
+        });
+        assert!(matches!(
+            parse_execute_result(&result),
+            Err(Error::TransactionFailed { msg, .. }) if msg == "MoveAbort"
+        ));
+    }
+}
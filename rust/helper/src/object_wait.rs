@@ -0,0 +1,249 @@
+// Wait for an object to reach an expected state (e.g. a field of a shared object
+// updated by another process), instead of sleeping an arbitrary time.
+//
+// Done with sui_getObject (showContent) on the workdir proxy. The object is polled
+// every OBJECT_POLL_MIN_INTERVAL at first, doubling up to OBJECT_POLL_MAX_INTERVAL.
+//
+// A deleted object fails right away (it will never match). An object not found is
+// retried until the timeout (it may not be created or indexed yet).
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+use crate::sui_ids::SuibaseObjectId;
+use crate::suibase_daemon_api::rpc_call;
+
+const OBJECT_POLL_MIN_INTERVAL: Duration = Duration::from_millis(100);
+const OBJECT_POLL_MAX_INTERVAL: Duration = Duration::from_secs(2);
+
+// Result of one sui_getObject.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ObjectLookup {
+    Found(JsonValue), // The "data" (objectId, version, digest, type, content...).
+    NotFound,
+    Deleted,
+}
+
+pub(crate) fn parse_object_lookup(
+    object_id: &str,
+    result: &JsonValue,
+) -> Result<ObjectLookup, Error> {
+    if result["data"].is_object() {
+        return Ok(ObjectLookup::Found(result["data"].clone()));
+    }
+    match result["error"]["code"].as_str() {
+        Some("notExists") => Ok(ObjectLookup::NotFound),
+        Some("deleted") => Ok(ObjectLookup::Deleted),
+        _ => Err(Error::RpcRequestError {
+            method: "sui_getObject".to_string(),
+            msg: format!("unexpected response for {}: {}", object_id, result),
+        }),
+    }
+}
+
+fn get_object(rpc_url: &str, object_id: &str) -> Result<ObjectLookup, Error> {
+    let params = serde_json::json!([object_id, { "showContent": true, "showType": true }]);
+    let result = rpc_call(rpc_url, "sui_getObject", params)?;
+    parse_object_lookup(object_id, &result)
+}
+
+// The version of an object JSON (sent as a string by the RPC).
+pub(crate) fn object_version(object: &JsonValue) -> Option<u64> {
+    match &object["version"] {
+        JsonValue::String(version) => version.parse().ok(),
+        version => version.as_u64(),
+    }
+}
+
+// Repeat lookup() until predicate() is true on the object, for up to timeout.
+pub(crate) fn poll_object<L, F>(
+    object_id: &str,
+    timeout: Duration,
+    mut lookup: L,
+    predicate: F,
+) -> Result<JsonValue, Error>
+where
+    L: FnMut() -> Result<ObjectLookup, Error>,
+    F: Fn(&JsonValue) -> bool,
+{
+    let deadline = Instant::now() + timeout;
+    let mut interval = OBJECT_POLL_MIN_INTERVAL;
+    let mut last_object: Option<JsonValue> = None;
+    loop {
+        match lookup()? {
+            ObjectLookup::Found(object) => {
+                if predicate(&object) {
+                    return Ok(object);
+                }
+                last_object = Some(object);
+            }
+            ObjectLookup::Deleted => {
+                return Err(Error::ObjectDeleted {
+                    id: object_id.to_string(),
+                })
+            }
+            ObjectLookup::NotFound => {}
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return match last_object {
+                Some(object) => Err(Error::ObjectWaitTimeout {
+                    id: object_id.to_string(),
+                    last_content: object["content"].to_string(),
+                }),
+                None => Err(Error::ObjectNotFound {
+                    id: object_id.to_string(),
+                }),
+            };
+        }
+        std::thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(OBJECT_POLL_MAX_INTERVAL);
+    }
+}
+
+// The predicate gets the "content" of the object (e.g. content["fields"]["count"]).
+pub(crate) fn wait_for_object<F>(
+    rpc_url: &str,
+    object_id: &str,
+    timeout: Duration,
+    predicate: F,
+) -> Result<JsonValue, Error>
+where
+    F: Fn(&JsonValue) -> bool,
+{
+    object_id.parse::<SuibaseObjectId>()?;
+    poll_object(
+        object_id,
+        timeout,
+        || get_object(rpc_url, object_id),
+        |object| predicate(&object["content"]),
+    )
+}
+
+pub(crate) fn wait_for_object_version(
+    rpc_url: &str,
+    object_id: &str,
+    min_version: u64,
+    timeout: Duration,
+) -> Result<JsonValue, Error> {
+    object_id.parse::<SuibaseObjectId>()?;
+    poll_object(
+        object_id,
+        timeout,
+        || get_object(rpc_url, object_id),
+        |object| object_version(object).is_some_and(|version| version >= min_version),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn counter(version: u64, count: u64) -> ObjectLookup {
+        ObjectLookup::Found(json!({
+            "objectId": "0x5",
+            "version": version.to_string(),
+            "content": { "dataType": "moveObject", "fields": { "count": count.to_string() } },
+        }))
+    }
+
+    fn count_at_least(min: u64) -> impl Fn(&JsonValue) -> bool {
+        move |object| {
+            object["content"]["fields"]["count"]
+                .as_str()
+                .and_then(|count| count.parse::<u64>().ok())
+                .is_some_and(|count| count >= min)
+        }
+    }
+
+    #[test]
+    fn test_parse_object_lookup() {
+        let found = json!({ "data": { "objectId": "0x5", "version": "7" } });
+        let lookup = parse_object_lookup("0x5", &found).unwrap();
+        assert!(
+            matches!(&lookup, ObjectLookup::Found(object) if object_version(object) == Some(7))
+        );
+
+        let not_found = json!({ "error": { "code": "notExists", "object_id": "0x5" } });
+        assert_eq!(
+            parse_object_lookup("0x5", &not_found).unwrap(),
+            ObjectLookup::NotFound
+        );
+        let deleted = json!({ "error": { "code": "deleted", "object_id": "0x5", "version": 9 } });
+        assert_eq!(
+            parse_object_lookup("0x5", &deleted).unwrap(),
+            ObjectLookup::Deleted
+        );
+        assert!(matches!(
+            parse_object_lookup("0x5", &json!({})),
+            Err(Error::RpcRequestError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_poll_object() {
+        // Not created yet, then incremented by another process.
+        let mut lookups = vec![
+            ObjectLookup::NotFound,
+            counter(1, 0),
+            counter(2, 1),
+            counter(4, 3),
+        ]
+        .into_iter();
+        let start = Instant::now();
+        let object = poll_object(
+            "0x5",
+            Duration::from_secs(10),
+            || Ok(lookups.next().unwrap()),
+            count_at_least(3),
+        )
+        .unwrap();
+        assert_eq!(object_version(&object), Some(4));
+        // Backing off (100 + 200 + 400 ms), but not to the maximum yet.
+        assert!(start.elapsed() >= Duration::from_millis(700));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The last content is in the timeout error.
+        let res = poll_object(
+            "0x5",
+            Duration::from_millis(250),
+            || Ok(counter(2, 1)),
+            count_at_least(3),
+        );
+        match res {
+            Err(Error::ObjectWaitTimeout { id, last_content }) => {
+                assert_eq!(id, "0x5");
+                assert!(last_content.contains("\"count\":\"1\""), "{}", last_content);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let res = poll_object(
+            "0x5",
+            Duration::from_millis(250),
+            || Ok(ObjectLookup::NotFound),
+            count_at_least(3),
+        );
+        assert!(matches!(res, Err(Error::ObjectNotFound { .. })));
+
+        // A deleted object is reported without waiting for the timeout.
+        let start = Instant::now();
+        let res = poll_object(
+            "0x5",
+            Duration::from_secs(10),
+            || Ok(ObjectLookup::Deleted),
+            count_at_least(3),
+        );
+        assert!(matches!(res, Err(Error::ObjectDeleted { .. })));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert!(matches!(
+            wait_for_object("http://127.0.0.1:1", "xyz", Duration::ZERO, |_| true),
+            Err(Error::ObjectIdInvalid { .. })
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+// Build metadata of a published package (see Helper::package_build_info).
+//
+// Read from the publication directory (published-data/<package>/most-recent):
+//
+//   build/<package>/BuildInfo.yaml          Written by the Move compiler (flags, source digest).
+//   build/<package>/bytecode_modules/*.mv   The compiled modules (dependencies in a sub-directory).
+//   build-metadata.json                     Written by the publish flow (sui version, source
+//                                           path and git commit). Missing for older records.
+//   publish-output.json                     The "sui client publish --json" output.
+//
+// The name of the publication directory is the publish time (Unix epoch in milliseconds).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+
+use crate::error::Error;
+
+const BUILD_INFO_FILE: &str = "BuildInfo.yaml";
+const BUILD_METADATA_FILE: &str = "build-metadata.json";
+const PUBLISH_OUTPUT_FILE: &str = "publish-output.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageBuildInfo {
+    pub package_name: String,
+    pub published_path: String, // The publication directory (symlinks resolved).
+    pub build_timestamp_ms: Option<u64>,
+    pub compiler_version: Option<String>, // The sui binary used (e.g. "sui 1.22.0-abc1234").
+    pub source_path: Option<String>,      // Directory of the Move.toml.
+    pub git_commit: Option<String>,       // HEAD of the source, when in a git repo.
+    pub source_digest: Option<String>,    // Computed by the Move compiler.
+    pub build_flags: HashMap<String, String>, // e.g. "dev_mode" -> "false"
+    pub dependencies: Vec<String>,
+}
+
+fn read_to_string(path: &Path) -> Result<Option<String>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::PackageBuildReadError {
+            path: path.to_string_lossy().to_string(),
+            msg: e.to_string(),
+        }),
+    }
+}
+
+fn parse_error(path: &Path, msg: impl ToString) -> Error {
+    Error::PackageBuildReadError {
+        path: path.to_string_lossy().to_string(),
+        msg: msg.to_string(),
+    }
+}
+
+// The build output of the package. The compiler names it after the package, but any
+// other with a BuildInfo.yaml is accepted (e.g. Move.toml name with another case).
+fn build_dir(package_name: &str, published_dir: &Path) -> Option<PathBuf> {
+    let build = published_dir.join("build");
+    let expected = build.join(package_name);
+    if expected.join(BUILD_INFO_FILE).is_file() {
+        return Some(expected);
+    }
+    let mut found: Vec<PathBuf> = std::fs::read_dir(&build)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(BUILD_INFO_FILE).is_file())
+        .collect();
+    found.sort();
+    found.into_iter().next()
+}
+
+fn yaml_scalar(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::Bool(b) => Some(b.to_string()),
+        YamlValue::Number(n) => Some(n.to_string()),
+        YamlValue::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn non_empty(value: &JsonValue) -> Option<String> {
+    value
+        .as_str()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+pub(crate) fn read_build_info(
+    package_name: &str,
+    published_dir: &Path,
+) -> Result<PackageBuildInfo, Error> {
+    let mut info = PackageBuildInfo {
+        package_name: package_name.to_string(),
+        published_path: published_dir.to_string_lossy().to_string(),
+        build_timestamp_ms: published_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u64>().ok()),
+        compiler_version: None,
+        source_path: None,
+        git_commit: None,
+        source_digest: None,
+        build_flags: HashMap::new(),
+        dependencies: Vec::new(),
+    };
+
+    if let Some(build_dir) = build_dir(package_name, published_dir) {
+        let path = build_dir.join(BUILD_INFO_FILE);
+        if let Some(contents) = read_to_string(&path)? {
+            let yaml: YamlValue =
+                serde_yaml::from_str(&contents).map_err(|e| parse_error(&path, e))?;
+            let compiled = &yaml["compiled_package_info"];
+            info.source_digest = yaml_scalar(&compiled["source_digest"]);
+            if let Some(flags) = compiled["build_flags"].as_mapping() {
+                for (name, value) in flags {
+                    if let (Some(name), Some(value)) = (name.as_str(), yaml_scalar(value)) {
+                        info.build_flags.insert(name.to_string(), value);
+                    }
+                }
+            }
+            // The Move.lock is next to the Move.toml.
+            info.source_path = info
+                .build_flags
+                .get("lock_file")
+                .and_then(|lock_file| Path::new(lock_file).parent())
+                .map(|dir| dir.to_string_lossy().to_string());
+            if let Some(dependencies) = yaml["dependencies"].as_sequence() {
+                info.dependencies = dependencies.iter().filter_map(yaml_scalar).collect();
+            }
+        }
+    }
+
+    let path = published_dir.join(BUILD_METADATA_FILE);
+    if let Some(contents) = read_to_string(&path)? {
+        let json: JsonValue = serde_json::from_str(&contents).map_err(|e| parse_error(&path, e))?;
+        info.compiler_version = non_empty(&json["sui_version"]);
+        info.git_commit = non_empty(&json["git_commit"]);
+        if let Some(source_path) = non_empty(&json["source_path"]) {
+            info.source_path = Some(source_path);
+        }
+    }
+
+    Ok(info)
+}
+
+// Module names, sorted. From the compiled modules, or the publish output when the
+// build directory is gone.
+pub(crate) fn read_modules(package_name: &str, published_dir: &Path) -> Result<Vec<String>, Error> {
+    if let Some(build_dir) = build_dir(package_name, published_dir) {
+        let bytecode_dir = build_dir.join("bytecode_modules");
+        if bytecode_dir.is_dir() {
+            let entries =
+                std::fs::read_dir(&bytecode_dir).map_err(|e| parse_error(&bytecode_dir, e))?;
+            let mut modules: Vec<String> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "mv"))
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+                .collect();
+            modules.sort();
+            return Ok(modules);
+        }
+    }
+
+    let path = published_dir.join(PUBLISH_OUTPUT_FILE);
+    if let Some(contents) = read_to_string(&path)? {
+        let json: JsonValue = serde_json::from_str(&contents).map_err(|e| parse_error(&path, e))?;
+        let published = json["objectChanges"]
+            .as_array()
+            .and_then(|changes| changes.iter().find(|change| change["type"] == "published"));
+        if let Some(modules) = published.and_then(|change| change["modules"].as_array()) {
+            let mut modules: Vec<String> = modules
+                .iter()
+                .filter_map(|module| module.as_str().map(|s| s.to_string()))
+                .collect();
+            modules.sort();
+            return Ok(modules);
+        }
+    }
+
+    Err(Error::PackageBuildNotFound {
+        package_name: package_name.to_string(),
+        path: published_dir.to_string_lossy().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A publication from before build-metadata.json, with its build directory removed.
+    #[test]
+    fn test_older_publication() {
+        let tmp = tempfile::tempdir().unwrap();
+        let published_dir = tmp.path().join("1699671472695");
+        fs::create_dir_all(&published_dir).unwrap();
+
+        let info = read_build_info("demo", &published_dir).unwrap();
+        assert_eq!(info.build_timestamp_ms, Some(1699671472695));
+        assert_eq!(info.compiler_version, None);
+        assert_eq!(info.source_path, None);
+        assert_eq!(info.git_commit, None);
+        assert!(info.build_flags.is_empty());
+
+        assert!(matches!(
+            read_modules("demo", &published_dir),
+            Err(Error::PackageBuildNotFound { .. })
+        ));
+
+        // The modules are still in the publish output.
+        fs::write(
+            published_dir.join(PUBLISH_OUTPUT_FILE),
+            r#"{"objectChanges":[
+                {"type":"mutated"},
+                {"type":"published","modules":["tools","Counter"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_modules("demo", &published_dir).unwrap(),
+            vec!["Counter", "tools"]
+        );
+
+        fs::write(published_dir.join(BUILD_METADATA_FILE), "{").unwrap();
+        assert!(matches!(
+            read_build_info("demo", &published_dir),
+            Err(Error::PackageBuildReadError { .. })
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+// Object ID and address of Sui, without a dependency on sui-types.
+//
+// Returned by the convenience methods of the Helper (e.g. package_id()), so an app (or
+// the UniFFI bindings) does not have to match the sui-types version of this crate.
+//
+// Both are 32 bytes, displayed as "0x" followed by 64 lowercase hex digits (same as
+// sui-types). Parsing accepts:
+//   - "0x" followed by 1 to 64 hex digits, left padded with zeros (e.g. "0x2").
+//   - exactly 64 hex digits, without the "0x".
+//
+// The conversions to/from sui_types::base_types::{ObjectID, SuiAddress} are behind the
+// "sui-types" feature (enabled by default).
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+// Number of bytes of an object ID or address.
+pub const SUI_ID_LENGTH: usize = 32;
+
+fn parse_hex_id(s: &str) -> Option<[u8; SUI_ID_LENGTH]> {
+    let (digits, prefixed) = match s.strip_prefix("0x") {
+        Some(digits) => (digits, true),
+        None => (s, false),
+    };
+    let max_digits = SUI_ID_LENGTH * 2;
+    if digits.is_empty()
+        || digits.len() > max_digits
+        || (!prefixed && digits.len() != max_digits)
+        || !digits.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let padded = format!("{:0>width$}", digits, width = max_digits);
+    let mut bytes = [0u8; SUI_ID_LENGTH];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn fmt_hex_id(bytes: &[u8; SUI_ID_LENGTH], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("0x")?;
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SuibaseObjectId([u8; SUI_ID_LENGTH]);
+
+impl SuibaseObjectId {
+    pub const fn new(bytes: [u8; SUI_ID_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SUI_ID_LENGTH] {
+        &self.0
+    }
+}
+
+impl fmt::Display for SuibaseObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex_id(&self.0, f)
+    }
+}
+
+impl fmt::Debug for SuibaseObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SuibaseObjectId({})", self)
+    }
+}
+
+impl FromStr for SuibaseObjectId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hex_id(s)
+            .map(Self)
+            .ok_or_else(|| Error::ObjectIdInvalid { id: s.to_string() })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SuibaseAddress([u8; SUI_ID_LENGTH]);
+
+impl SuibaseAddress {
+    pub const fn new(bytes: [u8; SUI_ID_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SUI_ID_LENGTH] {
+        &self.0
+    }
+}
+
+impl fmt::Display for SuibaseAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex_id(&self.0, f)
+    }
+}
+
+impl fmt::Debug for SuibaseAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SuibaseAddress({})", self)
+    }
+}
+
+impl FromStr for SuibaseAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hex_id(s)
+            .map(Self)
+            .ok_or_else(|| Error::AddressInvalid {
+                address: s.to_string(),
+            })
+    }
+}
+
+#[cfg(feature = "sui-types")]
+mod sui_types_conversions {
+    use super::{SuibaseAddress, SuibaseObjectId};
+    use sui_types::base_types::{ObjectID, SuiAddress};
+
+    impl From<ObjectID> for SuibaseObjectId {
+        fn from(id: ObjectID) -> Self {
+            Self(id.into_bytes())
+        }
+    }
+
+    impl From<SuibaseObjectId> for ObjectID {
+        fn from(id: SuibaseObjectId) -> Self {
+            ObjectID::new(id.0)
+        }
+    }
+
+    impl From<SuiAddress> for SuibaseAddress {
+        fn from(address: SuiAddress) -> Self {
+            Self(address.to_inner())
+        }
+    }
+
+    impl From<SuibaseAddress> for SuiAddress {
+        fn from(address: SuibaseAddress) -> Self {
+            SuiAddress::from(ObjectID::new(address.0))
+        }
+    }
+}
+
+#[cfg(feature = "build-with-uniffi")]
+mod uniffi_conversions {
+    use super::{SuibaseAddress, SuibaseObjectId};
+
+    // Strings for the bindings (see the [Custom] typedef in suibase.udl).
+    impl crate::UniffiCustomTypeConverter for SuibaseObjectId {
+        type Builtin = String;
+
+        fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+            Ok(val.parse()?)
+        }
+
+        fn from_custom(obj: Self) -> Self::Builtin {
+            obj.to_string()
+        }
+    }
+
+    impl crate::UniffiCustomTypeConverter for SuibaseAddress {
+        type Builtin = String;
+
+        fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+            Ok(val.parse()?)
+        }
+
+        fn from_custom(obj: Self) -> Self::Builtin {
+            obj.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID_HEX: &str = "0x6f36c2a1e6c8a51a1c3b4e2b0a9f8e7d6c5b4a39281706f5e4d3c2b1a0998feb";
+
+    #[test]
+    fn test_sui_ids_round_trip() {
+        let id = SuibaseObjectId::from_str(ID_HEX).unwrap();
+        assert_eq!(id.to_string(), ID_HEX);
+        assert_eq!(id.as_bytes()[0], 0x6f);
+        assert_eq!(id.as_bytes()[31], 0xeb);
+
+        // Without the 0x, and in uppercase.
+        let address = SuibaseAddress::from_str(&ID_HEX[2..].to_uppercase()).unwrap();
+        assert_eq!(address.to_string(), ID_HEX);
+        assert_eq!(address.as_bytes(), id.as_bytes());
+
+        // Short literal, padded.
+        let id = SuibaseObjectId::from_str("0x2").unwrap();
+        assert_eq!(id.to_string(), format!("0x{:0>64}", "2"));
+        assert_eq!(SuibaseObjectId::new(*id.as_bytes()), id);
+        assert_eq!(format!("{:?}", id), format!("SuibaseObjectId({})", id));
+    }
+
+    #[test]
+    fn test_sui_ids_invalid() {
+        let too_long = format!("{}0", ID_HEX);
+        for bad in ["", "0x", "2", "0xg2", &ID_HEX[3..], too_long.as_str(), " 0x2"] {
+            assert!(
+                matches!(
+                    SuibaseObjectId::from_str(bad),
+                    Err(Error::ObjectIdInvalid { .. })
+                ),
+                "{:?}",
+                bad
+            );
+            assert!(
+                matches!(
+                    SuibaseAddress::from_str(bad),
+                    Err(Error::AddressInvalid { .. })
+                ),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[cfg(feature = "sui-types")]
+    #[test]
+    fn test_sui_ids_sui_types_conversions() {
+        use sui_types::base_types::{ObjectID, SuiAddress};
+
+        let object_id = ObjectID::from_hex_literal(ID_HEX).unwrap();
+        let id = SuibaseObjectId::from(object_id);
+        assert_eq!(id.to_string(), object_id.to_string());
+        assert_eq!(ObjectID::from(id), object_id);
+
+        let sui_address = SuiAddress::from_str(ID_HEX).unwrap();
+        let address = SuibaseAddress::from(sui_address);
+        assert_eq!(address.to_string(), sui_address.to_string());
+        assert_eq!(SuiAddress::from(address), sui_address);
+    }
+}
@@ -0,0 +1,557 @@
+// Minimal JSON-RPC client to the suibase-daemon (localhost:44399 by default).
+//
+// Intentionally done with std::net (blocking) to keep the helper free of any
+// async runtime dependency. Also used for the Sui RPC of the workdir proxy (see
+// rpc_call).
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+use crate::suibase_root::SuibaseRoot;
+
+// Default port. The daemon may use another one (see SuibaseRoot::active_api_port).
+pub(crate) const DAEMON_PORT: u16 = 44399;
+
+// Merging coins can take a few transactions, so be generous.
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Sui RPC of the workdir proxy (e.g. a transaction waiting for its local execution).
+const RPC_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasCoinBucket {
+    pub label: String, // Range in SUI (e.g. "0.01-0.1")
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasInventory {
+    pub address: String,
+    pub coin_count: u64,
+    pub total_balance: u64, // MIST
+    pub largest_coin_id: Option<String>,
+    pub largest_coin_balance: u64, // MIST
+    pub histogram: Vec<GasCoinBucket>,
+    pub merge_suggested: bool,
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeGasCoinsResult {
+    pub address: String,
+    pub coin_count_before: u64,
+    pub coin_count_after: u64,
+    pub tx_digests: Vec<String>,
+    pub info: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiBinaryProvenance {
+    pub origin: String,                // "repo-build", "downloaded" or "default-build"
+    pub sui_repo_path: Option<String>, // Set only by set-sui-repo.
+    pub binary_mtime: Option<String>,  // RFC3339
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileExport {
+    pub packages: Vec<String>, // Names of the published packages in the bundle.
+    pub key_count: u64,        // Private keys in the bundle (none unless requested).
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProfileImportOptions {
+    pub dry_run: bool,     // Report only, nothing is written.
+    pub force: bool,       // Apply even with conflicts (except for the keys).
+    pub import_keys: bool, // Append the private keys of the bundle to the keystore.
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileConflict {
+    pub kind: String, // "link", "package" or "key"
+    pub name: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileImport {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub changes: Vec<String>,
+    pub conflicts: Vec<ProfileConflict>,
+    pub warnings: Vec<String>,
+}
+
+fn parse_error(method: &str, msg: &str) -> Error {
+    Error::DaemonRequestError {
+        method: method.to_string(),
+        msg: msg.to_string(),
+    }
+}
+
+// Sui JSON-RPC style u64 (a string).
+fn as_u64_str(value: &JsonValue) -> u64 {
+    value.as_str().and_then(|s| s.parse().ok()).unwrap_or(0)
+}
+
+fn as_opt_string(value: &JsonValue) -> Option<String> {
+    value.as_str().map(|s| s.to_string())
+}
+
+fn as_strings(value: &JsonValue) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(as_opt_string).collect())
+        .unwrap_or_default()
+}
+
+// Failure of a json_rpc_call().
+pub(crate) enum RpcFailure {
+    Connect,         // Nothing listening (or not reachable).
+    Request(String), // Any other failure, including a JSON-RPC error response.
+}
+
+// Blocking JSON-RPC request to an HTTP (not HTTPS) server.
+pub(crate) fn json_rpc_call(
+    host: &str,
+    port: u16,
+    method: &str,
+    params: JsonValue,
+    timeout: Duration,
+) -> Result<JsonValue, RpcFailure> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    })
+    .to_string();
+
+    let addr = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or(RpcFailure::Connect)?;
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(2))
+        .map_err(|_| RpcFailure::Connect)?;
+    let _ = stream.set_read_timeout(Some(timeout));
+
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        host,
+        port,
+        body.len(),
+        body
+    );
+    let mut response = String::new();
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.read_to_string(&mut response))
+        .map_err(|e| RpcFailure::Request(e.to_string()))?;
+
+    let json_body = match response.split_once("\r\n\r\n") {
+        Some((_, json_body)) => json_body,
+        None => return Err(RpcFailure::Request("missing HTTP body".to_string())),
+    };
+    let json: JsonValue =
+        serde_json::from_str(json_body).map_err(|e| RpcFailure::Request(e.to_string()))?;
+    if let Some(err) = json.get("error") {
+        let msg = err["message"].as_str().unwrap_or("unknown error");
+        return Err(RpcFailure::Request(msg.to_string()));
+    }
+    Ok(json["result"].clone())
+}
+
+pub(crate) fn call(method: &str, params: JsonValue) -> Result<JsonValue, Error> {
+    let port = SuibaseRoot::new().active_api_port().unwrap_or(DAEMON_PORT);
+    json_rpc_call("127.0.0.1", port, method, params, DAEMON_TIMEOUT).map_err(
+        |failure| match failure {
+            RpcFailure::Connect => Error::DaemonNotRunning,
+            RpcFailure::Request(msg) => parse_error(method, &msg),
+        },
+    )
+}
+
+// Host and port of an "http://host:port" URL (the proxy never uses HTTPS).
+pub(crate) fn parse_http_url(url: &str) -> Option<(String, u16)> {
+    let authority = url.strip_prefix("http://")?.split('/').next()?;
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None if !authority.is_empty() => Some((authority.to_string(), 80)),
+        None => None,
+    }
+}
+
+// Sui JSON-RPC of a workdir (e.g. its proxy, see client_rpc_url).
+pub(crate) fn rpc_call(rpc_url: &str, method: &str, params: JsonValue) -> Result<JsonValue, Error> {
+    let (host, port) = parse_http_url(rpc_url).ok_or_else(|| Error::RpcUrlNotSupported {
+        url: rpc_url.to_string(),
+    })?;
+    json_rpc_call(&host, port, method, params, RPC_TIMEOUT).map_err(|failure| {
+        let msg = match failure {
+            RpcFailure::Connect => format!("{} not responding", rpc_url),
+            RpcFailure::Request(msg) => msg,
+        };
+        Error::RpcRequestError {
+            method: method.to_string(),
+            msg,
+        }
+    })
+}
+
+pub(crate) fn gas_inventory(workdir: &str, address: Option<String>) -> Result<GasInventory, Error> {
+    let result = call(
+        "getGasInventory",
+        serde_json::json!({ "workdir": workdir, "address": address }),
+    )?;
+    Ok(parse_gas_inventory(&result))
+}
+
+pub(crate) fn merge_gas_coins(
+    workdir: &str,
+    address: Option<String>,
+    max_coins_per_tx: Option<u32>,
+    confirm: bool,
+) -> Result<MergeGasCoinsResult, Error> {
+    let result = call(
+        "mergeGasCoins",
+        serde_json::json!({
+            "workdir": workdir,
+            "address": address,
+            "max_coins_per_tx": max_coins_per_tx,
+            "confirm": confirm,
+        }),
+    )?;
+    Ok(MergeGasCoinsResult {
+        address: result["address"].as_str().unwrap_or_default().to_string(),
+        coin_count_before: result["coinCountBefore"].as_u64().unwrap_or(0),
+        coin_count_after: result["coinCountAfter"].as_u64().unwrap_or(0),
+        tx_digests: as_strings(&result["txDigests"]),
+        info: as_opt_string(&result["info"]),
+    })
+}
+
+// Returns false when the workdir was already active.
+pub(crate) fn set_active_workdir(workdir: &str) -> Result<bool, Error> {
+    let result = call(
+        "setActiveWorkdir",
+        serde_json::json!({ "workdir": workdir }),
+    )?;
+    Ok(parse_set_active_workdir(&result))
+}
+
+pub(crate) fn sui_binary_provenance(workdir: &str) -> Result<SuiBinaryProvenance, Error> {
+    let method = "getWorkdirStatus";
+    let result = call(method, serde_json::json!({ "workdir": workdir }))?;
+    parse_sui_binary_provenance(&result).ok_or_else(|| {
+        // No binary yet, or status not polled yet by the daemon.
+        parse_error(method, "sui binary provenance not available")
+    })
+}
+
+// Deep link to an object in the sui-explorer served by the daemon.
+pub(crate) fn explorer_url_for_object(workdir: &str, object_id: &str) -> Result<String, Error> {
+    let method = "getExplorerInfo";
+    let result = call(method, serde_json::json!([]))?;
+    let base_url = result["url"].as_str().ok_or_else(|| {
+        let info = result["info"]
+            .as_str()
+            .unwrap_or("sui-explorer not listening");
+        parse_error(method, info)
+    })?;
+    Ok(explorer_object_url(base_url, workdir, object_id))
+}
+
+// The bundle is written as returned by the daemon (JSON), replacing 'path' atomically.
+pub(crate) fn export_profile(
+    workdir: &str,
+    keys: &[String],
+    path: &Path,
+) -> Result<ProfileExport, Error> {
+    let method = "exportWorkdirProfile";
+    let result = call(
+        method,
+        serde_json::json!({ "workdir": workdir, "keys": keys }),
+    )?;
+    let profile = &result["profile"];
+    if !profile.is_object() {
+        return Err(parse_error(method, "missing profile"));
+    }
+    let write_error = |msg: String| Error::ProfileWriteError {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let contents = serde_json::to_string_pretty(profile).map_err(|e| write_error(e.to_string()))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| write_error("not a file path".to_string()))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    std::fs::write(&tmp_path, contents).map_err(|e| write_error(e.to_string()))?;
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(write_error(e.to_string()));
+    }
+    Ok(parse_profile_export(&result))
+}
+
+pub(crate) fn import_profile(
+    workdir: &str,
+    path: &Path,
+    options: &ProfileImportOptions,
+) -> Result<ProfileImport, Error> {
+    let read_error = |msg: String| Error::ProfileReadError {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| read_error(e.to_string()))?;
+    let profile: JsonValue =
+        serde_json::from_str(&contents).map_err(|e| read_error(e.to_string()))?;
+    let result = call(
+        "importWorkdirProfile",
+        serde_json::json!({
+            "workdir": workdir,
+            "profile": profile,
+            "dry_run": options.dry_run,
+            "force": options.force,
+            "import_keys": options.import_keys,
+        }),
+    )?;
+    Ok(parse_profile_import(&result))
+}
+
+// The explorer selects the network with a query parameter ("local" for localnet).
+fn explorer_object_url(base_url: &str, workdir: &str, object_id: &str) -> String {
+    let network = match workdir {
+        "localnet" => Some("local"),
+        "devnet" | "testnet" | "mainnet" => Some(workdir),
+        _ => None,
+    };
+    let base_url = base_url.trim_end_matches('/');
+    match network {
+        Some(network) => format!("{}/object/{}?network={}", base_url, object_id, network),
+        None => format!("{}/object/{}", base_url, object_id),
+    }
+}
+
+fn parse_sui_binary_provenance(result: &JsonValue) -> Option<SuiBinaryProvenance> {
+    let sui_binary = result.get("suiBinary")?;
+    Some(SuiBinaryProvenance {
+        origin: as_opt_string(&sui_binary["origin"])?,
+        sui_repo_path: as_opt_string(&sui_binary["suiRepoPath"]),
+        binary_mtime: as_opt_string(&sui_binary["binaryMtime"]),
+        version: as_opt_string(&sui_binary["version"]),
+    })
+}
+
+// "<workdir> is now active" or "<workdir> is already active".
+fn parse_set_active_workdir(result: &JsonValue) -> bool {
+    !result["info"]
+        .as_str()
+        .is_some_and(|info| info.ends_with("is already active"))
+}
+
+fn parse_profile_export(result: &JsonValue) -> ProfileExport {
+    let profile = &result["profile"];
+    ProfileExport {
+        packages: profile["manifest"]["packages"]
+            .as_array()
+            .map(|packages| {
+                packages
+                    .iter()
+                    .filter_map(|package| as_opt_string(&package["name"]))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        key_count: profile["keys"]
+            .as_array()
+            .map_or(0, |keys| keys.len() as u64),
+        warnings: as_strings(&result["warnings"]),
+    }
+}
+
+fn parse_profile_import(result: &JsonValue) -> ProfileImport {
+    ProfileImport {
+        dry_run: result["dryRun"].as_bool().unwrap_or(false),
+        applied: result["applied"].as_bool().unwrap_or(false),
+        changes: as_strings(&result["changes"]),
+        conflicts: result["conflicts"]
+            .as_array()
+            .map(|conflicts| {
+                conflicts
+                    .iter()
+                    .map(|conflict| ProfileConflict {
+                        kind: conflict["kind"].as_str().unwrap_or_default().to_string(),
+                        name: conflict["name"].as_str().unwrap_or_default().to_string(),
+                        detail: conflict["detail"].as_str().unwrap_or_default().to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        warnings: as_strings(&result["warnings"]),
+    }
+}
+
+fn parse_gas_inventory(result: &JsonValue) -> GasInventory {
+    GasInventory {
+        address: result["address"].as_str().unwrap_or_default().to_string(),
+        coin_count: result["coinCount"].as_u64().unwrap_or(0),
+        total_balance: as_u64_str(&result["totalBalance"]),
+        largest_coin_id: as_opt_string(&result["largestCoin"]["coinObjectId"]),
+        largest_coin_balance: as_u64_str(&result["largestCoin"]["balance"]),
+        histogram: result["histogram"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .map(|bucket| GasCoinBucket {
+                        label: bucket["label"].as_str().unwrap_or_default().to_string(),
+                        count: bucket["count"].as_u64().unwrap_or(0),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        merge_suggested: result["mergeSuggested"].as_bool().unwrap_or(false),
+        suggestion: as_opt_string(&result["suggestion"]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://localhost:44340"),
+            Some(("localhost".to_string(), 44340))
+        );
+        assert_eq!(
+            parse_http_url("http://0.0.0.0:44342/"),
+            Some(("0.0.0.0".to_string(), 44342))
+        );
+        assert_eq!(
+            parse_http_url("http://node"),
+            Some(("node".to_string(), 80))
+        );
+        assert_eq!(parse_http_url("https://fullnode.testnet.sui.io:443"), None);
+    }
+
+    #[test]
+    fn test_parse_gas_inventory() {
+        let result = serde_json::json!({
+            "header": { "method": "getGasInventory" },
+            "address": "0xabc",
+            "coinCount": 2,
+            "totalBalance": "3000000000",
+            "largestCoin": { "coinObjectId": "0x5", "balance": "2000000000" },
+            "histogram": [ { "label": "<0.01", "count": 0 }, { "label": "1-10", "count": 2 } ],
+            "mergeSuggested": false
+        });
+        let inventory = parse_gas_inventory(&result);
+        assert_eq!(inventory.coin_count, 2);
+        assert_eq!(inventory.total_balance, 3_000_000_000);
+        assert_eq!(inventory.largest_coin_id.as_deref(), Some("0x5"));
+        assert_eq!(inventory.histogram[1].count, 2);
+        assert_eq!(inventory.suggestion, None);
+    }
+
+    #[test]
+    fn test_parse_sui_binary_provenance() {
+        let result = serde_json::json!({
+            "header": { "method": "getWorkdirStatus", "key": "testnet" },
+            "status": "OK",
+            "suiBinary": {
+                "origin": "repo-build",
+                "suiRepoPath": "/home/user/sui",
+                "binaryMtime": "2024-05-01T10:00:00+00:00",
+                "version": "1.30.1-abc"
+            }
+        });
+        let provenance = parse_sui_binary_provenance(&result).unwrap();
+        assert_eq!(provenance.origin, "repo-build");
+        assert_eq!(provenance.sui_repo_path.as_deref(), Some("/home/user/sui"));
+        assert_eq!(provenance.version.as_deref(), Some("1.30.1-abc"));
+
+        let result = serde_json::json!({ "suiBinary": { "origin": "downloaded" } });
+        let provenance = parse_sui_binary_provenance(&result).unwrap();
+        assert_eq!(provenance.origin, "downloaded");
+        assert_eq!(provenance.sui_repo_path, None);
+
+        let result = serde_json::json!({ "status": "DOWN" });
+        assert_eq!(parse_sui_binary_provenance(&result), None);
+    }
+
+    #[test]
+    fn test_parse_set_active_workdir() {
+        let result = serde_json::json!({
+            "header": { "method": "setActiveWorkdir", "key": "testnet" },
+            "result": true,
+            "info": "testnet is now active"
+        });
+        assert!(parse_set_active_workdir(&result));
+        let result = serde_json::json!({ "result": true, "info": "testnet is already active" });
+        assert!(!parse_set_active_workdir(&result));
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let result = serde_json::json!({
+            "header": { "method": "exportWorkdirProfile", "key": "localnet" },
+            "profile": {
+                "format": "suibase-workdir-profile",
+                "version": 1,
+                "manifest": {
+                    "workdir": "localnet",
+                    "packages": [ { "name": "demo", "packageId": "0x5" }, { "name": "other" } ],
+                    "addresses": []
+                },
+                "publishedData": [],
+                "keys": [ { "alias": "deployer", "address": "0x1" } ]
+            }
+        });
+        let export = parse_profile_export(&result);
+        assert_eq!(export.packages, vec!["demo", "other"]);
+        assert_eq!(export.key_count, 1);
+        assert!(export.warnings.is_empty());
+
+        let result = serde_json::json!({
+            "header": { "method": "importWorkdirProfile", "key": "localnet" },
+            "dryRun": true,
+            "applied": false,
+            "changes": [ "suibase.yaml replaced" ],
+            "conflicts": [ { "kind": "link", "name": "localnet", "detail": "rpc" } ],
+            "warnings": []
+        });
+        let import = parse_profile_import(&result);
+        assert!(import.dry_run);
+        assert!(!import.applied);
+        assert_eq!(import.changes.len(), 1);
+        assert_eq!(import.conflicts[0].kind, "link");
+        assert_eq!(import.conflicts[0].detail, "rpc");
+    }
+
+    #[test]
+    fn test_explorer_object_url() {
+        assert_eq!(
+            explorer_object_url("http://localhost:44381/", "localnet", "0x2"),
+            "http://localhost:44381/object/0x2?network=local"
+        );
+        assert_eq!(
+            explorer_object_url("http://localhost:44380", "testnet", "0x5"),
+            "http://localhost:44380/object/0x5?network=testnet"
+        );
+        assert_eq!(
+            explorer_object_url("http://localhost:44380", "cargobin", "0x5"),
+            "http://localhost:44380/object/0x5"
+        );
+    }
+}
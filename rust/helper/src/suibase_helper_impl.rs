@@ -2,15 +2,39 @@
 //
 // This is the implementation. See lib.rs for the public API and documentation.
 
-use sui_types::base_types::{ObjectID, SuiAddress};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+#[cfg(feature = "sui-types")]
+use serde_json::Value as JsonValue;
+#[cfg(feature = "sui-types")]
+use sui_types::base_types::SuiAddress;
+
+use crate::active_workdir;
+use crate::daemon_endpoints::DaemonEndpoints;
+use crate::env_file::{self, PublishedIds};
 use crate::error::Error;
-use crate::suibase_root::SuibaseRoot;
+use crate::helper_cache::{CacheKey, CachePolicy, HelperCache};
+#[cfg(feature = "sui-types")]
+use crate::keystore::{self, KeystoreReport};
+#[cfg(feature = "sui-types")]
+use crate::move_call::{self, MoveCallResult};
+use crate::package_build::{self, PackageBuildInfo};
+use crate::sui_ids::{SuibaseAddress, SuibaseObjectId};
+use crate::suibase_daemon_api::{
+    self, GasInventory, MergeGasCoinsResult, ProfileExport, ProfileImport, ProfileImportOptions,
+    SuiBinaryProvenance,
+};
+use crate::suibase_root::{Compatibility, InstallationStatus, SuibaseRoot};
 use crate::suibase_workdir::SuibaseWorkdir;
+use crate::tx_lookup::{self, TxStatus};
+use crate::workdir_status::{self, WorkdirStatus};
 
 pub struct SuibaseHelperImpl {
     root: SuibaseRoot,               // for most features related to ~/suibase
     workdir: Option<SuibaseWorkdir>, // for *one* selected workdir under ~/suibase/workdirs
+    cache: Option<HelperCache>,      // None unless opt-in (see set_cache_policy)
+    sui_keys_fallback: bool,         // See set_sui_keys_fallback
 }
 
 impl Default for SuibaseHelperImpl {
@@ -33,6 +57,64 @@ impl SuibaseHelperImpl {
         SuibaseHelperImpl {
             root: SuibaseRoot::new(),
             workdir: None,
+            cache: None,
+            sui_keys_fallback: false,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_suibase_path(suibase_path: &std::path::Path) -> SuibaseHelperImpl {
+        SuibaseHelperImpl {
+            root: SuibaseRoot::with_suibase_path(suibase_path),
+            workdir: None,
+            cache: None,
+            sui_keys_fallback: false,
+        }
+    }
+
+    // Enable the caching of the lookups (or change how they are invalidated).
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) {
+        match &mut self.cache {
+            Some(cache) => cache.set_policy(policy),
+            None => self.cache = Some(HelperCache::new(policy)),
+        }
+    }
+
+    pub fn invalidate_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.invalidate();
+        }
+    }
+
+    // Load the keystore with sui-keys when the helper cannot read it (only for the
+    // default "active" address).
+    pub fn set_sui_keys_fallback(&mut self, enabled: bool) {
+        self.sui_keys_fallback = enabled;
+        self.invalidate_cache();
+    }
+
+    // Do 'load' through the cache (when enabled). 'watched' are the files it uses.
+    fn cached<T, W, L>(
+        &mut self,
+        lookup: &'static str,
+        arg: &str,
+        watched: W,
+        load: L,
+    ) -> Result<T, Error>
+    where
+        T: Clone + Send + 'static,
+        W: FnOnce(&SuibaseWorkdir, &SuibaseRoot) -> Vec<PathBuf>,
+        L: FnOnce(&SuibaseWorkdir, &mut SuibaseRoot) -> Result<T, Error>,
+    {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let root = &mut self.root;
+        match &mut self.cache {
+            Some(cache) => {
+                let key = CacheKey::new(&wd.get_name()?, lookup, arg);
+                let watched = watched(wd, root);
+                cache.get_or_load(key, watched, || load(wd, root))
+            }
+            None => load(wd, root),
         }
     }
 
@@ -42,6 +124,16 @@ impl SuibaseHelperImpl {
         Ok(self.root.is_installed())
     }
 
+    // Stricter than is_installed(). See InstallationStatus.
+    pub fn installation_status(self: &mut SuibaseHelperImpl) -> Result<InstallationStatus, Error> {
+        Ok(self.root.installation_status())
+    }
+
+    // Compare the ~/suibase version with the range supported by this helper.
+    pub fn check_compatibility(self: &mut SuibaseHelperImpl) -> Result<Compatibility, Error> {
+        self.root.check_compatibility()
+    }
+
     // Select an existing workdir by name.
     //
     // Possible values are:
@@ -60,6 +152,11 @@ impl SuibaseHelperImpl {
         Ok(())
     }
 
+    // Change the active workdir (does not change the selected one).
+    pub fn set_active_workdir(&mut self, workdir_name: &str) -> Result<bool, Error> {
+        active_workdir::set_active_workdir(&mut self.root, workdir_name)
+    }
+
     // Get the name of the selected workdir.
     pub fn workdir(&self) -> Result<String, Error> {
         match &self.workdir {
@@ -74,10 +171,12 @@ impl SuibaseHelperImpl {
     pub fn keystore_pathname(&mut self) -> Result<String, Error> {
         // TODO Implement this better with suibase.yaml and/or ENV variables.
         //      See https://github.com/chainmovers/suibase/issues/6
-        match &self.workdir {
-            Some(wd) => Ok(wd.keystore_pathname(&mut self.root)?),
-            None => Err(Error::WorkdirNotSelected),
-        }
+        self.cached(
+            "keystore_pathname",
+            "",
+            |wd, root| wd.client_config_watched(root),
+            |wd, root| wd.keystore_pathname(root),
+        )
     }
 
     // Get the ObjectID of the last successfully published "package_name".
@@ -88,11 +187,26 @@ impl SuibaseHelperImpl {
     pub fn package_object_id(
         self: &mut SuibaseHelperImpl,
         package_name: &str,
-    ) -> Result<ObjectID, Error> {
-        match &self.workdir {
-            Some(wd) => Ok(wd.package_object_id(&mut self.root, package_name)?),
-            None => Err(Error::WorkdirNotSelected),
-        }
+    ) -> Result<SuibaseObjectId, Error> {
+        self.cached(
+            "package_object_id",
+            package_name,
+            |wd, _| wd.published_file_watched(package_name, "package-id"),
+            |wd, root| wd.package_object_id(root, package_name),
+        )
+    }
+
+    // Build metadata of the last published "package_name" (see package_build.rs).
+    pub fn package_build_info(&mut self, package_name: &str) -> Result<PackageBuildInfo, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let published_dir = wd.published_dir(&mut self.root, package_name)?;
+        package_build::read_build_info(package_name, &published_dir)
+    }
+
+    pub fn package_modules(&mut self, package_name: &str) -> Result<Vec<String>, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let published_dir = wd.published_dir(&mut self.root, package_name)?;
+        package_build::read_modules(package_name, &published_dir)
     }
 
     // Get the ObjectID of the objects that were created when the package was published.
@@ -116,11 +230,14 @@ impl SuibaseHelperImpl {
     pub fn published_new_object_ids(
         self: &mut SuibaseHelperImpl,
         object_type: &str,
-    ) -> Result<Vec<ObjectID>, Error> {
-        match &self.workdir {
-            Some(wd) => Ok(wd.published_new_object_ids(&mut self.root, object_type)?),
-            None => Err(Error::WorkdirNotSelected),
-        }
+    ) -> Result<Vec<SuibaseObjectId>, Error> {
+        let package_name = object_type.split("::").next().unwrap_or_default().trim();
+        self.cached(
+            "published_new_object_ids",
+            object_type,
+            |wd, _| wd.published_file_watched(package_name, "created-objects"),
+            |wd, root| wd.published_new_object_ids(root, object_type),
+        )
     }
 
     // Get an address by name.
@@ -137,11 +254,26 @@ impl SuibaseHelperImpl {
     pub fn client_sui_address(
         self: &mut SuibaseHelperImpl,
         address_name: &str,
-    ) -> Result<SuiAddress, Error> {
-        match &self.workdir {
-            Some(wd) => Ok(wd.client_sui_address(&mut self.root, address_name)?),
-            None => Err(Error::WorkdirNotSelected),
-        }
+    ) -> Result<SuibaseAddress, Error> {
+        let sui_keys_fallback = self.sui_keys_fallback;
+        self.cached(
+            "client_sui_address",
+            address_name,
+            |wd, root| {
+                if address_name == "active" {
+                    wd.client_config_watched(root)
+                } else {
+                    wd.state_file_watched("dns")
+                }
+            },
+            |wd, root| {
+                if address_name == "active" {
+                    wd.client_active_address(root, sui_keys_fallback)
+                } else {
+                    wd.client_sui_address(root, address_name)
+                }
+            },
+        )
     }
 
     // Get a RPC URL for the selected workdir.
@@ -152,6 +284,10 @@ impl SuibaseHelperImpl {
         }
     }
 
+    pub fn daemon_endpoints(&mut self) -> Result<Option<DaemonEndpoints>, Error> {
+        Ok(self.root.daemon_endpoints())
+    }
+
     // Get a Websocket URL for the selected workdir.
     pub fn ws_url(&mut self) -> Result<String, Error> {
         match &self.workdir {
@@ -159,4 +295,181 @@ impl SuibaseHelperImpl {
             None => Err(Error::WorkdirNotSelected),
         }
     }
+
+    // Services of the selected workdir (from the daemon, or probed directly).
+    pub fn workdir_status(&mut self) -> Result<WorkdirStatus, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        workdir_status::workdir_status(&mut self.root, wd)
+    }
+
+    // Link to an object in the sui-explorer of the daemon, for the network of the selected workdir.
+    pub fn explorer_url_for_object(&mut self, object_id: &str) -> Result<String, Error> {
+        let workdir = self.workdir()?;
+        let object_id: SuibaseObjectId = object_id.parse()?;
+        suibase_daemon_api::explorer_url_for_object(&workdir, &object_id.to_string())
+    }
+
+    // SUI coins inventory of an address (None for the active address).
+    //
+    // Delegated to the suibase-daemon.
+    pub fn gas_inventory(&mut self, address: Option<String>) -> Result<GasInventory, Error> {
+        let workdir = self.workdir()?;
+        suibase_daemon_api::gas_inventory(&workdir, address)
+    }
+
+    // Cross-check the addresses used by the workdir with its keystore.
+    #[cfg(feature = "sui-types")]
+    pub fn verify_keystore(&mut self) -> Result<KeystoreReport, Error> {
+        let keystore_pathname = self.keystore_pathname()?;
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let active_address = wd.client_active_address_setting(&mut self.root)?;
+        let named_addresses = wd.named_addresses(&mut self.root)?;
+        let named_addresses: Vec<SuiAddress> =
+            named_addresses.into_iter().map(SuiAddress::from).collect();
+        keystore::verify(
+            Path::new(&keystore_pathname),
+            active_address.map(SuiAddress::from),
+            &named_addresses,
+        )
+    }
+
+    // Add a private key to the keystore of the workdir.
+    //
+    // Refused on mainnet unless allow_mainnet is true.
+    #[cfg(feature = "sui-types")]
+    pub fn import_key(
+        &mut self,
+        private_key: &str,
+        allow_mainnet: bool,
+    ) -> Result<SuibaseAddress, Error> {
+        let workdir = self.workdir()?;
+        if workdir == "mainnet" && !allow_mainnet {
+            return Err(Error::KeyImportNotAllowed { workdir });
+        }
+        let keystore_pathname = self.keystore_pathname()?;
+        let address = keystore::import_key(Path::new(&keystore_pathname), private_key)?;
+        // e.g. the active address may now default to a key of the keystore.
+        self.invalidate_cache();
+        Ok(address.into())
+    }
+
+    // Merge the SUI coins of an address (None for the active address).
+    //
+    // Delegated to the suibase-daemon. Refused on mainnet unless confirm is true.
+    pub fn merge_gas_coins(
+        &mut self,
+        address: Option<String>,
+        max_coins_per_tx: Option<u32>,
+        confirm: bool,
+    ) -> Result<MergeGasCoinsResult, Error> {
+        let workdir = self.workdir()?;
+        suibase_daemon_api::merge_gas_coins(&workdir, address, max_coins_per_tx, confirm)
+    }
+
+    // Where the sui binary of the selected workdir comes from.
+    //
+    // Delegated to the suibase-daemon.
+    pub fn sui_binary_provenance(&mut self) -> Result<SuiBinaryProvenance, Error> {
+        let workdir = self.workdir()?;
+        suibase_daemon_api::sui_binary_provenance(&workdir)
+    }
+
+    // Bundle of the setup of the selected workdir, written to 'path'.
+    //
+    // Delegated to the suibase-daemon.
+    pub fn export_profile(&mut self, path: &Path, keys: &[String]) -> Result<ProfileExport, Error> {
+        let workdir = self.workdir()?;
+        suibase_daemon_api::export_profile(&workdir, keys, path)
+    }
+
+    pub fn import_profile(
+        &mut self,
+        path: &Path,
+        options: &ProfileImportOptions,
+    ) -> Result<ProfileImport, Error> {
+        let workdir = self.workdir()?;
+        let result = suibase_daemon_api::import_profile(&workdir, path, options)?;
+        if result.applied {
+            // Published data (and maybe the keystore) changed.
+            self.invalidate_cache();
+        }
+        Ok(result)
+    }
+
+    // Unsigned transaction for a call of the last published 'package_name', with
+    // the active address as the signer.
+    #[cfg(feature = "sui-types")]
+    pub fn build_move_call(
+        &mut self,
+        package_name: &str,
+        module: &str,
+        function: &str,
+        type_args: &[String],
+        args: &[String],
+    ) -> Result<JsonValue, Error> {
+        let package_id = self.package_object_id(package_name)?;
+        let signer = self.client_sui_address("active")?;
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let rpc_url = wd.client_rpc_url(&mut self.root)?;
+        move_call::build_move_call(
+            &rpc_url,
+            &signer.into(),
+            &package_id.into(),
+            module,
+            function,
+            type_args,
+            args,
+        )
+    }
+
+    // Same as build_move_call(), then signed with the workdir keystore and executed.
+    #[cfg(feature = "sui-types")]
+    pub fn execute_move_call(
+        &mut self,
+        package_name: &str,
+        module: &str,
+        function: &str,
+        type_args: &[String],
+        args: &[String],
+    ) -> Result<MoveCallResult, Error> {
+        let unsigned_tx = self.build_move_call(package_name, module, function, type_args, args)?;
+        let signer = self.client_sui_address("active")?;
+        let keystore_pathname = self.keystore_pathname()?;
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let rpc_url = wd.client_rpc_url(&mut self.root)?;
+        move_call::sign_and_execute(&rpc_url, &keystore_pathname, &signer.into(), &unsigned_tx)
+    }
+
+    // Objects created by a transaction of the selected workdir, grouped by type.
+    pub fn tx_created_objects(
+        &mut self,
+        digest: &str,
+    ) -> Result<HashMap<String, Vec<SuibaseObjectId>>, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let rpc_url = wd.client_rpc_url(&mut self.root)?;
+        tx_lookup::tx_created_objects(&rpc_url, digest)
+    }
+
+    // Success/failure of a transaction of the selected workdir.
+    pub fn tx_status(&mut self, digest: &str) -> Result<TxStatus, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        let rpc_url = wd.client_rpc_url(&mut self.root)?;
+        tx_lookup::tx_status(&rpc_url, digest)
+    }
+
+    // Proxy RPC URL of the selected workdir (for the calls done without the lock).
+    pub fn client_rpc_url(&mut self) -> Result<String, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        wd.client_rpc_url(&mut self.root)
+    }
+
+    // Latest package ids (and ids of the objects created at publication) of the selected workdir.
+    pub fn published_ids(
+        &mut self,
+        package_names: &[&str],
+        object_types: &[&str],
+    ) -> Result<PublishedIds, Error> {
+        let wd = self.workdir.as_ref().ok_or(Error::WorkdirNotSelected)?;
+        env_file::collect_published_ids(&mut self.root, wd, package_names, object_types)
+    }
 }
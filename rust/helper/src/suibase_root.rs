@@ -1,5 +1,79 @@
 use home::home_dir;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::daemon_endpoints::{
+    load_daemon_endpoints, DaemonEndpoints, ENDPOINT_PURPOSE_API, ENDPOINT_PURPOSE_PROXY,
+};
+use crate::error::Error;
+use crate::suibase_home::suibase_home;
+
+/// Result of a more thorough check than is_installed().
+///
+/// `missing` lists paths (relative to ~/suibase) that are expected but not found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallationStatus {
+    NotInstalled,
+    PartiallyInitialized { missing: Vec<String> },
+    Ok,
+}
+
+/// Result of check_compatibility() when the installed suibase scripts are usable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    Compatible,
+    // Not below the minimum, but more recent than what this helper was tested with.
+    NewerThanTested { found: String },
+}
+
+// Range of ~/suibase versions (SUIBASE_VERSION in the version file) supported by this helper.
+//
+// 0.1.5 introduced the suibase-daemon (proxy, gas and move calls depend on it).
+pub const MIN_SUIBASE_VERSION: &str = "0.1.5";
+pub const TESTED_SUIBASE_VERSION: &str = "0.1.7";
+
+// Relative to ~/suibase. Has a line like: export SUIBASE_VERSION="0.1.7"
+const VERSION_FILE: &str = "scripts/common/__globals.sh";
+
+// Used when the version cannot be read. Exist in every layout since MIN_SUIBASE_VERSION.
+const LAYOUT_MARKERS: [&str; 2] = [
+    "rust/helper/Cargo.toml",
+    "rust/suibase/crates/suibase-daemon/Cargo.toml",
+];
+
+// "major.minor.patch" (any "-build" suffix ignored).
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.split('-').next()?;
+    let mut numbers = version.split('.').map(|n| n.trim().parse::<u32>());
+    let major = numbers.next()?.ok()?;
+    let minor = numbers.next()?.ok()?;
+    let patch = numbers.next()?.ok()?;
+    if numbers.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+// Extract the SUIBASE_VERSION value from the content of the version file.
+fn read_suibase_version(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let value = line.strip_prefix("SUIBASE_VERSION=")?;
+        let value = value.trim().trim_matches('"');
+        parse_version(value).map(|_| value.to_string())
+    })
+}
+
+// Written by the suibase-daemon, relative to ~/suibase/workdirs. Has the ports actually
+// listened on, which differ from suibase.yaml when a configured port was already in use
+// (e.g. by the daemon of another user on the same host).
+const ACTIVE_PORTS_FILE: &str = "common/active-ports.yaml";
+
+// Workdirs that are checked by installation_status() when their directory exists.
+const KNOWN_WORKDIRS: [&str; 5] = ["localnet", "devnet", "testnet", "mainnet", "cargobin"];
+
+// Files expected in every initialized workdir (see create_state_as_needed in __globals.sh).
+const WORKDIR_STATE_FILES: [&str; 2] = ["name", "user_request"];
 
 pub(crate) struct SuibaseRoot {
     // Parent to all internal variables, except for the per workdir ones (see suibase_workdir.rs)
@@ -12,15 +86,32 @@ pub(crate) struct SuibaseRoot {
     // Absolute path to suibase/workdirs
     // (e.g. /home/johndoe/suibase/workdirs )
     workdirs_path: String,
+
+    suibase_path_exists: bool,
+    workdirs_path_exists: bool,
+
+    // When set, used instead of ~/suibase or SUIBASE_HOME (for tests).
+    suibase_path_override: Option<PathBuf>,
 }
 
 impl SuibaseRoot {
     pub fn new() -> SuibaseRoot {
+        Self::new_internal(None)
+    }
+
+    pub fn with_suibase_path(suibase_path: &Path) -> SuibaseRoot {
+        Self::new_internal(Some(suibase_path.to_path_buf()))
+    }
+
+    fn new_internal(suibase_path_override: Option<PathBuf>) -> SuibaseRoot {
         // Create with default init state + refresh_state().
         let mut new_obj = SuibaseRoot {
             is_installed: false,
             suibase_path: String::new(),
             workdirs_path: String::new(),
+            suibase_path_exists: false,
+            workdirs_path_exists: false,
+            suibase_path_override,
         };
         new_obj.refresh_state();
         new_obj
@@ -31,6 +122,16 @@ impl SuibaseRoot {
         self.is_installed
     }
 
+    // true when not ~/suibase (see with_suibase_path).
+    pub fn has_suibase_path_override(self: &SuibaseRoot) -> bool {
+        self.suibase_path_override.is_some()
+    }
+
+    // true when ~/suibase exists, but not ~/suibase/workdirs.
+    pub fn is_workdirs_missing(self: &SuibaseRoot) -> bool {
+        self.suibase_path_exists && !self.workdirs_path_exists
+    }
+
     #[allow(dead_code)]
     pub fn suibase_path(self: &SuibaseRoot) -> &str {
         &self.suibase_path
@@ -40,34 +141,182 @@ impl SuibaseRoot {
         &self.workdirs_path
     }
 
+    // The user home. Has the ~/.sui used by the cargobin workdir.
+    //
+    // With an override, the parent of the suibase path is used instead.
+    pub fn home_path(self: &SuibaseRoot) -> PathBuf {
+        if self.suibase_path_override.is_none() {
+            if let Some(home) = home_dir() {
+                return home;
+            }
+        }
+        Path::new(&self.suibase_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    // Content of daemon-endpoints.json (None when there is no usable file).
+    pub fn daemon_endpoints(self: &SuibaseRoot) -> Option<DaemonEndpoints> {
+        load_daemon_endpoints(&self.workdirs_path)
+    }
+
+    // Port of the suibase-daemon API (None when not known from daemon-endpoints.json
+    // or active-ports.yaml).
+    pub fn active_api_port(self: &SuibaseRoot) -> Option<u16> {
+        if let Some(port) = self
+            .daemon_endpoints()
+            .and_then(|endpoints| endpoints.fresh_port(None, ENDPOINT_PURPOSE_API))
+        {
+            return Some(port);
+        }
+        self.load_active_ports()
+            .and_then(|ports| ports["api_port"].as_u64())
+            .and_then(|port| u16::try_from(port).ok())
+    }
+
+    // Port of the proxy for a workdir (None when not known from daemon-endpoints.json
+    // or active-ports.yaml).
+    pub fn active_proxy_port(self: &SuibaseRoot, workdir: &str) -> Option<u16> {
+        if let Some(port) = self
+            .daemon_endpoints()
+            .and_then(|endpoints| endpoints.fresh_port(Some(workdir), ENDPOINT_PURPOSE_PROXY))
+        {
+            return Some(port);
+        }
+        self.load_active_ports()
+            .and_then(|ports| ports["proxy_ports"][workdir].as_u64())
+            .and_then(|port| u16::try_from(port).ok())
+    }
+
+    fn load_active_ports(self: &SuibaseRoot) -> Option<serde_yaml::Value> {
+        if self.workdirs_path.is_empty() {
+            return None;
+        }
+        let content =
+            std::fs::read_to_string(Path::new(&self.workdirs_path).join(ACTIVE_PORTS_FILE)).ok()?;
+        serde_yaml::from_str(&content).ok()
+    }
+
     pub fn refresh_state(self: &mut SuibaseRoot) {
-        if let Some(mut path_buf) = home_dir() {
-            path_buf.push("suibase");
+        let suibase_path_buf = match &self.suibase_path_override {
+            Some(path) => Some(path.clone()),
+            None => suibase_home(),
+        };
+
+        if let Some(mut path_buf) = suibase_path_buf {
             self.suibase_path = path_buf.to_string_lossy().to_string();
 
             path_buf.push("workdirs");
             self.workdirs_path = path_buf.to_string_lossy().to_string();
         }
 
-        let base_path_ok = if self.suibase_path.is_empty() {
+        self.suibase_path_exists = if self.suibase_path.is_empty() {
             false
         } else {
             Path::new(&self.suibase_path).exists()
         };
 
-        let workdirs_path_ok = if self.workdirs_path.is_empty() {
+        self.workdirs_path_exists = if self.workdirs_path.is_empty() {
             false
         } else {
             Path::new(&self.workdirs_path).exists()
         };
 
-        self.is_installed = base_path_ok && workdirs_path_ok;
+        self.is_installed = self.suibase_path_exists && self.workdirs_path_exists;
+    }
+
+    pub fn installation_status(self: &mut SuibaseRoot) -> InstallationStatus {
+        self.refresh_state();
+
+        if !self.suibase_path_exists {
+            return InstallationStatus::NotInstalled;
+        }
+
+        let mut missing = Vec::new();
+        if !self.workdirs_path_exists {
+            missing.push("workdirs".to_string());
+            return InstallationStatus::PartiallyInitialized { missing };
+        }
+
+        let workdirs_path = PathBuf::from(&self.workdirs_path);
+
+        // A dangling "active" symlink is as bad as a missing workdir.
+        let active_path = workdirs_path.join("active");
+        if active_path.symlink_metadata().is_ok() && !active_path.exists() {
+            missing.push("workdirs/active".to_string());
+        }
+
+        // Only check the workdirs that the user did create. Not all need to exist.
+        for workdir in KNOWN_WORKDIRS {
+            let workdir_path = workdirs_path.join(workdir);
+            if !workdir_path.is_dir() {
+                continue;
+            }
+            let state_path = workdir_path.join(".state");
+            if !state_path.is_dir() {
+                missing.push(format!("workdirs/{}/.state", workdir));
+                continue;
+            }
+            for state_file in WORKDIR_STATE_FILES {
+                if !state_path.join(state_file).is_file() {
+                    missing.push(format!("workdirs/{}/.state/{}", workdir, state_file));
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            InstallationStatus::Ok
+        } else {
+            InstallationStatus::PartiallyInitialized { missing }
+        }
+    }
+
+    pub fn check_compatibility(self: &mut SuibaseRoot) -> Result<Compatibility, Error> {
+        self.refresh_state();
+        if !self.suibase_path_exists {
+            return Err(Error::NotInstalled);
+        }
+        let suibase_path = PathBuf::from(&self.suibase_path);
+        let required = MIN_SUIBASE_VERSION.to_string();
+
+        let found = std::fs::read_to_string(suibase_path.join(VERSION_FILE))
+            .ok()
+            .and_then(|content| read_suibase_version(&content));
+
+        let found = match found {
+            Some(found) => found,
+            None => {
+                // No usable version. Can only tell if the layout is not older than the minimum.
+                if LAYOUT_MARKERS
+                    .iter()
+                    .all(|marker| suibase_path.join(marker).exists())
+                {
+                    return Ok(Compatibility::Compatible);
+                }
+                return Err(Error::IncompatibleSuibase {
+                    found: "unknown".to_string(),
+                    required,
+                });
+            }
+        };
+
+        // Both constants are valid versions (see tests).
+        let found_version = parse_version(&found).unwrap_or_default();
+        if found_version < parse_version(MIN_SUIBASE_VERSION).unwrap_or_default() {
+            return Err(Error::IncompatibleSuibase { found, required });
+        }
+        if found_version > parse_version(TESTED_SUIBASE_VERSION).unwrap_or_default() {
+            return Ok(Compatibility::NewerThanTested { found });
+        }
+        Ok(Compatibility::Compatible)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SuibaseRoot;
+    use super::*;
+    use std::fs;
 
     #[test]
     fn test_new() {
@@ -93,4 +342,166 @@ mod tests {
         assert_eq!(workdir_path.is_empty(), false);
         assert_eq!(workdir_path.ends_with("suibase/workdirs"), true);
     }
+
+    #[test]
+    fn test_status_not_installed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut sb = SuibaseRoot::with_suibase_path(&tmp.path().join("suibase"));
+        assert_eq!(sb.is_installed(), false);
+        assert_eq!(sb.installation_status(), InstallationStatus::NotInstalled);
+    }
+
+    #[test]
+    fn test_status_workdirs_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut sb = SuibaseRoot::with_suibase_path(tmp.path());
+        assert_eq!(sb.is_installed(), false);
+        assert_eq!(sb.is_workdirs_missing(), true);
+        assert_eq!(
+            sb.installation_status(),
+            InstallationStatus::PartiallyInitialized {
+                missing: vec!["workdirs".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_status_half_initialized_workdirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workdirs = tmp.path().join("workdirs");
+        // localnet without any .state, devnet with a partial .state, testnet OK.
+        fs::create_dir_all(workdirs.join("localnet")).unwrap();
+        fs::create_dir_all(workdirs.join("devnet/.state")).unwrap();
+        fs::write(workdirs.join("devnet/.state/name"), "devnet").unwrap();
+        fs::create_dir_all(workdirs.join("testnet/.state")).unwrap();
+        fs::write(workdirs.join("testnet/.state/name"), "testnet").unwrap();
+        fs::write(workdirs.join("testnet/.state/user_request"), "stop").unwrap();
+
+        let mut sb = SuibaseRoot::with_suibase_path(tmp.path());
+        assert_eq!(sb.is_installed(), true);
+        assert_eq!(
+            sb.installation_status(),
+            InstallationStatus::PartiallyInitialized {
+                missing: vec![
+                    "workdirs/localnet/.state".to_string(),
+                    "workdirs/devnet/.state/user_request".to_string(),
+                ]
+            }
+        );
+
+        // Repair and check again.
+        fs::create_dir_all(workdirs.join("localnet/.state")).unwrap();
+        fs::write(workdirs.join("localnet/.state/name"), "localnet").unwrap();
+        fs::write(workdirs.join("localnet/.state/user_request"), "stop").unwrap();
+        fs::write(workdirs.join("devnet/.state/user_request"), "stop").unwrap();
+        assert_eq!(sb.installation_status(), InstallationStatus::Ok);
+    }
+
+    fn write_version_file(suibase_path: &Path, version: &str) {
+        let path = suibase_path.join(VERSION_FILE);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let content = format!(
+            "# Format is: \"major.minor.patch-build\"\nexport SUIBASE_VERSION=\"{}\"\n",
+            version
+        );
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.1.7"), Some((0, 1, 7)));
+        assert_eq!(parse_version("1.12.0-a3c9b2f"), Some((1, 12, 0)));
+        assert_eq!(parse_version("0.1"), None);
+        assert_eq!(parse_version("0.1.7.2"), None);
+        assert_eq!(parse_version("$VERSION"), None);
+        assert!(parse_version(MIN_SUIBASE_VERSION).is_some());
+        assert!(parse_version(MIN_SUIBASE_VERSION) <= parse_version(TESTED_SUIBASE_VERSION));
+    }
+
+    #[test]
+    fn test_compatibility_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut sb = SuibaseRoot::with_suibase_path(tmp.path());
+
+        write_version_file(tmp.path(), TESTED_SUIBASE_VERSION);
+        assert_eq!(sb.check_compatibility().unwrap(), Compatibility::Compatible);
+
+        write_version_file(tmp.path(), "99.0.0-abcdef");
+        assert_eq!(
+            sb.check_compatibility().unwrap(),
+            Compatibility::NewerThanTested {
+                found: "99.0.0-abcdef".to_string()
+            }
+        );
+
+        write_version_file(tmp.path(), "0.0.9");
+        assert!(matches!(
+            sb.check_compatibility(),
+            Err(Error::IncompatibleSuibase { found, required })
+                if found == "0.0.9" && required == MIN_SUIBASE_VERSION
+        ));
+    }
+
+    #[test]
+    fn test_compatibility_missing_version_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut sb = SuibaseRoot::with_suibase_path(&tmp.path().join("suibase"));
+        assert!(matches!(sb.check_compatibility(), Err(Error::NotInstalled)));
+
+        // Old layout (no suibase-daemon).
+        let mut sb = SuibaseRoot::with_suibase_path(tmp.path());
+        fs::create_dir_all(tmp.path().join("rust/helper")).unwrap();
+        fs::write(tmp.path().join("rust/helper/Cargo.toml"), "").unwrap();
+        assert!(matches!(
+            sb.check_compatibility(),
+            Err(Error::IncompatibleSuibase { found, .. }) if found == "unknown"
+        ));
+
+        // Current layout.
+        let daemon_path = tmp.path().join("rust/suibase/crates/suibase-daemon");
+        fs::create_dir_all(&daemon_path).unwrap();
+        fs::write(daemon_path.join("Cargo.toml"), "").unwrap();
+        assert_eq!(sb.check_compatibility().unwrap(), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn test_active_ports() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sb = SuibaseRoot::with_suibase_path(tmp.path());
+        assert_eq!(sb.active_api_port(), None);
+        assert_eq!(sb.active_proxy_port("localnet"), None);
+
+        let common_path = tmp.path().join("workdirs/common");
+        fs::create_dir_all(&common_path).unwrap();
+        fs::write(
+            common_path.join("active-ports.yaml"),
+            "api_port: 44400\nproxy_ports:\n  localnet: 44345\n",
+        )
+        .unwrap();
+        assert_eq!(sb.active_api_port(), Some(44400));
+        assert_eq!(sb.active_proxy_port("localnet"), Some(44345));
+        assert_eq!(sb.active_proxy_port("testnet"), None);
+
+        // daemon-endpoints.json is preferred while its daemon is running...
+        let endpoints = |pid: u32| {
+            format!(
+                "{{\"version\": 1, \"pid\": {}, \"generation\": 1, \"stale\": false, \
+                \"endpoints\": [{{\"purpose\": \"proxy\", \"workdir\": \"localnet\", \
+                \"url\": \"http://localhost:44350\", \"port\": 44350}}]}}",
+                pid
+            )
+        };
+        let endpoints_path = common_path.join("daemon-endpoints.json");
+        fs::write(&endpoints_path, endpoints(std::process::id())).unwrap();
+        assert_eq!(sb.active_proxy_port("localnet"), Some(44350));
+        assert_eq!(sb.active_api_port(), Some(44400));
+
+        // ...but not after a crash (the pid no longer exists).
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        fs::write(&endpoints_path, endpoints(dead_pid)).unwrap();
+        assert!(sb.daemon_endpoints().unwrap().stale);
+        assert_eq!(sb.active_proxy_port("localnet"), Some(44345));
+    }
 }
@@ -8,11 +8,46 @@ use std::str::FromStr;
 use serde_json::Value;
 use serde_yaml::Value as YamlValue;
 
-use sui_types::base_types::{ObjectID, SuiAddress};
+#[cfg(feature = "sui-types")]
+use sui_keys::keystore::{AccountKeystore, FileBasedKeystore};
 
+use crate::daemon_endpoints::{ENDPOINT_PURPOSE_PROXY, ENDPOINT_PURPOSE_WS};
 use crate::error::Error;
+#[cfg(feature = "sui-types")]
+use crate::keystore;
+use crate::sui_ids::{SuibaseAddress, SuibaseObjectId};
+use crate::suibase_daemon_api::parse_http_url;
 use crate::suibase_root::SuibaseRoot;
 
+// Replace the port of a proxy URL (localhost only, other URLs are returned as-is).
+fn with_proxy_port(rpc: &str, active_port: u16) -> String {
+    match parse_http_url(rpc) {
+        Some((host, port)) if host == "localhost" || host == "127.0.0.1" => rpc.replacen(
+            &format!("{}:{}", host, port),
+            &format!("{}:{}", host, active_port),
+            1,
+        ),
+        _ => rpc.to_string(),
+    }
+}
+
+// The cargobin workdir is for the user's own ~/.cargo/bin/sui. Its "config" symlinks to
+// the user's ~/.sui/sui_config (unless overridden), and it has no links, dns or published
+// data maintained by suibase.
+const CARGOBIN: &str = "cargobin";
+const CARGOBIN_DEFAULT_CONFIG: &str = ".sui/sui_config";
+
+// Websocket URL for a RPC URL (same host and port).
+fn ws_url_from_rpc(rpc: &str) -> String {
+    if let Some(rest) = rpc.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc.to_string()
+    }
+}
+
 pub(crate) struct SuibaseWorkdir {
     workdir_name: Option<String>,
     workdir_path: Option<String>,
@@ -32,6 +67,11 @@ impl SuibaseWorkdir {
         workdir_name: &str,
     ) -> Result<(), Error> {
         if !root.is_installed() {
+            if root.is_workdirs_missing() {
+                return Err(Error::WorkdirsNotExists {
+                    path: root.workdirs_path().to_string(),
+                });
+            }
             return Err(Error::NotInstalled);
         }
 
@@ -39,9 +79,12 @@ impl SuibaseWorkdir {
             return Err(Error::WorkdirNameEmpty);
         }
 
-        // Check that the workdir do exists.
+        // Check that the workdir do exists (a dangling "active" symlink is same as not existing).
         let mut path_buf = PathBuf::from(root.workdirs_path());
         path_buf.push(workdir_name);
+        if !path_buf.exists() {
+            return Err(Error::WorkdirNotExists);
+        }
         path_buf = std::fs::canonicalize(path_buf).map_err(|_| Error::WorkdirAccessError)?;
 
         let workdir_path = path_buf.to_string_lossy().to_string();
@@ -55,12 +98,29 @@ impl SuibaseWorkdir {
             return Err(Error::WorkdirNotExists);
         }
 
+        // The .state directory and its files are created by the scripts when the workdir
+        // is initialized. Missing means an interrupted install/update.
+        path_buf.push(".state");
+        if !path_buf.is_dir() {
+            return Err(Error::WorkdirInitializationIncomplete {
+                workdir: workdir_name.to_string(),
+            });
+        }
+
+        if !path_buf.join("user_request").is_file() {
+            return Err(Error::WorkdirInitializationIncomplete {
+                workdir: workdir_name.to_string(),
+            });
+        }
+
         // Get the actual workdir name from the .state/name
         //
         // It resolved the workdir name when "active", but also, it generally provides
         // a sanity check that the workdir was created and is read accessible by this app.
-        path_buf.push(".state");
         path_buf.push("name");
+        if !path_buf.is_file() {
+            return Err(Error::WorkdirStateNameAccessFailed);
+        }
         let mut in_str =
             std::fs::read_to_string(&path_buf).map_err(|_| Error::WorkdirAccessError)?;
         in_str = in_str.trim().to_string();
@@ -89,9 +149,18 @@ impl SuibaseWorkdir {
         &self,
         root: &mut SuibaseRoot,
         package_name: &str,
-    ) -> Result<ObjectID, Error> {
+    ) -> Result<SuibaseObjectId, Error> {
         let pathname =
             self.get_pathname_published_file(root, package_name, "package-id", "json")?;
+
+        // The package directory exists, but not the id file (e.g. publication interrupted).
+        if !Path::new(&pathname).exists() {
+            return Err(Error::PackageIdFileNotFound {
+                package_name: package_name.to_string(),
+                path: pathname,
+            });
+        }
+
         let mut in_str = std::fs::read_to_string(&pathname).map_err(|io_error| {
             Error::PublishedDataAccessError {
                 package_name: package_name.to_string(),
@@ -110,29 +179,107 @@ impl SuibaseWorkdir {
 
         // Parse the expected hex string.
         let package_id =
-            ObjectID::from_hex_literal(package_id_hex).map_err(|_| Error::PackageIdInvalidHex {
+            SuibaseObjectId::from_str(package_id_hex).map_err(|_| Error::PackageIdInvalidHex {
                 id: package_id_hex.to_string(),
             })?;
         Ok(package_id)
     }
 
-    pub(crate) fn keystore_pathname(&self, root: &mut SuibaseRoot) -> Result<String, Error> {
+    // The directory of the last publication of a package (the "most-recent" symlink resolved).
+    pub(crate) fn published_dir(
+        &self,
+        root: &mut SuibaseRoot,
+        package_name: &str,
+    ) -> Result<PathBuf, Error> {
         if !root.is_installed() {
             return Err(Error::NotInstalled);
         }
 
+        if package_name.is_empty() {
+            return Err(Error::PackageNameEmpty);
+        }
+
+        if self.workdir_name.is_none() {
+            return Err(Error::WorkdirNameNotSet);
+        }
+        let workdir_name = self.workdir_name.as_ref().unwrap().to_string();
+
         if self.workdir_path.is_none() {
             return Err(Error::WorkdirPathNotSet);
         }
         let workdir_path = self.workdir_path.as_ref().unwrap().to_string();
 
+        // Check if the publication of package was done.
         let mut path_buf = PathBuf::from(workdir_path);
-        path_buf.push("config");
-        path_buf.push("sui");
-        path_buf.set_extension("keystore");
+        path_buf.push("published-data");
+        if self.is_cargobin() && !path_buf.is_dir() {
+            return Err(Error::UnsupportedForWorkdir {
+                workdir: workdir_name,
+                what: "published packages".to_string(),
+            });
+        }
+        path_buf.push(package_name);
+        path_buf.push("most-recent");
+
+        let symlink = std::fs::read_link(&path_buf);
+        if let Ok(symlink_target) = symlink {
+            // Do resolve the symlink portion as part of the original path.
+            let canonical_path = std::fs::canonicalize(&path_buf);
+            if let Ok(resolved_path) = canonical_path {
+                path_buf = resolved_path;
+            } else {
+                return Err(Error::PublishedDataAccessErrorInvalidSymlink {
+                    package_name: package_name.to_string(),
+                    path: path_buf.to_string_lossy().to_string(),
+                    symlink_target: symlink_target.to_string_lossy().to_string(),
+                });
+            }
+        } else {
+            return Err(Error::PublishedDataAccessErrorSymlinkNotFound {
+                package_name: package_name.to_string(),
+                path: path_buf.to_string_lossy().to_string(),
+            });
+        }
+
+        // Do an intermediate check to potentially error and provide a simplified advise
+        // to publish the package (versus raising an error specific to package-id.json file).
+        let published_path = path_buf.to_string_lossy().to_string();
+        let path_exists = if published_path.is_empty() {
+            false
+        } else {
+            Path::new(&published_path).exists()
+        };
+
+        if !path_exists {
+            return Err(Error::PublishedDataNotFound {
+                package_name: package_name.to_string(),
+                workdir: workdir_name,
+                path: published_path,
+            });
+        }
+
+        Ok(path_buf)
+    }
+
+    pub(crate) fn keystore_pathname(&self, root: &mut SuibaseRoot) -> Result<String, Error> {
+        if !root.is_installed() {
+            return Err(Error::NotInstalled);
+        }
+
+        let config_path = self.config_path(root)?;
+
+        // Use the keystore configured in client.yaml, otherwise default to config/sui.keystore
+        let keystore_file = match Self::get_keystore_from_client_config(&config_path) {
+            Some(keystore_file) => keystore_file,
+            None => {
+                let mut path_buf = config_path;
+                path_buf.push("sui");
+                path_buf.set_extension("keystore");
+                path_buf.to_string_lossy().to_string()
+            }
+        };
 
         // Suggest to run the Sui client if keystore does not exists.
-        let keystore_file = path_buf.to_string_lossy().to_string();
         let keystore_file_exists = if keystore_file.is_empty() {
             false
         } else {
@@ -152,7 +299,7 @@ impl SuibaseWorkdir {
         &self,
         root: &mut SuibaseRoot,
         object_type: &str,
-    ) -> Result<Vec<ObjectID>, Error> {
+    ) -> Result<Vec<SuibaseObjectId>, Error> {
         // Validate the parameter format.
         let mut names = vec![];
         for found in object_type.split("::") {
@@ -194,14 +341,12 @@ impl SuibaseWorkdir {
                         {
                             if let Some(objectid_field) = created_object.get("objectId") {
                                 if let Some(objectid_str) = objectid_field.as_str() {
-                                    objects.push(
-                                        ObjectID::from_hex_literal(objectid_str).map_err(|_| {
-                                            Error::PublishedNewObjectParseError {
-                                                path: pathname.to_string(),
-                                                id: objectid_str.to_string(),
-                                            }
-                                        })?,
-                                    );
+                                    objects.push(SuibaseObjectId::from_str(objectid_str).map_err(
+                                        |_| Error::PublishedNewObjectParseError {
+                                            path: pathname.to_string(),
+                                            id: objectid_str.to_string(),
+                                        },
+                                    )?);
                                 }
                             }
                         }
@@ -217,15 +362,22 @@ impl SuibaseWorkdir {
         &self,
         root: &mut SuibaseRoot,
         address_name: &str,
-    ) -> Result<SuiAddress, Error> {
+    ) -> Result<SuibaseAddress, Error> {
         // Validate the parameters.
         if address_name.is_empty() {
             return Err(Error::AddressNameEmpty);
         }
 
         if address_name == "active" {
-            // Different logic equivalent to "sui client active-address"
-            return self.get_client_active_address(root);
+            return self.client_active_address(root, false);
+        }
+
+        // The named addresses are created by suibase (not in the user's own sui client config).
+        if self.is_cargobin() {
+            return Err(Error::UnsupportedForWorkdir {
+                workdir: CARGOBIN.to_string(),
+                what: format!("address name '{}'", address_name),
+            });
         }
 
         let pathname: &str = &self.get_pathname_state(root, "dns")?;
@@ -245,7 +397,7 @@ impl SuibaseWorkdir {
             if let Some(known_item) = known.get(address_name) {
                 if let Some(address_v) = known_item.get("address") {
                     if let Some(address_str) = address_v.as_str() {
-                        return SuiAddress::from_str(address_str).map_err(|_| {
+                        return SuibaseAddress::from_str(address_str).map_err(|_| {
                             Error::WorkdirStateDNSParseError {
                                 path: pathname.to_string(),
                                 address: address_str.to_string(),
@@ -260,13 +412,104 @@ impl SuibaseWorkdir {
         })
     }
 
+    // Files used by the lookups (for the HelperCache). Best effort, the lookup itself
+    // reports any problem with these.
+    pub(crate) fn published_file_watched(
+        &self,
+        package_name: &str,
+        file_name: &str,
+    ) -> Vec<PathBuf> {
+        match &self.workdir_path {
+            Some(workdir_path) => {
+                let most_recent = PathBuf::from(workdir_path)
+                    .join("published-data")
+                    .join(package_name)
+                    .join("most-recent");
+                let file = most_recent.join(file_name).with_extension("json");
+                vec![most_recent, file]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub(crate) fn client_config_watched(&self, root: &SuibaseRoot) -> Vec<PathBuf> {
+        match self.config_path(root) {
+            // The directory is for a keystore created/removed.
+            Ok(config_path) => vec![
+                config_path.join("client.yaml"),
+                config_path.join("sui.keystore"),
+                config_path,
+            ],
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub(crate) fn state_file_watched(&self, state_name: &str) -> Vec<PathBuf> {
+        match &self.workdir_path {
+            Some(workdir_path) => vec![PathBuf::from(workdir_path).join(".state").join(state_name)],
+            None => Vec::new(),
+        }
+    }
+
+    // The cargobin workdir has no links, so these are from the active env of client.yaml.
+    //
+    // Otherwise, the endpoint of the running daemon (see daemon-endpoints.json) is
+    // preferred over the primary link.
     pub(crate) fn rpc_url(&self, root: &mut SuibaseRoot) -> Result<String, Error> {
+        if self.is_cargobin() {
+            return self.client_rpc_url(root);
+        }
+        if let Some(url) = self.daemon_endpoint_url(root, ENDPOINT_PURPOSE_PROXY) {
+            return Ok(url);
+        }
         self.get_url_from_state(root, "rpc")
     }
 
     pub(crate) fn ws_url(&self, root: &mut SuibaseRoot) -> Result<String, Error> {
+        if self.is_cargobin() {
+            let (workdir_name, data) = self.load_client_config(root)?;
+            let env = Self::get_client_active_env(&data).ok_or(Error::ConfigReadError {
+                workdir: workdir_name.clone(),
+            })?;
+            // Optional in client.yaml, then on the same host/port as the RPC.
+            return match (env["ws"].as_str(), env["rpc"].as_str()) {
+                (Some(ws), _) => Ok(ws.to_string()),
+                (None, Some(rpc)) => Ok(ws_url_from_rpc(rpc)),
+                (None, None) => Err(Error::ConfigReadError {
+                    workdir: workdir_name,
+                }),
+            };
+        }
+        if let Some(url) = self.daemon_endpoint_url(root, ENDPOINT_PURPOSE_WS) {
+            return Ok(url);
+        }
         self.get_url_from_state(root, "ws")
     }
+
+    fn daemon_endpoint_url(&self, root: &SuibaseRoot, purpose: &str) -> Option<String> {
+        let workdir_name = self.workdir_name.as_deref()?;
+        root.daemon_endpoints()?
+            .fresh_url(Some(workdir_name), purpose)
+            .map(|url| url.to_string())
+    }
+
+    // RPC URL of the active env in client.yaml.
+    //
+    // Suibase configures it to be the workdir proxy (e.g. http://localhost:44340).
+    pub(crate) fn client_rpc_url(&self, root: &mut SuibaseRoot) -> Result<String, Error> {
+        let (workdir_name, data) = self.load_client_config(root)?;
+        Self::get_client_active_env(&data)
+            .and_then(|env| env["rpc"].as_str())
+            .map(|rpc| match root.active_proxy_port(&workdir_name) {
+                // client.yaml may still have the configured proxy port while the daemon
+                // listens on another one (see active-ports.yaml).
+                Some(active_port) => with_proxy_port(rpc, active_port),
+                None => rpc.to_string(),
+            })
+            .ok_or(Error::ConfigReadError {
+                workdir: workdir_name,
+            })
+    }
 }
 
 impl SuibaseWorkdir {
@@ -280,73 +523,11 @@ impl SuibaseWorkdir {
         file_name: &str,
         extension: &str,
     ) -> Result<String, Error> {
-        // Build pathname and do some error detections.
-
-        if !root.is_installed() {
-            return Err(Error::NotInstalled);
-        }
-
-        if package_name.is_empty() {
-            return Err(Error::PackageNameEmpty);
-        }
-
         if file_name.is_empty() {
             return Err(Error::FileNameEmpty);
         }
 
-        if self.workdir_name.is_none() {
-            return Err(Error::WorkdirNameNotSet);
-        }
-        let workdir_name = self.workdir_name.as_ref().unwrap().to_string();
-
-        if self.workdir_path.is_none() {
-            return Err(Error::WorkdirPathNotSet);
-        }
-        let workdir_path = self.workdir_path.as_ref().unwrap().to_string();
-
-        // Check if the publication of package was done.
-        let mut path_buf = PathBuf::from(workdir_path);
-        path_buf.push("published-data");
-        path_buf.push(package_name);
-        path_buf.push("most-recent");
-
-        let symlink = std::fs::read_link(&path_buf);
-        if let Ok(symlink_target) = symlink {
-            // Do resolve the symlink portion as part of the original path.
-            let canonical_path = std::fs::canonicalize(&path_buf);
-            if let Ok(resolved_path) = canonical_path {
-                path_buf = resolved_path;
-            } else {
-                return Err(Error::PublishedDataAccessErrorInvalidSymlink {
-                    package_name: package_name.to_string(),
-                    path: path_buf.to_string_lossy().to_string(),
-                    symlink_target: symlink_target.to_string_lossy().to_string(),
-                });
-            }
-        } else {
-            return Err(Error::PublishedDataAccessErrorSymlinkNotFound {
-                package_name: package_name.to_string(),
-                path: path_buf.to_string_lossy().to_string(),
-            });
-        }
-
-        // Do an intermediate check to potentially error and provide a simplified advise
-        // to publish the package (versus raising an error specific to package-id.json file).
-        let published_path = path_buf.to_string_lossy().to_string();
-        let path_exists = if published_path.is_empty() {
-            false
-        } else {
-            Path::new(&published_path).exists()
-        };
-
-        if !path_exists {
-            return Err(Error::PublishedDataNotFound {
-                package_name: package_name.to_string(),
-                workdir: workdir_name,
-                path: published_path,
-            });
-        }
-
+        let mut path_buf = self.published_dir(root, package_name)?;
         path_buf.push(file_name);
         path_buf.set_extension(extension);
 
@@ -483,7 +664,182 @@ impl SuibaseWorkdir {
         })
     }
 
-    fn get_client_active_address(&self, root: &mut SuibaseRoot) -> Result<SuiAddress, Error> {
+    fn is_cargobin(&self) -> bool {
+        self.workdir_name.as_deref() == Some(CARGOBIN)
+    }
+
+    // The directory with client.yaml and the keystore.
+    //
+    // Normally <workdir>/config. For cargobin, the scripts create it as a symlink to
+    // ~/.sui/sui_config, which is used directly while the symlink does not exist.
+    fn config_path(&self, root: &SuibaseRoot) -> Result<PathBuf, Error> {
+        if self.workdir_path.is_none() {
+            return Err(Error::WorkdirPathNotSet);
+        }
+        let config_path = PathBuf::from(self.workdir_path.as_ref().unwrap()).join("config");
+        if self.is_cargobin() && !config_path.exists() {
+            return Ok(root.home_path().join(CARGOBIN_DEFAULT_CONFIG));
+        }
+        Ok(config_path)
+    }
+
+    // The env matching "active_env" (otherwise the first one).
+    fn get_client_active_env(data: &YamlValue) -> Option<&YamlValue> {
+        let active_env = data["active_env"].as_str().unwrap_or_default();
+        data["envs"].as_sequence().and_then(|envs| {
+            envs.iter()
+                .find(|env| env["alias"].as_str() == Some(active_env))
+                .or_else(|| envs.first())
+        })
+    }
+
+    fn get_keystore_from_client_config(config_path: &Path) -> Option<String> {
+        // Best effort. Any problem reading client.yaml is reported elsewhere.
+        //
+        // Expected format:
+        //   keystore:
+        //     File: /home/johndoe/suibase/workdirs/localnet/config/sui.keystore
+        let mut path_buf = config_path.to_path_buf();
+        path_buf.push("client");
+        path_buf.set_extension("yaml");
+        let file = File::open(path_buf).ok()?;
+        let data: YamlValue = serde_yaml::from_reader(BufReader::new(file)).ok()?;
+        let keystore_file = data["keystore"]["File"].as_str()?.trim();
+        if keystore_file.is_empty() {
+            return None;
+        }
+        Some(keystore_file.to_string())
+    }
+
+    // Same as "sui client active-address", but only from the files (the sui binary
+    // does not have to be installed):
+    //   - the "active_address" of client.yaml.
+    //   - otherwise (e.g. "~" in the user's own ~/.sui config, or no client.yaml yet)
+    //     the first address of the keystore.
+    //
+    // With 'sui_keys_fallback', a keystore that cannot be read is loaded again with
+    // sui-keys (same as the sui client).
+    pub(crate) fn client_active_address(
+        &self,
+        root: &mut SuibaseRoot,
+        sui_keys_fallback: bool,
+    ) -> Result<SuibaseAddress, Error> {
+        let setting = if self.config_path(root)?.join("client.yaml").exists() {
+            self.client_active_address_setting(root)?
+        } else {
+            None
+        };
+        match setting {
+            Some(sui_address) => Ok(sui_address), // Success!
+            None => self.get_keystore_first_address(root, sui_keys_fallback),
+        }
+    }
+
+    // The "active_address" YAML field of client.yaml (None when not set).
+    pub(crate) fn client_active_address_setting(
+        &self,
+        root: &mut SuibaseRoot,
+    ) -> Result<Option<SuibaseAddress>, Error> {
+        let (_, data) = self.load_client_config(root)?;
+        let active_addr: &str = match data["active_address"].as_str() {
+            Some(active_addr) => active_addr,
+            None => return Ok(None),
+        };
+        let sui_address = SuibaseAddress::from_str(active_addr).map_err(|_| {
+            Error::ConfigActiveAddressParseError {
+                address: active_addr.to_string(),
+            }
+        })?;
+        Ok(Some(sui_address))
+    }
+
+    // Every address named by suibase (e.g. "sb-1-ed25519"), sorted by name.
+    //
+    // Empty for cargobin, and while the workdir has no dns state yet.
+    pub(crate) fn named_addresses(
+        &self,
+        root: &mut SuibaseRoot,
+    ) -> Result<Vec<SuibaseAddress>, Error> {
+        if self.is_cargobin() {
+            return Ok(Vec::new());
+        }
+        let pathname: &str = &self.get_pathname_state(root, "dns")?;
+        let file = match File::open(pathname) {
+            Ok(file) => file,
+            Err(_) if !Path::new(pathname).exists() => return Ok(Vec::new()),
+            Err(_) => {
+                return Err(Error::WorkdirStateDNSAccessFailed {
+                    path: pathname.to_string(),
+                })
+            }
+        };
+        let top: HashMap<String, Value> =
+            serde_json::from_reader(BufReader::new(file)).map_err(|_| {
+                Error::WorkdirStateDNSReadError {
+                    path: pathname.to_string(),
+                }
+            })?;
+
+        let mut named: Vec<(&String, &str)> = top
+            .get("known")
+            .and_then(|known| known.as_object())
+            .map(|known| {
+                known
+                    .iter()
+                    .filter_map(|(name, item)| Some((name, item.get("address")?.as_str()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        named.sort();
+        named
+            .into_iter()
+            .map(|(_, address_str)| {
+                SuibaseAddress::from_str(address_str).map_err(|_| {
+                    Error::WorkdirStateDNSParseError {
+                        path: pathname.to_string(),
+                        address: address_str.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "sui-types")]
+    fn get_keystore_first_address(
+        &self,
+        root: &mut SuibaseRoot,
+        sui_keys_fallback: bool,
+    ) -> Result<SuibaseAddress, Error> {
+        let keystore_path = PathBuf::from(self.keystore_pathname(root)?);
+        let first_address = match keystore::first_address(&keystore_path) {
+            Ok(first_address) => first_address,
+            Err(_) if sui_keys_fallback => FileBasedKeystore::new(&keystore_path)
+                .ok()
+                .and_then(|keystore| keystore.addresses().first().copied()),
+            Err(e) => return Err(e),
+        };
+        first_address
+            .map(SuibaseAddress::from)
+            .ok_or(Error::ConfigActiveAddressParseError {
+                address: "<missing>".to_string(),
+            })
+    }
+
+    // Decoding the keys of the keystore requires sui-types, so only the active_address
+    // of client.yaml is available without the "sui-types" feature.
+    #[cfg(not(feature = "sui-types"))]
+    fn get_keystore_first_address(
+        &self,
+        _root: &mut SuibaseRoot,
+        _sui_keys_fallback: bool,
+    ) -> Result<SuibaseAddress, Error> {
+        Err(Error::ConfigActiveAddressParseError {
+            address: "<missing>".to_string(),
+        })
+    }
+
+    // Returns the workdir name and the parsed client.yaml.
+    fn load_client_config(&self, root: &mut SuibaseRoot) -> Result<(String, YamlValue), Error> {
         // Directly access and parse the client.yaml.
         if !root.is_installed() {
             return Err(Error::NotInstalled);
@@ -494,14 +850,8 @@ impl SuibaseWorkdir {
         }
         let workdir_name: &str = &self.workdir_name.as_ref().unwrap().to_string();
 
-        if self.workdir_path.is_none() {
-            return Err(Error::WorkdirPathNotSet);
-        }
-        let workdir_path = self.workdir_path.as_ref().unwrap().to_string();
-
         // Check if the config directory is available (and resolve symlinks)
-        let mut path_buf = PathBuf::from(workdir_path);
-        path_buf.push("config");
+        let mut path_buf = self.config_path(root)?;
         path_buf.push("client");
         path_buf.set_extension("yaml");
         path_buf = std::fs::canonicalize(path_buf).map_err(|_| Error::ConfigAccessError {
@@ -519,19 +869,327 @@ impl SuibaseWorkdir {
                 workdir: workdir_name.to_string(),
             })?;
 
-        // Try to parse the "active_address" YAML field
-        let active_addr: &str = &data["active_address"]
-            .as_str()
-            .map(|s| s.to_string())
-            .ok_or(Error::ConfigActiveAddressParseError {
-                address: "<missing>".to_string(),
-            })?;
-        let sui_address = SuiAddress::from_str(active_addr).map_err(|_| {
-            Error::ConfigActiveAddressParseError {
-                address: active_addr.to_string(),
+        Ok((workdir_name.to_string(), data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuibaseWorkdir;
+    use crate::error::Error;
+    use crate::suibase_root::SuibaseRoot;
+    use std::fs;
+    use std::path::Path;
+
+    // Create a minimal but valid workdir layout under <root>/workdirs/<name>
+    fn create_workdir(root: &Path, name: &str) {
+        let state = root.join("workdirs").join(name).join(".state");
+        fs::create_dir_all(&state).unwrap();
+        fs::write(state.join("name"), name).unwrap();
+        fs::write(state.join("user_request"), "stop").unwrap();
+    }
+
+    fn select(root: &Path, name: &str) -> Result<(SuibaseRoot, SuibaseWorkdir), Error> {
+        let mut sb = SuibaseRoot::with_suibase_path(root);
+        let mut wd = SuibaseWorkdir::new();
+        wd.init_from_existing(&mut sb, name)?;
+        Ok((sb, wd))
+    }
+
+    #[test]
+    fn test_workdirs_dir_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let res = select(tmp.path(), "localnet");
+        assert!(matches!(res, Err(Error::WorkdirsNotExists { .. })));
+    }
+
+    #[test]
+    fn test_workdir_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_workdir(tmp.path(), "devnet");
+        let res = select(tmp.path(), "localnet");
+        assert!(matches!(res, Err(Error::WorkdirNotExists)));
+    }
+
+    #[test]
+    fn test_workdir_state_incomplete() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workdir = tmp.path().join("workdirs/localnet");
+
+        // No .state at all.
+        fs::create_dir_all(&workdir).unwrap();
+        let res = select(tmp.path(), "localnet");
+        assert!(matches!(
+            res,
+            Err(Error::WorkdirInitializationIncomplete { .. })
+        ));
+
+        // .state present, but no user_request.
+        fs::create_dir_all(workdir.join(".state")).unwrap();
+        fs::write(workdir.join(".state/name"), "localnet").unwrap();
+        let res = select(tmp.path(), "localnet");
+        assert!(matches!(
+            res,
+            Err(Error::WorkdirInitializationIncomplete { .. })
+        ));
+
+        // user_request present, but no name.
+        fs::remove_file(workdir.join(".state/name")).unwrap();
+        fs::write(workdir.join(".state/user_request"), "stop").unwrap();
+        let res = select(tmp.path(), "localnet");
+        assert!(matches!(res, Err(Error::WorkdirStateNameAccessFailed)));
+
+        // Repaired.
+        fs::write(workdir.join(".state/name"), "localnet").unwrap();
+        let (_, wd) = select(tmp.path(), "localnet").unwrap();
+        assert_eq!(wd.get_name().unwrap(), "localnet");
+    }
+
+    #[test]
+    fn test_keystore_configured_but_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_workdir(tmp.path(), "localnet");
+        let config = tmp.path().join("workdirs/localnet/config");
+        fs::create_dir_all(&config).unwrap();
+        let keystore = config.join("custom.keystore");
+        fs::write(
+            config.join("client.yaml"),
+            format!("keystore:\n  File: {}\n", keystore.display()),
+        )
+        .unwrap();
+
+        let (mut sb, wd) = select(tmp.path(), "localnet").unwrap();
+        match wd.keystore_pathname(&mut sb) {
+            Err(Error::SuibaseKeystoreNotExists { path }) => {
+                assert_eq!(path, keystore.to_string_lossy())
             }
-        })?;
+            other => panic!("unexpected {:?}", other),
+        }
+
+        fs::write(&keystore, "[]").unwrap();
+        assert_eq!(
+            wd.keystore_pathname(&mut sb).unwrap(),
+            keystore.to_string_lossy()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_published_package_without_id_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_workdir(tmp.path(), "localnet");
+        let package = tmp.path().join("workdirs/localnet/published-data/demo");
+        fs::create_dir_all(package.join("1")).unwrap();
+
+        // No "most-recent" symlink yet.
+        let (mut sb, wd) = select(tmp.path(), "localnet").unwrap();
+        let res = wd.package_object_id(&mut sb, "demo");
+        assert!(matches!(
+            res,
+            Err(Error::PublishedDataAccessErrorSymlinkNotFound { .. })
+        ));
+
+        // Symlink to a publication directory without package-id.json
+        std::os::unix::fs::symlink(package.join("1"), package.join("most-recent")).unwrap();
+        let res = wd.package_object_id(&mut sb, "demo");
+        assert!(matches!(res, Err(Error::PackageIdFileNotFound { .. })));
+
+        // Invalid content is still detected.
+        fs::write(package.join("1/package-id.json"), "0x2").unwrap();
+        let res = wd.package_object_id(&mut sb, "demo");
+        assert!(matches!(res, Err(Error::PackageIdJsonInvalidFormat)));
+    }
+
+    // Publication of the demo-app package, as laid out by "localnet publish".
+    #[cfg(unix)]
+    #[test]
+    fn test_published_package_build_info() {
+        use crate::package_build;
+
+        let tmp = tempfile::tempdir().unwrap();
+        create_workdir(tmp.path(), "localnet");
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/published-data");
+        std::os::unix::fs::symlink(
+            &fixture,
+            tmp.path().join("workdirs/localnet/published-data"),
+        )
+        .unwrap();
+
+        let (mut sb, wd) = select(tmp.path(), "localnet").unwrap();
+        let published_dir = wd.published_dir(&mut sb, "demo").unwrap();
+        assert!(published_dir.ends_with("HPDM7J4PRD6OTJTGEMMPTFN2TM/1727712345678"));
+
+        let info = package_build::read_build_info("demo", &published_dir).unwrap();
+        assert_eq!(info.build_timestamp_ms, Some(1727712345678));
+        assert_eq!(
+            info.compiler_version.as_deref(),
+            Some("sui 1.34.0-3b2a1c5d9e8f")
+        );
+        assert_eq!(
+            info.git_commit.as_deref(),
+            Some("9f1c2b7e4a6d8c0b3e5f7a9d1c2b4e6f8a0c2d4e")
+        );
+        assert_eq!(
+            info.source_path.as_deref(),
+            Some("/home/user/suibase/rust/demo-app/move")
+        );
+        assert!(info.source_digest.unwrap().starts_with("3E8A1B0F"));
+        assert_eq!(info.build_flags["dev_mode"], "false");
+        assert_eq!(info.build_flags["default_flavor"], "sui");
+        assert!(!info.build_flags.contains_key("lint_flag"));
+        assert_eq!(info.dependencies, vec!["Log", "MoveStdlib", "Sui"]);
+
+        // The dependencies are not modules of the package.
+        let modules = package_build::read_modules("demo", &published_dir).unwrap();
+        assert_eq!(modules, vec!["Counter"]);
+
+        assert!(matches!(
+            wd.published_dir(&mut sb, "unknown"),
+            Err(Error::PublishedDataAccessErrorSymlinkNotFound { .. })
+        ));
+    }
+
+    // A cargobin workdir using the user's own sui client config (<home>/.sui/sui_config),
+    // with suibase installed in <home>/suibase.
+    fn create_cargobin(home: &Path, config: &Path, active_address: &str) {
+        create_workdir(&home.join("suibase"), "cargobin");
+        fs::create_dir_all(config).unwrap();
+        let keystore = config.join("sui.keystore");
+        fs::write(&keystore, "[]").unwrap();
+        fs::write(
+            config.join("client.yaml"),
+            format!(
+                "keystore:\n\
+                 \x20 File: {}\n\
+                 envs:\n\
+                 \x20 - alias: devnet\n\
+                 \x20   rpc: \"https://fullnode.devnet.sui.io:443\"\n\
+                 \x20   ws: \"wss://fullnode.devnet.sui.io:443/ws\"\n\
+                 \x20 - alias: local\n\
+                 \x20   rpc: \"http://127.0.0.1:9000\"\n\
+                 \x20   ws: ~\n\
+                 active_env: local\n\
+                 active_address: {}\n",
+                keystore.display(),
+                active_address
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_cargobin() {
+        use crate::sui_ids::SuibaseAddress;
+        use std::str::FromStr;
+
+        let home = tempfile::tempdir().unwrap();
+        let config = home.path().join(".sui/sui_config");
+        let address = "0x2c6f4e8a5d7cb3f1e0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7";
+        create_cargobin(home.path(), &config, &format!("\"{}\"", address));
+
+        let (mut sb, wd) = select(&home.path().join("suibase"), "cargobin").unwrap();
+        assert_eq!(wd.get_name().unwrap(), "cargobin");
+        assert_eq!(
+            wd.keystore_pathname(&mut sb).unwrap(),
+            config.join("sui.keystore").to_string_lossy()
+        );
+        assert_eq!(
+            wd.client_sui_address(&mut sb, "active").unwrap(),
+            SuibaseAddress::from_str(address).unwrap()
+        );
+
+        // From the active env (no ws configured, so same host/port as the rpc).
+        assert_eq!(wd.rpc_url(&mut sb).unwrap(), "http://127.0.0.1:9000");
+        assert_eq!(wd.ws_url(&mut sb).unwrap(), "ws://127.0.0.1:9000");
+
+        // Not maintained by suibase for cargobin.
+        assert!(matches!(
+            wd.client_sui_address(&mut sb, "sb-1-ed25519"),
+            Err(Error::UnsupportedForWorkdir { .. })
+        ));
+        assert!(matches!(
+            wd.package_object_id(&mut sb, "demo"),
+            Err(Error::UnsupportedForWorkdir { .. })
+        ));
+        assert!(matches!(
+            wd.published_new_object_ids(&mut sb, "demo::tools::Anvil"),
+            Err(Error::UnsupportedForWorkdir { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cargobin_config_symlink() {
+        let home = tempfile::tempdir().unwrap();
+
+        // The user pointed the cargobin config elsewhere (not ~/.sui/sui_config).
+        let config = home.path().join("my-sui-config");
+        create_cargobin(home.path(), &config, "~");
+        let workdir = home.path().join("suibase/workdirs/cargobin");
+        std::os::unix::fs::symlink(&config, workdir.join("config")).unwrap();
+        let client_yaml = config.join("client.yaml");
+        let contents = fs::read_to_string(&client_yaml).unwrap();
+        fs::write(
+            &client_yaml,
+            contents.replace("active_env: local", "active_env: devnet"),
+        )
+        .unwrap();
+
+        let (mut sb, wd) = select(&home.path().join("suibase"), "cargobin").unwrap();
+        assert_eq!(
+            wd.keystore_pathname(&mut sb).unwrap(),
+            config.join("sui.keystore").to_string_lossy()
+        );
+        assert_eq!(
+            wd.rpc_url(&mut sb).unwrap(),
+            "https://fullnode.devnet.sui.io:443"
+        );
+        assert_eq!(
+            wd.ws_url(&mut sb).unwrap(),
+            "wss://fullnode.devnet.sui.io:443/ws"
+        );
+
+        // No active_address and an empty keystore.
+        assert!(matches!(
+            wd.client_sui_address(&mut sb, "active"),
+            Err(Error::ConfigActiveAddressParseError { .. })
+        ));
+
+        // A published-data directory is used like for the other workdirs.
+        fs::create_dir_all(workdir.join("published-data")).unwrap();
+        assert!(matches!(
+            wd.package_object_id(&mut sb, "demo"),
+            Err(Error::PublishedDataAccessErrorSymlinkNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_with_proxy_port() {
+        use super::with_proxy_port;
+        assert_eq!(
+            with_proxy_port("http://localhost:44340", 44345),
+            "http://localhost:44345"
+        );
+        assert_eq!(
+            with_proxy_port("http://127.0.0.1:44342/", 44343),
+            "http://127.0.0.1:44343/"
+        );
+        assert_eq!(
+            with_proxy_port("https://fullnode.testnet.sui.io:443", 44343),
+            "https://fullnode.testnet.sui.io:443"
+        );
+    }
 
-        Ok(sui_address) // Success!
+    #[test]
+    fn test_ws_url_from_rpc() {
+        use super::ws_url_from_rpc;
+        assert_eq!(
+            ws_url_from_rpc("http://127.0.0.1:9000"),
+            "ws://127.0.0.1:9000"
+        );
+        assert_eq!(
+            ws_url_from_rpc("https://fullnode.devnet.sui.io:443"),
+            "wss://fullnode.devnet.sui.io:443"
+        );
     }
 }
@@ -0,0 +1,160 @@
+// Lookup of an executed transaction by digest (e.g. one done with the sui client).
+//
+// Done with sui_getTransactionBlock on the workdir proxy. The transaction may not
+// be indexed yet right after its execution (most often on localnet), so a
+// "not found" is retried for a moment before being reported.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+use crate::sui_ids::SuibaseObjectId;
+use crate::suibase_daemon_api::rpc_call;
+
+const TX_LOOKUP_ATTEMPTS: u32 = 10;
+const TX_LOOKUP_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+// Part of the Sui RPC error message for an unknown digest.
+const TX_NOT_FOUND_MSG: &str = "Could not find the referenced transaction";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxStatus {
+    pub digest: String,
+    pub success: bool,
+    pub error: Option<String>, // Set on failure (e.g. a MoveAbort).
+}
+
+fn get_transaction_block(
+    rpc_url: &str,
+    digest: &str,
+    options: JsonValue,
+) -> Result<JsonValue, Error> {
+    if digest.is_empty() {
+        return Err(Error::TransactionDigestEmpty);
+    }
+    let params = serde_json::json!([digest, options]);
+    let mut attempt = 1;
+    loop {
+        match rpc_call(rpc_url, "sui_getTransactionBlock", params.clone()) {
+            Err(Error::RpcRequestError { msg, .. }) if msg.contains(TX_NOT_FOUND_MSG) => {
+                if attempt >= TX_LOOKUP_ATTEMPTS {
+                    return Err(Error::TransactionNotFound {
+                        digest: digest.to_string(),
+                    });
+                }
+                attempt += 1;
+                std::thread::sleep(TX_LOOKUP_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(crate) fn tx_created_objects(
+    rpc_url: &str,
+    digest: &str,
+) -> Result<HashMap<String, Vec<SuibaseObjectId>>, Error> {
+    let options = serde_json::json!({ "showObjectChanges": true });
+    let result = get_transaction_block(rpc_url, digest, options)?;
+    parse_created_objects(&result)
+}
+
+pub(crate) fn tx_status(rpc_url: &str, digest: &str) -> Result<TxStatus, Error> {
+    let options = serde_json::json!({ "showEffects": true });
+    let result = get_transaction_block(rpc_url, digest, options)?;
+    parse_tx_status(digest, &result)
+}
+
+// Created object IDs grouped by their full type tag (e.g. "0x2::coin::Coin<0x2::sui::SUI>").
+pub(crate) fn parse_created_objects(
+    result: &JsonValue,
+) -> Result<HashMap<String, Vec<SuibaseObjectId>>, Error> {
+    let mut created: HashMap<String, Vec<SuibaseObjectId>> = HashMap::new();
+    let changes = result["objectChanges"].as_array().into_iter().flatten();
+    for change in changes.filter(|change| change["type"].as_str() == Some("created")) {
+        let object_type = change["objectType"].as_str().unwrap_or_default();
+        let object_id = change["objectId"].as_str().unwrap_or_default();
+        let object_id: SuibaseObjectId = object_id.parse().map_err(|_| Error::RpcRequestError {
+            method: "sui_getTransactionBlock".to_string(),
+            msg: format!("invalid objectId {}", object_id),
+        })?;
+        created
+            .entry(object_type.to_string())
+            .or_default()
+            .push(object_id);
+    }
+    Ok(created)
+}
+
+pub(crate) fn parse_tx_status(digest: &str, result: &JsonValue) -> Result<TxStatus, Error> {
+    let status = &result["effects"]["status"];
+    match status["status"].as_str() {
+        Some("success") => Ok(TxStatus {
+            digest: digest.to_string(),
+            success: true,
+            error: None,
+        }),
+        Some(status_str) => Ok(TxStatus {
+            digest: digest.to_string(),
+            success: false,
+            error: Some(status["error"].as_str().unwrap_or(status_str).to_string()),
+        }),
+        None => Err(Error::RpcRequestError {
+            method: "sui_getTransactionBlock".to_string(),
+            msg: format!("missing effects status in {}", result),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_created_objects() {
+        let coin = "0x2::coin::Coin<0x2::sui::SUI>";
+        let result = serde_json::json!({
+            "digest": "5xyz",
+            "objectChanges": [
+                { "type": "mutated", "objectId": "0x5", "objectType": coin },
+                { "type": "created", "objectId": "0x6", "objectType": "0x9::Counter::Counter" },
+                { "type": "created", "objectId": "0x7", "objectType": coin },
+                { "type": "created", "objectId": "0x8", "objectType": "0x9::Counter::Counter" },
+                { "type": "published", "packageId": "0x9" }
+            ]
+        });
+        let created = parse_created_objects(&result).unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(
+            created["0x9::Counter::Counter"],
+            vec![
+                "0x6".parse::<SuibaseObjectId>().unwrap(),
+                "0x8".parse::<SuibaseObjectId>().unwrap()
+            ]
+        );
+        assert_eq!(created[coin].len(), 1);
+
+        // Nothing created (e.g. showObjectChanges not supported).
+        let created = parse_created_objects(&serde_json::json!({ "digest": "5xyz" })).unwrap();
+        assert!(created.is_empty());
+    }
+
+    #[test]
+    fn test_parse_tx_status() {
+        let result = serde_json::json!({ "effects": { "status": { "status": "success" } } });
+        let status = parse_tx_status("5xyz", &result).unwrap();
+        assert!(status.success);
+        assert_eq!(status.error, None);
+
+        let result = serde_json::json!({
+            "effects": { "status": { "status": "failure", "error": "MoveAbort(..., 1)" } }
+        });
+        let status = parse_tx_status("5xyz", &result).unwrap();
+        assert!(!status.success);
+        assert_eq!(status.error.as_deref(), Some("MoveAbort(..., 1)"));
+
+        assert!(parse_tx_status("5xyz", &serde_json::json!({})).is_err());
+    }
+}
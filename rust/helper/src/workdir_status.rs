@@ -0,0 +1,363 @@
+// Whether the services of a workdir are up (see Helper::workdir_status).
+//
+// The suibase-daemon already monitors every workdir, so its getWorkdirStatus is used
+// when the daemon is reachable. Otherwise, the fullnode (primary link) and the proxy
+// are probed directly with a short timeout. The faucet is reported only by the daemon.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+use crate::suibase_daemon_api::{json_rpc_call, parse_http_url, RpcFailure, DAEMON_PORT};
+use crate::suibase_root::SuibaseRoot;
+use crate::suibase_workdir::SuibaseWorkdir;
+
+const DAEMON_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Cheapest request supported by every Sui node.
+const PROBE_METHOD: &str = "sui_getChainIdentifier";
+
+// Delay between two status checks of wait_until_up().
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkdirService {
+    pub name: String, // "node", "faucet" or "proxy"
+    pub up: bool,
+    pub info: Option<String>, // Details when down (e.g. "NOT RUNNING").
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkdirStatus {
+    pub workdir: String,
+    pub up: bool,          // The node (or the multi-link RPC of a remote network) is up.
+    pub from_daemon: bool, // false when determined by probing the URLs directly.
+    pub latency_ms: u64,   // Round-trip of the daemon request or of the node probe.
+    pub services: Vec<WorkdirService>,
+}
+
+impl WorkdirStatus {
+    pub fn service(&self, name: &str) -> Option<&WorkdirService> {
+        self.services.iter().find(|service| service.name == name)
+    }
+}
+
+fn as_opt_string(value: &JsonValue) -> Option<String> {
+    value.as_str().map(|s| s.to_string())
+}
+
+// Service name for a label of "<workdir> status" (as relayed by the daemon).
+fn service_name(label: &str) -> Option<&'static str> {
+    match label {
+        "Localnet process" | "Multi-link RPC" => Some("node"),
+        "Faucet process" => Some("faucet"),
+        "Proxy server" => Some("proxy"),
+        _ => None,
+    }
+}
+
+// None when the daemon does not know (yet) the services of the workdir.
+pub(crate) fn parse_daemon_status(
+    workdir: &str,
+    result: &JsonValue,
+    latency: Duration,
+) -> Option<WorkdirStatus> {
+    let services: Vec<WorkdirService> = result["services"]
+        .as_array()?
+        .iter()
+        .filter_map(|service| {
+            let name = service_name(service["label"].as_str()?)?;
+            let status = service["status"].as_str().unwrap_or_default();
+            let up = status == "OK" || status == "DEGRADED";
+            Some(WorkdirService {
+                name: name.to_string(),
+                up,
+                info: as_opt_string(&service["statusInfo"])
+                    .or_else(|| (!up && !status.is_empty()).then(|| status.to_string())),
+            })
+        })
+        .collect();
+    let up = services.iter().find(|service| service.name == "node")?.up;
+    Some(WorkdirStatus {
+        workdir: workdir.to_string(),
+        up,
+        from_daemon: true,
+        latency_ms: latency.as_millis() as u64,
+        services,
+    })
+}
+
+pub(crate) fn daemon_workdir_status(
+    api_port: u16,
+    workdir: &str,
+) -> Result<Option<WorkdirStatus>, Error> {
+    let method = "getWorkdirStatus";
+    let start = Instant::now();
+    let result = json_rpc_call(
+        "127.0.0.1",
+        api_port,
+        method,
+        serde_json::json!({ "workdir": workdir }),
+        DAEMON_STATUS_TIMEOUT,
+    )
+    .map_err(|failure| match failure {
+        RpcFailure::Connect => Error::DaemonNotRunning,
+        RpcFailure::Request(msg) => Error::DaemonRequestError {
+            method: method.to_string(),
+            msg,
+        },
+    })?;
+    Ok(parse_daemon_status(workdir, &result, start.elapsed()))
+}
+
+// Up when the URL answers a JSON-RPC request. Also returns the round-trip time.
+fn probe(name: &str, url: Option<&str>) -> (WorkdirService, Duration) {
+    let start = Instant::now();
+    let outcome = match url.and_then(parse_http_url) {
+        Some((host, port)) => json_rpc_call(
+            &host,
+            port,
+            PROBE_METHOD,
+            serde_json::json!([]),
+            PROBE_TIMEOUT,
+        )
+        .map(|_| ())
+        .map_err(|failure| match failure {
+            RpcFailure::Connect => "not responding".to_string(),
+            RpcFailure::Request(msg) => msg,
+        }),
+        None => Err(match url {
+            Some(url) => format!("cannot probe {}", url),
+            None => "unknown URL".to_string(),
+        }),
+    };
+    let service = WorkdirService {
+        name: name.to_string(),
+        up: outcome.is_ok(),
+        info: outcome.err(),
+    };
+    (service, start.elapsed())
+}
+
+pub(crate) fn probe_workdir_status(
+    workdir: &str,
+    node_url: Option<&str>,
+    proxy_url: Option<&str>,
+) -> WorkdirStatus {
+    let (node, latency) = probe("node", node_url);
+    let (proxy, _) = probe("proxy", proxy_url);
+    WorkdirStatus {
+        workdir: workdir.to_string(),
+        up: node.up,
+        from_daemon: false,
+        latency_ms: latency.as_millis() as u64,
+        services: vec![node, proxy],
+    }
+}
+
+pub(crate) fn workdir_status(
+    root: &mut SuibaseRoot,
+    wd: &SuibaseWorkdir,
+) -> Result<WorkdirStatus, Error> {
+    let workdir = wd.get_name()?;
+    let api_port = root.active_api_port().unwrap_or(DAEMON_PORT);
+    if let Ok(Some(status)) = daemon_workdir_status(api_port, &workdir) {
+        return Ok(status);
+    }
+    let node_url = wd.rpc_url(root).ok();
+    let proxy_url = wd.client_rpc_url(root).ok();
+    Ok(probe_workdir_status(
+        &workdir,
+        node_url.as_deref(),
+        proxy_url.as_deref(),
+    ))
+}
+
+// Repeat get_status() until the workdir is up, for up to timeout.
+pub(crate) fn wait_until_up<F>(timeout: Duration, mut get_status: F) -> Result<WorkdirStatus, Error>
+where
+    F: FnMut() -> Result<WorkdirStatus, Error>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        let status = get_status()?;
+        if status.up {
+            return Ok(status);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::WorkdirDown {
+                workdir: status.workdir,
+            });
+        }
+        std::thread::sleep(READY_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    // Stand-in for the daemon or a Sui node: answers every request with 'result'.
+    fn stub_server(result: JsonValue) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !String::from_utf8_lossy(&request).contains("\"params\"") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let body =
+                    serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        port
+    }
+
+    // A port with nothing listening.
+    fn closed_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    fn localnet_with_api_port(tmp: &std::path::Path, api_port: u16, node_url: &str) {
+        let workdir = tmp.join("workdirs/localnet");
+        fs::create_dir_all(workdir.join(".state")).unwrap();
+        fs::write(workdir.join(".state/name"), "localnet").unwrap();
+        fs::write(workdir.join(".state/user_request"), "start").unwrap();
+        fs::write(
+            workdir.join(".state/links"),
+            format!(
+                "{{\"selection\":{{\"primary\":1}},\"links\":[{{\"id\":1,\"rpc\":\"{}\"}}]}}",
+                node_url
+            ),
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.join("workdirs/common")).unwrap();
+        fs::write(
+            tmp.join("workdirs/common/active-ports.yaml"),
+            format!("api_port: {}\n", api_port),
+        )
+        .unwrap();
+    }
+
+    fn daemon_result(localnet_status: &str) -> JsonValue {
+        serde_json::json!({
+            "header": { "method": "getWorkdirStatus", "key": "localnet" },
+            "status": if localnet_status == "OK" { "OK" } else { "DOWN" },
+            "services": [
+                { "label": "Localnet process", "status": localnet_status },
+                { "label": "Faucet process", "status": "OK", "helpInfo": "http://0.0.0.0:9123" },
+                { "label": "Proxy server", "status": "OK" },
+                { "label": "Multi-link RPC", "status": "OK" }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_parse_daemon_status() {
+        let latency = Duration::from_millis(3);
+        let status = parse_daemon_status("localnet", &daemon_result("OK"), latency).unwrap();
+        assert!(status.up && status.from_daemon);
+        assert_eq!(status.latency_ms, 3);
+        assert!(status.service("faucet").unwrap().up);
+        assert!(status.service("proxy").unwrap().up);
+
+        let result = serde_json::json!({
+            "services": [
+                { "label": "Localnet process", "status": "NOT RUNNING" },
+                { "label": "Proxy server", "status": "DOWN", "statusInfo": "port in use" }
+            ]
+        });
+        let status = parse_daemon_status("localnet", &result, latency).unwrap();
+        assert!(!status.up);
+        let node = status.service("node").unwrap();
+        assert_eq!(node.info.as_deref(), Some("NOT RUNNING"));
+        let proxy = status.service("proxy").unwrap();
+        assert_eq!(proxy.info.as_deref(), Some("port in use"));
+        assert_eq!(status.service("faucet"), None);
+
+        // Not polled yet by the daemon.
+        let result = serde_json::json!({ "header": { "method": "getWorkdirStatus" } });
+        assert_eq!(parse_daemon_status("localnet", &result, latency), None);
+    }
+
+    #[test]
+    fn test_status_from_daemon() {
+        let tmp = tempfile::tempdir().unwrap();
+        let api_port = stub_server(daemon_result("DOWN"));
+        localnet_with_api_port(tmp.path(), api_port, "http://127.0.0.1:9000");
+
+        let mut root = SuibaseRoot::with_suibase_path(tmp.path());
+        let mut wd = SuibaseWorkdir::new();
+        wd.init_from_existing(&mut root, "localnet").unwrap();
+
+        let status = workdir_status(&mut root, &wd).unwrap();
+        assert!(status.from_daemon);
+        assert!(!status.up);
+        assert!(!status.service("node").unwrap().up);
+
+        let err = wait_until_up(Duration::from_millis(100), || {
+            workdir_status(&mut root, &wd)
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::WorkdirDown { .. }));
+        assert_eq!(
+            err.to_string(),
+            "suibase: localnet is DOWN — run 'localnet start'"
+        );
+    }
+
+    #[test]
+    fn test_status_from_probe() {
+        let tmp = tempfile::tempdir().unwrap();
+        let node_port = stub_server(serde_json::json!("4c78adac"));
+        let node_url = format!("http://127.0.0.1:{}", node_port);
+        localnet_with_api_port(tmp.path(), closed_port(), &node_url);
+
+        let mut root = SuibaseRoot::with_suibase_path(tmp.path());
+        let mut wd = SuibaseWorkdir::new();
+        wd.init_from_existing(&mut root, "localnet").unwrap();
+
+        // No daemon, and no client.yaml for the proxy URL.
+        let status =
+            wait_until_up(Duration::from_secs(1), || workdir_status(&mut root, &wd)).unwrap();
+        assert!(!status.from_daemon);
+        assert!(status.up);
+        let proxy = status.service("proxy").unwrap();
+        assert!(!proxy.up);
+        assert_eq!(proxy.info.as_deref(), Some("unknown URL"));
+
+        let status = probe_workdir_status("localnet", Some("http://127.0.0.1:1"), None);
+        assert!(!status.up);
+        assert_eq!(
+            status.service("node").unwrap().info.as_deref(),
+            Some("not responding")
+        );
+        let status = probe_workdir_status("testnet", Some("https://rpc.example.com"), None);
+        assert!(!status.up);
+    }
+}
@@ -0,0 +1,200 @@
+// Run the suibase-helper-cli binary against a fixture ~/suibase (HOME set to a temp
+// directory), so these tests do not need an installed suibase.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use suibase::{
+    ClientAddressOutput, Error, ErrorOutput, KeystorePathOutput, PackageIdOutput,
+    PublishedObjectsOutput, RpcUrlOutput, WorkdirOutput, EXIT_CODE_USAGE,
+};
+
+const PACKAGE_ID: &str = "0x00000000000000000000000000000000000000000000000000000000000000a1";
+const COUNTER_ID: &str = "0x00000000000000000000000000000000000000000000000000000000000000c1";
+const ADDRESS: &str = "0x00000000000000000000000000000000000000000000000000000000000000d1";
+const RPC_URL: &str = "http://localhost:44340";
+
+// A localnet (also the active workdir) with the state files and one published package.
+fn create_fixture(home: &Path) {
+    let workdirs = home.join("suibase/workdirs");
+    let localnet = workdirs.join("localnet");
+    let state = localnet.join(".state");
+    fs::create_dir_all(&state).unwrap();
+    fs::write(state.join("name"), "localnet").unwrap();
+    fs::write(state.join("user_request"), "start").unwrap();
+    fs::write(
+        state.join("links"),
+        format!(
+            r#"{{"selection":{{"primary":1}},"links":[{{"id":1,"rpc":"{}"}}]}}"#,
+            RPC_URL
+        ),
+    )
+    .unwrap();
+    fs::write(
+        state.join("dns"),
+        format!(
+            r#"{{"known":{{"sb-1-ed25519":{{"address":"{}"}}}}}}"#,
+            ADDRESS
+        ),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(&localnet, workdirs.join("active")).unwrap();
+
+    let config = localnet.join("config");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("sui.keystore"), "[]").unwrap();
+
+    let package = localnet.join("published-data/demo");
+    fs::create_dir_all(package.join("1")).unwrap();
+    fs::write(
+        package.join("1/package-id.json"),
+        format!(r#"["{}"]"#, PACKAGE_ID),
+    )
+    .unwrap();
+    fs::write(
+        package.join("1/created-objects.json"),
+        format!(
+            r#"[{{"type":"{}::Counter::Counter","objectId":"{}"}}]"#,
+            PACKAGE_ID, COUNTER_ID
+        ),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(package.join("1"), package.join("most-recent")).unwrap();
+}
+
+// Exit code and stdout of the CLI.
+fn run_cli(home: &Path, args: &[&str]) -> (i32, String) {
+    run_cli_with_suibase_home(home, None, args)
+}
+
+// Same as run_cli(), with an optional SUIBASE_HOME.
+fn run_cli_with_suibase_home(
+    home: &Path,
+    suibase_home: Option<&Path>,
+    args: &[&str],
+) -> (i32, String) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_suibase-helper-cli"));
+    command
+        .args(args)
+        .env("HOME", home)
+        .env_remove("SUIBASE_HOME");
+    if let Some(suibase_home) = suibase_home {
+        command.env("SUIBASE_HOME", suibase_home);
+    }
+    let output = command.output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // A single JSON object on a single line.
+    assert_eq!(stdout.lines().count(), 1, "stdout: {}", stdout);
+    (output.status.code().unwrap(), stdout)
+}
+
+fn run_ok<T: serde::de::DeserializeOwned>(home: &Path, args: &[&str]) -> T {
+    let (code, stdout) = run_cli(home, args);
+    assert_eq!(code, 0, "stdout: {}", stdout);
+    serde_json::from_str(&stdout).unwrap()
+}
+
+fn run_err(home: &Path, args: &[&str]) -> ErrorOutput {
+    let (code, stdout) = run_cli(home, args);
+    let output: ErrorOutput = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(code, output.exit_code);
+    output
+}
+
+#[test]
+fn test_cli_accessors() {
+    let home = tempfile::tempdir().unwrap();
+    create_fixture(home.path());
+    let home = home.path();
+
+    let output: WorkdirOutput = run_ok(home, &["workdir"]);
+    assert_eq!(output.workdir, "localnet");
+
+    let output: RpcUrlOutput = run_ok(home, &["--workdir", "localnet", "rpc-url"]);
+    assert_eq!(output.rpc_url, RPC_URL);
+
+    let output: PackageIdOutput = run_ok(home, &["package-id", "demo"]);
+    assert_eq!(output.package_name, "demo");
+    assert_eq!(output.package_id, PACKAGE_ID);
+
+    let output: ClientAddressOutput = run_ok(home, &["client-address", "sb-1-ed25519"]);
+    assert_eq!(output.address, ADDRESS);
+
+    let output: PublishedObjectsOutput =
+        run_ok(home, &["published-objects", "demo::Counter::Counter"]);
+    assert_eq!(output.object_ids, vec![COUNTER_ID.to_string()]);
+
+    let output: KeystorePathOutput = run_ok(home, &["--workdir=localnet", "keystore-path"]);
+    assert!(output
+        .keystore_path
+        .ends_with("localnet/config/sui.keystore"));
+    assert_eq!(output.workdir, "localnet");
+}
+
+#[test]
+fn test_cli_errors() {
+    let home = tempfile::tempdir().unwrap();
+    let output = run_err(home.path(), &["workdir"]);
+    assert_eq!(output.error, "NotInstalled");
+    assert_eq!(output.exit_code, Error::NotInstalled.exit_code());
+
+    create_fixture(home.path());
+    let home = home.path();
+    let output = run_err(home, &["--workdir", "devnet", "workdir"]);
+    assert_eq!(output.exit_code, Error::WorkdirNotExists.exit_code());
+
+    let output = run_err(home, &["client-address", "sb-9-ed25519"]);
+    assert_eq!(output.error, "AddressNameNotFound");
+    assert_ne!(output.exit_code, Error::WorkdirNotExists.exit_code());
+
+    let output = run_err(home, &["published-objects", "demo::Counter"]);
+    assert_eq!(output.error, "ObjectTypeInvalidFormat");
+
+    let output = run_err(home, &["package-id", "unknown"]);
+    assert_eq!(output.error, "PublishedDataAccessErrorSymlinkNotFound");
+
+    for args in [
+        &[][..],
+        &["bogus"],
+        &["package-id"],
+        &["rpc-url", "extra"],
+        &["--workdir"],
+        &["--verbose", "workdir"],
+    ] {
+        let output = run_err(home, args);
+        assert_eq!(output.error, "Usage");
+        assert_eq!(output.exit_code, EXIT_CODE_USAGE);
+    }
+}
+
+// Installed outside of the home directory.
+#[test]
+fn test_cli_suibase_home() {
+    let home = tempfile::tempdir().unwrap();
+    let tools = tempfile::tempdir().unwrap();
+    create_fixture(tools.path());
+    let suibase_home = tools.path().join("suibase");
+    let home = home.path();
+
+    // Not at the default location.
+    let output = run_err(home, &["workdir"]);
+    assert_eq!(output.error, "NotInstalled");
+
+    // With SUIBASE_HOME.
+    let (code, stdout) =
+        run_cli_with_suibase_home(home, Some(&suibase_home), &["package-id", "demo"]);
+    assert_eq!(code, 0, "stdout: {}", stdout);
+    let output: PackageIdOutput = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output.package_id, PACKAGE_ID);
+
+    // With the ~/.suibase-location pointer file.
+    fs::write(
+        home.join(".suibase-location"),
+        format!("{}\n", suibase_home.display()),
+    )
+    .unwrap();
+    let output: KeystorePathOutput = run_ok(home, &["--workdir=localnet", "keystore-path"]);
+    let keystore_path = Path::new(&output.keystore_path);
+    assert!(keystore_path.starts_with(suibase_home.canonicalize().unwrap()));
+}
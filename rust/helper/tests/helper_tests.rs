@@ -1,6 +1,7 @@
 // These integration tests assume:
 //  - localnet is already installed
 //  - 'demo' package is already published to localnet.
+//  - localnet and the suibase-daemon are running (for the gas coins and move call tests).
 
 use log;
 use suibase::Helper;
@@ -18,6 +19,16 @@ fn test_is_installed() {
     assert!(sbh.is_installed().unwrap());
 }
 
+#[test]
+fn test_check_compatibility() {
+    let sbh = Helper::new();
+    // This helper is from the same ~/suibase, so must be compatible.
+    assert_eq!(
+        sbh.check_compatibility().unwrap(),
+        suibase::Compatibility::Compatible
+    );
+}
+
 #[test]
 fn test_localnet() {
     let sbh = Helper::new();
@@ -39,8 +50,175 @@ fn test_demo_package() {
     }
     assert!(package_id.is_ok());
     // Verify package_id is an hex string
-    let package_id = package_id.unwrap();
+    let package_id = package_id.unwrap().to_string();
     log::info!("package_id: {} length: {}", package_id, package_id.len());
     assert_eq!(package_id.starts_with("0x"), true);
     assert_eq!(package_id.len(), 66);
 }
+
+#[test]
+fn test_gas_coins_merge() {
+    init();
+    let sbh = Helper::new();
+    assert!(sbh.is_installed().unwrap());
+    sbh.select_workdir("localnet").unwrap();
+
+    // Split the largest coin to make sure there is something to merge.
+    let inventory = sbh.gas_inventory(None).unwrap();
+    let coin_id = inventory.largest_coin_id.unwrap();
+    let output = std::process::Command::new("lsui")
+        .args(["client", "split-coin", "--coin-id", &coin_id])
+        .args(["--count", "5", "--gas-budget", "50000000"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let before = sbh.gas_inventory(None).unwrap().coin_count;
+    let result = sbh.merge_gas_coins(None, Some(3), false).unwrap();
+    log::info!("merge_gas_coins: {:?}", result);
+    assert!(result.coin_count_before >= before);
+    assert!(!result.tx_digests.is_empty());
+    assert!(result.coin_count_after < result.coin_count_before);
+}
+
+#[cfg(feature = "sui-types")]
+#[test]
+fn test_demo_move_call() {
+    init();
+    let sbh = Helper::new();
+    assert!(sbh.is_installed().unwrap());
+    sbh.select_workdir("localnet").unwrap();
+
+    let counter_id = sbh.published_new_objects("demo::Counter::Counter").unwrap()[0].to_string();
+    let args = vec![counter_id.clone()];
+
+    let tx = sbh
+        .build_move_call("demo", "Counter", "increment", vec![], args.clone())
+        .unwrap();
+    assert!(tx["txBytes"].as_str().is_some());
+
+    let result = sbh
+        .execute_move_call("demo", "Counter", "increment", vec![], args)
+        .unwrap();
+    log::info!("execute_move_call: {:?}", result);
+    assert!(!result.digest.is_empty());
+    assert!(result.mutated_object_ids.contains(&counter_id));
+
+    // Unknown function is reported by the RPC.
+    let res = sbh.build_move_call("demo", "Counter", "bogus", vec![], vec![]);
+    assert!(matches!(res, Err(suibase::Error::RpcRequestError { .. })));
+}
+
+#[cfg(feature = "sui-types")]
+#[test]
+fn test_demo_tx_lookup() {
+    init();
+    let sbh = Helper::new();
+    assert!(sbh.is_installed().unwrap());
+    sbh.select_workdir("localnet").unwrap();
+
+    // Lookup right after the execution (may need the retry on localnet).
+    let counter_id = sbh.published_new_objects("demo::Counter::Counter").unwrap()[0].to_string();
+    let result = sbh
+        .execute_move_call("demo", "Counter", "increment", vec![], vec![counter_id])
+        .unwrap();
+    let status = sbh.tx_status(&result.digest).unwrap();
+    assert!(status.success, "{:?}", status);
+    assert_eq!(status.error, None);
+
+    let created = sbh.tx_created_objects(&result.digest).unwrap();
+    let created_count: usize = created.values().map(|ids| ids.len()).sum();
+    assert_eq!(created_count, result.created_object_ids.len());
+
+    // A coin split creates coins of the same type.
+    let inventory = sbh.gas_inventory(None).unwrap();
+    let coin_id = inventory.largest_coin_id.unwrap();
+    let output = std::process::Command::new("lsui")
+        .args(["client", "split-coin", "--coin-id", &coin_id])
+        .args(["--count", "3", "--gas-budget", "50000000", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let split: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let digest = split["digest"].as_str().unwrap();
+    let created = sbh.tx_created_objects_strings(digest).unwrap();
+    assert_eq!(created["0x2::coin::Coin<0x2::sui::SUI>"].len(), 2);
+
+    // Unknown (but valid) digest.
+    let res = sbh.tx_status("11111111111111111111111111111111");
+    assert!(matches!(
+        res,
+        Err(suibase::Error::TransactionNotFound { .. })
+    ));
+    assert!(matches!(
+        sbh.tx_status(""),
+        Err(suibase::Error::TransactionDigestEmpty)
+    ));
+}
+
+#[cfg(feature = "sui-types")]
+#[test]
+fn test_demo_wait_for_object() {
+    init();
+    let sbh = Helper::new();
+    assert!(sbh.is_installed().unwrap());
+    sbh.select_workdir("localnet").unwrap();
+
+    let counter_id = sbh.published_new_objects("demo::Counter::Counter").unwrap()[0].to_string();
+    let count = |content: &serde_json::Value| -> u64 {
+        content["fields"]["count"]
+            .as_str()
+            .and_then(|count| count.parse().ok())
+            .unwrap()
+    };
+    let timeout = std::time::Duration::from_secs(30);
+    let object = sbh.wait_for_object(&counter_id, timeout, |_| true).unwrap();
+    let start_count = count(&object["content"]);
+    let start_version: u64 = object["version"].as_str().unwrap().parse().unwrap();
+
+    // Incremented by another thread (with its own Helper) while waiting.
+    let incrementer = {
+        let counter_id = counter_id.clone();
+        std::thread::spawn(move || {
+            let sbh = Helper::new();
+            sbh.select_workdir("localnet").unwrap();
+            for _ in 0..3 {
+                sbh.execute_move_call(
+                    "demo",
+                    "Counter",
+                    "increment",
+                    vec![],
+                    vec![counter_id.clone()],
+                )
+                .unwrap();
+            }
+        })
+    };
+    let object = sbh
+        .wait_for_object(&counter_id, timeout, |content| {
+            count(content) >= start_count + 3
+        })
+        .unwrap();
+    assert!(count(&object["content"]) >= start_count + 3);
+    incrementer.join().unwrap();
+
+    let object = sbh
+        .wait_for_object_version(&counter_id, start_version + 3, timeout)
+        .unwrap();
+    assert!(count(&object["content"]) >= start_count + 3);
+
+    // Never reached: the last content is in the error.
+    let res = sbh.wait_for_object(&counter_id, std::time::Duration::from_secs(1), |content| {
+        count(content) == u64::MAX
+    });
+    match res {
+        Err(suibase::Error::ObjectWaitTimeout { last_content, .. }) => {
+            assert!(last_content.contains("count"), "{}", last_content)
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Valid, but not an object of localnet.
+    let res = sbh.wait_for_object("0x1234", std::time::Duration::from_secs(1), |_| true);
+    assert!(matches!(res, Err(suibase::Error::ObjectNotFound { .. })));
+}
@@ -1,3 +1,5 @@
+// Error response of the daemons proxy_server (axum handlers).
+//
 // TODO Needed? Refactor this to SuibaseError
 
 // Reference:
@@ -6,15 +6,32 @@
 //
 // Will cleanly self-exit on SIGTERM, Ctrl-C etc...
 //
+// Restarts caused by a panic or an error are tracked in AUTO_THREAD_STATS (see
+// getDaemonStats), and can be reported to the owner with an OnRestart callback.
+//
+// The delay before a restart doubles on consecutive failures. A circuit breaker
+// keeps the thread down for a cool-off period (or for good) when it keeps failing
+// (see RestartPolicy).
+//
 // See api_server.rs for an example of usage.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use axum::async_trait;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
-use tokio::time::Duration;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, Once};
+use tokio::time::{Duration, Instant};
 use tokio_graceful_shutdown::{ErrorAction, SubsystemBuilder, SubsystemHandle};
 
-use crate::log_safe;
+use crate::{log_safe, log_safe_err};
+
+// Lines of the panic backtrace kept in the stats.
+const BACKTRACE_SNIPPET_LINES: usize = 12;
 
 #[async_trait]
 pub trait Runnable<Parameter: Send> {
@@ -22,20 +39,232 @@ pub trait Runnable<Parameter: Send> {
     async fn run(self, subsys: SubsystemHandle) -> Result<()>;
 }
 
+// Circuit breaker: after 'max_restarts' failures within 'window', the thread is
+// kept down for 'cool_off' (instead of restarting after the backoff delay). With
+// 'give_up', it is instead never restarted again (and reported as failed).
+//
+// Backoff: 'restart_delay' is doubled on each additional failure within 'window',
+// up to 'max_restart_delay' (restart_delay * 2^(failures - 1), capped).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub max_restarts: u32, // 0 to never trip the circuit breaker.
+    pub window: Duration,
+    pub cool_off: Duration,
+    pub restart_delay: Duration,
+    pub max_restart_delay: Duration,
+    pub give_up: bool,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            cool_off: Duration::from_secs(300),
+            restart_delay: Duration::from_secs(2),
+            max_restart_delay: Duration::from_secs(10),
+            give_up: false,
+        }
+    }
+}
+
+// What the AutoThread does after a failure of its inner thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestartAction {
+    Restart(Duration), // After the backoff delay.
+    CoolOff(Duration),
+    GiveUp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartEvent {
+    pub name: String,
+    pub restart_count: u64, // Failures of all the threads with this name (see AUTO_THREAD_STATS).
+    pub error: String,      // Panic message or returned error.
+    pub action: RestartAction,
+}
+
+// Called by the AutoThread after every failure of its inner thread.
+pub type OnRestart = Arc<dyn Fn(&RestartEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestartStats {
+    pub restart_count: u64,
+    pub last_error: Option<String>, // Panic message or returned error.
+    pub last_backtrace: Option<String>, // Snippet, only for a panic.
+    pub last_restart: Option<DateTime<Utc>>,
+    pub cool_off_count: u64,
+    pub cool_off_until: Option<DateTime<Utc>>, // Set while the thread is kept down.
+    pub failed: bool,                          // Not restarted anymore (give up).
+}
+
+impl RestartStats {
+    pub fn is_degraded(&self) -> bool {
+        self.cool_off_until.is_some() || self.failed
+    }
+}
+
+// Restart stats of all AutoThread, by name.
+pub struct AutoThreadStats {
+    threads: Mutex<HashMap<String, RestartStats>>,
+}
+
+impl AutoThreadStats {
+    fn new() -> Self {
+        Self {
+            threads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Sorted by name.
+    pub fn snapshot(&self) -> Vec<(String, RestartStats)> {
+        let mut threads: Vec<(String, RestartStats)> = match self.threads.lock() {
+            Ok(threads) => threads
+                .iter()
+                .map(|(name, stats)| (name.clone(), stats.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        threads.sort_by(|a, b| a.0.cmp(&b.0));
+        threads
+    }
+
+    pub fn get(&self, name: &str) -> Option<RestartStats> {
+        self.threads.lock().ok()?.get(name).cloned()
+    }
+
+    // Names of the threads currently kept down by the circuit breaker (or failed).
+    pub fn degraded(&self) -> Vec<String> {
+        self.snapshot()
+            .into_iter()
+            .filter(|(_, stats)| stats.is_degraded())
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn update<F: FnOnce(&mut RestartStats)>(&self, name: &str, f: F) {
+        if let Ok(mut threads) = self.threads.lock() {
+            f(threads.entry(name.to_string()).or_default());
+        }
+    }
+}
+
+pub static AUTO_THREAD_STATS: Lazy<AutoThreadStats> = Lazy::new(AutoThreadStats::new);
+
+// Track failure times to decide when the circuit breaker trips.
+#[derive(Debug)]
+struct CircuitBreaker {
+    policy: RestartPolicy,
+    failures: VecDeque<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            failures: VecDeque::new(),
+        }
+    }
+
+    // Returns true when the thread should be kept down for the cool-off.
+    fn report_failure(&mut self, now: Instant) -> bool {
+        self.failures.push_back(now);
+        while let Some(oldest) = self.failures.front() {
+            if now.duration_since(*oldest) > self.policy.window {
+                self.failures.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.policy.max_restarts != 0 && self.failures.len() >= self.policy.max_restarts as usize
+        {
+            // Start fresh after the cool-off.
+            self.failures.clear();
+            return true;
+        }
+        false
+    }
+
+    // Delay before the next restart, from the failures still within the window.
+    fn backoff(&self) -> Duration {
+        let doublings = self.failures.len().saturating_sub(1).min(16) as u32;
+        self.policy
+            .restart_delay
+            .saturating_mul(1 << doublings)
+            .min(self.policy.max_restart_delay.max(self.policy.restart_delay))
+    }
+}
+
+thread_local! {
+    // Backtrace of the most recent panic on this thread (see install_panic_hook).
+    static LAST_PANIC_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Chain a panic hook that remembers the backtrace. The panic is caught on the
+// same thread that polled the future, so a thread_local is enough.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let prev_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+            let snippet: Vec<&str> = backtrace.lines().take(BACKTRACE_SNIPPET_LINES).collect();
+            LAST_PANIC_BACKTRACE.with(|last| *last.borrow_mut() = Some(snippet.join("\n")));
+            prev_hook(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
 pub struct AutoThread<Thread: Runnable<Parameter>, Parameter: Send> {
     pub name: String,
     pub params: Parameter,
+    policy: RestartPolicy,
+    on_restart: Option<OnRestart>,
     _thread: PhantomData<Thread>,
 }
 
 impl<Thread: Runnable<Parameter>, Parameter: Send> AutoThread<Thread, Parameter> {
     pub fn new(name: String, params: Parameter) -> Self {
+        Self::new_with_policy(name, params, RestartPolicy::default())
+    }
+
+    pub fn new_with_policy(name: String, params: Parameter, policy: RestartPolicy) -> Self {
         Self {
             name,
             params,
+            policy,
+            on_restart: None,
             _thread: PhantomData,
         }
     }
+
+    pub fn with_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_on_restart(mut self, on_restart: OnRestart) -> Self {
+        self.on_restart = Some(on_restart);
+        self
+    }
+}
+
+// Sleep, but return early on shutdown request.
+async fn sleep_unless_shutdown(subsys: &SubsystemHandle, duration: Duration) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {},
+        _ = subsys.on_shutdown_requested() => {},
+    }
 }
 
 #[async_trait]
@@ -43,38 +272,107 @@ impl<Thread: Runnable<Parameter> + Send + 'static, Parameter: Send + Clone> Runn
     for AutoThread<Thread, Parameter>
 {
     fn new(name: String, params: Parameter) -> Self {
-        Self {
-            name,
-            params,
-            _thread: PhantomData,
-        }
+        Self::new_with_policy(name, params, RestartPolicy::default())
     }
 
     async fn run(self, subsys: SubsystemHandle) -> Result<()> {
         let outer_task_name = format!("{}-outer", self.name);
         let inner_task_name = format!("{}-inner", self.name);
         log_safe!(format!("{} started", outer_task_name));
+        install_panic_hook();
+        let mut breaker = CircuitBreaker::new(self.policy.clone());
         loop {
             // Create an instance of the Thread. If it panics, then
             // we will just start a new instance on next loop iteration.
             let inner_thread = Thread::new(self.name.clone(), self.params.clone());
 
+            // The panic is caught here (instead of by the SubsystemBuilder) to get its message.
+            let (panic_tx, panic_rx) = tokio::sync::oneshot::channel::<(String, Option<String>)>();
             let nested_subsys = subsys.start(
-                SubsystemBuilder::new(inner_task_name.clone(), |a| inner_thread.run(a))
-                    .on_failure(ErrorAction::CatchAndLocalShutdown)
-                    .on_panic(ErrorAction::CatchAndLocalShutdown),
+                SubsystemBuilder::new(inner_task_name.clone(), |a| async move {
+                    match AssertUnwindSafe(inner_thread.run(a)).catch_unwind().await {
+                        Ok(result) => result,
+                        Err(payload) => {
+                            let msg = panic_message(&*payload);
+                            let backtrace =
+                                LAST_PANIC_BACKTRACE.with(|last| last.borrow_mut().take());
+                            let _ = panic_tx.send((msg.clone(), backtrace));
+                            Err(anyhow!("panic: {}", msg))
+                        }
+                    }
+                })
+                .on_failure(ErrorAction::CatchAndLocalShutdown)
+                .on_panic(ErrorAction::CatchAndLocalShutdown),
             );
 
             if let Err(err) = nested_subsys.join().await {
                 // TODO Restart the process on excess of errors for tentative recovery (e.g. memory leaks?)
                 log::error!("{}: {}", inner_task_name, err);
-                // Something went wrong, wait a couple of second before restarting
-                // the inner server, but do not block from exiting.
-                for _ in 0..4 {
-                    if subsys.is_shutdown_requested() {
-                        break;
+
+                let (last_error, last_backtrace) = match panic_rx.await {
+                    Ok((msg, backtrace)) => (format!("panic: {}", msg), backtrace),
+                    Err(_) => (err.to_string(), None),
+                };
+                let action = if !breaker.report_failure(Instant::now()) {
+                    RestartAction::Restart(breaker.backoff())
+                } else if self.policy.give_up {
+                    RestartAction::GiveUp
+                } else {
+                    RestartAction::CoolOff(self.policy.cool_off)
+                };
+                let mut restart_count = 0;
+                AUTO_THREAD_STATS.update(&self.name, |stats| {
+                    stats.restart_count += 1;
+                    stats.last_error = Some(last_error.clone());
+                    stats.last_backtrace = last_backtrace;
+                    stats.last_restart = Some(Utc::now());
+                    match action {
+                        RestartAction::Restart(_) => {}
+                        RestartAction::CoolOff(cool_off) => {
+                            stats.cool_off_count += 1;
+                            stats.cool_off_until = chrono::Duration::from_std(cool_off)
+                                .ok()
+                                .map(|d| Utc::now() + d);
+                        }
+                        RestartAction::GiveUp => stats.failed = true,
+                    }
+                    restart_count = stats.restart_count;
+                });
+                log_safe_err!(format!(
+                    "{} failed (restart #{}): {}",
+                    inner_task_name, restart_count, last_error
+                ));
+                if let Some(on_restart) = &self.on_restart {
+                    on_restart(&RestartEvent {
+                        name: self.name.clone(),
+                        restart_count,
+                        error: last_error,
+                        action: action.clone(),
+                    });
+                }
+
+                match action {
+                    RestartAction::Restart(delay) => {
+                        // Something went wrong, wait before restarting the inner
+                        // server, but do not block from exiting.
+                        sleep_unless_shutdown(&subsys, delay).await;
+                    }
+                    RestartAction::CoolOff(cool_off) => {
+                        log_safe_err!(format!(
+                            "{} failing repeatedly, kept down for {} secs",
+                            inner_task_name,
+                            cool_off.as_secs()
+                        ));
+                        sleep_unless_shutdown(&subsys, cool_off).await;
+                        AUTO_THREAD_STATS.update(&self.name, |stats| stats.cool_off_until = None);
+                    }
+                    RestartAction::GiveUp => {
+                        log_safe_err!(format!(
+                            "{} failing repeatedly, not restarted anymore",
+                            inner_task_name
+                        ));
+                        return Ok(());
                     }
-                    tokio::time::sleep(Duration::from_millis(500)).await;
                 }
             }
 
@@ -90,10 +388,197 @@ impl<Thread: Runnable<Parameter> + Send + 'static, Parameter: Send + Clone> Runn
                 // Sleep 1 second before restarting the inner thread.
                 // This is in-case of thread start failure, we don't want
                 // the CPU spinning on restarts attempts.
-                tokio::time::sleep(Duration::from_secs(1)).await;
+                sleep_unless_shutdown(&subsys, Duration::from_secs(1)).await;
             }
         }
         log_safe!(format!("{} normal thread exit", outer_task_name));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio_graceful_shutdown::Toplevel;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut breaker = CircuitBreaker::new(RestartPolicy {
+            max_restarts: 3,
+            window: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let t0 = Instant::now();
+        assert!(!breaker.report_failure(t0));
+        assert!(!breaker.report_failure(t0 + Duration::from_secs(1)));
+        // Third within the window trips the breaker.
+        assert!(breaker.report_failure(t0 + Duration::from_secs(2)));
+
+        // Failures spread over more than the window never trip it.
+        assert!(!breaker.report_failure(t0 + Duration::from_secs(20)));
+        assert!(!breaker.report_failure(t0 + Duration::from_secs(31)));
+        assert!(!breaker.report_failure(t0 + Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut breaker = CircuitBreaker::new(RestartPolicy {
+            max_restarts: 0,
+            window: Duration::from_secs(60),
+            restart_delay: Duration::from_secs(1),
+            max_restart_delay: Duration::from_secs(5),
+            ..Default::default()
+        });
+        let t0 = Instant::now();
+        let mut delays = Vec::new();
+        for secs in [0, 1, 2, 3, 4] {
+            assert!(!breaker.report_failure(t0 + Duration::from_secs(secs)));
+            delays.push(breaker.backoff().as_secs());
+        }
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+        // Back to 'restart_delay' once the failures are out of the window.
+        assert!(!breaker.report_failure(t0 + Duration::from_secs(100)));
+        assert_eq!(breaker.backoff(), Duration::from_secs(1));
+    }
+
+    // Panics on every run, after counting the number of runs.
+    struct PanicThread {
+        runs: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl Runnable<Arc<AtomicU32>> for PanicThread {
+        fn new(_name: String, runs: Arc<AtomicU32>) -> Self {
+            Self { runs }
+        }
+
+        async fn run(self, _subsys: SubsystemHandle) -> Result<()> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            panic!("injected panic {}", run);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_panic_restarts_and_cool_off() {
+        const NAME: &str = "TestPanicThread";
+        let runs = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+            cool_off: Duration::from_secs(100),
+            restart_delay: Duration::from_secs(1),
+            max_restart_delay: Duration::from_secs(1),
+            give_up: false,
+        };
+        let auto_thread =
+            AutoThread::<PanicThread, Arc<AtomicU32>>::new(NAME.to_string(), runs.clone())
+                .with_policy(policy);
+
+        let toplevel = Toplevel::new(move |s| async move {
+            s.start(SubsystemBuilder::new(NAME, |a| auto_thread.run(a)));
+        });
+        let toplevel_task = tokio::spawn(async move {
+            toplevel
+                .handle_shutdown_requests(Duration::from_secs(5))
+                .await
+        });
+
+        // Three quick failures trip the breaker.
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let stats = AUTO_THREAD_STATS.get(NAME).unwrap();
+        assert_eq!(stats.restart_count, 3);
+        assert_eq!(stats.cool_off_count, 1);
+        assert!(stats.is_degraded());
+        assert_eq!(stats.last_error.as_deref(), Some("panic: injected panic 3"));
+        assert!(stats.last_backtrace.is_some());
+        assert_eq!(AUTO_THREAD_STATS.degraded(), vec![NAME.to_string()]);
+
+        // Still down in the middle of the cool-off.
+        tokio::time::sleep(Duration::from_secs(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // Restarted after the cool-off.
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(runs.load(Ordering::SeqCst) > 3);
+
+        toplevel_task.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_panic_backoff_and_give_up() {
+        const NAME: &str = "TestGiveUpThread";
+        let runs = Arc::new(AtomicU32::new(0));
+        let events = Arc::new(Mutex::new(Vec::<RestartEvent>::new()));
+        let policy = RestartPolicy {
+            max_restarts: 4,
+            window: Duration::from_secs(600),
+            restart_delay: Duration::from_secs(2),
+            max_restart_delay: Duration::from_secs(4),
+            give_up: true,
+            ..Default::default()
+        };
+        let on_restart: OnRestart = {
+            let events = events.clone();
+            Arc::new(move |event: &RestartEvent| events.lock().unwrap().push(event.clone()))
+        };
+        let auto_thread = AutoThread::<PanicThread, Arc<AtomicU32>>::new_with_policy(
+            NAME.to_string(),
+            runs.clone(),
+            policy,
+        )
+        .with_on_restart(on_restart);
+
+        let start = Instant::now();
+        let toplevel = Toplevel::new(move |s| async move {
+            s.start(SubsystemBuilder::new(NAME, |a| auto_thread.run(a)));
+        });
+        let toplevel_task = tokio::spawn(async move {
+            toplevel
+                .handle_shutdown_requests(Duration::from_secs(5))
+                .await
+        });
+
+        // Runs at 0s, then after the backoff (2s, 4s, 4s) plus the 1 sec of every restart.
+        let runs_at = |secs: u64| {
+            let runs = runs.clone();
+            async move {
+                tokio::time::sleep_until(start + Duration::from_millis(secs * 1000 + 500)).await;
+                runs.load(Ordering::SeqCst)
+            }
+        };
+        assert_eq!(runs_at(0).await, 1);
+        assert_eq!(runs_at(2).await, 1);
+        assert_eq!(runs_at(3).await, 2);
+        assert_eq!(runs_at(7).await, 2);
+        assert_eq!(runs_at(8).await, 3);
+        assert_eq!(runs_at(13).await, 4);
+
+        // The fourth failure trips the breaker: never restarted.
+        assert_eq!(runs_at(1000).await, 4);
+        let actions: Vec<RestartAction> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.action.clone())
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                RestartAction::Restart(Duration::from_secs(2)),
+                RestartAction::Restart(Duration::from_secs(4)),
+                RestartAction::Restart(Duration::from_secs(4)),
+                RestartAction::GiveUp,
+            ]
+        );
+        let stats = AUTO_THREAD_STATS.get(NAME).unwrap();
+        assert!(stats.failed);
+        assert_eq!(stats.cool_off_count, 0);
+        assert!(AUTO_THREAD_STATS.degraded().contains(&NAME.to_string()));
+
+        toplevel_task.abort();
+    }
+}
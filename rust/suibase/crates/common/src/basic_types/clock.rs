@@ -0,0 +1,138 @@
+// Time source abstraction.
+//
+// Code that measures elapsed time (timeouts, retries...) gets "now" from a Clock
+// instead of calling Instant::now() directly. Production uses SystemClock, while
+// tests can use a MockClock that only moves when advanced.
+//
+// Note: code that *sleeps* should keep using tokio::time, which can be paused and
+// auto-advanced in tests with #[tokio::test(start_paused = true)].
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::time::Instant;
+
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    // Monotonic time, for measuring elapsed time.
+    fn now_instant(&self) -> Instant;
+
+    // Seconds since the UNIX epoch (wall-clock).
+    fn now_secs(&self) -> u64;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+// Clock that starts at the time of its creation and moves only with advance().
+//
+// Clones share the same time, so a test can keep a clone to advance the time
+// seen by the code under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    start_instant: Instant,
+    start_secs: u64,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            start_instant: Instant::now(),
+            start_secs: SystemClock.now_secs(),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut offset) = self.offset.lock() {
+            *offset += duration;
+        }
+    }
+
+    fn offset(&self) -> Duration {
+        self.offset.lock().map(|offset| *offset).unwrap_or_default()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now_instant(&self) -> Instant {
+        self.start_instant + self.offset()
+    }
+
+    fn now_secs(&self) -> u64 {
+        self.start_secs + self.offset().as_secs()
+    }
+}
+
+// Cheap to clone handle on a Clock. Defaults to the SystemClock.
+#[derive(Debug, Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new<C: Clock + 'static>(clock: C) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    pub fn now_instant(&self) -> Instant {
+        self.0.now_instant()
+    }
+
+    pub fn now_secs(&self) -> u64 {
+        self.0.now_secs()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+        let shared = SharedClock::new(clock.clone());
+        let t0 = shared.now_instant();
+        let s0 = shared.now_secs();
+        assert_eq!(shared.now_instant(), t0);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(shared.now_instant() - t0, Duration::from_millis(1500));
+        assert_eq!(shared.now_secs(), s0 + 1);
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(shared.now_secs(), s0 + 3601);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        // Smoke test against the real clock.
+        let clock = SharedClock::default();
+        let t0 = clock.now_instant();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(clock.now_instant() - t0 >= Duration::from_millis(20));
+        assert!(clock.now_secs() > 1_600_000_000);
+    }
+}
@@ -0,0 +1,187 @@
+// Classification of the Sui JSON-RPC methods and error codes.
+//
+// Used by the proxy to decide if a JSON-RPC error response (within a successful
+// HTTP response) should affect the health of the link:
+//
+//   - An error on an "execute" method is most often the transaction of the user
+//     being invalid. The link did nothing wrong.
+//   - An error on a "read" method is attributed to the server only for the
+//     server-fault code ranges.
+//
+// Transport errors and HTTP 5xx are not JSON-RPC errors (always a server fault).
+
+// Standard JSON-RPC 2.0 codes.
+pub const JSONRPC_PARSE_ERROR: i32 = -32700;
+pub const JSONRPC_INVALID_REQUEST: i32 = -32600;
+pub const JSONRPC_METHOD_NOT_FOUND: i32 = -32601;
+pub const JSONRPC_INVALID_PARAMS: i32 = -32602;
+pub const JSONRPC_INTERNAL_ERROR: i32 = -32603;
+
+// Range reserved for implementation-defined server errors.
+pub const JSONRPC_SERVER_ERROR_MIN: i32 = -32099;
+pub const JSONRPC_SERVER_ERROR_MAX: i32 = -32000;
+
+// Sui specific codes (see sui-json-rpc-api).
+pub const SUI_TRANSIENT_ERROR: i32 = -32050;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonRpcMethodKind {
+    Read,
+    Execute, // Build, dry-run or execute a transaction.
+    Unknown, // Handled like Read (when in doubt, assume a server problem).
+}
+
+// Methods that evaluate a transaction provided by the user.
+const EXECUTE_METHODS: [&str; 3] = [
+    "sui_executeTransactionBlock",
+    "sui_dryRunTransactionBlock",
+    "sui_devInspectTransactionBlock",
+];
+
+// Prefixes of the methods that only read the state of the network.
+const READ_METHOD_PREFIXES: [&str; 8] = [
+    "sui_get",
+    "suix_get",
+    "sui_multiGet",
+    "sui_tryGet",
+    "sui_tryMultiGet",
+    "suix_query",
+    "suix_resolve",
+    "rpc.discover",
+];
+
+pub fn jsonrpc_method_kind(method: &str) -> JsonRpcMethodKind {
+    if EXECUTE_METHODS.contains(&method) || method.starts_with("unsafe_") {
+        // unsafe_* are the transaction builders (e.g. unsafe_moveCall).
+        JsonRpcMethodKind::Execute
+    } else if READ_METHOD_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+    {
+        JsonRpcMethodKind::Read
+    } else {
+        JsonRpcMethodKind::Unknown
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonRpcErrorCategory {
+    ClientFault,  // The request is at fault. Never affects the link health.
+    ServerFault,  // The server is at fault. Degrades the link health.
+    Undetermined, // Counted only, unless configured as a quota-type error.
+}
+
+impl JsonRpcErrorCategory {
+    // Compact encoding for the NetworkMonitor messages.
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            JsonRpcErrorCategory::ClientFault => 1,
+            JsonRpcErrorCategory::ServerFault => 2,
+            JsonRpcErrorCategory::Undetermined => 0,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => JsonRpcErrorCategory::ClientFault,
+            2 => JsonRpcErrorCategory::ServerFault,
+            _ => JsonRpcErrorCategory::Undetermined,
+        }
+    }
+}
+
+pub fn jsonrpc_error_category(kind: JsonRpcMethodKind, code: i32) -> JsonRpcErrorCategory {
+    match code {
+        JSONRPC_INTERNAL_ERROR | SUI_TRANSIENT_ERROR => JsonRpcErrorCategory::ServerFault,
+        _ if kind == JsonRpcMethodKind::Execute => JsonRpcErrorCategory::ClientFault,
+        JSONRPC_SERVER_ERROR_MIN..=JSONRPC_SERVER_ERROR_MAX => JsonRpcErrorCategory::ServerFault,
+        _ => JsonRpcErrorCategory::Undetermined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonrpc_method_kind() {
+        for method in [
+            "sui_executeTransactionBlock",
+            "sui_dryRunTransactionBlock",
+            "sui_devInspectTransactionBlock",
+            "unsafe_moveCall",
+        ] {
+            assert_eq!(jsonrpc_method_kind(method), JsonRpcMethodKind::Execute);
+        }
+        for method in [
+            "sui_getObject",
+            "sui_multiGetObjects",
+            "sui_tryGetPastObject",
+            "suix_getBalance",
+            "suix_queryEvents",
+            "suix_resolveNameServiceAddress",
+            "sui_getLatestCheckpointSequenceNumber",
+        ] {
+            assert_eq!(jsonrpc_method_kind(method), JsonRpcMethodKind::Read);
+        }
+        assert_eq!(jsonrpc_method_kind("sui_test"), JsonRpcMethodKind::Unknown);
+        assert_eq!(jsonrpc_method_kind(""), JsonRpcMethodKind::Unknown);
+    }
+
+    #[test]
+    fn test_jsonrpc_error_category() {
+        use JsonRpcErrorCategory::*;
+        use JsonRpcMethodKind::*;
+
+        // Internal errors are always the server fault.
+        for kind in [Read, Execute, Unknown] {
+            assert_eq!(
+                jsonrpc_error_category(kind, JSONRPC_INTERNAL_ERROR),
+                ServerFault
+            );
+            assert_eq!(
+                jsonrpc_error_category(kind, SUI_TRANSIENT_ERROR),
+                ServerFault
+            );
+        }
+
+        // Anything else on an execute method is the client fault.
+        for code in [
+            JSONRPC_INVALID_PARAMS,
+            -32000,
+            -32002,
+            JSONRPC_PARSE_ERROR,
+            1,
+        ] {
+            assert_eq!(jsonrpc_error_category(Execute, code), ClientFault);
+        }
+
+        // Read methods degrade only for the server error range.
+        assert_eq!(jsonrpc_error_category(Read, -32000), ServerFault);
+        assert_eq!(jsonrpc_error_category(Unknown, -32099), ServerFault);
+        assert_eq!(jsonrpc_error_category(Read, -32100), Undetermined);
+        assert_eq!(
+            jsonrpc_error_category(Read, JSONRPC_INVALID_PARAMS),
+            Undetermined
+        );
+        assert_eq!(
+            jsonrpc_error_category(Unknown, JSONRPC_METHOD_NOT_FOUND),
+            Undetermined
+        );
+        assert_eq!(
+            jsonrpc_error_category(Read, JSONRPC_INVALID_REQUEST),
+            Undetermined
+        );
+    }
+
+    #[test]
+    fn test_jsonrpc_error_category_u8() {
+        for category in [
+            JsonRpcErrorCategory::ClientFault,
+            JsonRpcErrorCategory::ServerFault,
+            JsonRpcErrorCategory::Undetermined,
+        ] {
+            assert_eq!(JsonRpcErrorCategory::from_u8(category.as_u8()), category);
+        }
+    }
+}
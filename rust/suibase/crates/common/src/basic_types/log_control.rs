@@ -0,0 +1,346 @@
+// Logger singleton allowing some control at runtime.
+//
+// Features:
+//  - Initial filtering is done the same way as env_logger (RUST_LOG syntax, with a default).
+//  - Per target (module path) level override at runtime. A target also applies to all its
+//    sub-modules (e.g. "suibase_daemon::proxy_server" applies to "suibase_daemon::proxy_server::*").
+//  - Switch between line-oriented text and JSON (one object per line) output at runtime.
+//
+// Runtime overrides are never persisted (they are reset on restart).
+//
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use anyhow::{anyhow, Result};
+use log::{LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow!(
+                "invalid log format [{}] (expected json or text)",
+                s
+            )),
+        }
+    }
+}
+
+pub struct LogControl {
+    // Filtering set at initialization (RUST_LOG or default).
+    base_filter: RwLock<env_logger::filter::Filter>,
+    // Runtime overrides. Vec, because there are rarely more than a few.
+    overrides: RwLock<Vec<(String, LevelFilter)>>,
+    json: AtomicBool,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl LogControl {
+    fn new() -> Self {
+        Self::with_writer(Box::new(std::io::stderr()))
+    }
+
+    pub fn with_writer(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            base_filter: RwLock::new(env_logger::filter::Builder::new().build()),
+            overrides: RwLock::new(Vec::new()),
+            json: AtomicBool::new(false),
+            writer: Mutex::new(writer),
+        }
+    }
+
+    // Set the filtering, same syntax as RUST_LOG. 'filters' are "static" overrides applied
+    // after the spec (typically to quiet noisy dependencies).
+    pub fn set_base_filter(&self, spec: &str, filters: &[(&str, LevelFilter)]) {
+        let mut builder = env_logger::filter::Builder::new();
+        builder.parse(spec);
+        for (target, level) in filters {
+            builder.filter(Some(*target), *level);
+        }
+        if let Ok(mut base_filter) = self.base_filter.write() {
+            *base_filter = builder.build();
+        }
+        self.update_max_level();
+    }
+
+    // Install LOG_CONTROL as the global logger. Should be called once at startup.
+    //
+    // Filtering is initialized from RUST_LOG (or 'default_spec' when not defined).
+    pub fn init(default_spec: &str, filters: &[(&str, LevelFilter)]) -> Result<()> {
+        let spec = std::env::var("RUST_LOG").unwrap_or_else(|_| default_spec.to_string());
+        LOG_CONTROL.set_base_filter(&spec, filters);
+        log::set_logger(&*LOG_CONTROL).map_err(|e| anyhow!("logger init failed: {}", e))?;
+        LOG_CONTROL.update_max_level();
+        Ok(())
+    }
+
+    pub fn set_format(&self, format: LogFormat) {
+        self.json
+            .store(format == LogFormat::Json, Ordering::Relaxed);
+    }
+
+    pub fn format(&self) -> LogFormat {
+        if self.json.load(Ordering::Relaxed) {
+            LogFormat::Json
+        } else {
+            LogFormat::Text
+        }
+    }
+
+    // Change the level for a target (and its sub-modules).
+    //
+    // 'level' is one of "off", "error", "warn", "info", "debug" or "trace".
+    pub fn set_level(&self, target: &str, level: &str) -> Result<()> {
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(anyhow!("empty target"));
+        }
+        let level = LevelFilter::from_str(level.trim())
+            .map_err(|_| anyhow!("invalid log level [{}]", level))?;
+        {
+            let mut overrides = self
+                .overrides
+                .write()
+                .map_err(|_| anyhow!("log overrides lock poisoned"))?;
+            if let Some(entry) = overrides.iter_mut().find(|(t, _)| t == target) {
+                entry.1 = level;
+            } else {
+                overrides.push((target.to_string(), level));
+            }
+        }
+        self.update_max_level();
+        Ok(())
+    }
+
+    // Remove all runtime overrides.
+    pub fn reset_levels(&self) {
+        if let Ok(mut overrides) = self.overrides.write() {
+            overrides.clear();
+        }
+        self.update_max_level();
+    }
+
+    fn override_for(&self, target: &str) -> Option<LevelFilter> {
+        // Longest (most specific) matching target wins.
+        let overrides = self.overrides.read().ok()?;
+        overrides
+            .iter()
+            .filter(|(t, _)| {
+                target == t
+                    || (target.starts_with(t.as_str()) && target[t.len()..].starts_with("::"))
+            })
+            .max_by_key(|(t, _)| t.len())
+            .map(|(_, level)| *level)
+    }
+
+    fn update_max_level(&self) {
+        // The global max level is a fast early filter done by the log macros, so it must
+        // allow the most verbose of all the filters.
+        let mut max_level = match self.base_filter.read() {
+            Ok(base_filter) => base_filter.filter(),
+            Err(_) => LevelFilter::Info,
+        };
+        if let Ok(overrides) = self.overrides.read() {
+            for (_, level) in overrides.iter() {
+                max_level = max_level.max(*level);
+            }
+        }
+        log::set_max_level(max_level);
+    }
+
+    fn format_record(&self, record: &Record) -> String {
+        let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+        if self.json.load(Ordering::Relaxed) {
+            serde_json::json!({
+                "timestamp": timestamp.to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            })
+            .to_string()
+        } else {
+            format!(
+                "[{} {:<5} {}] {}",
+                timestamp,
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        }
+    }
+}
+
+impl Log for LogControl {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if let Some(level) = self.override_for(metadata.target()) {
+            return metadata.level() <= level;
+        }
+        match self.base_filter.read() {
+            Ok(base_filter) => base_filter.enabled(metadata),
+            Err(_) => false,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format_record(record);
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+pub static LOG_CONTROL: Lazy<LogControl> = Lazy::new(LogControl::new);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // Writer that can be inspected after the logger took ownership of it.
+    #[derive(Clone, Default)]
+    struct CapturedWriter {
+        buf: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for CapturedWriter {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.buf.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedWriter {
+        fn lines(&self) -> Vec<String> {
+            let buf = self.buf.lock().unwrap();
+            String::from_utf8_lossy(&buf)
+                .lines()
+                .map(|s| s.to_string())
+                .collect()
+        }
+    }
+
+    fn log_at(logger: &LogControl, target: &str, level: log::Level, msg: &str) {
+        logger.log(
+            &Record::builder()
+                .target(target)
+                .level(level)
+                .args(format_args!("{}", msg))
+                .build(),
+        );
+    }
+
+    fn new_logger() -> (LogControl, CapturedWriter) {
+        let writer = CapturedWriter::default();
+        let logger = LogControl::with_writer(Box::new(writer.clone()));
+        logger.set_base_filter("info", &[("jsonrpsee_server::server", LevelFilter::Warn)]);
+        (logger, writer)
+    }
+
+    #[test]
+    fn test_set_level_per_target() {
+        let (logger, writer) = new_logger();
+        let proxy = "suibase_daemon::proxy_server";
+
+        log_at(&logger, proxy, log::Level::Debug, "hidden");
+        assert!(writer.lines().is_empty());
+
+        logger.set_level(proxy, "debug").unwrap();
+        log_at(&logger, proxy, log::Level::Debug, "visible");
+        log_at(
+            &logger,
+            "suibase_daemon::proxy_server::inner",
+            log::Level::Debug,
+            "sub",
+        );
+        // Other targets and look-alike prefixes are not affected.
+        log_at(
+            &logger,
+            "suibase_daemon::network_monitor",
+            log::Level::Debug,
+            "x",
+        );
+        log_at(
+            &logger,
+            "suibase_daemon::proxy_serverx",
+            log::Level::Debug,
+            "y",
+        );
+
+        let lines = writer.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("DEBUG") && lines[0].ends_with("visible"));
+        assert!(lines[1].ends_with("sub"));
+
+        // Reset back to the base filter.
+        logger.reset_levels();
+        log_at(&logger, proxy, log::Level::Debug, "hidden again");
+        assert_eq!(writer.lines().len(), 2);
+    }
+
+    #[test]
+    fn test_static_filters_and_invalid_params() {
+        let (logger, writer) = new_logger();
+        log_at(
+            &logger,
+            "jsonrpsee_server::server",
+            log::Level::Info,
+            "noisy",
+        );
+        assert!(writer.lines().is_empty());
+
+        assert!(logger.set_level("suibase_daemon", "verbose").is_err());
+        assert!(logger.set_level(" ", "debug").is_err());
+
+        // An override can also make a target quieter.
+        logger.set_level("suibase_daemon", "off").unwrap();
+        log_at(
+            &logger,
+            "suibase_daemon::admin_controller",
+            log::Level::Error,
+            "x",
+        );
+        assert!(writer.lines().is_empty());
+    }
+
+    #[test]
+    fn test_json_format() {
+        let (logger, writer) = new_logger();
+        logger.set_format(LogFormat::from_str("JSON").unwrap());
+        assert_eq!(logger.format(), LogFormat::Json);
+        log_at(&logger, "suibase_daemon", log::Level::Warn, "say \"hi\"");
+
+        let lines = writer.lines();
+        assert_eq!(lines.len(), 1);
+        let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "suibase_daemon");
+        assert_eq!(value["message"], "say \"hi\"");
+        assert!(value["timestamp"].is_string());
+
+        assert!(LogFormat::from_str("xml").is_err());
+    }
+}
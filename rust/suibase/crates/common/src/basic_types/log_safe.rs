@@ -1,14 +1,21 @@
-// This singleton make sure that the same log message does not clutter the log file.
+// These singletons make sure that the same log message does not clutter the log file.
 //
-// Message from same caller location displayed within 1 minute since the last occurence
-// are counted instead of being log.
+// LOG_SAFE (log_safe! and log_safe_err! macros):
+//   Message from same caller location displayed within 1 minute since the last occurence
+//   are counted instead of being log.
+//
+// LOG_RATE_LIMITER (log_safe_error! and log_safe_warn! macros):
+//   Up to LOG_RATE_LIMIT_PER_WINDOW messages per caller location are logged per minute, the
+//   others are dropped. The first message logged after the window rolls is preceded by a
+//   single "suppressed K similar messages" summary. Not async, so usable anywhere.
 //
 use chrono::{Duration, Utc};
-use log::info;
+use log::{error, info};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 
 struct LoggerState {
@@ -28,6 +35,14 @@ impl LogSafe {
     }
 
     pub async fn info(&self, msg: &str, file: &str, line: u32) {
+        self.log(log::Level::Info, msg, file, line).await
+    }
+
+    pub async fn error(&self, msg: &str, file: &str, line: u32) {
+        self.log(log::Level::Error, msg, file, line).await
+    }
+
+    async fn log(&self, level: log::Level, msg: &str, file: &str, line: u32) {
         // Remove the path portion in 'file'
         // Uses OsStr to make sure this never panic.
         let file = Path::new(file)
@@ -59,10 +74,13 @@ impl LogSafe {
             _ => {
                 // If it's been more than a minute since the last log or if this is the first log,
                 // log the counter (if this isn't the first log), reset the counter and update the last log time
-                if state.counter > 0 {
-                    info!("(repeat {}) {} [{}]", state.counter, caller, msg);
-                } else {
-                    info!("{} [{}]", caller, msg);
+                match (level, state.counter) {
+                    (log::Level::Error, 0) => error!("{} [{}]", caller, msg),
+                    (log::Level::Error, counter) => {
+                        error!("(repeat {}) {} [{}]", counter, caller, msg)
+                    }
+                    (_, 0) => info!("{} [{}]", caller, msg),
+                    (_, counter) => info!("(repeat {}) {} [{}]", counter, caller, msg),
                 }
                 state.counter = 0;
                 state.last_log_time = Some(now);
@@ -73,6 +91,116 @@ impl LogSafe {
 
 pub static LOG_SAFE: Lazy<LogSafe> = Lazy::new(LogSafe::new);
 
+pub const LOG_RATE_LIMIT_PER_WINDOW: u32 = 5;
+pub const LOG_RATE_LIMIT_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+struct RateLimitState {
+    window_start: Instant,
+    emitted: u32,
+    suppressed: u64,
+}
+
+pub struct LogRateLimiter {
+    max_per_window: u32,
+    window: std::time::Duration,
+    // Key is the caller location (file!(), line!()).
+    states: std::sync::Mutex<HashMap<(&'static str, u32), RateLimitState>>,
+}
+
+impl LogRateLimiter {
+    pub fn new(max_per_window: u32, window: std::time::Duration) -> Self {
+        LogRateLimiter {
+            max_per_window,
+            window,
+            states: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Returns None when the message must be dropped, otherwise the number of messages
+    // suppressed in the previous window (to be reported before the message).
+    pub fn check(&self, file: &'static str, line: u32) -> Option<u64> {
+        self.check_at(file, line, Instant::now())
+    }
+
+    pub fn check_at(&self, file: &'static str, line: u32, now: Instant) -> Option<u64> {
+        let mut states = match self.states.lock() {
+            Ok(states) => states,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let state = states.entry((file, line)).or_insert(RateLimitState {
+            window_start: now,
+            emitted: 0,
+            suppressed: 0,
+        });
+
+        let mut summary = 0;
+        if now.saturating_duration_since(state.window_start) >= self.window {
+            summary = state.suppressed;
+            state.window_start = now;
+            state.emitted = 0;
+            state.suppressed = 0;
+        }
+
+        if state.emitted < self.max_per_window {
+            state.emitted += 1;
+            Some(summary)
+        } else {
+            state.suppressed += 1;
+            None
+        }
+    }
+
+    // Messages dropped so far in the current window of this caller location.
+    pub fn suppressed_count(&self, file: &'static str, line: u32) -> u64 {
+        let states = match self.states.lock() {
+            Ok(states) => states,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        states
+            .get(&(file, line))
+            .map_or(0, |state| state.suppressed)
+    }
+}
+
+pub static LOG_RATE_LIMITER: Lazy<LogRateLimiter> =
+    Lazy::new(|| LogRateLimiter::new(LOG_RATE_LIMIT_PER_WINDOW, LOG_RATE_LIMIT_WINDOW));
+
+// Same parameters as log::error!, but rate limited per call site (see LOG_RATE_LIMITER).
+#[macro_export]
+macro_rules! log_safe_error {
+    ($($arg:tt)+) => {
+        if let Some(suppressed) = $crate::basic_types::LOG_RATE_LIMITER.check(file!(), line!()) {
+            if suppressed > 0 {
+                log::error!(
+                    "suppressed {} similar messages from {}:{}",
+                    suppressed,
+                    file!(),
+                    line!()
+                );
+            }
+            log::error!($($arg)+);
+        }
+    };
+}
+
+// Same parameters as log::warn!, but rate limited per call site (see LOG_RATE_LIMITER).
+#[macro_export]
+macro_rules! log_safe_warn {
+    ($($arg:tt)+) => {
+        if let Some(suppressed) = $crate::basic_types::LOG_RATE_LIMITER.check(file!(), line!()) {
+            if suppressed > 0 {
+                log::warn!(
+                    "suppressed {} similar messages from {}:{}",
+                    suppressed,
+                    file!(),
+                    line!()
+                );
+            }
+            log::warn!($($arg)+);
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! log_safe {
     ($msg:expr) => {
@@ -82,6 +210,16 @@ macro_rules! log_safe {
     };
 }
 
+// Same as log_safe, but logged as an error.
+#[macro_export]
+macro_rules! log_safe_err {
+    ($msg:expr) => {
+        $crate::basic_types::LOG_SAFE
+            .error(&format!("{}", $msg), file!(), line!())
+            .await;
+    };
+}
+
 // A macro that check if a MPSC channel has more element queued
 // than the threshold. When exceeding, display a message using
 // a safe logger.
@@ -99,3 +237,61 @@ macro_rules! mpsc_q_check {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_suppression() {
+        let limiter = LogRateLimiter::new(3, std::time::Duration::from_secs(60));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at("a.rs", 10, start), Some(0));
+        }
+        for _ in 0..7 {
+            assert_eq!(limiter.check_at("a.rs", 10, start), None);
+        }
+        assert_eq!(limiter.suppressed_count("a.rs", 10), 7);
+
+        // Still within the window.
+        let later = start + std::time::Duration::from_secs(59);
+        assert_eq!(limiter.check_at("a.rs", 10, later), None);
+
+        // Window rolls: the summary is reported once, with the first message.
+        let rolled = start + std::time::Duration::from_secs(60);
+        assert_eq!(limiter.check_at("a.rs", 10, rolled), Some(8));
+        assert_eq!(limiter.check_at("a.rs", 10, rolled), Some(0));
+        assert_eq!(limiter.suppressed_count("a.rs", 10), 0);
+    }
+
+    #[test]
+    fn test_rate_limiter_call_sites() {
+        let limiter = LogRateLimiter::new(1, std::time::Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at("a.rs", 10, now), Some(0));
+        assert_eq!(limiter.check_at("a.rs", 10, now), None);
+
+        // Distinct call sites have their own bucket.
+        assert_eq!(limiter.check_at("a.rs", 11, now), Some(0));
+        assert_eq!(limiter.check_at("b.rs", 10, now), Some(0));
+        assert_eq!(limiter.suppressed_count("a.rs", 10), 1);
+        assert_eq!(limiter.suppressed_count("a.rs", 11), 0);
+        assert_eq!(limiter.suppressed_count("b.rs", 10), 0);
+    }
+
+    #[test]
+    fn test_log_safe_macros() {
+        // Each macro invocation is its own call site.
+        for _ in 0..LOG_RATE_LIMIT_PER_WINDOW + 2 {
+            log_safe_warn!("warn {}", 1);
+        }
+        let line = line!() - 2;
+        assert_eq!(LOG_RATE_LIMITER.suppressed_count(file!(), line), 2);
+
+        log_safe_error!("error {}", 2);
+        assert_eq!(LOG_RATE_LIMITER.suppressed_count(file!(), line!() - 1), 0);
+    }
+}
@@ -28,7 +28,7 @@
 
 pub type ManagedVecU8 = u8;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ManagedVec<T> {
     data: Vec<Option<T>>,
     some_len: ManagedVecU8,
@@ -1,11 +1,15 @@
 // This is a submodule specific to suibase-daemon.
 //
 // flatten everything under "common::basic_types" module.
+pub use self::app_error::*;
 pub use self::auto_thread::*;
 pub use self::autosize_vec::*;
 pub use self::autosize_vec_map_vec::*;
+pub use self::clock::*;
 pub use self::db_objects::*;
+pub use self::json_rpc::*;
 //pub(crate) use self::error::*;
+pub use self::log_control::*;
 pub use self::log_safe::*;
 pub use self::managed_vec::*;
 pub use self::managed_vec16::*;
@@ -14,11 +18,15 @@ pub use self::safe_uuid::*;
 pub use self::suibase_basic_types::*;
 pub use self::tokio_helpers::*;
 
+mod app_error;
 mod auto_thread;
 mod autosize_vec;
 mod autosize_vec_map_vec;
+mod clock;
 mod db_objects;
 mod error;
+mod json_rpc;
+mod log_control;
 mod log_safe;
 mod managed_vec;
 mod managed_vec16;
@@ -150,6 +150,10 @@ pub const EVENT_NOTIF_CONFIG_FILE_CHANGE: u8 = 128;
 pub const EVENT_DEBUG_PRINT: u8 = 129;
 pub const EVENT_SHELL_EXEC: u8 = 130;
 pub const EVENT_POST_PUBLISH: u8 = 131;
+pub const EVENT_NOTIF_TLS_FILE_CHANGE: u8 = 132; // A proxy_tls cert/key file was modified.
+pub const EVENT_QUERY_EVENTS: u8 = 133; // Filtered query of the events stored by the DBWorker.
+pub const EVENT_PREVIEW_CONFIG: u8 = 134; // Dry-run of a suibase.yaml change (see previewConfig).
+pub const EVENT_SET_LINK_PROFILE: u8 = 135; // Switch of link_profiles entry (see setLinkProfile).
 
 pub type AdminControllerTx = tokio::sync::mpsc::Sender<AdminControllerMsg>;
 pub type AdminControllerRx = tokio::sync::mpsc::Receiver<AdminControllerMsg>;
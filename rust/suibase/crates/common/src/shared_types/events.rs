@@ -1,4 +1,4 @@
-use common::basic_types::AutoSizeVec;
+use crate::basic_types::AutoSizeVec;
 
 #[derive(Debug, Clone)]
 pub struct SuiEventData {
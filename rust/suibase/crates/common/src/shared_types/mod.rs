@@ -3,6 +3,12 @@
 // This is a submodule specific to suibase-daemon.
 //
 // flatten everything under "common::shared_type" module.
+pub use self::events::*;
+pub use self::port_conflicts::*;
+pub use self::workdir_status::*;
 pub use self::workdirs::*;
 
+mod events;
+mod port_conflicts;
+mod workdir_status;
 mod workdirs;
@@ -0,0 +1,189 @@
+// Detection of the TCP ports configured more than once.
+//
+// A port conflict otherwise shows up only as a failure to bind (logged) for one
+// of the servers, so the AdminController re-checks all the ports on every config
+// reload and reports the conflicts as warnings and DEGRADED workdir status.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{GlobalsWorkdirsST, WorkdirUserConfig};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortUser {
+    Proxy {
+        workdir: String,
+    },
+    DtpService {
+        workdir: String,
+        service_type: String,
+    },
+    SuibaseApi,
+    DtpApi,
+    Webserver, // Also serves the sui-explorer.
+}
+
+impl PortUser {
+    pub fn workdir(&self) -> Option<&str> {
+        match self {
+            PortUser::Proxy { workdir } | PortUser::DtpService { workdir, .. } => Some(workdir),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PortUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortUser::Proxy { workdir } => write!(f, "{} proxy_port_number", workdir),
+            PortUser::DtpService {
+                workdir,
+                service_type,
+            } => write!(f, "{} dtp_services {} local_port", workdir, service_type),
+            PortUser::SuibaseApi => write!(f, "suibase_api_port_number"),
+            PortUser::DtpApi => write!(f, "dtp_api_port_number"),
+            PortUser::Webserver => write!(f, "webserver (sui-explorer) port"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortConflict {
+    pub port: u16,
+    pub users: Vec<PortUser>, // At least two, in config order.
+}
+
+impl PortConflict {
+    pub fn affects_workdir(&self, workdir: &str) -> bool {
+        self.users
+            .iter()
+            .any(|user| user.workdir() == Some(workdir))
+    }
+}
+
+impl fmt::Display for PortConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let users: Vec<String> = self.users.iter().map(|user| user.to_string()).collect();
+        write!(f, "port {} conflict: {}", self.port, users.join(", "))
+    }
+}
+
+// Returns *all* the conflicts (ordered by port number).
+//
+// 'configs' are the (workdir name, config) of every workdir loaded so far.
+pub fn find_port_conflicts(
+    workdirs: &GlobalsWorkdirsST,
+    configs: &[(String, &WorkdirUserConfig)],
+) -> Vec<PortConflict> {
+    let mut ports: BTreeMap<u16, Vec<PortUser>> = BTreeMap::new();
+    let mut add = |port: u16, user: PortUser| {
+        if port != 0 {
+            ports.entry(port).or_default().push(user);
+        }
+    };
+
+    add(workdirs.suibase_api_port, PortUser::SuibaseApi);
+    add(workdirs.dtp_api_port, PortUser::DtpApi);
+    add(workdirs.suibase_web_port, PortUser::Webserver);
+
+    for (workdir, config) in configs {
+        add(
+            config.proxy_port_number(),
+            PortUser::Proxy {
+                workdir: workdir.clone(),
+            },
+        );
+        for service in config.dtp_services() {
+            if let Some(local_port) = service.local_port() {
+                add(
+                    local_port,
+                    PortUser::DtpService {
+                        workdir: workdir.clone(),
+                        service_type: service.service_type().to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    ports
+        .into_iter()
+        .filter(|(_, users)| users.len() > 1)
+        .map(|(port, users)| PortConflict { port, users })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_config(dir: &std::path::Path, name: &str, yaml: &str) -> WorkdirUserConfig {
+        let path = dir.join(format!("{}.yaml", name));
+        std::fs::write(&path, yaml).unwrap();
+        let mut config = WorkdirUserConfig::new();
+        config
+            .load_and_merge_from_file(&path.to_string_lossy())
+            .unwrap();
+        config
+    }
+
+    #[test]
+    fn test_find_port_conflicts() {
+        let dir = std::env::temp_dir().join(format!("sb-port-conflicts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let workdirs = GlobalsWorkdirsST::new();
+
+        // Three conflicts:
+        //   - localnet and devnet proxy on 44340
+        //   - a localnet service on the suibase API port
+        //   - a devnet service on the port of a testnet service.
+        let localnet = load_config(
+            &dir,
+            "localnet",
+            "proxy_port_number: 44340\n\
+             dtp_services:\n\
+             \x20 - service_type: \"json-rpc\"\n\
+             \x20   client_auth: 0x1\n\
+             \x20   local_port: 44399\n",
+        );
+        let devnet = load_config(
+            &dir,
+            "devnet",
+            "proxy_port_number: 44340\n\
+             dtp_services:\n\
+             \x20 - service_type: \"ping\"\n\
+             \x20   local_port: 45000\n",
+        );
+        let testnet = load_config(
+            &dir,
+            "testnet",
+            "proxy_port_number: 44342\n\
+             dtp_services:\n\
+             \x20 - service_type: \"json-rpc\"\n\
+             \x20   local_port: 45000\n",
+        );
+        let configs = vec![
+            ("localnet".to_string(), &localnet),
+            ("devnet".to_string(), &devnet),
+            ("testnet".to_string(), &testnet),
+        ];
+
+        let conflicts = find_port_conflicts(&workdirs, &configs);
+        let ports: Vec<u16> = conflicts.iter().map(|c| c.port).collect();
+        assert_eq!(ports, vec![44340, 44399, 45000]);
+
+        assert_eq!(
+            conflicts[0].to_string(),
+            "port 44340 conflict: localnet proxy_port_number, devnet proxy_port_number"
+        );
+        assert_eq!(conflicts[1].users[0], PortUser::SuibaseApi);
+        assert!(conflicts[1].affects_workdir("localnet"));
+        assert!(!conflicts[1].affects_workdir("devnet"));
+        assert!(conflicts[2].affects_workdir("testnet"));
+
+        // Nothing to report without the conflicting workdirs.
+        let configs = vec![("testnet".to_string(), &testnet)];
+        assert!(find_port_conflicts(&workdirs, &configs).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
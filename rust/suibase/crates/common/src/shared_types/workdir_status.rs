@@ -0,0 +1,195 @@
+// Status of a workdir (or of one of its feature, like the multi-link RPC).
+//
+// Every transition is timestamped and has a cause, so the API can tell *since when*
+// and *why* something is DOWN.
+//
+// The wire format (Display/FromStr) is the same status words that the scripts
+// output (e.g. "OK", "DOWN") and that the API always returned.
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkdirState {
+    Initializing, // Not yet determined. Only the initial state.
+    Disabled,     // Workdir not initialized by the user.
+    Stopped,      // Initialized, but not started by the user.
+    Ok,
+    Degraded,
+    Down,
+    Unknown, // Status output could not be parsed.
+}
+
+impl WorkdirState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkdirState::Initializing => "INITIALIZING",
+            WorkdirState::Disabled => "DISABLED",
+            WorkdirState::Stopped => "STOPPED",
+            WorkdirState::Ok => "OK",
+            WorkdirState::Degraded => "DEGRADED",
+            WorkdirState::Down => "DOWN",
+            WorkdirState::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+impl fmt::Display for WorkdirState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WorkdirState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "INITIALIZING" => Ok(WorkdirState::Initializing),
+            "DISABLED" => Ok(WorkdirState::Disabled),
+            "STOPPED" => Ok(WorkdirState::Stopped),
+            "OK" => Ok(WorkdirState::Ok),
+            "DEGRADED" => Ok(WorkdirState::Degraded),
+            "DOWN" => Ok(WorkdirState::Down),
+            "UNKNOWN" => Ok(WorkdirState::Unknown),
+            _ => Err(anyhow::anyhow!("unknown workdir status [{}]", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkdirStatus {
+    state: WorkdirState,
+    since: DateTime<Utc>, // Time of the last transition.
+    cause: String,        // Cause of the last transition.
+}
+
+impl WorkdirStatus {
+    pub fn new() -> Self {
+        Self {
+            state: WorkdirState::Initializing,
+            since: Utc::now(),
+            cause: "daemon started".to_string(),
+        }
+    }
+
+    pub fn state(&self) -> WorkdirState {
+        self.state
+    }
+
+    pub fn since(&self) -> DateTime<Utc> {
+        self.since
+    }
+
+    pub fn cause(&self) -> &str {
+        &self.cause
+    }
+
+    pub fn is_operational(&self) -> bool {
+        matches!(self.state, WorkdirState::Ok | WorkdirState::Degraded)
+    }
+
+    // Apply a new state. Returns true if the state changed.
+    //
+    // Staying in the same state only refreshes the cause (the 'since' is preserved).
+    //
+    // Any determined state can go to any other one (the user can start/stop/reset a
+    // workdir at any time), but nothing goes back to Initializing. Such transition
+    // is logged and ignored (state preserved).
+    pub fn transition(&mut self, next: WorkdirState, cause: &str) -> bool {
+        self.transition_at(next, cause, Utc::now())
+    }
+
+    fn transition_at(&mut self, next: WorkdirState, cause: &str, now: DateTime<Utc>) -> bool {
+        if next == self.state {
+            if self.cause != cause {
+                self.cause = cause.to_string();
+            }
+            return false;
+        }
+        if next == WorkdirState::Initializing {
+            log::error!(
+                "illegal workdir status transition {} -> {} ({})",
+                self.state,
+                next,
+                cause
+            );
+            return false;
+        }
+        self.state = next;
+        self.since = now;
+        self.cause = cause.to_string();
+        true
+    }
+
+    // One line human-friendly summary (e.g. "DOWN since 2024-01-01T10:00:00Z (not started)")
+    pub fn summary_string(&self) -> String {
+        format!(
+            "{} since {} ({})",
+            self.state,
+            self.since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            self.cause
+        )
+    }
+}
+
+impl Default for WorkdirStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        let mut status = WorkdirStatus::new();
+        assert_eq!(status.state(), WorkdirState::Initializing);
+        assert!(!status.is_operational());
+
+        let t1 = Utc::now() + chrono::Duration::seconds(10);
+        assert!(status.transition_at(WorkdirState::Down, "not started", t1));
+        assert_eq!(status.since(), t1);
+        assert_eq!(status.cause(), "not started");
+
+        // Same state: cause refreshed, since preserved.
+        let t2 = t1 + chrono::Duration::seconds(10);
+        assert!(!status.transition_at(WorkdirState::Down, "no servers available", t2));
+        assert_eq!(status.since(), t1);
+        assert_eq!(status.cause(), "no servers available");
+
+        assert!(status.transition_at(WorkdirState::Degraded, "faucet down", t2));
+        assert!(status.is_operational());
+        assert!(status.summary_string().starts_with("DEGRADED since "));
+        assert!(status.summary_string().ends_with("(faucet down)"));
+    }
+
+    #[test]
+    fn test_illegal_transition_preserves_state() {
+        let mut status = WorkdirStatus::new();
+        status.transition(WorkdirState::Ok, "started");
+        let before = status.clone();
+
+        assert!(!status.transition(WorkdirState::Initializing, "bogus"));
+        assert_eq!(status, before);
+    }
+
+    #[test]
+    fn test_wire_format() {
+        for state in [
+            WorkdirState::Disabled,
+            WorkdirState::Stopped,
+            WorkdirState::Ok,
+            WorkdirState::Degraded,
+            WorkdirState::Down,
+            WorkdirState::Unknown,
+        ] {
+            assert_eq!(state.to_string().parse::<WorkdirState>().unwrap(), state);
+        }
+        assert_eq!(WorkdirState::Ok.to_string(), "OK");
+        assert!("RUNNING".parse::<WorkdirState>().is_err());
+    }
+}
@@ -2,7 +2,6 @@
 //
 // In particular, converts suibase.yaml to Rust structs.
 //
-use home::home_dir;
 use log::info;
 use std::collections::{HashMap, LinkedList};
 
@@ -11,6 +10,7 @@ use std::path::{Path, PathBuf};
 use anyhow::Result;
 
 use crate::basic_types::{ManagedElement, ManagedVec, ManagedVecU8, WorkdirIdx};
+use crate::utils::suibase_home;
 
 // workdir_idx are hard coded for performance.
 pub const WORKDIR_IDX_MAINNET: WorkdirIdx = 0;
@@ -61,6 +61,9 @@ pub struct DTPService {
     client_auth: Option<String>,
     server_auth: Option<String>,
     local_port: Option<u16>,
+    // Keep-alive of the client connections (off when no interval).
+    heartbeat_interval_secs: Option<u64>,
+    heartbeat_gas_budget: Option<u64>, // Mist, per connection.
 
     // "Calculated" fields not in the config.
     // 0 means 'wildcard' service_type and is used to configure
@@ -81,6 +84,8 @@ impl DTPService {
             client_auth: None,
             server_auth: None,
             local_port: None,
+            heartbeat_interval_secs: None,
+            heartbeat_gas_budget: None,
             service_idx: 0,
         }
     }
@@ -125,6 +130,14 @@ impl DTPService {
         self.local_port
     }
 
+    pub fn heartbeat_interval_secs(&self) -> Option<u64> {
+        self.heartbeat_interval_secs
+    }
+
+    pub fn heartbeat_gas_budget(&self) -> Option<u64> {
+        self.heartbeat_gas_budget
+    }
+
     pub fn service_idx(&self) -> u8 {
         self.service_idx
     }
@@ -273,6 +286,8 @@ impl WorkdirUserConfig {
         //     client_address: 0xef6e...
         //     remote_host: 0x6fff2...
         //     local_port: 45000
+        //     heartbeat_interval_secs: 30
+        //     heartbeat_gas_budget: 100000000
         //
         //   - service_type: "default"
         //     client_address: 0xc729...
@@ -389,6 +404,10 @@ impl WorkdirUserConfig {
                     let remote_host = service["remote_host"].as_str().map(|s| s.to_string()); // Optional
                     let local_port = service["local_port"].as_u64().map(|v| v as u16); // Optional
 
+                    // Keep-alive (optional).
+                    let heartbeat_interval_secs = service["heartbeat_interval_secs"].as_u64();
+                    let heartbeat_gas_budget = service["heartbeat_gas_budget"].as_u64();
+
                     let client_enabled = client_auth.is_some();
                     let server_enabled = server_auth.is_some();
 
@@ -403,6 +422,8 @@ impl WorkdirUserConfig {
                         client_auth,
                         server_auth,
                         local_port,
+                        heartbeat_interval_secs,
+                        heartbeat_gas_budget,
                         service_idx,
                     };
 
@@ -501,16 +522,15 @@ pub struct GlobalsWorkdirsST {
 
 impl GlobalsWorkdirsST {
     pub fn new() -> Self {
-        let home_dir = if let Some(home_dir) = home_dir() {
-            home_dir
+        // ~/suibase, unless overridden (see suibase_home.rs).
+        let suibase_home = if let Some(suibase_home) = suibase_home() {
+            suibase_home
         } else {
             // The program will likely fail to further initialize, so pointing to /tmp
             // in meantime is a reasonable default/fallback safe thing to do...
-            PathBuf::from("/tmp")
+            PathBuf::from("/tmp/suibase")
         };
 
-        let suibase_home = home_dir.join("suibase");
-
         // Generate all the suibase paths for state and config files of each WORKDIRS_KEYS.
         let mut workdirs = ManagedVec::new();
 
@@ -1,4 +1,6 @@
 // flatten everything under "common::utils" module.
 pub use self::strings::*;
+pub use self::suibase_home::*;
 
 mod strings;
+mod suibase_home;
@@ -15,3 +15,45 @@ pub fn remove_ascii_color_code(s: &str) -> String {
     }
     result
 }
+
+// Remove the ANSI escape sequences (colors, cursor moves, terminal title...) and the
+// other control characters, except newlines and tabs.
+//
+// Unlike remove_ascii_color_code(), a sequence not ending with 'm' (e.g. "\x1b[2K")
+// does not swallow the text that follows.
+pub fn strip_ansi_escapes(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameter and intermediate bytes, up to the final byte.
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if !('\x20'..='\x3f').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: up to BEL or ESC '\'.
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // Two characters sequence (e.g. "\x1bc").
+                _ => {}
+            },
+            '\n' | '\t' => result.push(c),
+            c if c.is_control() => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,97 @@
+// Location of the Suibase installation (normally ~/suibase).
+//
+// Resolved in this order:
+//   - the SUIBASE_HOME environment variable.
+//   - the path in the ~/.suibase-location pointer file (first non-empty line).
+//   - ~/suibase
+//
+// A leading "~/" is expanded in both overrides.
+//
+// This file is also compiled into the helper crate (see rust/helper/src/lib.rs), so it
+// must depend only on std and the "home" crate.
+use std::path::{Path, PathBuf};
+
+pub const SUIBASE_HOME_ENV: &str = "SUIBASE_HOME";
+
+// Relative to the user home.
+pub const SUIBASE_LOCATION_FILE: &str = ".suibase-location";
+
+// None only when the user home is unknown (and SUIBASE_HOME is not set).
+pub fn suibase_home() -> Option<PathBuf> {
+    let env_value = std::env::var(SUIBASE_HOME_ENV).ok();
+    resolve_suibase_home(env_value.as_deref(), home::home_dir().as_deref())
+}
+
+// Same as suibase_home(), with the environment variable value and the user home
+// passed by the caller.
+pub fn resolve_suibase_home(env_value: Option<&str>, home: Option<&Path>) -> Option<PathBuf> {
+    if let Some(env_value) = env_value.map(str::trim).filter(|value| !value.is_empty()) {
+        return Some(expand_home(env_value, home));
+    }
+    let home = home?;
+    if let Ok(contents) = std::fs::read_to_string(home.join(SUIBASE_LOCATION_FILE)) {
+        if let Some(line) = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+        {
+            return Some(expand_home(line, Some(home)));
+        }
+    }
+    Some(home.join("suibase"))
+}
+
+// A leading "~/" replaced by the user home (when known).
+pub fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_suibase_home() {
+        let home = std::env::temp_dir().join(format!("sb-home-resolve-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+
+        // Default.
+        assert_eq!(
+            resolve_suibase_home(None, Some(&home)),
+            Some(home.join("suibase"))
+        );
+        assert_eq!(resolve_suibase_home(None, None), None);
+
+        // Pointer file.
+        let pointer = home.join(SUIBASE_LOCATION_FILE);
+        std::fs::write(&pointer, "\n  /opt/tools/suibase  \n").unwrap();
+        assert_eq!(
+            resolve_suibase_home(None, Some(&home)),
+            Some(PathBuf::from("/opt/tools/suibase"))
+        );
+        std::fs::write(&pointer, "~/shared/suibase\n").unwrap();
+        assert_eq!(
+            resolve_suibase_home(Some(" "), Some(&home)),
+            Some(home.join("shared/suibase"))
+        );
+
+        // The environment variable wins.
+        assert_eq!(
+            resolve_suibase_home(Some("/mnt/suibase"), Some(&home)),
+            Some(PathBuf::from("/mnt/suibase"))
+        );
+        assert_eq!(
+            resolve_suibase_home(Some("~/sb"), Some(&home)),
+            Some(home.join("sb"))
+        );
+        assert_eq!(
+            resolve_suibase_home(Some("/mnt/suibase"), None),
+            Some(PathBuf::from("/mnt/suibase"))
+        );
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+}
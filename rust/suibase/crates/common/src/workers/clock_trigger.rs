@@ -0,0 +1,144 @@
+// Generate periodical audit message toward other threads.
+//
+// Shared by the daemons. The audit of the AdminController is sent from here, the
+// periodic events toward the other threads of a daemon (e.g. its NetworkMonitor) are
+// sent by its ClockTriggerHooks.
+use anyhow::Result;
+use axum::async_trait;
+use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+
+use crate::basic_types::{self, AdminControllerMsg, AdminControllerTx, AutoThread, Runnable};
+
+use tokio::time::{interval, Duration};
+
+#[async_trait]
+pub trait ClockTriggerHooks: Clone + Send + Sync + 'static {
+    // Called every second. The first tick is 1.
+    async fn on_tick(&self, tick: u64);
+}
+
+#[derive(Clone)]
+pub struct ClockTriggerParams<H: ClockTriggerHooks> {
+    hooks: H,
+    admctrl_tx: AdminControllerTx,
+}
+
+impl<H: ClockTriggerHooks> ClockTriggerParams<H> {
+    pub fn new(hooks: H, admctrl_tx: AdminControllerTx) -> Self {
+        Self { hooks, admctrl_tx }
+    }
+}
+
+pub struct ClockTrigger<H: ClockTriggerHooks> {
+    auto_thread: AutoThread<ClockTriggerThread<H>, ClockTriggerParams<H>>,
+}
+
+impl<H: ClockTriggerHooks> ClockTrigger<H> {
+    pub fn new(params: ClockTriggerParams<H>) -> Self {
+        Self {
+            auto_thread: AutoThread::new("ClockTrigger".to_string(), params),
+        }
+    }
+
+    pub async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.auto_thread.run(subsys).await
+    }
+}
+
+struct ClockTriggerThread<H: ClockTriggerHooks> {
+    name: String,
+    params: ClockTriggerParams<H>,
+}
+
+#[async_trait]
+impl<H: ClockTriggerHooks> Runnable<ClockTriggerParams<H>> for ClockTriggerThread<H> {
+    fn new(name: String, params: ClockTriggerParams<H>) -> Self {
+        Self { name, params }
+    }
+
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        log::info!("{} started", self.name);
+
+        match self.event_loop(&subsys).cancel_on_shutdown(&subsys).await {
+            Ok(()) => {
+                log::info!("normal thread exit (2)");
+                Ok(())
+            }
+            Err(_cancelled_by_shutdown) => {
+                log::info!("normal thread exit (1)");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<H: ClockTriggerHooks> ClockTriggerThread<H> {
+    async fn event_loop(&mut self, subsys: &SubsystemHandle) {
+        let mut interval = interval(Duration::from_secs(1));
+        let mut tick: u64 = 0;
+        loop {
+            tick += 1;
+
+            interval.tick().await;
+            if subsys.is_shutdown_requested() {
+                return;
+            }
+
+            self.params.hooks.on_tick(tick).await;
+
+            if (tick % 5) == 2 {
+                // Every 5 seconds, with first one ~2 seconds after start.
+                let mut msg = AdminControllerMsg::new();
+                msg.event_id = basic_types::EVENT_AUDIT;
+                let result = self.params.admctrl_tx.send(msg).await;
+                if let Err(e) = result {
+                    log::error!("admctrl_tx send_event_audit {}", e);
+                    // TODO This is bad if sustain for many seconds. Add watchdog here.
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic_types::MPSC_Q_SIZE;
+    use std::sync::{Arc, Mutex};
+    use tokio_graceful_shutdown::{SubsystemBuilder, Toplevel};
+
+    #[derive(Clone, Default)]
+    struct TestHooks {
+        ticks: Arc<Mutex<Vec<u64>>>,
+    }
+
+    #[async_trait]
+    impl ClockTriggerHooks for TestHooks {
+        async fn on_tick(&self, tick: u64) {
+            self.ticks.lock().unwrap().push(tick);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clock_trigger_ticks() {
+        let hooks = TestHooks::default();
+        let (admctrl_tx, mut admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let clock = ClockTrigger::new(ClockTriggerParams::new(hooks.clone(), admctrl_tx));
+        let toplevel = tokio::spawn(
+            Toplevel::new(move |s| async move {
+                s.start(SubsystemBuilder::new("clock", |a| clock.run(a)));
+            })
+            .handle_shutdown_requests(Duration::from_millis(1000)),
+        );
+
+        // First audit of the AdminController at the second tick (immediate first tick).
+        let msg = admctrl_rx.recv().await.unwrap();
+        assert_eq!(msg.event_id, basic_types::EVENT_AUDIT);
+        assert_eq!(*hooks.ticks.lock().unwrap(), vec![1, 2]);
+
+        // Next one 5 seconds later, with every tick given to the hooks.
+        admctrl_rx.recv().await.unwrap();
+        assert_eq!(*hooks.ticks.lock().unwrap(), (1..=7).collect::<Vec<u64>>());
+        toplevel.abort();
+    }
+}
@@ -1,10 +1,14 @@
 // This is a submodule specific to suibase-daemon.
 //
 // flatten everything under "common::workders" module.
+pub use self::clock_trigger::*;
+pub use self::poller::*;
+pub use self::request_worker::*;
 pub use self::shell_worker::*;
 pub use self::subscription_tracking::*;
-pub use self::poller::*;
 
+mod clock_trigger;
+mod poller;
+mod request_worker;
 mod shell_worker;
 mod subscription_tracking;
-mod poller;
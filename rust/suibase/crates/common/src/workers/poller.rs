@@ -5,6 +5,9 @@
 //
 // The polling action is done from a tokio task and is auto-restarted on panic!
 //
+// Optionally, the PollerWorker can also self-schedule its periodic polling (see PollerSchedule).
+//
+use std::hash::Hasher;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -12,6 +15,7 @@ use anyhow::Result;
 use axum::async_trait;
 
 use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
 use tokio_graceful_shutdown::{FutureExt, NestedSubsystem, SubsystemBuilder, SubsystemHandle};
 
 use crate::{
@@ -28,6 +32,116 @@ pub trait PollingTrait: Send {
     async fn update(&mut self);
 }
 
+// Self-scheduling of the periodic polling.
+//
+// Many pollers (one per workdir) firing at the exact same fixed interval end up synchronized
+// and cause periodic CPU/IO spikes. The jitter randomize every cycle by up to +/- jitter_pct
+// of the period to spread the load.
+//
+// When drift_correction is true, the next tick is based on the original schedule instead of
+// the completion time of the previous update. Ticks missed because of a slow update are
+// skipped (never "pile-up").
+#[derive(Clone, Debug)]
+pub struct PollerSchedule {
+    period: Duration,
+    jitter_pct: u8, // 0 to 100
+    drift_correction: bool,
+
+    // Next tick on the original schedule (before jitter is applied).
+    anchor: Option<Instant>,
+    rng_state: u64,
+}
+
+impl PollerSchedule {
+    pub fn new(period: Duration, jitter_pct: u8, drift_correction: bool) -> Self {
+        // Weak seed, but good enough to de-synchronize multiple pollers.
+        let mut hasher = twox_hash::XxHash64::with_seed(0);
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+        );
+        // Make sure pollers created at the same time still get a different seed.
+        static INSTANCE_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        hasher.write_u64(INSTANCE_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed));
+        Self {
+            period,
+            jitter_pct: jitter_pct.min(100),
+            drift_correction,
+            anchor: None,
+            rng_state: hasher.finish() | 1, // xorshift state must never be zero.
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn jitter_pct(&self) -> u8 {
+        self.jitter_pct
+    }
+
+    pub fn is_drift_correction(&self) -> bool {
+        self.drift_correction
+    }
+
+    // Forget the original schedule. Next call to next_deadline() starts a new one.
+    pub fn reset(&mut self) {
+        self.anchor = None;
+    }
+
+    // Returns when the next tick should fire. 'now' is when the previous update completed
+    // (or when the polling starts).
+    //
+    // The returned deadline is always in the future relative to 'now'.
+    pub fn next_deadline(&mut self, now: Instant) -> Instant {
+        let anchor = match self.anchor {
+            Some(anchor) if self.drift_correction => anchor,
+            _ => now,
+        };
+
+        // Advance the anchor until a tick lands in the future. This is where the
+        // ticks missed during a slow update get skipped.
+        let mut anchor = anchor + self.period;
+        loop {
+            let deadline = self.apply_jitter(anchor);
+            if deadline > now {
+                self.anchor = Some(anchor);
+                return deadline;
+            }
+            anchor += self.period;
+        }
+    }
+
+    fn apply_jitter(&mut self, anchor: Instant) -> Instant {
+        if self.jitter_pct == 0 {
+            return anchor;
+        }
+        let max_jitter_nanos = self.period.as_nanos() * self.jitter_pct as u128 / 100;
+        if max_jitter_nanos == 0 {
+            return anchor;
+        }
+        // Uniform offset in [-max_jitter, +max_jitter]
+        let offset = (self.next_random() as u128) % (2 * max_jitter_nanos + 1);
+        if offset >= max_jitter_nanos {
+            anchor + Duration::from_nanos((offset - max_jitter_nanos) as u64)
+        } else {
+            anchor - Duration::from_nanos((max_jitter_nanos - offset) as u64)
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // xorshift64
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+}
+
 #[allow(dead_code)]
 // T: A "trait object" implementing PollingTrait
 // P: The parameter needed to instantiate T.
@@ -53,7 +167,9 @@ where
     T: Instantiable<P> + PollingTrait + 'static,
     P: WorkdirContext + Clone,
 {
-    pub fn new(params: P, subsys: &SubsystemHandle) -> Self {
+    // When a schedule is specified, the PollerWorker will also periodically call
+    // update() on its own (otherwise, the instantiator must send EVENT_AUDIT).
+    pub fn new(params: P, schedule: Option<PollerSchedule>, subsys: &SubsystemHandle) -> Self {
         let (poller_tx, poller_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
 
         let polling_trait_obj = Arc::new(Mutex::new(T::new(params.clone())));
@@ -63,6 +179,7 @@ where
             poller_rx,
             poller_tx.clone(),
             params.workdir_idx(),
+            schedule,
         );
 
         let poller_worker = InnerPollerWorker::new(poller_params.clone());
@@ -96,6 +213,7 @@ pub struct InnerPollerWorkerParams {
     event_tx: GenericTx,             // To send messages to self.
     workdir_idx: WorkdirIdx,
     workdir_name: String,
+    schedule: Option<PollerSchedule>,
 }
 
 impl InnerPollerWorkerParams {
@@ -104,6 +222,7 @@ impl InnerPollerWorkerParams {
         event_rx: GenericRx,
         event_tx: GenericTx,
         workdir_idx: WorkdirIdx,
+        schedule: Option<PollerSchedule>,
     ) -> Self {
         Self {
             polling_object,
@@ -111,6 +230,7 @@ impl InnerPollerWorkerParams {
             event_tx,
             workdir_idx,
             workdir_name: WORKDIRS_KEYS[workdir_idx as usize].to_string(),
+            schedule,
         }
     }
 
@@ -142,15 +262,18 @@ struct PollerWorkerTask {
     task_name: String,
     params: InnerPollerWorkerParams,
     last_update_timestamp: Option<tokio::time::Instant>,
+    schedule: Option<PollerSchedule>,
 }
 
 #[async_trait]
 impl Runnable<InnerPollerWorkerParams> for PollerWorkerTask {
     fn new(task_name: String, params: InnerPollerWorkerParams) -> Self {
+        let schedule = params.schedule.clone();
         Self {
             task_name,
             params,
             last_update_timestamp: None,
+            schedule,
         }
     }
 
@@ -248,9 +371,32 @@ impl PollerWorkerTask {
         remove_generic_event_dups(&mut event_rx, &self.params.event_tx);
         mpsc_q_check!(event_rx); // Just to help verify if the Q unexpectedly "accumulate".
 
+        // When self-scheduled, the first tick is one period (+/- jitter) after the task start.
+        let mut next_tick: Option<Instant> = self
+            .schedule
+            .as_mut()
+            .map(|schedule| schedule.next_deadline(Instant::now()));
+
         while !subsys.is_shutdown_requested() {
-            // Wait for a message.
-            if let Some(msg) = event_rx.recv().await {
+            // Wait for a message (or the next scheduled tick).
+            let msg = if let Some(deadline) = next_tick {
+                tokio::select! {
+                    msg = event_rx.recv() => msg,
+                    _ = tokio::time::sleep_until(deadline) => {
+                        self.process_scheduled_tick().await;
+                        // Next tick is calculated after completion of the update.
+                        next_tick = self
+                            .schedule
+                            .as_mut()
+                            .map(|schedule| schedule.next_deadline(Instant::now()));
+                        continue;
+                    }
+                }
+            } else {
+                event_rx.recv().await
+            };
+
+            if let Some(msg) = msg {
                 mpsc_q_check!(event_rx);
                 match msg.event_id {
                     basic_types::EVENT_AUDIT => {
@@ -271,4 +417,120 @@ impl PollerWorkerTask {
             }
         }
     }
+
+    async fn process_scheduled_tick(&mut self) {
+        // Same as an audit (periodic tentative update), but self-generated.
+        let force = false;
+        self.callback_update_in_trait_object(force).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Simulate the PollerWorkerTask loop with a handler taking 'handler_duration'.
+    // Returns the spacing between every consecutive ticks.
+    async fn simulate_ticks(
+        schedule: &mut PollerSchedule,
+        handler_duration: Duration,
+        n_ticks: usize,
+    ) -> Vec<Duration> {
+        let mut ticks: Vec<Instant> = Vec::with_capacity(n_ticks);
+        let mut deadline = schedule.next_deadline(Instant::now());
+        for _ in 0..n_ticks {
+            tokio::time::sleep_until(deadline).await;
+            ticks.push(Instant::now());
+            tokio::time::sleep(handler_duration).await;
+            deadline = schedule.next_deadline(Instant::now());
+        }
+        ticks.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    fn mean_secs(spacings: &[Duration]) -> f64 {
+        spacings.iter().map(|d| d.as_secs_f64()).sum::<f64>() / spacings.len() as f64
+    }
+
+    // Timer resolution of tokio is 1 millisecond.
+    const TOLERANCE: Duration = Duration::from_millis(2);
+
+    fn assert_near(actual: Duration, expected: Duration) {
+        let diff = if actual > expected {
+            actual - expected
+        } else {
+            expected - actual
+        };
+        assert!(diff <= TOLERANCE, "{:?} != {:?}", actual, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fixed_schedule_with_drift_correction() {
+        let period = Duration::from_secs(1);
+        let mut schedule = PollerSchedule::new(period, 0, true);
+        let spacings = simulate_ticks(&mut schedule, Duration::from_millis(300), 50).await;
+        for spacing in spacings {
+            assert_near(spacing, period);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fixed_schedule_without_drift_correction() {
+        // Next tick is based on the completion time, so the handler duration accumulates.
+        let period = Duration::from_secs(1);
+        let handler = Duration::from_millis(300);
+        let mut schedule = PollerSchedule::new(period, 0, false);
+        let spacings = simulate_ticks(&mut schedule, handler, 50).await;
+        for spacing in spacings {
+            assert_near(spacing, period + handler);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_jitter_statistics() {
+        let period = Duration::from_secs(1);
+        let jitter_pct = 20;
+        let mut schedule = PollerSchedule::new(period, jitter_pct, true);
+        let spacings = simulate_ticks(&mut schedule, Duration::from_millis(10), 1000).await;
+
+        // Each tick is within +/-20% of its anchor, so the spacing between two
+        // ticks is within +/-40% of the period.
+        let min = Duration::from_millis(600) - TOLERANCE;
+        let max = Duration::from_millis(1400) + TOLERANCE;
+        for spacing in &spacings {
+            assert!(*spacing >= min && *spacing <= max, "{:?}", spacing);
+        }
+
+        // Drift corrected, so the average remains the period.
+        let mean = mean_secs(&spacings);
+        assert!((mean - 1.0).abs() < 0.01, "mean {}", mean);
+
+        // Verify there is really some randomization.
+        let variance = spacings
+            .iter()
+            .map(|d| (d.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / spacings.len() as f64;
+        assert!(variance.sqrt() > 0.05, "std dev {}", variance.sqrt());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_handler_no_pile_up() {
+        // A handler taking 2.5 periods. The missed ticks should be skipped
+        // instead of firing back-to-back.
+        let period = Duration::from_secs(1);
+        let handler = Duration::from_millis(2500);
+
+        let mut schedule = PollerSchedule::new(period, 0, true);
+        let spacings = simulate_ticks(&mut schedule, handler, 20).await;
+        for spacing in &spacings {
+            // Stays aligned on the original schedule (next slot after completion).
+            assert_near(*spacing, Duration::from_secs(3));
+        }
+
+        let mut schedule = PollerSchedule::new(period, 20, true);
+        let spacings = simulate_ticks(&mut schedule, handler, 200).await;
+        for spacing in &spacings {
+            assert!(*spacing > handler, "{:?}", spacing);
+        }
+    }
 }
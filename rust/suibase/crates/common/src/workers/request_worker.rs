@@ -0,0 +1,165 @@
+// Thread to initiate requests toward the target servers of a proxy (e.g. health check).
+//
+// The request goes through the proxy_server of the daemon (on localhost) with the
+// X-SBSD-SERVER-IDX header to force the target server. The proxy_server does all the
+// stats accumulation, so the outcome of the request is ignored here.
+//
+// Going through the proxy_server means the request reaching the link is built and sent
+// exactly like the user traffic (same headers added, same client and connections).
+//
+// Shared by the daemons. Each provides its own message type (see ServerCheckMsg).
+use anyhow::Result;
+use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+
+use crate::mpsc_q_check;
+
+pub const HEADER_SBSD_SERVER_IDX: &str = "X-SBSD-SERVER-IDX";
+pub const HEADER_SBSD_SERVER_HC: &str = "X-SBSD-SERVER-HC";
+
+const SERVER_CHECK_REQUEST_BODY: &str =
+    "{\"jsonrpc\":\"2.0\",\"method\":\"suix_getLatestSuiSystemState\",\"id\":1,\"params\":[\"\"]}";
+
+// What the RequestWorker needs from a daemon message (e.g. NetmonMsg).
+pub trait ServerCheckMsg: Send + 'static {
+    fn server_idx(&self) -> u8;
+
+    // Port of the proxy_server to send the request to.
+    fn proxy_port(&self) -> u16;
+
+    fn is_proxy_tls(&self) -> bool {
+        false
+    }
+}
+
+pub struct RequestWorker<M: ServerCheckMsg> {
+    msg_rx: tokio::sync::mpsc::Receiver<M>,
+    client: reqwest::Client,
+    tls_client: reqwest::Client, // For a proxy port with proxy_tls.
+}
+
+impl<M: ServerCheckMsg> RequestWorker<M> {
+    pub fn new(msg_rx: tokio::sync::mpsc::Receiver<M>) -> Self {
+        Self {
+            msg_rx,
+            client: reqwest::Client::new(),
+            // Only used toward our own proxy on localhost, which may use a
+            // self-signed cert not trusted by the system.
+            tls_client: reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    async fn do_request(&mut self, msg: M) {
+        let server_idx = msg.server_idx().to_string();
+
+        let (client, scheme) = if msg.is_proxy_tls() {
+            (&self.tls_client, "https")
+        } else {
+            (&self.client, "http")
+        };
+        let uri = format!("{}://localhost:{}", scheme, msg.proxy_port());
+        let _ = client
+            .request(reqwest::Method::POST, uri)
+            .timeout(std::time::Duration::from_secs(5))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::USER_AGENT, "curl/7.68.0")
+            .header(reqwest::header::ACCEPT, "*/*")
+            .header(HEADER_SBSD_SERVER_IDX, server_idx.as_str())
+            .header(HEADER_SBSD_SERVER_HC, "1")
+            .body(SERVER_CHECK_REQUEST_BODY)
+            .send()
+            .await;
+
+        // No error return here... never. Any failure of the request already
+        // reflected by its execution by the proxy-server.
+    }
+
+    async fn event_loop(&mut self, subsys: &SubsystemHandle) {
+        while !subsys.is_shutdown_requested() {
+            // Wait for a message.
+            if let Some(msg) = self.msg_rx.recv().await {
+                mpsc_q_check!(self.msg_rx);
+                // Process the message.
+                self.do_request(msg).await;
+            } else {
+                // Channel closed or shutdown requested.
+                return;
+            }
+        }
+    }
+
+    pub async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        log::info!("started");
+
+        match self.event_loop(&subsys).cancel_on_shutdown(&subsys).await {
+            Ok(()) => {
+                log::info!("normal thread exit (2)");
+                Ok(())
+            }
+            Err(_cancelled_by_shutdown) => {
+                log::info!("normal thread exit (1)");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic_types::MPSC_Q_SIZE;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_graceful_shutdown::{SubsystemBuilder, Toplevel};
+
+    struct TestMsg(u8, u16);
+
+    impl ServerCheckMsg for TestMsg {
+        fn server_idx(&self) -> u8 {
+            self.0
+        }
+        fn proxy_port(&self) -> u16 {
+            self.1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_check_request() {
+        // Stand-in for the proxy_server. Captures the request head.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_port = listener.local_addr().unwrap().port();
+        let captured = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await;
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+
+        let (msg_tx, msg_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let worker = RequestWorker::<TestMsg>::new(msg_rx);
+        let toplevel = tokio::spawn(
+            Toplevel::new(move |s| async move {
+                s.start(SubsystemBuilder::new("request-worker", |a| worker.run(a)));
+            })
+            .handle_shutdown_requests(std::time::Duration::from_millis(1000)),
+        );
+        msg_tx.send(TestMsg(3, proxy_port)).await.unwrap();
+
+        let request = captured.await.unwrap();
+        assert!(request.starts_with("post / http/1.1"));
+        assert!(request.contains("x-sbsd-server-idx: 3\r\n"));
+        assert!(request.contains("x-sbsd-server-hc: 1\r\n"));
+        toplevel.abort();
+    }
+}
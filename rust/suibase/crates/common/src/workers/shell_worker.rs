@@ -4,7 +4,16 @@
 //   - Run concurrently when for different workdir.
 //   - Run sequentially when for the same workdir.
 //
+// The commands do not inherit the daemon environment (e.g. the RUST_LOG of the daemon
+// was making the sui client logs interleave with its stdout). See shell_env().
+//
+// The stdout and stderr kept are each capped to SHELL_OUTPUT_MAX_BYTES (the rest is
+// read and discarded, so the command is never blocked on a full pipe). Non-UTF8
+// output is replaced with U+FFFD instead of failing the command.
+//
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
 use tokio::time::{self, Duration};
 
@@ -13,13 +22,138 @@ use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
 
 use crate::basic_types::{GenericChannelMsg, GenericRx, WorkdirIdx};
 use crate::mpsc_q_check;
+use crate::utils::{suibase_home, SUIBASE_HOME_ENV};
 
 use home::home_dir;
 
+// Only these variables are kept from the daemon environment.
+pub const SHELL_ENV_ALLOWLIST: [&str; 9] = [
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_ALL", "TZ", "TMPDIR",
+];
+
+// Applied last (after the caller overrides), unless the caller opts out.
+pub const SHELL_ENV_FORCED: [(&str, &str); 1] = [("RUST_LOG", "error")];
+
+// Default cap of each of stdout and stderr. A caller can change it with the
+// "max_output_bytes" of the data_json of the EVENT_EXEC message.
+pub const SHELL_OUTPUT_MAX_BYTES: usize = 1024 * 1024;
+
+// Start of the line appended to a capped output.
+pub const SHELL_OUTPUT_TRUNCATED_MARKER: &str = "[output truncated:";
+
+// Values of the variables with one of these in their name are not logged.
+const SHELL_ENV_SECRET_MARKERS: [&str; 6] =
+    ["SECRET", "TOKEN", "PASSWORD", "PASSPHRASE", "KEY", "AUTH"];
+
+// Build the environment of a command.
+//
+// 'inherited' is the daemon environment. The caller can add/override variables
+// with the optional data_json of the EVENT_EXEC message. Example:
+//
+//   { "env": { "SUI_CLIENT_ARGS": "--json" }, "forced_env": false }
+//
+// "forced_env": false disables SHELL_ENV_FORCED (e.g. to debug with RUST_LOG).
+pub fn shell_env(
+    inherited: impl IntoIterator<Item = (String, String)>,
+    data_json: Option<&serde_json::Value>,
+) -> BTreeMap<String, String> {
+    let mut env: BTreeMap<String, String> = inherited
+        .into_iter()
+        .filter(|(name, _)| SHELL_ENV_ALLOWLIST.contains(&name.as_str()))
+        .collect();
+
+    let mut forced_env = true;
+    if let Some(data_json) = data_json {
+        if let Some(overrides) = data_json["env"].as_object() {
+            for (name, value) in overrides {
+                match value.as_str() {
+                    Some(value) => {
+                        env.insert(name.clone(), value.to_string());
+                    }
+                    None => log::warn!("shell env {} ignored (not a string)", name),
+                }
+            }
+        }
+        if let Some(value) = data_json["forced_env"].as_bool() {
+            forced_env = value;
+        }
+    }
+
+    if forced_env {
+        for (name, value) in SHELL_ENV_FORCED {
+            env.insert(name.to_string(), value.to_string());
+        }
+    }
+    env
+}
+
+// Copy of an env that is safe to log.
+pub fn redact_shell_env(env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(name, value)| {
+            let upper_name = name.to_uppercase();
+            if SHELL_ENV_SECRET_MARKERS
+                .iter()
+                .any(|marker| upper_name.contains(marker))
+            {
+                (name.clone(), "<redacted>".to_string())
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+pub fn shell_output_max_bytes(data_json: Option<&serde_json::Value>) -> usize {
+    data_json
+        .and_then(|data_json| data_json["max_output_bytes"].as_u64())
+        .and_then(|max| usize::try_from(max).ok())
+        .unwrap_or(SHELL_OUTPUT_MAX_BYTES)
+}
+
+// Read up to EOF, but keep only the first max_bytes.
+//
+// A read error ends the output (what was read so far is kept).
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, max_bytes: usize) -> String {
+    let mut reader = match reader {
+        Some(reader) => reader,
+        None => return String::new(),
+    };
+    let mut kept: Vec<u8> = Vec::new();
+    let mut total: usize = 0;
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = max_bytes.saturating_sub(kept.len());
+                kept.extend_from_slice(&buf[..n.min(room)]);
+                total = total.saturating_add(n);
+            }
+        }
+    }
+    capped_output_to_string(&kept, total)
+}
+
+pub fn capped_output_to_string(kept: &[u8], total: usize) -> String {
+    let output = String::from_utf8_lossy(kept).to_string();
+    if total <= kept.len() {
+        return output;
+    }
+    format!(
+        "{}\n{} {} of {} bytes]",
+        output,
+        SHELL_OUTPUT_TRUNCATED_MARKER,
+        kept.len(),
+        total
+    )
+}
+
 pub struct ShellWorker {
     event_rx: GenericRx,
     workdir_idx: Option<WorkdirIdx>,
     home_dir: PathBuf,
+    suibase_home: PathBuf, // cwd of the commands.
 }
 
 impl ShellWorker {
@@ -29,10 +163,12 @@ impl ShellWorker {
         } else {
             PathBuf::from("/tmp")
         };
+        let suibase_home = suibase_home().unwrap_or_else(|| home_dir.join("suibase"));
         Self {
             event_rx,
             workdir_idx,
             home_dir,
+            suibase_home,
         }
     }
 
@@ -69,13 +205,23 @@ impl ShellWorker {
             resp = Some(pre_call_error);
         } else {
             let cmd = &msg.command.clone().unwrap();
-            let cwd = format!("{}/suibase", self.home_dir.display());
+            let cwd = self.suibase_home.display().to_string();
+
+            let mut env = shell_env(std::env::vars(), msg.data_json.as_ref());
+            env.entry("HOME".to_string())
+                .or_insert_with(|| self.home_dir.display().to_string());
+            // The scripts work on the same Suibase location as the daemon (see __globals.sh).
+            env.insert(
+                SUIBASE_HOME_ENV.to_string(),
+                self.suibase_home.display().to_string(),
+            );
 
             if !is_status_call {
                 log::info!(
-                    "do_exec() cwd={} cmd={:?} for workdir_idx={:?}",
+                    "do_exec() cwd={} cmd={:?} env={:?} for workdir_idx={:?}",
                     cwd,
                     msg,
+                    redact_shell_env(&env),
                     msg.workdir_idx
                 );
             }
@@ -83,6 +229,8 @@ impl ShellWorker {
             // Execute the command as if it was a bash script.
             let child = Command::new("bash")
                 .current_dir(cwd)
+                .env_clear()
+                .envs(&env)
                 .arg("-c")
                 .arg(cmd)
                 .stdout(std::process::Stdio::piped())
@@ -97,59 +245,51 @@ impl ShellWorker {
                 log::error!("{}", error_msg);
                 resp = Some(error_msg);
             } else {
-                let child = child.unwrap();
+                let mut child = child.unwrap();
                 let timeout = Duration::from_secs(if is_status_call { 30 } else { 60 });
-                let timeout_result = time::timeout(
-                    timeout,
-                    tokio::task::spawn_blocking(move || child.wait_with_output()),
-                )
+                let max_bytes = shell_output_max_bytes(msg.data_json.as_ref());
+                let stdout = child.stdout.take();
+                let stderr = child.stderr.take();
+                let timeout_result = time::timeout(timeout, async {
+                    let (stdout, stderr) = tokio::join!(
+                        read_capped(stdout, max_bytes),
+                        read_capped(stderr, max_bytes)
+                    );
+                    child.wait().await.map(|status| (status, stdout, stderr))
+                })
                 .await;
 
                 match timeout_result {
-                    Ok(spawn_result) => match spawn_result {
-                        Ok(output_future) => match output_future.await {
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                                let mut outputs = if stderr.is_empty() {
-                                    stdout
-                                } else {
-                                    format!("{}\n{}", stderr, stdout)
-                                };
-                                outputs = outputs.trim().to_string();
-                                if output.status.success() {
-                                    resp = Some(outputs);
-                                } else {
-                                    let error_msg = format!(
-                                        "Error: do_exec({:?}, {:?}) returned {}",
-                                        msg.workdir_idx, cmd, outputs
-                                    );
-                                    if !is_status_call {
-                                        log::error!("{}", error_msg);
-                                    }
-                                    resp = Some(error_msg);
-                                }
-                            }
-                            Err(e) => {
-                                let error_msg = format!(
-                                    "Error: do_exec({:?}, {:?}) command call failed: {}",
-                                    msg.workdir_idx, cmd, e
-                                );
-                                if !is_status_call {
-                                    log::error!("{}", error_msg);
-                                }
-                                resp = Some(error_msg);
-                            }
-                        },
-                        Err(e) => {
+                    Ok(Ok((status, stdout, stderr))) => {
+                        let mut outputs = if stderr.is_empty() {
+                            stdout
+                        } else {
+                            format!("{}\n{}", stderr, stdout)
+                        };
+                        outputs = outputs.trim().to_string();
+                        if status.success() {
+                            resp = Some(outputs);
+                        } else {
                             let error_msg = format!(
-                                "Error: do_exec({:?}, {:?}) join error: {}",
-                                msg.workdir_idx, cmd, e
+                                "Error: do_exec({:?}, {:?}) returned {}",
+                                msg.workdir_idx, cmd, outputs
                             );
-                            log::error!("{}", error_msg);
+                            if !is_status_call {
+                                log::error!("{}", error_msg);
+                            }
                             resp = Some(error_msg);
                         }
-                    },
+                    }
+                    Ok(Err(e)) => {
+                        let error_msg = format!(
+                            "Error: do_exec({:?}, {:?}) command call failed: {}",
+                            msg.workdir_idx, cmd, e
+                        );
+                        if !is_status_call {
+                            log::error!("{}", error_msg);
+                        }
+                        resp = Some(error_msg);
+                    }
                     // Handle timeout error.
                     Err(e) => {
                         let error_msg = format!(
@@ -212,3 +352,151 @@ impl ShellWorker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic_types::{EVENT_EXEC, MPSC_Q_SIZE};
+
+    async fn exec_env(worker: &mut ShellWorker, data_json: Option<serde_json::Value>) -> String {
+        let (resp_tx, resp_rx) = tokio::sync::oneshot::channel();
+        let mut msg = GenericChannelMsg::new();
+        msg.event_id = EVENT_EXEC;
+        msg.command = Some("env".to_string());
+        msg.data_json = data_json;
+        msg.resp_channel = Some(resp_tx);
+        worker.do_exec(msg).await;
+        resp_rx.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exec_env() {
+        let home = std::env::temp_dir().join(format!("sb-shell-env-{}", std::process::id()));
+        std::fs::create_dir_all(home.join("suibase")).unwrap();
+        std::env::set_var("SB_SHELL_WORKER_LEAK", "1");
+
+        let (_tx, rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let mut worker = ShellWorker::new(rx, None);
+        worker.home_dir = home.clone();
+        worker.suibase_home = home.join("suibase");
+
+        // Sanitized, with the forced settings.
+        let output = exec_env(&mut worker, None).await;
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.iter().any(|line| line.starts_with("PATH=")));
+        assert!(lines.contains(&"RUST_LOG=error"));
+        assert!(!output.contains("SB_SHELL_WORKER_LEAK"));
+        let suibase_home = format!("SUIBASE_HOME={}", home.join("suibase").display());
+        assert!(lines.contains(&suibase_home.as_str()));
+
+        // Caller overrides, with opt-out of the forced settings.
+        let data_json = serde_json::json!({
+            "env": { "SUI_CLIENT_ARGS": "--json", "RUST_LOG": "info" },
+            "forced_env": false,
+        });
+        let output = exec_env(&mut worker, Some(data_json)).await;
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&"SUI_CLIENT_ARGS=--json"));
+        assert!(lines.contains(&"RUST_LOG=info"));
+
+        // Forced settings win over the overrides by default.
+        let data_json = serde_json::json!({ "env": { "RUST_LOG": "info" } });
+        let output = exec_env(&mut worker, Some(data_json)).await;
+        assert!(output.lines().any(|line| line == "RUST_LOG=error"));
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    // Commands for the same worker (workdir) never overlap, in the order received.
+    #[tokio::test]
+    async fn test_exec_serialized() {
+        use tokio_graceful_shutdown::{SubsystemBuilder, Toplevel};
+
+        let home = std::env::temp_dir().join(format!("sb-shell-serial-{}", std::process::id()));
+        std::fs::create_dir_all(home.join("suibase")).unwrap();
+        let (tx, rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let mut worker = ShellWorker::new(rx, Some(1));
+        worker.home_dir = home.clone();
+        worker.suibase_home = home.join("suibase");
+        let toplevel = tokio::spawn(
+            Toplevel::new(move |s| async move {
+                s.start(SubsystemBuilder::new("shell-worker", |a| worker.run(a)));
+            })
+            .handle_shutdown_requests(Duration::from_millis(1000)),
+        );
+
+        // The second command fails if started before the first one is completed.
+        let mut resp_rxs = Vec::new();
+        for cmd in ["sleep 0.3 && echo done > serial.txt", "cat serial.txt"] {
+            let (resp_tx, resp_rx) = tokio::sync::oneshot::channel();
+            let mut msg = GenericChannelMsg::new();
+            msg.event_id = EVENT_EXEC;
+            msg.workdir_idx = Some(1);
+            msg.command = Some(cmd.to_string());
+            msg.resp_channel = Some(resp_tx);
+            tx.send(msg).await.unwrap();
+            resp_rxs.push(resp_rx);
+        }
+        let mut resps = Vec::new();
+        for resp_rx in resp_rxs {
+            resps.push(resp_rx.await.unwrap());
+        }
+        assert_eq!(resps, vec!["".to_string(), "done".to_string()]);
+
+        toplevel.abort();
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[tokio::test]
+    async fn test_exec_output_capped() {
+        let home = std::env::temp_dir().join(format!("sb-shell-capped-{}", std::process::id()));
+        std::fs::create_dir_all(home.join("suibase")).unwrap();
+        let (_tx, rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let mut worker = ShellWorker::new(rx, None);
+        worker.home_dir = home.clone();
+        worker.suibase_home = home.join("suibase");
+
+        // Multi-MB of binary on stdout (and some on stderr), with a small cap.
+        let (resp_tx, resp_rx) = tokio::sync::oneshot::channel();
+        let mut msg = GenericChannelMsg::new();
+        msg.event_id = EVENT_EXEC;
+        msg.command =
+            Some("head -c 5000000 /dev/urandom; head -c 300000 /dev/urandom >&2".to_string());
+        msg.data_json = Some(serde_json::json!({ "max_output_bytes": 100000 }));
+        msg.resp_channel = Some(resp_tx);
+        worker.do_exec(msg).await;
+        let output = resp_rx.await.unwrap();
+        // Each U+FFFD is at most 3 bytes per byte read.
+        assert!(output.len() < 2 * 3 * 100000 + 200, "{}", output.len());
+        assert!(output.contains(&format!(
+            "{} 100000 of 5000000 bytes]",
+            SHELL_OUTPUT_TRUNCATED_MARKER
+        )));
+        assert!(output.contains(&format!(
+            "{} 100000 of 300000 bytes]",
+            SHELL_OUTPUT_TRUNCATED_MARKER
+        )));
+
+        assert_eq!(capped_output_to_string(b"ok\xff", 3), "ok\u{fffd}");
+        assert_eq!(shell_output_max_bytes(None), SHELL_OUTPUT_MAX_BYTES);
+
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_redact_shell_env() {
+        let inherited = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "leaked".to_string()),
+        ];
+        let data_json = serde_json::json!({ "env": { "sui_api_token": "abc", "FOO": "bar" } });
+        let env = shell_env(inherited, Some(&data_json));
+        assert!(!env.contains_key("AWS_SECRET_ACCESS_KEY"));
+
+        let redacted = redact_shell_env(&env);
+        assert_eq!(redacted["sui_api_token"], "<redacted>");
+        assert_eq!(redacted["FOO"], "bar");
+        assert_eq!(redacted["PATH"], "/usr/bin");
+        assert_eq!(env["sui_api_token"], "abc");
+    }
+}
@@ -2,6 +2,69 @@
 //
 // This is intended to be used by websocket threads.
 //
+// Also keeps the delivery metrics of the subscription (see SubscriptionMetrics). A
+// subscription is degraded on a resubscribe storm (the connection keeps dropping)
+// or on sustained event drops (the consumer is not keeping up or is gone).
+//
+// The websocket thread restarts (with new trackings) on a connection loss, so the
+// metrics are carried over with delivery_stats()/set_delivery_stats().
+//
+use std::collections::VecDeque;
+
+use crate::basic_types::SharedClock;
+
+// Resubscribes within RESUBSCRIBE_STORM_WINDOW_SECS for a storm.
+pub const RESUBSCRIBE_STORM_COUNT: usize = 5;
+pub const RESUBSCRIBE_STORM_WINDOW_SECS: u64 = 300;
+
+// Dropped events within SUSTAINED_DROPS_WINDOW_SECS for sustained drops.
+pub const SUSTAINED_DROPS_COUNT: usize = 10;
+pub const SUSTAINED_DROPS_WINDOW_SECS: u64 = 60;
+
+// Snapshot of the delivery metrics (see SubscriptionTracking::metrics).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionMetrics {
+    pub events_delivered: u64,
+    pub events_dropped: u64,
+    pub resubscribe_count: u64, // Subscribe confirmations after the first one.
+    pub last_event_secs: Option<u64>, // Seconds since the UNIX epoch.
+    pub degraded_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionDeliveryStats {
+    events_delivered: u64,
+    events_dropped: u64,
+    subscribed_count: u64, // Subscribe confirmations (first one included).
+    last_event_secs: Option<u64>,
+    recent_resubscribes: VecDeque<tokio::time::Instant>, // Within the storm window.
+    recent_drops: VecDeque<tokio::time::Instant>,        // Within the drops window.
+}
+
+impl SubscriptionDeliveryStats {
+    fn prune_recent(&mut self, now: tokio::time::Instant) {
+        Self::prune(
+            &mut self.recent_resubscribes,
+            now,
+            RESUBSCRIBE_STORM_WINDOW_SECS,
+        );
+        Self::prune(&mut self.recent_drops, now, SUSTAINED_DROPS_WINDOW_SECS);
+    }
+
+    fn prune(
+        timestamps: &mut VecDeque<tokio::time::Instant>,
+        now: tokio::time::Instant,
+        window_secs: u64,
+    ) {
+        while let Some(timestamp) = timestamps.front() {
+            if now.duration_since(*timestamp).as_secs() < window_secs {
+                break;
+            }
+            timestamps.pop_front();
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SubscriptionTrackingState {
     // Valid state transitions:
@@ -48,6 +111,35 @@ impl From<SubscriptionTrackingState> for u32 {
     }
 }
 
+// Inverse of the u32 conversion (e.g. for the tracking_state stored in a MoveConfig).
+impl TryFrom<u32> for SubscriptionTrackingState {
+    type Error = u32;
+
+    fn try_from(val: u32) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(SubscriptionTrackingState::Disconnected),
+            1 => Ok(SubscriptionTrackingState::Subscribing),
+            2 => Ok(SubscriptionTrackingState::Subscribed),
+            3 => Ok(SubscriptionTrackingState::Unsubscribing),
+            4 => Ok(SubscriptionTrackingState::ReadyToDelete),
+            _ => Err(val),
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriptionTrackingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SubscriptionTrackingState::Disconnected => "Disconnected",
+            SubscriptionTrackingState::Subscribing => "Subscribing",
+            SubscriptionTrackingState::Subscribed => "Subscribed",
+            SubscriptionTrackingState::Unsubscribing => "Unsubscribing",
+            SubscriptionTrackingState::ReadyToDelete => "ReadyToDelete",
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct SubscriptionTracking {
     // Set once on instantiation for managed packages.
@@ -91,11 +183,26 @@ pub struct SubscriptionTracking {
 
     // Once requested to be removed from config, there is no way to go back.
     remove_request: bool,
+
+    // Never reset by a state change.
+    delivery: SubscriptionDeliveryStats,
+
+    // Time source for all the timestamps above.
+    clock: SharedClock,
 }
 
 impl SubscriptionTracking {
     pub fn new(package_id: String, src_addr: Option<String>, sender_addr: Option<String>) -> Self {
-        let now = tokio::time::Instant::now();
+        Self::new_with_clock(package_id, src_addr, sender_addr, SharedClock::default())
+    }
+
+    pub fn new_with_clock(
+        package_id: String,
+        src_addr: Option<String>,
+        sender_addr: Option<String>,
+        clock: SharedClock,
+    ) -> Self {
+        let now = clock.now_instant();
         Self {
             //toml_path: String::new(),
             name: String::new(),
@@ -114,6 +221,8 @@ impl SubscriptionTracking {
             subscribe_seq_numbers: Vec::new(),
             unsubscribe_seq_numbers: Vec::new(),
             remove_request: false,
+            delivery: SubscriptionDeliveryStats::default(),
+            clock,
         }
     }
 
@@ -124,7 +233,8 @@ impl SubscriptionTracking {
         timestamp: String,
         id: String,
     ) -> Self {
-        let now = tokio::time::Instant::now();
+        let clock = SharedClock::default();
+        let now = clock.now_instant();
         Self {
             //toml_path,
             name,
@@ -143,9 +253,11 @@ impl SubscriptionTracking {
             subscribe_seq_numbers: Vec::new(),
             unsubscribe_seq_numbers: Vec::new(),
             remove_request: false,
+            delivery: SubscriptionDeliveryStats::default(),
+            clock,
         }
     }
-/*
+    /*
     pub fn toml_path(&self) -> &String {
         &self.toml_path
     }*/
@@ -227,7 +339,7 @@ impl SubscriptionTracking {
 
     pub fn secs_since_last_request(&self) -> u64 {
         match self.request_sent_timestamp {
-            Some(timestamp) => timestamp.elapsed().as_secs(),
+            Some(timestamp) => self.clock.now_instant().duration_since(timestamp).as_secs(),
             None => u64::MAX,
         }
     }
@@ -262,7 +374,7 @@ impl SubscriptionTracking {
             self.subscription_number = u64::MAX;
             self.request_retry = 0;
         }
-        self.state_change_timestamp = Some(tokio::time::Instant::now());
+        self.state_change_timestamp = Some(self.clock.now_instant());
         self.state = new_state;
         true
     }
@@ -270,7 +382,7 @@ impl SubscriptionTracking {
     // Various way to report external actions/events.
     pub fn report_subscribing_request(&mut self, seq_number: u64) {
         self.subscribe_seq_numbers.push(seq_number);
-        self.request_sent_timestamp = Some(tokio::time::Instant::now());
+        self.request_sent_timestamp = Some(self.clock.now_instant());
         self.request_retry += 1;
         // Remove oldest request to avoid "memory leak" when failing for a long time.
         if self.subscribe_seq_numbers.len() > 50 {
@@ -288,11 +400,18 @@ impl SubscriptionTracking {
             Err(_e) => u64::MAX,
         };
         self.unsubscribed_id = Some(unsubscribe_id);
+
+        let now = self.clock.now_instant();
+        self.delivery.subscribed_count += 1;
+        if self.delivery.subscribed_count > 1 {
+            self.delivery.recent_resubscribes.push_back(now);
+            self.delivery.prune_recent(now);
+        }
     }
 
     pub fn report_unsubscribing_request(&mut self, seq_number: u64) {
         self.unsubscribe_seq_numbers.push(seq_number);
-        self.request_sent_timestamp = Some(tokio::time::Instant::now());
+        self.request_sent_timestamp = Some(self.clock.now_instant());
         self.request_retry += 1;
         // Remove oldest request to avoid "memory leak" when failing for a long time.
         if self.unsubscribe_seq_numbers.len() > 50 {
@@ -310,4 +429,175 @@ impl SubscriptionTracking {
     pub fn report_remove_request(&mut self) {
         self.remove_request = true; // Once set, can never be cleared.
     }
+
+    // An event was forwarded to the consumer.
+    pub fn report_event_delivered(&mut self) {
+        self.delivery.events_delivered += 1;
+        self.delivery.last_event_secs = Some(self.clock.now_secs());
+    }
+
+    // An event was received, but could not be forwarded to the consumer.
+    pub fn report_event_dropped(&mut self) {
+        let now = self.clock.now_instant();
+        self.delivery.events_dropped += 1;
+        self.delivery.last_event_secs = Some(self.clock.now_secs());
+        self.delivery.recent_drops.push_back(now);
+        self.delivery.prune_recent(now);
+    }
+
+    // None when healthy.
+    pub fn degraded_reason(&mut self) -> Option<String> {
+        self.delivery.prune_recent(self.clock.now_instant());
+        let resubscribes = self.delivery.recent_resubscribes.len();
+        if resubscribes >= RESUBSCRIBE_STORM_COUNT {
+            return Some(format!(
+                "resubscribed {} times in the last {} secs",
+                resubscribes, RESUBSCRIBE_STORM_WINDOW_SECS
+            ));
+        }
+        let drops = self.delivery.recent_drops.len();
+        if drops >= SUSTAINED_DROPS_COUNT {
+            return Some(format!(
+                "{} events dropped in the last {} secs",
+                drops, SUSTAINED_DROPS_WINDOW_SECS
+            ));
+        }
+        None
+    }
+
+    pub fn metrics(&mut self) -> SubscriptionMetrics {
+        SubscriptionMetrics {
+            degraded_reason: self.degraded_reason(),
+            events_delivered: self.delivery.events_delivered,
+            events_dropped: self.delivery.events_dropped,
+            resubscribe_count: self.delivery.subscribed_count.saturating_sub(1),
+            last_event_secs: self.delivery.last_event_secs,
+        }
+    }
+
+    pub fn delivery_stats(&self) -> &SubscriptionDeliveryStats {
+        &self.delivery
+    }
+
+    // The stats of the previous tracking of the same subscription.
+    pub fn set_delivery_stats(&mut self, delivery: SubscriptionDeliveryStats) {
+        self.delivery = delivery;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic_types::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_request_timing() {
+        let clock = MockClock::new();
+        let mut tracking = SubscriptionTracking::new_with_clock(
+            "0x2".to_string(),
+            None,
+            None,
+            SharedClock::new(clock.clone()),
+        );
+        assert_eq!(tracking.secs_since_last_request(), u64::MAX);
+
+        assert!(tracking.change_state_to(SubscriptionTrackingState::Subscribing));
+        tracking.report_subscribing_request(1);
+        assert!(tracking.is_subscribe_request_pending_response());
+        assert_eq!(tracking.secs_since_last_request(), 0);
+
+        clock.advance(Duration::from_millis(29_999));
+        assert_eq!(tracking.secs_since_last_request(), 29);
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(tracking.secs_since_last_request(), 30);
+
+        // A retry restarts the timer.
+        tracking.report_subscribing_request(2);
+        assert_eq!(tracking.request_retry(), 2);
+        assert_eq!(tracking.secs_since_last_request(), 0);
+
+        tracking.report_subscribing_response("7".to_string());
+        assert_eq!(tracking.subscription_number(), 7);
+        assert_eq!(tracking.secs_since_last_request(), u64::MAX);
+    }
+
+    #[test]
+    fn test_delivery_metrics() {
+        let clock = MockClock::new();
+        let mut tracking = SubscriptionTracking::new_with_clock(
+            "0x2".to_string(),
+            None,
+            None,
+            SharedClock::new(clock.clone()),
+        );
+        assert_eq!(tracking.metrics(), SubscriptionMetrics::default());
+
+        tracking.report_subscribing_response("1".to_string());
+        tracking.report_event_delivered();
+        tracking.report_event_delivered();
+        let metrics = tracking.metrics();
+        assert_eq!(metrics.events_delivered, 2);
+        assert_eq!(metrics.resubscribe_count, 0);
+        assert_eq!(metrics.last_event_secs, Some(tracking.clock.now_secs()));
+        assert_eq!(metrics.degraded_reason, None);
+
+        // Sustained drops, until they are out of the window.
+        for _ in 0..SUSTAINED_DROPS_COUNT {
+            tracking.report_event_dropped();
+        }
+        let metrics = tracking.metrics();
+        assert_eq!(metrics.events_dropped, SUSTAINED_DROPS_COUNT as u64);
+        assert_eq!(metrics.events_delivered, 2);
+        assert_eq!(
+            metrics.degraded_reason.as_deref(),
+            Some("10 events dropped in the last 60 secs")
+        );
+        clock.advance(Duration::from_secs(SUSTAINED_DROPS_WINDOW_SECS));
+        assert_eq!(tracking.degraded_reason(), None);
+        assert_eq!(tracking.metrics().events_dropped, 10);
+
+        // Resubscribe storm (the disconnects do not reset the metrics).
+        for number in 0..RESUBSCRIBE_STORM_COUNT {
+            tracking.change_state_to(SubscriptionTrackingState::Disconnected);
+            tracking.change_state_to(SubscriptionTrackingState::Subscribing);
+            tracking.report_subscribing_response(number.to_string());
+            clock.advance(Duration::from_secs(10));
+        }
+        let metrics = tracking.metrics();
+        assert_eq!(metrics.resubscribe_count, RESUBSCRIBE_STORM_COUNT as u64);
+        assert_eq!(
+            metrics.degraded_reason.as_deref(),
+            Some("resubscribed 5 times in the last 300 secs")
+        );
+
+        // Carried over to the tracking of the next connection (the first subscribe
+        // confirmation on it is also a resubscribe).
+        let mut next = SubscriptionTracking::new_with_clock(
+            "0x2".to_string(),
+            None,
+            None,
+            SharedClock::new(clock.clone()),
+        );
+        next.set_delivery_stats(tracking.delivery_stats().clone());
+        next.report_subscribing_response("9".to_string());
+        assert_eq!(next.metrics().resubscribe_count, 6);
+        assert_eq!(next.metrics().events_delivered, 2);
+
+        clock.advance(Duration::from_secs(RESUBSCRIBE_STORM_WINDOW_SECS - 40));
+        assert_eq!(tracking.degraded_reason(), None);
+    }
+
+    #[test]
+    fn test_state_u32_round_trip() {
+        for val in 0..5u32 {
+            let state = SubscriptionTrackingState::try_from(val).unwrap();
+            assert_eq!(u32::from(state), val);
+        }
+        assert_eq!(SubscriptionTrackingState::try_from(5), Err(5));
+        assert_eq!(
+            SubscriptionTrackingState::Subscribed.to_string(),
+            "Subscribed"
+        );
+    }
 }
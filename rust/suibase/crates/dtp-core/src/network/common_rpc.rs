@@ -1,30 +1,54 @@
 // Utility function to do common RPC calls.
 
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use anyhow::bail;
 use log::info;
 use move_core_types::language_storage::StructTag;
 use serde::Deserialize;
 use serde_json::{Map, Value};
-use shared_crypto::intent::Intent;
+use shared_crypto::intent::{Intent, IntentMessage};
 use sui_json_rpc_types::{
-    SuiData, SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectResponse, SuiObjectResponseQuery,
-    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+    MoveCallParams, RPCTransactionRequestParams, SuiData, SuiEvent, SuiExecutionStatus,
+    SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectResponse, SuiObjectResponseQuery,
+    SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
 use sui_keys::keystore::AccountKeystore;
 use sui_sdk::json::SuiJsonValue;
-use sui_types::base_types::SuiAddress;
+use sui_types::base_types::{ObjectRef, SuiAddress};
+use sui_types::crypto::{Signature, SuiSignature, ToFromBytes};
+use sui_types::digests::TransactionDigest;
+use sui_types::dynamic_field::DynamicFieldName;
+use sui_types::gas::GasCostSummary;
 use sui_types::{
-    base_types::ObjectID, quorum_driver_types::ExecuteTransactionRequestType,
-    transaction::Transaction,
+    base_types::ObjectID,
+    quorum_driver_types::ExecuteTransactionRequestType,
+    transaction::{InputObjectKind, Transaction, TransactionData, TransactionDataAPI},
 };
 
 use sui_types::error::SuiObjectResponseError;
 
-use crate::types::{DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn};
+use crate::types::{
+    classify_sui_error_msg, DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn, TimeoutPhase,
+    TxnConfirmation,
+};
 use serde::de::DeserializeOwned;
 
+// A transaction is executed with WaitForLocalExecution, so its response normally
+// has all the effects needed to update the local state (no follow-up reads).
+//
+// When a node returns a response without the needed effects, the transaction is
+// polled until these are available (slower fallback).
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const CONFIRMATION_POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Object created by a Move call (from the transaction effects).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CreatedObject {
+    pub object_id: ObjectID,
+    pub sender: SuiAddress,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct WeakRef {
     // Refer to a Sui object, but can't assume it still exists (e.g. was deleted).
@@ -95,75 +119,293 @@ impl WeakRef {
     }
 }
 
-// Perform a mostly generic move call.
-// Caller specify 'options' effects and deserialize the response.
-pub(crate) async fn do_move_call(
+// A Move call built by rpc.client_address, not yet signed (see build_move_call).
+#[derive(Debug, Clone)]
+pub(crate) struct MoveCallTx {
+    pub tx_data: TransactionData,
+    pub package_id: ObjectID,
+    pub call_module: String, // e.g. api
+    pub function: String,    // e.g. create
+    pub desc: String,        // For the errors.
+}
+
+// Build (without signing) a Move call. The gas coin is picked by the node, and
+// every owned input object is at its current version.
+pub(crate) async fn build_move_call(
     rpc: &SuiSDKParamsRPC,
     txn: &SuiSDKParamsTxn,
     call_module: &str,            // e.g. api
     function: &str,               // e.g. create
     call_args: Vec<SuiJsonValue>, // Can be empty vec![]
-    options: SuiTransactionBlockResponseOptions,
-) -> Result<SuiTransactionBlockResponse, anyhow::Error> {
-    let sui_client = match rpc.sui_client.as_ref() {
-        Some(x) => &x.inner,
-        None => bail!(DTPError::DTPMissingSuiClient),
-    };
-    let keystore = &txn.keystore.inner;
-
+) -> Result<MoveCallTx, DTPError> {
     let call_desc = format!(
         "{}::{}::{}({:?}) with signer {}",
         txn.package_id, call_module, function, call_args, rpc.client_address,
     );
 
-    let move_call = sui_client
-        .transaction_builder()
-        .move_call(
-            rpc.client_address,
-            txn.package_id,
-            call_module,
-            function,
-            vec![],
-            call_args,
-            None, // The node will pick a gas object from the signer.
-            1000000000,
-            None,
-        )
+    let move_call = rpc
+        .nodes
+        .with_failover("move_call", |sui_client| {
+            let call_args = call_args.clone();
+            async move {
+                sui_client
+                    .transaction_builder()
+                    .move_call(
+                        rpc.client_address,
+                        txn.package_id,
+                        call_module,
+                        function,
+                        vec![],
+                        call_args,
+                        None, // The node will pick a gas object from the signer.
+                        1000000000,
+                        None,
+                    )
+                    .await
+            }
+        })
         .await;
-    if let Err(e) = move_call {
-        return Err(DTPError::DTPFailedMoveCall {
-            desc: format!("move_call failed for {}", call_desc),
-            package_id: txn.package_id.to_string(),
-            client_address: rpc.client_address.to_string(),
-            inner: e.to_string(),
+    let move_call = match move_call {
+        Ok(move_call) => move_call,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::DTPFailedMoveCall {
+                desc: format!("move_call failed for {}", call_desc),
+                package_id: txn.package_id.to_string(),
+                client_address: rpc.client_address.to_string(),
+                inner: e.to_string(),
+            })
         }
-        .into());
-    }
-    let move_call = move_call.unwrap();
+    };
 
-    // Sign transaction.
+    Ok(MoveCallTx {
+        tx_data: move_call,
+        package_id: txn.package_id,
+        call_module: call_module.to_string(),
+        function: function.to_string(),
+        desc: call_desc,
+    })
+}
+
+// Sign with the key of rpc.client_address. Fails when the keystore has no key
+// for the signer.
+pub(crate) fn sign_with_keystore(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    tx_data: &TransactionData,
+) -> Result<Signature, DTPError> {
+    txn.keystore
+        .inner
+        .sign_secure(&rpc.client_address, tx_data, Intent::sui_transaction())
+        .map_err(|e| DTPError::NotAuthorized {
+            msg: format!("signing with {} failed ({})", rpc.client_address, e),
+        })
+}
+
+// A signature done elsewhere (see PreparedTransaction). Must be by the sender of
+// 'tx_data', for these exact bytes.
+pub(crate) fn verify_signature(
+    tx_data: &TransactionData,
+    signature_bytes: &[u8],
+) -> Result<Signature, DTPError> {
     let signature =
-        keystore.sign_secure(&rpc.client_address, &move_call, Intent::sui_transaction())?;
-
-    let tx = Transaction::from_data(move_call, vec![signature]);
-    let response = sui_client
-        .quorum_driver_api()
-        .execute_transaction_block(
-            tx,
-            options,
-            Some(ExecuteTransactionRequestType::WaitForLocalExecution),
-        )
+        Signature::from_bytes(signature_bytes).map_err(|e| DTPError::NotAuthorized {
+            msg: format!("signature invalid ({})", e),
+        })?;
+    let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+    signature
+        .verify_secure(&intent_msg, tx_data.sender(), signature.scheme())
+        .map_err(|e| DTPError::NotAuthorized {
+            msg: format!(
+                "signature does not match the transaction of {} ({})",
+                tx_data.sender(),
+                e
+            ),
+        })?;
+    Ok(signature)
+}
+
+// Net gas the transaction should spend (see do_move_call_ret_gas), from a dry-run.
+// Fails the same way as the execution would (e.g. Move abort).
+pub(crate) async fn dry_run_gas(rpc: &SuiSDKParamsRPC, call: &MoveCallTx) -> Result<u64, DTPError> {
+    let response = rpc
+        .nodes
+        .with_failover("dry_run_transaction_block", |sui_client| {
+            let tx_data = call.tx_data.clone();
+            async move {
+                sui_client
+                    .read_api()
+                    .dry_run_transaction_block(tx_data)
+                    .await
+                    .map_err(anyhow::Error::from)
+            }
+        })
         .await;
-    if response.is_err() {
-        return Err(DTPError::DTPFailedMoveCall {
-            desc: format!("response failed for {}", call_desc),
-            package_id: txn.package_id.to_string(),
-            client_address: rpc.client_address.to_string(),
-            inner: format!("Inner error [{}]", response.unwrap_err()),
+    let response = match response {
+        Ok(response) => response,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::DTPFailedMoveCall {
+                desc: format!("dry_run failed for {}", call.desc),
+                package_id: call.package_id.to_string(),
+                client_address: call.tx_data.sender().to_string(),
+                inner: e.to_string(),
+            })
+        }
+    };
+    if let SuiExecutionStatus::Failure { error } = response.effects.status() {
+        return Err(match classify_sui_error_msg(error) {
+            Some(e) => e,
+            None => DTPError::TransactionRejected {
+                digest: call.tx_data.digest().to_string(),
+                reason: format!("{} for {} (dry-run)", error, call.desc),
+            },
+        });
+    }
+    Ok(net_gas_spent(response.effects.gas_cost_summary()))
+}
+
+// The owned objects (including the gas coins) at the version used by 'tx_data'.
+// The shared objects are not included (their version is set by the network).
+pub(crate) fn owned_input_objects(tx_data: &TransactionData) -> Result<Vec<ObjectRef>, DTPError> {
+    let inputs = tx_data
+        .input_objects()
+        .map_err(|e| DTPError::DTPInternalError {
+            msg: format!("input_objects ({})", e),
+        })?;
+    Ok(inputs
+        .into_iter()
+        .filter_map(|input| match input {
+            InputObjectKind::ImmOrOwnedMoveObject(object_ref) => Some(object_ref),
+            _ => None,
+        })
+        .collect())
+}
+
+// Fails with DTPError::StaleObjectVersion when one of 'pinned' is no longer at its
+// version on the network (modified or deleted since).
+pub(crate) async fn check_object_versions(
+    rpc: &SuiSDKParamsRPC,
+    pinned: &[ObjectRef],
+) -> Result<(), DTPError> {
+    if pinned.is_empty() {
+        return Ok(());
+    }
+    let object_ids: Vec<ObjectID> = pinned.iter().map(|(object_id, _, _)| *object_id).collect();
+    let responses = rpc
+        .nodes
+        .with_failover("multi_get_object_with_options", |sui_client| {
+            let object_ids = object_ids.clone();
+            async move {
+                sui_client
+                    .read_api()
+                    .multi_get_object_with_options(object_ids, SuiObjectDataOptions::new())
+                    .await
+                    .map_err(anyhow::Error::from)
+            }
+        })
+        .await?;
+
+    for ((object_id, version, _), response) in pinned.iter().zip(responses) {
+        // 0 when deleted.
+        let current_version = response.data.map_or(0, |data| data.version.value());
+        if current_version != version.value() {
+            return Err(DTPError::StaleObjectVersion {
+                object_id: object_id.to_string(),
+                prepared_version: version.value(),
+                current_version,
+            });
         }
-        .into());
     }
-    let response = response.unwrap();
+    Ok(())
+}
+
+// Sum of the current versions of 'object_ids' (a deleted one counts as 0). Changes
+// whenever one of them is modified.
+pub(crate) async fn fetch_objects_version(
+    rpc: &SuiSDKParamsRPC,
+    object_ids: &[ObjectID],
+) -> Result<u64, DTPError> {
+    if object_ids.is_empty() {
+        return Ok(0);
+    }
+    let responses = rpc
+        .nodes
+        .with_failover("multi_get_object_with_options", |sui_client| {
+            let object_ids = object_ids.to_vec();
+            async move {
+                sui_client
+                    .read_api()
+                    .multi_get_object_with_options(object_ids, SuiObjectDataOptions::new())
+                    .await
+                    .map_err(anyhow::Error::from)
+            }
+        })
+        .await?;
+    Ok(responses
+        .into_iter()
+        .map(|response| response.data.map_or(0, |data| data.version.value()))
+        .sum())
+}
+
+// Sign with the key of rpc.client_address and execute (common part of all transactions).
+async fn sign_and_execute(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    tx_data: TransactionData,
+    call_desc: &str,
+    options: SuiTransactionBlockResponseOptions,
+) -> Result<SuiTransactionBlockResponse, DTPError> {
+    let signature = sign_with_keystore(rpc, txn, &tx_data)?;
+    execute_signed(rpc, tx_data, signature, call_desc, options).await
+}
+
+async fn execute_signed(
+    rpc: &SuiSDKParamsRPC,
+    tx_data: TransactionData,
+    signature: Signature,
+    call_desc: &str,
+    options: SuiTransactionBlockResponseOptions,
+) -> Result<SuiTransactionBlockResponse, DTPError> {
+    // The same signed transaction is submitted to the next node on transport
+    // failure (safe, a transaction is executed at most once on the network).
+    //
+    // On a Submit timeout the outcome is unknown: the transaction may still be
+    // executed (same digest), so the caller should reload the state before a retry.
+    let tx = Transaction::from_data(tx_data, vec![signature]);
+    let digest = tx.digest().to_string();
+    let response = rpc
+        .nodes
+        .with_failover_phase(
+            "execute_transaction_block",
+            TimeoutPhase::Submit,
+            |sui_client| {
+                let tx = tx.clone();
+                let options = options.clone();
+                async move {
+                    sui_client
+                        .quorum_driver_api()
+                        .execute_transaction_block(
+                            tx,
+                            options,
+                            Some(ExecuteTransactionRequestType::WaitForLocalExecution),
+                        )
+                        .await
+                        .map_err(anyhow::Error::from)
+                }
+            },
+        )
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::TransactionRejected {
+                digest,
+                reason: format!("{} for {}", e, call_desc),
+            })
+        }
+    };
 
     if !response.errors.is_empty() {
         let mut error_message = "Inner error [".to_string();
@@ -172,31 +414,232 @@ pub(crate) async fn do_move_call(
         }
         error_message.push(']');
 
-        bail!(DTPError::DTPFailedMoveCall {
-            desc: format!("response errors for {}", call_desc),
-            package_id: txn.package_id.to_string(),
-            client_address: rpc.client_address.to_string(),
-            inner: error_message
+        return Err(DTPError::TransactionRejected {
+            digest,
+            reason: format!("{} for {}", error_message, call_desc),
         });
     }
+
+    // Executed, but possibly failed (e.g. Move abort). Only known when the
+    // caller requested the effects.
+    if let Some(effects) = &response.effects {
+        if let SuiExecutionStatus::Failure { error } = effects.status() {
+            return Err(match classify_sui_error_msg(error) {
+                Some(gas_error @ DTPError::InsufficientGas { .. }) => gas_error,
+                _ => DTPError::TransactionRejected {
+                    digest,
+                    reason: format!("{} for {}", error, call_desc),
+                },
+            });
+        }
+    }
     Ok(response)
 }
 
+// Transfer 'amount' Mist from rpc.client_address to 'recipient' (e.g. to fund
+// the address of a localhost profile).
+//
+// Split from the largest SUI coin of the signer, which also pays for the gas.
+pub(crate) async fn transfer_sui_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    recipient: SuiAddress,
+    amount: u64,
+) -> Result<(), DTPError> {
+    let call_desc = format!(
+        "transfer_sui {} Mist to {} with signer {}",
+        amount, recipient, rpc.client_address
+    );
+    let tx_data = rpc
+        .nodes
+        .with_failover("transfer_sui", |sui_client| async move {
+            let coins = sui_client
+                .coin_read_api()
+                .get_coins(rpc.client_address, None, None, None)
+                .await?;
+            let coin = match coins.data.iter().max_by_key(|coin| coin.balance) {
+                Some(coin) => coin.coin_object_id,
+                None => {
+                    return Err(DTPError::InsufficientGas {
+                        needed: amount,
+                        available: 0,
+                    }
+                    .into())
+                }
+            };
+            sui_client
+                .transaction_builder()
+                .transfer_sui(
+                    rpc.client_address,
+                    coin,
+                    1000000000,
+                    recipient,
+                    Some(amount),
+                )
+                .await
+        })
+        .await;
+    let tx_data = match tx_data {
+        Ok(tx_data) => tx_data,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::DTPFailedMoveCall {
+                desc: format!("transaction build failed for {}", call_desc),
+                package_id: txn.package_id.to_string(),
+                client_address: rpc.client_address.to_string(),
+                inner: e.to_string(),
+            })
+        }
+    };
+    let options = SuiTransactionBlockResponseOptions::new().with_effects();
+    sign_and_execute(rpc, txn, tx_data, &call_desc, options)
+        .await
+        .map(|_| ())
+}
+
 pub(crate) async fn do_move_call_no_ret(
     rpc: &SuiSDKParamsRPC,
     txn: &SuiSDKParamsTxn,
     call_module: &str,            // e.g. api
     function: &str,               // e.g. open_connection
     call_args: Vec<SuiJsonValue>, // Can be empty vec![]
-) -> Result<(), anyhow::Error> {
-    let options = SuiTransactionBlockResponseOptions::new();
-    let response = do_move_call(rpc, txn, call_module, function, call_args, options).await;
-    if let Err(e) = response {
-        // TODO Append event_type info to error.
-        return Err(e);
+) -> Result<(), DTPError> {
+    do_move_call_ret_gas(rpc, txn, call_module, function, call_args)
+        .await
+        .map(|_| ())
+}
+
+// Same as do_move_call_no_ret, but returns the gas spent (Mist).
+//
+// The gas spent is the net cost for the sender (computation and storage, minus
+// the storage rebate). Zero when the effects could not be confirmed in time.
+pub(crate) async fn do_move_call_ret_gas(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    call_module: &str,            // e.g. api
+    function: &str,               // e.g. send_request
+    call_args: Vec<SuiJsonValue>, // Can be empty vec![]
+) -> Result<u64, DTPError> {
+    let call = build_move_call(rpc, txn, call_module, function, call_args).await?;
+    let signature = sign_with_keystore(rpc, txn, &call.tx_data)?;
+    execute_ret_gas(rpc, call, signature).await
+}
+
+// Execute a signed Move call, and returns the gas spent (see do_move_call_ret_gas).
+pub(crate) async fn execute_ret_gas(
+    rpc: &SuiSDKParamsRPC,
+    call: MoveCallTx,
+    signature: Signature,
+) -> Result<u64, DTPError> {
+    let start = Instant::now();
+    let options = SuiTransactionBlockResponseOptions::new().with_effects();
+    let response =
+        execute_signed(rpc, call.tx_data, signature, &call.desc, options.clone()).await?;
+    confirm_ret_gas(
+        rpc,
+        response,
+        options,
+        &call.call_module,
+        &call.function,
+        start,
+    )
+    .await
+}
+
+// Same as do_move_call_ret_gas, but with multiple calls of the same function in a
+// single transaction (one per element of 'calls', executed in that order).
+//
+// The calls succeed or fail together. Returns the gas spent for the whole transaction.
+pub(crate) async fn do_batch_move_call_ret_gas(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    call_module: &str,             // e.g. api
+    function: &str,                // e.g. send_request
+    calls: Vec<Vec<SuiJsonValue>>, // Arguments of each call.
+) -> Result<u64, DTPError> {
+    let start = Instant::now();
+    let call_desc = format!(
+        "{}::{}::{} x{} with signer {}",
+        txn.package_id,
+        call_module,
+        function,
+        calls.len(),
+        rpc.client_address,
+    );
+
+    let tx_data = rpc
+        .nodes
+        .with_failover("batch_transaction", |sui_client| {
+            let params: Vec<RPCTransactionRequestParams> = calls
+                .iter()
+                .map(|arguments| {
+                    RPCTransactionRequestParams::MoveCallRequestParams(MoveCallParams {
+                        package_object_id: txn.package_id,
+                        module: call_module.to_string(),
+                        function: function.to_string(),
+                        type_arguments: vec![],
+                        arguments: arguments.clone(),
+                    })
+                })
+                .collect();
+            async move {
+                sui_client
+                    .transaction_builder()
+                    .batch_transaction(rpc.client_address, params, None, 1000000000)
+                    .await
+            }
+        })
+        .await;
+    let tx_data = match tx_data {
+        Ok(tx_data) => tx_data,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::DTPFailedMoveCall {
+                desc: format!("batch_transaction failed for {}", call_desc),
+                package_id: txn.package_id.to_string(),
+                client_address: rpc.client_address.to_string(),
+                inner: e.to_string(),
+            })
+        }
+    };
+
+    let options = SuiTransactionBlockResponseOptions::new().with_effects();
+    let response = sign_and_execute(rpc, txn, tx_data, &call_desc, options.clone()).await?;
+    confirm_ret_gas(rpc, response, options, call_module, function, start).await
+}
+
+// Common part of do_move_call_ret_gas and do_batch_move_call_ret_gas.
+async fn confirm_ret_gas(
+    rpc: &SuiSDKParamsRPC,
+    mut response: SuiTransactionBlockResponse,
+    options: SuiTransactionBlockResponseOptions,
+    call_module: &str,
+    function: &str,
+    start: Instant,
+) -> Result<u64, DTPError> {
+    let polled = response.effects.is_none();
+    if polled {
+        // The result is known only once the effects are (e.g. Move abort).
+        if let Some(polled_response) = poll_transaction(rpc, response.digest, options, |response| {
+            response.effects.is_some()
+        })
+        .await?
+        {
+            response = polled_response;
+        }
     }
-    let _ = response.unwrap();
-    Ok(())
+    report_confirmation(rpc, call_module, function, start, polled);
+
+    let gas_spent = response
+        .effects
+        .as_ref()
+        .map_or(0, |effects| net_gas_spent(effects.gas_cost_summary()));
+    Ok(gas_spent)
+}
+
+// Computation and storage, minus the storage rebate.
+fn net_gas_spent(summary: &GasCostSummary) -> u64 {
+    (summary.computation_cost + summary.storage_cost).saturating_sub(summary.storage_rebate)
 }
 
 // Function that perform a move call and deserialize an expected single event 'T' effect.
@@ -209,55 +652,77 @@ pub(crate) async fn do_move_call_ret_event<T>(
     event_module: &str,           // e.g. events
     event_type: &str,             // e.g. ConnReq
     call_args: Vec<SuiJsonValue>, // Can be empty vec![]
-) -> Result<T, anyhow::Error>
+) -> Result<T, DTPError>
 where
     T: DeserializeOwned,
 {
+    let call = build_move_call(rpc, txn, call_module, function, call_args).await?;
+    let signature = sign_with_keystore(rpc, txn, &call.tx_data)?;
+    execute_ret_event(rpc, call, signature, event_module, event_type).await
+}
+
+// Execute a signed Move call, and deserialize its event 'T' (see do_move_call_ret_event).
+pub(crate) async fn execute_ret_event<T>(
+    rpc: &SuiSDKParamsRPC,
+    call: MoveCallTx,
+    signature: Signature,
+    event_module: &str, // e.g. events
+    event_type: &str,   // e.g. ConnReq
+) -> Result<T, DTPError>
+where
+    T: DeserializeOwned,
+{
+    let start = Instant::now();
     let options = SuiTransactionBlockResponseOptions::new()
         .with_events()
         .with_effects();
-    let response = do_move_call(rpc, txn, call_module, function, call_args, options).await;
-    if let Err(e) = response {
-        // TODO Append event_type info to error.
-        return Err(e);
-    }
-    let response = response.unwrap();
-
-    // Get the expected event effect.
-    let events = response.events.unwrap();
-
-    // Iterate the object changes, look for the "event_module::event_type".
+    let package_id = call.package_id;
+    let mut response =
+        execute_signed(rpc, call.tx_data, signature, &call.desc, options.clone()).await?;
 
     // TODO Optimize this?
-    let tag_str = format!("{}::{}::{}", txn.package_id, event_module, event_type);
+    let tag_str = format!("{}::{}::{}", package_id, event_module, event_type);
     let tag = StructTag::from_str(&tag_str)?;
+    let find_event = |response: &SuiTransactionBlockResponse| -> Option<SuiEvent> {
+        response
+            .events
+            .as_ref()?
+            .data
+            .iter()
+            .find(|event| event.package_id == package_id && event.type_ == tag)
+            .cloned()
+    };
 
-    for event in events.data {
-        info!("event {:?}", event);
-        if event.package_id == txn.package_id && event.type_ == tag {
-            // BCS deserialization.
-            let event_obj = bcs::from_bytes::<T>(&event.bcs);
-            if let Err(e) = event_obj {
-                bail!(DTPError::DTPFailedConvertBCS {
-                    object_type: std::any::type_name::<T>().to_string(),
-                    object_id: "NA".to_string(),
-                    raw_data: format!("event[{:?} inner error[{}]", event, e),
-                });
-            }
-            let event_obj = event_obj.unwrap();
-            return Ok(event_obj);
+    let polled = find_event(&response).is_none();
+    if polled {
+        if let Some(polled_response) =
+            poll_transaction(rpc, response.digest, options, |r| find_event(r).is_some()).await?
+        {
+            response = polled_response;
         }
     }
+    report_confirmation(rpc, &call.call_module, &call.function, start, polled);
 
-    bail!(DTPError::DTPFailedMoveCall {
+    // Get the expected event effect.
+    if let Some(event) = find_event(&response) {
+        info!("event {:?}", event);
+        // BCS deserialization.
+        return bcs::from_bytes::<T>(&event.bcs).map_err(|e| DTPError::DTPFailedConvertBCS {
+            object_type: std::any::type_name::<T>().to_string(),
+            object_id: "NA".to_string(),
+            raw_data: format!("event[{:?} inner error[{}]", event, e),
+        });
+    }
+
+    Err(DTPError::DTPFailedMoveCall {
         desc: format!(
             "event {}:{} not found in response",
             event_module, event_type
         ),
-        package_id: txn.package_id.to_string(),
+        package_id: package_id.to_string(),
         client_address: rpc.client_address.to_string(),
-        inner: "".to_string()
-    });
+        inner: "".to_string(),
+    })
 }
 
 // A move call that returns the ID of a new object created.
@@ -269,80 +734,205 @@ pub(crate) async fn do_move_call_ret_id(
     new_object_module: &str,      // e.g. host
     new_object_type: &str,        // e.g. Host
     call_args: Vec<SuiJsonValue>, // Can be empty vec![]
-) -> Result<ObjectID, anyhow::Error> {
+) -> Result<ObjectID, DTPError> {
+    let created = do_move_call_ret_created(
+        rpc,
+        txn,
+        call_module,
+        function,
+        new_object_module,
+        new_object_type,
+        call_args,
+    )
+    .await?;
+    Ok(created.object_id)
+}
+
+// Same as do_move_call_ret_id, with also what else is known about the new object
+// from the effects (to initialize an internal struct without reading the object).
+pub(crate) async fn do_move_call_ret_created(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    call_module: &str,            // e.g. api
+    function: &str,               // e.g. create
+    new_object_module: &str,      // e.g. host
+    new_object_type: &str,        // e.g. Host
+    call_args: Vec<SuiJsonValue>, // Can be empty vec![]
+) -> Result<CreatedObject, DTPError> {
+    let call = build_move_call(rpc, txn, call_module, function, call_args).await?;
+    let signature = sign_with_keystore(rpc, txn, &call.tx_data)?;
+    execute_ret_created(rpc, call, signature, new_object_module, new_object_type).await
+}
+
+// Execute a signed Move call, and find the object created (see do_move_call_ret_created).
+pub(crate) async fn execute_ret_created(
+    rpc: &SuiSDKParamsRPC,
+    call: MoveCallTx,
+    signature: Signature,
+    new_object_module: &str, // e.g. host
+    new_object_type: &str,   // e.g. Host
+) -> Result<CreatedObject, DTPError> {
+    let start = Instant::now();
     let options = SuiTransactionBlockResponseOptions::new()
         .with_object_changes()
         .with_effects();
-    let response = do_move_call(rpc, txn, call_module, function, call_args, options).await;
-    if let Err(e) = response {
-        return Err(e);
-    }
-    let response = response.unwrap();
-
-    // Get the id from the newly created Sui object.
-    let object_changes = response.object_changes.unwrap();
+    let mut response =
+        execute_signed(rpc, call.tx_data, signature, &call.desc, options.clone()).await?;
 
     // Iterate the object changes, look for the needed object (e.g. "host::Host")
-    let mut created_object_id = Option::<ObjectID>::None;
-    for object_change in object_changes {
-        info!("iter object {:?}", object_change);
-        match object_change {
-            sui_json_rpc_types::ObjectChange::Created {
-                object_type,
-                object_id,
-                ..
-            } => {
-                // Check if the object_type is "host::Host"
-                if object_type.name.to_string() == new_object_type
-                    && object_type.module.to_string() == new_object_module
-                {
-                    created_object_id = Some(object_id)
+    let find_created = |response: &SuiTransactionBlockResponse| -> Option<CreatedObject> {
+        response
+            .object_changes
+            .as_ref()?
+            .iter()
+            .find_map(|object_change| {
+                info!("iter object {:?}", object_change);
+                match object_change {
+                    sui_json_rpc_types::ObjectChange::Created {
+                        object_type,
+                        object_id,
+                        sender,
+                        ..
+                    } if object_type.name.as_str() == new_object_type
+                        && object_type.module.as_str() == new_object_module =>
+                    {
+                        Some(CreatedObject {
+                            object_id: *object_id,
+                            sender: *sender,
+                        })
+                    }
+                    _ => None,
                 }
-            }
-            _ => {}
+            })
+    };
+
+    let polled = find_created(&response).is_none();
+    if polled {
+        if let Some(polled_response) =
+            poll_transaction(rpc, response.digest, options, |r| find_created(r).is_some()).await?
+        {
+            response = polled_response;
         }
     }
-    if created_object_id.is_none() {
-        bail!(DTPError::DTPFailedMoveCall {
+    report_confirmation(rpc, &call.call_module, &call.function, start, polled);
+
+    match find_created(&response) {
+        Some(created) => Ok(created),
+        None => Err(DTPError::DTPFailedMoveCall {
             desc: format!(
                 "object {}:{} not found in response",
                 new_object_module, new_object_type
             ),
-            package_id: txn.package_id.to_string(),
+            package_id: call.package_id.to_string(),
             client_address: rpc.client_address.to_string(),
-            inner: "".to_string()
-        });
+            inner: "".to_string(),
+        }),
+    }
+}
+
+// Poll an executed transaction until 'is_complete' (or a timeout).
+//
+// Returns Ok(None) on timeout, and an error if the effects show that the
+// transaction failed.
+async fn poll_transaction<F>(
+    rpc: &SuiSDKParamsRPC,
+    digest: TransactionDigest,
+    options: SuiTransactionBlockResponseOptions,
+    is_complete: F,
+) -> Result<Option<SuiTransactionBlockResponse>, DTPError>
+where
+    F: Fn(&SuiTransactionBlockResponse) -> bool,
+{
+    info!("poll_transaction {} (effects missing in response)", digest);
+    let options = options.with_effects();
+    let deadline = Instant::now() + CONFIRMATION_POLL_TIMEOUT;
+    loop {
+        let response = rpc
+            .nodes
+            .with_failover_phase(
+                "get_transaction_with_options",
+                TimeoutPhase::Confirm,
+                |sui_client| {
+                    let options = options.clone();
+                    async move {
+                        sui_client
+                            .read_api()
+                            .get_transaction_with_options(digest, options)
+                            .await
+                            .map_err(anyhow::Error::from)
+                    }
+                },
+            )
+            .await;
+        match response {
+            Ok(response) => {
+                if let Some(effects) = &response.effects {
+                    if let SuiExecutionStatus::Failure { error } = effects.status() {
+                        return Err(match classify_sui_error_msg(error) {
+                            Some(gas_error @ DTPError::InsufficientGas { .. }) => gas_error,
+                            _ => DTPError::TransactionRejected {
+                                digest: digest.to_string(),
+                                reason: error.clone(),
+                            },
+                        });
+                    }
+                }
+                if is_complete(&response) {
+                    return Ok(Some(response));
+                }
+            }
+            Err(e) if e.is_actionable() => return Err(e),
+            Err(_) => {} // Likely not yet known by this node.
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
     }
+}
 
-    // Success.
-    Ok(created_object_id.unwrap())
+fn report_confirmation(
+    rpc: &SuiSDKParamsRPC,
+    call_module: &str,
+    function: &str,
+    start: Instant,
+    polled: bool,
+) {
+    rpc.nodes.report_confirmation(TxnConfirmation {
+        op: format!("{}::{}", call_module, function),
+        latency_ms: start.elapsed().as_millis() as u64,
+        polled,
+    });
 }
 
 pub(crate) async fn fetch_raw_move_object<T>(
     rpc: &SuiSDKParamsRPC,
     object_id: ObjectID,
-) -> Result<Option<T>, anyhow::Error>
+) -> Result<Option<T>, DTPError>
 where
     T: DeserializeOwned,
 {
-    let sui_client = match rpc.sui_client.as_ref() {
-        Some(x) => &x.inner,
-        None => bail!(DTPError::DTPMissingSuiClient),
-    };
-
     //let object_id_str = object_id.to_string();
-    let response = sui_client
-        .read_api()
-        .get_object_with_options(object_id, SuiObjectDataOptions::default().with_bcs())
+    let response = rpc
+        .nodes
+        .with_failover("get_object_with_options", |sui_client| async move {
+            sui_client
+                .read_api()
+                .get_object_with_options(object_id, SuiObjectDataOptions::default().with_bcs())
+                .await
+                .map_err(anyhow::Error::from)
+        })
         .await;
 
     if let Err(e) = response {
+        if e.is_actionable() {
+            return Err(e);
+        }
         return Err(DTPError::DTPFailedFetchObject {
             object_type: std::any::type_name::<T>().to_string(),
             object_id: object_id.to_string(),
             inner: e.to_string(),
-        }
-        .into());
+        });
     }
 
     let response = response.unwrap().into_object();
@@ -356,8 +946,7 @@ where
                     object_type: std::any::type_name::<T>().to_string(),
                     object_id: object_id.to_string(),
                     inner: e.to_string(),
-                }
-                .into());
+                });
             }
         }
     }
@@ -375,8 +964,7 @@ where
                     object_type: std::any::type_name::<T>().to_string(),
                     object_id: object_id.to_string(),
                     raw_data,
-                }
-                .into());
+                });
             }
             return Ok(Some(ret_value.unwrap()));
         }
@@ -386,8 +974,7 @@ where
         object_type: std::any::type_name::<T>().to_string(),
         object_id: object_id.to_string(),
         raw_data,
-    }
-    .into())
+    })
 }
 
 pub(crate) async fn fetch_raw_move_object_by_auth<T>(
@@ -396,15 +983,11 @@ pub(crate) async fn fetch_raw_move_object_by_auth<T>(
     module: &str,      // e.g. host
     object_type: &str, // e.g. Host
     auth_address: &SuiAddress,
-) -> Result<Option<T>, anyhow::Error>
+) -> Result<Option<T>, DTPError>
 where
     T: DeserializeOwned,
 {
     // Returns Ok(None) when confirmed 'address' does **not** own an instance of T.
-    let sui_client = match rpc.sui_client.as_ref() {
-        Some(x) => &x.inner,
-        None => bail!(DTPError::DTPMissingSuiClient),
-    };
 
     let object_type = format!("{}::{}::{}", package_id, module, object_type);
     let tag = StructTag::from_str(&object_type);
@@ -413,34 +996,44 @@ where
             object_type,
             object_id: "NA".to_string(),
             inner: e.to_string(),
-        }
-        .into());
+        });
     }
     let tag = tag.unwrap();
 
     let mut objects: Vec<SuiObjectResponse> = Vec::new();
     let mut cursor = None;
     loop {
-        let resp = sui_client
-            .read_api()
-            .get_owned_objects(
-                *auth_address,
-                Some(SuiObjectResponseQuery::new(
-                    Some(SuiObjectDataFilter::StructType(tag.clone())),
-                    Some(SuiObjectDataOptions::new().with_bcs()),
-                )),
-                cursor,
-                None,
-            )
+        let resp = rpc
+            .nodes
+            .with_failover("get_owned_objects", |sui_client| {
+                let tag = tag.clone();
+                async move {
+                    sui_client
+                        .read_api()
+                        .get_owned_objects(
+                            *auth_address,
+                            Some(SuiObjectResponseQuery::new(
+                                Some(SuiObjectDataFilter::StructType(tag)),
+                                Some(SuiObjectDataOptions::new().with_bcs()),
+                            )),
+                            cursor,
+                            None,
+                        )
+                        .await
+                        .map_err(anyhow::Error::from)
+                }
+            })
             .await;
 
         if let Err(e) = resp {
+            if e.is_actionable() {
+                return Err(e);
+            }
             return Err(DTPError::DTPFailedFetchObject {
                 object_type,
                 object_id: "NA".to_string(),
                 inner: e.to_string(),
-            }
-            .into());
+            });
         }
         let resp = resp.unwrap();
 
@@ -469,8 +1062,7 @@ where
                     object_type: std::any::type_name::<T>().to_string(),
                     object_id: "NA".to_string(),
                     inner: e.to_string(),
-                }
-                .into());
+                });
             }
         }
     }
@@ -488,8 +1080,7 @@ where
                     object_type: std::any::type_name::<T>().to_string(),
                     object_id: "NA".to_string(),
                     raw_data,
-                }
-                .into());
+                });
             }
             return Ok(Some(ret_value.unwrap()));
         }
@@ -499,6 +1090,204 @@ where
         object_type: std::any::type_name::<T>().to_string(),
         object_id: "NA".to_string(),
         raw_data,
+    })
+}
+
+// Fetch a dynamic field object (e.g. a Table entry) of 'parent_id'.
+//
+// Returns Ok(None) when confirmed the field does not exist.
+pub(crate) async fn fetch_raw_dynamic_field_object<T>(
+    rpc: &SuiSDKParamsRPC,
+    parent_id: ObjectID,
+    name: DynamicFieldName,
+) -> Result<Option<T>, DTPError>
+where
+    T: DeserializeOwned,
+{
+    let response = rpc
+        .nodes
+        .with_failover("get_dynamic_field_object", |sui_client| {
+            let name = name.clone();
+            async move {
+                sui_client
+                    .read_api()
+                    .get_dynamic_field_object(parent_id, name)
+                    .await
+                    .map_err(anyhow::Error::from)
+            }
+        })
+        .await;
+
+    if let Err(e) = response {
+        if e.is_actionable() {
+            return Err(e);
+        }
+        return Err(DTPError::DTPFailedFetchObject {
+            object_type: std::any::type_name::<T>().to_string(),
+            object_id: format!("{}[{}]", parent_id, name.value),
+            inner: e.to_string(),
+        });
+    }
+
+    let response = response.unwrap().into_object();
+    if let Err(e) = response {
+        match e {
+            SuiObjectResponseError::NotExists { .. } => return Ok(None),
+            SuiObjectResponseError::DynamicFieldNotFound { .. } => return Ok(None),
+            SuiObjectResponseError::Deleted { .. } => return Ok(None),
+            _ => {
+                return Err(DTPError::DTPFailedFetchObject {
+                    object_type: std::any::type_name::<T>().to_string(),
+                    object_id: format!("{}[{}]", parent_id, name.value),
+                    inner: e.to_string(),
+                });
+            }
+        }
+    }
+    let resp = response.unwrap();
+    let object_id = resp.object_id.to_string();
+
+    // Deserialize the BCS data into T
+    let raw_data = resp.to_string();
+    if let Some(sui_raw_data) = resp.bcs {
+        if let Some(sui_raw_mov_obj) = sui_raw_data.try_into_move() {
+            let ret_value: Result<T, anyhow::Error> = sui_raw_mov_obj.deserialize();
+            return match ret_value {
+                Ok(ret_value) => Ok(Some(ret_value)),
+                Err(e) => Err(DTPError::DTPFailedConvertBCS {
+                    object_type: std::any::type_name::<T>().to_string(),
+                    object_id,
+                    raw_data: format!("{},inner error[{}]", raw_data, e),
+                }),
+            };
+        }
+    };
+
+    Err(DTPError::DTPFailedConvertBCS {
+        object_type: std::any::type_name::<T>().to_string(),
+        object_id,
+        raw_data,
+    })
+}
+
+// Find an object created when 'package_id' was published (e.g. a shared object
+// created by a Move init function).
+//
+// Returns Ok(None) when confirmed the publish transaction did not create such object.
+pub(crate) async fn fetch_package_init_object_id(
+    rpc: &SuiSDKParamsRPC,
+    package_id: ObjectID,
+    module: &str,      // e.g. user_registry
+    object_type: &str, // e.g. HostNameRegistry
+) -> Result<Option<ObjectID>, DTPError> {
+    let object_desc = format!("{}::{}::{}", package_id, module, object_type);
+
+    // The previous transaction of an (immutable) package is its publication.
+    let package = rpc
+        .nodes
+        .with_failover("get_object_with_options", |sui_client| async move {
+            sui_client
+                .read_api()
+                .get_object_with_options(
+                    package_id,
+                    SuiObjectDataOptions::new().with_previous_transaction(),
+                )
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await
+        .and_then(|resp| resp.into_object().map_err(DTPError::from));
+    let publish_digest = match package {
+        Ok(package) => package.previous_transaction,
+        Err(e) if e.is_actionable() => return Err(e),
+        Err(e) => {
+            return Err(DTPError::DTPFailedFetchObject {
+                object_type: object_desc,
+                object_id: package_id.to_string(),
+                inner: e.to_string(),
+            });
+        }
+    };
+    let publish_digest = match publish_digest {
+        Some(publish_digest) => publish_digest,
+        None => return Err(DTPError::PackageIDNotFound),
+    };
+
+    let response = rpc
+        .nodes
+        .with_failover("get_transaction_with_options", |sui_client| async move {
+            sui_client
+                .read_api()
+                .get_transaction_with_options(
+                    publish_digest,
+                    SuiTransactionBlockResponseOptions::new().with_object_changes(),
+                )
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await;
+    if let Err(e) = response {
+        if e.is_actionable() {
+            return Err(e);
+        }
+        return Err(DTPError::DTPFailedFetchObject {
+            object_type: object_desc,
+            object_id: "NA".to_string(),
+            inner: e.to_string(),
+        });
+    }
+
+    let object_changes = response.unwrap().object_changes.unwrap_or_default();
+    for object_change in object_changes {
+        if let sui_json_rpc_types::ObjectChange::Created {
+            object_type: created_type,
+            object_id,
+            ..
+        } = object_change
+        {
+            if ObjectID::from(created_type.address) == package_id
+                && created_type.module.as_str() == module
+                && created_type.name.as_str() == object_type
+            {
+                return Ok(Some(object_id));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Check that 'package_id' has the function 'module::function' (e.g. a function added
+// by a later version of the DTP package).
+//
+// Returns Ok(false) when confirmed that the package does not have it.
+pub(crate) async fn package_has_function(
+    rpc: &SuiSDKParamsRPC,
+    package_id: ObjectID,
+    module: &str,   // e.g. user_registry
+    function: &str, // e.g. register_host_name
+) -> Result<bool, DTPError> {
+    let modules = rpc
+        .nodes
+        .with_failover(
+            "get_normalized_move_modules_by_package",
+            |sui_client| async move {
+                sui_client
+                    .read_api()
+                    .get_normalized_move_modules_by_package(package_id)
+                    .await
+                    .map_err(anyhow::Error::from)
+            },
+        )
+        .await;
+    match modules {
+        Ok(modules) => Ok(modules
+            .get(module)
+            .is_some_and(|normalized| normalized.exposed_functions.contains_key(function))),
+        Err(e) if e.is_actionable() => Err(e),
+        Err(e) => Err(DTPError::DTPFailedFetchObject {
+            object_type: format!("{}::{}", package_id, module),
+            object_id: package_id.to_string(),
+            inner: e.to_string(),
+        }),
     }
-    .into())
 }
@@ -0,0 +1,308 @@
+// Connection-level encryption of the payloads written on-chain.
+//
+// Each DTP instance has an X25519 keypair stored alongside its keystore. The public
+// key is advertised on its Host object (see host_internal.rs), and both ends of a
+// connection derive the same key with their own secret and the public key of the peer.
+//
+// The payloads are sealed with ChaCha20-Poly1305. The nonce is never transmitted: it
+// is derived from the direction and the pipe sequence number of the message, so a
+// (key, nonce) pair is never reused as long as a sequence number is not reused.
+//
+// Sealed payload framing:
+//
+//     [ ENC_FRAME_VERSION (1 byte) ][ ciphertext + 16 bytes tag ]
+//
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::types::DTPError;
+
+pub const ENC_FRAME_VERSION: u8 = 1;
+pub const ENC_PUBLIC_KEY_LENGTH: usize = 32;
+
+// Bytes added to a payload by ConnCipher::seal().
+pub const ENC_FRAME_OVERHEAD: usize = 1 + 16;
+
+// Domain separation of the key derivation (bump on any change of the scheme).
+const KDF_CONTEXT: &[u8] = b"dtp-conn-enc-v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnDirection {
+    CliToSrv, // Requests.
+    SrvToCli, // Responses.
+}
+
+// The keypair file of a client address, in the same directory as the keystore.
+pub fn enc_keypair_pathname(keystore_pathname: &Path, address: &SuiAddress) -> PathBuf {
+    let filename = format!("dtp-enc-{}.key", address);
+    match keystore_pathname.parent() {
+        Some(dir) => dir.join(filename),
+        None => PathBuf::from(filename),
+    }
+}
+
+#[derive(Clone)]
+pub struct EncKeypair {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl std::fmt::Debug for EncKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never display the secret.
+        f.debug_struct("EncKeypair")
+            .field("public", &self.public.as_bytes())
+            .finish()
+    }
+}
+
+impl EncKeypair {
+    pub fn generate() -> Self {
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    // Load the keypair, or generate and save a new one when the file does not exist.
+    pub fn load_or_create(pathname: &Path) -> Result<Self, DTPError> {
+        let config_err = |e: std::io::Error| DTPError::Config {
+            msg: format!("encryption key {:?} ({})", pathname, e),
+        };
+        match std::fs::read(pathname) {
+            Ok(bytes) => {
+                let bytes: [u8; 32] = bytes.try_into().map_err(|_| DTPError::Config {
+                    msg: format!("encryption key {:?} (invalid length)", pathname),
+                })?;
+                let secret = StaticSecret::from(bytes);
+                let public = PublicKey::from(&secret);
+                Ok(Self { secret, public })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let keypair = Self::generate();
+                write_secret_file(pathname, keypair.secret.as_bytes()).map_err(config_err)?;
+                Ok(keypair)
+            }
+            Err(e) => Err(config_err(e)),
+        }
+    }
+
+    pub fn public_key(&self) -> [u8; ENC_PUBLIC_KEY_LENGTH] {
+        self.public.to_bytes()
+    }
+}
+
+#[cfg(unix)]
+fn write_secret_file(pathname: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(pathname)?;
+    file.write_all(bytes)
+}
+
+#[cfg(not(unix))]
+fn write_secret_file(pathname: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    std::fs::write(pathname, bytes)
+}
+
+// Seal/open the payloads of one connection (same key at both ends).
+#[derive(Clone)]
+pub struct ConnCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl std::fmt::Debug for ConnCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ConnCipher { .. }")
+    }
+}
+
+impl ConnCipher {
+    // The TransportControl ID is mixed in the key, so every connection between
+    // the same two hosts uses a distinct key.
+    pub fn new(own: &EncKeypair, peer_public_key: &[u8], tc: &ObjectID) -> Result<Self, DTPError> {
+        let peer: [u8; ENC_PUBLIC_KEY_LENGTH] =
+            peer_public_key
+                .try_into()
+                .map_err(|_| DTPError::DTPEncryptionFailed {
+                    desc: "peer public key invalid length".to_string(),
+                })?;
+        let shared = own.secret.diffie_hellman(&PublicKey::from(peer));
+        if !shared.was_contributory() {
+            return Err(DTPError::DTPEncryptionFailed {
+                desc: "peer public key rejected".to_string(),
+            });
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(KDF_CONTEXT);
+        hasher.update(shared.as_bytes());
+        hasher.update(tc.as_ref());
+        let key = hasher.finalize();
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    fn nonce(direction: ConnDirection, seq_num: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[0] = match direction {
+            ConnDirection::CliToSrv => 0,
+            ConnDirection::SrvToCli => 1,
+        };
+        nonce[4..].copy_from_slice(&seq_num.to_le_bytes());
+        *Nonce::from_slice(&nonce)
+    }
+
+    pub fn seal(
+        &self,
+        direction: ConnDirection,
+        seq_num: u64,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, DTPError> {
+        let ciphertext = self
+            .cipher
+            .encrypt(&Self::nonce(direction, seq_num), plaintext)
+            .map_err(|_| DTPError::DTPEncryptionFailed {
+                desc: "seal".to_string(),
+            })?;
+        let mut sealed = Vec::with_capacity(ENC_FRAME_OVERHEAD + plaintext.len());
+        sealed.push(ENC_FRAME_VERSION);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(
+        &self,
+        direction: ConnDirection,
+        seq_num: u64,
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, DTPError> {
+        match sealed.split_first() {
+            Some((&ENC_FRAME_VERSION, ciphertext)) => self
+                .cipher
+                .decrypt(&Self::nonce(direction, seq_num), ciphertext)
+                .map_err(|_| DTPError::DTPEncryptionFailed {
+                    desc: format!("open seq {} (corrupted, or wrong key/seq)", seq_num),
+                }),
+            _ => Err(DTPError::DTPEncryptionFailed {
+                desc: "open (unknown frame version)".to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher_pair(tc: &ObjectID) -> (ConnCipher, ConnCipher) {
+        let cli = EncKeypair::generate();
+        let srv = EncKeypair::generate();
+        cipher_pair_with(&cli, &srv, tc)
+    }
+
+    fn cipher_pair_with(
+        cli: &EncKeypair,
+        srv: &EncKeypair,
+        tc: &ObjectID,
+    ) -> (ConnCipher, ConnCipher) {
+        (
+            ConnCipher::new(cli, &srv.public_key(), tc).unwrap(),
+            ConnCipher::new(srv, &cli.public_key(), tc).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let tc = ObjectID::from_hex_literal("0x1234").unwrap();
+        let (cli, srv) = cipher_pair(&tc);
+        let plaintext = b"hello from the client".to_vec();
+
+        let sealed = cli.seal(ConnDirection::CliToSrv, 1, &plaintext).unwrap();
+        assert_eq!(sealed.len(), plaintext.len() + ENC_FRAME_OVERHEAD);
+        assert_eq!(sealed[0], ENC_FRAME_VERSION);
+        assert!(!sealed.windows(plaintext.len()).any(|w| w == plaintext));
+        assert_eq!(
+            srv.open(ConnDirection::CliToSrv, 1, &sealed).unwrap(),
+            plaintext
+        );
+
+        // Same payload, other sequence number or direction: distinct bytes.
+        assert_ne!(
+            cli.seal(ConnDirection::CliToSrv, 2, &plaintext).unwrap(),
+            sealed
+        );
+        assert_ne!(
+            cli.seal(ConnDirection::SrvToCli, 1, &plaintext).unwrap(),
+            sealed
+        );
+
+        // Empty payload.
+        let sealed_empty = srv.seal(ConnDirection::SrvToCli, 7, &[]).unwrap();
+        assert!(cli
+            .open(ConnDirection::SrvToCli, 7, &sealed_empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_open_failures() {
+        let tc = ObjectID::from_hex_literal("0x1234").unwrap();
+        let cli_keypair = EncKeypair::generate();
+        let srv_keypair = EncKeypair::generate();
+        let (cli, srv) = cipher_pair_with(&cli_keypair, &srv_keypair, &tc);
+        let sealed = cli.seal(ConnDirection::CliToSrv, 5, b"data").unwrap();
+
+        assert!(srv.open(ConnDirection::CliToSrv, 6, &sealed).is_err());
+        assert!(srv.open(ConnDirection::SrvToCli, 5, &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(srv.open(ConnDirection::CliToSrv, 5, &tampered).is_err());
+
+        let mut bad_version = sealed.clone();
+        bad_version[0] = 0;
+        assert!(srv.open(ConnDirection::CliToSrv, 5, &bad_version).is_err());
+        assert!(srv.open(ConnDirection::CliToSrv, 5, &[]).is_err());
+
+        // Another connection between the same hosts uses another key.
+        let other_tc = ObjectID::from_hex_literal("0x5678").unwrap();
+        let (_, other_srv) = cipher_pair_with(&cli_keypair, &srv_keypair, &other_tc);
+        assert!(other_srv.open(ConnDirection::CliToSrv, 5, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_peer_key_rejected() {
+        let tc = ObjectID::from_hex_literal("0x1234").unwrap();
+        let own = EncKeypair::generate();
+        assert!(ConnCipher::new(&own, &[1u8; 31], &tc).is_err());
+        // Low-order point (all zero) gives a non-contributory shared secret.
+        assert!(ConnCipher::new(&own, &[0u8; 32], &tc).is_err());
+    }
+
+    #[test]
+    fn test_keypair_file() {
+        let dir = std::env::temp_dir().join(format!("dtp-enc-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let address = SuiAddress::ZERO;
+        let pathname = enc_keypair_pathname(&dir.join("sui.keystore"), &address);
+        assert_eq!(pathname.parent(), Some(dir.as_path()));
+        let _ = std::fs::remove_file(&pathname);
+
+        let created = EncKeypair::load_or_create(&pathname).unwrap();
+        let loaded = EncKeypair::load_or_create(&pathname).unwrap();
+        assert_eq!(created.public_key(), loaded.public_key());
+
+        std::fs::write(&pathname, [0u8; 5]).unwrap();
+        assert!(EncKeypair::load_or_create(&pathname).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -14,14 +14,43 @@
 //   "HostMoveRaw"
 //
 
+use std::str::FromStr;
 use std::sync::Arc;
 
 use log::info;
+use move_core_types::language_storage::TypeTag;
+use sui_sdk::json::SuiJsonValue;
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use sui_types::dynamic_field::DynamicFieldName;
 
-use crate::types::SuiSDKParamsRPC;
+use crate::types::{DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn};
 
-use super::HostMoveRaw;
+use super::{
+    service_type_by_short_name, service_type_short_name, HostEncKeyMoveRaw, HostMoveRaw,
+    ServiceType, C_SERVICE_TYPE_SHORT_NAMES,
+};
+
+// Name of the Host dynamic field with the encryption public key (see conn_crypto.rs).
+const HOST_ENC_KEY_FIELD: &str = "enc_key";
+
+// A service of a Host, as advertised by its Move object (see HostInternalST::services).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    pub service_idx: ServiceType,
+    pub name: String, // Short name (e.g. "json-rpc"), or "service-{idx}" when not a known type.
+    pub enabled: bool,
+    pub fee_per_request: Option<u64>, // Mist. Only for an enabled service.
+}
+
+fn service_name(service_idx: ServiceType) -> String {
+    service_type_short_name(service_idx)
+        .map_or_else(|| format!("service-{}", service_idx), str::to_string)
+}
+
+// Index of a known service type from its short name (e.g. "json-rpc"). Case insensitive.
+pub fn service_idx_by_name(name: &str) -> Option<ServiceType> {
+    service_type_by_short_name(name)
+}
 
 #[derive(Debug)]
 pub struct HostInternalST {
@@ -35,7 +64,7 @@ pub type HostInternalMT = Arc<tokio::sync::RwLock<HostInternalST>>;
 pub(crate) async fn get_host_internal_by_id(
     rpc: &SuiSDKParamsRPC,
     host_object_id: ObjectID,
-) -> Result<Option<HostInternalST>, anyhow::Error> {
+) -> Result<Option<HostInternalST>, DTPError> {
     info!(
         "get_host_internal_by_id start for object id: {:?}",
         host_object_id
@@ -61,7 +90,7 @@ pub(crate) async fn get_host_internal_by_auth(
     rpc: &SuiSDKParamsRPC,
     package_id: &ObjectID,
     address: &SuiAddress,
-) -> Result<Option<HostInternalST>, anyhow::Error> {
+) -> Result<Option<HostInternalST>, DTPError> {
     // When returning Ok(None) it means that it was verified
     // that this address does not OWN a Host object.
     info!("get_host_internal_by_auth 1");
@@ -85,6 +114,35 @@ pub(crate) async fn get_host_internal_by_auth(
     Ok(Some(ret))
 }
 
+// Returns Ok(None) if confirmed that the Host does not advertise an encryption key.
+pub(crate) async fn get_host_enc_key(
+    rpc: &SuiSDKParamsRPC,
+    host_id: ObjectID,
+) -> Result<Option<Vec<u8>>, DTPError> {
+    let key = DynamicFieldName {
+        type_: TypeTag::from_str("0x1::string::String")?,
+        value: serde_json::Value::String(HOST_ENC_KEY_FIELD.to_string()),
+    };
+    let raw =
+        super::common_rpc::fetch_raw_dynamic_field_object::<HostEncKeyMoveRaw>(rpc, host_id, key)
+            .await?;
+    Ok(raw.map(|raw| raw.value))
+}
+
+// Add (or replace) the encryption public key of a Host. Only its authority can do it.
+pub(crate) async fn set_host_enc_key_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    host_id: ObjectID,
+    public_key: &[u8],
+) -> Result<(), DTPError> {
+    let call_args = vec![
+        SuiJsonValue::from_object_id(host_id),
+        SuiJsonValue::new(serde_json::json!(public_key))?,
+    ];
+    super::common_rpc::do_move_call_no_ret(rpc, txn, "host", "set_enc_key", call_args).await
+}
+
 impl HostInternalST {
     pub(crate) fn new(object_id: ObjectID) -> HostInternalST {
         HostInternalST {
@@ -101,4 +159,144 @@ impl HostInternalST {
     pub fn authority(&self) -> Option<SuiAddress> {
         self.authority
     }
+
+    // Every known service type, plus the unknown ones enabled on the Host, ordered by
+    // index. Empty when the Host data was not retrieved from the network.
+    pub fn services(&self) -> Vec<ServiceInfo> {
+        let raw = match &self.raw {
+            Some(raw) => raw,
+            None => return Vec::new(),
+        };
+        let mut services: Vec<ServiceInfo> = C_SERVICE_TYPE_SHORT_NAMES
+            .iter()
+            .map(|(service_idx, name)| ServiceInfo {
+                service_idx: *service_idx,
+                name: name.to_string(),
+                enabled: false,
+                fee_per_request: None,
+            })
+            .collect();
+        for service in &raw.services {
+            let enabled = ServiceInfo {
+                service_idx: service.service_idx,
+                name: service_name(service.service_idx),
+                enabled: true,
+                fee_per_request: Some(service.fee_per_request),
+            };
+            match services
+                .iter()
+                .position(|info| info.service_idx == service.service_idx)
+            {
+                Some(pos) => services[pos] = enabled,
+                None => services.push(enabled),
+            }
+        }
+        services.sort_by_key(|info| info.service_idx);
+        services
+    }
+
+    // Fails with ServiceUnavailable when the Host does not offer 'service_idx'.
+    //
+    // Ok when the Host data was not retrieved (the network does the validation).
+    pub fn check_service(&self, service_idx: ServiceType) -> Result<(), DTPError> {
+        let raw = match &self.raw {
+            Some(raw) => raw,
+            None => return Ok(()),
+        };
+        if raw
+            .services
+            .iter()
+            .any(|service| service.service_idx == service_idx)
+        {
+            return Ok(());
+        }
+        Err(DTPError::ServiceUnavailable {
+            host: self.object_id.to_string(),
+            service_idx,
+            name: service_name(service_idx),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ConnAcceptedStats, ConnClosedStats, ConnRejectedStats, HostConfig};
+    use crate::network::{Service, C_SERVICE_TYPE_ECHO_IDX, C_SERVICE_TYPE_JSON_RPC_2_0_IDX};
+    use sui_types::id::UID;
+
+    fn service(service_idx: u8, fee_per_request: u64) -> Service {
+        Service {
+            service_idx,
+            fee_per_request,
+            conn_accepted: ConnAcceptedStats {
+                conn_accepted: 0,
+                conn_accepted_lru: 0,
+            },
+            conn_rejected: ConnRejectedStats {
+                conn_rej_host_max_con: 0,
+                conn_rej_srv_max_con: 0,
+                conn_rej_firewall: 0,
+                conn_rej_srv_down: 0,
+                conn_rej_cli_err: 0,
+                conn_rej_cli_no_fund: 0,
+            },
+            conn_closed: ConnClosedStats {
+                conn_closed_srv: 0,
+                conn_closed_cli: 0,
+                conn_closed_exp: 0,
+                conn_closed_lru: 0,
+                conn_closed_srv_sync_err: 0,
+                conn_closed_clt_sync_err: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_host_services() {
+        let object_id = ObjectID::from_hex_literal("0x1234").unwrap();
+        let mut host = HostInternalST::new(object_id);
+        assert!(host.services().is_empty());
+        assert!(host.check_service(C_SERVICE_TYPE_JSON_RPC_2_0_IDX).is_ok());
+
+        // Only the ping service, and an index not in the known table.
+        host.raw = Some(HostMoveRaw {
+            id: UID::new(object_id),
+            authority: SuiAddress::ZERO,
+            config: HostConfig { max_con: 10 },
+            services: vec![service(C_SERVICE_TYPE_ECHO_IDX, 5), service(42, 0)],
+        });
+        let services = host.services();
+        let ping = services.iter().find(|info| info.name == "ping").unwrap();
+        assert_eq!(ping.service_idx, C_SERVICE_TYPE_ECHO_IDX);
+        assert!(ping.enabled);
+        assert_eq!(ping.fee_per_request, Some(5));
+        let json_rpc = services
+            .iter()
+            .find(|info| info.name == "json-rpc")
+            .unwrap();
+        assert!(!json_rpc.enabled);
+        assert_eq!(json_rpc.fee_per_request, None);
+        assert_eq!(services.last().unwrap().name, "service-42");
+        assert!(services
+            .windows(2)
+            .all(|w| w[0].service_idx < w[1].service_idx));
+
+        assert!(host.check_service(C_SERVICE_TYPE_ECHO_IDX).is_ok());
+        let err = host
+            .check_service(C_SERVICE_TYPE_JSON_RPC_2_0_IDX)
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            DTPError::ServiceUnavailable { service_idx: 2, name, .. } if name == "json-rpc"
+        ));
+        assert!(err.is_actionable());
+
+        assert_eq!(
+            service_idx_by_name("JSON-RPC"),
+            Some(C_SERVICE_TYPE_JSON_RPC_2_0_IDX)
+        );
+        assert_eq!(service_idx_by_name("ping"), Some(C_SERVICE_TYPE_ECHO_IDX));
+        assert_eq!(service_idx_by_name("service-42"), None);
+    }
 }
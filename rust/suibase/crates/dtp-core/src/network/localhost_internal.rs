@@ -1,6 +1,8 @@
+use crate::types::DTPError;
 use crate::types::SuiSDKParamsRPC;
 use crate::types::SuiSDKParamsTxn;
 
+use super::common_rpc::CreatedObject;
 use super::host_internal::*;
 
 use sui_sdk::json::SuiJsonValue;
@@ -17,7 +19,7 @@ pub struct LocalhostInternal {
 pub(crate) async fn get_localhost_internal_by_id(
     rpc: &SuiSDKParamsRPC,
     host_id: ObjectID,
-) -> Result<Option<LocalhostInternal>, anyhow::Error> {
+) -> Result<Option<LocalhostInternal>, DTPError> {
     // Do the equivalent of get_host_by_id, but
     // create a handle that will allow for administrator
     // capabilities.
@@ -52,32 +54,45 @@ pub(crate) fn create_localhost_from_host(
     }
 }
 
+pub(crate) fn create_host_call_args() -> Vec<SuiJsonValue> {
+    let vargs: Vec<u8> = vec![];
+    vec![SuiJsonValue::from_bcs_bytes(None, &vargs).unwrap()]
+}
+
+// Initialized from the transaction effects (no read of the new object).
+pub(crate) fn create_localhost_from_created(
+    rpc: &SuiSDKParamsRPC,
+    created: CreatedObject,
+) -> LocalhostInternal {
+    let mut host_internal = HostInternalST::new(created.object_id);
+    host_internal.authority = Some(created.sender);
+    LocalhostInternal {
+        object_id: created.object_id,
+        admin_address: rpc.client_address,
+        firewall_initialized: false,
+        host_internal,
+    }
+}
+
 pub(crate) async fn create_localhost_on_network(
     rpc: &SuiSDKParamsRPC,
     txn: &SuiSDKParamsTxn,
-) -> Result<LocalhostInternal, anyhow::Error> {
+) -> Result<LocalhostInternal, DTPError> {
     // Do not allow to create a new one if one already exists
     // for this user.
-    let vargs: Vec<u8> = vec![];
-    let call_args = vec![SuiJsonValue::from_bcs_bytes(None, &vargs).unwrap()];
-    let host_object_id = super::common_rpc::do_move_call_ret_id(
+    let created = super::common_rpc::do_move_call_ret_created(
         rpc,
         txn,
         "api",
         "create_host",
         "host",
         "Host",
-        call_args,
+        create_host_call_args(),
     )
     .await?;
 
     // Success.
-    Ok(LocalhostInternal {
-        object_id: host_object_id,
-        admin_address: rpc.client_address,
-        firewall_initialized: false,
-        host_internal: HostInternalST::new(host_object_id),
-    })
+    Ok(create_localhost_from_created(rpc, created))
 }
 
 impl LocalhostInternal {
@@ -85,11 +100,15 @@ impl LocalhostInternal {
         &self.admin_address
     }
 
+    pub fn authority(&self) -> Option<SuiAddress> {
+        self.host_internal.authority()
+    }
+
     pub(crate) async fn init_firewall(
         &mut self,
         _rpc: &SuiSDKParamsRPC,
         _txn: &SuiSDKParamsTxn,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<(), DTPError> {
         // Dummy mutable for now... just to test the software design "layering"
         // with a mut.
         self.firewall_initialized = true;
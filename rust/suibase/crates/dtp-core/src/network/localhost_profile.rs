@@ -0,0 +1,152 @@
+// Localhost profiles
+//
+// A client address can manage multiple localhost Host objects, each identified by a
+// profile name (e.g. "staging", "prod").
+//
+// The Move package allows only one Host per address. Therefore, only the default
+// profile Host is owned by the auth address. Every other profile has its own derived
+// address: a key generated in the same keystore (with an alias), funded by the auth
+// address and recorded in a mapping file next to the keystore:
+//
+//     dtp-profiles-<auth address>.json   {"staging":"0x...","prod":"0x..."}
+//
+// The UserRegistry of each profile is owned by its address, so the on-chain state
+// is keyed by (auth address, profile) through this mapping.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use sui_sdk::types::base_types::SuiAddress;
+
+use crate::types::DTPError;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Profile names are also in keystore aliases (see profile_key_alias).
+pub const PROFILE_NAME_MAX_LENGTH: usize = 32;
+
+// Mist transferred by the auth address to a new profile address, for the gas
+// of its own transactions (the budget of one move call is 1 SUI).
+pub const PROFILE_INITIAL_FUNDING: u64 = 5_000_000_000;
+
+// ASCII letters, digits, '-' and '_' only (the keystore alias rules).
+pub fn validate_profile_name(profile: &str) -> Result<(), DTPError> {
+    let desc = if profile.is_empty() {
+        "empty"
+    } else if profile.len() > PROFILE_NAME_MAX_LENGTH {
+        "too long"
+    } else if !profile
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        "invalid character"
+    } else {
+        return Ok(());
+    };
+    Err(DTPError::Config {
+        msg: format!("profile {:?} invalid ({})", profile, desc),
+    })
+}
+
+// The mapping file of an auth address, in the same directory as the keystore.
+pub fn profiles_pathname(keystore_pathname: &Path, auth_address: &SuiAddress) -> PathBuf {
+    let filename = format!("dtp-profiles-{}.json", auth_address);
+    match keystore_pathname.parent() {
+        Some(dir) => dir.join(filename),
+        None => PathBuf::from(filename),
+    }
+}
+
+// Alias of the derived key in the keystore (must be unique in the keystore).
+pub fn profile_key_alias(auth_address: &SuiAddress, profile: &str) -> String {
+    let auth = auth_address.to_string();
+    let auth = auth.trim_start_matches("0x");
+    format!("dtp-{}-{}", &auth[..auth.len().min(8)], profile)
+}
+
+// profile -> derived address. Excludes the default profile (always the auth address).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProfileAddresses {
+    addresses: BTreeMap<String, SuiAddress>,
+}
+
+impl ProfileAddresses {
+    // An empty mapping when the file does not exist.
+    pub fn load(pathname: &Path) -> Result<Self, DTPError> {
+        let config_err = |desc: String| DTPError::Config {
+            msg: format!("profiles {:?} ({})", pathname, desc),
+        };
+        let contents = match std::fs::read_to_string(pathname) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(config_err(e.to_string())),
+        };
+        let addresses: BTreeMap<String, SuiAddress> =
+            serde_json::from_str(&contents).map_err(|e| config_err(e.to_string()))?;
+        Ok(Self { addresses })
+    }
+
+    pub fn save(&self, pathname: &Path) -> Result<(), DTPError> {
+        let contents = serde_json::to_string_pretty(&self.addresses).map_err(|e| {
+            DTPError::DTPInternalError {
+                msg: format!("profiles serialization ({})", e),
+            }
+        })?;
+        std::fs::write(pathname, contents).map_err(|e| DTPError::Config {
+            msg: format!("profiles {:?} ({})", pathname, e),
+        })
+    }
+
+    pub fn get(&self, profile: &str) -> Option<SuiAddress> {
+        self.addresses.get(profile).copied()
+    }
+
+    pub fn insert(&mut self, profile: &str, address: SuiAddress) {
+        self.addresses.insert(profile.to_string(), address);
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = &String> {
+        self.addresses.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert!(validate_profile_name(DEFAULT_PROFILE).is_ok());
+        assert!(validate_profile_name("prod_2-eu").is_ok());
+        assert!(validate_profile_name(&"a".repeat(PROFILE_NAME_MAX_LENGTH)).is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name(&"a".repeat(PROFILE_NAME_MAX_LENGTH + 1)).is_err());
+        assert!(validate_profile_name("my.profile").is_err());
+        assert!(validate_profile_name("my profile").is_err());
+    }
+
+    #[test]
+    fn test_profile_addresses_file() {
+        let dir = std::env::temp_dir().join(format!("dtp-profiles-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let auth = SuiAddress::ZERO;
+        let pathname = profiles_pathname(&dir.join("sui.keystore"), &auth);
+        assert_eq!(pathname.parent(), Some(dir.as_path()));
+        let _ = std::fs::remove_file(&pathname);
+        assert_eq!(profile_key_alias(&auth, "prod"), "dtp-00000000-prod");
+
+        let mut profiles = ProfileAddresses::load(&pathname).unwrap();
+        assert!(profiles.get("staging").is_none());
+        let staging = SuiAddress::random_for_testing_only();
+        profiles.insert("staging", staging);
+        profiles.save(&pathname).unwrap();
+
+        let loaded = ProfileAddresses::load(&pathname).unwrap();
+        assert_eq!(loaded, profiles);
+        assert_eq!(loaded.get("staging"), Some(staging));
+        assert_eq!(loaded.profiles().collect::<Vec<_>>(), vec!["staging"]);
+
+        std::fs::write(&pathname, "not json").unwrap();
+        assert!(ProfileAddresses::load(&pathname).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
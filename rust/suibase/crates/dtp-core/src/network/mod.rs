@@ -9,17 +9,25 @@
 //    use dtp_core::network::localhost_internal::LocalhostInternal;
 //pub use self::common_rpc::*;
 pub use self::common_rpc::*;
+pub use self::conn_crypto::*;
 pub use self::host_internal::*;
 pub use self::localhost_internal::*;
+pub use self::localhost_profile::*;
 pub use self::network_manager::*;
+pub use self::prepared_txn::*;
 pub use self::serde_types::*;
 pub use self::transport_control_internal::*;
+pub use self::tunnel_frame::*;
 pub use self::user_registry::*;
 
 mod common_rpc;
+mod conn_crypto;
 mod host_internal;
 mod localhost_internal;
+mod localhost_profile;
 mod network_manager;
+mod prepared_txn;
 mod serde_types;
 mod transport_control_internal;
+mod tunnel_frame;
 mod user_registry;
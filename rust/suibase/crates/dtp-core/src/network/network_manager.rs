@@ -1,20 +1,23 @@
 use crate::types::{
-    DTPError, KeystoreWrapped, PingStats, SuiClientWrapped, SuiSDKParamsRPC, SuiSDKParamsTxn,
+    DTPError, KeystoreWrapped, PingStats, RpcNodes, RpcStats, SuiSDKParamsRPC, SuiSDKParamsTxn,
 };
 
-use log::info;
+use log::{info, warn};
+use std::collections::HashMap;
 use std::path::PathBuf;
 //use std::str::FromStr;
 use std::sync::Arc;
-use sui_keys::keystore::{FileBasedKeystore, Keystore};
+use std::time::Duration;
+use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, Keystore};
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
-use sui_sdk::{SuiClient, SuiClientBuilder};
-
-use anyhow::bail;
+use sui_sdk::types::crypto::SignatureScheme;
 
 use super::{
-    HostInternalST, LocalhostInternal, TransportControlInternalMT, TransportControlInternalST,
-    UserRegistryInternal,
+    validate_profile_name, BatchConfig, ConnCipher, ConnEncryption, ConnReqMoveRaw, EncKeypair,
+    HostInternalST, HostNameRegistryInternal, KeepAliveConfig, LocalhostInternal, PreparedOp,
+    PreparedTransaction, ProfileAddresses, SubmittedInternal, TransportControlInternalMT,
+    TransportControlInternalST, UserRegistryInternal, DEFAULT_PROFILE, HEARTBEAT_CID,
+    PROFILE_INITIAL_FUNDING,
 };
 
 // The default location for localnet is relative to
@@ -48,176 +51,69 @@ pub struct SuiNode {
     rpc: SuiSDKParamsRPC,
 }
 
+// The localhost Host of one profile, and the objects to manage it.
 #[derive(Debug)]
-#[allow(dead_code)]
-pub struct NetworkManagerST {
-    sui_nodes: Vec<SuiNode>,
-
-    sui_txn: SuiSDKParamsTxn,
+struct LocalhostProfile {
+    // The signer is the authority of the Host: the auth address for the
+    // default profile, otherwise the address derived for the profile.
+    rpc: SuiSDKParamsRPC,
 
     localhost_id: Option<ObjectID>,
-    volunteers_id: Vec<ObjectID>,
 
     // Latest objects loaded from network.
     localhost: Option<LocalhostInternal>,
     registry: Option<UserRegistryInternal>,
 }
 
-pub type NetworkManagerMT = Arc<tokio::sync::RwLock<NetworkManagerST>>;
-
-impl NetworkManagerST {
-    pub async fn new(
-        auth_address: SuiAddress,
-        keystore_pathname: Option<&str>,
-    ) -> Result<Self, anyhow::Error> {
-        // TODO Extra validation that keystore and client_address are valid.
-
-        // TODO Rewrite the building of the PathBuf for devnet/testnet... mainnet.
-
-        let pathbuf = if let Some(x) = keystore_pathname {
-            PathBuf::from(x)
-        } else {
-            let path = env!("CARGO_MANIFEST_DIR");
-            let pathname = format!("{}/{}", path, DEFAULT_LOCALNET_KEYSTORE_PATHNAME);
-            PathBuf::from(pathname)
-        };
-
-        let keystore = Keystore::File(FileBasedKeystore::new(&pathbuf)?);
-
-        let rpc = SuiSDKParamsRPC {
-            client_address: auth_address,
-            sui_client: None,
-        };
-
-        // TODO Do this here ????
-        // Get the package_id from reading the file at:
-        //   ~/suibase/workdirs/localnet/published-data/dtp/most-recent/package-id.json
-        // Example of content:
-        //    ["0x6f3609095927e103a874bc1b82673ff202a42280344fca0054262642c8ed8feb"]
-        //
-        let txn = SuiSDKParamsTxn {
-            package_id: ObjectID::ZERO, // TODO Revisit this when mainnet.
-            gas_address: SuiAddress::ZERO,
-            keystore: KeystoreWrapped { inner: keystore },
-        };
-
-        Ok(NetworkManagerST {
-            sui_nodes: vec![SuiNode { rpc }],
-            sui_txn: txn,
+impl LocalhostProfile {
+    fn new(address: SuiAddress, nodes: &Arc<RpcNodes>) -> Self {
+        Self {
+            rpc: SuiSDKParamsRPC {
+                client_address: address,
+                nodes: nodes.clone(),
+            },
             localhost_id: None,
-            volunteers_id: Vec::new(),
             localhost: None,
             registry: None,
-        })
-    }
-
-    // Add RPC details to a Sui node.
-    pub async fn add_rpc_url(&mut self, http_url: &str) -> Result<(), anyhow::Error> {
-        if self.sui_nodes.is_empty() {
-            bail!(DTPError::DTPInternalError {
-                msg: "add_rpc_url".to_string()
-            })
-        }
-
-        if !self.sui_nodes.is_empty() && self.sui_nodes[0].rpc.sui_client.is_some() {
-            bail!(DTPError::DTPMultipleRPCNotImplemented)
         }
-
-        let sui_client = SuiClientBuilder::default().build(http_url).await?;
-        self.sui_nodes[0].rpc.sui_client = Some(SuiClientWrapped { inner: sui_client });
-
-        // Add event loop handling. For now, simply subscribe to
-        // all events touching this client.
-
-        Ok(())
     }
 
-    // Accessors
-    pub fn get_auth_address(&self) -> &SuiAddress {
-        &self.sui_nodes[0].rpc.client_address
-    }
-    pub fn get_package_id(&self) -> &ObjectID {
-        &self.sui_txn.package_id
-    }
-    pub fn get_localhost_id(&self) -> &Option<ObjectID> {
-        &self.localhost_id
-    }
-
-    // TODO Needed?
-    pub fn get_sui_client(&self) -> Option<&SuiClient> {
-        let sui_client_wrapped = self.sui_nodes[0].rpc.sui_client.as_ref();
-        if let Some(sui_client_wrapped) = sui_client_wrapped {
-            return Some(&sui_client_wrapped.inner);
-        }
-        None
-    }
-    pub fn get_gas_address(&self) -> &SuiAddress {
-        &self.sui_txn.gas_address
-    }
-
-    // Mutators
-    pub fn set_package_id(&mut self, package_id: ObjectID) {
-        self.sui_txn.package_id = package_id;
-    }
-
-    pub fn set_gas_address(&mut self, gas_address: SuiAddress) {
-        self.sui_txn.gas_address = gas_address;
-    }
-
-    /*
-    pub fn set_localhost_id(&mut self, localhost_id: ObjectID) {
-        self.localhost_id = Some(localhost_id);
-    }*/
-
-    // Accessors that do a JSON-RPC call.
-    pub async fn get_host_by_id(
-        &self,
-        host_id: ObjectID,
-    ) -> Result<Option<HostInternalST>, anyhow::Error> {
-        super::get_host_internal_by_id(&self.sui_nodes[0].rpc, host_id).await
-    }
-
-    pub async fn get_host_by_auth(
-        &self,
-        address: &SuiAddress,
-    ) -> Result<Option<HostInternalST>, anyhow::Error> {
-        super::get_host_internal_by_auth(&self.sui_nodes[0].rpc, &self.sui_txn.package_id, address)
-            .await
-    }
-
-    async fn get_localhost_id_from_registry(&mut self) -> Result<Option<ObjectID>, anyhow::Error> {
+    async fn get_localhost_id_from_registry(
+        &mut self,
+        package_id: &ObjectID,
+    ) -> Result<Option<ObjectID>, DTPError> {
         // Returns Ok(None) if confirmed there is no registry on network.
         // Uses cached UserRegistryInternal when already loaded.
-        self.load_user_registry().await?;
+        self.load_user_registry(package_id).await?;
         if let Some(registry) = &self.registry {
             if let Some(host_id) = registry.localhost_id() {
                 return Ok(Some(host_id));
             } else {
                 // Some registry but no host_id? Must be a bug.
-                bail!(DTPError::DTPInternalError {
-                    msg: "get_localhost_id_from_registry".to_string()
-                })
+                return Err(DTPError::DTPInternalError {
+                    msg: "get_localhost_id_from_registry".to_string(),
+                });
             }
         }
         Ok(None)
     }
 
-    async fn load_user_registry(&mut self) -> Result<(), anyhow::Error> {
+    async fn load_user_registry(&mut self, package_id: &ObjectID) -> Result<(), DTPError> {
         // Load the user registry from the network, if not already done.
         // To force an update, look for force_load_user_registry().
         if self.registry.is_none() {
-            self.force_load_user_registry().await?;
+            self.force_load_user_registry(package_id).await?;
         }
         Ok(())
     }
 
-    async fn force_load_user_registry(&mut self) -> Result<(), anyhow::Error> {
+    async fn force_load_user_registry(&mut self, package_id: &ObjectID) -> Result<(), DTPError> {
         // Load the latest user registry from the network, even if already loaded in-memory.
         // If does not exists or on failures, leave the memory version unmodified.
         let new_registry = super::get_user_registry_internal_by_auth(
-            &self.sui_nodes[0].rpc,
-            &self.sui_txn.package_id,
-            self.get_auth_address(),
+            &self.rpc,
+            package_id,
+            &self.rpc.client_address,
         )
         .await?;
         if new_registry.is_none() {
@@ -241,11 +137,10 @@ impl NetworkManagerST {
 
         Err(DTPError::DTPInternalError {
             msg: "force_load_user_registry".to_string(),
-        }
-        .into()) // Should never happen.
+        }) // Should never happen.
     }
 
-    pub async fn sync_registry(&mut self) -> Result<(), anyhow::Error> {
+    async fn sync_registry(&mut self, txn: &SuiSDKParamsTxn) -> Result<(), DTPError> {
         // (1) If there is no self.localhost_id and no registry, then do nothing.
         //
         // (1) If Some(self.localhost_id) because a new localhost has been created
@@ -268,61 +163,106 @@ impl NetworkManagerST {
 
         if self.registry.is_none() {
             // Load the registry to check if matching or need to be created.
-            self.load_user_registry().await?;
+            self.load_user_registry(&txn.package_id).await?;
             if self.registry.is_none() {
-                let new_registry = super::create_registry_on_network(
-                    &self.sui_nodes[0].rpc,
-                    &self.sui_txn,
-                    self.localhost_id.unwrap(),
-                )
-                .await?;
-                self.registry = Some(new_registry);
-                return Ok(());
+                self.create_registry(txn).await?;
+                if self.registry.as_ref().unwrap().localhost_id() == self.localhost_id {
+                    return Ok(());
+                }
+                // Created concurrently by another DTP instance of this address.
             }
         }
 
+        // The registry is the reference for all the DTP instances of this address. A
+        // localhost created concurrently that did not make it into the registry is
+        // rolled into the one of the registry (the Move package has no call to delete
+        // a Host, so the other one is left unused on the network).
+        let registry_localhost_id = self.registry.as_ref().unwrap().localhost_id();
+        if registry_localhost_id.is_some() && registry_localhost_id != self.localhost_id {
+            warn!(
+                "sync_registry: localhost {:?} not in the registry of {}, using {:?}",
+                self.localhost_id, self.rpc.client_address, registry_localhost_id
+            );
+            self.localhost_id = registry_localhost_id;
+            self.localhost = None; // Reloaded by ensure_localhost_ready().
+        }
+
         // TODO Logic to update the registry (not needed for now).
 
         Ok(())
     }
 
-    pub async fn get_localhost_by_auth(&mut self) -> Result<Option<HostInternalST>, anyhow::Error> {
+    async fn create_registry(&mut self, txn: &SuiSDKParamsTxn) -> Result<(), DTPError> {
+        // Must be called with a localhost_id and no registry loaded.
+        //
+        // On a version conflict, the registry is re-fetched: another instance of this
+        // address may have created it in the meantime (then used instead).
+        let localhost_id = self.localhost_id.unwrap();
+        let mut attempt = 1;
+        loop {
+            let e = match super::create_registry_on_network(&self.rpc, txn, localhost_id).await {
+                Ok(new_registry) => {
+                    self.registry = Some(new_registry);
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+            if !e.is_version_conflict() {
+                return Err(e);
+            }
+            let localhost_id_before = self.localhost_id;
+            self.force_load_user_registry(&txn.package_id).await?;
+            if self.registry.is_some() {
+                // Keep ours until sync_registry() decides (force_load_user_registry()
+                // copies the one of the registry).
+                self.localhost_id = localhost_id_before;
+                return Ok(());
+            }
+            if attempt >= super::REGISTRY_UPDATE_MAX_ATTEMPTS {
+                let owner = format!("of {}", self.rpc.client_address);
+                return Err(super::registry_conflict_error(&owner, attempt, &e));
+            }
+            info!("create_registry: conflict on attempt {}", attempt);
+            super::wait_before_registry_retry(attempt).await;
+            attempt += 1;
+        }
+    }
+
+    async fn get_localhost_by_auth(
+        &mut self,
+        package_id: &ObjectID,
+    ) -> Result<Option<HostInternalST>, DTPError> {
         // Note: The returned HostInternal is for the API Host object (which does not own a LocalhostInternal).
-        //       Instead, a single instance of LocalhostInternal is cached by the netmgr.
+        //       Instead, a single instance of LocalhostInternal is cached per profile.
 
         // Similar to get_host_by_auth, but do a few extra steps
         // Get the id from one of the following source (in order):
-        //   - Cached value in NetworkMgr.
-        //   - From the registry of the local auth.
-        //   - With a fetch of object owned by auth, and pick the first Host found.
+        //   - Cached value in the profile.
+        //   - From the registry of the profile address.
+        //   - With a fetch of object owned by the profile address, and pick the first Host found.
         //
         let localhost_id = match self.localhost_id {
             Some(x) => Some(x),
             None => {
-                self.load_user_registry().await?;
+                self.load_user_registry(package_id).await?;
                 self.localhost_id
             }
         };
 
         let host_internal: Option<HostInternalST> = if localhost_id.is_none() {
-            let auth_address = self.get_auth_address();
+            let auth_address = &self.rpc.client_address;
             info!(
                 "get_localhost_by_auth from network. Fetch for auth [{}]",
                 auth_address
             );
-            super::get_host_internal_by_auth(
-                &self.sui_nodes[0].rpc,
-                &self.sui_txn.package_id,
-                auth_address,
-            )
-            .await?
+            super::get_host_internal_by_auth(&self.rpc, package_id, auth_address).await?
         } else {
             let localhost_id = localhost_id.unwrap();
             info!(
                 "get_localhost_by_auth from network. Fetch for known id [{}]",
                 localhost_id
             );
-            super::get_host_internal_by_id(&self.sui_nodes[0].rpc, localhost_id).await?
+            super::get_host_internal_by_id(&self.rpc, localhost_id).await?
         };
 
         if host_internal.is_none() {
@@ -332,8 +272,7 @@ impl NetworkManagerST {
 
         // Initialize the cached localhost.
         let host_internal = host_internal.unwrap();
-        let localhost_internal =
-            super::create_localhost_from_host(&self.sui_nodes[0].rpc, host_internal);
+        let localhost_internal = super::create_localhost_from_host(&self.rpc, host_internal);
 
         let localhost_id = localhost_internal.object_id(); // Copy for later
 
@@ -353,22 +292,12 @@ impl NetworkManagerST {
         }))
     }
 
-    pub async fn load_local_client_registry(
+    async fn create_localhost_on_network(
         &mut self,
-    ) -> Result<(HostInternalST, LocalhostInternal), anyhow::Error> {
-        Err(DTPError::DTPNotImplemented.into())
-    }
-
-    // Mutators that do a JSON-RPC call and transaction.
-    pub async fn init_firewall(&self) -> Result<(), anyhow::Error> {
-        // TODO Verify here client_address == localhost.admin_address
-        // Detect user error.
-        Ok(())
-    }
-
-    pub async fn create_localhost_on_network(&mut self) -> Result<HostInternalST, anyhow::Error> {
+        txn: &SuiSDKParamsTxn,
+    ) -> Result<HostInternalST, DTPError> {
         // Note: The returned HostInternal is for the API Host object (which does not own a LocalhostInternal).
-        //       Instead, a single instance of LocalhostInternal is cached by the netmgr.
+        //       Instead, a single instance of LocalhostInternal is cached per profile.
 
         // This function clear all local state and check if a
         // Localhost instance already exists on the network.
@@ -380,53 +309,45 @@ impl NetworkManagerST {
         // tentatively be created.
         self.localhost_id = None;
 
-        // Do a RPC call to get the on-chain state of the registry.
-        // If there is no registry, then assume there is no localhost.
-        // Both will be created in a single transaction later (to
-        // minimize cost and race conditions possibility).
-        let _ = self.load_local_client_registry().await;
-
         // A Localhost is already on the network.
         if let Some(x) = self.localhost_id {
-            return Err((DTPError::DTPLocalhostAlreadyExists {
+            return Err(DTPError::DTPLocalhostAlreadyExists {
                 localhost: x.to_string(),
-                client: self.get_auth_address().to_string(),
-            })
-            .into());
+                client: self.rpc.client_address.to_string(),
+            });
         }
 
         // Proceed with the creation.
         // TODO Retry once in a controlled manner?
-        let localhost =
-            super::create_localhost_on_network(&self.sui_nodes[0].rpc, &self.sui_txn).await?;
+        let localhost = super::create_localhost_on_network(&self.rpc, txn).await?;
+        Ok(self.set_created_localhost(localhost))
+    }
 
+    // The localhost was just created on the network (see create_localhost_on_network
+    // and NetworkManagerST::submit_signed).
+    fn set_created_localhost(&mut self, localhost: LocalhostInternal) -> HostInternalST {
         let localhost_id = localhost.object_id(); // Copy for later
+        let authority = localhost.authority();
 
         self.localhost_id = Some(localhost.object_id());
         self.localhost = Some(localhost);
 
         // Creation succeeded.
         //
-        // Double check to minimize caller having to deal with "race condition". Do a RPC to
-        // the fullnode to verify if it reflects the creation. Keep trying for up to 10 seconds.
-        //
-        // Holding the end-user is not ideal, but will minimize a lot of "user complain" of
-        // temporary error that are not really error. Have to give the slowest fullnodes a chance
-        // to reflect the network changes.
-
-        // TODO Must be a more robust retry ... no bail on network failure here...
-        // self.ensure_localhost_ready().await?;
+        // No need to wait for the fullnode to reflect the creation: the transaction was
+        // executed with WaitForLocalExecution and the localhost is initialized from its
+        // effects (see do_move_call_ret_created).
 
-        // Create a Host for the API user with only the ObjectID set.
+        // Create a Host for the API user with only the ObjectID and authority set.
         // The API can "catch it" as the localhost and give it special handling.
-        Ok(HostInternalST {
+        HostInternalST {
             object_id: localhost_id,
-            authority: None,
+            authority,
             raw: None,
-        })
+        }
     }
 
-    pub async fn ensure_localhost_ready(&mut self) -> Result<(), anyhow::Error> {
+    async fn ensure_localhost_ready(&mut self, package_id: &ObjectID) -> Result<(), DTPError> {
         // Most of the time this function will not detect any problem and quickly return Ok.
         //
         // In rare occasion, may detect a corner case or network disruption may have left
@@ -444,25 +365,499 @@ impl NetworkManagerST {
         // use it yet (so the object fields were never retrieve!).
         if self.localhost.is_none() {
             // This should initialize self.localhost and self.localhost_id
-            let _ = self.get_localhost_by_auth().await?;
+            let _ = self.get_localhost_by_auth(package_id).await?;
         }
 
         // Last check to confirm.
-        if self.localhost.is_none() || self.get_localhost_id().is_none() {
-            bail!(DTPError::DTPLocalhostDoesNotExists)
+        if self.localhost.is_none() || self.localhost_id.is_none() {
+            return Err(DTPError::DTPLocalhostDoesNotExists);
         }
 
         Ok(())
     }
+}
+
+#[derive(Debug)]
+pub struct NetworkManagerST {
+    sui_nodes: Vec<SuiNode>,
+
+    sui_txn: SuiSDKParamsTxn,
+
+    // Keyed by profile name. The default profile is always present, the others
+    // are added on first use (see localhost_profile.rs).
+    localhosts: HashMap<String, LocalhostProfile>,
+    profile_addresses: ProfileAddresses,
+    profiles_pathname: PathBuf,
+
+    host_name_registry: Option<HostNameRegistryInternal>, // Shared by all users of the package.
+
+    // Connection-level encryption (see conn_crypto.rs). The key is advertised on
+    // the localhost only once enable_encryption() is called.
+    enc_keypair: EncKeypair,
+    encryption_enabled: bool,
+
+    batch_config: Option<BatchConfig>, // For the connections created afterward.
+    keep_alive_config: Option<KeepAliveConfig>, // Same.
+
+    // Check the services of the target Host before opening a connection.
+    service_validation: bool,
+}
+
+pub type NetworkManagerMT = Arc<tokio::sync::RwLock<NetworkManagerST>>;
+
+impl NetworkManagerST {
+    pub async fn new(
+        auth_address: SuiAddress,
+        keystore_pathname: Option<&str>,
+    ) -> Result<Self, DTPError> {
+        // TODO Extra validation that keystore and client_address are valid.
+
+        // TODO Rewrite the building of the PathBuf for devnet/testnet... mainnet.
+
+        let pathbuf = if let Some(x) = keystore_pathname {
+            PathBuf::from(x)
+        } else {
+            let path = env!("CARGO_MANIFEST_DIR");
+            let pathname = format!("{}/{}", path, DEFAULT_LOCALNET_KEYSTORE_PATHNAME);
+            PathBuf::from(pathname)
+        };
+
+        let keystore = match FileBasedKeystore::new(&pathbuf) {
+            Ok(keystore) => Keystore::File(keystore),
+            Err(e) => {
+                return Err(DTPError::Config {
+                    msg: format!("keystore {:?} ({})", pathbuf, e),
+                })
+            }
+        };
+
+        let enc_keypair =
+            EncKeypair::load_or_create(&super::enc_keypair_pathname(&pathbuf, &auth_address))?;
+
+        let profiles_pathname = super::profiles_pathname(&pathbuf, &auth_address);
+        let profile_addresses = ProfileAddresses::load(&profiles_pathname)?;
+
+        let rpc = SuiSDKParamsRPC {
+            client_address: auth_address,
+            nodes: Arc::new(RpcNodes::new()),
+        };
+
+        let mut localhosts = HashMap::new();
+        localhosts.insert(
+            DEFAULT_PROFILE.to_string(),
+            LocalhostProfile::new(auth_address, &rpc.nodes),
+        );
+
+        // TODO Do this here ????
+        // Get the package_id from reading the file at:
+        //   ~/suibase/workdirs/localnet/published-data/dtp/most-recent/package-id.json
+        // Example of content:
+        //    ["0x6f3609095927e103a874bc1b82673ff202a42280344fca0054262642c8ed8feb"]
+        //
+        let txn = SuiSDKParamsTxn {
+            package_id: ObjectID::ZERO, // TODO Revisit this when mainnet.
+            gas_address: SuiAddress::ZERO,
+            keystore: KeystoreWrapped { inner: keystore },
+        };
+
+        Ok(NetworkManagerST {
+            sui_nodes: vec![SuiNode { rpc }],
+            sui_txn: txn,
+            localhosts,
+            profile_addresses,
+            profiles_pathname,
+            host_name_registry: None,
+            enc_keypair,
+            encryption_enabled: false,
+            batch_config: None,
+            keep_alive_config: None,
+            service_validation: false,
+        })
+    }
+
+    // Add a RPC node. Can be called multiple times for redundancy.
+    //
+    // The nodes are tried in order they were added (see add_rpc_url_with_priority
+    // for more control). On transport error, a request is retried on the next node.
+    pub async fn add_rpc_url(&mut self, http_url: &str) -> Result<(), DTPError> {
+        self.add_rpc_url_with_priority(http_url, u8::MAX).await
+    }
+
+    // Lower 'priority' value are tried first.
+    pub async fn add_rpc_url_with_priority(
+        &mut self,
+        http_url: &str,
+        priority: u8,
+    ) -> Result<(), DTPError> {
+        if self.sui_nodes.is_empty() {
+            return Err(DTPError::DTPInternalError {
+                msg: "add_rpc_url".to_string(),
+            });
+        }
+
+        self.sui_nodes[0].rpc.nodes.add(http_url, priority).await?;
+
+        // Add event loop handling. For now, simply subscribe to
+        // all events touching this client.
+
+        Ok(())
+    }
+
+    // Which RPC node served the most recent operations (and health of each node).
+    pub fn rpc_stats(&self) -> RpcStats {
+        self.sui_nodes[0].rpc.nodes.stats()
+    }
+
+    // Deadline of every RPC operation (see DEFAULT_OPERATION_TIMEOUT).
+    pub fn set_operation_timeout(&self, timeout: Duration) {
+        self.sui_nodes[0].rpc.nodes.set_operation_timeout(timeout);
+    }
+
+    // Batching of the requests of the connections created afterward (see
+    // BatchConfig). None (the default) sends each request in its own transaction.
+    pub fn set_batch_config(&mut self, batch_config: Option<BatchConfig>) {
+        self.batch_config = batch_config;
+    }
+
+    // Keep-alive of the connections created afterward (see KeepAliveConfig). None
+    // (the default) writes no heartbeat, and the connections are always Alive.
+    //
+    // The heartbeats are written by a task started with start_keep_alive().
+    pub fn set_keep_alive_config(&mut self, keep_alive_config: Option<KeepAliveConfig>) {
+        self.keep_alive_config = keep_alive_config;
+    }
+
+    pub fn get_keep_alive_config(&self) -> Option<KeepAliveConfig> {
+        self.keep_alive_config
+    }
+
+    // When enabled, a connection to a service not offered by the target Host fails
+    // with ServiceUnavailable, without a transaction (see HostInternalST::services).
+    pub fn set_service_validation(&mut self, enabled: bool) {
+        self.service_validation = enabled;
+    }
+
+    fn validate_service(
+        &self,
+        target_host: &HostInternalST,
+        service_idx: u8,
+    ) -> Result<(), DTPError> {
+        if self.service_validation {
+            target_host.check_service(service_idx)?;
+        }
+        Ok(())
+    }
+
+    // Accessors
+    pub fn get_auth_address(&self) -> &SuiAddress {
+        &self.sui_nodes[0].rpc.client_address
+    }
+    pub fn get_package_id(&self) -> &ObjectID {
+        &self.sui_txn.package_id
+    }
+    pub fn get_localhost_id(&self, profile: &str) -> Option<ObjectID> {
+        self.localhosts
+            .get(profile)
+            .and_then(|localhost| localhost.localhost_id)
+    }
+
+    // The authority of the Host of 'profile'. None when the profile was never created.
+    pub fn get_profile_address(&self, profile: &str) -> Option<SuiAddress> {
+        if profile == DEFAULT_PROFILE {
+            return Some(*self.get_auth_address());
+        }
+        self.profile_addresses.get(profile)
+    }
+
+    // All the profiles created with this auth address (the default one first).
+    pub fn get_profiles(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profile_addresses.profiles().cloned())
+            .collect()
+    }
+
+    pub fn get_gas_address(&self) -> &SuiAddress {
+        &self.sui_txn.gas_address
+    }
+
+    // Mutators
+    pub fn set_package_id(&mut self, package_id: ObjectID) {
+        if package_id != self.sui_txn.package_id {
+            // Host names are scoped per package.
+            self.host_name_registry = None;
+        }
+        self.sui_txn.package_id = package_id;
+    }
+
+    pub fn set_gas_address(&mut self, gas_address: SuiAddress) {
+        self.sui_txn.gas_address = gas_address;
+    }
+
+    // Make sure self.localhosts has the entry of 'profile'.
+    //
+    // Returns false when the profile has no address yet (never created).
+    fn load_profile(&mut self, profile: &str) -> Result<bool, DTPError> {
+        validate_profile_name(profile)?;
+        if self.localhosts.contains_key(profile) {
+            return Ok(true);
+        }
+        match self.profile_addresses.get(profile) {
+            Some(address) => {
+                let localhost = LocalhostProfile::new(address, &self.sui_nodes[0].rpc.nodes);
+                self.localhosts.insert(profile.to_string(), localhost);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Generate the key of a new profile in the keystore, record it and fund it
+    // from the auth address (see localhost_profile.rs).
+    async fn derive_profile_address(&mut self, profile: &str) -> Result<SuiAddress, DTPError> {
+        let alias = super::profile_key_alias(self.get_auth_address(), profile);
+        let (address, _, _) = self
+            .sui_txn
+            .keystore
+            .inner
+            .generate_and_add_new_key(SignatureScheme::ED25519, Some(alias), None, None)
+            .map_err(|e| DTPError::Config {
+                msg: format!("profile {} key generation failed ({})", profile, e),
+            })?;
+
+        // Recorded before the funding, so the key is never "lost" on a failure.
+        self.profile_addresses.insert(profile, address);
+        self.profile_addresses.save(&self.profiles_pathname)?;
+        info!("profile {} derived address {}", profile, address);
+
+        super::transfer_sui_on_network(
+            &self.sui_nodes[0].rpc,
+            &self.sui_txn,
+            address,
+            PROFILE_INITIAL_FUNDING,
+        )
+        .await?;
+        Ok(address)
+    }
+
+    /*
+    pub fn set_localhost_id(&mut self, localhost_id: ObjectID) {
+        self.localhost_id = Some(localhost_id);
+    }*/
+
+    // Accessors that do a JSON-RPC call.
+    pub async fn get_host_by_id(
+        &self,
+        host_id: ObjectID,
+    ) -> Result<Option<HostInternalST>, DTPError> {
+        super::get_host_internal_by_id(&self.sui_nodes[0].rpc, host_id).await
+    }
+
+    pub async fn get_host_by_auth(
+        &self,
+        address: &SuiAddress,
+    ) -> Result<Option<HostInternalST>, DTPError> {
+        super::get_host_internal_by_auth(&self.sui_nodes[0].rpc, &self.sui_txn.package_id, address)
+            .await
+    }
+
+    async fn load_host_name_registry(&mut self) -> Result<HostNameRegistryInternal, DTPError> {
+        // The registry is created once with the package, so cache it "forever".
+        if let Some(registry) = &self.host_name_registry {
+            return Ok(registry.clone());
+        }
+        let registry =
+            super::get_host_name_registry_internal(&self.sui_nodes[0].rpc, self.sui_txn.package_id)
+                .await?;
+        match registry {
+            Some(registry) => {
+                self.host_name_registry = Some(registry.clone());
+                Ok(registry)
+            }
+            None => Err(DTPError::DTPHostNameRegistryNotFound {
+                package_id: self.sui_txn.package_id.to_string(),
+            }),
+        }
+    }
+
+    // Returns Ok(None) if confirmed the name is not registered.
+    pub async fn get_host_id_by_name(&mut self, name: &str) -> Result<Option<ObjectID>, DTPError> {
+        super::validate_host_name(name)?;
+        let registry = self.load_host_name_registry().await?;
+        super::get_host_id_by_name(&self.sui_nodes[0].rpc, &registry, name).await
+    }
+
+    // Register 'name' for the localhost of 'profile'. Succeed if already registered to it.
+    pub async fn register_host_name(&mut self, profile: &str, name: &str) -> Result<(), DTPError> {
+        super::validate_host_name(name)?;
+        if !self.load_profile(profile)? {
+            return Err(DTPError::DTPLocalhostDoesNotExists);
+        }
+        let registry = self.load_host_name_registry().await?;
+
+        // unwrap() will not fail because load_profile()
+        let localhost = self.localhosts.get_mut(profile).unwrap();
+        if localhost.localhost_id.is_none() {
+            localhost.localhost_id = localhost
+                .get_localhost_id_from_registry(&self.sui_txn.package_id)
+                .await?;
+        }
+        let localhost_id = match localhost.localhost_id {
+            Some(localhost_id) => localhost_id,
+            None => return Err(DTPError::DTPLocalhostDoesNotExists),
+        };
+
+        // Signed by the authority of the Host.
+        let rpc = &localhost.rpc;
+
+        // The registry is shared by all the users of the package, so the transaction
+        // may conflict with a concurrent registration (see REGISTRY_UPDATE_MAX_ATTEMPTS).
+        let mut attempt = 1;
+        loop {
+            // Check first (again on every attempt), so a collision is reported with a
+            // specific error instead of a Move abort.
+            match super::get_host_id_by_name(rpc, &registry, name).await? {
+                Some(host_id) if host_id == localhost_id => return Ok(()),
+                Some(host_id) => {
+                    return Err(DTPError::DTPHostNameAlreadyRegistered {
+                        name: name.to_string(),
+                        host: host_id.to_string(),
+                    })
+                }
+                None => {}
+            }
+
+            let result = super::register_host_name_on_network(
+                rpc,
+                &self.sui_txn,
+                &registry,
+                localhost_id,
+                name,
+            )
+            .await;
+            let e = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if e.is_version_conflict() {
+                if attempt >= super::REGISTRY_UPDATE_MAX_ATTEMPTS {
+                    let registry_id = registry.object_id().to_string();
+                    return Err(super::registry_conflict_error(&registry_id, attempt, &e));
+                }
+                info!(
+                    "register_host_name {}: conflict on attempt {}",
+                    name, attempt
+                );
+                super::wait_before_registry_retry(attempt).await;
+                attempt += 1;
+                continue;
+            }
+            // Someone else may have registered the name in the meantime.
+            if let Ok(Some(host_id)) = super::get_host_id_by_name(rpc, &registry, name).await {
+                if host_id != localhost_id {
+                    return Err(DTPError::DTPHostNameAlreadyRegistered {
+                        name: name.to_string(),
+                        host: host_id.to_string(),
+                    });
+                }
+            }
+            return Err(e);
+        }
+    }
+
+    pub async fn sync_registry(&mut self, profile: &str) -> Result<(), DTPError> {
+        if !self.load_profile(profile)? {
+            return Ok(()); // Do nothing.
+        }
+        // unwrap() will not fail because load_profile()
+        let localhost = self.localhosts.get_mut(profile).unwrap();
+        localhost.sync_registry(&self.sui_txn).await
+    }
+
+    // Get the localhost of 'profile' from the network (does not create it).
+    //
+    // Returns Ok(None) if confirmed that it does not exist.
+    pub async fn get_localhost(
+        &mut self,
+        profile: &str,
+    ) -> Result<Option<HostInternalST>, DTPError> {
+        match self.get_localhost_id(profile) {
+            // Get latest from likely existing Host object on the network.
+            Some(localhost_id) => self.get_host_by_id(localhost_id).await,
+            // Best-effort find among owned object of the profile address.
+            None => self.get_localhost_by_auth(profile).await,
+        }
+    }
+
+    pub async fn get_localhost_by_auth(
+        &mut self,
+        profile: &str,
+    ) -> Result<Option<HostInternalST>, DTPError> {
+        if !self.load_profile(profile)? {
+            return Ok(None);
+        }
+        // unwrap() will not fail because load_profile()
+        let localhost = self.localhosts.get_mut(profile).unwrap();
+        localhost
+            .get_localhost_by_auth(&self.sui_txn.package_id)
+            .await
+    }
+
+    pub async fn load_local_client_registry(
+        &mut self,
+    ) -> Result<(HostInternalST, LocalhostInternal), DTPError> {
+        Err(DTPError::DTPNotImplemented)
+    }
+
+    // Mutators that do a JSON-RPC call and transaction.
+    pub async fn init_firewall(&self) -> Result<(), DTPError> {
+        // TODO Verify here client_address == localhost.admin_address
+        // Detect user error.
+        Ok(())
+    }
+
+    pub async fn create_localhost_on_network(
+        &mut self,
+        profile: &str,
+    ) -> Result<HostInternalST, DTPError> {
+        // A profile other than the default one needs its own address first
+        // (one Host per address).
+        if !self.load_profile(profile)? {
+            self.derive_profile_address(profile).await?;
+            self.load_profile(profile)?;
+        }
+
+        // Do a RPC call to get the on-chain state of the registry.
+        // If there is no registry, then assume there is no localhost.
+        // Both will be created in a single transaction later (to
+        // minimize cost and race conditions possibility).
+        let _ = self.load_local_client_registry().await;
+
+        // unwrap() will not fail because load_profile()
+        let localhost = self.localhosts.get_mut(profile).unwrap();
+        localhost.create_localhost_on_network(&self.sui_txn).await
+    }
+
+    // The connections are always from the localhost of the default profile.
+    pub async fn ensure_localhost_ready(&mut self) -> Result<(), DTPError> {
+        // unwrap() will not fail because the default profile is always present.
+        let localhost = self.localhosts.get_mut(DEFAULT_PROFILE).unwrap();
+        localhost
+            .ensure_localhost_ready(&self.sui_txn.package_id)
+            .await
+    }
+
+    // Must be called only after ensure_localhost_ready() succeeded.
+    fn default_localhost(&self) -> &LocalhostInternal {
+        self.localhosts[DEFAULT_PROFILE].localhost.as_ref().unwrap()
+    }
 
     pub async fn ping_on_network(
         &mut self,
         target_host: &HostInternalST,
-    ) -> Result<PingStats, anyhow::Error> {
+    ) -> Result<PingStats, DTPError> {
         self.ensure_localhost_ready().await?;
 
-        // unwrap() will not fail because ensure_localhost_ready()
-        let localhost = self.localhost.as_ref().unwrap();
+        let localhost = self.default_localhost();
 
         // Create connection.
         let mut _tci = super::open_connection_on_network(
@@ -475,8 +870,17 @@ impl NetworkManagerST {
         .await?;
 
         // Make sure this DTP client
+        let rpc_stats = self.sui_nodes[0].rpc.nodes.stats();
         let stats = PingStats {
             ping_count_attempted: 1,
+            conn_creation_time: rpc_stats
+                .confirmations
+                .last()
+                .map_or(0, |confirmation| confirmation.latency_ms),
+            rpc_url: rpc_stats
+                .history
+                .last()
+                .map(|served_by| served_by.url.clone()),
             ..Default::default()
         };
 
@@ -487,12 +891,12 @@ impl NetworkManagerST {
         &mut self,
         target_host: &HostInternalST,
         service_idx: u8,
-    ) -> Result<TransportControlInternalMT, anyhow::Error> {
+    ) -> Result<TransportControlInternalMT, DTPError> {
         // Creates a new connection even if one already exists on the network.
+        self.validate_service(target_host, service_idx)?;
         self.ensure_localhost_ready().await?;
 
-        // unwrap() will not fail because ensure_localhost_ready()
-        let localhost = self.localhost.as_ref().unwrap();
+        let localhost = self.default_localhost();
 
         let tci = super::open_connection_on_network(
             &self.sui_nodes[0].rpc,
@@ -503,15 +907,251 @@ impl NetworkManagerST {
         )
         .await?;
 
+        self.setup_connection(&tci, target_host.object_id()).await?;
         Ok(tci)
     }
 
+    // Once the connection is open on the network.
+    async fn setup_connection(
+        &self,
+        tci: &TransportControlInternalMT,
+        peer_host_id: ObjectID,
+    ) -> Result<(), DTPError> {
+        // Encrypted only when both ends advertised a key on their Host.
+        let mut tc_guard = tci.write().await;
+        let tc = &mut *tc_guard;
+        if let Some(tc_id) = tc.get_conn_objects().map(|conn_objects| conn_objects.tc) {
+            let (encryption, cipher) = self.negotiate_encryption(peer_host_id, &tc_id).await?;
+            tc.set_encryption(encryption, cipher);
+        }
+        tc.set_batch_config(self.batch_config);
+        tc.set_keep_alive_config(self.keep_alive_config);
+        Ok(())
+    }
+
+    // Offline preparation of the one-shot methods (see PreparedTransaction). The
+    // signer is always the auth address, so there is no prepare for the Host of
+    // another profile.
+    pub async fn prepare_create_localhost(&self) -> Result<PreparedTransaction, DTPError> {
+        super::prepare_move_call(
+            &self.localhosts[DEFAULT_PROFILE].rpc,
+            &self.sui_txn,
+            "api",
+            "create_host",
+            super::create_host_call_args(),
+            PreparedOp::CreateHost,
+        )
+        .await
+    }
+
+    pub async fn prepare_create_connection(
+        &mut self,
+        target_host: &HostInternalST,
+        service_idx: u8,
+    ) -> Result<PreparedTransaction, DTPError> {
+        self.validate_service(target_host, service_idx)?;
+        self.ensure_localhost_ready().await?;
+
+        let srv_host_id = target_host.object_id();
+        let call_args =
+            super::open_connection_call_args(self.default_localhost(), srv_host_id, service_idx)?;
+        super::prepare_move_call(
+            &self.sui_nodes[0].rpc,
+            &self.sui_txn,
+            "api",
+            "open_connection",
+            call_args,
+            PreparedOp::CreateConnection {
+                srv_host_id,
+                service_idx,
+            },
+        )
+        .await
+    }
+
+    // Always in its own transaction (even with a BatchConfig).
+    pub async fn prepare_send_request(
+        &mut self,
+        conn: &TransportControlInternalMT,
+        data: Vec<u8>,
+    ) -> Result<PreparedTransaction, DTPError> {
+        let n_bytes = data.len();
+        let (ipipe, cid, seq_num, data) = {
+            let conn_guard = conn.read().await;
+            let conn_st = &*conn_guard;
+            let ipipe = self.request_ipipe(conn_st).await?;
+            let (seq_num, data) = conn_st.seal_next_request(data)?;
+            (ipipe, conn_st.peek_next_cid(), seq_num, data)
+        };
+        let call_args = super::send_request_call_args(ipipe, data, cid)?;
+        super::prepare_move_call(
+            &self.sui_nodes[0].rpc,
+            &self.sui_txn,
+            "api",
+            "send_request",
+            call_args,
+            PreparedOp::SendRequest {
+                conn: conn.clone(),
+                ipipe,
+                cid,
+                seq_num,
+                n_bytes,
+            },
+        )
+        .await
+    }
+
+    // Execute a prepared transaction signed elsewhere. The signature must be by the
+    // auth address for the prepared tx_bytes (checked before broadcasting).
+    //
+    // Not consumed, so it can be submitted again with another signature when rejected
+    // before broadcasting. Once executed, a new submit fails as stale.
+    pub async fn submit_signed(
+        &mut self,
+        prepared: &PreparedTransaction,
+        signature: &[u8],
+    ) -> Result<SubmittedInternal, DTPError> {
+        let signature = super::verify_signature(&prepared.call.tx_data, signature)?;
+        let rpc = &self.sui_nodes[0].rpc;
+        super::check_object_versions(rpc, &prepared.pinned).await?;
+
+        let call = prepared.call.clone();
+        match &prepared.op {
+            PreparedOp::CreateHost => {
+                let created =
+                    super::execute_ret_created(rpc, call, signature, "host", "Host").await?;
+                // unwrap() will not fail because the default profile is always present.
+                let localhost = self.localhosts.get_mut(DEFAULT_PROFILE).unwrap();
+                let localhost_internal =
+                    super::create_localhost_from_created(&localhost.rpc, created);
+                Ok(SubmittedInternal::Host(
+                    localhost.set_created_localhost(localhost_internal),
+                ))
+            }
+            PreparedOp::CreateConnection {
+                srv_host_id,
+                service_idx,
+            } => {
+                let conn_req_raw = super::execute_ret_event::<ConnReqMoveRaw>(
+                    rpc, call, signature, "events", "ConnReq",
+                )
+                .await?;
+                let tci = super::conn_req_to_internal(conn_req_raw, *srv_host_id, *service_idx)?;
+                self.setup_connection(&tci, *srv_host_id).await?;
+                Ok(SubmittedInternal::Connection(tci))
+            }
+            PreparedOp::SendRequest {
+                conn,
+                ipipe,
+                cid,
+                seq_num,
+                n_bytes,
+            } => {
+                conn.write()
+                    .await
+                    .commit_prepared_request(*ipipe, *seq_num, *cid)?;
+                let gas_spent = super::execute_ret_gas(rpc, call, signature).await?;
+                conn.write().await.report_request_sent(*n_bytes, gas_spent);
+                Ok(SubmittedInternal::Request)
+            }
+        }
+    }
+
+    // Advertise the encryption key on the localhost (a transaction only when not
+    // already done). The connections created afterward are encrypted when the
+    // peer did the same.
+    pub async fn enable_encryption(&mut self) -> Result<(), DTPError> {
+        self.ensure_localhost_ready().await?;
+        let localhost_id = self.default_localhost().object_id();
+
+        let rpc = &self.sui_nodes[0].rpc;
+        let public_key = self.enc_keypair.public_key();
+        let advertised = super::get_host_enc_key(rpc, localhost_id).await?;
+        if advertised.as_deref() != Some(&public_key[..]) {
+            super::set_host_enc_key_on_network(rpc, &self.sui_txn, localhost_id, &public_key)
+                .await?;
+        }
+        self.encryption_enabled = true;
+        Ok(())
+    }
+
+    pub fn is_encryption_enabled(&self) -> bool {
+        self.encryption_enabled
+    }
+
+    async fn negotiate_encryption(
+        &self,
+        peer_host_id: ObjectID,
+        tc: &ObjectID,
+    ) -> Result<(ConnEncryption, Option<ConnCipher>), DTPError> {
+        let peer_key = super::get_host_enc_key(&self.sui_nodes[0].rpc, peer_host_id).await?;
+        match (self.encryption_enabled, peer_key) {
+            (true, Some(peer_key)) => {
+                let cipher = ConnCipher::new(&self.enc_keypair, &peer_key, tc)?;
+                Ok((ConnEncryption::Encrypted, Some(cipher)))
+            }
+            (false, None) => Ok((ConnEncryption::Plaintext, None)),
+            _ => {
+                warn!(
+                    "connection {} with host {} is not encrypted (only one end has a key)",
+                    tc, peer_host_id
+                );
+                Ok((ConnEncryption::PlaintextMixedMode, None))
+            }
+        }
+    }
+
+    // For the server end of a connection: the cipher to open the requests and seal
+    // the responses. Returns Ok(None) when the connection is not encrypted.
+    pub async fn get_conn_cipher(
+        &self,
+        peer_host_id: ObjectID,
+        tc: ObjectID,
+    ) -> Result<Option<ConnCipher>, DTPError> {
+        let (_, cipher) = self.negotiate_encryption(peer_host_id, &tc).await?;
+        Ok(cipher)
+    }
+
     pub async fn send_request(
         &mut self,
         conn: &mut TransportControlInternalST,
         data: Vec<u8>,
-    ) -> Result<(), anyhow::Error> {
-        // Creates a new connection even if one already exists on the network.
+    ) -> Result<(), DTPError> {
+        let (cli_tx_pipe, cid) = self.prepare_request(conn).await?;
+
+        // Do the send_request move call. The stats are for the payload of the user.
+        let n_bytes = data.len();
+        let data = conn.seal_request(data)?;
+        let gas_spent = super::send_request_on_network(
+            &self.sui_nodes[0].rpc,
+            &self.sui_txn,
+            cli_tx_pipe,
+            data,
+            cid,
+        )
+        .await?;
+        conn.report_request_sent(n_bytes, gas_spent);
+        Ok(())
+    }
+
+    // Returns the TX ipipe and the correlation ID for the next request.
+    async fn prepare_request(
+        &mut self,
+        conn: &mut TransportControlInternalST,
+    ) -> Result<(ObjectID, u64), DTPError> {
+        let cli_tx_pipe = self.request_ipipe(conn).await?;
+
+        // Determine the correlation ID for this request.
+        let cid = conn.get_next_cid();
+
+        Ok((cli_tx_pipe, cid))
+    }
+
+    // The TX ipipe for the next request.
+    async fn request_ipipe(
+        &mut self,
+        conn: &TransportControlInternalST,
+    ) -> Result<ObjectID, DTPError> {
         self.ensure_localhost_ready().await?;
 
         // TODO Ensure ready to receive data.
@@ -519,34 +1159,56 @@ impl NetworkManagerST {
         // Identify the TX pipe to use from the ConnObjects.
         let conn_objects = conn.get_conn_objects();
         if conn_objects.is_none() {
-            bail!(DTPError::DTPInternalError {
-                msg: "send_request 1".to_string()
-            })
+            return Err(DTPError::DTPInternalError {
+                msg: "send_request 1".to_string(),
+            });
         }
         let conn_objects = conn_objects.unwrap();
         if conn_objects.cli_tx_ipipes.is_empty() {
-            bail!(DTPError::DTPInternalError {
-                msg: "send_request 2".to_string()
-            })
+            return Err(DTPError::DTPInternalError {
+                msg: "send_request 2".to_string(),
+            });
         }
 
         // For now, we just always use the first ipipe.
-        let cli_tx_pipe = conn_objects.cli_tx_ipipes[0];
+        Ok(conn_objects.cli_tx_ipipes[0])
+    }
 
-        // Determine the correlation ID for this request.
-        let cid = conn.get_next_cid();
+    // Write a batch of requests (data, cid) on 'ipipe' (see BatchConfig).
+    pub(crate) async fn send_batch_on_network(
+        &self,
+        ipipe: ObjectID,
+        requests: Vec<(Vec<u8>, u64)>,
+    ) -> Result<u64, DTPError> {
+        super::send_requests_on_network(&self.sui_nodes[0].rpc, &self.sui_txn, ipipe, requests)
+            .await
+    }
 
-        // Do the send_request move call.
+    // An empty request with HEARTBEAT_CID (see KeepAliveConfig). Returns the gas
+    // spent (Mist).
+    pub(crate) async fn send_heartbeat_on_network(
+        &self,
+        ipipe: ObjectID,
+        data: Vec<u8>,
+    ) -> Result<u64, DTPError> {
         super::send_request_on_network(
             &self.sui_nodes[0].rpc,
             &self.sui_txn,
-            cli_tx_pipe,
+            ipipe,
             data,
-            cid,
+            HEARTBEAT_CID,
         )
         .await
     }
 
+    // See common_rpc::fetch_objects_version.
+    pub(crate) async fn get_objects_version(
+        &self,
+        object_ids: &[ObjectID],
+    ) -> Result<u64, DTPError> {
+        super::fetch_objects_version(&self.sui_nodes[0].rpc, object_ids).await
+    }
+
     pub async fn low_level_send_response(
         &mut self,
         resp_ipipe_address: SuiAddress,
@@ -554,7 +1216,7 @@ impl NetworkManagerST {
         req_seq_num: u64,
         data: Vec<u8>,
         cid: u64,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<(), DTPError> {
         // For now, we just always use the first ipipe.
         let ipipe = ObjectID::from_address(resp_ipipe_address.into());
 
@@ -572,25 +1234,71 @@ impl NetworkManagerST {
     }
 }
 
+// Same as NetworkManagerST::send_request, for a connection with a BatchConfig.
+//
+// The locks are released while the request waits in the queue, so concurrent
+// senders share the transactions. Returns once the transaction of the batch is
+// confirmed.
+pub async fn send_request_batched(
+    netmgr: &NetworkManagerMT,
+    conn: &TransportControlInternalMT,
+    data: Vec<u8>,
+) -> Result<(), DTPError> {
+    let done = {
+        let mut netmgr_guard = netmgr.write().await;
+        let netmgr_st = &mut *netmgr_guard;
+
+        let mut conn_guard = conn.write().await;
+        let conn_st = &mut *conn_guard;
+
+        let (cli_tx_pipe, cid) = netmgr_st.prepare_request(conn_st).await?;
+        let n_bytes = data.len();
+        let data = conn_st.seal_request(data)?;
+        conn_st.queue_request(conn, netmgr, cli_tx_pipe, data, n_bytes, cid)?
+    };
+
+    match done.await {
+        Ok(result) => result,
+        Err(_) => Err(DTPError::DTPInternalError {
+            msg: "send_request_batched".to_string(),
+        }),
+    }
+}
+
+// Heartbeats of a connection created with a KeepAliveConfig (see
+// NetworkManagerST::get_keep_alive_config). The task ends once the connection is
+// dropped.
+pub fn start_keep_alive(
+    netmgr: &NetworkManagerMT,
+    conn: &TransportControlInternalMT,
+    config: KeepAliveConfig,
+) {
+    tokio::spawn(super::run_keep_alive(
+        config,
+        netmgr.clone(),
+        Arc::downgrade(conn),
+    ));
+}
+
 /*
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn instantiate_network_manager() -> Result<(), anyhow::Error> {
+    fn instantiate_network_manager() -> Result<(), DTPError> {
         // TODO
         Ok(())
     }
 
     #[test]
-    fn instantiate_host_internal() -> Result<(), anyhow::Error> {
+    fn instantiate_host_internal() -> Result<(), DTPError> {
         // TODO
         Ok(())
     }
 
     #[test]
-    fn instantiate_localhost_internal() -> Result<(), anyhow::Error> {
+    fn instantiate_localhost_internal() -> Result<(), DTPError> {
         // TODO
         Ok(())
     }
@@ -0,0 +1,134 @@
+// Offline transaction preparation, for a signer on another (e.g. air-gapped) machine.
+//
+// The one-shot methods (e.g. NetworkManagerST::create_connection) are split in two
+// phases:
+//
+//   prepare_*()      Build the transaction (JSON-RPC reads and a dry-run, no gas).
+//   submit_signed()  Verify the signature against the prepared bytes, then execute
+//                    and update the local state as the one-shot method does.
+//
+// The signer gets the BCS bytes of the TransactionData (tx_bytes) and returns the
+// serialized Sui signature (flag || signature || public key) of its intent message,
+// the same as "sui keytool sign" or sign_tx_bytes_offline().
+//
+// Every owned input object (including the gas coin) is pinned at its version when
+// prepared. When one of these changed before the submit (e.g. the gas coin was used
+// by another transaction), submit_signed() fails with DTPError::StaleObjectVersion
+// without broadcasting: prepare it again. Same for a request when another one was
+// sent on the connection since (the versions are then the request sequence numbers).
+//
+// The one-shot methods go through the same build/sign/execute phases, but without
+// the dry-run and the version check (signed right after the build).
+use std::path::PathBuf;
+
+use shared_crypto::intent::Intent;
+use sui_keys::keystore::{AccountKeystore, FileBasedKeystore};
+use sui_sdk::json::SuiJsonValue;
+use sui_types::base_types::{ObjectID, ObjectRef, SuiAddress};
+use sui_types::transaction::{TransactionData, TransactionDataAPI};
+
+use crate::types::{DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn};
+
+use super::{HostInternalST, MoveCallTx, TransportControlInternalMT};
+
+#[derive(Debug)]
+pub struct PreparedTransaction {
+    pub(crate) call: MoveCallTx,
+    tx_bytes: Vec<u8>,
+    expected_gas: u64,
+    pub(crate) pinned: Vec<ObjectRef>,
+    pub(crate) op: PreparedOp,
+}
+
+// What submit_signed() updates once executed.
+#[derive(Debug)]
+pub(crate) enum PreparedOp {
+    CreateHost, // Localhost of the default profile.
+    CreateConnection {
+        srv_host_id: ObjectID,
+        service_idx: u8,
+    },
+    SendRequest {
+        conn: TransportControlInternalMT,
+        ipipe: ObjectID,
+        cid: u64,
+        seq_num: u64, // Sealed with it (see TransportControlInternalST::seal_next_request).
+        n_bytes: usize, // Payload of the user (for the stats).
+    },
+}
+
+// Result of NetworkManagerST::submit_signed, depending on what was prepared.
+#[derive(Debug)]
+pub enum SubmittedInternal {
+    Host(HostInternalST),
+    Connection(TransportControlInternalMT),
+    Request,
+}
+
+impl PreparedTransaction {
+    // BCS of the TransactionData, to sign.
+    pub fn tx_bytes(&self) -> &[u8] {
+        &self.tx_bytes
+    }
+
+    // The address that must sign (the auth address).
+    pub fn signer(&self) -> SuiAddress {
+        self.call.tx_data.sender()
+    }
+
+    // Net gas (Mist) of the dry-run. The actual cost may differ slightly.
+    pub fn expected_gas(&self) -> u64 {
+        self.expected_gas
+    }
+
+    // Digest of the transaction (also once executed). A signer can recompute it from
+    // tx_bytes to confirm what it signs.
+    pub fn digest(&self) -> String {
+        self.call.tx_data.digest().to_string()
+    }
+}
+
+pub(crate) async fn prepare_move_call(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    call_module: &str,            // e.g. api
+    function: &str,               // e.g. open_connection
+    call_args: Vec<SuiJsonValue>, // Can be empty vec![]
+    op: PreparedOp,
+) -> Result<PreparedTransaction, DTPError> {
+    let call = super::build_move_call(rpc, txn, call_module, function, call_args).await?;
+    let expected_gas = super::dry_run_gas(rpc, &call).await?;
+    let pinned = super::owned_input_objects(&call.tx_data)?;
+    let tx_bytes = bcs::to_bytes(&call.tx_data).map_err(|e| DTPError::DTPInternalError {
+        msg: format!("prepare_move_call bcs ({})", e),
+    })?;
+    Ok(PreparedTransaction {
+        call,
+        tx_bytes,
+        expected_gas,
+        pinned,
+        op,
+    })
+}
+
+// Sign the tx_bytes of a PreparedTransaction with a key of a file keystore (e.g. on
+// the air-gapped machine). No network access.
+pub fn sign_tx_bytes_offline(
+    keystore_pathname: &str,
+    signer: &SuiAddress,
+    tx_bytes: &[u8],
+) -> Result<Vec<u8>, DTPError> {
+    let pathbuf = PathBuf::from(keystore_pathname);
+    let keystore = FileBasedKeystore::new(&pathbuf).map_err(|e| DTPError::Config {
+        msg: format!("keystore {:?} ({})", pathbuf, e),
+    })?;
+    let tx_data: TransactionData = bcs::from_bytes(tx_bytes).map_err(|e| DTPError::Config {
+        msg: format!("tx_bytes invalid ({})", e),
+    })?;
+    let signature = keystore
+        .sign_secure(signer, &tx_data, Intent::sui_transaction())
+        .map_err(|e| DTPError::NotAuthorized {
+            msg: format!("signing with {} failed ({})", signer, e),
+        })?;
+    Ok(signature.as_ref().to_vec())
+}
@@ -44,6 +44,15 @@ pub struct HostConfig {
     pub max_con: u32,
 }
 
+// Encryption public key advertised on a Host (a sui::dynamic_field::Field<String, vector<u8>>
+// of the Host UID). Absent when the Host owner did not enable encryption.
+#[derive(Deserialize, Debug)]
+pub struct HostEncKeyMoveRaw {
+    pub id: UID,
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
 // Data structure that **must** match the Move Host object
 #[derive(Deserialize, Debug)]
 pub struct HostMoveRaw {
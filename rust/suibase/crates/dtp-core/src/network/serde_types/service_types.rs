@@ -65,3 +65,32 @@ pub const C_SERVICE_TYPE_SSH_PORT: u32 = 22;
 
 // !!! Update SERVICE_TYPE_MAX_IDX when appending new service types. !!!
 pub const C_SERVICE_TYPE_MAX_IDX: u8 = 22;
+
+// Short names of the service types (e.g. for DTP::create_connection_by_name).
+pub const C_SERVICE_TYPE_SHORT_NAMES: &[(ServiceType, &str)] = &[
+    (C_SERVICE_TYPE_UDP_IDX, "udp"),
+    (C_SERVICE_TYPE_JSON_RPC_2_0_IDX, "json-rpc"),
+    (C_SERVICE_TYPE_GRAPHQL_IDX, "graphql"),
+    (C_SERVICE_TYPE_HTTP_IDX, "http"),
+    (C_SERVICE_TYPE_HTTPS_IDX, "https"),
+    (C_SERVICE_TYPE_ECHO_IDX, "ping"),
+    (C_SERVICE_TYPE_GRPC_IDX, "grpc"),
+    (C_SERVICE_TYPE_DISCARD_IDX, "discard"),
+    (C_SERVICE_TYPE_FTP_IDX, "ftp"),
+    (C_SERVICE_TYPE_SSH_IDX, "ssh"),
+];
+
+pub fn service_type_short_name(service_idx: ServiceType) -> Option<&'static str> {
+    C_SERVICE_TYPE_SHORT_NAMES
+        .iter()
+        .find(|(idx, _)| *idx == service_idx)
+        .map(|(_, name)| *name)
+}
+
+// Case insensitive.
+pub fn service_type_by_short_name(name: &str) -> Option<ServiceType> {
+    C_SERVICE_TYPE_SHORT_NAMES
+        .iter()
+        .find(|(_, short_name)| short_name.eq_ignore_ascii_case(name))
+        .map(|(idx, _)| *idx)
+}
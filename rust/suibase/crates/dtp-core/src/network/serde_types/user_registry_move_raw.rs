@@ -1,7 +1,7 @@
 // Must match Move object definition(s) on network
 use serde::Deserialize;
 
-use sui_sdk::types::base_types::SuiAddress;
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
 use sui_types::id::UID;
 
 #[derive(Deserialize, Debug)]
@@ -9,3 +9,25 @@ pub struct UserRegistryMoveRaw {
     pub id: UID,
     pub host_addr: SuiAddress,
 }
+
+// Mirror of sui::table::Table. The entries are dynamic fields of 'id'.
+#[derive(Deserialize, Debug)]
+pub struct TableMoveRaw {
+    pub id: ObjectID,
+    pub size: u64,
+}
+
+// Shared object created when the package is published (one per package instance).
+#[derive(Deserialize, Debug)]
+pub struct HostNameRegistryMoveRaw {
+    pub id: UID,
+    pub names: TableMoveRaw, // Table<String, ID>
+}
+
+// A HostNameRegistry entry as stored on network (a sui::dynamic_field::Field<String, ID>).
+#[derive(Deserialize, Debug)]
+pub struct HostNameEntryMoveRaw {
+    pub id: UID,
+    pub name: String,
+    pub value: ObjectID, // Host object ID.
+}
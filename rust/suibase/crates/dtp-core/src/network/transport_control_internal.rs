@@ -16,12 +16,18 @@
 use crate::types::{DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn};
 
 use super::host_internal::HostInternalST;
-use super::{ConnObjectsMoveRaw, ConnReqMoveRaw, LocalhostInternal};
+use super::{
+    ConnCipher, ConnDirection, ConnObjectsMoveRaw, ConnReqMoveRaw, LocalhostInternal,
+    NetworkManagerMT,
+};
 
 // Stuff needed typically for a Move Call
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
 use sui_sdk::json::SuiJsonValue;
+use tokio::sync::{mpsc, oneshot};
 
 use sui_types::base_types::{ObjectID, SuiAddress};
 
@@ -43,14 +49,13 @@ pub struct ConnObjectsInternal {
 
 pub fn conn_objects_raw_to_internal(
     raw: ConnObjectsMoveRaw,
-) -> Result<ConnObjectsInternal, anyhow::Error> {
+) -> Result<ConnObjectsInternal, DTPError> {
     let tc = match ObjectID::from_bytes(raw.tc) {
         Ok(x) => x,
         Err(e) => {
             return Err(DTPError::DTPFailedConnObjectsLoading {
                 desc: e.to_string(),
-            }
-            .into())
+            })
         }
     };
 
@@ -59,8 +64,7 @@ pub fn conn_objects_raw_to_internal(
         Err(e) => {
             return Err(DTPError::DTPFailedConnObjectsLoading {
                 desc: e.to_string(),
-            }
-            .into())
+            })
         }
     };
     let srv_tx_pipe = match ObjectID::from_bytes(raw.srv_tx_pipe) {
@@ -68,8 +72,7 @@ pub fn conn_objects_raw_to_internal(
         Err(e) => {
             return Err(DTPError::DTPFailedConnObjectsLoading {
                 desc: e.to_string(),
-            }
-            .into())
+            })
         }
     };
     let cli_tx_ipipes: Vec<ObjectID> = raw
@@ -104,9 +107,166 @@ pub fn conn_objects_raw_to_internal(
     })
 }
 
+// Cumulative traffic of a connection, as seen by this end-point.
+//
+// "sent" is what this end-point submitted (requests), "received" is what was
+// delivered to it (responses). The gas is for the transactions submitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportControlStats {
+    pub requests_sent: u64,
+    pub bytes_sent: u64,
+    pub responses_received: u64,
+    pub bytes_received: u64,
+    pub txns_submitted: u64,
+    pub gas_spent: u64, // Mist
+    // Batching (see BatchConfig). Each flush is counted with its reason.
+    pub batches_submitted: u64,
+    pub batched_requests: u64,
+    pub flushes_on_delay: u64,
+    pub flushes_on_bytes: u64,
+    pub flushes_on_count: u64,
+    // Keep-alive (see KeepAliveConfig). The gas is also in gas_spent.
+    pub heartbeats_sent: u64,
+    pub heartbeat_gas_spent: u64, // Mist
+}
+
+impl TransportControlStats {
+    // Requests per batch transaction. Zero when nothing was batched.
+    pub fn avg_batch_size(&self) -> f64 {
+        if self.batches_submitted == 0 {
+            return 0.0;
+        }
+        self.batched_requests as f64 / self.batches_submitted as f64
+    }
+}
+
+// Batching of the requests (see NetworkManagerST::set_batch_config).
+//
+// The requests of a connection are queued per outgoing ipipe and written by a
+// background task, many in a single transaction (one send_request Move call
+// each). A batch is flushed once its oldest request waited max_batch_delay_ms,
+// or when its payloads reach max_batch_bytes (or MAX_BATCH_REQUESTS requests).
+//
+// Only one batch per ipipe is submitted at a time, so the requests are written
+// in the order they were sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    pub max_batch_delay_ms: u64,
+    pub max_batch_bytes: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_delay_ms: 20,
+            max_batch_bytes: 16 * 1024,
+        }
+    }
+}
+
+// Move calls per transaction (well below the limits of the protocol).
+pub const MAX_BATCH_REQUESTS: usize = 128;
+
+// Keep-alive of a connection (see NetworkManagerST::set_keep_alive_config).
+//
+// A heartbeat is written every heartbeat_interval_ms while the connection is idle
+// (no request written since the previous one): an empty request with HEARTBEAT_CID,
+// which the server answers with an empty response (same cid). Neither is delivered
+// to the applications.
+//
+// The server is seen alive whenever its ipipes change (any response, including to
+// the heartbeats). It is Suspect after suspect_after_ms without being seen, and Dead
+// after dead_after_ms.
+//
+// The heartbeats of a connection stop once they spent gas_budget. The server can
+// still be seen afterward (e.g. responses to the requests).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAliveConfig {
+    pub heartbeat_interval_ms: u64,
+    pub suspect_after_ms: u64,
+    pub dead_after_ms: u64,
+    pub gas_budget: u64, // Mist, for all the heartbeats of a connection.
+}
+
+impl KeepAliveConfig {
+    // Suspect after 3 missed heartbeats, Dead after 10.
+    pub fn with_interval(heartbeat_interval_ms: u64, gas_budget: u64) -> Self {
+        Self {
+            heartbeat_interval_ms,
+            suspect_after_ms: heartbeat_interval_ms * 3,
+            dead_after_ms: heartbeat_interval_ms * 10,
+            gas_budget,
+        }
+    }
+
+    pub fn liveness_state(&self, unseen_for: Duration) -> LivenessState {
+        let unseen_ms = unseen_for.as_millis() as u64;
+        if unseen_ms >= self.dead_after_ms {
+            LivenessState::Dead
+        } else if unseen_ms >= self.suspect_after_ms {
+            LivenessState::Suspect
+        } else {
+            LivenessState::Alive
+        }
+    }
+}
+
+impl Default for KeepAliveConfig {
+    fn default() -> Self {
+        Self::with_interval(30_000, 100_000_000)
+    }
+}
+
+// Correlation ID of the heartbeats (the cid of the requests start at 1).
+pub const HEARTBEAT_CID: u64 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LivenessState {
+    Alive,
+    Suspect,
+    Dead,
+}
+
+// See TransportControlInternalST::liveness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Liveness {
+    pub last_seen: SystemTime, // When the connection was created, until seen.
+    pub state: LivenessState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushReason {
+    Delay, // Also when the connection is dropped.
+    Bytes,
+    Count,
+}
+
+#[derive(Debug)]
+struct BatchedRequest {
+    data: Vec<u8>,  // As written on-chain (sealed when encrypted).
+    n_bytes: usize, // Payload of the user (for the stats).
+    cid: u64,
+    done: oneshot::Sender<Result<(), DTPError>>,
+}
+
+// Queue of an ipipe. The task ends once the connection is dropped.
+#[derive(Debug, Clone)]
+struct PipeBatcher {
+    tx: mpsc::UnboundedSender<BatchedRequest>,
+}
+
+// Payloads of a connection, as negotiated when it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnEncryption {
+    Encrypted,
+    Plaintext,          // Neither end advertised an encryption key.
+    PlaintextMixedMode, // Only one end advertised an encryption key.
+}
+
 #[derive(Debug, Clone)]
 pub struct TransportControlInternalST {
     service_idx: u8,
+    srv_host_id: ObjectID, // Host of the peer (the server side of the connection).
     // Correlation ID.
     //
     // Unique for each request within the scope of this process.
@@ -116,12 +276,174 @@ pub struct TransportControlInternalST {
     cid_cnt: u64,
     // Set when TC confirmed exists on network.
     conn_objects: Option<ConnObjectsInternal>,
+    stats: TransportControlStats,
+    encryption: ConnEncryption,
+    cipher: Option<ConnCipher>, // Set only when Encrypted.
+    // Sequence number of the most recent request sent. Follows the pipe sequence
+    // number (first request is 1) and is used for the encryption nonce.
+    tx_seq_num: u64,
+    // Requests sent in their own transaction when None.
+    batch_config: Option<BatchConfig>,
+    batchers: HashMap<ObjectID, PipeBatcher>, // Keyed by ipipe.
+    // Liveness of the server. Always Alive without a KeepAliveConfig.
+    keep_alive: Option<KeepAliveConfig>,
+    last_seen: SystemTime,
+    peer_version: Option<u64>, // Sum of the versions of the server ipipes.
+    idle_seq_num: u64,         // tx_seq_num when the previous heartbeat was due.
 }
 
 impl TransportControlInternalST {
     pub fn get_service_idx(&self) -> u8 {
         self.service_idx
     }
+    pub fn get_srv_host_id(&self) -> ObjectID {
+        self.srv_host_id
+    }
+    pub fn get_stats(&self) -> TransportControlStats {
+        self.stats
+    }
+    pub fn get_encryption(&self) -> ConnEncryption {
+        self.encryption
+    }
+    pub fn get_batch_config(&self) -> Option<BatchConfig> {
+        self.batch_config
+    }
+
+    pub(crate) fn set_batch_config(&mut self, batch_config: Option<BatchConfig>) {
+        self.batch_config = batch_config;
+    }
+
+    pub fn get_keep_alive_config(&self) -> Option<KeepAliveConfig> {
+        self.keep_alive
+    }
+
+    pub(crate) fn set_keep_alive_config(&mut self, keep_alive: Option<KeepAliveConfig>) {
+        self.keep_alive = keep_alive;
+    }
+
+    pub fn liveness(&self) -> Liveness {
+        self.liveness_at(SystemTime::now())
+    }
+
+    fn liveness_at(&self, now: SystemTime) -> Liveness {
+        let state = match &self.keep_alive {
+            Some(keep_alive) => {
+                keep_alive.liveness_state(now.duration_since(self.last_seen).unwrap_or_default())
+            }
+            None => LivenessState::Alive,
+        };
+        Liveness {
+            last_seen: self.last_seen,
+            state,
+        }
+    }
+
+    // Something was received from the server.
+    pub fn report_peer_seen(&mut self) {
+        self.last_seen = SystemTime::now();
+    }
+
+    // The server ipipes were polled. Seen when their versions changed since the
+    // previous poll (the first one is only the reference).
+    pub(crate) fn report_peer_version(&mut self, version: u64) {
+        if self
+            .peer_version
+            .is_some_and(|previous| previous != version)
+        {
+            self.report_peer_seen();
+        }
+        self.peer_version = Some(version);
+    }
+
+    // A heartbeat is due when no request was sealed since the previous call, and the
+    // heartbeats did not spend the gas budget yet.
+    pub(crate) fn is_heartbeat_due(&mut self) -> bool {
+        let gas_budget = match &self.keep_alive {
+            Some(keep_alive) => keep_alive.gas_budget,
+            None => return false,
+        };
+        let is_idle = self.tx_seq_num == self.idle_seq_num;
+        self.idle_seq_num = self.tx_seq_num;
+        is_idle && self.stats.heartbeat_gas_spent < gas_budget
+    }
+
+    // Payload of the next heartbeat. Consumes a sequence number, as a request.
+    pub(crate) fn seal_heartbeat(&mut self) -> Result<Vec<u8>, DTPError> {
+        let data = self.seal_request(Vec::new())?;
+        self.idle_seq_num = self.tx_seq_num;
+        Ok(data)
+    }
+
+    pub(crate) fn report_heartbeat_sent(&mut self, gas_spent: u64) {
+        self.stats.heartbeats_sent += 1;
+        self.stats.heartbeat_gas_spent += gas_spent;
+        self.stats.txns_submitted += 1;
+        self.stats.gas_spent += gas_spent;
+    }
+
+    // Not written on-chain, so its sequence number is used by the next request.
+    pub(crate) fn report_heartbeat_failed(&mut self) {
+        self.tx_seq_num -= 1;
+        self.idle_seq_num = self.tx_seq_num;
+    }
+
+    pub(crate) fn set_encryption(
+        &mut self,
+        encryption: ConnEncryption,
+        cipher: Option<ConnCipher>,
+    ) {
+        self.encryption = encryption;
+        self.cipher = cipher;
+    }
+
+    // Payload to write on-chain for the next request (sealed when Encrypted).
+    pub(crate) fn seal_request(&mut self, data: Vec<u8>) -> Result<Vec<u8>, DTPError> {
+        self.tx_seq_num += 1;
+        self.seal_request_at(self.tx_seq_num, data)
+    }
+
+    // Same as seal_request, but the sequence number is consumed only once submitted
+    // (see commit_prepared_request). Returns also the sequence number.
+    pub(crate) fn seal_next_request(&self, data: Vec<u8>) -> Result<(u64, Vec<u8>), DTPError> {
+        let seq_num = self.tx_seq_num + 1;
+        Ok((seq_num, self.seal_request_at(seq_num, data)?))
+    }
+
+    fn seal_request_at(&self, seq_num: u64, data: Vec<u8>) -> Result<Vec<u8>, DTPError> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal(ConnDirection::CliToSrv, seq_num, &data),
+            None => Ok(data),
+        }
+    }
+
+    // A request prepared with seal_next_request and peek_next_cid is submitted. Fails
+    // when another request was sent on the connection since.
+    pub(crate) fn commit_prepared_request(
+        &mut self,
+        ipipe: ObjectID,
+        seq_num: u64,
+        cid: u64,
+    ) -> Result<(), DTPError> {
+        if seq_num != self.tx_seq_num + 1 {
+            return Err(DTPError::StaleObjectVersion {
+                object_id: ipipe.to_string(),
+                prepared_version: seq_num - 1,
+                current_version: self.tx_seq_num,
+            });
+        }
+        self.tx_seq_num = seq_num;
+        self.cid_cnt = self.cid_cnt.max(cid);
+        Ok(())
+    }
+
+    // Payload of a response to the request 'req_seq_num' (as read on-chain).
+    pub fn open_response(&self, req_seq_num: u64, data: Vec<u8>) -> Result<Vec<u8>, DTPError> {
+        match &self.cipher {
+            Some(cipher) => cipher.open(ConnDirection::SrvToCli, req_seq_num, &data),
+            None => Ok(data),
+        }
+    }
+
     pub fn get_conn_objects(&self) -> Option<ConnObjectsInternal> {
         self.conn_objects.clone()
     }
@@ -138,26 +460,260 @@ impl TransportControlInternalST {
         self.cid_cnt += 1;
         self.cid_cnt
     }
+
+    pub fn peek_next_cid(&self) -> u64 {
+        self.cid_cnt + 1
+    }
+
+    // A request was submitted in its own transaction.
+    pub fn report_request_sent(&mut self, n_bytes: usize, gas_spent: u64) {
+        self.stats.requests_sent += 1;
+        self.stats.bytes_sent += n_bytes as u64;
+        self.stats.txns_submitted += 1;
+        self.stats.gas_spent += gas_spent;
+    }
+
+    // A batch of 'n_requests' was submitted in a single transaction.
+    pub fn report_batch_sent(
+        &mut self,
+        n_requests: usize,
+        n_bytes: usize,
+        gas_spent: u64,
+        reason: FlushReason,
+    ) {
+        self.stats.requests_sent += n_requests as u64;
+        self.stats.bytes_sent += n_bytes as u64;
+        self.stats.txns_submitted += 1;
+        self.stats.gas_spent += gas_spent;
+        self.stats.batches_submitted += 1;
+        self.stats.batched_requests += n_requests as u64;
+        match reason {
+            FlushReason::Delay => self.stats.flushes_on_delay += 1,
+            FlushReason::Bytes => self.stats.flushes_on_bytes += 1,
+            FlushReason::Count => self.stats.flushes_on_count += 1,
+        }
+    }
+
+    pub fn report_response_received(&mut self, n_bytes: usize) {
+        self.stats.responses_received += 1;
+        self.stats.bytes_received += n_bytes as u64;
+        self.report_peer_seen();
+    }
+
+    // Queue a request (already sealed) for the batcher of 'ipipe', spawned on
+    // first use. The receiver is resolved once the transaction of its batch is
+    // confirmed, or failed with DTPError::BatchFailed.
+    //
+    // 'tc' must be the Arc of self (the stats are reported through it).
+    pub(crate) fn queue_request(
+        &mut self,
+        tc: &TransportControlInternalMT,
+        netmgr: &NetworkManagerMT,
+        ipipe: ObjectID,
+        data: Vec<u8>,
+        n_bytes: usize,
+        cid: u64,
+    ) -> Result<oneshot::Receiver<Result<(), DTPError>>, DTPError> {
+        let batch_config = match self.batch_config {
+            Some(batch_config) => batch_config,
+            None => {
+                return Err(DTPError::DTPInternalError {
+                    msg: "queue_request without batch config".to_string(),
+                })
+            }
+        };
+        let batcher = self.batchers.entry(ipipe).or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(run_pipe_batcher(
+                batch_config,
+                ipipe,
+                rx,
+                netmgr.clone(),
+                Arc::downgrade(tc),
+            ));
+            PipeBatcher { tx }
+        });
+        let (done, done_rx) = oneshot::channel();
+        let request = BatchedRequest {
+            data,
+            n_bytes,
+            cid,
+            done,
+        };
+        if batcher.tx.send(request).is_err() {
+            return Err(DTPError::DTPInternalError {
+                msg: "queue_request batcher gone".to_string(),
+            });
+        }
+        Ok(done_rx)
+    }
+}
+
+// Wait for the next batch. None once the connection is dropped and the queue
+// is empty.
+async fn collect_batch(
+    rx: &mut mpsc::UnboundedReceiver<BatchedRequest>,
+    config: &BatchConfig,
+) -> Option<(Vec<BatchedRequest>, FlushReason)> {
+    let first = rx.recv().await?;
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(config.max_batch_delay_ms);
+    let mut batch_bytes = first.data.len();
+    let mut batch = vec![first];
+    loop {
+        if batch_bytes >= config.max_batch_bytes {
+            return Some((batch, FlushReason::Bytes));
+        }
+        if batch.len() >= MAX_BATCH_REQUESTS {
+            return Some((batch, FlushReason::Count));
+        }
+        tokio::select! {
+            request = rx.recv() => match request {
+                Some(request) => {
+                    batch_bytes += request.data.len();
+                    batch.push(request);
+                }
+                // No more requests coming, so no need to wait.
+                None => return Some((batch, FlushReason::Delay)),
+            },
+            _ = tokio::time::sleep_until(deadline) => return Some((batch, FlushReason::Delay)),
+        }
+    }
+}
+
+async fn run_pipe_batcher(
+    config: BatchConfig,
+    ipipe: ObjectID,
+    mut rx: mpsc::UnboundedReceiver<BatchedRequest>,
+    netmgr: NetworkManagerMT,
+    tc: Weak<tokio::sync::RwLock<TransportControlInternalST>>,
+) {
+    while let Some((batch, reason)) = collect_batch(&mut rx, &config).await {
+        let batch_size = batch.len();
+        let n_bytes: usize = batch.iter().map(|request| request.n_bytes).sum();
+        let mut requests = Vec::with_capacity(batch_size);
+        let mut dones = Vec::with_capacity(batch_size);
+        for request in batch {
+            requests.push((request.data, request.cid));
+            dones.push(request.done);
+        }
+
+        let result = {
+            let netmgr_guard = netmgr.read().await;
+            netmgr_guard.send_batch_on_network(ipipe, requests).await
+        };
+        match result {
+            Ok(gas_spent) => {
+                // Reported before resolving, so the stats include the batch when
+                // the senders get the result.
+                if let Some(tc) = tc.upgrade() {
+                    let mut tc_guard = tc.write().await;
+                    tc_guard.report_batch_sent(batch_size, n_bytes, gas_spent, reason);
+                }
+                for done in dones {
+                    // The sender may have stopped waiting (cancelled).
+                    let _ = done.send(Ok(()));
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "batch of {} requests on {} failed ({})",
+                    batch_size,
+                    ipipe,
+                    e
+                );
+                let reason = e.to_string();
+                for done in dones {
+                    let _ = done.send(Err(DTPError::BatchFailed {
+                        batch_size,
+                        reason: reason.clone(),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+// Heartbeats of a connection with a KeepAliveConfig. Ends once the connection is
+// dropped.
+pub(crate) async fn run_keep_alive(
+    config: KeepAliveConfig,
+    netmgr: NetworkManagerMT,
+    tc: Weak<tokio::sync::RwLock<TransportControlInternalST>>,
+) {
+    let interval = Duration::from_millis(config.heartbeat_interval_ms.max(1));
+    loop {
+        tokio::time::sleep(interval).await;
+        let tc = match tc.upgrade() {
+            Some(tc) => tc,
+            None => return,
+        };
+        let conn_objects = match tc.read().await.get_conn_objects() {
+            Some(conn_objects) => conn_objects,
+            None => continue,
+        };
+
+        let netmgr_guard = netmgr.read().await;
+        match netmgr_guard
+            .get_objects_version(&conn_objects.srv_tx_ipipes)
+            .await
+        {
+            Ok(version) => tc.write().await.report_peer_version(version),
+            Err(e) => log::warn!("keep-alive of {} poll failed ({})", conn_objects.tc, e),
+        }
+
+        // The lock is held until the heartbeat is written, so no request is sealed
+        // in the meantime (same as NetworkManagerST::send_request).
+        let mut tc_guard = tc.write().await;
+        let ipipe = match conn_objects.cli_tx_ipipes.first() {
+            Some(ipipe) if tc_guard.is_heartbeat_due() => *ipipe,
+            _ => continue,
+        };
+        let result = match tc_guard.seal_heartbeat() {
+            Ok(data) => netmgr_guard.send_heartbeat_on_network(ipipe, data).await,
+            Err(e) => {
+                log::warn!("keep-alive of {} seal failed ({})", conn_objects.tc, e);
+                continue;
+            }
+        };
+        match result {
+            Ok(gas_spent) => tc_guard.report_heartbeat_sent(gas_spent),
+            // Might still be executed, so the sequence number is not reused.
+            Err(e @ DTPError::Timeout { .. }) => {
+                log::warn!("keep-alive of {} heartbeat failed ({})", conn_objects.tc, e)
+            }
+            Err(e) => {
+                log::warn!("keep-alive of {} heartbeat failed ({})", conn_objects.tc, e);
+                tc_guard.report_heartbeat_failed();
+            }
+        }
+    }
 }
 
 pub type TransportControlInternalMT = Arc<tokio::sync::RwLock<TransportControlInternalST>>;
 
-pub(crate) async fn open_connection_on_network(
-    rpc: &SuiSDKParamsRPC,
-    txn: &SuiSDKParamsTxn,
+pub(crate) fn open_connection_call_args(
     cli_host: &LocalhostInternal,
-    srv_host: &HostInternalST,
+    srv_host_id: ObjectID,
     service_idx: u8,
-) -> Result<TransportControlInternalMT, anyhow::Error> {
+) -> Result<Vec<SuiJsonValue>, DTPError> {
     // Creates also the related pipe(s) and inner pipe(s).
     let vargs: Vec<u8> = vec![];
-    let call_args = vec![
+    Ok(vec![
         SuiJsonValue::new(json!(service_idx))?,
         SuiJsonValue::from_object_id(cli_host.object_id()),
-        SuiJsonValue::from_object_id(srv_host.object_id()),
+        SuiJsonValue::from_object_id(srv_host_id),
         SuiJsonValue::from_bcs_bytes(None, &vargs).unwrap(),
-    ];
+    ])
+}
 
+pub(crate) async fn open_connection_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    cli_host: &LocalhostInternal,
+    srv_host: &HostInternalST,
+    service_idx: u8,
+) -> Result<TransportControlInternalMT, DTPError> {
+    let call_args = open_connection_call_args(cli_host, srv_host.object_id(), service_idx)?;
     let conn_req_raw = super::common_rpc::do_move_call_ret_event::<ConnReqMoveRaw>(
         rpc,
         txn,
@@ -169,36 +725,85 @@ pub(crate) async fn open_connection_on_network(
     )
     .await?;
 
+    conn_req_to_internal(conn_req_raw, srv_host.object_id(), service_idx)
+}
+
+// The ConnReq event emitted by open_connection.
+pub(crate) fn conn_req_to_internal(
+    conn_req_raw: ConnReqMoveRaw,
+    srv_host_id: ObjectID,
+    service_idx: u8,
+) -> Result<TransportControlInternalMT, DTPError> {
     // Build the internal representation.
     let conn_objs_raw = conn_req_raw.conn;
     let conn_objs = conn_objects_raw_to_internal(conn_objs_raw)?;
     let tci = TransportControlInternalST {
         service_idx,
+        srv_host_id,
         cid_cnt: 0,
         conn_objects: Some(conn_objs),
+        stats: TransportControlStats::default(),
+        encryption: ConnEncryption::Plaintext, // See NetworkManagerST::create_connection.
+        cipher: None,
+        tx_seq_num: 0,
+        batch_config: None, // See NetworkManagerST::create_connection.
+        batchers: HashMap::new(),
+        keep_alive: None, // See NetworkManagerST::create_connection.
+        last_seen: SystemTime::now(),
+        peer_version: None,
+        idle_seq_num: 0,
     };
 
     // All good. Make the TransportControlInternal thread safe.
     Ok(Arc::new(tokio::sync::RwLock::new(tci)))
 }
 
-pub(crate) async fn send_request_on_network(
-    rpc: &SuiSDKParamsRPC,
-    txn: &SuiSDKParamsTxn,
+pub(crate) fn send_request_call_args(
     ipipe: ObjectID,
     data: Vec<u8>,
     cid: u64,
-) -> Result<(), anyhow::Error> {
-    // Creates also the related pipe(s) and inner pipe(s).
+) -> Result<Vec<SuiJsonValue>, DTPError> {
     let vargs: Vec<u8> = vec![];
-    let call_args = vec![
+    Ok(vec![
         SuiJsonValue::from_object_id(ipipe),
         SuiJsonValue::new(json!(data))?,
         SuiJsonValue::new(json!(cid.to_string()))?, // TODO inefficient conversion, but needed for U64!?!?
         SuiJsonValue::from_bcs_bytes(None, &vargs).unwrap(),
-    ];
+    ])
+}
 
-    super::common_rpc::do_move_call_no_ret(rpc, txn, "api", "send_request", call_args).await
+// Returns the gas spent (Mist).
+pub(crate) async fn send_request_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    ipipe: ObjectID,
+    data: Vec<u8>,
+    cid: u64,
+) -> Result<u64, DTPError> {
+    let call_args = send_request_call_args(ipipe, data, cid)?;
+    super::common_rpc::do_move_call_ret_gas(rpc, txn, "api", "send_request", call_args).await
+}
+
+// Same as send_request_on_network, but for many requests (data, cid) written in
+// that order by a single transaction. Returns the gas spent (Mist).
+pub(crate) async fn send_requests_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    ipipe: ObjectID,
+    requests: Vec<(Vec<u8>, u64)>,
+) -> Result<u64, DTPError> {
+    let mut calls = Vec::with_capacity(requests.len());
+    for (data, cid) in requests {
+        let vargs: Vec<u8> = vec![];
+        calls.push(vec![
+            SuiJsonValue::from_object_id(ipipe),
+            SuiJsonValue::new(json!(data))?,
+            SuiJsonValue::new(json!(cid.to_string()))?,
+            SuiJsonValue::from_bcs_bytes(None, &vargs).unwrap(),
+        ]);
+    }
+
+    super::common_rpc::do_batch_move_call_ret_gas(rpc, txn, "api", "send_request", calls).await
 }
 
 pub(crate) async fn send_response_on_network(
@@ -209,7 +814,7 @@ pub(crate) async fn send_response_on_network(
     req_seq_num: u64,
     data: Vec<u8>,
     cid: u64,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), DTPError> {
     // Creates also the related pipe(s) and inner pipe(s).
     let vargs: Vec<u8> = vec![];
     let call_args = vec![
@@ -223,3 +828,110 @@ pub(crate) async fn send_response_on_network(
 
     super::common_rpc::do_move_call_no_ret(rpc, txn, "api", "send_response", call_args).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(n_bytes: usize) -> BatchedRequest {
+        let (done, _) = oneshot::channel();
+        BatchedRequest {
+            data: vec![0; n_bytes],
+            n_bytes,
+            cid: 0,
+            done,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_batch() {
+        let config = BatchConfig {
+            max_batch_delay_ms: 50,
+            max_batch_bytes: 10,
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // Flushed as soon as the bytes limit is reached.
+        for _ in 0..4 {
+            tx.send(request(4)).unwrap();
+        }
+        let (batch, reason) = collect_batch(&mut rx, &config).await.unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(reason, FlushReason::Bytes);
+
+        // The one left waits for the delay.
+        let start = tokio::time::Instant::now();
+        let (batch, reason) = collect_batch(&mut rx, &config).await.unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(reason, FlushReason::Delay);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // Without waiting once the connection is dropped.
+        tx.send(request(1)).unwrap();
+        drop(tx);
+        let start = tokio::time::Instant::now();
+        let (batch, _) = collect_batch(&mut rx, &config).await.unwrap();
+        assert_eq!(batch.len(), 1);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(collect_batch(&mut rx, &config).await.is_none());
+    }
+
+    #[test]
+    fn test_liveness() {
+        let config = KeepAliveConfig::with_interval(1000, 5000);
+        let conn_req = ConnReqMoveRaw {
+            flags: 0,
+            src: 0,
+            src_addr: SuiAddress::ZERO,
+            service_idx: 7,
+            conn: ConnObjectsMoveRaw {
+                tc: SuiAddress::ZERO,
+                cli_auth: SuiAddress::ZERO,
+                srv_auth: SuiAddress::ZERO,
+                cli_tx_pipe: SuiAddress::ZERO,
+                srv_tx_pipe: SuiAddress::ZERO,
+                cli_tx_ipipes: vec![SuiAddress::ZERO],
+                srv_tx_ipipes: vec![SuiAddress::ZERO],
+            },
+        };
+        let conn = conn_req_to_internal(conn_req, ObjectID::ZERO, 7).unwrap();
+        let mut tc = conn.try_write().unwrap();
+        let created = tc.liveness().last_seen;
+        assert_eq!(tc.liveness().state, LivenessState::Alive);
+
+        // Always Alive without a config.
+        let later = created + Duration::from_secs(3600);
+        assert_eq!(tc.liveness_at(later).state, LivenessState::Alive);
+        tc.set_keep_alive_config(Some(config));
+        assert_eq!(tc.liveness_at(later).state, LivenessState::Dead);
+        let at = |ms: u64| created + Duration::from_millis(ms);
+        assert_eq!(tc.liveness_at(at(2999)).state, LivenessState::Alive);
+        assert_eq!(tc.liveness_at(at(3000)).state, LivenessState::Suspect);
+        assert_eq!(tc.liveness_at(at(10000)).state, LivenessState::Dead);
+
+        // The first poll is only the reference.
+        tc.report_peer_version(10);
+        assert_eq!(tc.liveness().last_seen, created);
+        tc.report_peer_version(10);
+        assert_eq!(tc.liveness().last_seen, created);
+        tc.report_peer_version(12);
+        assert!(tc.liveness().last_seen >= created);
+
+        // Due only when idle, and within the gas budget.
+        assert!(tc.is_heartbeat_due());
+        tc.seal_heartbeat().unwrap();
+        tc.report_heartbeat_sent(3000);
+        assert!(tc.is_heartbeat_due());
+        tc.seal_request(b"request".to_vec()).unwrap();
+        assert!(!tc.is_heartbeat_due());
+        assert!(tc.is_heartbeat_due());
+        tc.seal_heartbeat().unwrap();
+        tc.report_heartbeat_failed();
+        assert_eq!(tc.seal_next_request(Vec::new()).unwrap().0, 3);
+        tc.report_heartbeat_sent(3000);
+        assert!(!tc.is_heartbeat_due());
+        let stats = tc.get_stats();
+        assert_eq!(stats.heartbeats_sent, 2);
+        assert_eq!(stats.gas_spent, 6000);
+    }
+}
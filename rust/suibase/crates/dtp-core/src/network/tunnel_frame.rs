@@ -0,0 +1,126 @@
+// Framing of the bytes streamed through a DTP tunnel (dtp_services with a local_port).
+//
+// A tunnel maps a TCP connection on the client side to a DTP connection. Each request
+// carries the next chunk written by the client application, and its response the next
+// chunk produced by the service on the server side:
+//
+//     [ TUNNEL_FRAME_VERSION (1 byte) ][ kind (1 byte) ][ seq (u32 BE) ][ payload ]
+//
+// The response to a request has the same seq. A Data frame with an empty payload is
+// valid (e.g. a request only polling for the bytes of the server side).
+//
+// A Close frame (no payload) tears down the connection. It is either a request (the
+// client application closed) or a response (the server side service closed).
+use crate::types::DTPError;
+
+pub const TUNNEL_FRAME_VERSION: u8 = 1;
+pub const TUNNEL_FRAME_HEADER_LENGTH: usize = 6;
+
+// Largest payload of a frame. Keeps a whole request well below the limit of Sui on
+// the size of a pure argument (including the framing and encryption overhead).
+pub const TUNNEL_MAX_PAYLOAD: usize = 8 * 1024;
+
+const KIND_DATA: u8 = 0;
+const KIND_CLOSE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelFrameKind {
+    Data,
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelFrame {
+    pub kind: TunnelFrameKind,
+    pub seq: u32,
+    pub payload: Vec<u8>,
+}
+
+impl TunnelFrame {
+    pub fn data(seq: u32, payload: Vec<u8>) -> Self {
+        Self {
+            kind: TunnelFrameKind::Data,
+            seq,
+            payload,
+        }
+    }
+
+    pub fn close(seq: u32) -> Self {
+        Self {
+            kind: TunnelFrameKind::Close,
+            seq,
+            payload: Vec::new(),
+        }
+    }
+
+    pub fn is_close(&self) -> bool {
+        self.kind == TunnelFrameKind::Close
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TUNNEL_FRAME_HEADER_LENGTH + self.payload.len());
+        bytes.push(TUNNEL_FRAME_VERSION);
+        bytes.push(match self.kind {
+            TunnelFrameKind::Data => KIND_DATA,
+            TunnelFrameKind::Close => KIND_CLOSE,
+        });
+        bytes.extend_from_slice(&self.seq.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DTPError> {
+        let invalid = |desc: &str| DTPError::DTPTunnelFrameInvalid {
+            desc: desc.to_string(),
+        };
+        if bytes.len() < TUNNEL_FRAME_HEADER_LENGTH {
+            return Err(invalid("too short"));
+        }
+        if bytes[0] != TUNNEL_FRAME_VERSION {
+            return Err(invalid(&format!("unsupported version {}", bytes[0])));
+        }
+        let seq = u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]);
+        let payload = &bytes[TUNNEL_FRAME_HEADER_LENGTH..];
+        if payload.len() > TUNNEL_MAX_PAYLOAD {
+            return Err(invalid("payload too large"));
+        }
+        match bytes[1] {
+            KIND_DATA => Ok(Self::data(seq, payload.to_vec())),
+            KIND_CLOSE if payload.is_empty() => Ok(Self::close(seq)),
+            KIND_CLOSE => Err(invalid("close with a payload")),
+            kind => Err(invalid(&format!("unknown kind {}", kind))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let frame = TunnelFrame::data(0x01020304, b"GET / HTTP/1.1\r\n".to_vec());
+        let bytes = frame.encode();
+        assert_eq!(bytes[..TUNNEL_FRAME_HEADER_LENGTH], [1, 0, 1, 2, 3, 4]);
+        assert_eq!(TunnelFrame::decode(&bytes).unwrap(), frame);
+
+        let poll = TunnelFrame::data(7, Vec::new());
+        assert_eq!(TunnelFrame::decode(&poll.encode()).unwrap(), poll);
+
+        let close = TunnelFrame::close(8);
+        assert!(TunnelFrame::decode(&close.encode()).unwrap().is_close());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(TunnelFrame::decode(&[1, 0, 0, 0, 0]).is_err());
+        assert!(TunnelFrame::decode(&[2, 0, 0, 0, 0, 0]).is_err());
+        assert!(TunnelFrame::decode(&[1, 9, 0, 0, 0, 0]).is_err());
+        assert!(TunnelFrame::decode(&[1, 1, 0, 0, 0, 0, 42]).is_err());
+
+        let mut oversized = TunnelFrame::data(0, vec![0; TUNNEL_MAX_PAYLOAD]).encode();
+        assert!(TunnelFrame::decode(&oversized).is_ok());
+        oversized.push(0);
+        assert!(TunnelFrame::decode(&oversized).is_err());
+    }
+}
@@ -14,16 +14,55 @@
 //   "UserRegistryMoveRaw"
 //
 
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use log::info;
 
+use move_core_types::language_storage::TypeTag;
 use sui_sdk::{
     json::SuiJsonValue,
     types::base_types::{ObjectID, SuiAddress},
 };
+use sui_types::dynamic_field::DynamicFieldName;
 
-use super::UserRegistryMoveRaw;
+use super::{HostNameEntryMoveRaw, HostNameRegistryMoveRaw, UserRegistryMoveRaw};
 use crate::types::{DTPError, SuiSDKParamsRPC, SuiSDKParamsTxn};
 
+// Host names are at most that many ASCII characters (enforced by the Move package).
+pub const HOST_NAME_MAX_LENGTH: usize = 64;
+
+// Optimistic updates of the registries.
+//
+// A registry update is not locked: when its transaction fails on an object version
+// conflict (e.g. another DTP instance of the same address used the same objects), the
+// caller re-fetches the registry, re-validates the change against the latest state and
+// tries again. After REGISTRY_UPDATE_MAX_ATTEMPTS, DTPError::RegistryConflict.
+pub const REGISTRY_UPDATE_MAX_ATTEMPTS: u32 = 5;
+const REGISTRY_RETRY_BASE_DELAY_MS: u64 = 100;
+
+// Exponential backoff plus a jitter of up to the base delay, so the concurrent writers
+// do not retry in lockstep. 'attempt' is the one that just failed (starts at 1).
+pub(crate) fn registry_retry_delay(attempt: u32, jitter_seed: u64) -> Duration {
+    let backoff_ms = REGISTRY_RETRY_BASE_DELAY_MS << attempt.clamp(1, 8).saturating_sub(1);
+    Duration::from_millis(backoff_ms + jitter_seed % REGISTRY_RETRY_BASE_DELAY_MS)
+}
+
+pub(crate) async fn wait_before_registry_retry(attempt: u32) {
+    let jitter_seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos() as u64);
+    tokio::time::sleep(registry_retry_delay(attempt, jitter_seed)).await;
+}
+
+pub(crate) fn registry_conflict_error(registry: &str, attempts: u32, err: &DTPError) -> DTPError {
+    DTPError::RegistryConflict {
+        registry: registry.to_string(),
+        attempts,
+        last_error: err.to_string(),
+    }
+}
+
 // Data structure that **must** match the Move Host object
 
 #[derive(Debug)]
@@ -35,12 +74,12 @@ pub struct UserRegistryInternal {
 }
 
 // Create an internal representation by consuming the raw Move object.
-fn raw_to_internal(raw: UserRegistryMoveRaw) -> Result<UserRegistryInternal, anyhow::Error> {
+fn raw_to_internal(raw: UserRegistryMoveRaw) -> Result<UserRegistryInternal, DTPError> {
     // Convert raw.host_addr to an ObjectID.
     let result = ObjectID::from_bytes(raw.host_addr);
     if let Err(e) = result {
         let desc = format!("host_addr ObjectIDParseError={}", e);
-        return Err(DTPError::DTPFailedRegistryLoad { desc }.into());
+        return Err(DTPError::DTPFailedRegistryLoad { desc });
     }
     let localhost_id = Some(result.unwrap());
 
@@ -57,7 +96,7 @@ fn raw_to_internal(raw: UserRegistryMoveRaw) -> Result<UserRegistryInternal, any
 pub(crate) async fn get_user_registry_internal_by_id(
     rpc: &SuiSDKParamsRPC,
     object_id: ObjectID,
-) -> Result<Option<UserRegistryInternal>, anyhow::Error> {
+) -> Result<Option<UserRegistryInternal>, DTPError> {
     info!("get_user_registry_internal_by_id 1");
     let raw =
         super::common_rpc::fetch_raw_move_object::<UserRegistryMoveRaw>(rpc, object_id).await?;
@@ -74,7 +113,7 @@ pub(crate) async fn get_user_registry_internal_by_auth(
     rpc: &SuiSDKParamsRPC,
     package_id: &ObjectID,
     address: &SuiAddress,
-) -> Result<Option<UserRegistryInternal>, anyhow::Error> {
+) -> Result<Option<UserRegistryInternal>, DTPError> {
     // When returning Ok(None) it means that it was verified
     // that this address does not OWN a UserRegistry.
     info!("get_user_registry_internal_by_auth 1");
@@ -99,7 +138,7 @@ pub(crate) async fn create_registry_on_network(
     rpc: &SuiSDKParamsRPC,
     txn: &SuiSDKParamsTxn,
     localhost_id: ObjectID,
-) -> Result<UserRegistryInternal, anyhow::Error> {
+) -> Result<UserRegistryInternal, DTPError> {
     // There should be only one UserRegistry per client address.
     //
     // Caller is responsible to verify if one already exists.
@@ -154,3 +193,163 @@ impl UserRegistryInternal {
         }
     }
 }
+
+// Host names
+//
+// The package has a single shared HostNameRegistry (created when published) mapping
+// a human readable name to a Host ObjectID. Therefore, names are scoped per package
+// instance (the same name can be used with another package ID).
+//
+// Requires a DTP package (maintained outside of this tree) with, in its user_registry
+// module:
+//   - the HostNameRegistry shared object, created by the module init.
+//     Its "names" Table<String, ID> holds the name entries.
+//   - entry fun register_host_name(&mut HostNameRegistry, &Host, String, &mut TxContext)
+//     called by the authority of the Host. Aborts if the name is already registered.
+//
+// An older package fails with DTPHostNameRegistryNotFound (no registry object) or
+// DTPMoveFunctionNotFound (no register_host_name).
+#[derive(Debug, Clone)]
+pub struct HostNameRegistryInternal {
+    object_id: ObjectID,
+    names_table_id: ObjectID, // Parent of the name entries (dynamic fields).
+    has_register_entry: bool, // False with a package that has the registry, but no entry.
+}
+
+impl HostNameRegistryInternal {
+    pub fn object_id(&self) -> ObjectID {
+        self.object_id
+    }
+}
+
+// Names are the same on every platform and safe to display: ASCII letters, digits,
+// '-', '_' and '.' only.
+pub fn validate_host_name(name: &str) -> Result<(), DTPError> {
+    let desc = if name.is_empty() {
+        "empty"
+    } else if name.len() > HOST_NAME_MAX_LENGTH {
+        "too long"
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        "invalid character"
+    } else {
+        return Ok(());
+    };
+    Err(DTPError::DTPHostNameInvalid {
+        name: name.to_string(),
+        desc: desc.to_string(),
+    })
+}
+
+pub(crate) async fn get_host_name_registry_internal(
+    rpc: &SuiSDKParamsRPC,
+    package_id: ObjectID,
+) -> Result<Option<HostNameRegistryInternal>, DTPError> {
+    // Returns Ok(None) if confirmed that the package has no HostNameRegistry.
+    let registry_id = super::common_rpc::fetch_package_init_object_id(
+        rpc,
+        package_id,
+        "user_registry",
+        "HostNameRegistry",
+    )
+    .await?;
+    let registry_id = match registry_id {
+        Some(registry_id) => registry_id,
+        None => return Ok(None),
+    };
+
+    let raw = super::common_rpc::fetch_raw_move_object::<HostNameRegistryMoveRaw>(rpc, registry_id)
+        .await?;
+    let raw = match raw {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+    let has_register_entry = super::common_rpc::package_has_function(
+        rpc,
+        package_id,
+        "user_registry",
+        "register_host_name",
+    )
+    .await?;
+    Ok(Some(HostNameRegistryInternal {
+        object_id: *raw.id.object_id(),
+        names_table_id: raw.names.id,
+        has_register_entry,
+    }))
+}
+
+pub(crate) async fn get_host_id_by_name(
+    rpc: &SuiSDKParamsRPC,
+    registry: &HostNameRegistryInternal,
+    name: &str,
+) -> Result<Option<ObjectID>, DTPError> {
+    // Returns Ok(None) if confirmed that the name is not registered.
+    let key = DynamicFieldName {
+        type_: TypeTag::from_str("0x1::string::String")?,
+        value: serde_json::Value::String(name.to_string()),
+    };
+    let raw = super::common_rpc::fetch_raw_dynamic_field_object::<HostNameEntryMoveRaw>(
+        rpc,
+        registry.names_table_id,
+        key,
+    )
+    .await?;
+    Ok(raw.map(|raw| raw.value))
+}
+
+pub(crate) async fn register_host_name_on_network(
+    rpc: &SuiSDKParamsRPC,
+    txn: &SuiSDKParamsTxn,
+    registry: &HostNameRegistryInternal,
+    host_id: ObjectID,
+    name: &str,
+) -> Result<(), DTPError> {
+    if !registry.has_register_entry {
+        return Err(DTPError::DTPMoveFunctionNotFound {
+            package_id: txn.package_id.to_string(),
+            function: "user_registry::register_host_name".to_string(),
+        });
+    }
+    // The Move call aborts if the name is already registered (caller should check
+    // first for a more specific error).
+    let call_args = vec![
+        SuiJsonValue::from_object_id(registry.object_id),
+        SuiJsonValue::from_object_id(host_id),
+        SuiJsonValue::new(serde_json::Value::String(name.to_string()))?,
+    ];
+    super::common_rpc::do_move_call_no_ret(
+        rpc,
+        txn,
+        "user_registry",
+        "register_host_name",
+        call_args,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_host_name() {
+        assert!(validate_host_name("my-host_1.dev").is_ok());
+        assert!(validate_host_name(&"a".repeat(HOST_NAME_MAX_LENGTH)).is_ok());
+        assert!(validate_host_name("").is_err());
+        assert!(validate_host_name(&"a".repeat(HOST_NAME_MAX_LENGTH + 1)).is_err());
+        assert!(validate_host_name("my host").is_err());
+        assert!(validate_host_name("hôte").is_err());
+    }
+
+    #[test]
+    fn test_registry_retry_delay() {
+        assert_eq!(registry_retry_delay(1, 0), Duration::from_millis(100));
+        assert_eq!(registry_retry_delay(2, 0), Duration::from_millis(200));
+        assert_eq!(registry_retry_delay(3, 142), Duration::from_millis(442));
+        // Jitter stays below the base delay, backoff is capped.
+        assert_eq!(registry_retry_delay(1, 99), Duration::from_millis(199));
+        assert_eq!(registry_retry_delay(40, 0), registry_retry_delay(8, 0));
+    }
+}
@@ -1,9 +1,49 @@
+// Errors of the DTP API.
+//
+// An application typically handles only the "failure classes" (see is_actionable())
+// and reports the other variants as-is:
+//
+//   RpcTransport         No RPC node reachable (retry later or add_rpc_url).
+//   Timeout              No answer within the operation timeout (see TimeoutPhase).
+//   TransactionRejected  Transaction submitted but rejected/aborted by the network.
+//   ObjectNotFound       A referenced object (e.g. host id) does not exist.
+//   NotAuthorized        Not the owner, or no key in the keystore for the signer.
+//   InsufficientGas      Gas coins of the signer too low for the transaction.
+//   Config               Bad parameter or setup (e.g. no RPC url added).
+//   BatchFailed          The transaction of a batch of requests failed (send again).
+//   StaleObjectVersion   An owned object changed since the transaction was prepared
+//                        (prepare it again, see PreparedTransaction).
+//   ServiceUnavailable   The Host does not offer the service (see Host::services).
+//   RegistryConflict     A registry kept changing under concurrent updates (retries
+//                        exhausted, try again later).
+//
+// The Sui SDK errors are mapped to these classes with from_sui_sdk_error().
 use anyhow;
 
 use core::option::Option;
 use sui_sdk::types::error::SuiError;
+use sui_types::error::SuiObjectResponseError;
 use thiserror;
 
+// Which part of an operation did not complete in time (see DTPError::Timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    Read,    // Any JSON-RPC read (includes building a transaction).
+    Submit,  // Transaction execution. It may still be executed by the network.
+    Confirm, // Waiting for the effects of an executed transaction.
+}
+
+impl std::fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phase = match self {
+            TimeoutPhase::Read => "read",
+            TimeoutPhase::Submit => "submit",
+            TimeoutPhase::Confirm => "confirm",
+        };
+        write!(f, "{}", phase)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[allow(clippy::large_enum_variant)]
 pub enum DTPError {
@@ -36,9 +76,30 @@ pub enum DTPError {
     #[error("DTP Failed loading registry: {desc:?}")]
     DTPFailedRegistryLoad { desc: String },
 
+    #[error("DTP Host name {name:?} already registered to host {host:?}")]
+    DTPHostNameAlreadyRegistered { name: String, host: String },
+
+    #[error("DTP Host name {name:?} invalid ({desc})")]
+    DTPHostNameInvalid { name: String, desc: String },
+
+    #[error("DTP Package {package_id:?} has no host name registry (package too old?)")]
+    DTPHostNameRegistryNotFound { package_id: String },
+
+    #[error("DTP Package {package_id:?} has no Move function {function:?} (package too old?)")]
+    DTPMoveFunctionNotFound {
+        package_id: String,
+        function: String,
+    },
+
     #[error("DTP Failed loading ConnObjects: {desc:?}")]
     DTPFailedConnObjectsLoading { desc: String },
 
+    #[error("DTP Connection encryption failed: {desc}")]
+    DTPEncryptionFailed { desc: String },
+
+    #[error("DTP Invalid tunnel frame: {desc}")]
+    DTPTunnelFrameInvalid { desc: String },
+
     #[error(
         "DTP Failed fetching object {object_type:?}::{object_id:?}. Info from sui_sdk-> {inner:?}"
     )]
@@ -58,6 +119,59 @@ pub enum DTPError {
     #[error("DTP Support for more than one RPC node not yet implemented. Consider contributing.")]
     DTPMultipleRPCNotImplemented,
 
+    #[error("DTP RPC transport error with {url:?}: {msg}")]
+    RpcTransport { url: String, msg: String },
+
+    #[error("DTP {phase} timed out after {timeout_ms} ms ({op})")]
+    Timeout {
+        phase: TimeoutPhase,
+        op: String, // e.g. "get_object_with_options"
+        timeout_ms: u64,
+    },
+
+    #[error("DTP Transaction {digest} rejected: {reason}")]
+    TransactionRejected { digest: String, reason: String },
+
+    #[error("DTP Object {id} not found")]
+    ObjectNotFound { id: String },
+
+    #[error("DTP Not authorized: {msg}")]
+    NotAuthorized { msg: String },
+
+    #[error("DTP Insufficient gas (needed {needed} MIST, available {available} MIST)")]
+    InsufficientGas { needed: u64, available: u64 }, // 0 when not reported by the network.
+
+    #[error("DTP Config error: {msg}")]
+    Config { msg: String },
+
+    // Every request of the batch gets this error (see BatchConfig).
+    #[error("DTP Batch of {batch_size} requests failed: {reason}")]
+    BatchFailed { batch_size: usize, reason: String },
+
+    // A version is 0 when not known (or the object was deleted).
+    #[error("DTP Object {object_id} changed since prepared (version {prepared_version}, now {current_version})")]
+    StaleObjectVersion {
+        object_id: String,
+        prepared_version: u64,
+        current_version: u64,
+    },
+
+    // Detected locally, before any transaction (see DTP::set_service_validation).
+    #[error("DTP Service {name} ({service_idx}) not enabled on host {host}")]
+    ServiceUnavailable {
+        host: String,
+        service_idx: u8,
+        name: String,
+    },
+
+    // Optimistic retries of a registry update exhausted (see user_registry.rs).
+    #[error("DTP Registry {registry} update conflicted {attempts} times: {last_error}")]
+    RegistryConflict {
+        registry: String,
+        attempts: u32,
+        last_error: String,
+    },
+
     #[error(
         "DTP Failed RPC get_objects_owned_by_address({client:?}). Info from sui_sdk-> {inner:?}"
     )]
@@ -80,7 +194,155 @@ pub enum DTPError {
     InnerSuiError(#[from] SuiError),
 
     #[error("DTP inner anyhow::Error {0:?}")]
-    InnerAnyhowError(#[from] anyhow::Error),
+    InnerAnyhowError(anyhow::Error),
+}
+
+// Allows '?' on the many Sui SDK functions returning anyhow::Error.
+//
+// (The reverse From<DTPError> for anyhow::Error is provided by anyhow, so an
+//  application can keep using anyhow and downcast_ref::<DTPError>() when needed).
+impl From<anyhow::Error> for DTPError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<DTPError>() {
+            Ok(dtp_err) => dtp_err,
+            Err(err) => from_sui_sdk_error(err),
+        }
+    }
+}
+
+impl From<SuiObjectResponseError> for DTPError {
+    fn from(err: SuiObjectResponseError) -> Self {
+        match err {
+            SuiObjectResponseError::NotExists { object_id } => DTPError::ObjectNotFound {
+                id: object_id.to_string(),
+            },
+            SuiObjectResponseError::Deleted { object_id, .. } => DTPError::ObjectNotFound {
+                id: object_id.to_string(),
+            },
+            _ => DTPError::InnerAnyhowError(err.into()),
+        }
+    }
+}
+
+// Map an error from the Sui SDK to the most specific failure class.
+//
+// Most failures are detected by the fullnode and reach the SDK as a JSON-RPC error
+// message (the typed Sui error is lost), so the matching is done on the text of the
+// whole error chain. Falls back on InnerAnyhowError.
+pub fn from_sui_sdk_error(err: anyhow::Error) -> DTPError {
+    if super::rpc_nodes::is_transport_error(&err) {
+        return DTPError::RpcTransport {
+            url: "NA".to_string(),
+            msg: err.to_string(),
+        };
+    }
+    let msg = format!("{:#}", err);
+    match classify_sui_error_msg(&msg) {
+        Some(dtp_err) => dtp_err,
+        None => DTPError::InnerAnyhowError(err),
+    }
+}
+
+const GAS_ERROR_PATTERNS: [&str; 6] = [
+    "InsufficientGas",
+    "GasBalanceTooLow",
+    "lower than the needed amount",
+    "Cannot find gas coin",
+    "No valid gas coins",
+    "InsufficientCoinBalance",
+];
+
+const NOT_AUTHORIZED_PATTERNS: [&str; 6] = [
+    "IncorrectUserSignature",
+    "IncorrectSigner",
+    "Signature is not valid",
+    "is owned by account address",
+    "Cannot find key for address",
+    "ENotAuthorized",
+];
+
+const STALE_OBJECT_PATTERNS: [&str; 2] = [
+    "ObjectVersionUnavailableForConsumption",
+    "is not available for consumption",
+];
+
+// Another transaction holds (or just consumed) an object version needed by the
+// transaction. Retrying with the latest versions should succeed.
+//
+// Not "equivocated": the object is then locked until the next epoch.
+const VERSION_CONFLICT_PATTERNS: [&str; 4] = [
+    "ObjectVersionUnavailableForConsumption",
+    "is not available for consumption",
+    "ObjectLockConflict",
+    "already locked by a different transaction",
+];
+
+const OBJECT_NOT_FOUND_PATTERNS: [&str; 5] = [
+    "ObjectNotFound",
+    "Could not find the referenced object",
+    "DynamicFieldNotFound",
+    "NotExists",
+    "does not exist",
+];
+
+pub(crate) fn classify_sui_error_msg(msg: &str) -> Option<DTPError> {
+    let has_any = |patterns: &[&str]| patterns.iter().any(|pattern| msg.contains(pattern));
+
+    if has_any(&GAS_ERROR_PATTERNS) {
+        // e.g. "Balance of gas object 5 is lower than the needed amount: 10"
+        //   or "GasBalanceTooLow { gas_balance: 5, needed_gas_amount: 10 }"
+        let available = number_after(msg, "gas_balance: ")
+            .or_else(|| number_after(msg, "Balance of gas object "))
+            .unwrap_or(0);
+        let needed = number_after(msg, "needed_gas_amount: ")
+            .or_else(|| number_after(msg, "needed amount: "))
+            .unwrap_or(0);
+        return Some(DTPError::InsufficientGas { needed, available });
+    }
+    if has_any(&STALE_OBJECT_PATTERNS) {
+        // e.g. "Object (0x12, SequenceNumber(5), o#...) is not available for consumption,
+        //       its current version: SequenceNumber(6)"
+        let current_version = number_after(msg, "current version: SequenceNumber(")
+            .or_else(|| number_after(msg, "current_version: SequenceNumber("))
+            .unwrap_or(0);
+        return Some(DTPError::StaleObjectVersion {
+            object_id: first_object_id(msg).unwrap_or_else(|| "NA".to_string()),
+            prepared_version: number_after(msg, "SequenceNumber(").unwrap_or(0),
+            current_version,
+        });
+    }
+    if has_any(&NOT_AUTHORIZED_PATTERNS) {
+        return Some(DTPError::NotAuthorized {
+            msg: msg.to_string(),
+        });
+    }
+    if has_any(&OBJECT_NOT_FOUND_PATTERNS) {
+        return Some(DTPError::ObjectNotFound {
+            id: first_object_id(msg).unwrap_or_else(|| "NA".to_string()),
+        });
+    }
+    None
+}
+
+fn number_after(msg: &str, prefix: &str) -> Option<u64> {
+    let start = msg.find(prefix)? + prefix.len();
+    let digits: String = msg[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn first_object_id(msg: &str) -> Option<String> {
+    let start = msg.find("0x")?;
+    let hex_len = msg[start + 2..]
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit())
+        .count();
+    if hex_len == 0 {
+        return None;
+    }
+    Some(msg[start..start + 2 + hex_len].to_string())
 }
 
 pub struct MoreInfo {
@@ -88,7 +350,7 @@ pub struct MoreInfo {
     pub internal_err_report_to_devs: bool,
 }
 
-// DTP API uses always anyhow::Error.
+// For an application that converted the DTPError into an anyhow::Error.
 //
 // Actionable info for the API user are obtain through
 // functions provided here.
@@ -116,7 +378,190 @@ impl DTPError {
                 fix_caller_into_dtp_api: true,
                 internal_err_report_to_devs: false,
             }),
+            DTPError::DTPHostNameAlreadyRegistered { .. } => Some(MoreInfo {
+                fix_caller_into_dtp_api: false,
+                internal_err_report_to_devs: false,
+            }),
+            DTPError::DTPHostNameInvalid { .. } => Some(MoreInfo {
+                fix_caller_into_dtp_api: true,
+                internal_err_report_to_devs: false,
+            }),
+            // The package ID is of a DTP package without the feature.
+            DTPError::DTPHostNameRegistryNotFound { .. }
+            | DTPError::DTPMoveFunctionNotFound { .. } => Some(MoreInfo {
+                fix_caller_into_dtp_api: true,
+                internal_err_report_to_devs: false,
+            }),
+            DTPError::RpcTransport { .. }
+            | DTPError::Timeout { .. }
+            | DTPError::TransactionRejected { .. }
+            | DTPError::ObjectNotFound { .. }
+            | DTPError::InsufficientGas { .. }
+            | DTPError::BatchFailed { .. }
+            | DTPError::StaleObjectVersion { .. }
+            | DTPError::ServiceUnavailable { .. }
+            | DTPError::RegistryConflict { .. } => Some(MoreInfo {
+                fix_caller_into_dtp_api: false,
+                internal_err_report_to_devs: false,
+            }),
+            DTPError::NotAuthorized { .. } | DTPError::Config { .. } => Some(MoreInfo {
+                fix_caller_into_dtp_api: true,
+                internal_err_report_to_devs: false,
+            }),
             _ => None,
         }
     }
+
+    // True for the failure classes (see top of this file). Other errors are
+    // typically wrapped with more context (e.g. DTPFailedMoveCall).
+    pub fn is_actionable(&self) -> bool {
+        matches!(
+            self,
+            DTPError::RpcTransport { .. }
+                | DTPError::Timeout { .. }
+                | DTPError::TransactionRejected { .. }
+                | DTPError::ObjectNotFound { .. }
+                | DTPError::NotAuthorized { .. }
+                | DTPError::InsufficientGas { .. }
+                | DTPError::Config { .. }
+                | DTPError::BatchFailed { .. }
+                | DTPError::StaleObjectVersion { .. }
+                | DTPError::ServiceUnavailable { .. }
+                | DTPError::RegistryConflict { .. }
+        )
+    }
+
+    // True when the transaction failed only because of a concurrent use of one of its
+    // objects (see VERSION_CONFLICT_PATTERNS). The Move call wrappers keep the text of
+    // the Sui error, so it is matched there too.
+    pub fn is_version_conflict(&self) -> bool {
+        let msg = match self {
+            DTPError::StaleObjectVersion { .. } => return true,
+            DTPError::DTPFailedMoveCall { inner, .. } => inner.clone(),
+            DTPError::TransactionRejected { reason, .. } => reason.clone(),
+            DTPError::InnerAnyhowError(err) => format!("{:#}", err),
+            _ => return false,
+        };
+        VERSION_CONFLICT_PATTERNS
+            .iter()
+            .any(|pattern| msg.contains(pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sui_sdk::types::base_types::ObjectID;
+
+    #[test]
+    fn test_unknown_object_id() {
+        let object_id = ObjectID::from_hex_literal("0x1234").unwrap();
+        let err: DTPError = SuiObjectResponseError::NotExists { object_id }.into();
+        assert!(matches!(&err, DTPError::ObjectNotFound { id } if *id == object_id.to_string()));
+        assert!(err.is_actionable());
+
+        // Same when reported by the fullnode for a Move call argument.
+        let err: DTPError = anyhow::anyhow!(
+            "RPC call failed: Could not find the referenced object 0x1234 at version None"
+        )
+        .into();
+        assert!(matches!(err, DTPError::ObjectNotFound { id } if id == "0x1234"));
+    }
+
+    #[test]
+    fn test_from_sui_sdk_error() {
+        let err: DTPError =
+            anyhow::anyhow!("Balance of gas object 5 is lower than the needed amount: 10").into();
+        assert!(matches!(
+            err,
+            DTPError::InsufficientGas {
+                needed: 10,
+                available: 5
+            }
+        ));
+        let err: DTPError = anyhow::Error::msg(
+            "Error checking transaction input objects: \
+             GasBalanceTooLow { gas_balance: 7, needed_gas_amount: 2000 }",
+        )
+        .into();
+        assert!(matches!(
+            err,
+            DTPError::InsufficientGas {
+                needed: 2000,
+                available: 7
+            }
+        ));
+
+        let err: DTPError = anyhow::anyhow!(
+            "Object (0x12, SequenceNumber(5), o#Ab1) is not available for consumption, \
+             its current version: SequenceNumber(6)"
+        )
+        .into();
+        assert!(matches!(
+            &err,
+            DTPError::StaleObjectVersion {
+                object_id,
+                prepared_version: 5,
+                current_version: 6
+            } if object_id == "0x12"
+        ));
+        assert!(err.is_actionable());
+
+        let err: DTPError = anyhow::anyhow!("Cannot find key for address: [0x7]").into();
+        assert!(matches!(err, DTPError::NotAuthorized { .. }));
+
+        let err: DTPError = jsonrpsee::core::ClientError::RequestTimeout.into();
+        assert!(matches!(err, DTPError::RpcTransport { .. }));
+
+        // A DTPError is not re-classified when going through anyhow.
+        let err: anyhow::Error = DTPError::Config {
+            msg: "no RPC url".to_string(),
+        }
+        .into();
+        assert!(matches!(DTPError::from(err), DTPError::Config { .. }));
+
+        let err: DTPError = anyhow::anyhow!("something else").into();
+        assert!(matches!(err, DTPError::InnerAnyhowError(_)));
+        assert!(!err.is_actionable());
+    }
+
+    #[test]
+    fn test_is_version_conflict() {
+        let err = DTPError::DTPFailedMoveCall {
+            desc: "register_host_name".to_string(),
+            package_id: "0x2a".to_string(),
+            client_address: "0x7".to_string(),
+            inner: "Transaction execution failed: ObjectLockConflict { obj_ref: (0x12, \
+                    SequenceNumber(5), o#Ab1), pending_transaction: TransactionDigest(Cd2) }"
+                .to_string(),
+        };
+        assert!(err.is_version_conflict());
+
+        let err: DTPError = anyhow::anyhow!(
+            "Object (0x12, SequenceNumber(5), o#Ab1) is not available for consumption, \
+             its current version: SequenceNumber(6)"
+        )
+        .into();
+        assert!(err.is_version_conflict());
+
+        let err: DTPError = anyhow::anyhow!(
+            "Failed to sign transaction by a quorum of validators because one or more of \
+             its objects is equivocated until the next epoch"
+        )
+        .into();
+        assert!(!err.is_version_conflict());
+        assert!(!DTPError::DTPHostNameAlreadyRegistered {
+            name: "a".to_string(),
+            host: "0x12".to_string(),
+        }
+        .is_version_conflict());
+
+        let err = DTPError::RegistryConflict {
+            registry: "0x12".to_string(),
+            attempts: 5,
+            last_error: "ObjectLockConflict".to_string(),
+        };
+        assert!(err.is_actionable());
+        assert!(!err.is_version_conflict());
+    }
 }
@@ -9,9 +9,11 @@
 //    ...
 
 pub use self::error::*;
+pub use self::rpc_nodes::*;
 pub use self::stats::*;
 pub use self::sui_sdk_wrapped::*;
 
 pub mod error;
+pub mod rpc_nodes;
 pub mod stats;
 pub mod sui_sdk_wrapped;
@@ -0,0 +1,483 @@
+// Multiple RPC nodes (fullnodes) with priorities and failover.
+//
+// Nodes are tried in order of priority (lowest value first, insertion order for ties).
+//
+// A node is marked down on a transport error (connection refused, timeout etc...) and is
+// then tried last until DOWN_RETRY_DELAY elapsed. There is no background health check,
+// the next real request sent to the node is the "health check" (lazy).
+//
+// Errors that are not transport related (e.g. object does not exist, Move abort) are
+// returned to the caller right away. Another node would very likely give the same answer.
+//
+// Failover is safe for transactions because the same signed transaction bytes are
+// idempotent on Sui (a transaction executed on one node will not be executed twice).
+//
+// Every operation has a deadline (see set_operation_timeout). It covers all the
+// nodes attempted, so a stalled node ends the operation with DTPError::Timeout (the
+// node is marked down and the next operation starts with another node).
+//
+// Cancellation safety: the futures can be dropped at any await point (e.g. by a
+// tokio::time::timeout of the caller). The std Mutex is never held across an await
+// and a client is stored only once fully built, so the RpcNodes stays usable.
+//
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use sui_sdk::{SuiClient, SuiClientBuilder};
+
+use super::{DTPError, SuiClientWrapped, TimeoutPhase, TxnConfirmation};
+
+const DOWN_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+pub const DEFAULT_OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Number of most recent operations kept for debugging (see RpcStats::history).
+const HISTORY_SIZE: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcNodeStats {
+    pub url: String,
+    pub priority: u8,
+    pub is_down: bool,
+    pub served_count: u64,
+    pub transport_error_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcOpServedBy {
+    pub op: String,
+    pub url: String,
+    pub failover_count: u8, // Number of nodes that failed before this one succeeded.
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RpcStats {
+    pub nodes: Vec<RpcNodeStats>,
+    // Most recent operations, oldest first.
+    pub history: Vec<RpcOpServedBy>,
+    // Number of calls per operation (e.g. "get_object_with_options"), failed or not.
+    pub op_counts: BTreeMap<String, u64>,
+    // Most recent transaction confirmations, oldest first.
+    pub confirmations: Vec<TxnConfirmation>,
+}
+
+// Health state of a node. Independent of the SuiClient to keep the selection
+// logic simple to verify.
+#[derive(Debug, Clone)]
+struct RpcNodeState {
+    url: String,
+    priority: u8,
+    down_since: Option<Instant>,
+    served_count: u64,
+    transport_error_count: u64,
+}
+
+#[derive(Debug, Default)]
+struct RpcNodesState {
+    nodes: Vec<RpcNodeState>,
+    history: VecDeque<RpcOpServedBy>,
+    op_counts: BTreeMap<String, u64>,
+    confirmations: VecDeque<TxnConfirmation>,
+    op_timeout: Option<Duration>, // None for DEFAULT_OPERATION_TIMEOUT.
+}
+
+impl RpcNodesState {
+    fn add(&mut self, url: &str, priority: u8) -> Option<usize> {
+        if self.nodes.iter().any(|node| node.url == url) {
+            return None;
+        }
+        self.nodes.push(RpcNodeState {
+            url: url.to_string(),
+            priority,
+            down_since: None,
+            served_count: 0,
+            transport_error_count: 0,
+        });
+        Some(self.nodes.len() - 1)
+    }
+
+    // Order in which the nodes should be attempted.
+    fn try_order(&self, now: Instant) -> Vec<usize> {
+        let is_up = |node: &RpcNodeState| match node.down_since {
+            None => true,
+            Some(down_since) => now.duration_since(down_since) >= DOWN_RETRY_DELAY,
+        };
+
+        let mut up: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| is_up(&self.nodes[i]))
+            .collect();
+        up.sort_by_key(|&i| self.nodes[i].priority); // Stable, so insertion order for ties.
+
+        // Down nodes are still attempted as a last resort (least recently failed first).
+        let mut down: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| !is_up(&self.nodes[i]))
+            .collect();
+        down.sort_by_key(|&i| self.nodes[i].down_since);
+
+        up.extend(down);
+        up
+    }
+
+    fn report_ok(&mut self, idx: usize, op: &str, failover_count: u8) {
+        let node = &mut self.nodes[idx];
+        if node.down_since.is_some() {
+            info!("RPC node {} is back up", node.url);
+            node.down_since = None;
+        }
+        node.served_count += 1;
+
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(RpcOpServedBy {
+            op: op.to_string(),
+            url: node.url.clone(),
+            failover_count,
+        });
+    }
+
+    fn count_op(&mut self, op: &str) {
+        *self.op_counts.entry(op.to_string()).or_default() += 1;
+    }
+
+    fn report_confirmation(&mut self, confirmation: TxnConfirmation) {
+        if self.confirmations.len() == HISTORY_SIZE {
+            self.confirmations.pop_front();
+        }
+        self.confirmations.push_back(confirmation);
+    }
+
+    fn report_transport_error(&mut self, idx: usize, now: Instant) {
+        let node = &mut self.nodes[idx];
+        node.down_since = Some(now);
+        node.transport_error_count += 1;
+    }
+
+    fn stats(&self, now: Instant) -> RpcStats {
+        let down = |node: &RpcNodeState| match node.down_since {
+            None => false,
+            Some(down_since) => now.duration_since(down_since) < DOWN_RETRY_DELAY,
+        };
+        RpcStats {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| RpcNodeStats {
+                    url: node.url.clone(),
+                    priority: node.priority,
+                    is_down: down(node),
+                    served_count: node.served_count,
+                    transport_error_count: node.transport_error_count,
+                })
+                .collect(),
+            history: self.history.iter().cloned().collect(),
+            op_counts: self.op_counts.clone(),
+            confirmations: self.confirmations.iter().cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RpcNodes {
+    state: std::sync::Mutex<RpcNodesState>,
+    // Same indexing as state.nodes. A client is None until successfully built.
+    clients: tokio::sync::RwLock<Vec<Option<SuiClientWrapped>>>,
+}
+
+impl RpcNodes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().nodes.is_empty()
+    }
+
+    // Add a node. Lower 'priority' value are tried first.
+    //
+    // The client is built right away, but a failure is not an error: the node is
+    // just marked down and the build will be re-attempted later.
+    pub async fn add(&self, url: &str, priority: u8) -> Result<(), DTPError> {
+        let idx = match self.state.lock().unwrap().add(url, priority) {
+            Some(idx) => idx,
+            None => {
+                return Err(DTPError::Config {
+                    msg: format!("add_rpc_url duplicate {}", url),
+                })
+            }
+        };
+        self.clients.write().await.push(None);
+
+        let timeout = self.operation_timeout();
+        let msg = match tokio::time::timeout(timeout, self.get_client(idx)).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("no answer after {} ms", timeout.as_millis()),
+        };
+        warn!("RPC node {} not reachable on add ({})", url, msg);
+        self.state
+            .lock()
+            .unwrap()
+            .report_transport_error(idx, Instant::now());
+        Ok(())
+    }
+
+    pub fn stats(&self) -> RpcStats {
+        self.state.lock().unwrap().stats(Instant::now())
+    }
+
+    // Apply to the operations started after this call.
+    pub fn set_operation_timeout(&self, timeout: Duration) {
+        self.state.lock().unwrap().op_timeout = Some(timeout);
+    }
+
+    pub fn operation_timeout(&self) -> Duration {
+        self.state
+            .lock()
+            .unwrap()
+            .op_timeout
+            .unwrap_or(DEFAULT_OPERATION_TIMEOUT)
+    }
+
+    pub fn report_confirmation(&self, confirmation: TxnConfirmation) {
+        self.state.lock().unwrap().report_confirmation(confirmation);
+    }
+
+    async fn get_client(&self, idx: usize) -> Result<SuiClient, anyhow::Error> {
+        if let Some(Some(client)) = self.clients.read().await.get(idx) {
+            return Ok(client.inner.clone());
+        }
+
+        let url = self.state.lock().unwrap().nodes[idx].url.clone();
+        let sui_client = SuiClientBuilder::default().build(&url).await?;
+        if let Some(slot) = self.clients.write().await.get_mut(idx) {
+            *slot = Some(SuiClientWrapped {
+                inner: sui_client.clone(),
+            });
+        }
+        Ok(sui_client)
+    }
+
+    // Call 'f' with the client of the best node, and fail over to the next node
+    // on transport errors. 'op' is a short description for debugging.
+    //
+    // The error of 'f' is mapped with from_sui_sdk_error(), and is RpcTransport
+    // when all the nodes failed.
+    //
+    // For a read (see with_failover_phase for the other phases).
+    pub async fn with_failover<T, F, Fut>(&self, op: &str, f: F) -> Result<T, DTPError>
+    where
+        F: FnMut(SuiClient) -> Fut,
+        Fut: Future<Output = Result<T, anyhow::Error>>,
+    {
+        self.with_failover_phase(op, TimeoutPhase::Read, f).await
+    }
+
+    // Same as with_failover. 'phase' is reported in the DTPError::Timeout.
+    pub async fn with_failover_phase<T, F, Fut>(
+        &self,
+        op: &str,
+        phase: TimeoutPhase,
+        mut f: F,
+    ) -> Result<T, DTPError>
+    where
+        F: FnMut(SuiClient) -> Fut,
+        Fut: Future<Output = Result<T, anyhow::Error>>,
+    {
+        let (try_order, timeout) = {
+            let mut state = self.state.lock().unwrap();
+            state.count_op(op);
+            let timeout = state.op_timeout.unwrap_or(DEFAULT_OPERATION_TIMEOUT);
+            (state.try_order(Instant::now()), timeout)
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+        if try_order.is_empty() {
+            return Err(DTPError::Config {
+                msg: "no RPC url (see add_rpc_url)".to_string(),
+            });
+        }
+
+        let mut failover_count: u8 = 0;
+        let mut last_err: Option<DTPError> = None;
+        for idx in try_order {
+            let url = self.state.lock().unwrap().nodes[idx].url.clone();
+            // A client that can't be built is handled like a transport error.
+            let attempt = async {
+                match self.get_client(idx).await {
+                    Ok(sui_client) => f(sui_client).await,
+                    Err(e) => Err(DTPError::RpcTransport {
+                        url: url.clone(),
+                        msg: e.to_string(),
+                    }
+                    .into()),
+                }
+            };
+            let result = match tokio::time::timeout_at(deadline, attempt).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("RPC {} timed out on {}", op, url);
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .report_transport_error(idx, Instant::now());
+                    return Err(DTPError::Timeout {
+                        phase,
+                        op: op.to_string(),
+                        timeout_ms: timeout.as_millis() as u64,
+                    });
+                }
+            };
+            match result {
+                Ok(value) => {
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .report_ok(idx, op, failover_count);
+                    return Ok(value);
+                }
+                Err(e) if is_transport_error(&e) => {
+                    warn!("RPC {} failed on {}, trying next node ({})", op, url, e);
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .report_transport_error(idx, Instant::now());
+                    failover_count = failover_count.saturating_add(1);
+                    last_err = Some(DTPError::RpcTransport {
+                        url,
+                        msg: e.to_string(),
+                    });
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // All nodes failed.
+        Err(last_err.unwrap())
+    }
+}
+
+// Errors for which another node could succeed.
+pub(crate) fn is_transport_error(err: &anyhow::Error) -> bool {
+    use jsonrpsee::core::ClientError;
+    let is_transport_client_error = |e: &ClientError| {
+        matches!(
+            e,
+            ClientError::Transport(_) | ClientError::RequestTimeout | ClientError::RestartNeeded(_)
+        )
+    };
+
+    err.chain().any(|cause| {
+        if let Some(DTPError::RpcTransport { .. }) = cause.downcast_ref::<DTPError>() {
+            return true;
+        }
+        if let Some(sui_sdk::error::Error::RpcError(e)) =
+            cause.downcast_ref::<sui_sdk::error::Error>()
+        {
+            return is_transport_client_error(e);
+        }
+        if let Some(e) = cause.downcast_ref::<ClientError>() {
+            return is_transport_client_error(e);
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_state(nodes: &[(&str, u8)]) -> RpcNodesState {
+        let mut state = RpcNodesState::default();
+        for (url, priority) in nodes {
+            state.add(url, *priority).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn test_try_order_by_priority() {
+        let state = new_state(&[("http://a", 20), ("http://b", 10), ("http://c", 20)]);
+        assert_eq!(state.try_order(Instant::now()), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_down_node_tried_last_then_retried() {
+        let mut state = new_state(&[("http://bogus", 0), ("http://good", 1)]);
+        let t0 = Instant::now();
+
+        // First node fails, second serves.
+        state.report_transport_error(0, t0);
+        state.report_ok(1, "move_call", 1);
+        assert_eq!(state.try_order(t0), vec![1, 0]);
+
+        let stats = state.stats(t0);
+        assert!(stats.nodes[0].is_down);
+        assert_eq!(stats.nodes[0].transport_error_count, 1);
+        assert_eq!(stats.nodes[1].served_count, 1);
+        assert_eq!(
+            stats.history,
+            vec![RpcOpServedBy {
+                op: "move_call".to_string(),
+                url: "http://good".to_string(),
+                failover_count: 1,
+            }]
+        );
+
+        // After the delay, the higher priority node is given another chance.
+        let later = t0 + DOWN_RETRY_DELAY;
+        assert_eq!(state.try_order(later), vec![0, 1]);
+        state.report_ok(0, "get_object", 0);
+        assert!(!state.stats(later).nodes[0].is_down);
+    }
+
+    #[test]
+    fn test_duplicate_url_and_history_bound() {
+        let mut state = new_state(&[("http://a", 0)]);
+        assert!(state.add("http://a", 5).is_none());
+
+        for _ in 0..(HISTORY_SIZE + 3) {
+            state.report_ok(0, "op", 0);
+        }
+        let stats = state.stats(Instant::now());
+        assert_eq!(stats.history.len(), HISTORY_SIZE);
+        assert_eq!(stats.nodes[0].served_count, (HISTORY_SIZE + 3) as u64);
+    }
+
+    #[test]
+    fn test_op_counts_and_confirmations() {
+        let mut state = new_state(&[("http://a", 0)]);
+        state.count_op("get_object_with_options");
+        state.count_op("execute_transaction_block");
+        state.count_op("get_object_with_options");
+        for i in 0..(HISTORY_SIZE + 1) {
+            state.report_confirmation(TxnConfirmation {
+                op: "api::open_connection".to_string(),
+                latency_ms: i as u64,
+                polled: false,
+            });
+        }
+
+        let stats = state.stats(Instant::now());
+        assert_eq!(stats.op_counts["get_object_with_options"], 2);
+        assert_eq!(stats.op_counts["execute_transaction_block"], 1);
+        assert_eq!(stats.confirmations.len(), HISTORY_SIZE);
+        assert_eq!(stats.confirmations[0].latency_ms, 1);
+    }
+
+    #[test]
+    fn test_is_transport_error() {
+        let err: anyhow::Error = DTPError::RpcTransport {
+            url: "http://bogus".to_string(),
+            msg: "connection refused".to_string(),
+        }
+        .into();
+        assert!(is_transport_error(&err));
+
+        let err: anyhow::Error = DTPError::DTPObjectIDNotFound.into();
+        assert!(!is_transport_error(&err));
+        let err: anyhow::Error = jsonrpsee::core::ClientError::RequestTimeout.into();
+        assert!(is_transport_error(&err));
+    }
+}
@@ -11,4 +11,14 @@ pub struct PingStats {
     pub avg_gas_cost: u32,        // Mist
     pub max_gas_cost: u32,        // Mist
     pub total_gas_cost: u32,      // Mist
+    pub rpc_url: Option<String>,  // RPC node that served the last operation.
+}
+
+// Time for a transaction to be confirmed (submission until the needed effects
+// are known).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxnConfirmation {
+    pub op: String,      // Move call (e.g. "api::open_connection")
+    pub latency_ms: u64, // milliseconds
+    pub polled: bool,    // true when the effects had to be polled (slower fallback).
 }
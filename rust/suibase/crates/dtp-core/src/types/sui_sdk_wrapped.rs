@@ -1,9 +1,13 @@
 // Some SUI SDK objects are wrapped.
 //
 
+use std::sync::Arc;
+
 use derive_where::derive_where;
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
 
+use super::RpcNodes;
+
 // This is for tacking a Debug Trait to Mysten Labs SuiClient
 #[derive_where(Debug)]
 #[derive_where(skip_inner(Debug))]
@@ -19,11 +23,15 @@ pub struct KeystoreWrapped {
 }
 
 // When a function requires SuiSDKParamsRPC you can
-// assume that it will make a RPC call.
+// assume that it will make a RPC call (with failover
+// among the nodes).
+//
+// The nodes are shared by all the signers of a NetworkManager (one
+// per localhost profile).
 #[derive(Debug)]
 pub struct SuiSDKParamsRPC {
     pub client_address: SuiAddress,
-    pub sui_client: Option<SuiClientWrapped>,
+    pub nodes: Arc<RpcNodes>,
 }
 
 // When a function take SuiSDKParamsTxn you can
@@ -1,13 +1,15 @@
 use std::error::Error;
 
-use common::shared_types::{GlobalsWorkdirConfigST, WorkdirUserConfig};
+use common::shared_types::{
+    find_port_conflicts, GlobalsWorkdirConfigST, WorkdirUserConfig, WORKDIRS_KEYS,
+};
+use common::workers::ShellWorker;
 use common::{basic_types::*, log_safe};
 
 use crate::network_monitor::NetMonTx;
 use crate::shared_types::{Globals, InputPort, WebSocketWorkerMsg, WebSocketWorkerTx};
 use crate::workdirs_watcher::WorkdirsWatcher;
-use crate::workers::ShellWorker;
-use crate::workers::{WebSocketWorker, WebSocketWorkerParams};
+use crate::workers::{TunnelWorker, TunnelWorkerParams, WebSocketWorker, WebSocketWorkerParams};
 
 use anyhow::{anyhow, Result};
 
@@ -49,6 +51,9 @@ struct WorkdirTracking {
 
     events_worker_tx: Option<WebSocketWorkerTx>,
     events_worker_handle: Option<NestedSubsystem<Box<dyn Error + Send + Sync>>>, // Set when the events_writer_worker is started.
+
+    tunnel_worker_tx: Option<GenericTx>,
+    tunnel_worker_handle: Option<NestedSubsystem<Box<dyn Error + Send + Sync>>>, // Set when the tunnel_worker is started.
 }
 
 impl std::fmt::Debug for WorkdirTracking {
@@ -199,8 +204,7 @@ impl AdminController {
         if wd_tracking.shell_slow_worker_handle.is_none() {
             let (shell_worker_tx, shell_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
             wd_tracking.shell_slow_worker_tx = Some(shell_worker_tx);
-            let shell_worker =
-                ShellWorker::new(self.globals.clone(), shell_worker_rx, Some(workdir_idx));
+            let shell_worker = ShellWorker::new(shell_worker_rx, Some(workdir_idx));
 
             let nested = subsys.start(SubsystemBuilder::new(
                 format!("shell-slow-worker-{}", workdir_idx),
@@ -217,8 +221,7 @@ impl AdminController {
         if wd_tracking.shell_fast_worker_handle.is_none() {
             let (shell_worker_tx, shell_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
             wd_tracking.shell_fast_worker_tx = Some(shell_worker_tx);
-            let shell_worker =
-                ShellWorker::new(self.globals.clone(), shell_worker_rx, Some(workdir_idx));
+            let shell_worker = ShellWorker::new(shell_worker_rx, Some(workdir_idx));
             let nested = subsys.start(SubsystemBuilder::new(
                 format!("shell-fast-worker-{}", workdir_idx),
                 |a| shell_worker.run(a),
@@ -427,8 +430,6 @@ impl AdminController {
                 Self::apply_workdir_config(input_port, &workdir_config);
                 Some((port_idx, input_port.port_number()))
             } else {
-                // TODO Verify there is no conflicting port assignment.
-
                 // No InputPort yet for that workdir... so create it.
                 let mut input_port =
                     InputPort::new(workdir_idx, workdir_name.clone(), &workdir_config);
@@ -500,6 +501,18 @@ impl AdminController {
                     events_writer_worker.run(a)
                 }));
                 wd_tracking.events_worker_handle = Some(nested);
+
+                // Tunnels (dtp_services with a local_port) for this workdir.
+                let (tunnel_worker_tx, tunnel_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+                let tunnel_worker_params =
+                    TunnelWorkerParams::new(self.globals.clone(), tunnel_worker_rx, workdir_idx);
+                wd_tracking.tunnel_worker_tx = Some(tunnel_worker_tx);
+
+                let tunnel_worker = TunnelWorker::new(tunnel_worker_params);
+                let nested = subsys.start(SubsystemBuilder::new("tunnel-worker", |a| {
+                    tunnel_worker.run(a)
+                }));
+                wd_tracking.tunnel_worker_handle = Some(nested);
             }
         } else {
             // Send EVENT_UPDATE to the WebSocketWorker (already started).
@@ -515,10 +528,56 @@ impl AdminController {
                     ));
                 }
             }
+
+            // Send EVENT_UPDATE to the TunnelWorker (listeners follow the config).
+            if let Some(worker_tx) = wd_tracking.tunnel_worker_tx.as_ref() {
+                let mut msg = GenericChannelMsg::new();
+                msg.event_id = EVENT_UPDATE;
+                msg.workdir_idx = Some(workdir_idx);
+                if let Err(e) = worker_tx.try_send(msg) {
+                    log_safe!(format!("send EVENT_UPDATE to tunnel worker failed: {}", e));
+                }
+            }
         }
 
         // Remember the changes that were applied.
         wd_tracking.last_read_config = Some(workdir_config);
+
+        self.check_port_conflicts().await;
+    }
+
+    async fn check_port_conflicts(&mut self) {
+        // Re-check the ports of all the workdirs loaded so far (a change in one workdir
+        // can create or resolve a conflict with another).
+        //
+        // Conflicts are not fatal. They are logged and kept in the globals so that the
+        // affected workdirs are reported DEGRADED (see update_globals_workdir_status).
+        let configs: Vec<(String, &WorkdirUserConfig)> = self
+            .wd_tracking
+            .iter()
+            .filter_map(|(workdir_idx, wd_tracking)| {
+                let workdir_name = WORKDIRS_KEYS.get(usize::from(workdir_idx))?;
+                let config = wd_tracking.last_read_config.as_ref()?;
+                Some((workdir_name.to_string(), config))
+            })
+            .collect();
+
+        let conflicts = {
+            let workdirs_guard = self.globals.workdirs.read().await;
+            find_port_conflicts(&workdirs_guard, &configs)
+        };
+
+        let mut port_conflicts_guard = self.globals.port_conflicts.write().await;
+        if *port_conflicts_guard == conflicts {
+            return; // No change. Avoid repeating the same warnings on every reload.
+        }
+        for conflict in &conflicts {
+            log::warn!("cfg {}", conflict);
+        }
+        if conflicts.is_empty() && !port_conflicts_guard.is_empty() {
+            log::info!("cfg port conflicts resolved");
+        }
+        *port_conflicts_guard = conflicts;
     }
 
     async fn event_loop(&mut self, subsys: &SubsystemHandle) {
@@ -223,6 +223,13 @@ impl StatusService {
     }
 }
 
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusPortConflict {
+    pub port: u16,
+    pub users: Vec<String>, // e.g. "localnet proxy_port_number", "suibase_api_port_number"
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -245,6 +252,10 @@ pub struct WorkdirStatusResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub services: Option<Vec<StatusService>>,
 
+    // Ports of this workdir also configured elsewhere (the workdir is then DEGRADED).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_conflicts: Option<Vec<StatusPortConflict>>,
+
     // This is the output when the option 'display' is true.
     // Will also change the default to false for all the other fields.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -265,6 +276,7 @@ impl WorkdirStatusResponse {
             client_version: None,
             network_version: None,
             services: None,
+            port_conflicts: None,
             display: None,
             debug: None,
         }
@@ -479,6 +491,87 @@ impl VersionedEq for PackagesConfigResponse {
     }
 }
 
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedPackage {
+    pub name: String,
+    pub uuid: String,       // Same as the key of the MoveConfig.
+    pub package_id: String, // Hexa (no 0x). Latest published.
+    pub package_timestamp: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toml_path: Option<String>,
+
+    // One of "Disconnected", "Subscribing", "Subscribed", "Unsubscribing", "ReadyToDelete".
+    pub subscription_state: String,
+
+    // Older package ids (oldest first), does not include package_id.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub previous_package_ids: Vec<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirPackagesResponse {
+    pub header: Header,
+    pub packages: Vec<TrackedPackage>, // Sorted by name.
+}
+
+impl WorkdirPackagesResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Default for WorkdirPackagesResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A Suibase console-log event of a Move package.
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveConsoleLogLine {
+    pub timestamp: String, // RFC 3339 (from the event timestampMs).
+    pub level: String,     // "ERROR", "WARN", "INFO", "DEBUG" or "TRACE".
+    pub package_name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+
+    pub message: String,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveConsoleLogResponse {
+    pub header: Header,
+    pub lines: Vec<MoveConsoleLogLine>, // Oldest first.
+}
+
+impl MoveConsoleLogResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl Default for MoveConsoleLogResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -595,6 +688,22 @@ pub trait PackagesApi {
         data_uuid: Option<String>,
     ) -> RpcResult<PackagesConfigResponse>;
 
+    #[method(name = "getWorkdirPackages")]
+    async fn get_workdir_packages(&self, workdir: String) -> RpcResult<WorkdirPackagesResponse>;
+
+    // Most recent console-log events of the Move packages (all of them, or only
+    // of 'package' when specified).
+    //
+    // Up to 'limit' lines (default 100). Older events are in the move-console.log
+    // file of the workdir.
+    #[method(name = "getMoveConsoleLog")]
+    async fn get_move_console_log(
+        &self,
+        workdir: String,
+        package: Option<String>,
+        limit: Option<u32>,
+    ) -> RpcResult<MoveConsoleLogResponse>;
+
     #[method(name = "prePublish")]
     async fn pre_publish(
         &self,
@@ -129,7 +129,7 @@ impl DtpApiImpl {
         host_sla_idx: ManagedVecU16,
         tc: String,
         cid: u64,
-    ) -> Result<String, anyhow::Error> {
+    ) -> Result<Vec<u8>, anyhow::Error> {
         if cid == 0 {
             bail!("Invalid cid=0");
         }
@@ -160,7 +160,7 @@ impl DtpApiImpl {
             }
             Err(e) => {
                 log::error!("Error waiting for callback: {:?}", e);
-                "".to_string()
+                Vec::new()
             }
         };
 
@@ -721,8 +721,8 @@ impl DtpApiServer for DtpApiImpl {
             }
             let open_conn = open_conn.unwrap();
             info!(
-                "impl_dtp_api: Connection.conn_objects = {:?}",
-                open_conn.get_conn_objects().await
+                "impl_dtp_api: Connection.info = {:?}",
+                open_conn.info().await
             );
             conn = Some(open_conn);
         }
@@ -826,6 +826,7 @@ impl DtpApiServer for DtpApiImpl {
             return Err(RpcSuibaseError::InternalError(e.to_string()).into());
         }
         let response = response.unwrap();
+        conn.report_response_received(response.len()).await;
 
         // Wait block for a response using the request handle.
         // (the handle is just a one-shot channel with timeout).
@@ -954,7 +955,7 @@ impl DtpApiServer for DtpApiImpl {
             resp.debug = Some(debug_out);
         }
         //resp.result = "Success".to_string();
-        resp.result = response;
+        resp.result = String::from_utf8_lossy(&response).to_string();
         Ok(resp)
     }
 }
@@ -9,8 +9,8 @@ use common::basic_types::{AdminControllerMsg, AdminControllerTx, WorkdirIdx};
 use crate::shared_types::Globals;
 
 use super::{
-    GeneralApiServer, Header, InfoResponse, RpcInputError, RpcSuibaseError, StatusService,
-    VersionsResponse, WorkdirStatusResponse,
+    GeneralApiServer, Header, InfoResponse, RpcInputError, RpcSuibaseError, StatusPortConflict,
+    StatusService, VersionsResponse, WorkdirStatusResponse,
 };
 
 use super::def_header::Versioned;
@@ -209,6 +209,32 @@ impl GeneralApiImpl {
         true
     }
 
+    async fn apply_port_conflicts(&self, workdir: &str, resp: &mut WorkdirStatusResponse) {
+        // Report the port conflicts detected by the AdminController on config reload.
+        let conflicts: Vec<StatusPortConflict> = {
+            let port_conflicts_guard = self.globals.port_conflicts.read().await;
+            port_conflicts_guard
+                .iter()
+                .filter(|conflict| conflict.affects_workdir(workdir))
+                .map(|conflict| StatusPortConflict {
+                    port: conflict.port,
+                    users: conflict.users.iter().map(|user| user.to_string()).collect(),
+                })
+                .collect()
+        };
+        if conflicts.is_empty() {
+            return;
+        }
+
+        // Something is likely failing to bind its port, so an "OK" is downgraded.
+        if resp.status.as_deref() == Some("OK") {
+            resp.status = Some("DEGRADED".to_string());
+            let ports: Vec<String> = conflicts.iter().map(|c| c.port.to_string()).collect();
+            resp.status_info = Some(format!("port conflict on {}", ports.join(", ")));
+        }
+        resp.port_conflicts = Some(conflicts);
+    }
+
     async fn update_globals_workdir_status(
         &self,
         workdir: String,
@@ -251,8 +277,11 @@ impl GeneralApiImpl {
             .all(|line| !line.trim_start().starts_with("Error:"));
 
         if is_successful {
-            is_successful =
-                self.convert_status_cmd_resp_to_status_response(cmd_resp, workdir, &mut resp);
+            is_successful = self.convert_status_cmd_resp_to_status_response(
+                cmd_resp,
+                workdir.clone(),
+                &mut resp,
+            );
         }
 
         if !is_successful {
@@ -260,6 +289,8 @@ impl GeneralApiImpl {
             resp.status = Some("DOWN".to_string());
         }
 
+        self.apply_port_conflicts(&workdir, &mut resp).await;
+
         {
             // Get the globals for the target workdir_idx.
             let mut globals_read_guard = self.globals.get_status(workdir_idx).write().await;
@@ -7,16 +7,21 @@ use jsonrpsee::core::RpcResult;
 use jsonrpsee_types::ErrorObjectOwned as RpcError;
 
 use chrono::Utc;
+use common::workers::SubscriptionTrackingState;
 
 use crate::admin_controller::AdminController;
 use crate::api::RpcSuibaseError;
 use crate::shared_types::{Globals, GlobalsPackagesConfigST};
 
 use super::{
-    MoveConfig, PackageInstance, PackagesApiServer, PackagesConfigResponse, RpcInputError,
-    SuccessResponse, WorkdirSuiEventsResponse,
+    MoveConfig, MoveConsoleLogLine, MoveConsoleLogResponse, PackageInstance, PackagesApiServer,
+    PackagesConfigResponse, RpcInputError, SuccessResponse, TrackedPackage,
+    WorkdirPackagesResponse, WorkdirSuiEventsResponse,
 };
 
+// Default 'limit' of getMoveConsoleLog.
+const MOVE_CONSOLE_LOG_DEFAULT_LIMIT: u32 = 100;
+
 pub struct PackagesApiImpl {
     pub globals: Globals,
     pub admctrl_tx: AdminControllerTx,
@@ -282,6 +287,92 @@ impl PackagesApiServer for PackagesApiImpl {
         resp_ready.header.key = Some(workdir.clone());
         return Ok(resp_ready);
     }
+
+    async fn get_workdir_packages(&self, workdir: String) -> RpcResult<WorkdirPackagesResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match self.globals.get_workdir_idx_by_name(&workdir).await {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let mut resp = WorkdirPackagesResponse::new();
+        {
+            let globals_read_guard = self.globals.packages_config.read().await;
+            let globals = &*globals_read_guard;
+
+            // No uuids in the header: the tracking_state are updated in-place (without
+            // changing the data_uuid of the packages config).
+            if let Some(move_configs) =
+                GlobalsPackagesConfigST::get_move_configs(&globals.workdirs, workdir_idx)
+            {
+                for (uuid, move_config) in move_configs {
+                    // Not yet published (should not happen).
+                    let latest = match &move_config.latest_package {
+                        Some(latest) => latest,
+                        None => continue,
+                    };
+                    let subscription_state =
+                        match SubscriptionTrackingState::try_from(move_config.tracking_state) {
+                            Ok(state) => state.to_string(),
+                            Err(val) => format!("Unknown({})", val),
+                        };
+                    resp.packages.push(TrackedPackage {
+                        name: latest.package_name.clone(),
+                        uuid: uuid.clone(),
+                        package_id: latest.package_id.clone(),
+                        package_timestamp: latest.package_timestamp.clone(),
+                        toml_path: move_config.path.clone(),
+                        subscription_state,
+                        previous_package_ids: move_config
+                            .older_packages
+                            .iter()
+                            .map(|older| older.package_id.clone())
+                            .collect(),
+                    });
+                }
+            }
+        }
+        resp.packages
+            .sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.uuid.cmp(&b.uuid)));
+
+        resp.header.method = "getWorkdirPackages".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn get_move_console_log(
+        &self,
+        workdir: String,
+        package: Option<String>,
+        limit: Option<u32>,
+    ) -> RpcResult<MoveConsoleLogResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match self.globals.get_workdir_idx_by_name(&workdir).await {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let limit = limit.unwrap_or(MOVE_CONSOLE_LOG_DEFAULT_LIMIT) as usize;
+
+        let entries = {
+            let console_log_guard = self.globals.get_move_console_log(workdir_idx).read().await;
+            console_log_guard.entries(package.as_deref(), limit)
+        };
+
+        let mut resp = MoveConsoleLogResponse::new();
+        resp.lines = entries
+            .into_iter()
+            .map(|entry| MoveConsoleLogLine {
+                timestamp: entry.timestamp_str(),
+                level: entry.level_str().to_string(),
+                package_name: entry.package_name,
+                sender: entry.sender,
+                message: entry.message,
+            })
+            .collect();
+        resp.header.method = "getMoveConsoleLog".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
 }
 
 impl PackagesApiImpl {
@@ -411,3 +502,55 @@ impl PackagesApiImpl {
         Ok((workdir_idx, package_uuid))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_types::MoveConsoleLogEntry;
+    use common::basic_types::{EVENT_LEVEL_INFO, MPSC_Q_SIZE};
+    use common::shared_types::WORKDIR_IDX_LOCALNET;
+
+    #[tokio::test]
+    async fn test_get_move_console_log() {
+        let globals = Globals::new();
+        {
+            let mut console_log_guard = globals
+                .get_move_console_log(WORKDIR_IDX_LOCALNET)
+                .write()
+                .await;
+            for (package_name, message) in [("demo", "a"), ("other", "b"), ("demo", "c")] {
+                console_log_guard.push(MoveConsoleLogEntry {
+                    timestamp_ms: 1703895010111,
+                    level: EVENT_LEVEL_INFO,
+                    package_name: package_name.to_string(),
+                    sender: None,
+                    message: message.to_string(),
+                });
+            }
+        }
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let api = PackagesApiImpl::new(globals, admctrl_tx);
+
+        let resp = api
+            .get_move_console_log("localnet".to_string(), None, Some(2))
+            .await
+            .unwrap();
+        let messages: Vec<&str> = resp.lines.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["b", "c"]);
+        assert_eq!(resp.header.method, "getMoveConsoleLog");
+
+        let resp = api
+            .get_move_console_log("localnet".to_string(), Some("demo".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(resp.lines.len(), 2);
+        assert_eq!(resp.lines[0].timestamp, "2023-12-30T00:10:10.111Z");
+        assert_eq!(resp.lines[0].level, "INFO");
+        assert_eq!(resp.lines[0].package_name, "demo");
+
+        assert!(api
+            .get_move_console_log("bogus".to_string(), None, None)
+            .await
+            .is_err());
+    }
+}
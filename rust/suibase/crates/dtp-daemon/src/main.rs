@@ -28,15 +28,14 @@ use anyhow::Result;
 use api::APIServerParams;
 use clap::*;
 
-use clock_trigger::{ClockTrigger, ClockTriggerParams};
+use clock_trigger::NetmonClockHooks;
 use colored::Colorize;
 use common::basic_types::MPSC_Q_SIZE;
+use common::workers::{ClockTrigger, ClockTriggerParams};
 use env_logger::{Builder, Env};
 
 mod admin_controller;
 mod api;
-mod app_error;
-
 mod clock_trigger;
 mod network_monitor;
 mod proxy_server;
@@ -94,8 +93,9 @@ impl Command {
                 let apiserver_params = APIServerParams::new(globals.clone(), admctrl_tx.clone());
                 let apiserver = APIServer::new(apiserver_params);
 
-                let clock_params = ClockTriggerParams::new(netmon_tx.clone(), admctrl_tx.clone());
-                let clock: ClockTrigger = ClockTrigger::new(clock_params);
+                let clock_hooks = NetmonClockHooks::new(netmon_tx.clone());
+                let clock_params = ClockTriggerParams::new(clock_hooks, admctrl_tx.clone());
+                let clock = ClockTrigger::new(clock_params);
 
                 // Start all top levels subsystems.
                 let errors = Toplevel::new(|s| async move {
@@ -7,7 +7,8 @@ use crate::shared_types::{
     GlobalsProxyMT, RequestFailedReason, SendFailedReason, ServerStats, TargetServer,
     REQUEST_FAILED_BAD_REQUEST_HTTP, SEND_FAILED_RESP_HTTP_STATUS, SEND_FAILED_UNSPECIFIED_STATUS,
 };
-use crate::workers::RequestWorker;
+
+use common::workers::{RequestWorker, ServerCheckMsg};
 
 use bitflags::bitflags;
 
@@ -16,8 +17,7 @@ use tokio_graceful_shutdown::{FutureExt, SubsystemBuilder, SubsystemHandle};
 
 use tokio::time::{Duration, Instant};
 
-pub const HEADER_SBSD_SERVER_IDX: &str = "X-SBSD-SERVER-IDX";
-pub const HEADER_SBSD_SERVER_HC: &str = "X-SBSD-SERVER-HC";
+pub use common::workers::{HEADER_SBSD_SERVER_HC, HEADER_SBSD_SERVER_IDX};
 
 pub struct NetmonMsg {
     // Internal messaging. Sent for every user request/response.
@@ -65,6 +65,18 @@ impl NetmonMsg {
     */
 }
 
+// For the RequestWorker (EVENT_DO_SERVER_HEALTH_CHECK).
+impl ServerCheckMsg for NetmonMsg {
+    // Inherent methods have priority (no recursion).
+    fn server_idx(&self) -> u8 {
+        self.server_idx()
+    }
+
+    fn proxy_port(&self) -> u16 {
+        self.para16()[0]
+    }
+}
+
 // Events ID.
 // See GenericChannelID for guidelines to set these values.
 pub type NetmonEvent = u8;
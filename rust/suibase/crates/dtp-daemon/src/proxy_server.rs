@@ -2,7 +2,6 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use std::sync::Arc;
 
-use crate::app_error::AppError;
 use std::time::Duration;
 
 use common::basic_types::*;
@@ -6,7 +6,7 @@ use tokio::sync::Mutex;
 #[derive(Debug)]
 pub struct OneShotCallbackMessage {
     pub cid: u64,
-    pub response: String,
+    pub response: Vec<u8>, // Raw data of the response (not necessarily UTF-8).
 }
 
 #[derive(Debug)]
@@ -125,7 +125,7 @@ impl GlobalsDTPConnsStateClientST {
             if let Some(channel) = callback.resp_channel.take() {
                 let msg = OneShotCallbackMessage {
                     cid: callback.cid,
-                    response: "".to_string(),
+                    response: Vec::new(),
                 };
                 let result = channel.send(msg);
                 if let Err(e) = result {
@@ -171,7 +171,7 @@ impl GlobalsDTPConnsStateClientST {
         None
     }
 
-    pub fn trigger_send_callback(&mut self, tc: String, response: String) {
+    pub fn trigger_send_callback(&mut self, tc: String, response: Vec<u8>) {
         if let Some(callback) = self.send_callbacks.get_mut(&tc) {
             if let Some(channel) = callback.resp_channel.take() {
                 let msg = OneShotCallbackMessage {
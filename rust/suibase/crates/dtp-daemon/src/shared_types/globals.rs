@@ -20,13 +20,13 @@ use common::basic_types::{GenericTx, ManagedVec, WorkdirIdx};
 
 use super::{
     GlobalsDTPConnsStateClientST, GlobalsDTPConnsStateRxST, GlobalsDTPConnsStateServerST,
-    GlobalsDTPConnsStateTxST, GlobalsEventsDataST, GlobalsPackagesConfigST, WebSocketWorkerIOTx,
-    WebSocketWorkerTx,
+    GlobalsDTPConnsStateTxST, GlobalsMoveConsoleLogST, GlobalsPackagesConfigST,
+    WebSocketWorkerIOTx, WebSocketWorkerTx,
 };
 
 use common::shared_types::{
-    GlobalsWorkdirConfigST, GlobalsWorkdirsST, Workdir, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET,
-    WORKDIR_IDX_MAINNET, WORKDIR_IDX_TESTNET,
+    GlobalsEventsDataST, GlobalsWorkdirConfigST, GlobalsWorkdirsST, PortConflict, Workdir,
+    WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET, WORKDIR_IDX_MAINNET, WORKDIR_IDX_TESTNET,
 };
 
 #[derive(Debug)]
@@ -140,7 +140,9 @@ pub type GlobalsConfigMT = Arc<tokio::sync::RwLock<GlobalsWorkdirConfigST>>;
 pub type GlobalsChannelsMT = Arc<tokio::sync::RwLock<GlobalsChannelsST>>;
 pub type GlobalsPackagesConfigMT = Arc<tokio::sync::RwLock<GlobalsPackagesConfigST>>;
 pub type GlobalsEventsDataMT = Arc<tokio::sync::RwLock<GlobalsEventsDataST>>;
+pub type GlobalsMoveConsoleLogMT = Arc<tokio::sync::RwLock<GlobalsMoveConsoleLogST>>;
 pub type GlobalsWorkdirsMT = Arc<tokio::sync::RwLock<GlobalsWorkdirsST>>;
+pub type GlobalsPortConflictsMT = Arc<tokio::sync::RwLock<Vec<PortConflict>>>;
 pub type GlobalsAPIMutexMT = Arc<tokio::sync::Mutex<GlobalsAPIMutexST>>;
 pub type GlobalsDTPConnsStateClientMT = Arc<tokio::sync::RwLock<GlobalsDTPConnsStateClientST>>;
 pub type GlobalsDTPConnsStateServerMT = Arc<tokio::sync::RwLock<GlobalsDTPConnsStateServerST>>;
@@ -181,6 +183,9 @@ pub struct Globals {
     // These config are *rarely* changed for the lifetime of the process.
     pub workdirs: GlobalsWorkdirsMT,
 
+    // Ports configured more than once across all workdirs (re-checked on every config reload).
+    pub port_conflicts: GlobalsPortConflictsMT,
+
     // All workdirs status as presented on the UI (e.g. which process are running, is the localnet down?)
     pub status_localnet: GlobalsWorkdirStatusMT,
     pub status_devnet: GlobalsWorkdirStatusMT,
@@ -196,6 +201,12 @@ pub struct Globals {
     pub events_data_testnet: GlobalsEventsDataMT,
     pub events_data_mainnet: GlobalsEventsDataMT,
 
+    // Most recent Suibase console-log events of the Move packages (see move_console_log.rs).
+    pub move_console_log_localnet: GlobalsMoveConsoleLogMT,
+    pub move_console_log_devnet: GlobalsMoveConsoleLogMT,
+    pub move_console_log_testnet: GlobalsMoveConsoleLogMT,
+    pub move_console_log_mainnet: GlobalsMoveConsoleLogMT,
+
     // To avoid race conditions, all JSON-RPC API calls are serialized for a given workdir.
     pub api_mutex_localnet: GlobalsAPIMutexMT,
     pub api_mutex_devnet: GlobalsAPIMutexMT,
@@ -237,6 +248,7 @@ impl Globals {
             channels_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsChannelsST::new())),
             channels_mainnet: Arc::new(tokio::sync::RwLock::new(GlobalsChannelsST::new())),
             workdirs: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirsST::new())),
+            port_conflicts: Arc::new(tokio::sync::RwLock::new(Vec::new())),
             status_localnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirStatusST::new())),
             status_devnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirStatusST::new())),
             status_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirStatusST::new())),
@@ -246,6 +258,18 @@ impl Globals {
             events_data_devnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
             events_data_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
             events_data_mainnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
+            move_console_log_localnet: Arc::new(tokio::sync::RwLock::new(
+                GlobalsMoveConsoleLogST::new(),
+            )),
+            move_console_log_devnet: Arc::new(tokio::sync::RwLock::new(
+                GlobalsMoveConsoleLogST::new(),
+            )),
+            move_console_log_testnet: Arc::new(tokio::sync::RwLock::new(
+                GlobalsMoveConsoleLogST::new(),
+            )),
+            move_console_log_mainnet: Arc::new(tokio::sync::RwLock::new(
+                GlobalsMoveConsoleLogST::new(),
+            )),
             api_mutex_localnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
             api_mutex_devnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
             api_mutex_testnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
@@ -356,6 +380,17 @@ impl Globals {
             _ => None,
         }
     }
+    pub fn get_move_console_log(&self, workdir_idx: WorkdirIdx) -> &GlobalsMoveConsoleLogMT {
+        // Use hard coded workdir_idx to dispatch the right data.
+        match workdir_idx {
+            WORKDIR_IDX_LOCALNET => &self.move_console_log_localnet,
+            WORKDIR_IDX_DEVNET => &self.move_console_log_devnet,
+            WORKDIR_IDX_TESTNET => &self.move_console_log_testnet,
+            WORKDIR_IDX_MAINNET => &self.move_console_log_mainnet,
+            _ => panic!("Invalid workdir_idx {}", workdir_idx),
+        }
+    }
+
     pub fn events_data_as_mut(
         &mut self,
         workdir_idx: WorkdirIdx,
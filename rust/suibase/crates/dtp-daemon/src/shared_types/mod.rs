@@ -8,9 +8,9 @@ pub(crate) use self::dtp_conns_state_client::*;
 pub(crate) use self::dtp_conns_state_rx::*;
 pub(crate) use self::dtp_conns_state_server::*;
 pub(crate) use self::dtp_conns_state_tx::*;
-pub(crate) use self::events::*;
 pub(crate) use self::globals::*;
 pub(crate) use self::input_port::*;
+pub(crate) use self::move_console_log::*;
 pub(crate) use self::packages::*;
 pub(crate) use self::server_stats::*;
 pub(crate) use self::target_server::*;
@@ -20,9 +20,9 @@ mod dtp_conns_state_client;
 mod dtp_conns_state_rx;
 mod dtp_conns_state_server;
 mod dtp_conns_state_tx;
-mod events;
 mod globals;
 mod input_port;
+mod move_console_log;
 mod packages;
 mod server_stats;
 mod target_server;
@@ -0,0 +1,236 @@
+// Suibase console-log events (DTP src 4) emitted by instrumented Move packages.
+//
+// The WebSocketWorkerIO appends each of them to
+// ~/suibase/workdirs/<workdir>/logs/move-console.log and keeps the most recent in
+// memory for the getMoveConsoleLog API (e.g. a "Move console" panel).
+use std::collections::{HashSet, VecDeque};
+use std::io::Write;
+use std::path::Path;
+
+use common::basic_types::{
+    EventLevel, EVENT_LEVEL_DEBUG, EVENT_LEVEL_ERROR, EVENT_LEVEL_INFO, EVENT_LEVEL_MAX,
+    EVENT_LEVEL_MIN, EVENT_LEVEL_TRACE, EVENT_LEVEL_WARN,
+};
+use serde_json::{Map, Value};
+
+// Most recent entries kept in memory (per workdir).
+pub const MOVE_CONSOLE_LOG_CAPACITY: usize = 1000;
+
+pub const MOVE_CONSOLE_LOG_DIR: &str = "logs";
+pub const MOVE_CONSOLE_LOG_FILENAME: &str = "move-console.log";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveConsoleLogEntry {
+    pub timestamp_ms: u64,
+    pub level: EventLevel,
+    pub package_name: String,
+    pub sender: Option<String>,
+    pub message: String,
+}
+
+impl MoveConsoleLogEntry {
+    // From the "result" object of a Sui event notification. Example:
+    //
+    //   "result": Object {
+    //      "packageId": String("0xe065...3b08"),
+    //      "type": String("0xe065...3b08::console::ConsoleEvent"),
+    //      "parsedJson": Object {"level": Number(3), "message": String("X"), "sender": ...},
+    //      "timestampMs": String("1703895010111"),
+    //      ...
+    //   }
+    //
+    // The error is a description of the first missing or invalid field.
+    pub fn from_event(package_name: &str, result: &Map<String, Value>) -> Result<Self, String> {
+        let parsed_json = result
+            .get("parsedJson")
+            .and_then(|v| v.as_object())
+            .ok_or("missing parsedJson")?;
+
+        let level = parsed_json
+            .get("level")
+            .and_then(|v| v.as_u64())
+            .ok_or("missing parsedJson.level")?;
+        if level < EVENT_LEVEL_MIN as u64 || level > EVENT_LEVEL_MAX as u64 {
+            return Err(format!("invalid parsedJson.level {}", level));
+        }
+
+        let message = parsed_json
+            .get("message")
+            .and_then(|v| v.as_str())
+            .ok_or("missing parsedJson.message")?;
+
+        let sender = parsed_json
+            .get("sender")
+            .and_then(|v| v.as_str())
+            .map(|sender| sender.to_string());
+
+        let timestamp_ms = result
+            .get("timestampMs")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|timestamp_ms| *timestamp_ms != 0)
+            .ok_or("missing or invalid timestampMs")?;
+
+        Ok(Self {
+            timestamp_ms,
+            level: level as EventLevel,
+            package_name: package_name.to_string(),
+            sender,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn level_str(&self) -> &'static str {
+        match self.level {
+            EVENT_LEVEL_ERROR => "ERROR",
+            EVENT_LEVEL_WARN => "WARN",
+            EVENT_LEVEL_INFO => "INFO",
+            EVENT_LEVEL_DEBUG => "DEBUG",
+            EVENT_LEVEL_TRACE => "TRACE",
+            _ => "INVALID",
+        }
+    }
+
+    // RFC 3339 (UTC, milliseconds) of the event timestamp.
+    pub fn timestamp_str(&self) -> String {
+        chrono::DateTime::from_timestamp_millis(self.timestamp_ms as i64)
+            .map(|ts| ts.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_default()
+    }
+
+    // One line of the move-console.log file.
+    pub fn to_log_line(&self) -> String {
+        // A multi-line message would break the one event per line format.
+        let message = self.message.replace('\n', "\\n");
+        format!(
+            "{} {:5} [{}] {}",
+            self.timestamp_str(),
+            self.level_str(),
+            self.package_name,
+            message
+        )
+    }
+}
+
+// Append the entry to <workdir_path>/logs/move-console.log (created as needed).
+pub fn append_move_console_log(
+    workdir_path: &Path,
+    entry: &MoveConsoleLogEntry,
+) -> std::io::Result<()> {
+    let dir = workdir_path.join(MOVE_CONSOLE_LOG_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(MOVE_CONSOLE_LOG_FILENAME))?;
+    writeln!(file, "{}", entry.to_log_line())
+}
+
+#[derive(Debug)]
+pub struct GlobalsMoveConsoleLogST {
+    entries: VecDeque<MoveConsoleLogEntry>, // Oldest first.
+
+    // Packages for which a malformed console event was already logged.
+    malformed_reported: HashSet<String>,
+}
+
+impl GlobalsMoveConsoleLogST {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            malformed_reported: HashSet::new(),
+        }
+    }
+
+    pub fn push(&mut self, entry: MoveConsoleLogEntry) {
+        if self.entries.len() == MOVE_CONSOLE_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    // The 'limit' most recent entries (oldest first), optionally of a single package.
+    pub fn entries(&self, package_name: Option<&str>, limit: usize) -> Vec<MoveConsoleLogEntry> {
+        let mut entries: Vec<MoveConsoleLogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| package_name.map_or(true, |name| entry.package_name == name))
+            .take(limit)
+            .cloned()
+            .collect();
+        entries.reverse();
+        entries
+    }
+
+    // Returns true only the first time for a package (so it gets logged once).
+    pub fn report_malformed(&mut self, package_name: &str) -> bool {
+        self.malformed_reported.insert(package_name.to_string())
+    }
+}
+
+impl Default for GlobalsMoveConsoleLogST {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_json(level: u64, message: &str) -> Map<String, Value> {
+        serde_json::json!({
+            "packageId": "0xe0654f522ae3cb1a364174f740275d57f5a87b430d669c5a0554b975af683b08",
+            "type": "0xe065::console::ConsoleEvent",
+            "parsedJson": { "level": level, "message": message, "sender": "0xf7ae" },
+            "timestampMs": "1703895010111"
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+    }
+
+    #[test]
+    fn test_from_event() {
+        let entry = MoveConsoleLogEntry::from_event("demo", &result_json(3, "a\nb")).unwrap();
+        assert_eq!(entry.level_str(), "INFO");
+        assert_eq!(entry.sender.as_deref(), Some("0xf7ae"));
+        assert_eq!(
+            entry.to_log_line(),
+            "2023-12-30T00:10:10.111Z INFO  [demo] a\\nb"
+        );
+
+        for level in [0, 6] {
+            assert!(MoveConsoleLogEntry::from_event("demo", &result_json(level, "x")).is_err());
+        }
+        let mut result = result_json(3, "x");
+        result.remove("timestampMs");
+        assert!(MoveConsoleLogEntry::from_event("demo", &result).is_err());
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut log = GlobalsMoveConsoleLogST::new();
+        for i in 0..MOVE_CONSOLE_LOG_CAPACITY + 10 {
+            let package_name = if i % 2 == 0 { "even" } else { "odd" };
+            let mut entry =
+                MoveConsoleLogEntry::from_event(package_name, &result_json(3, "x")).unwrap();
+            entry.message = i.to_string();
+            log.push(entry);
+        }
+        let messages = |entries: Vec<MoveConsoleLogEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.message).collect()
+        };
+        assert_eq!(
+            log.entries(None, usize::MAX).len(),
+            MOVE_CONSOLE_LOG_CAPACITY
+        );
+        assert_eq!(messages(log.entries(None, 2)), ["1008", "1009"]);
+        assert_eq!(messages(log.entries(Some("even"), 2)), ["1006", "1008"]);
+        assert!(log.entries(Some("other"), 2).is_empty());
+
+        assert!(log.report_malformed("even"));
+        assert!(!log.report_malformed("even"));
+    }
+}
@@ -12,12 +12,10 @@
 //   - Shell command on different workdir can be executed concurrently.
 //
 // flatten everything under "workers" module.
-pub(crate) use self::request_worker::*;
-pub(crate) use self::shell_worker::*;
+pub(crate) use self::tunnel_worker::*;
 pub(crate) use self::websocket_worker::*;
 pub(crate) use self::websocket_worker_io::*;
 
-mod request_worker;
-mod shell_worker;
+mod tunnel_worker;
 mod websocket_worker;
 mod websocket_worker_io;
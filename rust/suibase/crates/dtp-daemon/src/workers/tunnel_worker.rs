@@ -0,0 +1,811 @@
+// TCP tunnels over DTP connections (dtp_services with a local_port).
+//
+// Client side (one TunnelWorker per workdir):
+//   Listen on 127.0.0.1:<local_port> of every client-enabled service. Each accepted
+//   TCP connection gets its own DTP connection to the remote_host of the service.
+//
+// Server side (TunnelServerSession, one per incoming DTP connection):
+//   Created by the WebSocketWorkerIO on the first request of a connection for a
+//   server-enabled service. Bytes are forwarded to 127.0.0.1:<local_port>.
+//
+// The bytes are streamed as TunnelFrame (see dtp-core tunnel_frame.rs). A request
+// carries the next chunk read from the client application and its response the next
+// chunk read from the server side service. Every request is an on-chain write, so:
+//
+//   - Flow control: a single unacknowledged write per connection (the application
+//     socket is not read again until the response is received, which back-pressure
+//     the application through TCP).
+//
+//   - An idle connection is polled with empty requests, with an exponential backoff.
+//
+// The close of either side is propagated with a Close frame.
+//
+// With a keep-alive on the service (heartbeat_interval_secs), a tunnel is also closed
+// once its server is Dead (see KeepAliveConfig), instead of waiting for a response
+// timeout.
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::shared_types::{
+    DTPConnStateDataClient, ExtendedWebSocketWorkerIOMsg, Globals, WebSocketWorkerIOMsg,
+};
+
+use common::basic_types::{
+    self, AutoThread, GenericChannelMsg, GenericRx, ManagedVecU16, Runnable, WorkdirIdx,
+    MPSC_Q_SIZE,
+};
+use common::shared_types::{DTPService, WORKDIRS_KEYS};
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use dtp_core::network::{TunnelFrame, TUNNEL_MAX_PAYLOAD};
+use dtp_sdk::{Connection, Host, KeepAliveConfig, LivenessState, DTP};
+use sui_types::base_types::SuiAddress;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+
+// Time waited for more bytes from the server side service before responding.
+const TUNNEL_READ_WAIT: Duration = Duration::from_millis(200);
+
+// Delay between the requests of an idle connection (doubled while idle).
+const TUNNEL_POLL_MIN: Duration = Duration::from_millis(250);
+const TUNNEL_POLL_MAX: Duration = Duration::from_secs(4);
+
+// The tunnel connection is closed when a response takes longer.
+const TUNNEL_RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Time allowed for the WebSocketWorkerIO to subscribe to a new connection.
+const TUNNEL_SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Keep-alive of the connections of a service, None when not configured.
+pub fn service_keep_alive(service: &DTPService) -> Option<KeepAliveConfig> {
+    let interval_secs = service.heartbeat_interval_secs()?;
+    let gas_budget = service
+        .heartbeat_gas_budget()
+        .unwrap_or(KeepAliveConfig::default().gas_budget);
+    Some(KeepAliveConfig::with_interval(
+        interval_secs * 1000,
+        gas_budget,
+    ))
+}
+
+// Resolves once the server of 'conn' is Dead (never without a keep-alive).
+async fn wait_peer_dead(conn: &Connection) {
+    loop {
+        if conn.liveness().await.state == LivenessState::Dead {
+            return;
+        }
+        tokio::time::sleep(TUNNEL_POLL_MAX).await;
+    }
+}
+
+// Read the next chunk from a socket.
+//
+// Returns an empty chunk when nothing was received within 'wait' and None once the
+// peer closed.
+async fn read_chunk<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    wait: Duration,
+) -> std::io::Result<Option<Vec<u8>>> {
+    let mut buf = vec![0u8; TUNNEL_MAX_PAYLOAD];
+    match tokio::time::timeout(wait, reader.read(&mut buf)).await {
+        Err(_elapsed) => Ok(Some(Vec::new())),
+        Ok(Ok(0)) => Ok(None),
+        Ok(Ok(n)) => {
+            buf.truncate(n);
+            Ok(Some(buf))
+        }
+        Ok(Err(e)) => Err(e),
+    }
+}
+
+// One request/response exchange on a tunnel connection.
+#[async_trait]
+pub trait TunnelTransport: Send {
+    async fn request(&mut self, frame: TunnelFrame) -> Result<TunnelFrame>;
+}
+
+// Stream a client application TCP connection through 'transport' until either side
+// closes.
+pub async fn run_tunnel_client<T: TunnelTransport>(stream: TcpStream, transport: &mut T) {
+    let (mut reader, mut writer) = stream.into_split();
+    let mut seq: u32 = 0;
+    let mut poll_delay = TUNNEL_POLL_MIN;
+    loop {
+        // Wait for bytes from the application (up to the poll delay).
+        let request = match read_chunk(&mut reader, poll_delay).await {
+            Ok(Some(chunk)) => TunnelFrame::data(seq, chunk),
+            Ok(None) | Err(_) => TunnelFrame::close(seq),
+        };
+        let is_close = request.is_close();
+        let is_idle = request.payload.is_empty();
+
+        let response = match transport.request(request).await {
+            Ok(response) if response.seq == seq => response,
+            Ok(response) => {
+                log::warn!("tunnel response seq {} for request {}", response.seq, seq);
+                break;
+            }
+            Err(e) => {
+                log::warn!("tunnel request {} failed ({})", seq, e);
+                break;
+            }
+        };
+        if is_close || response.is_close() {
+            break;
+        }
+
+        // A write failure is detected by the next read (application closed).
+        if !response.payload.is_empty() {
+            let _ = writer.write_all(&response.payload).await;
+        }
+
+        poll_delay = if is_idle && response.payload.is_empty() {
+            std::cmp::min(poll_delay * 2, TUNNEL_POLL_MAX)
+        } else {
+            TUNNEL_POLL_MIN
+        };
+        seq = seq.wrapping_add(1);
+    }
+    let _ = writer.shutdown().await;
+}
+
+// Server side state of one tunnel connection.
+pub struct TunnelServerSession {
+    local_port: u16,
+    stream: Option<TcpStream>, // None until the first request, and once closed.
+    next_seq: u32,
+    is_closed: bool,
+}
+
+impl TunnelServerSession {
+    pub fn new(local_port: u16) -> Self {
+        Self {
+            local_port,
+            stream: None,
+            next_seq: 0,
+            is_closed: false,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    // Forward the request to the local service. Returns the response to the client.
+    pub async fn handle_request(&mut self, request: TunnelFrame) -> TunnelFrame {
+        let seq = request.seq;
+        if self.is_closed || request.is_close() {
+            return self.close(seq);
+        }
+        if seq != self.next_seq {
+            log::warn!("tunnel request seq {} (expected {})", seq, self.next_seq);
+            return self.close(seq);
+        }
+        self.next_seq = self.next_seq.wrapping_add(1);
+
+        if self.stream.is_none() {
+            let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), self.local_port);
+            match TcpStream::connect(address).await {
+                Ok(stream) => self.stream = Some(stream),
+                Err(e) => {
+                    log::warn!("tunnel connect to {} failed ({})", address, e);
+                    return self.close(seq);
+                }
+            }
+        }
+        let stream = self.stream.as_mut().unwrap();
+
+        if !request.payload.is_empty() && stream.write_all(&request.payload).await.is_err() {
+            return self.close(seq);
+        }
+        match read_chunk(stream, TUNNEL_READ_WAIT).await {
+            Ok(Some(chunk)) => TunnelFrame::data(seq, chunk),
+            Ok(None) | Err(_) => self.close(seq),
+        }
+    }
+
+    fn close(&mut self, seq: u32) -> TunnelFrame {
+        self.stream = None; // Drop closes the local socket.
+        self.is_closed = true;
+        TunnelFrame::close(seq)
+    }
+}
+
+// A request received by the WebSocketWorkerIO for a tunnel connection.
+pub struct TunnelServerRequest {
+    pub frame: TunnelFrame,
+    pub cid: u64,
+    pub resp_ipipe_addr: SuiAddress,
+    pub dtp: Arc<Mutex<DTP>>,
+}
+
+pub type TunnelServerTx = tokio::sync::mpsc::Sender<TunnelServerRequest>;
+
+// Start the session of a new incoming tunnel connection. The requests are handled in
+// order, and the task exits once the connection is closed.
+pub fn spawn_tunnel_server_session(tc_addr: String, local_port: u16) -> TunnelServerTx {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<TunnelServerRequest>(MPSC_Q_SIZE);
+    tokio::spawn(async move {
+        let mut session = TunnelServerSession::new(local_port);
+        while let Some(request) = rx.recv().await {
+            let response = session.handle_request(request.frame).await;
+            let mut dtp = request.dtp.lock().await;
+            let result = dtp
+                .low_level_send_response(
+                    request.resp_ipipe_addr,
+                    0,
+                    0,
+                    response.encode(),
+                    request.cid,
+                )
+                .await;
+            if let Err(e) = result {
+                log::warn!("tunnel tc={} failed to send response ({})", tc_addr, e);
+                break;
+            }
+            if session.is_closed() {
+                break;
+            }
+        }
+        log::info!("tunnel tc={} closed", tc_addr);
+    });
+    tx
+}
+
+// Client side DTP connection of a tunnel.
+struct TunnelClientConn {
+    globals: Globals,
+    workdir_idx: WorkdirIdx,
+    host_sla_idx: ManagedVecU16,
+    dtp: Arc<Mutex<DTP>>,
+    conn: Connection,
+    tc_address: String,
+}
+
+impl TunnelClientConn {
+    async fn open(
+        globals: &Globals,
+        workdir_idx: WorkdirIdx,
+        service: &DTPService,
+    ) -> Result<Self> {
+        let service_idx = service.service_idx();
+        let remote_host = service
+            .remote_host()
+            .ok_or_else(|| anyhow!("remote_host not defined"))?;
+
+        let (gas_addr, package_id) = {
+            let config_guard = globals.get_config(workdir_idx).read().await;
+            let user_config = &config_guard.user_config;
+            let gas_addr = service
+                .gas_address()
+                .cloned()
+                .or_else(|| user_config.dtp_default_gas_address());
+            (gas_addr, user_config.dtp_package_id())
+        };
+        let gas_addr = gas_addr.ok_or_else(|| anyhow!("gas address not defined"))?;
+        let gas_addr = dtp_sdk::str_to_sui_address(&gas_addr)?;
+        let package_id = package_id.ok_or_else(|| anyhow!("package id not defined"))?;
+        let package_id = dtp_sdk::str_to_object_id(&package_id)?;
+        let workdir = globals
+            .get_workdir_by_idx(workdir_idx)
+            .await
+            .ok_or_else(|| anyhow!("workdir {} not found", workdir_idx))?;
+        let keystore_path = workdir.path().join("config").join("sui.keystore");
+
+        // Same DTP client as the ping API (one per service and remote host).
+        let (dtp, host_sla_idx, is_new) = {
+            let mut conns_state_guard = globals.dtp_conns_state_client(workdir_idx).write().await;
+            let conns_state = &mut *conns_state_guard;
+            let existing = conns_state
+                .conns
+                .get_if_some(service_idx, remote_host, 0)
+                .and_then(|idx| Some((idx, conns_state.conns.get(idx)?.dtp.clone()?)));
+            if let Some((host_sla_idx, dtp)) = existing {
+                (dtp, host_sla_idx, false)
+            } else {
+                let mut new_dtp = DTP::new(gas_addr, keystore_path.to_str()).await?;
+                // TODO Remove hard coding (same as ping).
+                new_dtp.add_rpc_url("http://localhost:44340").await?;
+                new_dtp.set_gas_address(gas_addr).await;
+                new_dtp.set_package_id(package_id).await;
+                let dtp = Arc::new(Mutex::new(new_dtp));
+
+                let mut new_conn_state = DTPConnStateDataClient::new();
+                new_conn_state.set_dtp(&dtp);
+                let host_sla_idx = conns_state
+                    .conns
+                    .push(new_conn_state, service_idx, remote_host.clone(), 0)
+                    .ok_or_else(|| anyhow!("Max number of connections reached"))?;
+                (dtp, host_sla_idx, true)
+            }
+        };
+
+        let target_host = {
+            let mut dtp_guard = dtp.lock().await;
+            if is_new {
+                // Make sure the localhost exists (created as needed).
+                dtp_guard.get_host().await?;
+            }
+            Self::resolve_remote_host(&dtp_guard, remote_host).await?
+        };
+
+        let conn = {
+            let dtp_guard = dtp.lock().await;
+            dtp_guard
+                .set_keep_alive_config(service_keep_alive(service))
+                .await;
+            dtp_guard
+                .create_connection(&target_host, service_idx)
+                .await?
+        };
+        let tc_address = conn
+            .get_tc_address()
+            .await
+            .ok_or_else(|| anyhow!("TC address missing in Connection object"))?;
+
+        let tunnel_conn = Self {
+            globals: globals.clone(),
+            workdir_idx,
+            host_sla_idx,
+            dtp,
+            conn,
+            tc_address,
+        };
+        tunnel_conn.subscribe(package_id.to_string()).await?;
+        Ok(tunnel_conn)
+    }
+
+    // remote_host is either a Host object id or a registered host name.
+    async fn resolve_remote_host(dtp: &DTP, remote_host: &str) -> Result<Host> {
+        let host = match dtp_sdk::str_to_object_id(remote_host) {
+            Ok(host_id) => dtp.get_host_by_id(host_id).await?,
+            Err(_) => dtp.resolve_host(remote_host).await?,
+        };
+        host.ok_or_else(|| anyhow!("remote host {} does not exist", remote_host))
+    }
+
+    // Have the WebSocketWorkerIO monitor the ipipes of the connection (for the responses).
+    async fn subscribe(&self, package_id: String) -> Result<()> {
+        let subscribed = {
+            let mut conns_state_guard = self
+                .globals
+                .dtp_conns_state_client(self.workdir_idx)
+                .write()
+                .await;
+            let conns_state = &mut *conns_state_guard;
+            let cid = conns_state.create_subs_callback(self.host_sla_idx);
+            conns_state.get_subs_callback(cid)
+        };
+
+        let channel = {
+            let channels_guard = self.globals.get_channels(self.workdir_idx).read().await;
+            channels_guard.to_websocket_worker_io.clone()
+        };
+        let channel = channel.ok_or_else(|| anyhow!("WebSocketWorkerIO not running"))?;
+        let mut msg = GenericChannelMsg::new();
+        msg.event_id = basic_types::EVENT_EXEC;
+        msg.command = Some("conn_update".to_string());
+        msg.workdir_idx = Some(self.workdir_idx);
+        let ext_msg = ExtendedWebSocketWorkerIOMsg {
+            generic: msg,
+            package: Some(package_id),
+            conn: Some(self.conn.clone()),
+            host_sla_idx: Some(self.host_sla_idx),
+            ..Default::default()
+        };
+        channel
+            .send(WebSocketWorkerIOMsg::Extended(ext_msg))
+            .await
+            .map_err(|_| anyhow!("WebSocketWorkerIO channel closed"))?;
+
+        let result = match subscribed {
+            Some(subscribed) => tokio::time::timeout(TUNNEL_SUBSCRIBE_TIMEOUT, subscribed)
+                .await
+                .map_err(|_| anyhow!("subscription timeout"))
+                .map(|_| ()),
+            None => Err(anyhow!("subscription callback missing")),
+        };
+        self.globals
+            .dtp_conns_state_client(self.workdir_idx)
+            .write()
+            .await
+            .delete_subs_callback(self.host_sla_idx);
+        result
+    }
+
+    // The tunnel is closed with this error (logged as the event of the Dead connection).
+    async fn peer_dead_error(&self) -> anyhow::Error {
+        let last_seen = self.conn.liveness().await.last_seen;
+        let unseen_for = last_seen.elapsed().unwrap_or_default();
+        log::warn!(
+            "DTP connection tc={} dead (server not seen for {}s)",
+            self.tc_address,
+            unseen_for.as_secs()
+        );
+        anyhow!("server dead")
+    }
+}
+
+#[async_trait]
+impl TunnelTransport for TunnelClientConn {
+    async fn request(&mut self, frame: TunnelFrame) -> Result<TunnelFrame> {
+        let liveness = self.conn.liveness().await;
+        if liveness.state == LivenessState::Dead {
+            return Err(self.peer_dead_error().await);
+        }
+
+        let response_channel = {
+            let mut conns_state_guard = self
+                .globals
+                .dtp_conns_state_client(self.workdir_idx)
+                .write()
+                .await;
+            let conns_state = &mut *conns_state_guard;
+            let cid = conns_state.create_send_callback(self.host_sla_idx, self.tc_address.clone());
+            conns_state.get_send_callback(cid)
+        };
+
+        let sent = self
+            .dtp
+            .lock()
+            .await
+            .send_request(&mut self.conn, frame.encode())
+            .await;
+
+        let response = match (sent, response_channel) {
+            (Err(e), _) => Err(anyhow!(e)),
+            (Ok(()), None) => Err(anyhow!("response callback missing")),
+            (Ok(()), Some(channel)) => {
+                let response = tokio::time::timeout(TUNNEL_RESPONSE_TIMEOUT, channel);
+                tokio::select! {
+                    result = response => match result {
+                        Ok(Ok(msg)) => Ok(msg.response),
+                        Ok(Err(_)) => Err(anyhow!("response callback dropped")),
+                        Err(_) => Err(anyhow!("no response after {:?}", TUNNEL_RESPONSE_TIMEOUT)),
+                    },
+                    _ = wait_peer_dead(&self.conn) => Err(self.peer_dead_error().await),
+                }
+            }
+        };
+        self.globals
+            .dtp_conns_state_client(self.workdir_idx)
+            .write()
+            .await
+            .delete_send_callback(self.host_sla_idx, self.tc_address.clone());
+
+        let response = response?;
+        self.conn.report_response_received(response.len()).await;
+        Ok(TunnelFrame::decode(&response)?)
+    }
+}
+
+#[derive(Clone)]
+pub struct TunnelWorkerParams {
+    globals: Globals,
+    event_rx: Arc<Mutex<GenericRx>>,
+    workdir_idx: WorkdirIdx,
+    workdir_name: String,
+}
+
+impl TunnelWorkerParams {
+    pub fn new(globals: Globals, event_rx: GenericRx, workdir_idx: WorkdirIdx) -> Self {
+        Self {
+            globals,
+            event_rx: Arc::new(Mutex::new(event_rx)),
+            workdir_idx,
+            workdir_name: WORKDIRS_KEYS[workdir_idx as usize].to_string(),
+        }
+    }
+}
+
+pub struct TunnelWorker {
+    auto_thread: AutoThread<TunnelThread, TunnelWorkerParams>,
+}
+
+impl TunnelWorker {
+    pub fn new(params: TunnelWorkerParams) -> Self {
+        Self {
+            auto_thread: AutoThread::new("TunnelWorker".to_string(), params),
+        }
+    }
+
+    pub async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.auto_thread.run(subsys).await
+    }
+}
+
+struct TunnelListener {
+    service: DTPService,
+    handle: JoinHandle<()>,
+}
+
+struct TunnelThread {
+    name: String,
+    params: TunnelWorkerParams,
+
+    // Key is the local_port.
+    listeners: HashMap<u16, TunnelListener>,
+}
+
+#[async_trait]
+impl Runnable<TunnelWorkerParams> for TunnelThread {
+    fn new(name: String, params: TunnelWorkerParams) -> Self {
+        Self {
+            name,
+            params,
+            listeners: HashMap::new(),
+        }
+    }
+
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        let result = self.event_loop(&subsys).cancel_on_shutdown(&subsys).await;
+        for listener in self.listeners.values() {
+            listener.handle.abort();
+        }
+        if result.is_err() {
+            log::info!("normal thread exit (1)");
+        }
+        Ok(())
+    }
+}
+
+impl TunnelThread {
+    async fn event_loop(&mut self, subsys: &SubsystemHandle) {
+        self.update_listeners().await;
+
+        let event_rx = Arc::clone(&self.params.event_rx);
+        let mut event_rx = event_rx.lock().await;
+        while !subsys.is_shutdown_requested() {
+            match event_rx.recv().await {
+                Some(msg) => {
+                    common::mpsc_q_check!(event_rx);
+                    if msg.event_id == basic_types::EVENT_UPDATE {
+                        self.update_listeners().await;
+                    } else {
+                        log::error!("Unexpected event_id {:?}", msg);
+                    }
+                }
+                None => return,
+            }
+        }
+    }
+
+    // Start/stop the listeners to match the client-enabled services of the config.
+    async fn update_listeners(&mut self) {
+        let services: Vec<DTPService> = {
+            let config_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            config_guard
+                .user_config
+                .dtp_services()
+                .iter()
+                .filter(|s| s.is_client_enabled() && s.remote_host().is_some())
+                .filter(|s| s.local_port().is_some())
+                .cloned()
+                .collect()
+        };
+
+        self.listeners.retain(|local_port, listener| {
+            let keep = services.contains(&listener.service) && !listener.handle.is_finished();
+            if !keep {
+                log::info!("{} stop tunnel listener port {}", self.name, local_port);
+                listener.handle.abort();
+            }
+            keep
+        });
+
+        for service in services {
+            let local_port = service.local_port().unwrap();
+            if self.listeners.contains_key(&local_port) {
+                continue;
+            }
+            let handle = tokio::spawn(run_tunnel_listener(
+                self.params.globals.clone(),
+                self.params.workdir_idx,
+                self.params.workdir_name.clone(),
+                service.clone(),
+            ));
+            self.listeners
+                .insert(local_port, TunnelListener { service, handle });
+        }
+    }
+}
+
+async fn run_tunnel_listener(
+    globals: Globals,
+    workdir_idx: WorkdirIdx,
+    workdir_name: String,
+    service: DTPService,
+) {
+    let local_port = service.local_port().unwrap_or_default();
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), local_port);
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::warn!(
+                "{} dtp_services {} local_port {} bind failed ({})",
+                workdir_name,
+                service.service_type(),
+                local_port,
+                e
+            );
+            return;
+        }
+    };
+    log::info!(
+        "{} tunnel listening on {} toward {}",
+        workdir_name,
+        address,
+        service.remote_host().map_or("", |host| host.as_str())
+    );
+
+    // The connections are opened one at the time (the WebSocketWorkerIO subscription
+    // callback is per service and remote host).
+    let open_lock = Arc::new(Mutex::new(()));
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _peer)) => stream,
+            Err(e) => {
+                log::warn!("{} tunnel accept failed ({})", workdir_name, e);
+                tokio::time::sleep(TUNNEL_POLL_MIN).await;
+                continue;
+            }
+        };
+        let globals = globals.clone();
+        let workdir_name = workdir_name.clone();
+        let service = service.clone();
+        let open_lock = open_lock.clone();
+        tokio::spawn(async move {
+            let conn = {
+                let _open_guard = open_lock.lock().await;
+                TunnelClientConn::open(&globals, workdir_idx, &service).await
+            };
+            match conn {
+                Ok(mut conn) => run_tunnel_client(stream, &mut conn).await,
+                Err(e) => log::warn!("{} tunnel connection failed ({})", workdir_name, e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The on-chain leg replaced by a direct call to the server session (with the
+    // frames still going through their encoding).
+    struct LocalTransport {
+        session: TunnelServerSession,
+    }
+
+    #[async_trait]
+    impl TunnelTransport for LocalTransport {
+        async fn request(&mut self, frame: TunnelFrame) -> Result<TunnelFrame> {
+            let frame = TunnelFrame::decode(&frame.encode())?;
+            let response = self.session.handle_request(frame).await;
+            Ok(TunnelFrame::decode(&response.encode())?)
+        }
+    }
+
+    async fn bind_local() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_http_request() {
+        // Tiny HTTP server on the server side.
+        let (http_listener, http_port) = bind_local().await;
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = http_listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let body = "hello through DTP";
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        // Client side local_port.
+        let (tunnel_listener, tunnel_port) = bind_local().await;
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = tunnel_listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut transport = LocalTransport {
+                        session: TunnelServerSession::new(http_port),
+                    };
+                    run_tunnel_client(stream, &mut transport).await;
+                    assert!(transport.session.is_closed());
+                });
+            }
+        });
+
+        for _ in 0..2 {
+            let body = reqwest::get(format!("http://127.0.0.1:{}", tunnel_port))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert_eq!(body, "hello through DTP");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_server_session() {
+        // Local service echoing, then closing on "bye".
+        let (listener, port) = bind_local().await;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            while let Ok(n) = stream.read(&mut buf).await {
+                if n == 0 || &buf[..n] == b"bye" {
+                    break;
+                }
+                stream.write_all(&buf[..n]).await.unwrap();
+            }
+        });
+
+        let mut session = TunnelServerSession::new(port);
+        let response = session
+            .handle_request(TunnelFrame::data(0, b"abc".to_vec()))
+            .await;
+        assert_eq!(response, TunnelFrame::data(0, b"abc".to_vec()));
+
+        // Poll without anything to receive.
+        let response = session
+            .handle_request(TunnelFrame::data(1, Vec::new()))
+            .await;
+        assert_eq!(response, TunnelFrame::data(1, Vec::new()));
+
+        // The close of the local service is propagated.
+        let response = session
+            .handle_request(TunnelFrame::data(2, b"bye".to_vec()))
+            .await;
+        assert!(response.is_close());
+        assert!(session.is_closed());
+        let response = session
+            .handle_request(TunnelFrame::data(3, Vec::new()))
+            .await;
+        assert!(response.is_close());
+
+        // Out of order request.
+        let mut session = TunnelServerSession::new(port);
+        assert!(session
+            .handle_request(TunnelFrame::data(5, Vec::new()))
+            .await
+            .is_close());
+
+        // Nothing listening on the local port.
+        let (listener, unused_port) = bind_local().await;
+        drop(listener);
+        let mut session = TunnelServerSession::new(unused_port);
+        assert!(session
+            .handle_request(TunnelFrame::data(0, b"x".to_vec()))
+            .await
+            .is_close());
+    }
+}
@@ -8,19 +8,25 @@
 //
 // The thread is auto-restart in case of panic.
 
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Instant;
 use std::{collections::HashMap, sync::Arc};
 
 use crate::shared_types::{
-    ExtendedWebSocketWorkerIOMsg, Globals, GlobalsPackagesConfigST, WebSocketWorkerIOMsg,
-    WebSocketWorkerIORx, WebSocketWorkerIOTx, WebSocketWorkerMsg, WebSocketWorkerTx,
+    append_move_console_log, ExtendedWebSocketWorkerIOMsg, Globals, GlobalsPackagesConfigST,
+    MoveConsoleLogEntry, WebSocketWorkerIOMsg, WebSocketWorkerIORx, WebSocketWorkerIOTx,
+    WebSocketWorkerTx,
+};
+use crate::workers::{
+    service_keep_alive, spawn_tunnel_server_session, TunnelServerRequest, TunnelServerTx,
 };
 
-use common::log_safe;
 use common::shared_types::{
     WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET, WORKDIR_IDX_MAINNET,
     WORKDIR_IDX_TESTNET,
 };
+use common::{log_safe_error, log_safe_warn};
 
 use common::basic_types::{
     self, AutoThread, GenericChannelMsg, ManagedVecU16, Runnable, WorkdirIdx,
@@ -29,7 +35,8 @@ use common::basic_types::{
 use anyhow::{bail, Result};
 use axum::async_trait;
 
-use dtp_sdk::DTP;
+use dtp_core::network::TunnelFrame;
+use dtp_sdk::{KeepAliveConfig, LivenessState, DTP, HEARTBEAT_CID};
 use futures::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
@@ -177,6 +184,20 @@ struct WebSocketWorkerIOThread {
     cli_conns: ClientConnTrackingMap,
     srv_conns: ServerConnTrackingMap,
 
+    // Where the move-console.log is written. Resolved on the first console event.
+    workdir_path: Option<PathBuf>,
+
+    // Incoming tunnel connections (requests forwarded to a local_port).
+    //
+    // Key is a TransportController Sui address ("0x" string).
+    tunnel_sessions: HashMap<String, TunnelServerTx>,
+
+    // When a request was last received on an incoming connection, for the services
+    // with a keep-alive. Dropped once Dead (see drop_dead_srv_conns).
+    //
+    // Key is a TransportController Sui address ("0x" string).
+    srv_last_seen: HashMap<String, (u8, Instant)>, // (service_idx, last_seen)
+
     websocket: WebSocketIOManagement,
 }
 
@@ -190,6 +211,9 @@ impl Runnable<WebSocketWorkerIOParams> for WebSocketWorkerIOThread {
             localhost_subs: HashMap::new(),
             cli_conns: HashMap::new(),
             srv_conns: HashMap::new(),
+            workdir_path: None,
+            tunnel_sessions: HashMap::new(),
+            srv_last_seen: HashMap::new(),
             websocket: WebSocketIOManagement::new(),
         }
     }
@@ -266,7 +290,7 @@ impl WebSocketWorkerIOThread {
                     // Pong are automatically queued by tungstenite, just need to flush them once in while.
                     // https://docs.rs/tungstenite/latest/tungstenite/protocol/struct.WebSocket.html#method.flush
                     if let Err(e) = write.flush().await {
-                        log::error!("flush write.send error: {:?}", e);
+                        log_safe_error!("flush write.send error: {:?}", e);
                     }
                 }
                 return;
@@ -643,24 +667,8 @@ impl WebSocketWorkerIOThread {
                 let rx_result = self
                     .handle_ws_msg_for_package(subscription_number, result)
                     .await;
-                if let Ok((package_uuid, package_name)) = rx_result {
-                    let msg = GenericChannelMsg {
-                        event_id: basic_types::EVENT_EXEC,
-                        command: Some("add_sui_event".to_string()),
-                        params: vec![package_uuid, package_name],
-                        data_json: Some(json_msg.clone()),
-                        workdir_idx: Some(self.params.workdir_idx),
-                        resp_channel: None,
-                    };
-                    let ws_msg = WebSocketWorkerMsg::Generic(msg);
-                    if self.params.parent_tx.send(ws_msg).await.is_err() {
-                        let error_msg = format!(
-                            "Failed to add_sui_event for workdir_idx={} message={:?}",
-                            self.params.workdir_idx, json_msg
-                        );
-                        log::error!("{}", error_msg);
-                    }
-                    return;
+                if let Ok((_package_uuid, package_name)) = rx_result {
+                    self.handle_ws_msg_console_log(&package_name, result).await;
                 }
             }
         }
@@ -673,7 +681,7 @@ impl WebSocketWorkerIOThread {
         peer_ipipe_addr: &String,
         _cli_host_addr: &String,
         srv_host_addr: &String,
-        _tc_addr: &String,
+        tc_addr: &String,
         _src_addr: &String,
         parsed_json: &Map<String, Value>,
     ) -> Result<(), anyhow::Error> {
@@ -785,10 +793,67 @@ impl WebSocketWorkerIOThread {
         }
 
         let dtp_access = dtp_access.unwrap();
+        let resp_ipipe_addr = SuiAddress::from_str(peer_ipipe_addr)?;
+
+        if self.service_keep_alive(service_idx).await.is_some() {
+            self.srv_last_seen
+                .insert(tc_addr.clone(), (service_idx, Instant::now()));
+        }
+
+        // A heartbeat is answered with an empty heartbeat, and never forwarded.
+        if cid == HEARTBEAT_CID {
+            let mut dtp = dtp_access.lock().await;
+            let resp_result = dtp
+                .low_level_send_response(resp_ipipe_addr, 0, 0, Vec::new(), HEARTBEAT_CID)
+                .await;
+            if let Err(e) = resp_result {
+                log::warn!(
+                    "Failed to answer heartbeat. workdir={} tc={} error={}",
+                    self.params.workdir_name,
+                    tc_addr,
+                    e
+                );
+            }
+            return Ok(());
+        }
+
+        // Services with a local_port are tunnels, the others are echoed back.
+        if let Some(local_port) = self.tunnel_local_port(service_idx).await {
+            let frame = match TunnelFrame::decode(&data_bytes) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::error!(
+                        "Invalid tunnel request. workdir={} tc={} error={}",
+                        self.params.workdir_name,
+                        tc_addr,
+                        e
+                    );
+                    return Ok(());
+                }
+            };
+            self.tunnel_sessions.retain(|_, tx| !tx.is_closed());
+            let tx = self
+                .tunnel_sessions
+                .entry(tc_addr.clone())
+                .or_insert_with(|| spawn_tunnel_server_session(tc_addr.clone(), local_port));
+            let request = TunnelServerRequest {
+                frame,
+                cid,
+                resp_ipipe_addr,
+                dtp: dtp_access,
+            };
+            if tx.try_send(request).is_err() {
+                log::error!(
+                    "Tunnel request dropped. workdir={} tc={}",
+                    self.params.workdir_name,
+                    tc_addr
+                );
+            }
+            return Ok(());
+        }
+
         {
             let mut dtp = dtp_access.lock().await;
-            // Convert resp_ipipe_addr to a String.
-            let resp_ipipe_addr = SuiAddress::from_str(peer_ipipe_addr)?;
             let resp_result = dtp
                 .low_level_send_response(resp_ipipe_addr, 0, 0, data_bytes, cid)
                 .await;
@@ -806,6 +871,85 @@ impl WebSocketWorkerIOThread {
         Ok(())
     }
 
+    // The local_port of the service, when this host serves it as a tunnel.
+    async fn tunnel_local_port(&self, service_idx: u8) -> Option<u16> {
+        let config_guard = self
+            .params
+            .globals
+            .get_config(self.params.workdir_idx)
+            .read()
+            .await;
+        config_guard
+            .user_config
+            .dtp_services()
+            .iter()
+            .find(|s| s.service_idx() == service_idx && s.is_server_enabled())
+            .and_then(|s| s.local_port())
+    }
+
+    // The keep-alive of the service, when this host serves it.
+    async fn service_keep_alive(&self, service_idx: u8) -> Option<KeepAliveConfig> {
+        let config_guard = self
+            .params
+            .globals
+            .get_config(self.params.workdir_idx)
+            .read()
+            .await;
+        config_guard
+            .user_config
+            .dtp_services()
+            .iter()
+            .find(|s| s.service_idx() == service_idx && s.is_server_enabled())
+            .and_then(service_keep_alive)
+    }
+
+    // Drop the incoming connections Dead for the keep-alive of their service (no
+    // request, including the heartbeats, for its threshold). Their tunnel session
+    // ends (with its connection to the local_port).
+    async fn drop_dead_srv_conns(&mut self) {
+        if self.srv_last_seen.is_empty() {
+            return;
+        }
+        let mut keep_alives: HashMap<u8, Option<KeepAliveConfig>> = HashMap::new();
+        for (service_idx, _) in self.srv_last_seen.values() {
+            if !keep_alives.contains_key(service_idx) {
+                let keep_alive = self.service_keep_alive(*service_idx).await;
+                keep_alives.insert(*service_idx, keep_alive);
+            }
+        }
+
+        let workdir_name = &self.params.workdir_name;
+        let tunnel_sessions = &mut self.tunnel_sessions;
+        self.srv_last_seen
+            .retain(|tc_addr, &mut (service_idx, last_seen)| {
+                // No longer tracked once the service has no keep-alive.
+                let keep_alive = match keep_alives.get(&service_idx) {
+                    Some(Some(keep_alive)) => keep_alive,
+                    _ => return false,
+                };
+                // A tunnel closed by its client is not reported.
+                if tunnel_sessions
+                    .get(tc_addr)
+                    .is_some_and(|tx| tx.is_closed())
+                {
+                    tunnel_sessions.remove(tc_addr);
+                    return false;
+                }
+                let unseen_for = last_seen.elapsed();
+                if keep_alive.liveness_state(unseen_for) != LivenessState::Dead {
+                    return true;
+                }
+                tunnel_sessions.remove(tc_addr);
+                log::warn!(
+                    "DTP connection tc={} dead (client not seen for {}s). workdir={}",
+                    tc_addr,
+                    unseen_for.as_secs(),
+                    workdir_name
+                );
+                false
+            });
+    }
+
     async fn handle_ws_msg_for_srv_ipipe(
         &mut self,
         subscription_number: u64,
@@ -813,7 +957,7 @@ impl WebSocketWorkerIOThread {
         src_addr: &str,
         parsed_json: &Map<String, Value>,
     ) -> Result<(), anyhow::Error> {
-        // Get the user data. For now convert to a string.
+        // Get the user data (raw bytes, interpreted by the requester).
         let data = parsed_json.get("data");
         if data.is_none() {
             log::error!(
@@ -834,6 +978,16 @@ impl WebSocketWorkerIOThread {
         }
         let data = data.unwrap();
 
+        // The responses to the heartbeats are not for the applications (the keep-alive
+        // of the connection sees the server alive from its ipipes).
+        let cid = parsed_json
+            .get("cid")
+            .and_then(|cid| cid.as_str())
+            .and_then(|cid| u64::from_str(cid).ok());
+        if cid == Some(HEARTBEAT_CID) {
+            return Ok(());
+        }
+
         // Map the Vec<Value> to a Vec<Byte>
         let mut data_bytes: Vec<u8> = Vec::new();
         for value in data {
@@ -849,19 +1003,7 @@ impl WebSocketWorkerIOThread {
             let value = value.unwrap();
             data_bytes.push(value as u8);
         }
-        // Convert data_bytes to a string.
-        let data = String::from_utf8(data_bytes);
-        if data.is_err() {
-            log::error!(
-                "Invalid data conversion in DTP Sui Event message. workdir={} message={:?}",
-                self.params.workdir_name,
-                parsed_json
-            );
-            return Ok(());
-        }
-        let data = data.unwrap();
 
-        // Extract the reply as a string.
         // If a matching request, forward the data into the one-shot response channel.
         // Consume the pending request.
         {
@@ -873,7 +1015,7 @@ impl WebSocketWorkerIOThread {
                 .await;
             let conns_state = &mut *conns_state_guard;
 
-            conns_state.trigger_send_callback(tc_id.to_string(), data);
+            conns_state.trigger_send_callback(tc_id.to_string(), data_bytes);
         }
 
         info!(
@@ -983,6 +1125,62 @@ impl WebSocketWorkerIOThread {
         Ok((package_uuid, package_name))
     }
 
+    // Suibase console-log event from an instrumented Move package.
+    //
+    // Appended to the move-console.log file of the workdir and kept in memory for
+    // the getMoveConsoleLog API.
+    async fn handle_ws_msg_console_log(&mut self, package_name: &str, result: &Map<String, Value>) {
+        let workdir_idx = self.params.workdir_idx;
+        let entry = match MoveConsoleLogEntry::from_event(package_name, result) {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Skipped. Logged only once per package (likely the same for all its events).
+                let mut console_log_guard = self
+                    .params
+                    .globals
+                    .get_move_console_log(workdir_idx)
+                    .write()
+                    .await;
+                if console_log_guard.report_malformed(package_name) {
+                    log::warn!(
+                        "Malformed console event from package {} ({}). workdir={} result={:?}",
+                        package_name,
+                        e,
+                        self.params.workdir_name,
+                        result
+                    );
+                }
+                return;
+            }
+        };
+
+        if self.workdir_path.is_none() {
+            self.workdir_path = self
+                .params
+                .globals
+                .get_workdir_by_idx(workdir_idx)
+                .await
+                .map(|workdir| workdir.path().to_path_buf());
+        }
+        if let Some(workdir_path) = &self.workdir_path {
+            if let Err(e) = append_move_console_log(workdir_path, &entry) {
+                log_safe_error!(
+                    "Failed to write move-console.log for workdir {}: {}",
+                    self.params.workdir_name,
+                    e
+                );
+            }
+        }
+
+        let mut console_log_guard = self
+            .params
+            .globals
+            .get_move_console_log(workdir_idx)
+            .write()
+            .await;
+        console_log_guard.push(entry);
+    }
+
     // Returns is_correlated_msg and trig_audit_event.
     fn tracker_update_state_correlation(
         tracker: &mut SubscriptionTracking,
@@ -1107,6 +1305,8 @@ impl WebSocketWorkerIOThread {
         }
 
         /*log::info!("Received an audit message: {:?}", msg);*/
+        self.drop_dead_srv_conns().await;
+
         let mut state_change = false;
         {
             // Get a reader lock on the globals packages_config.
@@ -1475,9 +1675,9 @@ impl WebSocketWorkerIOThread {
         }
         let connection = msg.conn.unwrap();
 
-        let conn_obj = connection.get_conn_objects().await;
+        let conn_obj = connection.info().await;
         if conn_obj.is_none() {
-            log::error!("process_conn_update - Missing Connection.conn_objects");
+            log::error!("process_conn_update - Missing Connection.info");
             return;
         }
         let conn_objs = conn_obj.unwrap();
@@ -1657,7 +1857,7 @@ impl WebSocketWorkerIOThread {
                 // 'send' is equivalent to call write+flush.
                 // https://docs.rs/tungstenite/latest/tungstenite/protocol/struct.WebSocket.html#method.send
                 if let Err(e) = write.send(msg).await {
-                    log::error!("subscribe write.send error: {:?}", e);
+                    log_safe_error!("subscribe write.send error: {:?}", e);
                 } /*else {
                       log::info!("subscribe write.send success");
                   }*/
@@ -1758,7 +1958,7 @@ impl WebSocketWorkerIOThread {
             if let Some(ref mut write) = websocket.write {
                 log::info!("Sending unsubscribe message: {:?}", msg);
                 if let Err(e) = write.send(msg).await {
-                    log::error!("unsubscribe write.send error: {:?}", e);
+                    log_safe_error!("unsubscribe write.send error: {:?}", e);
                 } else {
                     log::info!("unsubscribe write.send success");
                 }
@@ -1817,7 +2017,7 @@ impl WebSocketWorkerIOThread {
                 if !e.to_string().contains("Connection refused") {
                     // "Connection refused" is annoying when localnet is not running, so ignore it.
                     // TODO Make this more "aware" about if localnet should be running or not.
-                    log_safe!(format!("connect_async error: {:?}", e));
+                    log_safe_warn!("connect_async error: {:?}", e);
                 }
 
                 self.websocket.write = None;
@@ -1915,3 +2115,96 @@ impl WebSocketWorkerIOThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::basic_types::MPSC_Q_SIZE;
+
+    const PACKAGE_ID: &str = "e0654f522ae3cb1a364174f740275d57f5a87b430d669c5a0554b975af683b08";
+    const SUBSCRIPTION_NUMBER: u64 = 42;
+
+    // A "suix_subscribeEvent" notification from the package.
+    fn console_event(parsed_json: Value) -> Message {
+        let json_msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "suix_subscribeEvent",
+            "params": {
+                "subscription": SUBSCRIPTION_NUMBER,
+                "result": {
+                    "id": { "txDigest": "3VuaCUx5K7bo7SCak", "eventSeq": "0" },
+                    "packageId": format!("0x{}", PACKAGE_ID),
+                    "transactionModule": "Counter",
+                    "sender": "0xf7ae",
+                    "type": format!("0x{}::console::ConsoleEvent", PACKAGE_ID),
+                    "parsedJson": parsed_json,
+                    "timestampMs": "1703895010111"
+                }
+            }
+        });
+        Message::Text(json_msg.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_console_log_events() {
+        let globals = Globals::new();
+        let (io_tx, io_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let (parent_tx, _parent_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let params = WebSocketWorkerIOParams::new(
+            globals.clone(),
+            io_rx,
+            io_tx,
+            parent_tx,
+            WORKDIR_IDX_LOCALNET,
+        );
+        let mut thread = WebSocketWorkerIOThread::new("test".to_string(), params);
+        let workdir_path =
+            std::env::temp_dir().join(format!("dtp-move-console-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&workdir_path);
+        thread.workdir_path = Some(workdir_path.clone());
+
+        let mut tracker = SubscriptionTracking::new_for_managed_package(
+            "demo".to_string(),
+            "uuid".to_string(),
+            "timestamp".to_string(),
+            PACKAGE_ID.to_string(),
+        );
+        tracker.change_state_to(SubscriptionTrackingState::Subscribing);
+        tracker.report_subscribing_response(SUBSCRIPTION_NUMBER.to_string());
+        tracker.change_state_to(SubscriptionTrackingState::Subscribed);
+        thread.package_subs.insert(PACKAGE_ID.to_string(), tracker);
+
+        for (level, message) in [(3, "increment() entry called"), (1, "overflow")] {
+            let event = console_event(serde_json::json!({
+                "src": 4, "src_addr": "0xf7ae", "level": level, "message": message
+            }));
+            thread.process_ws_msg(event).await;
+        }
+        // Malformed (no level), skipped.
+        for _ in 0..2 {
+            let event = console_event(serde_json::json!({
+                "src": 4, "src_addr": "0xf7ae", "message": "no level"
+            }));
+            thread.process_ws_msg(event).await;
+        }
+
+        let log = std::fs::read_to_string(workdir_path.join("logs").join("move-console.log"));
+        let _ = std::fs::remove_dir_all(&workdir_path);
+        assert_eq!(
+            log.unwrap(),
+            "2023-12-30T00:10:10.111Z INFO  [demo] increment() entry called\n\
+             2023-12-30T00:10:10.111Z ERROR [demo] overflow\n"
+        );
+
+        let mut console_log_guard = globals
+            .get_move_console_log(WORKDIR_IDX_LOCALNET)
+            .write()
+            .await;
+        let entries = console_log_guard.entries(Some("demo"), 10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].message, "overflow");
+        assert_eq!(entries[1].sender, None);
+        // The malformed events were already reported.
+        assert!(!console_log_guard.report_malformed("demo"));
+    }
+}
@@ -12,13 +12,20 @@ fn init() {
 }
 
 async fn api_request(method: &str) -> serde_json::Value {
+    let value = api_request_with_params(method, json!(["localnet"])).await;
+    let _ = value["result"]["header"]["methodUuid"].as_str().unwrap();
+    let _ = value["result"]["header"]["dataUuid"].as_str().unwrap();
+    value
+}
+
+async fn api_request_with_params(method: &str, params: serde_json::Value) -> serde_json::Value {
     let client = reqwest::Client::new();
     let request_url = "http://localhost:44398";
     let request_body = json!({
         "jsonrpc": "2.0",
         "id": 1,
         "method": method,
-        "params": ["localnet"]
+        "params": params
     });
 
     let response = match client.post(request_url).json(&request_body).send().await {
@@ -38,8 +45,6 @@ async fn api_request(method: &str) -> serde_json::Value {
     // Some sanity checks.
     let hdr_method = value["result"]["header"]["method"].as_str().unwrap();
     assert_eq!(hdr_method, method);
-    let _ = value["result"]["header"]["methodUuid"].as_str().unwrap();
-    let _ = value["result"]["header"]["dataUuid"].as_str().unwrap();
     value
 }
 
@@ -61,3 +66,65 @@ async fn test_sanity_api() {
     log::info!("response_body: {}", response);
     assert_eq!(response["result"]["status"].as_str().unwrap(), "OK");
 }
+
+#[tokio::test]
+async fn test_workdir_packages() {
+    init();
+    // Fake a publication by doing the same prePublish/postPublish calls
+    // that the publish scripts do around "sui client publish".
+    let dir = std::env::temp_dir().join(format!("dtp-test-packages-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let move_toml_path = dir.join("Move.toml").to_string_lossy().to_string();
+    std::fs::write(&move_toml_path, "[package]\nname = \"demo\"\n").unwrap();
+
+    let response =
+        api_request_with_params("prePublish", json!(["localnet", move_toml_path, "demo"])).await;
+    // info is "<package_uuid>,<package_timestamp>"
+    let info = response["result"]["info"].as_str().unwrap().to_string();
+    let (package_uuid, package_timestamp) = info.split_once(',').unwrap();
+
+    let package_id = "0x00000000000000000000000000000000000000000000000000000000000d7358";
+    let response = api_request_with_params(
+        "postPublish",
+        json!([
+            "localnet",
+            move_toml_path,
+            "demo",
+            package_uuid,
+            package_timestamp,
+            package_id
+        ]),
+    )
+    .await;
+    assert!(response["result"]["result"].as_bool().unwrap());
+
+    // The websocket worker should eventually subscribe to the package events.
+    let mut state = String::new();
+    for _ in 0..30 {
+        let response = api_request_with_params("getWorkdirPackages", json!(["localnet"])).await;
+        log::info!("response_body: {}", response);
+        let packages = response["result"]["packages"].as_array().unwrap();
+        let package = packages
+            .iter()
+            .find(|package| package["uuid"].as_str() == Some(package_uuid))
+            .expect("published package not listed");
+        assert_eq!(package["name"].as_str().unwrap(), "demo");
+        assert_eq!(
+            package["packageId"].as_str().unwrap(),
+            package_id.trim_start_matches("0x")
+        );
+        assert_eq!(
+            package["packageTimestamp"].as_str().unwrap(),
+            package_timestamp
+        );
+        assert_eq!(package["tomlPath"].as_str().unwrap(), move_toml_path);
+        state = package["subscriptionState"].as_str().unwrap().to_string();
+        if state == "Subscribed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    assert_eq!(state, "Subscribed");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -18,23 +18,67 @@
 // There is a one-to-one relationship between a Sui client address
 // and a DTP instance.
 //
+// A client address can manage multiple Hosts, each identified by a profile
+// name (e.g. dtp.get_host_for_profile("staging")). The methods without a
+// profile parameter are for the DEFAULT_PROFILE Host.
+//
 // Sui SDK and DTP SDK can co-exist and be used independently.
+//
+// Payloads are written on-chain. Call DTP::enable_encryption() on both ends
+// to have them sealed (see ConnectionInfo::encrypted).
+//
+// All the functions return a DTPError. Match the failure classes (e.g.
+// DTPError::InsufficientGas) for specific handling, or just use '?' into
+// an anyhow::Error (see DTPError::is_actionable).
+//
+// Every JSON-RPC call (including a transaction submission and the wait for its
+// effects) fails with DTPError::Timeout after the operation timeout (default 30
+// seconds, see DTP::set_operation_timeout).
+//
+// High-frequency senders can have their requests batched: many written by a
+// single transaction (see DTP::set_batch_config).
+//
+// A connection can detect a server that stopped (see DTP::set_keep_alive_config
+// and Connection::liveness).
+//
+// The services offered by a Host are listed by Host::services(). A connection can
+// be created with the name of a service (see DTP::create_connection_by_name).
+//
+// For a key kept offline (e.g. air-gapped signing), a transaction can be prepared
+// without signing (DTP::prepare_create_host, prepare_create_connection and
+// prepare_send), signed elsewhere and then executed with DTP::submit_signed.
+//
+// Cancellation: all the async methods can be dropped before completion (e.g. with
+// tokio::time::timeout or tokio::select!). The DTP instance remains usable, with
+// these caveats:
+//   - A transaction may still be executed by the network (as for a Submit timeout).
+//     The DTP state is reloaded from the network on next use, so a cancelled
+//     get_host() or register_host_name() can simply be called again.
+//   - Most methods have exclusive access to the DTP instance while executing.
+//     Cancelling one of them unblocks the other callers right away.
 
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
-use anyhow::bail;
 use dtp_core::{
     network::{
-        HostInternalMT, HostInternalST, NetworkManagerMT, NetworkManagerST,
+        send_request_batched, service_idx_by_name, start_keep_alive, ConnEncryption,
+        HostInternalMT, HostInternalST, NetworkManagerMT, NetworkManagerST, SubmittedInternal,
         TransportControlInternalMT,
     },
-    types::PingStats,
+    types::{PingStats, RpcStats},
 };
 
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
 
-// Re-export ConnObjectsInternal for debug purposes.
-pub use dtp_core::network::ConnObjectsInternal;
+// Kept for existing users. Use Connection::info() instead.
+#[deprecated(note = "use Connection::info() and ConnectionInfo")]
+pub type ConnObjectsInternal = dtp_core::network::ConnObjectsInternal;
+
+pub use dtp_core::network::ServiceInfo;
+pub use dtp_core::network::{sign_tx_bytes_offline, PreparedTransaction};
+pub use dtp_core::network::{BatchConfig, ConnCipher, ConnDirection, DEFAULT_PROFILE};
+pub use dtp_core::network::{KeepAliveConfig, Liveness, LivenessState, HEARTBEAT_CID};
+pub use dtp_core::types::{DTPError, TimeoutPhase, DEFAULT_OPERATION_TIMEOUT};
 
 #[derive(Debug, Clone)]
 pub struct Host {
@@ -59,6 +103,62 @@ impl Host {
     pub fn package_id(&self) -> &ObjectID {
         &self.package_id
     }
+
+    // Services offered by this Host (and the known ones it does not offer).
+    //
+    // As of when the Host was retrieved (call get_host_by_id again to refresh).
+    pub async fn services(&self) -> Vec<ServiceInfo> {
+        self.host_internal.read().await.services()
+    }
+}
+
+// Objects on the network for a connection (see Connection::info).
+//
+// These do not change for the lifetime of the connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub tc: ObjectID, // TransportControl
+    pub peer_host_id: ObjectID,
+    pub service_idx: u8, // Service type (e.g. 7 for ECHO).
+    pub cli_auth: SuiAddress,
+    pub srv_auth: SuiAddress,
+    pub cli_tx_pipe: ObjectID,
+    pub srv_tx_pipe: ObjectID,
+    pub cli_tx_ipipes: Vec<ObjectID>,
+    pub srv_tx_ipipes: Vec<ObjectID>,
+    pub encrypted: bool,
+    // Only one end enabled encryption, so the payloads are in plaintext.
+    pub plaintext_warning: bool,
+}
+
+// Cumulative traffic of a connection since it was created (see Connection::stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub requests_sent: u64,
+    pub bytes_sent: u64,
+    pub responses_received: u64,
+    pub bytes_received: u64,
+    pub txns_submitted: u64,
+    pub gas_spent: u64, // Mist
+    // Batching (see DTP::set_batch_config). Each flush is counted with its reason.
+    pub batches_submitted: u64,
+    pub batched_requests: u64,
+    pub flushes_on_delay: u64,
+    pub flushes_on_bytes: u64,
+    pub flushes_on_count: u64,
+    // Keep-alive (see DTP::set_keep_alive_config). The gas is also in gas_spent.
+    pub heartbeats_sent: u64,
+    pub heartbeat_gas_spent: u64, // Mist
+}
+
+impl ConnectionStats {
+    // Requests per batch transaction. Zero when nothing was batched.
+    pub fn avg_batch_size(&self) -> f64 {
+        if self.batches_submitted == 0 {
+            return 0.0;
+        }
+        self.batched_requests as f64 / self.batches_submitted as f64
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +168,87 @@ pub struct Connection {
 }
 
 impl Connection {
+    // Returns None until the connection is confirmed on the network.
+    pub async fn info(&self) -> Option<ConnectionInfo> {
+        let tc_guard = self.tc_internal.read().await;
+        let tc = &*tc_guard;
+        let conn_objects = tc.get_conn_objects()?;
+        let encryption = tc.get_encryption();
+        Some(ConnectionInfo {
+            tc: conn_objects.tc,
+            peer_host_id: tc.get_srv_host_id(),
+            service_idx: tc.get_service_idx(),
+            cli_auth: conn_objects.cli_auth,
+            srv_auth: conn_objects.srv_auth,
+            cli_tx_pipe: conn_objects.cli_tx_pipe,
+            srv_tx_pipe: conn_objects.srv_tx_pipe,
+            cli_tx_ipipes: conn_objects.cli_tx_ipipes,
+            srv_tx_ipipes: conn_objects.srv_tx_ipipes,
+            encrypted: encryption == ConnEncryption::Encrypted,
+            plaintext_warning: encryption == ConnEncryption::PlaintextMixedMode,
+        })
+    }
+
+    pub async fn stats(&self) -> ConnectionStats {
+        let tc_guard = self.tc_internal.read().await;
+        let tc = &*tc_guard;
+        let stats = tc.get_stats();
+        ConnectionStats {
+            requests_sent: stats.requests_sent,
+            bytes_sent: stats.bytes_sent,
+            responses_received: stats.responses_received,
+            bytes_received: stats.bytes_received,
+            txns_submitted: stats.txns_submitted,
+            gas_spent: stats.gas_spent,
+            batches_submitted: stats.batches_submitted,
+            batched_requests: stats.batched_requests,
+            flushes_on_delay: stats.flushes_on_delay,
+            flushes_on_bytes: stats.flushes_on_bytes,
+            flushes_on_count: stats.flushes_on_count,
+            heartbeats_sent: stats.heartbeats_sent,
+            heartbeat_gas_spent: stats.heartbeat_gas_spent,
+        }
+    }
+
+    // When the server was last seen, and its state according to the KeepAliveConfig
+    // of the connection (always Alive without one).
+    pub async fn liveness(&self) -> Liveness {
+        let tc_guard = self.tc_internal.read().await;
+        let tc = &*tc_guard;
+        tc.liveness()
+    }
+
+    // For something received from the server outside of the SDK (e.g. a heartbeat
+    // response seen by an event subscription).
+    pub async fn report_peer_seen(&self) {
+        let mut tc_guard = self.tc_internal.write().await;
+        let tc = &mut *tc_guard;
+        tc.report_peer_seen();
+    }
+
+    // The responses are delivered outside of the SDK (e.g. by an event subscription
+    // of the dtp-daemon), so the receiver reports them here for the stats.
+    pub async fn report_response_received(&self, n_bytes: usize) {
+        let mut tc_guard = self.tc_internal.write().await;
+        let tc = &mut *tc_guard;
+        tc.report_response_received(n_bytes);
+    }
+
+    // Payload of a response as written on-chain by the server. Opened with the
+    // sequence number of the request when the connection is encrypted, otherwise
+    // returned as-is.
+    pub async fn open_response(
+        &self,
+        req_seq_num: u64,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, DTPError> {
+        let tc_guard = self.tc_internal.read().await;
+        let tc = &*tc_guard;
+        tc.open_response(req_seq_num, data)
+    }
+
+    #[deprecated(note = "use Connection::info()")]
+    #[allow(deprecated)]
     pub async fn get_conn_objects(&self) -> Option<ConnObjectsInternal> {
         let tc_guard = self.tc_internal.read().await;
         let tc = &*tc_guard;
@@ -81,6 +262,14 @@ impl Connection {
     }
 }
 
+// What DTP::submit_signed() created, depending on what was prepared.
+#[derive(Debug, Clone)]
+pub enum Submitted {
+    Host(Host),
+    Connection(Connection),
+    Request,
+}
+
 #[derive(Debug)]
 pub struct DTP {
     // Multi-thread safe implementation hidden in dtp-core.
@@ -91,7 +280,7 @@ impl DTP {
     pub async fn new(
         auth_address: SuiAddress,
         keystore_pathname: Option<&str>,
-    ) -> Result<Self, anyhow::Error> {
+    ) -> Result<Self, DTPError> {
         let netmgr = Arc::new(tokio::sync::RwLock::new(
             NetworkManagerST::new(auth_address, keystore_pathname).await?,
         ));
@@ -105,14 +294,14 @@ impl DTP {
     //   JSON-RPC: No
     //   Gas Cost: No
     // TODO Refactor into a DTP builder to avoid error prone need for caller to do "await".
-    pub async fn set_package_id(&mut self, package_id: ObjectID) {
+    pub async fn set_package_id(&self, package_id: ObjectID) {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
         netmgr.set_package_id(package_id);
     }
 
-    pub async fn set_gas_address(&mut self, gas_address: SuiAddress) {
+    pub async fn set_gas_address(&self, gas_address: SuiAddress) {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -122,13 +311,75 @@ impl DTP {
     // Mutators
     //   JSON-RPC: Sometimes
     //   Gas Cost: No
-    pub async fn add_rpc_url(&mut self, http_url: &str) -> Result<(), anyhow::Error> {
+    pub async fn add_rpc_url(&self, http_url: &str) -> Result<(), DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
         netmgr.add_rpc_url(http_url).await
     }
 
+    // Same as add_rpc_url, but lower 'priority' value are tried first.
+    //
+    // Multiple URLs are used for redundancy: on a transport error, the request is
+    // retried on the next URL (including transaction submission).
+    pub async fn add_rpc_url_with_priority(
+        &self,
+        http_url: &str,
+        priority: u8,
+    ) -> Result<(), DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.add_rpc_url_with_priority(http_url, priority).await
+    }
+
+    // Deadline of each operation toward the RPC URLs (DEFAULT_OPERATION_TIMEOUT
+    // when not set). Applies to the calls started afterward.
+    pub async fn set_operation_timeout(&self, timeout: Duration) {
+        let netmgr_guard = self.netmgr.read().await;
+        let netmgr = &*netmgr_guard;
+
+        netmgr.set_operation_timeout(timeout);
+    }
+
+    // Batching of the requests for the connections created afterward (None, the
+    // default, sends each request in its own transaction).
+    //
+    // Concurrent send_request() on a connection are then written together, for
+    // less gas and transactions. Each one still returns only once its request
+    // is confirmed. A failed batch fails all its requests with DTPError::BatchFailed.
+    pub async fn set_batch_config(&self, batch_config: Option<BatchConfig>) {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.set_batch_config(batch_config);
+    }
+
+    // Keep-alive of the connections created afterward (None, the default, writes no
+    // heartbeat).
+    //
+    // An idle connection then writes a heartbeat every heartbeat_interval_ms (paid
+    // by the gas address, up to gas_budget per connection), and its server becomes
+    // Suspect then Dead once not seen for the thresholds (see Connection::liveness).
+    pub async fn set_keep_alive_config(&self, keep_alive_config: Option<KeepAliveConfig>) {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.set_keep_alive_config(keep_alive_config);
+    }
+
+    // Check the services of the target Host (see Host::services) before opening a
+    // connection. Disabled by default (the network does the validation).
+    //
+    // When enabled, create_connection() to a service not offered fails with
+    // DTPError::ServiceUnavailable, without a transaction.
+    pub async fn set_service_validation(&self, enabled: bool) {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.set_service_validation(enabled);
+    }
+
     // Accessors
     //   JSON-RPC: No
     //   Gas Cost: No
@@ -154,10 +405,30 @@ impl DTP {
     }
 
     pub async fn localhost_id(&self) -> Option<ObjectID> {
-        let netmgr_guard = self.netmgr.write().await;
+        self.localhost_id_for_profile(DEFAULT_PROFILE).await
+    }
+
+    pub async fn localhost_id_for_profile(&self, profile: &str) -> Option<ObjectID> {
+        let netmgr_guard = self.netmgr.read().await;
         let netmgr = &*netmgr_guard;
 
-        *netmgr.get_localhost_id()
+        netmgr.get_localhost_id(profile)
+    }
+
+    // The profiles created with this client address (DEFAULT_PROFILE first).
+    pub async fn profiles(&self) -> Vec<String> {
+        let netmgr_guard = self.netmgr.read().await;
+        let netmgr = &*netmgr_guard;
+
+        netmgr.get_profiles()
+    }
+
+    // Health of each RPC URL and which one served the most recent operations.
+    pub async fn rpc_stats(&self) -> RpcStats {
+        let netmgr_guard = self.netmgr.read().await;
+        let netmgr = &*netmgr_guard;
+
+        netmgr.rpc_stats()
     }
 
     // get_host
@@ -170,32 +441,43 @@ impl DTP {
     // (can setup firewall, enable services etc...)
     //
     // If the host does not exists, it will be tentatively created on the network.
-    pub async fn get_host(&mut self) -> Result<Host, anyhow::Error> {
+    pub async fn get_host(&self) -> Result<Host, DTPError> {
+        self.get_host_for_profile(DEFAULT_PROFILE).await
+    }
+
+    // get_host_for_profile
+    //   JSON-RPC: Yes
+    //   Gas Cost: Yes
+    //
+    // Same as get_host(), but for the Host of 'profile'.
+    //
+    // On first use of a profile (other than DEFAULT_PROFILE), a key is added to the
+    // keystore for it, funded from the auth address (see create_host_on_network_for_profile).
+    pub async fn get_host_for_profile(&self, profile: &str) -> Result<Host, DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
         // Note: the netmgr do also cache the LocalhostInternal. Can it be used?
         // For now, always retrieve latest from network.
-        let mut host_internal: Option<HostInternalST> = None;
-        if netmgr.get_localhost_id().is_none() {
-            // Best-effort find among owned object of auth.
-            let result = netmgr.get_localhost_by_auth().await?;
-            if let Some(result) = result {
-                host_internal = Some(result);
-            }
-        } else {
-            // Get latest from likely existing Host object on the network.
-            let localhost_id = netmgr.get_localhost_id().unwrap();
-            host_internal = netmgr.get_host_by_id(localhost_id).await?;
-        }
+        let mut host_internal: Option<HostInternalST> = netmgr.get_localhost(profile).await?;
         if host_internal.is_none() {
             // Create a new Host object on the network.
-            host_internal = Some(netmgr.create_localhost_on_network().await?);
+            host_internal = Some(netmgr.create_localhost_on_network(profile).await?);
         }
         // Should exist at this point.
-        let host_internal = host_internal.unwrap();
+        let mut host_internal = host_internal.unwrap();
 
-        netmgr.sync_registry().await?;
+        netmgr.sync_registry(profile).await?;
+
+        // When another DTP instance of the same address created its Host concurrently,
+        // the one in the registry wins (see LocalhostProfile::sync_registry).
+        if let Some(localhost_id) = netmgr.get_localhost_id(profile) {
+            if localhost_id != host_internal.object_id() {
+                if let Some(registry_host) = netmgr.get_host_by_id(localhost_id).await? {
+                    host_internal = registry_host;
+                }
+            }
+        }
 
         Ok(Host {
             id: host_internal.object_id(),
@@ -214,7 +496,7 @@ impl DTP {
     // RPC calls and/or create connections to it.
     //
     // Returns Ok(None) if confirmed that the host does not exists.
-    pub async fn get_host_by_id(&self, host_id: ObjectID) -> Result<Option<Host>, anyhow::Error> {
+    pub async fn get_host_by_id(&self, host_id: ObjectID) -> Result<Option<Host>, DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -230,6 +512,54 @@ impl DTP {
         }))
     }
 
+    // register_host_name
+    //   JSON-RPC: Yes
+    //   Gas Cost: Yes
+    //
+    // Register a human-readable name for the Host of the auth address, so that
+    // peers can find it with resolve_host() instead of its ObjectID.
+    //
+    // Names are unique per DTP package. Fails with DTPHostNameAlreadyRegistered
+    // if the name is used by another Host (succeed if already registered to this Host).
+    //
+    // Requires a DTP package with host names (see HostNameRegistryInternal). Fails with
+    // DTPHostNameRegistryNotFound or DTPMoveFunctionNotFound with an older package.
+    pub async fn register_host_name(&self, name: &str) -> Result<(), DTPError> {
+        self.register_host_name_for_profile(DEFAULT_PROFILE, name)
+            .await
+    }
+
+    pub async fn register_host_name_for_profile(
+        &self,
+        profile: &str,
+        name: &str,
+    ) -> Result<(), DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.register_host_name(profile, name).await
+    }
+
+    // resolve_host
+    //   JSON-RPC: Yes
+    //   Gas Cost: No
+    //
+    // Get an handle of the Host registered with 'name' (see register_host_name).
+    //
+    // Returns Ok(None) if confirmed that the name is not registered.
+    pub async fn resolve_host(&self, name: &str) -> Result<Option<Host>, DTPError> {
+        let host_id = {
+            let mut netmgr_guard = self.netmgr.write().await;
+            let netmgr = &mut *netmgr_guard;
+
+            netmgr.get_host_id_by_name(name).await?
+        };
+        match host_id {
+            Some(host_id) => self.get_host_by_id(host_id).await,
+            None => Ok(None),
+        }
+    }
+
     // create_host_on_network
     //
     //   JSON-RPC: Yes
@@ -246,13 +576,26 @@ impl DTP {
     // the creator of the DTP Host object has such capability.
     //
     // Take note that a client address support at most one Host object
-    // and attempts to create more should fail.
+    // and attempts to create more should fail. Use a profile to have more.
+    //
+    pub async fn create_host_on_network(&self) -> Result<Host, DTPError> {
+        self.create_host_on_network_for_profile(DEFAULT_PROFILE)
+            .await
+    }
+
+    // Same as create_host_on_network(), but for the Host of 'profile'.
     //
-    pub async fn create_host_on_network(&mut self) -> Result<Host, anyhow::Error> {
+    // The Host of a profile other than DEFAULT_PROFILE is owned by an address
+    // derived for it: a key added to the keystore on first use (recorded next to
+    // the keystore), funded from the auth address for its gas.
+    pub async fn create_host_on_network_for_profile(
+        &self,
+        profile: &str,
+    ) -> Result<Host, DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
-        let host_internal = netmgr.create_localhost_on_network().await?;
+        let host_internal = netmgr.create_localhost_on_network(profile).await?;
         Ok(Host {
             id: host_internal.object_id(),
             package_id: *netmgr.get_package_id(),
@@ -260,15 +603,47 @@ impl DTP {
         })
     }
 
+    // enable_encryption
+    //   JSON-RPC: Yes
+    //   Gas Cost: Yes (once per Host)
+    //
+    // Advertise the encryption key of this client address on its Host. The
+    // connections created afterward with a Host that did the same are encrypted.
+    //
+    // The key is generated on first use and stored next to the keystore.
+    pub async fn enable_encryption(&self) -> Result<(), DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.enable_encryption().await
+    }
+
+    // conn_cipher
+    //   JSON-RPC: Yes
+    //   Gas Cost: No
+    //
+    // For the server end of a connection created by 'peer_host': the cipher to
+    // open its requests (ConnDirection::CliToSrv) and seal the responses
+    // (ConnDirection::SrvToCli), both with the sequence number of the request.
+    //
+    // Returns Ok(None) when the connection is not encrypted.
+    pub async fn conn_cipher(
+        &self,
+        peer_host: &Host,
+        tc: ObjectID,
+    ) -> Result<Option<ConnCipher>, DTPError> {
+        let netmgr_guard = self.netmgr.read().await;
+        let netmgr = &*netmgr_guard;
+
+        netmgr.get_conn_cipher(*peer_host.object_id(), tc).await
+    }
+
     // Ping Service
     //   JSON-RPC: Yes
     //   Gas Cost: Yes
     //
     // Note: This util fn not yet implemented. For now, use create_connection()/send()
-    pub async fn ping_on_network(
-        &mut self,
-        target_host: &Host,
-    ) -> Result<PingStats, anyhow::Error> {
+    pub async fn ping_on_network(&self, target_host: &Host) -> Result<PingStats, DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -285,32 +660,57 @@ impl DTP {
     //
     // Use target Host object and ServiceType.
     pub async fn create_connection(
-        &mut self,
+        &self,
         target_host: &Host,
         service_idx: u8,
-    ) -> Result<Connection, anyhow::Error> {
+    ) -> Result<Connection, DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
         let target_host_guard = target_host.host_internal.read().await;
         let target_host_internal = &*target_host_guard;
 
-        Ok(Connection {
-            tc_internal: netmgr
-                .create_connection(target_host_internal, service_idx)
-                .await?,
-        })
+        let tc_internal = netmgr
+            .create_connection(target_host_internal, service_idx)
+            .await?;
+        if let Some(keep_alive_config) = netmgr.get_keep_alive_config() {
+            start_keep_alive(&self.netmgr, &tc_internal, keep_alive_config);
+        }
+        Ok(Connection { tc_internal })
+    }
+
+    // Same as create_connection(), with the short name of the service (e.g. "ping"
+    // or "json-rpc", see ServiceInfo::name).
+    //
+    // Always checks the services of the target Host (see set_service_validation).
+    pub async fn create_connection_by_name(
+        &self,
+        target_host: &Host,
+        service_name: &str,
+    ) -> Result<Connection, DTPError> {
+        let service_idx = service_idx_by_name(service_name).ok_or_else(|| DTPError::Config {
+            msg: format!("unknown service name {:?}", service_name),
+        })?;
+        target_host
+            .host_internal
+            .read()
+            .await
+            .check_service(service_idx)?;
+        self.create_connection(target_host, service_idx).await
     }
 
     // Send data into a connection.
     //   JSON-RPC: Yes
     //   Gas Cost: Yes
     //
-    pub async fn send_request(
-        &mut self,
-        conn: &mut Connection,
-        data: Vec<u8>,
-    ) -> Result<(), anyhow::Error> {
+    // Batched when the connection was created with a BatchConfig (the requests
+    // of a connection are written in the order of the calls).
+    pub async fn send_request(&self, conn: &mut Connection, data: Vec<u8>) -> Result<(), DTPError> {
+        let batched = conn.tc_internal.read().await.get_batch_config().is_some();
+        if batched {
+            return send_request_batched(&self.netmgr, &conn.tc_internal, data).await;
+        }
+
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -320,6 +720,83 @@ impl DTP {
         netmgr.send_request(conn, data).await
     }
 
+    // Offline preparation (for a key that is not in the keystore of this DTP).
+    //   JSON-RPC: Yes
+    //   Gas Cost: No
+    //
+    // Same as create_host_on_network(), create_connection() and send_request(), but
+    // the transaction is only built (see PreparedTransaction). Sign its tx_bytes with
+    // the key of the client address (e.g. sign_tx_bytes_offline on the air-gapped
+    // machine), then call submit_signed().
+    //
+    // A prepared send is always in its own transaction (not batched).
+    pub async fn prepare_create_host(&self) -> Result<PreparedTransaction, DTPError> {
+        let netmgr_guard = self.netmgr.read().await;
+        let netmgr = &*netmgr_guard;
+
+        netmgr.prepare_create_localhost().await
+    }
+
+    pub async fn prepare_create_connection(
+        &self,
+        target_host: &Host,
+        service_idx: u8,
+    ) -> Result<PreparedTransaction, DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        let target_host_guard = target_host.host_internal.read().await;
+        let target_host_internal = &*target_host_guard;
+
+        netmgr
+            .prepare_create_connection(target_host_internal, service_idx)
+            .await
+    }
+
+    pub async fn prepare_send(
+        &self,
+        conn: &Connection,
+        data: Vec<u8>,
+    ) -> Result<PreparedTransaction, DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        netmgr.prepare_send_request(&conn.tc_internal, data).await
+    }
+
+    // Execute a prepared transaction.
+    //   JSON-RPC: Yes
+    //   Gas Cost: Yes
+    //
+    // 'signature' is the serialized Sui signature (flag || signature || public key).
+    // Nothing is broadcast when it is not for the prepared tx_bytes by the client
+    // address (DTPError::NotAuthorized, can be submitted again with the right one),
+    // or when the transaction became stale (DTPError::StaleObjectVersion, prepare
+    // it again). A transaction executed once is stale afterward.
+    pub async fn submit_signed(
+        &self,
+        prepared: &PreparedTransaction,
+        signature: &[u8],
+    ) -> Result<Submitted, DTPError> {
+        let mut netmgr_guard = self.netmgr.write().await;
+        let netmgr = &mut *netmgr_guard;
+
+        match netmgr.submit_signed(prepared, signature).await? {
+            SubmittedInternal::Host(host_internal) => Ok(Submitted::Host(Host {
+                id: host_internal.object_id(),
+                package_id: *netmgr.get_package_id(),
+                host_internal: Arc::new(tokio::sync::RwLock::new(host_internal)),
+            })),
+            SubmittedInternal::Connection(tc_internal) => {
+                if let Some(keep_alive_config) = netmgr.get_keep_alive_config() {
+                    start_keep_alive(&self.netmgr, &tc_internal, keep_alive_config);
+                }
+                Ok(Submitted::Connection(Connection { tc_internal }))
+            }
+            SubmittedInternal::Request => Ok(Submitted::Request),
+        }
+    }
+
     // Send a Datagram on an existing connection.
     //   JSON-RPC: Yes
     //   Gas Cost: Yes
@@ -329,13 +806,13 @@ impl DTP {
     //
     // Note: Early implementation. This will be simplified eventually.
     pub async fn low_level_send_response(
-        &mut self,
+        &self,
         resp_ipipe_address: SuiAddress,
         req_ipipe_idx: u8,
         req_seq_num: u64,
         data: Vec<u8>,
         cid: u64,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<(), DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -349,7 +826,7 @@ impl DTP {
     //   Gas Cost: Yes
     //
     // The firewall will be configurable from this point, but not yet enabled.
-    pub async fn init_firewall(&mut self) -> Result<(), anyhow::Error> {
+    pub async fn init_firewall(&self) -> Result<(), DTPError> {
         let mut netmgr_guard = self.netmgr.write().await;
         let netmgr = &mut *netmgr_guard;
 
@@ -358,7 +835,7 @@ impl DTP {
 }
 
 // Utility functions.
-pub fn str_to_sui_address(address: &str) -> Result<SuiAddress, anyhow::Error> {
+pub fn str_to_sui_address(address: &str) -> Result<SuiAddress, DTPError> {
     // If address does not start with "0x", append it to address.
     // Can you please code this?
     let address = if address.starts_with("0x") {
@@ -369,12 +846,14 @@ pub fn str_to_sui_address(address: &str) -> Result<SuiAddress, anyhow::Error> {
 
     let ret_value = SuiAddress::from_str(&address);
     if let Err(e) = ret_value {
-        bail!("address invalid: {} {}", address, e.to_string())
+        return Err(DTPError::Config {
+            msg: format!("address invalid: {} {}", address, e),
+        });
     }
     Ok(ret_value.unwrap())
 }
 
-pub fn str_to_object_id(object_id: &str) -> Result<ObjectID, anyhow::Error> {
+pub fn str_to_object_id(object_id: &str) -> Result<ObjectID, DTPError> {
     // If object_id does not start with "0x", append it to object_id.
     let object_id = if object_id.starts_with("0x") {
         object_id.to_string()
@@ -384,7 +863,9 @@ pub fn str_to_object_id(object_id: &str) -> Result<ObjectID, anyhow::Error> {
 
     let ret_value = ObjectID::from_str(&object_id);
     if let Err(e) = ret_value {
-        bail!("object id invalid: {} {}", object_id, e.to_string())
+        return Err(DTPError::Config {
+            msg: format!("object id invalid: {} {}", object_id, e),
+        });
     }
     Ok(ret_value.unwrap())
 }
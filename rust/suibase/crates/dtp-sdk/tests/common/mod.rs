@@ -0,0 +1,34 @@
+// Helpers shared by the integration tests requiring a running localnet with the DTP
+// package published.
+use dtp_sdk::DTP;
+use sui_sdk::types::base_types::ObjectID;
+
+pub const LOCALNET_PROXY_URL: &str = "http://localhost:44340";
+
+pub fn localnet_path(sub_path: &str) -> String {
+    let home = home::home_dir().unwrap();
+    format!(
+        "{}/suibase/workdirs/localnet/{}",
+        home.to_string_lossy(),
+        sub_path
+    )
+}
+
+pub fn dtp_package_id() -> ObjectID {
+    let path = localnet_path("published-data/dtp/most-recent/package-id.json");
+    let contents = std::fs::read_to_string(&path).expect("DTP package not published");
+    // Example of content: ["0x6f36...8feb"]
+    let ids: Vec<String> = serde_json::from_str(&contents).unwrap();
+    dtp_sdk::str_to_object_id(&ids[0]).unwrap()
+}
+
+pub async fn new_dtp(env_var: &str) -> Result<DTP, anyhow::Error> {
+    let address = std::env::var(env_var).unwrap_or_else(|_| panic!("{} not set", env_var));
+    let address = dtp_sdk::str_to_sui_address(&address)?;
+    let keystore = localnet_path("config/sui.keystore");
+    let dtp = DTP::new(address, Some(&keystore)).await?;
+    dtp.add_rpc_url(LOCALNET_PROXY_URL).await?;
+    dtp.set_package_id(dtp_package_id()).await;
+    dtp.set_gas_address(address).await;
+    Ok(dtp)
+}
@@ -0,0 +1,45 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_create_connection_without_object_reads() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let reads = |stats: &dtp_core::types::RpcStats| {
+        stats
+            .op_counts
+            .get("get_object_with_options")
+            .copied()
+            .unwrap_or(0)
+    };
+    let before = client.rpc_stats().await;
+
+    let conn = client.create_connection(&target_host, 7).await?;
+    assert!(conn.info().await.is_some());
+
+    // Everything needed was in the transaction effects.
+    let after = client.rpc_stats().await;
+    assert_eq!(reads(&after), reads(&before));
+    assert_eq!(
+        after.op_counts.get("get_transaction_with_options"),
+        before.op_counts.get("get_transaction_with_options")
+    );
+    let confirmation = after.confirmations.last().unwrap();
+    assert_eq!(confirmation.op, "api::open_connection");
+    assert!(!confirmation.polled);
+    Ok(())
+}
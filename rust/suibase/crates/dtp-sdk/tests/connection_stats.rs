@@ -0,0 +1,108 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_sdk::{BatchConfig, ConnectionStats};
+use sui_sdk::types::base_types::SuiAddress;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_connection_info_and_stats() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let mut conn = client.create_connection(&target_host, 7).await?;
+    let info = conn.info().await.expect("connection not confirmed");
+    assert_eq!(info.peer_host_id, *server_host.object_id());
+    assert_eq!(info.service_idx, 7);
+    assert_eq!(info.cli_auth, client.client_address().await);
+    assert_eq!(info.srv_auth, server.client_address().await);
+    assert!(!info.cli_tx_ipipes.is_empty());
+    assert!(!info.srv_tx_ipipes.is_empty());
+    assert_eq!(conn.stats().await, ConnectionStats::default());
+
+    // Request from the client.
+    let request = b"ping from the client".to_vec();
+    client.send_request(&mut conn, request.clone()).await?;
+
+    // Response from the server. Delivered to the client by an event subscription
+    // outside of the SDK (e.g. the dtp-daemon), which reports it on the connection.
+    let response = b"pong".to_vec();
+    let resp_ipipe = SuiAddress::from(info.srv_tx_ipipes[0]);
+    server
+        .low_level_send_response(resp_ipipe, 0, 0, response.clone(), 1)
+        .await?;
+    conn.report_response_received(response.len()).await;
+
+    let stats = conn.stats().await;
+    assert_eq!(stats.requests_sent, 1);
+    assert_eq!(stats.bytes_sent, request.len() as u64);
+    assert_eq!(stats.responses_received, 1);
+    assert_eq!(stats.bytes_received, response.len() as u64);
+    assert_eq!(stats.txns_submitted, 1);
+    assert!(stats.gas_spent > 0);
+
+    // Shared by the clones of a Connection.
+    assert_eq!(conn.clone().stats().await, stats);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_batched_requests() -> Result<(), anyhow::Error> {
+    const N_REQUESTS: usize = 50;
+
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    client
+        .set_batch_config(Some(BatchConfig {
+            max_batch_delay_ms: 200,
+            ..Default::default()
+        }))
+        .await;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+    let conn = client.create_connection(&target_host, 7).await?;
+
+    // Small requests sent concurrently, each returns once confirmed.
+    let sends = (0..N_REQUESTS).map(|i| {
+        let mut conn = conn.clone();
+        let client = &client;
+        async move {
+            client
+                .send_request(&mut conn, format!("req {}", i).into_bytes())
+                .await
+        }
+    });
+    for result in futures::future::join_all(sends).await {
+        result?;
+    }
+
+    let stats = conn.stats().await;
+    assert_eq!(stats.requests_sent, N_REQUESTS as u64);
+    assert_eq!(stats.batched_requests, N_REQUESTS as u64);
+    assert_eq!(stats.txns_submitted, stats.batches_submitted);
+    assert!(stats.txns_submitted <= (N_REQUESTS / 5) as u64);
+    assert!(stats.avg_batch_size() >= 5.0);
+    assert_eq!(
+        stats.flushes_on_delay + stats.flushes_on_bytes + stats.flushes_on_count,
+        stats.batches_submitted
+    );
+    assert!(stats.gas_spent > 0);
+    Ok(())
+}
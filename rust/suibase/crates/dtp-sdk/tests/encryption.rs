@@ -0,0 +1,54 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_sdk::ConnDirection;
+use sui_sdk::types::base_types::SuiAddress;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_encrypted_connection() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+    server.enable_encryption().await?;
+    // Idempotent (no transaction when already advertised).
+    server.enable_encryption().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let client_host = client.get_host().await?;
+    client.enable_encryption().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let mut conn = client.create_connection(&target_host, 7).await?;
+    let info = conn.info().await.expect("connection not confirmed");
+    assert!(info.encrypted);
+    assert!(!info.plaintext_warning);
+
+    // The stats are for the payload of the user, not the sealed one.
+    let request = b"secret from the client".to_vec();
+    client.send_request(&mut conn, request.clone()).await?;
+    assert_eq!(conn.stats().await.bytes_sent, request.len() as u64);
+
+    // Server end: same key derived from its own secret and the client Host.
+    let cipher = server
+        .conn_cipher(&client_host, info.tc)
+        .await?
+        .expect("connection not encrypted at the server end");
+    let response = b"secret from the server".to_vec();
+    let sealed = cipher.seal(ConnDirection::SrvToCli, 1, &response)?;
+    assert!(!sealed.windows(response.len()).any(|w| w == response));
+
+    let resp_ipipe = SuiAddress::from(info.srv_tx_ipipes[0]);
+    server
+        .low_level_send_response(resp_ipipe, 0, 1, sealed.clone(), 1)
+        .await?;
+    assert_eq!(conn.open_response(1, sealed.clone()).await?, response);
+    assert!(conn.open_response(2, sealed).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// Failures detected without a Sui network (nothing listening on BOGUS_URL).
+//
+// The errors are matched on the DTPError variant, not on the message.
+use dtp_sdk::{DTPError, DTP};
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+
+const BOGUS_URL: &str = "http://localhost:1";
+
+#[tokio::test]
+async fn test_no_rpc_url_is_config_error() -> Result<(), anyhow::Error> {
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    let err = dtp.get_host_by_id(ObjectID::ZERO).await.unwrap_err();
+    assert!(matches!(err, DTPError::Config { .. }));
+    assert!(err.is_actionable());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unreachable_rpc_is_transport_error() -> Result<(), anyhow::Error> {
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    dtp.add_rpc_url(BOGUS_URL).await?;
+
+    let err = dtp.get_host_by_id(ObjectID::ZERO).await.unwrap_err();
+    assert!(matches!(err, DTPError::RpcTransport { ref url, .. } if url == BOGUS_URL));
+
+    // Still a DTPError after going through anyhow.
+    let err: anyhow::Error = err.into();
+    assert!(matches!(
+        err.downcast_ref::<DTPError>(),
+        Some(DTPError::RpcTransport { .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_input_errors() -> Result<(), anyhow::Error> {
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    assert!(matches!(
+        dtp.resolve_host("not a name").await.unwrap_err(),
+        DTPError::DTPHostNameInvalid { .. }
+    ));
+    assert!(matches!(
+        dtp_sdk::str_to_object_id("0xnot-hex").unwrap_err(),
+        DTPError::Config { .. }
+    ));
+    Ok(())
+}
@@ -0,0 +1,34 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_core::types::DTPError;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_register_and_resolve_host_name() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    // Unique per test run (the registry lives as long as the package).
+    let name = format!("test-host-{}", chrono::Utc::now().timestamp_millis());
+    server.register_host_name(&name).await?;
+    // Registering again for the same host is fine.
+    server.register_host_name(&name).await?;
+
+    // Resolve from another DTP instance (another client address).
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let resolved = client.resolve_host(&name).await?.expect("name not found");
+    assert_eq!(resolved.object_id(), server_host.object_id());
+
+    assert!(client.resolve_host("test-host-unknown").await?.is_none());
+
+    // Same name for another host is a collision.
+    let _ = client.get_host().await?;
+    let err = client.register_host_name(&name).await.unwrap_err();
+    assert!(matches!(err, DTPError::DTPHostNameAlreadyRegistered { .. }));
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+//
+// The server side is emulated by a task answering with heartbeat responses (as the
+// dtp-daemon does), then killed.
+mod common;
+
+use std::time::Duration;
+
+use common::new_dtp;
+use dtp_sdk::{KeepAliveConfig, LivenessState, HEARTBEAT_CID};
+use sui_sdk::types::base_types::SuiAddress;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_peer_becomes_dead() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let config = KeepAliveConfig {
+        heartbeat_interval_ms: 1000,
+        suspect_after_ms: 4000,
+        dead_after_ms: 8000,
+        gas_budget: 1_000_000_000,
+    };
+    client.set_keep_alive_config(Some(config)).await;
+    let conn = client.create_connection(&target_host, 7).await?;
+    let info = conn.info().await.expect("connection not confirmed");
+
+    // The server peer, writing a heartbeat response every interval.
+    let resp_ipipe = SuiAddress::from(info.srv_tx_ipipes[0]);
+    let peer = tokio::spawn(async move {
+        loop {
+            let result = server
+                .low_level_send_response(resp_ipipe, 0, 0, Vec::new(), HEARTBEAT_CID)
+                .await;
+            assert!(result.is_ok(), "{:?}", result);
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+        }
+    });
+
+    // Seen alive for longer than the thresholds.
+    tokio::time::sleep(Duration::from_millis(10_000)).await;
+    assert_eq!(conn.liveness().await.state, LivenessState::Alive);
+    assert!(conn.stats().await.heartbeats_sent > 0);
+
+    // Killed: Dead within the threshold (plus a poll interval and its latency).
+    peer.abort();
+    let killed_at = std::time::SystemTime::now();
+    let mut state = LivenessState::Alive;
+    for _ in 0..60 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        state = conn.liveness().await.state;
+        if state == LivenessState::Dead {
+            break;
+        }
+    }
+    assert_eq!(state, LivenessState::Dead);
+    let liveness = conn.liveness().await;
+    let unseen = liveness.last_seen.elapsed()?;
+    assert!(unseen >= Duration::from_millis(config.dead_after_ms));
+    assert!(killed_at.elapsed()? <= Duration::from_millis(config.dead_after_ms + 3000));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_sdk::DEFAULT_PROFILE;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_localhost_profiles() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let default_host = server.get_host().await?;
+
+    // Unique per test run (a profile is never deleted from the keystore).
+    let ts = chrono::Utc::now().timestamp_millis();
+    let staging = format!("staging-{}", ts);
+    let prod = format!("prod-{}", ts);
+    let staging_host = server.get_host_for_profile(&staging).await?;
+    let prod_host = server.get_host_for_profile(&prod).await?;
+
+    // One Host per profile.
+    assert_ne!(staging_host.object_id(), prod_host.object_id());
+    assert_ne!(staging_host.object_id(), default_host.object_id());
+    assert_ne!(prod_host.object_id(), default_host.object_id());
+    assert_eq!(
+        server.localhost_id_for_profile(&staging).await,
+        Some(*staging_host.object_id())
+    );
+    let profiles = server.profiles().await;
+    assert_eq!(profiles[0], DEFAULT_PROFILE);
+    assert!(profiles.contains(&staging) && profiles.contains(&prod));
+
+    // Getting it again does not create another one.
+    let again = server.get_host_for_profile(&staging).await?;
+    assert_eq!(again.object_id(), staging_host.object_id());
+
+    // The mapping is persisted: found again by a new DTP instance.
+    let reloaded = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let again = reloaded.get_host_for_profile(&prod).await?;
+    assert_eq!(again.object_id(), prod_host.object_id());
+
+    // Both are independently reachable by another client address.
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    for host in [&staging_host, &prod_host] {
+        let target = client
+            .get_host_by_id(*host.object_id())
+            .await?
+            .expect("host not found");
+        let stats = client.ping_on_network(&target).await?;
+        assert_eq!(stats.ping_count_attempted, 1);
+    }
+
+    assert!(server.get_host_for_profile("not.valid").await.is_err());
+    Ok(())
+}
@@ -0,0 +1,49 @@
+// Integration tests requiring a running localnet (with the suibase proxy).
+//
+// Run with:
+//    cargo test -p dtp-sdk -- --ignored
+use dtp_sdk::DTP;
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+
+const LOCALNET_PROXY_URL: &str = "http://localhost:44340";
+
+// Nothing should be listening on that port.
+const BOGUS_URL: &str = "http://localhost:1";
+
+#[tokio::test]
+#[ignore = "requires a running localnet"]
+async fn test_bogus_first_url_fails_over() -> Result<(), anyhow::Error> {
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    dtp.add_rpc_url(BOGUS_URL).await?;
+    dtp.add_rpc_url(LOCALNET_PROXY_URL).await?;
+
+    // Confirmed to not exist (not an error), served by the second URL.
+    let host = dtp.get_host_by_id(ObjectID::ZERO).await?;
+    assert!(host.is_none());
+
+    let stats = dtp.rpc_stats().await;
+    assert_eq!(stats.nodes.len(), 2);
+    assert!(stats.nodes[0].is_down);
+    assert!(!stats.nodes[1].is_down);
+    let served_by = stats.history.last().unwrap();
+    assert_eq!(served_by.url, LOCALNET_PROXY_URL);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires a running localnet"]
+async fn test_priority_overrides_insertion_order() -> Result<(), anyhow::Error> {
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    dtp.add_rpc_url_with_priority(LOCALNET_PROXY_URL, 10)
+        .await?;
+    dtp.add_rpc_url_with_priority(BOGUS_URL, 20).await?;
+
+    let host = dtp.get_host_by_id(ObjectID::ZERO).await?;
+    assert!(host.is_none());
+
+    let stats = dtp.rpc_stats().await;
+    let served_by = stats.history.last().unwrap();
+    assert_eq!(served_by.url, LOCALNET_PROXY_URL);
+    assert_eq!(served_by.failover_count, 0);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+//
+// The prepared transactions are signed out-of-band (as on an air-gapped machine),
+// with the keystore file but without the DTP instance.
+mod common;
+
+use common::{localnet_path, new_dtp};
+use dtp_sdk::{DTPError, Submitted};
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_prepare_and_submit_signed() -> Result<(), anyhow::Error> {
+    let keystore = localnet_path("config/sui.keystore");
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let prepared = client.prepare_create_connection(&target_host, 7).await?;
+    assert_eq!(prepared.signer(), client.client_address().await);
+    assert!(prepared.expected_gas() > 0);
+
+    // Signed by another address: rejected before broadcasting.
+    let server_address = server.client_address().await;
+    let signature =
+        dtp_sdk::sign_tx_bytes_offline(&keystore, &server_address, prepared.tx_bytes())?;
+    let err = client
+        .submit_signed(&prepared, &signature)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DTPError::NotAuthorized { .. }));
+
+    let signature =
+        dtp_sdk::sign_tx_bytes_offline(&keystore, &prepared.signer(), prepared.tx_bytes())?;
+    let conn = match client.submit_signed(&prepared, &signature).await? {
+        Submitted::Connection(conn) => conn,
+        other => panic!("unexpected {:?}", other),
+    };
+    let info = conn.info().await.expect("connection not confirmed");
+    assert_eq!(info.peer_host_id, *server_host.object_id());
+    let confirmation = client.rpc_stats().await.confirmations.pop().unwrap();
+    assert_eq!(confirmation.op, "api::open_connection");
+
+    // Executed once only.
+    let err = client
+        .submit_signed(&prepared, &signature)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DTPError::StaleObjectVersion { .. }));
+
+    // Both prepared with the same gas coin (and sequence number), so the second one
+    // is stale once the first is executed.
+    let first = client.prepare_send(&conn, b"first".to_vec()).await?;
+    let second = client.prepare_send(&conn, b"second".to_vec()).await?;
+    let signer = first.signer();
+    let first_signature = dtp_sdk::sign_tx_bytes_offline(&keystore, &signer, first.tx_bytes())?;
+    let second_signature = dtp_sdk::sign_tx_bytes_offline(&keystore, &signer, second.tx_bytes())?;
+    let result = client.submit_signed(&first, &first_signature).await;
+    assert!(matches!(result, Ok(Submitted::Request)));
+    let result = client.submit_signed(&second, &second_signature).await;
+    assert!(matches!(result, Err(DTPError::StaleObjectVersion { .. })));
+    let stats = conn.stats().await;
+    assert_eq!(stats.requests_sent, 1);
+    assert_eq!(stats.bytes_sent, 5);
+    assert!(stats.gas_spent > 0);
+
+    // The one-shot send is on the same sequence (not desynchronized by the stale one).
+    let mut conn = conn;
+    client.send_request(&mut conn, b"one-shot".to_vec()).await?;
+    assert_eq!(conn.stats().await.requests_sent, 2);
+    Ok(())
+}
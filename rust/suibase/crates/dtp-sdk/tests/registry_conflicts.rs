@@ -0,0 +1,65 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_sdk::DTPError;
+
+// DTP instances of the same client address, all running concurrently.
+const CONCURRENT_INSTANCES: usize = 4;
+
+// The only acceptable failures under contention: the transaction lost a race for
+// the gas coins (the caller sends again) or the retries of a registry update ran out.
+fn is_contention(err: &DTPError) -> bool {
+    err.is_version_conflict() || matches!(err, DTPError::RegistryConflict { .. })
+}
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_concurrent_connection_creations() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host_id = *server.get_host().await?.object_id();
+
+    let mut clients = Vec::new();
+    for _ in 0..CONCURRENT_INSTANCES {
+        clients.push(new_dtp("DTP_TEST_ADDRESS_2").await?);
+    }
+
+    // Every instance ends up with the Host of the registry, even when created
+    // concurrently.
+    let hosts = futures::future::join_all(clients.iter().map(|client| client.get_host())).await;
+    let mut host_ids = Vec::new();
+    for host in hosts {
+        match host {
+            Ok(host) => host_ids.push(*host.object_id()),
+            Err(e) => assert!(is_contention(&e), "get_host failed: {}", e),
+        }
+    }
+    assert!(!host_ids.is_empty());
+    assert!(host_ids.iter().all(|host_id| *host_id == host_ids[0]));
+
+    // Then all open a connection to the same server at once.
+    let connections = futures::future::join_all(clients.iter().map(|client| async move {
+        let target_host = client
+            .get_host_by_id(server_host_id)
+            .await?
+            .expect("server host not found");
+        client.create_connection(&target_host, 7).await
+    }))
+    .await;
+    let mut created = 0;
+    for connection in connections {
+        match connection {
+            Ok(_) => created += 1,
+            Err(e) => assert!(is_contention(&e), "create_connection failed: {}", e),
+        }
+    }
+    assert!(created > 0);
+
+    // Nothing left half-done: a later sync agrees with the concurrent ones.
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    assert_eq!(*client.get_host().await?.object_id(), host_ids[0]);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+// Integration tests requiring a running localnet with the DTP package published.
+//
+// The two (distinct) client addresses must be in the localnet keystore, and the Host
+// of the first one must offer only the ping service:
+//    DTP_TEST_ADDRESS_1=0x... DTP_TEST_ADDRESS_2=0x... cargo test -p dtp-sdk -- --ignored
+mod common;
+
+use common::new_dtp;
+use dtp_sdk::DTPError;
+
+#[tokio::test]
+#[ignore = "requires a running localnet with the DTP package"]
+async fn test_unavailable_service_fails_fast() -> Result<(), anyhow::Error> {
+    let server = new_dtp("DTP_TEST_ADDRESS_1").await?;
+    let server_host = server.get_host().await?;
+
+    let client = new_dtp("DTP_TEST_ADDRESS_2").await?;
+    let _ = client.get_host().await?;
+    let target_host = client
+        .get_host_by_id(*server_host.object_id())
+        .await?
+        .expect("server host not found");
+
+    let services = target_host.services().await;
+    let enabled: Vec<&str> = services
+        .iter()
+        .filter(|service| service.enabled)
+        .map(|service| service.name.as_str())
+        .collect();
+    assert_eq!(enabled, vec!["ping"]);
+
+    // Rejected locally: no JSON-RPC call, so no transaction either.
+    let ops_before = client.rpc_stats().await.op_counts;
+    let err = client
+        .create_connection_by_name(&target_host, "json-rpc")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        DTPError::ServiceUnavailable { service_idx: 2, .. }
+    ));
+
+    client.set_service_validation(true).await;
+    let err = client.create_connection(&target_host, 2).await.unwrap_err();
+    assert!(matches!(err, DTPError::ServiceUnavailable { .. }));
+    assert_eq!(client.rpc_stats().await.op_counts, ops_before);
+
+    // The offered service still connects.
+    let conn = client
+        .create_connection_by_name(&target_host, "ping")
+        .await?;
+    assert_eq!(conn.info().await.map(|info| info.service_idx), Some(7));
+    Ok(())
+}
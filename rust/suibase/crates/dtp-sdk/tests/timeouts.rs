@@ -0,0 +1,78 @@
+// Timeout and cancellation of the network operations (no Sui network needed).
+//
+// The RPC "node" accepts the connections and then never answers.
+use std::time::Duration;
+
+use dtp_sdk::{DTPError, TimeoutPhase, DTP};
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use tokio::io::AsyncReadExt;
+
+const OPERATION_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Returns the URL of a stalling RPC server.
+async fn spawn_stalling_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            // Consume the requests, but keep the connection open forever.
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    url
+}
+
+async fn new_dtp_with_stalling_rpc() -> Result<(DTP, String), anyhow::Error> {
+    let url = spawn_stalling_server().await;
+    let dtp = DTP::new(SuiAddress::ZERO, None).await?;
+    dtp.set_operation_timeout(OPERATION_TIMEOUT).await;
+    dtp.add_rpc_url(&url).await?;
+    Ok((dtp, url))
+}
+
+#[tokio::test]
+async fn test_stalled_rpc_times_out() -> Result<(), anyhow::Error> {
+    let (dtp, _url) = new_dtp_with_stalling_rpc().await?;
+
+    let start = tokio::time::Instant::now();
+    let err = dtp.get_host_by_id(ObjectID::ZERO).await.unwrap_err();
+    assert!(start.elapsed() < OPERATION_TIMEOUT * 4);
+    assert!(matches!(
+        err,
+        DTPError::Timeout {
+            phase: TimeoutPhase::Read,
+            timeout_ms: 500,
+            ..
+        }
+    ));
+    assert!(err.is_actionable());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_usable_after_cancellation() -> Result<(), anyhow::Error> {
+    let (dtp, url) = new_dtp_with_stalling_rpc().await?;
+
+    // Cancelled by the caller before the operation timeout.
+    let cancelled =
+        tokio::time::timeout(OPERATION_TIMEOUT / 5, dtp.get_host_by_id(ObjectID::ZERO)).await;
+    assert!(cancelled.is_err());
+
+    // Not blocked by the cancelled call.
+    let stats = tokio::time::timeout(OPERATION_TIMEOUT, dtp.rpc_stats()).await?;
+    assert_eq!(stats.nodes.len(), 1);
+    assert_eq!(stats.nodes[0].url, url);
+    assert!(stats.nodes[0].is_down);
+
+    // The next operation runs (and times out) normally.
+    let err = dtp.resolve_host("some-name").await.unwrap_err();
+    assert!(matches!(err, DTPError::Timeout { .. }));
+    Ok(())
+}
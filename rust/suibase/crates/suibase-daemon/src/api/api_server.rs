@@ -13,7 +13,9 @@ use axum::async_trait;
 use anyhow::Result;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
 
-use crate::shared_types::Globals;
+use crate::shared_types::{
+    choose_port, is_port_free, load_common_config, ActivePorts, Globals, StartupPhase,
+};
 
 use common::{
     basic_types::{AdminControllerTx, AutoThread, Runnable},
@@ -29,6 +31,8 @@ use crate::api::impl_proxy_api::ProxyApiImpl;
 use super::PackagesApiServer;
 use crate::api::impl_packages_api::PackagesApiImpl;
 
+use super::{validated_methods, RegisteredMethods};
+
 use jsonrpsee::{core::server::Methods, server::ServerBuilder};
 use std::net::SocketAddr;
 use tower_http::cors::{AllowOrigin, CorsLayer};
@@ -105,45 +109,459 @@ impl APIServerThread {
 
         let builder = ServerBuilder::default().set_http_middleware(middleware);
 
-        // TODO Put here the suibase.yaml proxy_port_number.
+        // Another daemon (e.g. of another user on the same host) may already listen on
+        // the configured port. Fallback to a nearby port (see ActivePorts).
+        let globals = &self.params.globals;
+        let common_config = load_common_config(globals).await;
+        let configured_port = globals.config.read().await.daemon_port;
+        let daemon_port = choose_port(
+            configured_port,
+            common_config.port_fallback_range(),
+            common_config.is_strict_ports(),
+            is_port_free,
+        )
+        .map_err(anyhow::Error::msg)?;
+        if daemon_port != configured_port {
+            log::warn!(
+                "port {} already in use, API listening on port {}",
+                configured_port,
+                daemon_port
+            );
+        }
+
         let server = builder
-            .build(SocketAddr::from(([127, 0, 0, 1], 44399)))
+            .build(SocketAddr::from(([127, 0, 0, 1], daemon_port)))
             .await?;
 
-        let mut all_methods = Methods::new();
+        globals.config.write().await.daemon_port_active = Some(daemon_port);
+        ActivePorts::save(globals).await;
 
-        {
-            let api = ProxyApiImpl::new(
-                self.params.globals.proxy.clone(),
-                self.params.admctrl_tx.clone(),
-            );
-            let methods = api.into_rpc();
-            if let Err(e) = all_methods.merge(methods) {
-                log::error!("Error merging ProxyApiImpl methods: {}", e);
+        let all_methods = build_api_methods(&self.params.globals, &self.params.admctrl_tx);
+
+        let handle = server.start(all_methods);
+        globals.startup.record(StartupPhase::ApiReady);
+        handle.stopped().await;
+
+        Ok(())
+    }
+}
+
+// All the methods served by the APIServer.
+//
+// getCapabilities reports the methods registered here (see capabilities.rs). Their
+// params are validated first (see params.rs).
+pub fn build_api_methods(globals: &Globals, admctrl_tx: &AdminControllerTx) -> Methods {
+    let mut all_methods = Methods::new();
+    let registered_methods = RegisteredMethods::default();
+
+    {
+        let api = ProxyApiImpl::new(globals.proxy.clone(), admctrl_tx.clone())
+            .with_workdirs_status(globals);
+        let methods = api.into_rpc();
+        if let Err(e) = all_methods.merge(methods) {
+            log::error!("Error merging ProxyApiImpl methods: {}", e);
+        }
+    }
+
+    {
+        let api = GeneralApiImpl::new(
+            globals.clone(),
+            admctrl_tx.clone(),
+            registered_methods.clone(),
+        );
+        let methods = api.into_rpc();
+        if let Err(e) = all_methods.merge(methods) {
+            log::error!("Error merging GeneralApiImpl methods: {}", e);
+        }
+    }
+
+    {
+        let api = PackagesApiImpl::new(globals.clone(), admctrl_tx.clone());
+        let methods = api.into_rpc();
+        if let Err(e) = all_methods.merge(methods) {
+            log::error!("Error merging ModulesApiImpl methods: {}", e);
+        }
+    }
+
+    registered_methods.set(&all_methods);
+    validated_methods(all_methods)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use common::basic_types::{WorkdirIdx, LOG_CONTROL, MPSC_Q_SIZE};
+    use jsonrpsee::core::params::{ArrayParams, ObjectParams};
+    use jsonrpsee::core::server::MethodsError;
+    use log::Log;
+
+    use crate::api::{
+        method_params, CapabilitiesResponse, DaemonStatsResponse, RpcErrorCode, SuccessResponse,
+        Versioned, WorkdirStatusResponse, WorkdirsStatusResponse, API_METHODS, API_PARAMS,
+        API_VERSION,
+    };
+    use crate::shared_types::{
+        create_initialized_workdir, read_active_workdir, GlobalsWorkdirsST, InputPort,
+        StartupStats, WorkdirUserConfig, WORKDIRS_KEYS, WORKDIR_IDX_LOCALNET,
+    };
+
+    #[tokio::test]
+    async fn test_get_capabilities() {
+        let globals = Globals::new();
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+
+        let resp: CapabilitiesResponse = methods
+            .call("getCapabilities", ArrayParams::new())
+            .await
+            .unwrap();
+        assert_eq!(resp.api_version, API_VERSION);
+        assert_eq!(resp.header.method, "getCapabilities");
+        assert_eq!(resp.header.api_version.as_deref(), Some(API_VERSION));
+        assert!(resp.features.iter().any(|f| f == "weighted_distribution"));
+
+        // Every registered method is listed (and only those).
+        let mut registered: Vec<&str> = methods.method_names().collect();
+        registered.sort();
+        let listed: Vec<&str> = resp.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(listed, registered);
+
+        // No stale entry in API_METHODS.
+        for (name, since) in API_METHODS {
+            let method = resp.methods.iter().find(|m| m.name == *name);
+            assert_eq!(method.map(|m| m.since.as_str()), Some(*since), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_params() {
+        let globals = Globals::new();
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+
+        // Every error is from the registry, and tells the method.
+        let mut missing_params = 0;
+        for method in methods.method_names() {
+            let request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": {},
+            });
+            let (response, _) = methods
+                .raw_json_request(&request.to_string(), 1)
+                .await
+                .unwrap();
+            let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+            let error = &response["error"];
+            if error.is_null() {
+                continue;
+            }
+            let code = error["code"]
+                .as_i64()
+                .and_then(|code| RpcErrorCode::from_code(code as i32));
+            assert!(code.is_some(), "{}: {}", method, error);
+            let data = &error["data"];
+            assert_eq!(data["method"], method, "{}", error);
+
+            // Only a required param can be missing.
+            if code == Some(RpcErrorCode::MissingParam) {
+                let field = data["field"].as_str().unwrap();
+                let params = method_params(method).unwrap();
+                assert!(params.iter().any(|p| p.name == field && p.required));
+                missing_params += 1;
             }
         }
+        let with_required = API_PARAMS
+            .iter()
+            .filter(|(_, params)| params.iter().any(|p| p.required))
+            .count();
+        assert_eq!(missing_params, with_required);
 
-        {
-            let api =
-                GeneralApiImpl::new(self.params.globals.clone(), self.params.admctrl_tx.clone());
-            let methods = api.into_rpc();
-            if let Err(e) = all_methods.merge(methods) {
-                log::error!("Error merging GeneralApiImpl methods: {}", e);
+        // Mistyped or unknown params are rejected before the handler.
+        let mut params = ObjectParams::new();
+        params.insert("workdir", "localnet").unwrap();
+        params.insert("tail_line", 5).unwrap();
+        params.insert("process", "sui").unwrap();
+        let error = methods
+            .call::<_, serde_json::Value>("getProcessLog", params)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("params tail_line is unknown"),
+            "{}",
+            error
+        );
+
+        let mut params = ArrayParams::new();
+        params.insert("1").unwrap();
+        let error = methods
+            .call::<_, serde_json::Value>("getJobStatus", params)
+            .await
+            .unwrap_err();
+        match error {
+            MethodsError::JsonRpc(error) => {
+                assert_eq!(error.code(), RpcErrorCode::InvalidParamType.code());
+                assert_eq!(
+                    error.message(),
+                    "params job_id must be an unsigned 64 bits integer"
+                );
+            }
+            e => panic!("{}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_log_level() {
+        let globals = Globals::new();
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+
+        // A target of its own, the logger is global.
+        let target = "suibase_daemon::api::test_set_log_level";
+        let enabled = |level: log::Level| {
+            LOG_CONTROL.enabled(&log::Metadata::builder().target(target).level(level).build())
+        };
+        let set_log_level = |level: &str| {
+            let mut params = ObjectParams::new();
+            params.insert("target", target).unwrap();
+            params.insert("level", level).unwrap();
+            methods.call::<_, SuccessResponse>("setLogLevel", params)
+        };
+
+        let resp = set_log_level("trace").await.unwrap();
+        assert!(resp.result);
+        assert_eq!(resp.header.key.as_deref(), Some(target));
+        assert!(enabled(log::Level::Trace));
+
+        set_log_level("warn").await.unwrap();
+        assert!(enabled(log::Level::Warn));
+        assert!(!enabled(log::Level::Info));
+
+        // Rejected, and the filtering is unchanged.
+        match set_log_level("verbose").await.unwrap_err() {
+            MethodsError::JsonRpc(error) => {
+                assert_eq!(error.code(), RpcErrorCode::InvalidParams.code())
             }
+            e => panic!("{}", e),
+        }
+        assert!(enabled(log::Level::Warn));
+        assert!(!enabled(log::Level::Info));
+    }
+
+    #[tokio::test]
+    async fn test_get_workdirs_status() {
+        let mut globals = Globals::new();
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+
+        // Localnet started, with its proxy and 2 links (not yet checked).
+        let mut status = WorkdirStatusResponse::new();
+        status.status = Some("OK".to_string());
+        status.status_info = Some("all services running".to_string());
+        status.status_since = Some("2024-01-01T00:00:00+00:00".to_string());
+        status.status_cause = Some("localnet started".to_string());
+        globals.get_status(WORKDIR_IDX_LOCALNET).write().await.ui = Some(Versioned::new(status));
+        globals
+            .set_asui_selection(Some("localnet".to_string()))
+            .await;
+
+        let mut config = WorkdirUserConfig::new();
+        config
+            .load_and_merge_from_str(
+                "links:\n\x20 - alias: \"a\"\n\x20   rpc: \"http://a\"\n\
+                 \x20 - alias: \"b\"\n\x20   rpc: \"http://b\"\n",
+                "snippet",
+            )
+            .unwrap();
+        let mut input_port = InputPort::new(WORKDIR_IDX_LOCALNET, "localnet".to_string(), &config);
+        for link in config.links().values() {
+            input_port.add_target_server(link);
         }
+        input_port.set_actual_port_number(Some(44340));
+        globals.proxy.write().await.input_ports.push(input_port);
+
+        let resp: WorkdirsStatusResponse = methods
+            .call("getWorkdirsStatus", ArrayParams::new())
+            .await
+            .unwrap();
+        let workdirs: Vec<&str> = resp.workdirs.iter().map(|w| w.workdir.as_str()).collect();
+        assert_eq!(workdirs, WORKDIRS_KEYS);
+
+        // Same data as the single workdir call.
+        let mut params = ArrayParams::new();
+        params.insert("localnet").unwrap();
+        let single: WorkdirStatusResponse = methods.call("getWorkdirStatus", params).await.unwrap();
+        let localnet = resp
+            .workdirs
+            .iter()
+            .find(|w| w.workdir == "localnet")
+            .unwrap();
+        assert_eq!(localnet.status, single.status);
+        assert_eq!(localnet.status_info, single.status_info);
+        assert_eq!(localnet.status_since, single.status_since);
+        assert_eq!(localnet.status_cause, single.status_cause);
+        assert!(localnet.is_active);
+        assert_eq!(localnet.proxy_port, Some(44340));
+        assert_eq!(localnet.links.initializing, 2);
+
+        // Not yet initialized workdirs are still listed.
+        let devnet = resp
+            .workdirs
+            .iter()
+            .find(|w| w.workdir == "devnet")
+            .unwrap();
+        assert_eq!(devnet.status.as_deref(), Some("INITIALIZING"));
+        assert!(!devnet.is_active && devnet.proxy_port.is_none());
+    }
 
+    #[tokio::test]
+    async fn test_startup_lazy_workdir_init() {
+        // Every workdir takes 300ms for its first status (see STARTUP_INIT_DELAY_ENV).
+        let mut globals = Globals::new();
+        globals.startup = Arc::new(StartupStats::with_init_delay(Duration::from_millis(300)));
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+        globals.startup.record(StartupPhase::ApiReady);
+
+        // Same as the AdminController and the CliPoller of every workdir, concurrently.
+        let inits: Vec<_> = (0..WORKDIRS_KEYS.len() as WorkdirIdx)
+            .map(|workdir_idx| {
+                let globals = globals.clone();
+                tokio::spawn(async move {
+                    let startup = &globals.startup;
+                    startup.record(StartupPhase::ConfigLoaded(workdir_idx));
+                    tokio::time::sleep(startup.init_delay()).await;
+                    let mut status = WorkdirStatusResponse::new();
+                    status.status = Some("STOPPED".to_string());
+                    globals.get_status(workdir_idx).write().await.ui = Some(Versioned::new(status));
+                    startup.record(StartupPhase::StatusKnown(workdir_idx));
+                })
+            })
+            .collect();
+
+        // The API answers right away.
+        let started_at = std::time::Instant::now();
+        let resp: WorkdirsStatusResponse = methods
+            .call("getWorkdirsStatus", ArrayParams::new())
+            .await
+            .unwrap();
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        for summary in &resp.workdirs {
+            assert_eq!(summary.status.as_deref(), Some("INITIALIZING"));
+        }
+
+        // A call needing a workdir not yet initialized fails (instead of waiting).
+        let mut params = ArrayParams::new();
+        params.insert("testnet").unwrap();
+        params.insert("status").unwrap();
+        match methods
+            .call::<_, SuccessResponse>("workdirCommand", params)
+            .await
+            .unwrap_err()
         {
-            let api =
-                PackagesApiImpl::new(self.params.globals.clone(), self.params.admctrl_tx.clone());
-            let methods = api.into_rpc();
-            if let Err(e) = all_methods.merge(methods) {
-                log::error!("Error merging ModulesApiImpl methods: {}", e);
+            MethodsError::JsonRpc(error) => {
+                assert_eq!(error.code(), RpcErrorCode::Initializing.code());
+                assert!(error.message().contains("testnet status not yet known"));
             }
+            e => panic!("{}", e),
         }
+        let stats: DaemonStatsResponse = methods
+            .call("getDaemonStats", ArrayParams::new())
+            .await
+            .unwrap();
+        assert!(stats.startup.api_ready_ms.is_some());
+        assert_eq!(stats.startup.initialized_ms, None);
 
-        let handle = server.start(all_methods);
-        handle.stopped().await;
+        // Then converges.
+        for init in inits {
+            init.await.unwrap();
+        }
+        let resp: WorkdirsStatusResponse = methods
+            .call("getWorkdirsStatus", ArrayParams::new())
+            .await
+            .unwrap();
+        for summary in &resp.workdirs {
+            assert_eq!(summary.status.as_deref(), Some("STOPPED"));
+        }
+        let stats: DaemonStatsResponse = methods
+            .call("getDaemonStats", ArrayParams::new())
+            .await
+            .unwrap();
+        let initialized_ms = stats.startup.initialized_ms.unwrap();
+        assert!(initialized_ms >= 300);
+        // Not one workdir after the other.
+        assert!(initialized_ms < 4 * 300);
+        let workdirs: Vec<&str> = stats
+            .startup
+            .workdirs
+            .iter()
+            .map(|w| w.workdir.as_str())
+            .collect();
+        assert_eq!(workdirs, WORKDIRS_KEYS);
+        for workdir in &stats.startup.workdirs {
+            assert!(workdir.config_loaded_ms.unwrap() <= workdir.status_known_ms.unwrap());
+        }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_set_active_workdir() {
+        let dir = std::env::temp_dir().join(format!("sbsd-set-active-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let workdirs_path = dir.join("workdirs");
+        create_initialized_workdir(&workdirs_path, "localnet");
+        create_initialized_workdir(&workdirs_path, "testnet");
+
+        let globals = Globals::new();
+        *globals.workdirs.write().await = GlobalsWorkdirsST::with_suibase_home(&dir);
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let methods = build_api_methods(&globals, &admctrl_tx);
+
+        let set_active = |workdir: &str| {
+            let mut params = ArrayParams::new();
+            params.insert(workdir).unwrap();
+            methods.call::<_, SuccessResponse>("setActiveWorkdir", params)
+        };
+        let active_in_status = || async {
+            let resp: WorkdirsStatusResponse = methods
+                .call("getWorkdirsStatus", ArrayParams::new())
+                .await
+                .unwrap();
+            resp.workdirs
+                .iter()
+                .filter(|w| w.is_active)
+                .map(|w| w.workdir.clone())
+                .collect::<Vec<_>>()
+        };
+
+        for workdir in ["localnet", "testnet", "localnet"] {
+            let resp = set_active(workdir).await.unwrap();
+            assert!(resp.result);
+            assert_eq!(resp.info, Some(format!("{} is now active", workdir)));
+            assert_eq!(
+                read_active_workdir(&workdirs_path).as_deref(),
+                Some(workdir)
+            );
+            assert_eq!(active_in_status().await, vec![workdir.to_string()]);
+        }
+        let resp = set_active("localnet").await.unwrap();
+        assert_eq!(resp.info.as_deref(), Some("localnet is already active"));
+
+        // Concurrent switches are serialized (the last one wins).
+        let (a, b) = tokio::join!(set_active("testnet"), set_active("localnet"));
+        assert!(a.unwrap().result && b.unwrap().result);
+        let active = read_active_workdir(&workdirs_path).unwrap();
+        assert_eq!(active_in_status().await, vec![active]);
+
+        // Not initialized, or not a workdir.
+        assert!(set_active("devnet").await.is_err());
+        assert!(set_active("active").await.is_err());
+        assert_eq!(active_in_status().await.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,143 @@
+// Versioning and capability discovery of the JSON-RPC API (see getCapabilities).
+//
+// API_METHODS is the metadata of every method. The list returned by getCapabilities
+// is the methods actually registered by the APIServer (not this table), so the two
+// cannot drift: a registered method missing from API_METHODS fails a debug assertion.
+//
+// When adding a method:
+//   - Add it to API_METHODS with the API_VERSION introducing it.
+//   - Bump the minor of API_VERSION (the major on any breaking change).
+use std::sync::{Arc, OnceLock};
+
+use jsonrpsee::core::server::Methods;
+
+use super::ApiMethodInfo;
+
+// Semantic version of the API. Also in the header of every response.
+pub const API_VERSION: &str = "1.11.0";
+
+// (method name, API_VERSION when it became available).
+pub const API_METHODS: &[(&str, &str)] = &[
+    // ProxyApi
+    ("getLinks", "1.0.0"),
+    ("fsChange", "1.0.0"),
+    ("previewConfig", "1.0.0"),
+    ("setLinkProfile", "1.0.0"),
+    ("getRecentRequests", "1.0.0"),
+    ("getConfigHistory", "1.0.0"),
+    ("resetServerStats", "1.3.0"),
+    ("getUsageReport", "1.6.0"),
+    ("getShadowReport", "1.10.0"),
+    // GeneralApi
+    ("getVersions", "1.0.0"),
+    ("getCapabilities", "1.0.0"),
+    ("workdirCommand", "1.0.0"),
+    ("getWorkdirStatus", "1.0.0"),
+    ("getWorkdirsStatus", "1.2.0"),
+    ("setAsuiSelection", "1.0.0"),
+    ("setActiveWorkdir", "1.5.0"),
+    ("workdirRefresh", "1.0.0"),
+    ("setLogLevel", "1.0.0"),
+    ("getDaemonStats", "1.0.0"),
+    ("getSystemCheck", "1.0.0"),
+    ("cleanupWorkdirProcesses", "1.1.0"),
+    ("getProcessLog", "1.4.0"),
+    ("getExplorerInfo", "1.0.0"),
+    ("getTelemetryPreview", "1.9.0"),
+    ("getGasInventory", "1.0.0"),
+    ("mergeGasCoins", "1.0.0"),
+    ("snapshotLocalnet", "1.0.0"),
+    ("restoreLocalnet", "1.0.0"),
+    ("listLocalnetSnapshots", "1.0.0"),
+    ("deleteLocalnetSnapshot", "1.0.0"),
+    ("getJobStatus", "1.0.0"),
+    ("exportWorkdirProfile", "1.11.0"),
+    ("importWorkdirProfile", "1.11.0"),
+    // PackagesApi
+    ("getWorkdirEvents", "1.0.0"),
+    ("getWorkdirPackages", "1.0.0"),
+    ("getSubscriptions", "1.7.0"),
+    ("prePublish", "1.0.0"),
+    ("postPublish", "1.0.0"),
+];
+
+// Optional features of this daemon build.
+pub const API_FEATURES: &[&str] = &[
+    "cached_system_values",  // proxy_serve_cached_system_values
+    "events_db",             // Sui events of the workdirs stored in sqlite.
+    "link_metrics",          // Prometheus scraping of the "metrics" links.
+    "link_profiles",         // See setLinkProfile.
+    "links_status_reasons",  // getLinks summary "reasons".
+    "localnet_snapshots",    // See snapshotLocalnet.
+    "proxy_tls",             // HTTPS proxy ports.
+    "rate_groups",           // Limits shared by several links (see getLinks).
+    "telemetry",             // Opt-in anonymized counters (see getTelemetryPreview).
+    "typed_errors",          // Error codes of the RpcErrorCode registry.
+    "webhooks",              // Notifications of link/workdir status changes.
+    "weighted_distribution", // proxy_distribution: weighted
+];
+
+// The methods of the API, sorted by name.
+//
+// Debug builds assert that every method has its API_METHODS entry.
+pub fn api_methods_info<'a>(names: impl Iterator<Item = &'a str>) -> Vec<ApiMethodInfo> {
+    let mut methods: Vec<ApiMethodInfo> = names
+        .map(|name| {
+            let since = API_METHODS
+                .iter()
+                .find(|(method, _)| *method == name)
+                .map(|(_, since)| since.to_string());
+            debug_assert!(
+                since.is_some(),
+                "method {} registered without API_METHODS entry",
+                name
+            );
+            ApiMethodInfo {
+                name: name.to_string(),
+                since: since.unwrap_or_else(|| API_VERSION.to_string()),
+            }
+        })
+        .collect();
+    methods.sort_by(|a, b| a.name.cmp(&b.name));
+    methods
+}
+
+// Set by the APIServer once all the methods are registered, for getCapabilities.
+#[derive(Clone, Default)]
+pub struct RegisteredMethods(Arc<OnceLock<Vec<ApiMethodInfo>>>);
+
+impl RegisteredMethods {
+    pub fn set(&self, methods: &Methods) {
+        let _ = self.0.set(api_methods_info(methods.method_names()));
+    }
+
+    pub fn get(&self) -> Option<&Vec<ApiMethodInfo>> {
+        self.0.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_methods_table() {
+        // No duplicate, and never introduced after the current version.
+        let mut names: Vec<&str> = API_METHODS.iter().map(|(name, _)| *name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), API_METHODS.len());
+        let semver = |v: &str| -> Vec<u32> { v.split('.').map(|n| n.parse().unwrap()).collect() };
+        for (name, since) in API_METHODS {
+            assert_eq!(semver(since).len(), 3, "{}", name);
+            assert!(semver(since) <= semver(API_VERSION), "{}", name);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "method getSecret registered without API_METHODS entry")]
+    fn test_method_without_metadata() {
+        api_methods_info(["getLinks", "getSecret"].into_iter());
+    }
+}
@@ -4,12 +4,14 @@ use serde_with::serde_as;
 
 use common::basic_types::SafeUuid;
 
+use super::API_VERSION;
+
 fn is_empty_string(s: &String) -> bool {
     s.is_empty()
 }
 
 #[serde_as]
-#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Header {
     // Header fields
@@ -29,6 +31,9 @@ pub struct Header {
     //        lower than the previous one for this method (e.g. system time went backward) or the PID of
     //        the process changes. Complements data_uuid for added reliability on various edge cases.
     //
+    //    - api_version:
+    //        Semantic version of the API of this daemon (see getCapabilities).
+    //
     #[serde(skip_serializing_if = "is_empty_string")]
     pub method: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -39,6 +44,8 @@ pub struct Header {
     pub key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub semver: Option<String>, // Semantic versioning of the backend API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
 }
 
 // A trait for comparing two objects for equivalence, excluding the header fields (if any).
@@ -54,6 +61,7 @@ impl Header {
             data_uuid: None,
             key: None,
             semver: None,
+            api_version: None,
         }
     }
 
@@ -63,6 +71,17 @@ impl Header {
     }
 }
 
+// The header of a response. Header::new() is for the headers nested in a
+// response (e.g. getVersions), so without the api_version.
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            api_version: Some(API_VERSION.to_string()),
+            ..Self::new("")
+        }
+    }
+}
+
 // Class to conveniently add UUID versioning to any data structure.
 //
 // That versioning can be used to initialize the method_uuid and data_uuid fields of a Header
@@ -37,14 +37,62 @@ pub struct LinkStats {
     pub alias: String,
 
     #[serde(skip_serializing_if = "String::is_empty")]
-    pub status: String, // Empty string, "OK" or "DOWN"
+    pub status: String, // Empty string, "OK", "DEGRADED", "PROBING", "MAINTENANCE" or "DOWN"
+
+    // Empty string for a "rpc" link, otherwise "metrics" or "monitor-only".
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub role: String,
 
     #[serde(skip_serializing_if = "String::is_empty")]
     pub health_pct: String,
 
+    // Left side of the workdir "health_score" expression (when configured). A working
+    // link is "DEGRADED" instead of "OK" when its comparison is false.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub health_expr_score: String,
+
     #[serde(skip_serializing_if = "String::is_empty")]
     pub load_pct: String,
 
+    // Share of the new requests intended for this link ("weighted" proxy_distribution only).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub weight_pct: String,
+
+    // Configured share of the requests of a canary link (see canary_pct in suibase.yaml).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary_pct: Option<u8>,
+
+    // Request rates, sampled every few seconds.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub qps: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub qpm: String,
+
+    // Configured rate limits of the link (the proxy does not exceed them).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_secs: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_min: Option<u32>,
+
+    // Also limited by this entry of the rate_groups (see LinksResponse).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_group: Option<String>,
+
+    // Requests since 00:00 UTC, and the daily quota of the link (when configured).
+    pub day_count: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_day: Option<u32>,
+
+    // Requests in the current UTC month, and the monthly_budget of the link (when
+    // configured). See getUsageReport for the previous months.
+    pub month_count: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<u64>,
+
     #[serde(skip_serializing_if = "String::is_empty")]
     pub resp_time: String,
 
@@ -53,6 +101,41 @@ pub struct LinkStats {
 
     #[serde(skip_serializing_if = "String::is_empty")]
     pub error_info: String, // Sometime more info when DOWN.
+
+    // Count of responses per HTTP status class.
+    pub status_2xx: u64,
+    pub status_4xx: u64,
+    pub status_5xx: u64,
+
+    // Requests since the stats were last reset.
+    pub request_count: u64,
+
+    // Most frequent JSON-RPC error codes (highest count first).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub error_codes: Vec<LinkErrorCodeCount>,
+
+    // Scraped from the link "metrics" URL (role "metrics" only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_secs: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highest_synced_checkpoint: Option<u64>,
+
+    // RFC 3339. Set while the provider rate limits the link (not selected until then).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttled_until: Option<String>,
+
+    // Count of rate limiting responses (HTTP 429 or a throttle_codes of the link).
+    pub throttle_count: u64,
+
+    // Requests also sent to this link because the best link was slow (see proxy_hedge),
+    // and how many were answered first by this link.
+    pub hedge_count: u64,
+    pub hedge_won_count: u64,
+
+    // RFC 3339. Set while in a scheduled maintenance window (status MAINTENANCE).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_until: Option<String>,
 }
 
 impl LinkStats {
@@ -64,6 +147,68 @@ impl LinkStats {
     }
 }
 
+// Limits shared by the links of a same provider (see rate_groups in suibase.yaml).
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateGroupStats {
+    pub name: String,
+    pub links: Vec<String>, // Aliases of the links in the group.
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_secs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_min: Option<u32>,
+
+    // Combined request rates of the links, sampled every few seconds.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub qps: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub qpm: String,
+
+    // Attempts toward a link of the group with a token of the group, and without
+    // (skipped to a link of another group, or rejected).
+    pub granted_count: u64,
+    pub denied_count: u64,
+}
+
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkErrorCodeCount {
+    pub code: i32,
+    pub count: u64,
+}
+
+// Why the multi-link status is not (fully) OK. See LinksSummary::reasons.
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinksStatusReason {
+    // One of the LINKS_REASON_* (more may be added, ignore unknown kinds).
+    pub kind: String,
+    pub detail: String,
+
+    // RFC 3339. When known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+pub const LINKS_REASON_CONFIG_ERROR: &str = "config_error";
+pub const LINKS_REASON_NODE_PROCESS_DEAD: &str = "node_process_dead";
+pub const LINKS_REASON_ALL_LINKS_DOWN: &str = "all_links_down";
+pub const LINKS_REASON_LINKS_DEGRADED: &str = "links_degraded";
+pub const LINKS_REASON_RATE_LIMITED: &str = "rate_limited";
+pub const LINKS_REASON_DAEMON_SUBSYSTEM_DOWN: &str = "daemon_subsystem_down";
+pub const LINKS_REASON_SUBSCRIPTIONS_DEGRADED: &str = "subscriptions_degraded";
+
+impl LinksStatusReason {
+    pub fn new(kind: &str, detail: String, since: Option<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            detail,
+            since,
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -73,7 +218,43 @@ pub struct LinksSummary {
     pub success_on_retry: u64,
     pub fail_network_down: u64,
     pub fail_bad_request: u64,
+    pub fail_overload: u64, // Rejected by the proxy (see proxy_max_concurrency).
+    pub client_errors: u64, // Malformed JSON-RPC rejected by the proxy (never sent upstream).
+    pub fail_ip_denied: u64, // Source IP not in proxy_allowed_ips (rejected with 403).
     pub fail_others: u64,
+
+    // Daemon threads kept down after repeated failures (see getDaemonStats).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_threads: Vec<String>,
+
+    // Failure to load the proxy_tls cert/key (see suibase.yaml).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_tls_error: Option<String>,
+
+    // Configured port in use by another process (see strict_ports in suibase.yaml).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_port_error: Option<String>,
+
+    // Entry of link_profiles used instead of the 'links' section (see setLinkProfile).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_link_profile: Option<String>,
+
+    // proxy_distribution in suibase.yaml, when not the default "best".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_distribution: Option<String>,
+
+    // Actionable causes of a DOWN or degraded status, most important first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasons: Option<Vec<LinksStatusReason>>,
+
+    // Links having used 80% or more of their monthly_budget, e.g. "alchemy (85%)".
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub budget_warnings: Vec<String>,
+
+    // Links whose clock differs from this host by more than time_skew_threshold_secs,
+    // e.g. "local clock 42.5s ahead of sui.io". Informative only (no effect on routing).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub time_skew_warnings: Vec<String>,
 }
 
 impl LinksSummary {
@@ -92,6 +273,20 @@ pub struct LinksResponse {
 
     pub info: String, // More details about the status (e.g. '50% degraded', 'all servers down', etc...)
 
+    // Time (RFC3339) and cause of the last status change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_cause: Option<String>,
+
+    // proxy_port_number in suibase.yaml, and the port the proxy is listening on. They
+    // differ when the configured port was already in use (e.g. by the daemon of
+    // another user on the same host).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_port_configured: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_port: Option<u16>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<LinksSummary>,
 
@@ -99,6 +294,10 @@ pub struct LinksResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub links: Option<Vec<LinkStats>>,
 
+    // With the links, when the workdir has rate_groups.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_groups: Option<Vec<RateGroupStats>>,
+
     // This is the output when the option 'display' is true.
     // Will also change the default to false for the summary/links output.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -116,14 +315,245 @@ impl LinksResponse {
             header: Header::default(),
             status: "DISABLED".to_string(),
             info: "INITIALIZING".to_string(),
+            status_since: None,
+            status_cause: None,
+            proxy_port_configured: None,
+            proxy_port: None,
             summary: None,
             links: None,
+            rate_groups: None,
             display: None,
             debug: None,
         }
     }
 }
 
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkConfigChange {
+    pub alias: String,
+    pub field: String, // rpc, ws, metrics, priority, role or enabled.
+    pub before: String,
+    pub after: String,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewConfigResponse {
+    pub header: Header,
+
+    // Links diff versus the active config (aliases).
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<LinkConfigChange>,
+
+    // Resulting selection ranking with the current health data. Each inner vector
+    // is a group of links of same quality (see InputPort::selection_vectors). The
+    // links not (yet) known healthy are in 'fallback' (least worst first).
+    pub selectable: Vec<String>,
+    pub selection: Vec<Vec<String>>,
+    pub fallback: Vec<String>,
+
+    pub warnings: Vec<String>,
+}
+
+impl PreviewConfigResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            selectable: Vec::new(),
+            selection: Vec::new(),
+            fallback: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Default for PreviewConfigResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentRequestInfo {
+    pub request_id: String, // Trace id (X-Request-Id).
+    pub method: String,     // JSON-RPC method ("batch" for an array of requests).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>, // Last link attempted.
+    pub latency_ms: u64,
+    pub status: u16, // HTTP status returned to the client.
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentRequestsResponse {
+    pub header: Header,
+    pub requests: Vec<RecentRequestInfo>, // Most recent first.
+}
+
+impl RecentRequestsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl Default for RecentRequestsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Also the format of a line of <workdir>/logs/config-history.jsonl (with the
+// secrets already redacted).
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    pub path: String,   // e.g. "proxy_enabled", "links.<alias>" or "links.<alias>.rpc"
+    pub change: String, // "added", "removed" or "modified"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHistoryEntry {
+    pub timestamp: String, // RFC 3339, when the change was applied.
+    pub changes: Vec<ConfigChange>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHistoryResponse {
+    pub header: Header,
+    pub entries: Vec<ConfigHistoryEntry>, // Most recent first.
+}
+
+impl ConfigHistoryResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Default for ConfigHistoryResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkUsageInfo {
+    pub alias: String,
+    pub requests: u64,
+
+    // Not set when the link has no monthly_budget (or is no longer in suibase.yaml).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_pct: Option<String>,
+
+    // Highest budget threshold alerted in the month (0 when none).
+    pub alerted_pct: u32,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportResponse {
+    pub header: Header,
+    pub month: String, // "YYYY-MM" (UTC)
+    pub links: Vec<LinkUsageInfo>,
+    pub months: Vec<String>, // Months having a count, most recent first.
+}
+
+impl UsageReportResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            month: String::new(),
+            links: Vec::new(),
+            months: Vec::new(),
+        }
+    }
+}
+
+impl Default for UsageReportResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowMethodInfo {
+    pub method: String,
+    pub compared: u64,
+    pub mismatches: u64,
+    pub failed: u64, // No usable response from the shadow link.
+}
+
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowDiffInfo {
+    pub request_id: String, // Trace id (X-Request-Id).
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>, // Link that answered the client.
+    pub timestamp_ms: u64,
+    // e.g. "result.version: \"12\" != \"13\"" (the value of the link first).
+    pub differences: Vec<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowReportResponse {
+    pub header: Header,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_link: Option<String>, // Not set when the shadow mode is disabled.
+    pub shadow_pct: u8,
+    pub dropped: u64, // Copies not sent because of the rate limits of the shadow link.
+    pub methods: Vec<ShadowMethodInfo>,
+    pub diffs: Vec<ShadowDiffInfo>, // Most recent first.
+}
+
+impl ShadowReportResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            shadow_link: None,
+            shadow_pct: 0,
+            dropped: 0,
+            methods: Vec::new(),
+            diffs: Vec::new(),
+        }
+    }
+}
+
+impl Default for ShadowReportResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -152,125 +582,793 @@ pub struct StatusService {
     pub pid: Option<u64>,
 }
 
-impl StatusService {
-    pub fn new(label: String) -> Self {
+impl StatusService {
+    pub fn new(label: String) -> Self {
+        Self {
+            label,
+            status: None,
+            status_info: None,
+            help_info: None,
+            pid: None,
+        }
+    }
+}
+
+// Provenance of the sui binary of a workdir (see set-sui-repo).
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiBinaryInfo {
+    pub origin: String, // "repo-build", "downloaded" or "default-build"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sui_repo_path: Option<String>, // Set only by set-sui-repo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_mtime: Option<String>, // RFC3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirStatusResponse {
+    pub header: Header,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>, // This is a single word combined "Multi-Link status". Either "OK" or "DOWN".
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<String>, // More details about the status (e.g. '50% degraded', 'internal error', etc...)
+
+    // Time (RFC3339) and cause of the last status change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_cause: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sui_binary: Option<SuiBinaryInfo>,
+
+    // Finer grain status for each process/feature/service.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services: Option<Vec<StatusService>>,
+
+    // Snippet of the raw "<workdir> status" output when it could not be parsed (UNKNOWN status).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<String>,
+}
+
+impl WorkdirStatusResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            status: None,
+            status_info: None,
+            status_since: None,
+            status_cause: None,
+            client_version: None,
+            network_version: None,
+            sui_binary: None,
+            services: None,
+            debug: None,
+        }
+    }
+}
+
+impl Default for WorkdirStatusResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VersionedEq for WorkdirStatusResponse {
+    fn versioned_eq(&self, other: &Self) -> bool {
+        // Purposely do not include header in the comparison.
+        self.status == other.status
+            && self.status_info == other.status_info
+            && self.status_since == other.status_since
+            && self.status_cause == other.status_cause
+            && self.client_version == other.client_version
+            && self.network_version == other.network_version
+            && self.sui_binary == other.sui_binary
+            && self.services == other.services
+            && self.debug == other.debug
+    }
+}
+
+// Number of links of a workdir by their status (see LinkStats::status).
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinksHealthCount {
+    pub ok: u32,
+    pub down: u32,
+    pub probing: u32,
+    pub maintenance: u32,
+    pub initializing: u32, // Health not yet determined.
+    pub monitor_only: u32, // Whatever their status.
+}
+
+// One workdir of getWorkdirsStatus.
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirStatusSummary {
+    pub workdir: String,
+
+    // Same as getWorkdirStatus. "INITIALIZING" until the first status of the workdir.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_cause: Option<String>,
+
+    pub is_active: bool, // Workdir selected with asui.
+
+    // Port the proxy is listening on (may differ from suibase.yaml, see strict_ports).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_port: Option<u16>,
+
+    pub links: LinksHealthCount,
+
+    // Problems detected by the daemon (e.g. suibase.yaml value ignored, proxy port in use).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+
+    // Sui event subscriptions resubscribing or dropping events (see getSubscriptions).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_subscriptions: Vec<String>,
+}
+
+impl WorkdirStatusSummary {
+    pub fn new(workdir: String) -> Self {
+        Self {
+            workdir,
+            status: None,
+            status_info: None,
+            status_since: None,
+            status_cause: None,
+            is_active: false,
+            proxy_port: None,
+            links: LinksHealthCount::default(),
+            warnings: Vec::new(),
+            degraded_subscriptions: Vec::new(),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirsStatusResponse {
+    pub header: Header,
+    pub workdirs: Vec<WorkdirStatusSummary>, // Order of WORKDIRS_KEYS (mainnet first).
+
+    // Daemon threads kept down after repeated failures (see getDaemonStats).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_threads: Vec<String>,
+}
+
+impl WorkdirsStatusResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            workdirs: Vec::new(),
+            degraded_threads: Vec::new(),
+        }
+    }
+}
+
+impl Default for WorkdirsStatusResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiEvents {
+    pub message: String,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<serde_json::Value>, // The stored Sui event (filtered queries only).
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirSuiEventsResponse {
+    pub header: Header,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<SuiEvents>>,
+}
+
+impl WorkdirSuiEventsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            events: None,
+        }
+    }
+}
+
+impl Default for WorkdirSuiEventsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessResponse {
+    pub header: Header,
+    pub result: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
+impl SuccessResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            result: false,
+            info: None,
+        }
+    }
+}
+
+impl Default for SuccessResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadRestartStats {
+    pub name: String,
+    pub restart_count: u64, // Restarts caused by a panic or an error.
+    pub cool_off_count: u64,
+    pub degraded: bool, // true while kept down for a cool-off period (or failed).
+    pub failed: bool,   // true when not restarted anymore.
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_backtrace: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_restart: Option<String>, // RFC 3339
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cool_off_until: Option<String>, // RFC 3339
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryStats {
+    pub delivered: u64,
+    pub retried: u64,
+    pub dropped: u64, // Queue full or all attempts failed.
+}
+
+// Restarts of a worker of a workdir (ThreadRestartStats counts all workdirs together).
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerRestartStats {
+    pub name: String,
+    pub workdir: String,
+    pub restart_count: u64,
+    pub last_error: String,
+    pub last_restart: String, // RFC 3339
+    pub failed: bool,
+}
+
+// Milliseconds since the start of the daemon (None while not yet done).
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirStartupStats {
+    pub workdir: String,
+    pub config_loaded_ms: Option<u64>, // First load of its suibase.yaml.
+    pub status_known_ms: Option<u64>,  // First status (INITIALIZING until then).
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupPhasesStats {
+    pub api_ready_ms: Option<u64>,
+    pub initialized_ms: Option<u64>, // Status of every workdir known.
+    pub workdirs: Vec<WorkdirStartupStats>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonStatsResponse {
+    pub header: Header,
+    pub threads: Vec<ThreadRestartStats>,
+    pub worker_restarts: Vec<WorkerRestartStats>,
+    pub webhooks: WebhookDeliveryStats,
+    pub startup: StartupPhasesStats,
+}
+
+impl DaemonStatsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            threads: Vec::new(),
+            worker_restarts: Vec::new(),
+            webhooks: WebhookDeliveryStats::default(),
+            startup: StartupPhasesStats::default(),
+        }
+    }
+}
+
+impl Default for DaemonStatsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemCheckItem {
+    pub name: String,   // e.g. "scripts", "localnet.proxy"
+    pub status: String, // "pass", "warn" or "fail"
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>, // Remediation (always set when not "pass").
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemCheckResponse {
+    pub header: Header,
+    pub status: String, // Worst status of all the checks.
+    pub checks: Vec<SystemCheckItem>,
+}
+
+impl SystemCheckResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            status: String::new(),
+            checks: Vec::new(),
+        }
+    }
+}
+
+impl Default for SystemCheckResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProcessAction {
+    pub pid: u32,
+    pub process: String, // "sui" or "sui-faucet"
+    pub cmd: String,
+    pub action: String, // "adopted", "terminated", "killed" or "failed"
+    pub reason: String, // e.g. "not in .state/sui-process.pid"
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProcessesResponse {
+    pub header: Header,
+    pub result: bool, // false when a process is still running after SIGKILL.
+    pub actions: Vec<WorkdirProcessAction>, // Empty when no sui/faucet process found.
+}
+
+impl WorkdirProcessesResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            result: false,
+            actions: Vec::new(),
+        }
+    }
+}
+
+impl Default for WorkdirProcessesResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessLogResponse {
+    pub header: Header,
+    pub process: String,       // "sui" or "sui-faucet"
+    pub path: String,          // Absolute path of the live log (e.g. ".../config/sui-process.log").
+    pub size: u64,             // Bytes of the live log (0 when missing).
+    pub archives: Vec<String>, // Rotated logs, most recent first.
+    pub lines: Vec<String>,    // Oldest first.
+}
+
+impl ProcessLogResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            process: String::new(),
+            path: String::new(),
+            size: 0,
+            archives: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl Default for ProcessLogResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorerInfoResponse {
+    pub header: Header,
+    pub configured_port: u16, // sui_explorer_port (common suibase.yaml)
+
+    // None while the webserver is not listening.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>, // e.g. "http://localhost:44380"
+
+    // Set when not listening, or not on the configured port (e.g. already in use).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
+impl ExplorerInfoResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            configured_port: 0,
+            port: None,
+            url: None,
+            info: None,
+        }
+    }
+}
+
+impl Default for ExplorerInfoResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryPreviewResponse {
+    pub header: Header,
+    pub enabled: bool, // telemetry_enabled (common suibase.yaml)
+
+    // None when only kept locally (no telemetry_endpoint).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    // Exactly what the next report would send. None while disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+}
+
+impl TelemetryPreviewResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            enabled: false,
+            endpoint: None,
+            payload: None,
+        }
+    }
+}
+
+impl Default for TelemetryPreviewResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasCoinInfo {
+    pub coin_object_id: String,
+    pub balance: String, // MIST, as a string (same as Sui JSON-RPC).
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasCoinBucket {
+    pub label: String, // Range in SUI (e.g. "0.01-0.1")
+    pub count: u64,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasInventoryResponse {
+    pub header: Header,
+    pub address: String,
+    pub coin_count: u64,
+    pub total_balance: String, // MIST
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub largest_coin: Option<GasCoinInfo>,
+
+    // Coin count per balance range (smallest range first).
+    pub histogram: Vec<GasCoinBucket>,
+
+    // True when merging the coins is recommended (see mergeGasCoins).
+    pub merge_suggested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl GasInventoryResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            address: String::new(),
+            coin_count: 0,
+            total_balance: "0".to_string(),
+            largest_coin: None,
+            histogram: Vec::new(),
+            merge_suggested: false,
+            suggestion: None,
+        }
+    }
+}
+
+impl Default for GasInventoryResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeGasCoinsResponse {
+    pub header: Header,
+    pub address: String,
+    pub coin_count_before: u64,
+    pub coin_count_after: u64,
+    pub tx_digests: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>, // More details when the merge stopped early.
+}
+
+impl MergeGasCoinsResponse {
+    pub fn new() -> Self {
         Self {
-            label,
-            status: None,
-            status_info: None,
-            help_info: None,
-            pid: None,
+            header: Header::default(),
+            address: String::new(),
+            coin_count_before: 0,
+            coin_count_after: 0,
+            tx_digests: Vec::new(),
+            info: None,
         }
     }
 }
 
+impl Default for MergeGasCoinsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct WorkdirStatusResponse {
-    pub header: Header,
-
+pub struct JobStatus {
+    pub job_id: u64,
+    pub method: String, // Method that started the job (e.g. "snapshotLocalnet").
+    pub workdir: String,
+    pub state: String,    // "RUNNING", "DONE" or "FAILED"
+    pub progress: String, // Current step (e.g. "Stopping localnet").
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub status: Option<String>, // This is a single word combined "Multi-Link status". Either "OK" or "DOWN".
-
+    pub info: Option<String>, // Result details, or the error when FAILED.
+    pub started_at: String, // RFC 3339
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub status_info: Option<String>, // More details about the status (e.g. '50% degraded', 'internal error', etc...)
+    pub ended_at: Option<String>, // RFC 3339
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub client_version: Option<String>,
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusResponse {
+    pub header: Header,
+    pub job: JobStatus,
+}
 
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalnetSnapshotInfo {
+    pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub network_version: Option<String>,
+    pub sui_version: Option<String>, // Version of the sui binary that created the snapshot.
+    pub created_at: String, // RFC 3339
+}
 
-    // Finer grain status for each process/feature/service.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub services: Option<Vec<StatusService>>,
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalnetSnapshotsResponse {
+    pub header: Header,
+    pub snapshots: Vec<LocalnetSnapshotInfo>, // Sorted by name.
 }
 
-impl WorkdirStatusResponse {
+impl LocalnetSnapshotsResponse {
     pub fn new() -> Self {
         Self {
             header: Header::default(),
-            status: None,
-            status_info: None,
-            client_version: None,
-            network_version: None,
-            services: None,
+            snapshots: Vec::new(),
         }
     }
 }
 
-impl Default for WorkdirStatusResponse {
+impl Default for LocalnetSnapshotsResponse {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl VersionedEq for WorkdirStatusResponse {
-    fn versioned_eq(&self, other: &Self) -> bool {
-        // Purposely do not include header in the comparison.
-        self.status == other.status
-            && self.status_info == other.status_info
-            && self.client_version == other.client_version
-            && self.network_version == other.network_version
-            && self.services == other.services
-    }
+// Bundle of a workdir setup (see exportWorkdirProfile and workdir_profile.rs).
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProfile {
+    pub format: String, // "suibase-workdir-profile"
+    pub version: u32,
+    pub manifest: WorkdirProfileManifest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suibase_yaml: Option<String>, // The user suibase.yaml of the workdir.
+    #[serde(default)]
+    pub published_data: Vec<WorkdirProfileFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<WorkdirProfileKey>, // Only the ones selected on export.
 }
 
+// The environment the profile was exported from. Not applied on import (the package
+// ids are only compared with the workdir ones).
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct SuiEvents {
-    pub message: String,
-    pub timestamp: String,
+pub struct WorkdirProfileManifest {
+    pub workdir: String,
+    pub created_at: String, // RFC 3339
+    pub daemon_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suibase_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sui_version: Option<String>,
+    pub platform: String, // e.g. "linux-x86_64"
+    #[serde(default)]
+    pub packages: Vec<WorkdirProfilePackage>, // Sorted by name.
+    #[serde(default)]
+    pub addresses: Vec<WorkdirProfileAddress>, // Named addresses (sui.aliases).
 }
 
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct WorkdirSuiEventsResponse {
-    pub header: Header,
+pub struct WorkdirProfilePackage {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_id: Option<String>, // Of the most recent publication.
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProfileAddress {
+    pub alias: String,
+    pub address: String,
+}
 
+// A file or symlink of published-data.
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProfileFile {
+    pub path: String, // Relative to published-data (e.g. "demo/most-recent").
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub events: Option<Vec<SuiEvents>>,
+    pub symlink: Option<String>, // Target, also relative to published-data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>, // Base64. None for a symlink.
 }
 
-impl WorkdirSuiEventsResponse {
-    pub fn new() -> Self {
-        Self {
-            header: Header::default(),
-            events: None,
-        }
-    }
+// A keystore entry. This is a secret.
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkdirProfileKey {
+    pub alias: String,
+    pub address: String,
+    pub public_key_base64: String, // As in sui.aliases.
+    pub private_key: String,       // As in sui.keystore (Base64 of flag || private key).
 }
 
-impl Default for WorkdirSuiEventsResponse {
-    fn default() -> Self {
-        Self::new()
-    }
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportWorkdirProfileResponse {
+    pub header: Header,
+    pub profile: WorkdirProfile,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>, // e.g. a symlink not exported.
 }
 
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct SuccessResponse {
+pub struct WorkdirProfileConflict {
+    pub kind: String, // "link", "package" or "key"
+    pub name: String, // The alias or package name.
+    pub detail: String,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWorkdirProfileResponse {
     pub header: Header,
-    pub result: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub info: Option<String>,
+    pub dry_run: bool,
+    pub applied: bool,        // false on dry_run, or when a conflict is not forced.
+    pub changes: Vec<String>, // Done (or that would be done), e.g. "package demo added".
+    pub conflicts: Vec<WorkdirProfileConflict>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-impl SuccessResponse {
+impl ImportWorkdirProfileResponse {
     pub fn new() -> Self {
         Self {
             header: Header::default(),
-            result: false,
-            info: None,
+            dry_run: false,
+            applied: false,
+            changes: Vec::new(),
+            conflicts: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 }
 
-impl Default for SuccessResponse {
+impl Default for ImportWorkdirProfileResponse {
     fn default() -> Self {
         Self::new()
     }
@@ -471,6 +1569,39 @@ impl Default for MoveConfig {
     }
 }
 
+// Delivery of the Sui events of one package (see events_sync.rs).
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageEventsSync {
+    pub package_id: String,
+    pub package_name: String,
+
+    // High-water mark. None until the first backfill completes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hwm_checkpoint: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hwm_tx_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hwm_event_seq: Option<u64>,
+
+    // Checkpoints since the high-water mark (as of the last audit).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lag_checkpoints: Option<u64>,
+
+    pub backfill_pending: bool, // Set on a (re)subscription, until the backfill completes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_backfill_at: Option<String>, // RFC 3339
+    pub last_backfill_recovered: u64, // Events missed by the subscription.
+    pub last_backfill_truncated: bool, // Stopped by events_backfill_window_secs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_backfill_error: Option<String>,
+
+    // Since the daemon started.
+    pub events_recovered: u64,
+    pub duplicates_dropped: u64,
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -490,6 +1621,10 @@ pub struct WorkdirPackagesResponse {
     //   - The MoveConfig.path must all be distinct.
     //
     move_configs: HashMap<String, MoveConfig>, // Key is the UUID
+
+    // Added on each response (not part of the versioned data).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events_sync: Vec<PackageEventsSync>,
 }
 
 impl WorkdirPackagesResponse {
@@ -497,6 +1632,7 @@ impl WorkdirPackagesResponse {
         Self {
             header: Header::default(),
             move_configs: HashMap::new(),
+            events_sync: Vec::new(),
             //move_configs_set: HashSet::new(),
         }
     }
@@ -629,6 +1765,51 @@ impl Default for WorkdirPackagesResponse {
     }
 }
 
+// One Sui event subscription of getSubscriptions.
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionInfo {
+    pub package_id: String,
+    pub package_name: String,
+    pub state: String, // e.g. "Subscribed" (see SubscriptionTrackingState).
+
+    // Since the daemon started.
+    pub events_delivered: u64,
+    pub events_dropped: u64, // Received, but could not be forwarded to the events writer.
+    pub resubscribe_count: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_event_at: Option<String>, // RFC 3339
+
+    // Set on a resubscribe storm or sustained drops.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded_reason: Option<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionsResponse {
+    pub header: Header,
+    pub subscriptions: Vec<SubscriptionInfo>,
+}
+
+impl SubscriptionsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            subscriptions: Vec::new(),
+        }
+    }
+}
+
+impl Default for SubscriptionsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VersionedEq for WorkdirPackagesResponse {
     fn versioned_eq(&self, other: &Self) -> bool {
         // Purposely do not include header in the comparison.
@@ -669,6 +1850,41 @@ impl VersionedEq for VersionsResponse {
     }
 }
 
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiMethodInfo {
+    pub name: String,
+    pub since: String, // API version that introduced the method.
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilitiesResponse {
+    pub header: Header,
+    pub api_version: String,
+    pub methods: Vec<ApiMethodInfo>, // Sorted by name.
+    pub features: Vec<String>,
+}
+
+impl CapabilitiesResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            api_version: String::new(),
+            methods: Vec::new(),
+            features: Vec::new(),
+        }
+    }
+}
+
+impl Default for CapabilitiesResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[rpc(server)]
 pub trait ProxyApi {
     /// Returns data about all the RPC/Websocket links
@@ -689,6 +1905,73 @@ pub trait ProxyApi {
 
     #[method(name = "fsChange")]
     async fn fs_change(&self, path: String) -> RpcResult<InfoResponse>;
+
+    /// Dry-run of a suibase.yaml change for a workdir (e.g. a links section).
+    ///
+    /// The snippet is merged after the current suibase.yaml files and goes
+    /// through the same parsing as a reload. Nothing is applied.
+    #[method(name = "previewConfig")]
+    async fn preview_config(
+        &self,
+        workdir: String,
+        yaml: String,
+    ) -> RpcResult<PreviewConfigResponse>;
+
+    /// Switch the links of a workdir to one of its suibase.yaml link_profiles.
+    ///
+    /// Sets active_link_profile in the user suibase.yaml and reloads it. The
+    /// profile must be defined. An empty profile goes back to the 'links' section.
+    #[method(name = "setLinkProfile")]
+    async fn set_link_profile(&self, workdir: String, profile: String) -> RpcResult<InfoResponse>;
+
+    /// Last requests handled by the proxy of a workdir (debugging aid).
+    ///
+    /// Most recent first, up to 'limit' (default and max 100). Each request is
+    /// identified by its trace id (the X-Request-Id returned by the proxy).
+    #[method(name = "getRecentRequests")]
+    async fn get_recent_requests(
+        &self,
+        workdir: String,
+        limit: Option<u32>,
+    ) -> RpcResult<RecentRequestsResponse>;
+
+    /// Changes of the suibase.yaml config applied to a workdir (what changed and when).
+    ///
+    /// Most recent first, up to 'limit' (default and max 100). Persisted in
+    /// <workdir>/logs/config-history.jsonl, so older changes survive a restart.
+    /// Secrets (e.g. webhook secret, URL credentials) are redacted.
+    #[method(name = "getConfigHistory")]
+    async fn get_config_history(
+        &self,
+        workdir: String,
+        limit: Option<u32>,
+    ) -> RpcResult<ConfigHistoryResponse>;
+
+    /// Requests per link in a UTC month (default is the current month), with their
+    /// monthly_budget (see suibase.yaml).
+    ///
+    /// 'month' is "YYYY-MM". Counted for the proxied requests only (not the health
+    /// checks), and kept across daemon restarts in <workdir>/.state/link-usage.json.
+    #[method(name = "getUsageReport")]
+    async fn get_usage_report(
+        &self,
+        workdir: String,
+        month: Option<String>,
+    ) -> RpcResult<UsageReportResponse>;
+
+    /// Comparison of the responses of the links with the shadow_link (see suibase.yaml).
+    ///
+    /// Per method, the read requests also sent to the shadow link and how many had a
+    /// different response, with the differences of the last mismatches (most recent
+    /// first). Restarts from zero when the shadow_link changes.
+    #[method(name = "getShadowReport")]
+    async fn get_shadow_report(&self, workdir: String) -> RpcResult<ShadowReportResponse>;
+
+    /// Restart the stats of all the links of a workdir from zero.
+    ///
+    /// Also deletes the stats kept across daemon restarts (see proxy_stats_persist).
+    #[method(name = "resetServerStats")]
+    async fn reset_server_stats(&self, workdir: String) -> RpcResult<InfoResponse>;
 }
 
 #[rpc(server)]
@@ -703,6 +1986,13 @@ pub trait GeneralApi {
     #[method(name = "getVersions")]
     async fn get_versions(&self, workdir: Option<String>) -> RpcResult<VersionsResponse>;
 
+    // API version, methods supported by this daemon (with the API version introducing
+    // each) and optional features (e.g. "events_db").
+    //
+    // For clients to adapt to older daemons, instead of probing methods.
+    #[method(name = "getCapabilities")]
+    async fn get_capabilities(&self) -> RpcResult<CapabilitiesResponse>;
+
     #[method(name = "workdirCommand")]
     async fn workdir_command(&self, workdir: String, command: String)
         -> RpcResult<SuccessResponse>;
@@ -722,6 +2012,12 @@ pub trait GeneralApi {
         data_uuid: Option<String>,
     ) -> RpcResult<WorkdirStatusResponse>;
 
+    // Status summary of every workdir in a single call (e.g. for a UI refresh).
+    //
+    // Same status as getWorkdirStatus, with the proxy port and link counts of getLinks.
+    #[method(name = "getWorkdirsStatus")]
+    async fn get_workdirs_status(&self) -> RpcResult<WorkdirsStatusResponse>;
+
     // Allow to modify the asui selection.
     //
     // Choices are "localnet", "devnet", "testnet" or "mainnet".
@@ -730,6 +2026,15 @@ pub trait GeneralApi {
     #[method(name = "setAsuiSelection")]
     async fn set_asui_selection(&self, workdir: String) -> RpcResult<SuccessResponse>;
 
+    // Same as setAsuiSelection, but done by the daemon itself (no shell command).
+    //
+    // The workdir must be initialized ("create" done, not necessarily started).
+    // Concurrent calls are serialized. getWorkdirsStatus reflects the change on return.
+    //
+    // info is "<workdir> is now active" or "<workdir> is already active".
+    #[method(name = "setActiveWorkdir")]
+    async fn set_active_workdir(&self, workdir: String) -> RpcResult<SuccessResponse>;
+
     // Notify the daemon to update its status for a specific workdir.
     //
     // Should be called only when a CLI command was executed and is known
@@ -739,6 +2044,156 @@ pub trait GeneralApi {
     // just trig an "immediate" refresh.
     #[method(name = "workdirRefresh")]
     async fn workdir_refresh(&self, workdir: String) -> RpcResult<SuccessResponse>;
+
+    // Change the log level of a module target (and its sub-modules) at runtime.
+    //
+    // Example: {"target": "suibase_daemon::proxy_server", "level": "debug"}
+    //
+    // Level is one of "off", "error", "warn", "info", "debug" or "trace".
+    //
+    // Not persisted. All levels are back to their default on daemon restart.
+    #[method(name = "setLogLevel")]
+    async fn set_log_level(&self, target: String, level: String) -> RpcResult<SuccessResponse>;
+
+    // Restart stats of the daemon auto-restarting threads (e.g. APIServer).
+    //
+    // Only threads that had at least one restart caused by a panic or an error are listed.
+    #[method(name = "getDaemonStats")]
+    async fn get_daemon_stats(&self) -> RpcResult<DaemonStatsResponse>;
+
+    // Self-diagnostic of the installation (e.g. for a "localnet start" failing).
+    //
+    // Scripts, workdirs state, sui binaries, keystores, orphaned sui/faucet processes,
+    // proxy ports, disk space and daemon lock. For the started workdirs, also the proxy
+    // and websocket connectivity.
+    //
+    // The checks run concurrently, each with a timeout (completes in a few seconds).
+    #[method(name = "getSystemCheck")]
+    async fn get_system_check(&self) -> RpcResult<SystemCheckResponse>;
+
+    // Adopt or terminate the sui and sui-faucet processes of a workdir.
+    //
+    // A process is adopted when its pid is the one recorded by the scripts (.state/*.pid)
+    // and it is running. Any other is an orphan (e.g. left by a crash, still holding a
+    // port or the database lock), terminated with SIGTERM then SIGKILL.
+    //
+    // Also done by workdirCommand before a "start".
+    #[method(name = "cleanupWorkdirProcesses")]
+    async fn cleanup_workdir_processes(
+        &self,
+        workdir: String,
+    ) -> RpcResult<WorkdirProcessesResponse>;
+
+    // Last lines of the log of a workdir process ("sui" or "sui-faucet").
+    //
+    // 'tail_lines' defaults to 100 (at most 10000). With 'grep', only the lines
+    // containing that substring (plain text, case sensitive).
+    //
+    // Completed from the most recent archive when the log was just rotated (see
+    // process_log_max_mb in suibase.yaml).
+    #[method(name = "getProcessLog")]
+    async fn get_process_log(
+        &self,
+        workdir: String,
+        process: String,
+        tail_lines: Option<u32>,
+        grep: Option<String>,
+    ) -> RpcResult<ProcessLogResponse>;
+
+    // URL of the local sui-explorer served by this daemon.
+    //
+    // The port may differ from sui_explorer_port when it was already in use. Append
+    // "/object/<id>?network=local" (or devnet, testnet, mainnet) for a deep link.
+    #[method(name = "getExplorerInfo")]
+    async fn get_explorer_info(&self) -> RpcResult<ExplorerInfoResponse>;
+
+    // Anonymized counters of the next telemetry report (see telemetry_enabled in the
+    // common suibase.yaml). Also in ~/suibase/workdirs/common/telemetry.json.
+    //
+    // Telemetry is disabled by default, then 'enabled' is false and there is no payload.
+    #[method(name = "getTelemetryPreview")]
+    async fn get_telemetry_preview(&self) -> RpcResult<TelemetryPreviewResponse>;
+
+    // SUI coins inventory of an address (default to the workdir active address).
+    //
+    // Coins are retrieved through the workdir proxy. Response is cached ~10 seconds.
+    #[method(name = "getGasInventory")]
+    async fn get_gas_inventory(
+        &self,
+        workdir: String,
+        address: Option<String>,
+    ) -> RpcResult<GasInventoryResponse>;
+
+    // Merge the SUI coins of an address (default to the workdir active address).
+    //
+    // Up to 'max_coins_per_tx' coins are merged per transaction (signed with the
+    // workdir keystore). Refused on mainnet unless 'confirm' is true.
+    #[method(name = "mergeGasCoins")]
+    async fn merge_gas_coins(
+        &self,
+        workdir: String,
+        address: Option<String>,
+        max_coins_per_tx: Option<u32>,
+        confirm: Option<bool>,
+    ) -> RpcResult<MergeGasCoinsResponse>;
+
+    // Save the localnet chain state under a name (localnet only).
+    //
+    // The localnet is stopped while its config and databases are copied, then
+    // restarted. Returns immediately, progress is polled with getJobStatus.
+    #[method(name = "snapshotLocalnet")]
+    async fn snapshot_localnet(&self, name: String) -> RpcResult<JobStatusResponse>;
+
+    // Replace the localnet chain state with a snapshot (localnet only).
+    //
+    // Refused when the snapshot was created by another sui version, unless
+    // 'force' is true. Returns immediately, progress is polled with getJobStatus.
+    #[method(name = "restoreLocalnet")]
+    async fn restore_localnet(
+        &self,
+        name: String,
+        force: Option<bool>,
+    ) -> RpcResult<JobStatusResponse>;
+
+    #[method(name = "listLocalnetSnapshots")]
+    async fn list_localnet_snapshots(&self) -> RpcResult<LocalnetSnapshotsResponse>;
+
+    #[method(name = "deleteLocalnetSnapshot")]
+    async fn delete_localnet_snapshot(&self, name: String) -> RpcResult<SuccessResponse>;
+
+    // Status of a job started by another method (e.g. snapshotLocalnet).
+    //
+    // Only the most recent jobs are kept, and not across daemon restarts.
+    #[method(name = "getJobStatus")]
+    async fn get_job_status(&self, job_id: u64) -> RpcResult<JobStatusResponse>;
+
+    // Bundle of the workdir setup, to reproduce it elsewhere (e.g. onboarding a
+    // teammate): the user suibase.yaml, the published-data and a manifest.
+    //
+    // 'keys' are the aliases or addresses of the keystore entries to include. None
+    // by default (these are private keys).
+    #[method(name = "exportWorkdirProfile")]
+    async fn export_workdir_profile(
+        &self,
+        workdir: String,
+        keys: Option<Vec<String>>,
+    ) -> RpcResult<ExportWorkdirProfileResponse>;
+
+    // Apply a bundle of exportWorkdirProfile into a workdir.
+    //
+    // Nothing is written on 'dry_run', or when there is a conflict (a link with the same
+    // alias but another definition, or a package already published with another id)
+    // unless 'force' is true. The keys of the bundle are imported only with
+    // 'import_keys', and never over an existing alias (only that key is skipped).
+    #[method(name = "importWorkdirProfile")]
+    async fn import_workdir_profile(
+        &self,
+        workdir: String,
+        profile: WorkdirProfile,
+        dry_run: Option<bool>,
+        force: Option<bool>,
+        import_keys: Option<bool>,
+    ) -> RpcResult<ImportWorkdirProfileResponse>;
 }
 
 #[rpc(server)]
@@ -749,6 +2204,7 @@ pub trait PackagesApi {
         workdir: String,
         after_ts: Option<String>,
         last_ts: Option<String>,
+        filters: Option<serde_json::Value>,
     ) -> RpcResult<WorkdirSuiEventsResponse>;
 
     #[method(name = "getWorkdirPackages")]
@@ -759,6 +2215,11 @@ pub trait PackagesApi {
         data_uuid: Option<String>,
     ) -> RpcResult<WorkdirPackagesResponse>;
 
+    // Delivery metrics of the Sui event subscriptions of the workdir (one per
+    // monitored package), as of the last audit of its websocket worker.
+    #[method(name = "getSubscriptions")]
+    async fn get_subscriptions(&self, workdir: String) -> RpcResult<SubscriptionsResponse>;
+
     #[method(name = "prePublish")]
     async fn pre_publish(
         &self,
@@ -1,31 +1,288 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
 use axum::async_trait;
 
-use common::basic_types::AdminControllerTx;
+use common::basic_types::{AdminControllerTx, WorkdirIdx, AUTO_THREAD_STATS, LOG_CONTROL};
+use common::shared_types::WorkdirState;
 use jsonrpsee::core::RpcResult;
 
 use crate::admin_controller::AdminController;
-use crate::shared_types::{Globals, GlobalsWorkdirsST};
+use crate::shared_types::{
+    build_gas_inventory, check_daemon_lock, check_disk_space, check_keystore, check_proxy_port,
+    check_proxy_rpc, check_restore_version, check_scripts, check_sui_binary, check_time_skew,
+    check_websocket, check_workdir_processes, check_workdir_state, cleanup_workdir_processes,
+    create_snapshot, delete_snapshot, export_workdir_profile, fetch_gas_coins, get_snapshot,
+    import_workdir_profile, is_port_free, is_valid_snapshot_name, is_valid_sui_id,
+    is_workdir_initialized, list_snapshots, next_merge_batch, parse_active_address,
+    parse_sui_version_output, parse_suibase_version, parse_tx_digest, process_actions_summary,
+    process_log_archives, process_log_path, read_active_workdir, restore_snapshot,
+    tail_process_log, with_check_timeout, worst_status, write_active_workdir, GasCoin, Globals,
+    GlobalsWorkdirsST, InputPort, LinkRole, ProfileImportOptions, WorkdirProcessKind,
+    DEFAULT_PROCESS_LOG_TAIL_LINES, GAS_INVENTORY_CACHE_DURATION, MAX_PROCESS_LOG_TAIL_LINES,
+    MERGE_DEFAULT_COINS_PER_TX, MERGE_GAS_BUDGET, MERGE_MAX_TXS, PROCESS_ACTION_ADOPTED,
+    PROCESS_ACTION_FAILED, PROCESS_TERM_TIMEOUT, SYSTEM_CHECK_TIMEOUT, WORKDIRS_KEYS,
+    WORKDIRS_SUI_SCRIPTS, WORKDIR_IDX_LOCALNET,
+};
+use crate::workers::websocket_url;
 
 use super::{
-    GeneralApiServer, Header, RpcInputError, RpcSuibaseError, SuccessResponse, VersionsResponse,
-    WorkdirStatusResponse,
+    link_status, CapabilitiesResponse, DaemonStatsResponse, ExplorerInfoResponse,
+    ExportWorkdirProfileResponse, GasInventoryResponse, GeneralApiServer, Header,
+    ImportWorkdirProfileResponse, JobStatusResponse, LinksHealthCount, LocalnetSnapshotsResponse,
+    MergeGasCoinsResponse, ProcessLogResponse, RegisteredMethods, RpcInputError, RpcSuibaseError,
+    StartupPhasesStats, SuccessResponse, SystemCheckItem, SystemCheckResponse,
+    TelemetryPreviewResponse, ThreadRestartStats, VersionsResponse, WebhookDeliveryStats,
+    WorkdirProcessAction, WorkdirProcessesResponse, WorkdirProfile, WorkdirStartupStats,
+    WorkdirStatusResponse, WorkdirStatusSummary, WorkdirsStatusResponse, WorkerRestartStats,
+    API_FEATURES, API_VERSION,
 };
 
 use super::def_header::Versioned;
 
+// getWorkdirStatus response from the globals (same uuids as the globals).
+fn workdir_status_response(ui: &Versioned<WorkdirStatusResponse>) -> WorkdirStatusResponse {
+    let mut resp = ui.get_data().clone();
+    resp.header.set_from_uuids(ui.get_uuid());
+    resp
+}
+
+// The proxy part of a getWorkdirsStatus entry (same link status as getLinks).
+fn set_proxy_summary(summary: &mut WorkdirStatusSummary, input_port: &InputPort) {
+    summary.proxy_port = input_port.actual_port_number();
+
+    let mut links = LinksHealthCount::default();
+    for (_, target_server) in input_port.target_servers.iter() {
+        if target_server.role() == LinkRole::MonitorOnly {
+            links.monitor_only += 1;
+            continue;
+        }
+        match link_status(&target_server.stats) {
+            "OK" => links.ok += 1, // Includes the DEGRADED ones (see health_score).
+            "PROBING" => links.probing += 1,
+            "MAINTENANCE" => links.maintenance += 1,
+            "" => links.initializing += 1,
+            _ => links.down += 1,
+        }
+    }
+    summary.links = links;
+
+    summary.warnings = input_port
+        .proxy_tls_error()
+        .into_iter()
+        .chain(input_port.proxy_port_error())
+        .cloned()
+        .chain(input_port.config_warnings().iter().cloned())
+        .collect();
+}
+
 pub struct GeneralApiImpl {
     pub globals: Globals,
     pub admctrl_tx: AdminControllerTx,
+    client: reqwest::Client, // For requests through the proxy server.
+    registered_methods: RegisteredMethods, // For getCapabilities.
 }
 
 impl GeneralApiImpl {
-    pub fn new(globals: Globals, admctrl_tx: AdminControllerTx) -> Self {
+    pub fn new(
+        globals: Globals,
+        admctrl_tx: AdminControllerTx,
+        registered_methods: RegisteredMethods,
+    ) -> Self {
         Self {
             globals,
             admctrl_tx,
+            registered_methods,
+            // Only used toward our own proxy on localhost, which may use a
+            // self-signed cert (see proxy_tls).
+            client: reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    // Validate the address param, or get the active address of the workdir when None.
+    async fn resolve_address(
+        &self,
+        workdir_idx: WorkdirIdx,
+        workdir: &str,
+        address: Option<String>,
+    ) -> RpcResult<String> {
+        if let Some(address) = address {
+            if !is_valid_sui_id(&address) {
+                return Err(RpcInputError::InvalidParams("address".to_string(), address).into());
+            }
+            return Ok(address);
+        }
+
+        let cmd_resp = match AdminController::send_shell_exec(
+            &self.admctrl_tx,
+            workdir_idx,
+            format!(
+                "{} client active-address",
+                WORKDIRS_SUI_SCRIPTS[workdir_idx as usize]
+            ),
+        )
+        .await
+        {
+            Ok(cmd_resp) => cmd_resp,
+            Err(e) => format!("Error: {e}"),
+        };
+
+        match parse_active_address(&cmd_resp) {
+            Some(address) => Ok(address),
+            None => Err(RpcSuibaseError::NotFound(format!(
+                "{} active address not found [{}]",
+                workdir, cmd_resp
+            ))
+            .into()),
+        }
+    }
+
+    // Initializing error until the first status of the workdir (instead of waiting
+    // behind its initialization, see startup.rs).
+    async fn check_status_known(&self, workdir_idx: WorkdirIdx, workdir: &str) -> RpcResult<()> {
+        if self
+            .globals
+            .get_status(workdir_idx)
+            .read()
+            .await
+            .ui
+            .is_some()
+        {
+            return Ok(());
+        }
+        Err(RpcSuibaseError::Initializing(format!(
+            "{} status not yet known, retry shortly",
+            workdir
+        ))
+        .into())
+    }
+
+    async fn get_proxy_url(&self, workdir: &str) -> RpcResult<String> {
+        let globals_read_guard = self.globals.proxy.read().await;
+        let globals = &*globals_read_guard;
+        match globals.find_input_port_by_name(workdir) {
+            Some(input_port) if input_port.is_proxy_enabled() => {
+                let scheme = if input_port.is_proxy_tls() {
+                    "https"
+                } else {
+                    "http"
+                };
+                Ok(format!(
+                    "{}://localhost:{}",
+                    scheme,
+                    input_port.listening_port_number()
+                ))
+            }
+            _ => Err(
+                RpcSuibaseError::NotEnabled(format!("{} proxy server not enabled", workdir)).into(),
+            ),
+        }
+    }
+
+    async fn fetch_gas_coins(&self, proxy_url: &str, address: &str) -> RpcResult<Vec<GasCoin>> {
+        match fetch_gas_coins(&self.client, proxy_url, address).await {
+            Ok(coins) => Ok(coins),
+            Err(e) => Err(RpcSuibaseError::InternalError(e.to_string()).into()),
+        }
+    }
+
+    // Version of the sui binary of a workdir (e.g. "1.30.1-abc123"), None if unknown.
+    async fn sui_version(&self, workdir_idx: WorkdirIdx) -> Option<String> {
+        let cmd = format!("{} --version", WORKDIRS_SUI_SCRIPTS[workdir_idx as usize]);
+        match AdminController::send_shell_exec(&self.admctrl_tx, workdir_idx, cmd).await {
+            Ok(cmd_resp) => parse_sui_version_output(&cmd_resp),
+            Err(_) => None,
         }
     }
 
+    async fn localnet_sui_version(&self) -> Option<String> {
+        self.sui_version(WORKDIR_IDX_LOCALNET).await
+    }
+
+    // Index and path of a workdir, by name.
+    async fn workdir_by_name(&self, workdir: &str) -> RpcResult<(WorkdirIdx, PathBuf)> {
+        let workdir_idx =
+            match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir.to_string())
+                .await
+            {
+                Some(workdir_idx) => workdir_idx,
+                None => {
+                    return Err(RpcInputError::InvalidParams(
+                        "workdir".to_string(),
+                        workdir.to_string(),
+                    )
+                    .into())
+                }
+            };
+        match GlobalsWorkdirsST::get_workdir_by_idx(&self.globals, workdir_idx).await {
+            Some(wd) => Ok((workdir_idx, wd.path_cloned())),
+            None => Err(RpcSuibaseError::NotFound(format!("{} workdir not found", workdir)).into()),
+        }
+    }
+
+    async fn localnet_path(&self) -> RpcResult<PathBuf> {
+        match GlobalsWorkdirsST::get_workdir_by_idx(&self.globals, WORKDIR_IDX_LOCALNET).await {
+            Some(workdir) => Ok(workdir.path_cloned()),
+            None => Err(RpcSuibaseError::NotFound("localnet workdir not found".to_string()).into()),
+        }
+    }
+
+    // See cleanupWorkdirProcesses. The caller holds the api mutex of the workdir.
+    async fn cleanup_processes(&self, workdir_idx: WorkdirIdx) -> Vec<WorkdirProcessAction> {
+        match GlobalsWorkdirsST::get_workdir_by_idx(&self.globals, workdir_idx).await {
+            Some(wd) => cleanup_workdir_processes(wd.path(), PROCESS_TERM_TIMEOUT).await,
+            None => Vec::new(),
+        }
+    }
+
+    // Start a snapshot or restore in the background. Progress is in the job status.
+    async fn start_localnet_snapshot_job(
+        &self,
+        op: LocalnetSnapshotOp,
+        name: String,
+        sui_version: Option<String>,
+    ) -> RpcResult<JobStatusResponse> {
+        let workdir_path = self.localnet_path().await?;
+        let method = op.method();
+        let job = match self.globals.jobs.write().await.start(method, "localnet") {
+            Some(job) => job,
+            None => {
+                return Err(RpcSuibaseError::Busy(
+                    "another localnet job is in progress".to_string(),
+                )
+                .into())
+            }
+        };
+
+        // Used to wait for the node after the restart (skipped when no proxy).
+        let proxy_url = self.get_proxy_url("localnet").await.ok();
+
+        let job_params = LocalnetSnapshotJob {
+            op,
+            job_id: job.job_id,
+            name: name.clone(),
+            sui_version,
+            workdir_path,
+            proxy_url,
+            globals: self.globals.clone(),
+            admctrl_tx: self.admctrl_tx.clone(),
+            client: self.client.clone(),
+        };
+        tokio::spawn(job_params.run());
+
+        let mut resp = JobStatusResponse {
+            header: Header::default(),
+            job,
+        };
+        resp.header.method = method.to_string();
+        resp.header.key = Some(name);
+        Ok(resp)
+    }
+
     fn convert_set_active_cmd_resp_to_success_response(
         cmd_response: String,
         workdir_name: String,
@@ -98,6 +355,154 @@ impl GeneralApiImpl {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalnetSnapshotOp {
+    Snapshot,
+    Restore,
+}
+
+impl LocalnetSnapshotOp {
+    fn method(&self) -> &'static str {
+        match self {
+            LocalnetSnapshotOp::Snapshot => "snapshotLocalnet",
+            LocalnetSnapshotOp::Restore => "restoreLocalnet",
+        }
+    }
+}
+
+// How long to wait for the localnet node to respond after its restart.
+const LOCALNET_RESTART_TIMEOUT_SECS: u64 = 120;
+
+struct LocalnetSnapshotJob {
+    op: LocalnetSnapshotOp,
+    job_id: u64,
+    name: String,
+    sui_version: Option<String>, // Recorded in a new snapshot.
+    workdir_path: PathBuf,
+    proxy_url: Option<String>,
+    globals: Globals,
+    admctrl_tx: AdminControllerTx,
+    client: reqwest::Client,
+}
+
+impl LocalnetSnapshotJob {
+    async fn run(self) {
+        let result = {
+            // Serialized with the other API calls on localnet.
+            let _api_mutex_guard = self
+                .globals
+                .get_api_mutex(WORKDIR_IDX_LOCALNET)
+                .lock()
+                .await;
+            self.do_steps().await
+        };
+        match &result {
+            Ok(info) => log::info!("{} {}: {}", self.op.method(), self.name, info),
+            Err(e) => log::warn!("{} {} failed: {}", self.op.method(), self.name, e),
+        }
+
+        // The localnet was stopped/started... update the status now.
+        let _ = AdminController::send_event_update(&self.admctrl_tx, WORKDIR_IDX_LOCALNET).await;
+        self.globals.jobs.write().await.finish(self.job_id, result);
+    }
+
+    async fn set_progress(&self, progress: &str) {
+        self.globals
+            .jobs
+            .write()
+            .await
+            .set_progress(self.job_id, progress);
+    }
+
+    async fn localnet_command(&self, command: &str) -> Result<(), String> {
+        let cmd_resp = match AdminController::send_shell_exec(
+            &self.admctrl_tx,
+            WORKDIR_IDX_LOCALNET,
+            format!("localnet {}", command),
+        )
+        .await
+        {
+            Ok(cmd_resp) => cmd_resp,
+            Err(e) => format!("Error: {e}"),
+        };
+        if cmd_resp.trim_start().starts_with("Error:") {
+            return Err(format!("localnet {} failed: {}", command, cmd_resp.trim()));
+        }
+        Ok(())
+    }
+
+    async fn do_steps(&self) -> Result<String, String> {
+        self.set_progress("Stopping localnet").await;
+        self.localnet_command("stop").await?;
+
+        let copy_result = {
+            let op = self.op;
+            let name = self.name.clone();
+            let sui_version = self.sui_version.clone();
+            let workdir_path = self.workdir_path.clone();
+            self.set_progress(match op {
+                LocalnetSnapshotOp::Snapshot => "Copying chain state",
+                LocalnetSnapshotOp::Restore => "Restoring chain state",
+            })
+            .await;
+            tokio::task::spawn_blocking(move || match op {
+                LocalnetSnapshotOp::Snapshot => {
+                    create_snapshot(&workdir_path, &name, sui_version).map(|_| ())
+                }
+                LocalnetSnapshotOp::Restore => restore_snapshot(&workdir_path, &name),
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|copy_result| copy_result.map_err(|e| e.to_string()))
+        };
+
+        // Restart even when the copy failed (the chain state is then left untouched,
+        // except for a failed restore).
+        self.set_progress("Starting localnet").await;
+        self.localnet_command("start").await?;
+        if let Some(proxy_url) = &self.proxy_url {
+            self.set_progress("Waiting for the localnet node").await;
+            self.wait_for_node(proxy_url).await?;
+        }
+
+        copy_result?;
+        Ok(match self.op {
+            LocalnetSnapshotOp::Snapshot => format!("Snapshot [{}] created", self.name),
+            LocalnetSnapshotOp::Restore => format!("Snapshot [{}] restored", self.name),
+        })
+    }
+
+    async fn wait_for_node(&self, proxy_url: &str) -> Result<(), String> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sui_getLatestCheckpointSequenceNumber",
+            "params": [],
+        });
+        for _ in 0..LOCALNET_RESTART_TIMEOUT_SECS {
+            let resp = self
+                .client
+                .post(proxy_url)
+                .timeout(std::time::Duration::from_secs(1))
+                .json(&body)
+                .send()
+                .await;
+            if let Ok(resp) = resp {
+                if let Ok(resp) = resp.json::<serde_json::Value>().await {
+                    if resp.get("result").is_some() {
+                        return Ok(());
+                    }
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+        Err(format!(
+            "localnet node not responding after {} seconds",
+            LOCALNET_RESTART_TIMEOUT_SECS
+        ))
+    }
+}
+
 #[async_trait]
 impl GeneralApiServer for GeneralApiImpl {
     async fn workdir_command(
@@ -112,6 +517,7 @@ impl GeneralApiServer for GeneralApiImpl {
             Some(workdir_idx) => workdir_idx,
             None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
         };
+        self.check_status_known(workdir_idx, &workdir).await?;
 
         // Prevent shell injection by not allowing some bash ways to chain commands.
         if command.contains(';') || command.contains('&') || command.contains('|') {
@@ -127,6 +533,20 @@ impl GeneralApiServer for GeneralApiImpl {
         let mut api_mutex_guard = self.globals.get_api_mutex(workdir_idx).lock().await;
         let _api_mutex = &mut *api_mutex_guard;
 
+        // A process left by a previous run (e.g. after a crash) can make the start fail.
+        let mut cleanup_info = None;
+        if command.split_whitespace().next() == Some("start") {
+            let actions = self.cleanup_processes(workdir_idx).await;
+            if !actions.is_empty() {
+                let summary = process_actions_summary(&actions);
+                if actions.iter().any(|a| a.action == PROCESS_ACTION_FAILED) {
+                    resp.info = Some(format!("Error: start not done: {}", summary));
+                    return Ok(resp);
+                }
+                cleanup_info = Some(summary);
+            }
+        }
+
         let cmd_resp = match AdminController::send_shell_exec(
             &self.admctrl_tx,
             workdir_idx,
@@ -143,7 +563,10 @@ impl GeneralApiServer for GeneralApiImpl {
 
         // Return the response to the caller... can't interpret if successful.
         resp.result = true;
-        resp.info = Some(cmd_resp);
+        resp.info = match cleanup_info {
+            Some(cleanup_info) => Some(format!("{}\n{}", cleanup_info, cmd_resp)),
+            None => Some(cmd_resp),
+        };
 
         Ok(resp)
     }
@@ -158,8 +581,8 @@ impl GeneralApiServer for GeneralApiImpl {
         };
 
         if workdir.is_none() {
-            return Err(RpcSuibaseError::InfoError(
-                "Backend initializing. Active directory not yet identified".to_string(),
+            return Err(RpcSuibaseError::Initializing(
+                "Active directory not yet identified".to_string(),
             )
             .into());
         }
@@ -192,10 +615,9 @@ impl GeneralApiServer for GeneralApiImpl {
                 resp.versions.push(hdr);
                 resp.asui_selection = asui_selection;
             } else {
-                return Err(RpcSuibaseError::InfoError(
-                    "Backend initializing. Status not yet retreived".to_string(),
-                )
-                .into());
+                return Err(
+                    RpcSuibaseError::Initializing("Status not yet retreived".to_string()).into(),
+                );
             }
         }
 
@@ -236,6 +658,23 @@ impl GeneralApiServer for GeneralApiImpl {
         Ok(resp)
     }
 
+    async fn get_capabilities(&self) -> RpcResult<CapabilitiesResponse> {
+        let mut resp = CapabilitiesResponse::new();
+        resp.header.method = "getCapabilities".to_string();
+        resp.header.semver = Some(env!("CARGO_PKG_VERSION").to_string());
+        resp.api_version = API_VERSION.to_string();
+        resp.methods = match self.registered_methods.get() {
+            Some(methods) => methods.clone(),
+            None => {
+                return Err(
+                    RpcSuibaseError::Initializing("Methods not yet registered".to_string()).into(),
+                )
+            }
+        };
+        resp.features = API_FEATURES.iter().map(|f| f.to_string()).collect();
+        Ok(resp)
+    }
+
     async fn get_workdir_status(
         &self,
         workdir: String,
@@ -272,18 +711,70 @@ impl GeneralApiServer for GeneralApiImpl {
                         return Err(RpcSuibaseError::OutdatedUUID().into());
                     }
                 }
-                let mut resp = ui.get_data().clone();
-                resp.header.set_from_uuids(ui.get_uuid());
-                return Ok(resp);
+                return Ok(workdir_status_response(ui));
             } else {
-                return Err(RpcSuibaseError::InfoError(
-                    "Backend still initializing. Status not yet known".to_string(),
-                )
-                .into());
+                return Err(
+                    RpcSuibaseError::Initializing("Status not yet known".to_string()).into(),
+                );
             }
         }
     }
 
+    async fn get_workdirs_status(&self) -> RpcResult<WorkdirsStatusResponse> {
+        let mut resp = WorkdirsStatusResponse::new();
+        resp.header.method = "getWorkdirsStatus".to_string();
+        resp.header.semver = Some(env!("CARGO_PKG_VERSION").to_string());
+
+        let asui_selection = self.globals.get_asui_selection().await;
+
+        // Locks are taken one at a time, in the order of the workdir indexes and then
+        // the proxy and subscriptions globals (never two held at the same time, as for
+        // getLinks).
+        for (workdir_idx, workdir) in WORKDIRS_KEYS.iter().enumerate() {
+            let mut summary = WorkdirStatusSummary::new(workdir.to_string());
+            summary.is_active = asui_selection.as_deref() == Some(*workdir);
+
+            let globals_read_guard = self
+                .globals
+                .get_status(workdir_idx as WorkdirIdx)
+                .read()
+                .await;
+            let globals = &*globals_read_guard;
+            if let Some(ui) = &globals.ui {
+                let status = workdir_status_response(ui);
+                summary.status = status.status;
+                summary.status_info = status.status_info;
+                summary.status_since = status.status_since;
+                summary.status_cause = status.status_cause;
+            } else {
+                summary.status = Some(WorkdirState::Initializing.to_string());
+            }
+            resp.workdirs.push(summary);
+        }
+
+        {
+            let globals_read_guard = self.globals.proxy.read().await;
+            let globals = &*globals_read_guard;
+            for summary in resp.workdirs.iter_mut() {
+                if let Some(input_port) = globals.find_input_port_by_name(&summary.workdir) {
+                    set_proxy_summary(summary, input_port);
+                }
+            }
+        }
+
+        {
+            let globals_read_guard = self.globals.subscriptions.read().await;
+            let globals = &*globals_read_guard;
+            for (workdir_idx, summary) in resp.workdirs.iter_mut().enumerate() {
+                summary.degraded_subscriptions =
+                    globals.degraded_reasons(workdir_idx as WorkdirIdx);
+            }
+        }
+
+        resp.degraded_threads = AUTO_THREAD_STATS.degraded();
+        Ok(resp)
+    }
+
     async fn set_asui_selection(&self, workdir: String) -> RpcResult<SuccessResponse> {
         // Verify workdir param is OK and get its corresponding workdir_idx.
         let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
@@ -292,6 +783,7 @@ impl GeneralApiServer for GeneralApiImpl {
             Some(workdir_idx) => workdir_idx,
             None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
         };
+        self.check_status_known(workdir_idx, &workdir).await?;
         let mut resp = SuccessResponse::new();
         resp.header.method = "setAsuiSelection".to_string();
         resp.header.key = Some(workdir.clone());
@@ -331,6 +823,56 @@ impl GeneralApiServer for GeneralApiImpl {
         Ok(resp)
     }
 
+    async fn set_active_workdir(&self, workdir: String) -> RpcResult<SuccessResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let workdirs_path = self.globals.workdirs.read().await.path().to_path_buf();
+        if !is_workdir_initialized(&workdirs_path.join(&workdir)) {
+            return Err(RpcSuibaseError::NotFound(format!(
+                "{} workdir not initialized. Do '{} start' or '{} create'",
+                workdir, workdir, workdir
+            ))
+            .into());
+        }
+
+        // Serialized with any other switch, whatever the workdir.
+        let (is_changed, previous) = {
+            let mut asui_selection = self.globals.lock_asui_selection().await;
+            let previous = read_active_workdir(&workdirs_path);
+            let is_changed = write_active_workdir(&workdirs_path, &workdir)
+                .map_err(|e| RpcSuibaseError::FileAccessError(e.to_string()))?;
+            *asui_selection = Some(workdir.clone());
+            (is_changed, previous)
+        };
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = "setActiveWorkdir".to_string();
+        resp.header.key = Some(workdir.clone());
+        resp.result = true;
+        if !is_changed {
+            resp.info = Some(format!("{} is already active", workdir));
+            return Ok(resp);
+        }
+        log::info!("setActiveWorkdir: {} is now active", workdir);
+        resp.info = Some(format!("{} is now active", workdir));
+
+        // Update the status of both workdirs now (instead of waiting for next audit).
+        let _ = AdminController::send_event_update(&self.admctrl_tx, workdir_idx).await;
+        if let Some(previous) = previous {
+            if let Some(previous_idx) =
+                GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &previous).await
+            {
+                let _ = AdminController::send_event_update(&self.admctrl_tx, previous_idx).await;
+            }
+        }
+        Ok(resp)
+    }
+
     async fn workdir_refresh(&self, workdir: String) -> RpcResult<SuccessResponse> {
         // Verify workdir param is OK and get its corresponding workdir_idx.
         let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
@@ -349,4 +891,586 @@ impl GeneralApiServer for GeneralApiImpl {
         resp.result = true;
         Ok(resp)
     }
+
+    async fn set_log_level(&self, target: String, level: String) -> RpcResult<SuccessResponse> {
+        if let Err(e) = LOG_CONTROL.set_level(&target, &level) {
+            log::warn!("setLogLevel: {}", e);
+            if target.trim().is_empty() {
+                return Err(RpcInputError::InvalidParams("target".to_string(), target).into());
+            }
+            return Err(RpcInputError::InvalidParams("level".to_string(), level).into());
+        }
+        log::info!("setLogLevel: {} set to {}", target, level);
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = "setLogLevel".to_string();
+        resp.header.key = Some(target);
+        resp.result = true;
+        Ok(resp)
+    }
+
+    async fn get_daemon_stats(&self) -> RpcResult<DaemonStatsResponse> {
+        let mut resp = DaemonStatsResponse::new();
+        resp.header.method = "getDaemonStats".to_string();
+        resp.threads = AUTO_THREAD_STATS
+            .snapshot()
+            .into_iter()
+            .map(|(name, stats)| ThreadRestartStats {
+                name,
+                restart_count: stats.restart_count,
+                cool_off_count: stats.cool_off_count,
+                degraded: stats.is_degraded(),
+                failed: stats.failed,
+                last_error: stats.last_error,
+                last_backtrace: stats.last_backtrace,
+                last_restart: stats.last_restart.map(|t| t.to_rfc3339()),
+                cool_off_until: stats.cool_off_until.map(|t| t.to_rfc3339()),
+            })
+            .collect();
+        resp.worker_restarts = self
+            .globals
+            .worker_restarts
+            .snapshot()
+            .into_iter()
+            .map(|restart| WorkerRestartStats {
+                name: restart.name,
+                workdir: WORKDIRS_KEYS
+                    .get(restart.workdir_idx as usize)
+                    .unwrap_or(&"unknown")
+                    .to_string(),
+                restart_count: restart.restart_count,
+                last_error: restart.last_error,
+                last_restart: restart.last_restart.to_rfc3339(),
+                failed: restart.failed,
+            })
+            .collect();
+        let webhook_stats = &self.globals.webhook_stats;
+        resp.webhooks = WebhookDeliveryStats {
+            delivered: webhook_stats.delivered(),
+            retried: webhook_stats.retried(),
+            dropped: webhook_stats.dropped(),
+        };
+        let startup = self.globals.startup.snapshot();
+        resp.startup = StartupPhasesStats {
+            api_ready_ms: startup.api_ready_ms,
+            initialized_ms: startup.initialized_ms(),
+            workdirs: WORKDIRS_KEYS
+                .iter()
+                .zip(startup.workdirs)
+                .map(|(workdir, phases)| WorkdirStartupStats {
+                    workdir: workdir.to_string(),
+                    config_loaded_ms: phases.config_loaded_ms,
+                    status_known_ms: phases.status_known_ms,
+                })
+                .collect(),
+        };
+        Ok(resp)
+    }
+
+    async fn get_explorer_info(&self) -> RpcResult<ExplorerInfoResponse> {
+        let mut resp = ExplorerInfoResponse::new();
+        resp.header.method = "getExplorerInfo".to_string();
+        let config_guard = self.globals.config.read().await;
+        resp.configured_port = config_guard.explorer_port;
+        resp.port = config_guard.explorer_port_active;
+        resp.url = resp.port.map(|port| format!("http://localhost:{}", port));
+        resp.info = config_guard.explorer_port_error.clone();
+        Ok(resp)
+    }
+
+    async fn get_telemetry_preview(&self) -> RpcResult<TelemetryPreviewResponse> {
+        let mut resp = TelemetryPreviewResponse::new();
+        resp.header.method = "getTelemetryPreview".to_string();
+        let config_guard = self.globals.config.read().await;
+        resp.payload = config_guard.telemetry_preview.clone();
+        resp.enabled = resp.payload.is_some();
+        resp.endpoint = config_guard.telemetry_endpoint.clone();
+        Ok(resp)
+    }
+
+    async fn get_system_check(&self) -> RpcResult<SystemCheckResponse> {
+        type CheckFuture = Pin<Box<dyn Future<Output = SystemCheckItem> + Send>>;
+        let mut checks: Vec<(String, CheckFuture)> = Vec::new();
+
+        // Everything needed is copied first, so no lock is held while checking.
+        let suibase_path = PathBuf::from(self.globals.workdirs.read().await.suibase_home());
+        {
+            let path = suibase_path.clone();
+            checks.push((
+                "scripts".to_string(),
+                Box::pin(async move { check_scripts(&path) }),
+            ));
+        }
+        checks.push((
+            "disk_space".to_string(),
+            Box::pin(check_disk_space(suibase_path.clone())),
+        ));
+        checks.push((
+            "daemon_lock".to_string(),
+            Box::pin(check_daemon_lock(suibase_path.clone())),
+        ));
+
+        // Only the workdirs installed (e.g. devnet is often never used).
+        for (workdir_idx, workdir) in WORKDIRS_KEYS.iter().enumerate() {
+            let workdir_idx = workdir_idx as WorkdirIdx;
+            let path = match GlobalsWorkdirsST::get_workdir_by_idx(&self.globals, workdir_idx).await
+            {
+                Some(wd) if wd.path().is_dir() => wd.path_cloned(),
+                _ => continue,
+            };
+            let (wd, p) = (workdir.to_string(), path.clone());
+            checks.push((
+                format!("{}.state", wd),
+                Box::pin(async move { check_workdir_state(&wd, &p) }),
+            ));
+            let (wd, p) = (workdir.to_string(), path.clone());
+            checks.push((
+                format!("{}.sui_binary", wd),
+                Box::pin(async move { check_sui_binary(&wd, &p) }),
+            ));
+            let (wd, p) = (workdir.to_string(), path.clone());
+            checks.push((
+                format!("{}.keystore", wd),
+                Box::pin(async move { check_keystore(&wd, &p) }),
+            ));
+            let wd = workdir.to_string();
+            checks.push((
+                format!("{}.processes", wd),
+                Box::pin(async move { check_workdir_processes(&wd, &path) }),
+            ));
+        }
+
+        {
+            let globals_read_guard = self.globals.proxy.read().await;
+            let globals = &*globals_read_guard;
+            for (_, input_port) in globals.input_ports.iter() {
+                if !input_port.is_proxy_enabled() {
+                    continue;
+                }
+                let workdir = input_port.workdir_name().to_string();
+                let port = input_port.listening_port_number();
+                let item = check_proxy_port(
+                    &workdir,
+                    port,
+                    input_port.is_proxy_server_running(),
+                    input_port.proxy_port_error(),
+                    is_port_free,
+                );
+                checks.push((item.name.clone(), Box::pin(async move { item })));
+
+                if input_port.time_skew_threshold_secs() != 0 {
+                    let item = check_time_skew(
+                        &workdir,
+                        &input_port.time_skews(),
+                        input_port.time_skew_threshold_secs(),
+                    );
+                    checks.push((item.name.clone(), Box::pin(async move { item })));
+                }
+
+                // Connectivity is expected only when started by the user.
+                if !input_port.is_user_request_start() {
+                    continue;
+                }
+                let scheme = if input_port.is_proxy_tls() {
+                    "https"
+                } else {
+                    "http"
+                };
+                let proxy_url = format!("{}://localhost:{}", scheme, port);
+                checks.push((
+                    format!("{}.proxy", workdir),
+                    Box::pin(check_proxy_rpc(
+                        self.client.clone(),
+                        workdir.clone(),
+                        proxy_url,
+                    )),
+                ));
+                if let Some(url) = websocket_url(input_port.workdir_idx()) {
+                    checks.push((
+                        format!("{}.websocket", workdir),
+                        Box::pin(check_websocket(workdir, url.to_string())),
+                    ));
+                }
+            }
+        }
+
+        let checks = checks
+            .into_iter()
+            .map(|(name, check)| with_check_timeout(name, SYSTEM_CHECK_TIMEOUT, check));
+
+        let mut resp = SystemCheckResponse::new();
+        resp.header.method = "getSystemCheck".to_string();
+        resp.checks = futures::future::join_all(checks).await;
+        resp.status = worst_status(&resp.checks);
+        Ok(resp)
+    }
+
+    async fn cleanup_workdir_processes(
+        &self,
+        workdir: String,
+    ) -> RpcResult<WorkdirProcessesResponse> {
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        // Not while a workdirCommand might be starting/stopping the processes.
+        let _api_mutex_guard = self.globals.get_api_mutex(workdir_idx).lock().await;
+        let actions = self.cleanup_processes(workdir_idx).await;
+
+        // Update the status now (instead of waiting for next audit).
+        if actions.iter().any(|a| a.action != PROCESS_ACTION_ADOPTED) {
+            let _ = AdminController::send_event_update(&self.admctrl_tx, workdir_idx).await;
+        }
+
+        let mut resp = WorkdirProcessesResponse::new();
+        resp.header.method = "cleanupWorkdirProcesses".to_string();
+        resp.header.key = Some(workdir);
+        resp.result = !actions.iter().any(|a| a.action == PROCESS_ACTION_FAILED);
+        resp.actions = actions;
+        Ok(resp)
+    }
+
+    async fn get_process_log(
+        &self,
+        workdir: String,
+        process: String,
+        tail_lines: Option<u32>,
+        grep: Option<String>,
+    ) -> RpcResult<ProcessLogResponse> {
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let kind = match WorkdirProcessKind::parse(&process) {
+            Some(kind) => kind,
+            None => return Err(RpcInputError::InvalidParams("process".to_string(), process).into()),
+        };
+        let tail_lines = tail_lines
+            .unwrap_or(DEFAULT_PROCESS_LOG_TAIL_LINES)
+            .min(MAX_PROCESS_LOG_TAIL_LINES);
+        let grep = grep.filter(|grep| !grep.is_empty());
+
+        let log_path = match GlobalsWorkdirsST::get_workdir_by_idx(&self.globals, workdir_idx).await
+        {
+            Some(wd) => process_log_path(wd.path(), kind),
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        // Can be a large file (and a decompression), so not on the async runtime.
+        let path = log_path.clone();
+        let lines = tokio::task::spawn_blocking(move || {
+            tail_process_log(&path, tail_lines, grep.as_deref())
+        })
+        .await
+        .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?
+        .map_err(|e| RpcSuibaseError::FileAccessError(format!("{:?} ({})", log_path, e)))?;
+
+        let mut resp = ProcessLogResponse::new();
+        resp.header.method = "getProcessLog".to_string();
+        resp.header.key = Some(workdir);
+        resp.process = kind.as_str().to_string();
+        resp.size = std::fs::metadata(&log_path).map_or(0, |metadata| metadata.len());
+        resp.archives = process_log_archives(&log_path)
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        resp.path = log_path.to_string_lossy().to_string();
+        resp.lines = lines;
+        Ok(resp)
+    }
+
+    async fn get_gas_inventory(
+        &self,
+        workdir: String,
+        address: Option<String>,
+    ) -> RpcResult<GasInventoryResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        self.check_status_known(workdir_idx, &workdir).await?;
+
+        let mut api_mutex_guard = self.globals.get_api_mutex(workdir_idx).lock().await;
+        let api_mutex = &mut *api_mutex_guard;
+
+        if let Some((time, cached_address, resp)) = &api_mutex.last_gas_inventory {
+            if *cached_address == address && time.elapsed() < GAS_INVENTORY_CACHE_DURATION {
+                return Ok(resp.clone());
+            }
+        }
+
+        let proxy_url = self.get_proxy_url(&workdir).await?;
+        let resolved_address = self
+            .resolve_address(workdir_idx, &workdir, address.clone())
+            .await?;
+        let coins = self.fetch_gas_coins(&proxy_url, &resolved_address).await?;
+
+        let mut resp = build_gas_inventory(&resolved_address, &coins);
+        resp.header.method = "getGasInventory".to_string();
+        resp.header.key = Some(workdir);
+        api_mutex.last_gas_inventory = Some((tokio::time::Instant::now(), address, resp.clone()));
+        Ok(resp)
+    }
+
+    async fn merge_gas_coins(
+        &self,
+        workdir: String,
+        address: Option<String>,
+        max_coins_per_tx: Option<u32>,
+        confirm: Option<bool>,
+    ) -> RpcResult<MergeGasCoinsResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        self.check_status_known(workdir_idx, &workdir).await?;
+
+        // Real funds... the caller must be explicit.
+        if workdir == "mainnet" && confirm != Some(true) {
+            return Err(RpcInputError::InvalidParams(
+                "confirm".to_string(),
+                "must be true to merge coins on mainnet".to_string(),
+            )
+            .into());
+        }
+
+        // Range already validated (see API_PARAMS).
+        let max_coins_per_tx = max_coins_per_tx.unwrap_or(MERGE_DEFAULT_COINS_PER_TX);
+
+        let proxy_url = self.get_proxy_url(&workdir).await?;
+        let address = self.resolve_address(workdir_idx, &workdir, address).await?;
+
+        let mut resp = MergeGasCoinsResponse::new();
+        resp.header.method = "mergeGasCoins".to_string();
+        resp.header.key = Some(workdir.clone());
+        resp.address = address.clone();
+
+        let mut coins = self.fetch_gas_coins(&proxy_url, &address).await?;
+        resp.coin_count_before = coins.len() as u64;
+
+        // The api_mutex is held for one transaction at a time (not for the whole merge),
+        // so the other calls for this workdir wait for at most one transaction.
+        for _ in 0..MERGE_MAX_TXS {
+            let batch = next_merge_batch(&coins, max_coins_per_tx as usize);
+            if batch.is_empty() {
+                break;
+            }
+            if !batch.iter().all(|coin_id| is_valid_sui_id(coin_id)) {
+                resp.info = Some(format!("Unexpected coin ID in {:?}", batch));
+                break;
+            }
+
+            // pay-all-sui to self merges all the input coins into one (the first pays the gas).
+            let cmd = format!(
+                "{} client pay-all-sui --input-coins {} --recipient {} --gas-budget {} --json",
+                WORKDIRS_SUI_SCRIPTS[workdir_idx as usize],
+                batch.join(" "),
+                address,
+                MERGE_GAS_BUDGET
+            );
+            let cmd_resp = {
+                let mut api_mutex_guard = self.globals.get_api_mutex(workdir_idx).lock().await;
+                let api_mutex = &mut *api_mutex_guard;
+                api_mutex.last_gas_inventory = None;
+                match AdminController::send_shell_exec(&self.admctrl_tx, workdir_idx, cmd).await {
+                    Ok(cmd_resp) => cmd_resp,
+                    Err(e) => format!("Error: {e}"),
+                }
+            };
+            match parse_tx_digest(&cmd_resp) {
+                Ok(digest) => resp.tx_digests.push(digest),
+                Err(e) => {
+                    log::warn!("mergeGasCoins {}: {}", workdir, e);
+                    resp.info = Some(format!("Merge stopped: {}", e));
+                    break;
+                }
+            }
+
+            // Coins object versions changed, so always start again from a fresh list.
+            let prev_count = coins.len();
+            coins = self.fetch_gas_coins(&proxy_url, &address).await?;
+            if coins.len() >= prev_count {
+                resp.info = Some("Merge stopped: coin count did not decrease".to_string());
+                break;
+            }
+        }
+        resp.coin_count_after = coins.len() as u64;
+
+        Ok(resp)
+    }
+
+    async fn snapshot_localnet(&self, name: String) -> RpcResult<JobStatusResponse> {
+        if !is_valid_snapshot_name(&name) {
+            return Err(RpcInputError::InvalidParams("name".to_string(), name).into());
+        }
+        let workdir_path = self.localnet_path().await?;
+        if get_snapshot(&workdir_path, &name).is_ok() {
+            return Err(
+                RpcSuibaseError::Conflict(format!("snapshot [{}] already exists", name)).into(),
+            );
+        }
+        let sui_version = self.localnet_sui_version().await;
+        self.start_localnet_snapshot_job(LocalnetSnapshotOp::Snapshot, name, sui_version)
+            .await
+    }
+
+    async fn restore_localnet(
+        &self,
+        name: String,
+        force: Option<bool>,
+    ) -> RpcResult<JobStatusResponse> {
+        if !is_valid_snapshot_name(&name) {
+            return Err(RpcInputError::InvalidParams("name".to_string(), name).into());
+        }
+        let workdir_path = self.localnet_path().await?;
+        let snapshot = match get_snapshot(&workdir_path, &name) {
+            Ok(snapshot) => snapshot,
+            Err(e) => return Err(RpcSuibaseError::NotFound(e.to_string()).into()),
+        };
+
+        // The databases are not guaranteed compatible across sui versions.
+        let sui_version = self.localnet_sui_version().await;
+        if let Err(e) = check_restore_version(
+            snapshot.sui_version.as_deref(),
+            sui_version.as_deref(),
+            force.unwrap_or(false),
+        ) {
+            return Err(RpcSuibaseError::Conflict(e).into());
+        }
+        self.start_localnet_snapshot_job(LocalnetSnapshotOp::Restore, name, None)
+            .await
+    }
+
+    async fn list_localnet_snapshots(&self) -> RpcResult<LocalnetSnapshotsResponse> {
+        let workdir_path = self.localnet_path().await?;
+        let mut resp = LocalnetSnapshotsResponse::new();
+        resp.header.method = "listLocalnetSnapshots".to_string();
+        resp.header.key = Some("localnet".to_string());
+        resp.snapshots = list_snapshots(&workdir_path);
+        Ok(resp)
+    }
+
+    async fn delete_localnet_snapshot(&self, name: String) -> RpcResult<SuccessResponse> {
+        if !is_valid_snapshot_name(&name) {
+            return Err(RpcInputError::InvalidParams("name".to_string(), name).into());
+        }
+        let workdir_path = self.localnet_path().await?;
+
+        // Not while a restore might be reading it.
+        let _api_mutex_guard = self
+            .globals
+            .get_api_mutex(WORKDIR_IDX_LOCALNET)
+            .lock()
+            .await;
+        if let Err(e) = delete_snapshot(&workdir_path, &name) {
+            let error = match e.downcast_ref::<std::io::Error>() {
+                Some(_) => RpcSuibaseError::FileAccessError(e.to_string()),
+                None => RpcSuibaseError::NotFound(e.to_string()),
+            };
+            return Err(error.into());
+        }
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = "deleteLocalnetSnapshot".to_string();
+        resp.header.key = Some(name);
+        resp.result = true;
+        Ok(resp)
+    }
+
+    async fn get_job_status(&self, job_id: u64) -> RpcResult<JobStatusResponse> {
+        let job = match self.globals.jobs.read().await.get(job_id) {
+            Some(job) => job,
+            None => {
+                return Err(RpcSuibaseError::NotFound(format!("job {} not found", job_id)).into())
+            }
+        };
+        let mut resp = JobStatusResponse {
+            header: Header::default(),
+            job,
+        };
+        resp.header.method = "getJobStatus".to_string();
+        resp.header.key = Some(job_id.to_string());
+        Ok(resp)
+    }
+
+    async fn export_workdir_profile(
+        &self,
+        workdir: String,
+        keys: Option<Vec<String>>,
+    ) -> RpcResult<ExportWorkdirProfileResponse> {
+        let (workdir_idx, workdir_path) = self.workdir_by_name(&workdir).await?;
+        let (mut profile, warnings) =
+            match export_workdir_profile(&workdir, &workdir_path, &keys.unwrap_or_default()) {
+                Ok(export) => export,
+                Err(e) => {
+                    return Err(match e.downcast_ref::<std::io::Error>() {
+                        Some(_) => RpcSuibaseError::FileAccessError(e.to_string()).into(),
+                        None => {
+                            RpcInputError::InvalidParams("keys".to_string(), e.to_string()).into()
+                        }
+                    })
+                }
+            };
+
+        let suibase_path = PathBuf::from(self.globals.workdirs.read().await.suibase_home());
+        profile.manifest.suibase_version =
+            std::fs::read_to_string(suibase_path.join("scripts/common/__globals.sh"))
+                .ok()
+                .as_deref()
+                .and_then(parse_suibase_version);
+        profile.manifest.sui_version = self.sui_version(workdir_idx).await;
+
+        let mut resp = ExportWorkdirProfileResponse {
+            header: Header::default(),
+            profile,
+            warnings,
+        };
+        resp.header.method = "exportWorkdirProfile".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn import_workdir_profile(
+        &self,
+        workdir: String,
+        profile: WorkdirProfile,
+        dry_run: Option<bool>,
+        force: Option<bool>,
+        import_keys: Option<bool>,
+    ) -> RpcResult<ImportWorkdirProfileResponse> {
+        let (workdir_idx, workdir_path) = self.workdir_by_name(&workdir).await?;
+        let options = ProfileImportOptions {
+            dry_run: dry_run.unwrap_or(false),
+            force: force.unwrap_or(false),
+            import_keys: import_keys.unwrap_or(false),
+        };
+
+        // One import at a time (the conflicts are checked then written).
+        let _api_mutex_guard = self.globals.get_api_mutex(workdir_idx).lock().await;
+        let mut resp = match import_workdir_profile(&workdir_path, &profile, options) {
+            Ok(resp) => resp,
+            Err(e) => {
+                return Err(match e.downcast_ref::<std::io::Error>() {
+                    Some(_) => RpcSuibaseError::FileAccessError(e.to_string()).into(),
+                    None => {
+                        RpcInputError::InvalidParams("profile".to_string(), e.to_string()).into()
+                    }
+                })
+            }
+        };
+        resp.header.method = "importWorkdirProfile".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
 }
@@ -17,8 +17,8 @@ use crate::api::RpcSuibaseError;
 use crate::shared_types::{Globals, GlobalsWorkdirsST};
 
 use super::{
-    PackagesApiServer, RpcInputError, SuccessResponse, WorkdirPackagesResponse,
-    WorkdirSuiEventsResponse,
+    PackagesApiServer, RpcInputError, SubscriptionsResponse, SuccessResponse, SuiEvents,
+    WorkdirPackagesResponse, WorkdirSuiEventsResponse,
 };
 
 pub struct PackagesApiImpl {
@@ -50,6 +50,7 @@ impl PackagesApiServer for PackagesApiImpl {
         workdir: String,
         _after_ts: Option<String>,
         _last_ts: Option<String>,
+        filters: Option<serde_json::Value>,
     ) -> RpcResult<WorkdirSuiEventsResponse> {
         // data/display/debug allow variations of how the output
         // is produced (and they may be combined).
@@ -58,9 +59,11 @@ impl PackagesApiServer for PackagesApiImpl {
         // with the exception of data defaulting to true when
         // the other (display and debug) are false.
         //
+        // filters: see SuiEvent::query_events for the syntax, e.g.
+        //   [{"field":"sender","equals":"0x..."},{"field":"owner","equals":"0x..."}]
 
         // Verify workdir param is OK and get its corresponding workdir_idx.
-        let _workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
             .await
         {
             Some(workdir_idx) => workdir_idx,
@@ -71,6 +74,41 @@ impl PackagesApiServer for PackagesApiImpl {
         let mut resp = WorkdirSuiEventsResponse::new();
         resp.header.method = "getEvents".to_string();
         resp.header.key = Some(workdir.clone());
+
+        if let Some(filters) = filters {
+            let query_resp = AdminController::send_query_events(
+                &self.admctrl_tx,
+                workdir_idx,
+                filters.to_string(),
+            )
+            .await
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+            let query_resp: serde_json::Value = serde_json::from_str(&query_resp)
+                .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+            if let Some(error) = query_resp.get("error").and_then(|v| v.as_str()) {
+                return Err(
+                    RpcInputError::InvalidParams("filters".to_string(), error.to_string()).into(),
+                );
+            }
+            let events = query_resp
+                .get("events")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            resp.events = Some(
+                events
+                    .into_iter()
+                    .map(|mut event| SuiEvents {
+                        message: event["event"]["type"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        timestamp: event["timestampMs"].to_string(),
+                        event: Some(event["event"].take()),
+                    })
+                    .collect(),
+            );
+        }
         Ok(resp)
     }
 
@@ -194,15 +232,35 @@ impl PackagesApiServer for PackagesApiImpl {
                 // Response with the latest global data.
                 let mut resp = ui.get_data().clone();
                 resp.header.set_from_uuids(ui.get_uuid());
+                resp.events_sync = self
+                    .globals
+                    .events_sync
+                    .read()
+                    .await
+                    .get_packages(workdir_idx);
                 return Ok(resp);
             } else {
-                return Err(RpcSuibaseError::InfoError(
-                    "Backend still initializing. Status not yet known".to_string(),
-                )
-                .into());
+                return Err(
+                    RpcSuibaseError::Initializing("Status not yet known".to_string()).into(),
+                );
             }
         }
     }
+
+    async fn get_subscriptions(&self, workdir: String) -> RpcResult<SubscriptionsResponse> {
+        let workdir_idx = match GlobalsWorkdirsST::get_workdir_idx_by_name(&self.globals, &workdir)
+            .await
+        {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let mut resp = SubscriptionsResponse::new();
+        resp.header.method = "getSubscriptions".to_string();
+        resp.header.key = Some(workdir);
+        resp.subscriptions = self.globals.subscriptions.read().await.get(workdir_idx);
+        Ok(resp)
+    }
 }
 
 impl PackagesApiImpl {
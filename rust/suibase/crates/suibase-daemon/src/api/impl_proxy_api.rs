@@ -1,25 +1,96 @@
+use std::collections::HashMap;
+
 use tokio::sync::Mutex;
 
 use axum::async_trait;
 
 use jsonrpsee::core::RpcResult;
 
-use crate::shared_types::{GlobalsProxyMT, ServerStats};
-use common::basic_types::{AdminControllerMsg, AdminControllerTx, SafeUuid, TargetServerIdx};
+use crate::admin_controller::AdminController;
+use crate::shared_types::{
+    budget_threshold_reached, is_valid_usage_month, time_skew_warnings, usage_month, Globals,
+    GlobalsProxyMT, GlobalsSubscriptionsMT, GlobalsWorkdirStatusMT, HealthMetrics, HealthRule,
+    LinkRole, ProxyDistribution, RateLimiterUsage, RateLimits, ServerStats,
+    CONFIG_HISTORY_CAPACITY, RATE_LIMIT_MIN_HEADROOM, RECENT_REQUESTS_CAPACITY, WORKDIRS_KEYS,
+};
+use common::basic_types::{
+    AdminControllerMsg, AdminControllerTx, EpochTimestamp, SafeUuid, TargetServerIdx, WorkdirIdx,
+    AUTO_THREAD_STATS,
+};
+use common::shared_types::{WorkdirState, WorkdirStatus};
 
-use super::{InfoResponse, ProxyApiServer, VersionedEq};
-use super::{LinkStats, LinksResponse, LinksSummary, RpcInputError};
+use super::RateGroupStats;
+use super::{ConfigHistoryResponse, RecentRequestInfo, RecentRequestsResponse};
+use super::{InfoResponse, PreviewConfigResponse, ProxyApiServer, RpcSuibaseError, VersionedEq};
+use super::{LinkErrorCodeCount, LinkStats, LinksResponse, LinksSummary, RpcInputError};
+use super::{LinkUsageInfo, UsageReportResponse};
+use super::{
+    LinksStatusReason, LINKS_REASON_ALL_LINKS_DOWN, LINKS_REASON_CONFIG_ERROR,
+    LINKS_REASON_DAEMON_SUBSYSTEM_DOWN, LINKS_REASON_LINKS_DEGRADED,
+    LINKS_REASON_NODE_PROCESS_DEAD, LINKS_REASON_RATE_LIMITED, LINKS_REASON_SUBSCRIPTIONS_DEGRADED,
+};
+use super::{ShadowDiffInfo, ShadowMethodInfo, ShadowReportResponse};
 
 use super::def_header::Versioned;
 
+// Status of a link as reported by getLinks ("OK", "DOWN", "PROBING", "MAINTENANCE").
+//
+// Empty while the link has not yet determined its initial health. getLinks also
+// reports "DEGRADED" instead of "OK" when the link fails the workdir health_score.
+pub(crate) fn link_status(server_stats: &ServerStats) -> &'static str {
+    if server_stats.is_in_maintenance() {
+        // Planned, so not reported as DOWN whatever its health.
+        "MAINTENANCE"
+    } else if server_stats.is_warmup_failed() {
+        "DOWN"
+    } else if server_stats.is_probing() {
+        "PROBING"
+    } else if server_stats.health_score() == 0.0 {
+        ""
+    } else if server_stats.is_healthy() {
+        "OK"
+    } else {
+        "DOWN"
+    }
+}
+
 #[derive(Clone, PartialEq)]
 struct GetLinksInput {
-    pub target_servers_stats: Option<Vec<(TargetServerIdx, ServerStats)>>,
+    // With the max_per_day of the link.
+    pub target_servers_stats: Option<Vec<(TargetServerIdx, ServerStats, LinkRole, Option<u32>)>>,
     pub all_servers_stats: Option<ServerStats>,
     pub selection_vectors: Option<Vec<Vec<u8>>>,
+    pub selection_weights: Vec<(TargetServerIdx, f64)>,
+    pub proxy_distribution: ProxyDistribution,
+    pub health_rule: Option<HealthRule>,
     pub input_port_found: bool,
-    pub proxy_enabled: bool,
     pub user_request_start: bool,
+    pub proxy_tls_error: Option<String>,
+    pub proxy_port_configured: Option<u16>,
+    pub proxy_port: Option<u16>,
+    pub proxy_port_error: Option<String>,
+    pub active_link_profile: Option<String>,
+    pub config_warnings: Vec<String>,
+    // Processes of the workdir not running while it is started (see getWorkdirStatus).
+    pub dead_processes: Vec<String>,
+    pub dead_processes_since: Option<String>,
+    // Alias -> (requests in the current month, monthly_budget).
+    pub monthly_usage: HashMap<String, (u64, Option<u64>)>,
+    // Alias -> canary_pct, of the canary links only.
+    pub canary_pcts: HashMap<String, u8>,
+    // Alias -> rate_group, of the links in a group.
+    pub link_rate_groups: HashMap<String, String>,
+    // Alias -> max_per_secs/max_per_min, of the rate limited links only.
+    pub link_rate_limits: HashMap<String, RateLimits>,
+    // Name, limits and tokens used of each of the rate_groups.
+    pub rate_groups: Vec<(String, RateLimits, RateLimiterUsage)>,
+    // Sui event subscriptions resubscribing or dropping events (see getSubscriptions).
+    pub degraded_subscriptions: Vec<String>,
+    pub time_skew_threshold_secs: u64,
+    // Multi-link RPC status, maintained by the NetworkMonitor (see InputPort).
+    pub links_status: WorkdirStatus,
+    pub links_info: String,
+    pub links_reason: Option<&'static str>,
 }
 
 impl GetLinksInput {
@@ -28,9 +99,29 @@ impl GetLinksInput {
             target_servers_stats: None,
             all_servers_stats: None,
             selection_vectors: None,
+            selection_weights: Vec::new(),
+            proxy_distribution: ProxyDistribution::Best,
+            health_rule: None,
             input_port_found: false,
-            proxy_enabled: false,
             user_request_start: false,
+            proxy_tls_error: None,
+            proxy_port_configured: None,
+            proxy_port: None,
+            proxy_port_error: None,
+            active_link_profile: None,
+            config_warnings: Vec::new(),
+            dead_processes: Vec::new(),
+            dead_processes_since: None,
+            monthly_usage: HashMap::new(),
+            canary_pcts: HashMap::new(),
+            link_rate_groups: HashMap::new(),
+            link_rate_limits: HashMap::new(),
+            rate_groups: Vec::new(),
+            degraded_subscriptions: Vec::new(),
+            time_skew_threshold_secs: 0,
+            links_status: WorkdirStatus::new(),
+            links_info: String::new(),
+            links_reason: None,
         }
     }
 }
@@ -45,6 +136,8 @@ pub struct ProxyApiImpl {
     pub globals: GlobalsProxyMT,
     pub admctrl_tx: AdminControllerTx,
     prev_get_links_input: Mutex<Versioned<GetLinksInput>>,
+    workdirs_status: Vec<GlobalsWorkdirStatusMT>, // By WorkdirIdx (see with_workdirs_status).
+    subscriptions: Option<GlobalsSubscriptionsMT>, // See with_workdirs_status.
 }
 
 impl ProxyApiImpl {
@@ -54,7 +147,213 @@ impl ProxyApiImpl {
             globals,
             admctrl_tx,
             prev_get_links_input,
+            workdirs_status: Vec::new(),
+            subscriptions: None,
+        }
+    }
+
+    // Also check the processes and the event subscriptions of the workdirs for the
+    // getLinks status reasons.
+    pub fn with_workdirs_status(mut self, globals: &Globals) -> Self {
+        self.workdirs_status = (0..WORKDIRS_KEYS.len())
+            .map(|idx| globals.get_status(idx as WorkdirIdx).clone())
+            .collect();
+        self.subscriptions = Some(globals.subscriptions.clone());
+        self
+    }
+
+    // The processes (e.g. "Faucet process") reported DOWN or NOT RUNNING by the last
+    // "<workdir> status", and since when the workdir status is not OK.
+    async fn get_dead_processes(&self, workdir: &str) -> (Vec<String>, Option<String>) {
+        let status = WORKDIRS_KEYS
+            .iter()
+            .position(|key| *key == workdir)
+            .and_then(|idx| self.workdirs_status.get(idx));
+        let status = match status {
+            Some(status) => status.read().await,
+            None => return (Vec::new(), None),
+        };
+        let services = status
+            .ui
+            .as_ref()
+            .and_then(|ui| ui.get_data().services.clone())
+            .unwrap_or_default();
+        let dead_processes: Vec<String> = services
+            .into_iter()
+            .filter(|service| service.label.ends_with("process"))
+            .filter(|service| {
+                matches!(
+                    service.status.as_deref(),
+                    Some("DOWN") | Some("NOT RUNNING")
+                )
+            })
+            .map(|service| service.label)
+            .collect();
+        let since = if dead_processes.is_empty() || status.status.state() == WorkdirState::Ok {
+            None
+        } else {
+            Some(status.status.since().to_rfc3339())
+        };
+        (dead_processes, since)
+    }
+
+    async fn get_degraded_subscriptions(&self, workdir: &str) -> Vec<String> {
+        let workdir_idx = WORKDIRS_KEYS.iter().position(|key| *key == workdir);
+        match (workdir_idx, &self.subscriptions) {
+            (Some(workdir_idx), Some(subscriptions)) => subscriptions
+                .read()
+                .await
+                .degraded_reasons(workdir_idx as WorkdirIdx),
+            _ => Vec::new(),
+        }
+    }
+
+    // The actionable causes of the multi-link status, most important first.
+    //
+    // 'links_reason' is set when the links themselves explain the status.
+    fn status_reasons(
+        inputs: &GetLinksInput,
+        link_stats: &[LinkStats],
+        links_reason: Option<&str>,
+        status_since: Option<&String>,
+        degraded_threads: &[String],
+    ) -> Vec<LinksStatusReason> {
+        let mut reasons = Vec::new();
+
+        // The proxy does not serve at all.
+        for error in [&inputs.proxy_tls_error, &inputs.proxy_port_error]
+            .into_iter()
+            .flatten()
+        {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_CONFIG_ERROR,
+                error.clone(),
+                None,
+            ));
+        }
+
+        // A process stopped on its own (not by the user).
+        if inputs.user_request_start && !inputs.dead_processes.is_empty() {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_NODE_PROCESS_DEAD,
+                format!("{} not running", inputs.dead_processes.join(", ")),
+                inputs.dead_processes_since.clone(),
+            ));
+        }
+
+        let rpc_links = link_stats.iter().filter(|link| link.role.is_empty());
+        let down_links: Vec<String> = rpc_links
+            .clone()
+            .filter(|link| link.status == "DOWN")
+            .map(|link| {
+                if link.error_info.is_empty() {
+                    link.alias.clone()
+                } else {
+                    format!("{} ({})", link.alias, link.error_info)
+                }
+            })
+            .collect();
+        match links_reason {
+            Some(LINKS_REASON_ALL_LINKS_DOWN) => reasons.push(LinksStatusReason::new(
+                LINKS_REASON_ALL_LINKS_DOWN,
+                format!("no link available, DOWN: {}", down_links.join(", ")),
+                status_since.cloned(),
+            )),
+            Some(LINKS_REASON_LINKS_DEGRADED) => reasons.push(LinksStatusReason::new(
+                LINKS_REASON_LINKS_DEGRADED,
+                format!("links DOWN: {}", down_links.join(", ")),
+                None,
+            )),
+            _ => {}
+        }
+
+        let throttled: Vec<&str> = rpc_links
+            .clone()
+            .filter(|link| link.throttled_until.is_some())
+            .map(|link| link.alias.as_str())
+            .collect();
+        if !throttled.is_empty() {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_RATE_LIMITED,
+                format!("rate limited by the provider: {}", throttled.join(", ")),
+                None,
+            ));
+        }
+
+        // Refilled at 00:00 UTC.
+        let quota_used: Vec<String> = rpc_links
+            .filter_map(|link| {
+                let max_per_day = link.max_per_day?;
+                let headroom = 1.0 - link.day_count as f64 / max_per_day as f64;
+                (headroom < RATE_LIMIT_MIN_HEADROOM)
+                    .then(|| format!("{} ({}/{})", link.alias, link.day_count, max_per_day))
+            })
+            .collect();
+        if !quota_used.is_empty() {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_RATE_LIMITED,
+                format!("daily quota nearly used: {}", quota_used.join(", ")),
+                None,
+            ));
+        }
+
+        // The value was ignored or adjusted, so maybe not what the user intended.
+        for warning in &inputs.config_warnings {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_CONFIG_ERROR,
+                warning.clone(),
+                None,
+            ));
+        }
+
+        if !inputs.degraded_subscriptions.is_empty() {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_SUBSCRIPTIONS_DEGRADED,
+                inputs.degraded_subscriptions.join(", "),
+                None,
+            ));
         }
+
+        if !degraded_threads.is_empty() {
+            reasons.push(LinksStatusReason::new(
+                LINKS_REASON_DAEMON_SUBSYSTEM_DOWN,
+                format!("failing repeatedly: {}", degraded_threads.join(", ")),
+                None,
+            ));
+        }
+        reasons
+    }
+
+    // The links of each group are from 'link_stats' (with their sampled rates).
+    fn rate_group_stats(
+        rate_groups: &[(String, RateLimits, RateLimiterUsage)],
+        link_stats: &[LinkStats],
+    ) -> Vec<RateGroupStats> {
+        rate_groups
+            .iter()
+            .map(|(name, limits, usage)| {
+                let members: Vec<&LinkStats> = link_stats
+                    .iter()
+                    .filter(|link| link.rate_group.as_ref() == Some(name))
+                    .collect();
+                let sum = |rate: fn(&LinkStats) -> &String| -> f64 {
+                    members
+                        .iter()
+                        .filter_map(|link| rate(link).parse::<f64>().ok())
+                        .sum()
+                };
+                RateGroupStats {
+                    name: name.clone(),
+                    links: members.iter().map(|link| link.alias.clone()).collect(),
+                    max_per_secs: limits.max_per_secs,
+                    max_per_min: limits.max_per_min,
+                    qps: Self::fmt_f64_api(sum(|link| &link.qps)),
+                    qpm: Self::fmt_f64_api(sum(|link| &link.qpm)),
+                    granted_count: usage.granted,
+                    denied_count: usage.denied,
+                }
+            })
+            .collect()
     }
 
     fn fmt_f64_api(input: f64) -> String {
@@ -139,6 +438,21 @@ impl ProxyApiImpl {
         }
     }
 
+    fn fmt_str_qps(input: &str) -> String {
+        // Requests per second, displayed within a field of fixed width of 7 characters.
+        //
+        // Empty, negative or bad input becomes  "      -"
+        // When input is >=100000, the output is ">99999"
+        let value = input.parse::<f64>().unwrap_or(-1.0);
+        if value.is_sign_negative() || !value.is_finite() {
+            "      -".to_string()
+        } else if value >= 99999.95f64 {
+            " >99999".to_string()
+        } else {
+            format!("{:7.1}", value)
+        }
+    }
+
     fn fmt_str_score(input: &str) -> String {
         // Similar to fmt_str_pct, except:
         //   - 0.0 is shown as empty field (spaces).
@@ -247,6 +561,11 @@ impl ProxyApiServer for ProxyApiImpl {
         let mut inputs = GetLinksInput::new();
         let mut inputs_version: Option<SafeUuid> = None;
 
+        // Before the lock on the proxy globals (never both held).
+        (inputs.dead_processes, inputs.dead_processes_since) =
+            self.get_dead_processes(&workdir).await;
+        inputs.degraded_subscriptions = self.get_degraded_subscriptions(&workdir).await;
+
         {
             // Get read lock access to the globals and just quickly copy what is needed.
             // Most parsing and processing is done outside the lock.
@@ -255,20 +574,82 @@ impl ProxyApiServer for ProxyApiImpl {
 
             if let Some(input_port) = globals.find_input_port_by_name(&workdir) {
                 inputs.input_port_found = true;
-                inputs.proxy_enabled = input_port.is_proxy_enabled();
                 inputs.user_request_start = input_port.is_user_request_start();
+                inputs.proxy_tls_error = input_port.proxy_tls_error().cloned();
+                inputs.proxy_port_configured = Some(input_port.port_number());
+                inputs.proxy_port = input_port.actual_port_number();
+                inputs.proxy_port_error = input_port.proxy_port_error().cloned();
+                inputs.active_link_profile = input_port.active_link_profile().cloned();
+                inputs.config_warnings = input_port.config_warnings().to_vec();
 
                 inputs.all_servers_stats = Some(input_port.all_servers_stats.clone());
 
                 let target_servers = &input_port.target_servers;
 
+                let month = usage_month(chrono::Utc::now());
+                inputs.monthly_usage = target_servers
+                    .iter()
+                    .map(|(_, target_server)| {
+                        let alias = target_server.alias();
+                        let usage = input_port.link_usage().get(&alias, &month);
+                        let budget = target_server.get_config().monthly_budget;
+                        (alias, (usage.requests, budget))
+                    })
+                    .collect();
+                inputs.canary_pcts = target_servers
+                    .iter()
+                    .filter_map(|(_, target_server)| {
+                        let canary_pct = target_server.get_config().canary_pct?;
+                        Some((target_server.alias(), canary_pct))
+                    })
+                    .collect();
+                inputs.link_rate_groups = target_servers
+                    .iter()
+                    .filter_map(|(_, target_server)| {
+                        let group = target_server.get_config().rate_group.clone()?;
+                        Some((target_server.alias(), group))
+                    })
+                    .collect();
+                inputs.link_rate_limits = target_servers
+                    .iter()
+                    .filter_map(|(_, target_server)| {
+                        let limits = target_server.rate_limiter()?.limits();
+                        Some((target_server.alias(), limits))
+                    })
+                    .collect();
+                inputs.rate_groups = input_port
+                    .rate_groups()
+                    .iter()
+                    .map(|(name, limiter)| (name.clone(), limiter.limits(), limiter.usage()))
+                    .collect();
+
                 inputs.target_servers_stats = Some(
                     target_servers
                         .iter()
-                        .map(|(idx, target_server)| (idx, target_server.stats.clone()))
+                        .map(|(idx, target_server)| {
+                            (
+                                idx,
+                                target_server.stats.clone(),
+                                target_server.role(),
+                                target_server.get_config().max_per_day,
+                            )
+                        })
                         .collect(),
                 );
                 inputs.selection_vectors = Some(input_port.selection_vectors.clone());
+                inputs.selection_weights = input_port.selection_weights.clone();
+                inputs.proxy_distribution = input_port.proxy_distribution();
+                inputs.health_rule = input_port.health_rule().cloned();
+                inputs.time_skew_threshold_secs = input_port.time_skew_threshold_secs();
+                inputs.links_status = input_port.links_status().clone();
+                inputs.links_info = input_port.links_info().to_string();
+                inputs.links_reason = input_port.links_reason();
+
+                if debug {
+                    if let Some(allowlist) = input_port.proxy_allowlist() {
+                        debug_out.push_str(&format!("proxy_allowed_ips: {}\n", allowlist));
+                    }
+                }
             }
 
             // If debug, then extensively add more info to the output.
@@ -287,13 +668,17 @@ impl ProxyApiServer for ProxyApiImpl {
         } // Release the read lock.
 
         // Map the target_servers_stats into the API LinkStats.
-        let mut healthy_server_count: usize = 0;
-        let mut neutral_health_count: usize = 0;
         let mut link_stats: Vec<LinkStats> = Vec::new();
         let mut load_distribution_depth = 0;
         if let Some(target_servers_stats) = inputs.target_servers_stats {
+            let now = EpochTimestamp::now();
             let mut total_request: u64 = 0;
             let mut link_n_request: Vec<u64> = Vec::with_capacity(target_servers_stats.len());
+            // For the checkpoint_lag of the health_score.
+            let highest_checkpoint = target_servers_stats
+                .iter()
+                .filter_map(|(_, stats, _, _)| stats.highest_synced_checkpoint())
+                .max();
             // Prepare LinkStats, which is the "metrics" portion of the API.
             //
             // The "display/debug" portion is built from the "metrics" portion.
@@ -318,7 +703,7 @@ impl ProxyApiServer for ProxyApiImpl {
                     // remember the position of that element in target_servers_stats.
                     let idx = target_servers_stats
                         .iter()
-                        .position(|(i, _)| *i == unmap_idx);
+                        .position(|(i, _, _, _)| *i == unmap_idx);
                     if let Some(idx) = idx {
                         indices.push(idx);
                     } else {
@@ -340,37 +725,90 @@ impl ProxyApiServer for ProxyApiImpl {
                 indices.extend(missing_indices);
             }
 
+            // With weights, the load is distributed on the links having one.
+            if !inputs.selection_weights.is_empty() {
+                load_distribution_depth = inputs.selection_weights.len();
+            }
+
             for i in indices {
-                let server_stats = &target_servers_stats[i].1;
+                let (server_idx, server_stats, role, max_per_day) = &target_servers_stats[i];
                 let mut link_stat = LinkStats::new(server_stats.alias());
+                if *role != LinkRole::Rpc {
+                    link_stat.role = role.as_str().to_string();
+                }
+                if let Some((_, weight)) = inputs
+                    .selection_weights
+                    .iter()
+                    .find(|(idx, _)| idx == server_idx)
+                {
+                    link_stat.weight_pct = Self::fmt_f64_api(weight * 100.0);
+                }
+                link_stat.canary_pct = inputs.canary_pcts.get(&link_stat.alias).copied();
+                link_stat.uptime_secs = server_stats.uptime_secs();
+                link_stat.highest_synced_checkpoint = server_stats.highest_synced_checkpoint();
+                link_stat.throttle_count = server_stats.throttle_count();
+                link_stat.hedge_count = server_stats.hedge_count();
+                link_stat.hedge_won_count = server_stats.hedge_won_count();
+                link_stat.throttled_until = server_stats.throttled_until(&now).map(|until| {
+                    let remaining = chrono::Duration::from_std(until - now).unwrap_or_default();
+                    (chrono::Utc::now() + remaining).to_rfc3339()
+                });
+                link_stat.maintenance_until = server_stats.maintenance_until().and_then(|until| {
+                    chrono::DateTime::from_timestamp(until as i64, 0)
+                        .map(|until| until.to_rfc3339())
+                });
 
                 let mut n_request = 0u64;
                 let mut n_success = 0u64;
                 server_stats.get_accum_stats(&mut n_request, &mut n_success);
                 total_request += n_request;
+                link_stat.request_count = n_request;
                 if n_request != 0 {
                     let success_pct = (n_success as f64 * 100.0f64) / (n_request as f64);
                     link_stat.success_pct = Self::fmt_f64_api(success_pct);
                 };
 
-                let health_score = server_stats.health_score();
-                if health_score.is_normal() && health_score.is_sign_positive() {
-                    healthy_server_count += 1;
-                }
-                link_stat.health_pct = Self::fmt_f64_api(health_score);
+                link_stat.health_pct = Self::fmt_f64_api(server_stats.health_score());
 
+                link_stat.qps = Self::fmt_f64_api(server_stats.qps());
+                link_stat.qpm = Self::fmt_f64_api(server_stats.qpm());
+                if let Some(limits) = inputs.link_rate_limits.get(&link_stat.alias) {
+                    link_stat.max_per_secs = limits.max_per_secs;
+                    link_stat.max_per_min = limits.max_per_min;
+                }
+                link_stat.rate_group = inputs.link_rate_groups.get(&link_stat.alias).cloned();
+                link_stat.day_count = server_stats.day_count();
+                link_stat.max_per_day = *max_per_day;
+                if let Some((month_count, budget)) = inputs.monthly_usage.get(&link_stat.alias) {
+                    link_stat.month_count = *month_count;
+                    link_stat.monthly_budget = *budget;
+                }
                 link_stat.resp_time = Self::fmt_f64_api(server_stats.avg_latency_ms());
                 link_stat.error_info = server_stats.error_info();
 
-                link_stat.status = if health_score == 0.0 {
-                    // The server has not yet "determine" its initial health state.
-                    neutral_health_count += 1;
-                    String::new()
-                } else if server_stats.is_healthy() {
-                    "OK".to_string()
-                } else {
-                    "DOWN".to_string()
-                };
+                link_stat.status_2xx = server_stats.http_status_class_count(2);
+                link_stat.status_4xx = server_stats.http_status_class_count(4);
+                link_stat.status_5xx = server_stats.http_status_class_count(5);
+                link_stat.error_codes = server_stats
+                    .top_jsonrpc_error_codes(3)
+                    .into_iter()
+                    .map(|(code, count)| LinkErrorCodeCount { code, count })
+                    .collect();
+
+                link_stat.status = link_status(server_stats).to_string();
+                if let Some(rule) = &inputs.health_rule {
+                    let metrics = HealthMetrics::from_stats(
+                        server_stats,
+                        *max_per_day,
+                        highest_checkpoint,
+                        &now,
+                    );
+                    link_stat.health_expr_score = Self::fmt_f64_api(rule.score(&metrics));
+                    // Still selected (the built-in scoring drives the selection).
+                    if link_stat.status == "OK" && !rule.is_ok(&metrics) {
+                        link_stat.status = "DEGRADED".to_string();
+                    }
+                }
 
                 // Push always together for 1:1 index matching.
                 link_stats.push(link_stat);
@@ -386,6 +824,7 @@ impl ProxyApiServer for ProxyApiImpl {
             }
         }
         let link_stats = link_stats; // Make immutable.
+        let rate_groups = Self::rate_group_stats(&inputs.rate_groups, &link_stats);
 
         // Map the all_servers_stats into the API LinksSummary.
         let mut summary_stats = LinksSummary::new();
@@ -396,45 +835,60 @@ impl ProxyApiServer for ProxyApiImpl {
             all_servers_stats.get_classified_failure(
                 &mut summary_stats.fail_network_down,
                 &mut summary_stats.fail_bad_request,
+                &mut summary_stats.fail_overload,
+                &mut summary_stats.client_errors,
                 &mut summary_stats.fail_others,
             );
+            summary_stats.fail_ip_denied = all_servers_stats.ip_denied_count();
         }
+        summary_stats.degraded_threads = AUTO_THREAD_STATS.degraded();
+        summary_stats.proxy_tls_error = inputs.proxy_tls_error.clone();
+        summary_stats.proxy_port_error = inputs.proxy_port_error.clone();
+        summary_stats.active_link_profile = inputs.active_link_profile.clone();
+        if inputs.proxy_distribution != ProxyDistribution::Best {
+            summary_stats.proxy_distribution = Some(inputs.proxy_distribution.as_str().to_string());
+        }
+        summary_stats.budget_warnings = link_stats
+            .iter()
+            .filter_map(|link| {
+                let budget = link.monthly_budget?;
+                budget_threshold_reached(link.month_count, budget)?;
+                let used_pct = link.month_count.saturating_mul(100) / budget.max(1);
+                Some(format!("{} ({}%)", link.alias, used_pct))
+            })
+            .collect();
+        let time_skews: Vec<(String, i64)> = inputs
+            .target_servers_stats
+            .iter()
+            .flatten()
+            .filter_map(|(_, stats, _, _)| stats.time_skew_ms().map(|skew| (stats.alias(), skew)))
+            .collect();
+        summary_stats.time_skew_warnings =
+            time_skew_warnings(&time_skews, inputs.time_skew_threshold_secs);
 
         if !inputs.input_port_found {
             return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into());
         }
 
-        // Identify the multi-link RPC status.
-        let load_balance_str = if load_distribution_depth > 1 {
-            ", load-balanced".to_string()
-        } else {
-            String::new()
-        };
+        // The multi-link RPC status, since when and why (see InputPort::update_links_status).
+        let status = &inputs.links_status;
+        resp.status = status.state().to_string();
+        resp.status_since = Some(status.since().to_rfc3339());
+        resp.status_cause = Some(status.cause().to_string());
+        resp.info = inputs.links_info.clone();
+        resp.proxy_port_configured = inputs.proxy_port_configured;
+        resp.proxy_port = inputs.proxy_port;
 
-        let server_count = link_stats.len();
-        (resp.status, resp.info) = if !inputs.proxy_enabled {
-            ("DOWN".to_string(), "proxy not enabled".to_string())
-        } else if !inputs.user_request_start {
-            ("DOWN".to_string(), format!("{} not started", workdir))
-        } else if server_count == 0 {
-            ("DOWN".to_string(), "no links in suibase.yaml".to_string())
-        } else if neutral_health_count == link_stats.len() {
-            ("DOWN".to_string(), "initializing".to_string())
-        } else if healthy_server_count == 0 {
-            ("DOWN".to_string(), "no servers available".to_string())
-        } else if healthy_server_count * 100 / server_count > 50 {
-            let resp_info = if workdir == "localnet" {
-                load_balance_str
-            } else {
-                format!("protected{}", load_balance_str)
-            };
-            ("OK".to_string(), resp_info)
-        } else {
-            (
-                "OK".to_string(),
-                format!(">50% degraded{}", load_balance_str),
-            )
-        };
+        let reasons = Self::status_reasons(
+            &inputs,
+            &link_stats,
+            inputs.links_reason,
+            resp.status_since.as_ref(),
+            &summary_stats.degraded_threads,
+        );
+        if !reasons.is_empty() {
+            summary_stats.reasons = Some(reasons);
+        }
 
         let mut display_out = String::new();
 
@@ -446,45 +900,146 @@ impl ProxyApiServer for ProxyApiImpl {
                 } else {
                     format!(" ( {} )", resp.info)
                 };
+                // Only the most important reason (see the summary for all).
+                let resp_reason = match summary_stats.reasons.as_ref().and_then(|r| r.first()) {
+                    Some(reason) => format!("  reason: {} [{}]\n", reason.detail, reason.kind),
+                    None => String::new(),
+                };
                 display_out.push_str(&format!(
-                    "multi-link RPC: {}{}\n\n\
+                    "multi-link RPC: {}{}\n{}\n\
                     Cumulative Request Stats\n\
   -------------------------\n\
   Success first attempt {:>9}\n\
   Success after retry   {:>9}\n\
   Failure bad request   {:>9}\n\
+  Failure overload      {:>9}\n\
+  Failure client error  {:>9}\n\
+  Failure IP denied     {:>9}\n\
   Failure others        {:>9}\n\n",
                     resp.status,
                     resp_info,
+                    resp_reason,
                     summary_stats.success_on_first_attempt,
                     summary_stats.success_on_retry,
                     summary_stats.fail_bad_request,
+                    summary_stats.fail_overload,
+                    summary_stats.client_errors,
+                    summary_stats.fail_ip_denied,
                     summary_stats.fail_others,
                 ));
+                if !summary_stats.degraded_threads.is_empty() {
+                    display_out.push_str(&format!(
+                        "Degraded (failing repeatedly): {}\n\n",
+                        summary_stats.degraded_threads.join(", ")
+                    ));
+                }
+                if let Some(proxy_tls_error) = &summary_stats.proxy_tls_error {
+                    display_out.push_str(&format!("TLS error: {}\n\n", proxy_tls_error));
+                }
+                if let Some(proxy_port_error) = &summary_stats.proxy_port_error {
+                    display_out.push_str(&format!("Port error: {}\n\n", proxy_port_error));
+                }
+                if !summary_stats.budget_warnings.is_empty() {
+                    display_out.push_str(&format!(
+                        "Monthly budget nearly used: {}\n\n",
+                        summary_stats.budget_warnings.join(", ")
+                    ));
+                }
+                if !summary_stats.time_skew_warnings.is_empty() {
+                    display_out.push_str(&format!(
+                        "WARNING: Clock skew: {} (check NTP on this host)\n\n",
+                        summary_stats.time_skew_warnings.join(", ")
+                    ));
+                }
             }
 
             if links {
-                display_out.push_str(
-                    "alias                Status  Health%   Load%   RespT ms  Success%\n--------------------------------------------------------------------\n"
-                );
+                // The QPS and limits columns only when there is a limit to compare with.
+                let rate_limited = link_stats
+                    .iter()
+                    .any(|link| link.max_per_secs.is_some() || link.max_per_min.is_some());
+                if rate_limited {
+                    display_out.push_str(
+                        "alias                Status  Health%   Load%   RespT ms  Success%      QPS  Limits\n--------------------------------------------------------------------------------------------\n"
+                    );
+                } else {
+                    display_out.push_str(
+                        "alias                Status  Health%   Load%   RespT ms  Success%\n--------------------------------------------------------------------\n"
+                    );
+                }
                 let mut load_distributed = load_distribution_depth;
+                let weighted = !inputs.selection_weights.is_empty();
                 for link_stat in link_stats.iter() {
-                    let load_dist_marker = if load_distributed > 0 {
+                    let load_dist_marker = if weighted {
+                        if link_stat.weight_pct.is_empty() {
+                            ""
+                        } else {
+                            "*"
+                        }
+                    } else if load_distributed > 0 {
                         load_distributed -= 1;
                         "*"
                     } else {
                         ""
                     };
+                    let role_marker = if link_stat.role == LinkRole::MonitorOnly.as_str() {
+                        " (monitor-only)"
+                    } else if link_stat.maintenance_until.is_some() {
+                        " (maintenance)"
+                    } else if link_stat.throttled_until.is_some() {
+                        " (throttled)"
+                    } else if link_stat.canary_pct.is_some() {
+                        " (canary)"
+                    } else {
+                        ""
+                    };
+                    let load = if rate_limited {
+                        let limits: Vec<String> = [
+                            (link_stat.max_per_secs, "/s"),
+                            (link_stat.max_per_min, "/min"),
+                        ]
+                        .iter()
+                        .filter_map(|(limit, unit)| limit.map(|limit| format!("{}{}", limit, unit)))
+                        .collect();
+                        format!(
+                            "{:>9}  {:<13}",
+                            Self::fmt_str_qps(&link_stat.qps),
+                            limits.join(" ")
+                        )
+                    } else {
+                        String::new()
+                    };
                     display_out.push_str(&format!(
-                        "{:<21}{:^6}{:1}{:>7}{:>8}{:>11}{:>10}  {}\n",
+                        "{:<21}{:^6}{:1}{:>7}{:>8}{:>11}{:>10}{}  {}{}\n",
                         format!("{:.20}", link_stat.alias),
-                        link_stat.status,
+                        // Abbreviated to fit the column.
+                        if link_stat.maintenance_until.is_some() {
+                            "MAINT"
+                        } else {
+                            &link_stat.status
+                        },
                         load_dist_marker,
                         Self::fmt_str_score(&link_stat.health_pct),
                         Self::fmt_str_pct(&link_stat.load_pct),
                         Self::fmt_str_ms(&link_stat.resp_time),
                         Self::fmt_str_pct(&link_stat.success_pct),
+                        load,
                         link_stat.error_info,
+                        role_marker,
+                    ));
+                }
+                for group in &rate_groups {
+                    let limit = |limit: Option<u32>, unit: &str| {
+                        limit.map(|limit| format!(" max {}{}", limit, unit))
+                    };
+                    display_out.push_str(&format!(
+                        "\nrate_group {} ({}): {} QPS{}{}, {} denied\n",
+                        group.name,
+                        group.links.join(", "),
+                        Self::fmt_str_qps(&group.qps).trim(),
+                        limit(group.max_per_secs, "/s").unwrap_or_default(),
+                        limit(group.max_per_min, "/min").unwrap_or_default(),
+                        group.denied_count,
                     ));
                 }
             }
@@ -518,6 +1073,9 @@ impl ProxyApiServer for ProxyApiImpl {
             }
             if links {
                 resp.links = Some(link_stats);
+                if !rate_groups.is_empty() {
+                    resp.rate_groups = Some(rate_groups);
+                }
             }
 
             if let Some(version) = inputs_version {
@@ -546,4 +1104,284 @@ impl ProxyApiServer for ProxyApiImpl {
         resp.info = "Success".to_string();
         Ok(resp)
     }
+
+    async fn preview_config(
+        &self,
+        workdir: String,
+        yaml: String,
+    ) -> RpcResult<PreviewConfigResponse> {
+        let workdir_idx = match WORKDIRS_KEYS.iter().position(|name| *name == workdir) {
+            Some(workdir_idx) => workdir_idx as WorkdirIdx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        // The AdminController does the loading/parsing (same as a real reload).
+        let preview = AdminController::send_preview_config(&self.admctrl_tx, workdir_idx, yaml)
+            .await
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+        let preview: serde_json::Value = serde_json::from_str(&preview)
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+        if let Some(error) = preview.get("error").and_then(|v| v.as_str()) {
+            return Err(RpcInputError::InvalidParams("yaml".to_string(), error.to_string()).into());
+        }
+        let mut resp: PreviewConfigResponse = serde_json::from_value(preview)
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+
+        resp.header.method = "previewConfig".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn set_link_profile(&self, workdir: String, profile: String) -> RpcResult<InfoResponse> {
+        let workdir_idx = match WORKDIRS_KEYS.iter().position(|name| *name == workdir) {
+            Some(workdir_idx) => workdir_idx as WorkdirIdx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        // The AdminController validates, writes the user suibase.yaml and reloads it.
+        let result = AdminController::send_set_link_profile(&self.admctrl_tx, workdir_idx, profile)
+            .await
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+        let result: serde_json::Value = serde_json::from_str(&result)
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+        if let Some(error) = result.get("error").and_then(|v| v.as_str()) {
+            return Err(
+                RpcInputError::InvalidParams("profile".to_string(), error.to_string()).into(),
+            );
+        }
+
+        let mut resp = InfoResponse::new();
+        resp.header.method = "setLinkProfile".to_string();
+        resp.header.key = Some(workdir);
+        resp.info = "Success".to_string();
+        Ok(resp)
+    }
+
+    async fn get_recent_requests(
+        &self,
+        workdir: String,
+        limit: Option<u32>,
+    ) -> RpcResult<RecentRequestsResponse> {
+        let limit = limit
+            .map(|limit| limit as usize)
+            .unwrap_or(RECENT_REQUESTS_CAPACITY);
+
+        let mut resp = RecentRequestsResponse::new();
+        {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            let input_port = match globals.find_input_port_by_name(&workdir) {
+                Some(input_port) => input_port,
+                None => {
+                    return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into())
+                }
+            };
+            let recent_requests = match input_port.recent_requests().lock() {
+                Ok(recent_requests) => recent_requests.latest(limit),
+                Err(_) => Vec::new(),
+            };
+            for entry in recent_requests {
+                let alias = entry
+                    .server_idx
+                    .and_then(|server_idx| input_port.target_servers.get(server_idx))
+                    .map(|target_server| target_server.alias());
+                resp.requests.push(RecentRequestInfo {
+                    request_id: entry.request_id,
+                    method: entry.method,
+                    alias,
+                    latency_ms: entry.latency.as_millis() as u64,
+                    status: entry.http_status,
+                });
+            }
+        }
+
+        resp.header.method = "getRecentRequests".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn get_config_history(
+        &self,
+        workdir: String,
+        limit: Option<u32>,
+    ) -> RpcResult<ConfigHistoryResponse> {
+        let limit = limit
+            .map(|limit| limit as usize)
+            .unwrap_or(CONFIG_HISTORY_CAPACITY);
+
+        let mut resp = ConfigHistoryResponse::new();
+        {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            let input_port = match globals.find_input_port_by_name(&workdir) {
+                Some(input_port) => input_port,
+                None => {
+                    return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into())
+                }
+            };
+            resp.entries = input_port.config_history().latest(limit);
+        }
+
+        resp.header.method = "getConfigHistory".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn get_usage_report(
+        &self,
+        workdir: String,
+        month: Option<String>,
+    ) -> RpcResult<UsageReportResponse> {
+        let month = match month {
+            Some(month) if !is_valid_usage_month(&month) => {
+                return Err(RpcInputError::InvalidParams("month".to_string(), month).into())
+            }
+            Some(month) => month,
+            None => usage_month(chrono::Utc::now()),
+        };
+
+        let mut resp = UsageReportResponse::new();
+        {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            let input_port = match globals.find_input_port_by_name(&workdir) {
+                Some(input_port) => input_port,
+                None => {
+                    return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into())
+                }
+            };
+            let usage = input_port.link_usage();
+
+            // The configured links first, then those counted before being removed.
+            let mut budgets: Vec<(String, Option<u64>)> = input_port
+                .target_servers
+                .iter()
+                .map(|(_, target_server)| {
+                    (
+                        target_server.alias(),
+                        target_server.get_config().monthly_budget,
+                    )
+                })
+                .collect();
+            budgets.sort_by(|a, b| a.0.cmp(&b.0));
+            for alias in usage.aliases() {
+                if !budgets.iter().any(|(configured, _)| configured == alias)
+                    && usage.get(alias, &month).requests != 0
+                {
+                    budgets.push((alias.clone(), None));
+                }
+            }
+
+            resp.links = budgets
+                .into_iter()
+                .map(|(alias, monthly_budget)| {
+                    let month_usage = usage.get(&alias, &month);
+                    let used_pct = monthly_budget.map(|budget| {
+                        Self::fmt_f64_api(
+                            month_usage.requests as f64 * 100.0 / budget.max(1) as f64,
+                        )
+                    });
+                    LinkUsageInfo {
+                        alias,
+                        requests: month_usage.requests,
+                        monthly_budget,
+                        used_pct,
+                        alerted_pct: month_usage.alerted_pct,
+                    }
+                })
+                .collect();
+            resp.months = usage.months();
+        }
+
+        resp.month = month;
+        resp.header.method = "getUsageReport".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn get_shadow_report(&self, workdir: String) -> RpcResult<ShadowReportResponse> {
+        let mut resp = ShadowReportResponse::new();
+        {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            let input_port = match globals.find_input_port_by_name(&workdir) {
+                Some(input_port) => input_port,
+                None => {
+                    return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into())
+                }
+            };
+            if let Some(config) = input_port.proxy_shadow() {
+                resp.shadow_link = Some(config.link.clone());
+                resp.shadow_pct = config.pct;
+            }
+            let shadow_stats = input_port.shadow_stats();
+            if let Ok(stats) = shadow_stats.lock() {
+                resp.dropped = stats.dropped();
+                resp.methods = stats
+                    .methods()
+                    .iter()
+                    .map(|(method, method_stats)| ShadowMethodInfo {
+                        method: method.clone(),
+                        compared: method_stats.compared,
+                        mismatches: method_stats.mismatches,
+                        failed: method_stats.failed,
+                    })
+                    .collect();
+                resp.diffs = stats
+                    .latest_diffs()
+                    .into_iter()
+                    .map(|diff| ShadowDiffInfo {
+                        alias: input_port
+                            .target_servers
+                            .get(diff.server_idx)
+                            .map(|target_server| target_server.alias()),
+                        request_id: diff.request_id,
+                        method: diff.method,
+                        timestamp_ms: diff.unix_time_ms,
+                        differences: diff.differences,
+                    })
+                    .collect();
+            }
+        }
+
+        resp.header.method = "getShadowReport".to_string();
+        resp.header.key = Some(workdir);
+        Ok(resp)
+    }
+
+    async fn reset_server_stats(&self, workdir: String) -> RpcResult<InfoResponse> {
+        let stats_file = {
+            let mut globals_write_guard = self.globals.write().await;
+            let globals = &mut *globals_write_guard;
+            let input_port = match globals.find_input_port_by_name_mut(&workdir) {
+                Some(input_port) => input_port,
+                None => {
+                    return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into())
+                }
+            };
+            input_port.clear_stats();
+            input_port.proxy_stats_file().cloned()
+        };
+
+        // Otherwise restored on the next daemon start.
+        if let Some(stats_file) = stats_file {
+            match std::fs::remove_file(&stats_file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(RpcSuibaseError::FileAccessError(format!(
+                        "{:?} not deleted ({})",
+                        stats_file, e
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        let mut resp = InfoResponse::new();
+        resp.header.method = "resetServerStats".to_string();
+        resp.header.key = Some(workdir);
+        resp.info = "Success".to_string();
+        Ok(resp)
+    }
 }
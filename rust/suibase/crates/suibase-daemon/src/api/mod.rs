@@ -2,14 +2,19 @@
 //
 // flatten under "api" module.
 pub(crate) use self::api_server::*;
+pub(crate) use self::capabilities::*;
 pub(crate) use self::def_header::*;
 pub(crate) use self::def_methods::*;
+pub(crate) use self::impl_proxy_api::{link_status, ProxyApiImpl};
+pub(crate) use self::params::*;
 pub(crate) use self::rpc_error::*;
 
 mod api_server;
+mod capabilities;
 mod def_header;
 mod def_methods;
 mod impl_general_api;
 mod impl_packages_api;
 mod impl_proxy_api;
+mod params;
 mod rpc_error;
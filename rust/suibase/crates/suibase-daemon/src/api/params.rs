@@ -0,0 +1,459 @@
+// Validation of the params of every API method, before calling its handler.
+//
+// The params parsing generated by jsonrpsee ignores the unknown params (e.g. a typo of
+// an optional one), and its errors do not name the field. So API_PARAMS declares the
+// params of each method (same names and order as in def_methods.rs) and
+// validated_methods() wraps all the methods to:
+//   - Reject an unknown, missing or mistyped param, or a value out of its range, with
+//     a RpcInputError naming the field.
+//   - Add the method name to the data of any error (see rpc_error.rs).
+//
+// The params can be by name (snake_case or lowerCamelCase, like jsonrpsee) or by
+// position.
+use std::sync::Arc;
+
+use jsonrpsee::core::server::{Methods, MethodsError, RpcModule};
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::types::Params;
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+
+use crate::shared_types::MERGE_MAX_COINS_PER_TX;
+
+use super::{with_method, RpcInputError, RpcSuibaseError};
+
+use self::ParamKind::{Bool, Json, String as Str, U32, U64};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    String,
+    Bool,
+    U32,
+    U64,
+    Json, // Any JSON value, validated by the handler.
+}
+
+impl ParamKind {
+    fn is_valid(self, value: &JsonValue) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Bool => value.is_boolean(),
+            Self::U32 => serde_json::from_value::<u32>(value.clone()).is_ok(),
+            Self::U64 => serde_json::from_value::<u64>(value.clone()).is_ok(),
+            Self::Json => true,
+        }
+    }
+
+    fn expected(self) -> &'static str {
+        match self {
+            Self::String => "a string",
+            Self::Bool => "a boolean",
+            Self::U32 => "an unsigned 32 bits integer",
+            Self::U64 => "an unsigned 64 bits integer",
+            Self::Json => "a JSON value",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamSpec {
+    pub name: &'static str, // snake_case, as the handler argument.
+    pub kind: ParamKind,
+    pub required: bool,
+    pub range: Option<(u64, u64)>, // Inclusive, for the integers.
+}
+
+impl ParamSpec {
+    const fn with_range(mut self, min: u64, max: u64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    fn is_named(&self, key: &str) -> bool {
+        key == self.name || key == lower_camel_case(self.name)
+    }
+
+    fn validate(&self, value: &JsonValue) -> Result<(), RpcInputError> {
+        if value.is_null() {
+            if self.required {
+                return Err(RpcInputError::MissingParam(self.name.to_string()));
+            }
+            return Ok(());
+        }
+        if !self.kind.is_valid(value) {
+            return Err(RpcInputError::InvalidParamType(
+                self.name.to_string(),
+                self.kind.expected().to_string(),
+            ));
+        }
+        if let (Some((min, max)), Some(n)) = (self.range, value.as_u64()) {
+            if !(min..=max).contains(&n) {
+                return Err(RpcInputError::ParamOutOfRange(
+                    self.name.to_string(),
+                    n.to_string(),
+                    format!("{}..{}", min, max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn required(name: &'static str, kind: ParamKind) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind,
+        required: true,
+        range: None,
+    }
+}
+
+const fn optional(name: &'static str, kind: ParamKind) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind,
+        required: false,
+        range: None,
+    }
+}
+
+// (method name, its params). Keep in sync with def_methods.rs.
+pub const API_PARAMS: &[(&str, &[ParamSpec])] = &[
+    // ProxyApi
+    (
+        "getLinks",
+        &[
+            required("workdir", Str),
+            optional("summary", Bool),
+            optional("links", Bool),
+            optional("data", Bool),
+            optional("display", Bool),
+            optional("debug", Bool),
+        ],
+    ),
+    ("fsChange", &[required("path", Str)]),
+    (
+        "previewConfig",
+        &[required("workdir", Str), required("yaml", Str)],
+    ),
+    (
+        "setLinkProfile",
+        &[required("workdir", Str), required("profile", Str)],
+    ),
+    (
+        "getRecentRequests",
+        &[required("workdir", Str), optional("limit", U32)],
+    ),
+    (
+        "getConfigHistory",
+        &[required("workdir", Str), optional("limit", U32)],
+    ),
+    (
+        "getUsageReport",
+        &[required("workdir", Str), optional("month", Str)],
+    ),
+    ("getShadowReport", &[required("workdir", Str)]),
+    ("resetServerStats", &[required("workdir", Str)]),
+    // GeneralApi
+    ("getVersions", &[optional("workdir", Str)]),
+    ("getCapabilities", &[]),
+    (
+        "workdirCommand",
+        &[required("workdir", Str), required("command", Str)],
+    ),
+    (
+        "getWorkdirStatus",
+        &[
+            required("workdir", Str),
+            optional("method_uuid", Str),
+            optional("data_uuid", Str),
+        ],
+    ),
+    ("getWorkdirsStatus", &[]),
+    ("setAsuiSelection", &[required("workdir", Str)]),
+    ("setActiveWorkdir", &[required("workdir", Str)]),
+    ("workdirRefresh", &[required("workdir", Str)]),
+    (
+        "setLogLevel",
+        &[required("target", Str), required("level", Str)],
+    ),
+    ("getDaemonStats", &[]),
+    ("getSystemCheck", &[]),
+    ("cleanupWorkdirProcesses", &[required("workdir", Str)]),
+    (
+        "getProcessLog",
+        &[
+            required("workdir", Str),
+            required("process", Str),
+            optional("tail_lines", U32),
+            optional("grep", Str),
+        ],
+    ),
+    ("getExplorerInfo", &[]),
+    ("getTelemetryPreview", &[]),
+    (
+        "getGasInventory",
+        &[required("workdir", Str), optional("address", Str)],
+    ),
+    (
+        "mergeGasCoins",
+        &[
+            required("workdir", Str),
+            optional("address", Str),
+            optional("max_coins_per_tx", U32).with_range(2, MERGE_MAX_COINS_PER_TX as u64),
+            optional("confirm", Bool),
+        ],
+    ),
+    ("snapshotLocalnet", &[required("name", Str)]),
+    (
+        "restoreLocalnet",
+        &[required("name", Str), optional("force", Bool)],
+    ),
+    ("listLocalnetSnapshots", &[]),
+    ("deleteLocalnetSnapshot", &[required("name", Str)]),
+    ("getJobStatus", &[required("job_id", U64)]),
+    (
+        "exportWorkdirProfile",
+        &[required("workdir", Str), optional("keys", Json)],
+    ),
+    (
+        "importWorkdirProfile",
+        &[
+            required("workdir", Str),
+            required("profile", Json),
+            optional("dry_run", Bool),
+            optional("force", Bool),
+            optional("import_keys", Bool),
+        ],
+    ),
+    // PackagesApi
+    (
+        "getWorkdirEvents",
+        &[
+            required("workdir", Str),
+            optional("after_ts", Str),
+            optional("last_ts", Str),
+            optional("filters", Json),
+        ],
+    ),
+    (
+        "getWorkdirPackages",
+        &[
+            required("workdir", Str),
+            optional("method_uuid", Str),
+            optional("data_uuid", Str),
+        ],
+    ),
+    ("getSubscriptions", &[required("workdir", Str)]),
+    (
+        "prePublish",
+        &[
+            required("workdir", Str),
+            required("move_toml_path", Str),
+            required("package_name", Str),
+        ],
+    ),
+    (
+        "postPublish",
+        &[
+            required("workdir", Str),
+            required("move_toml_path", Str),
+            required("package_name", Str),
+            required("package_uuid", Str),
+            required("package_timestamp", Str),
+            required("package_id", Str),
+        ],
+    ),
+];
+
+// Alias accepted by jsonrpsee (e.g. "tailLines" for "tail_lines").
+fn lower_camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut is_upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            is_upper = true;
+        } else if is_upper {
+            camel.extend(c.to_uppercase());
+            is_upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+pub fn method_params(method: &str) -> Option<&'static [ParamSpec]> {
+    API_PARAMS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map(|(_, params)| *params)
+}
+
+// The raw params of a request (None when there is none).
+//
+// Debug builds assert that the method has its API_PARAMS entry.
+pub fn validate_params(method: &str, raw_params: Option<&str>) -> Result<(), RpcInputError> {
+    let specs = match method_params(method) {
+        Some(specs) => specs,
+        None => {
+            debug_assert!(
+                false,
+                "method {} registered without API_PARAMS entry",
+                method
+            );
+            return Ok(());
+        }
+    };
+    let params = match raw_params.map(serde_json::from_str::<JsonValue>) {
+        None => JsonValue::Null,
+        Some(Ok(params)) => params,
+        Some(Err(e)) => {
+            return Err(RpcInputError::InvalidParams(
+                "params".to_string(),
+                e.to_string(),
+            ))
+        }
+    };
+
+    let null = JsonValue::Null;
+    let mut values: Vec<&JsonValue> = vec![&null; specs.len()];
+    match &params {
+        JsonValue::Null => {}
+        JsonValue::Object(by_name) => {
+            for (key, value) in by_name {
+                match specs.iter().position(|spec| spec.is_named(key)) {
+                    Some(idx) => values[idx] = value,
+                    None => return Err(RpcInputError::UnknownParam(key.to_string())),
+                }
+            }
+        }
+        JsonValue::Array(by_position) => {
+            if by_position.len() > specs.len() {
+                return Err(RpcInputError::UnknownParam(format!("[{}]", specs.len())));
+            }
+            for (idx, value) in by_position.iter().enumerate() {
+                values[idx] = value;
+            }
+        }
+        _ => {
+            return Err(RpcInputError::InvalidParamType(
+                "params".to_string(),
+                "an object or an array".to_string(),
+            ))
+        }
+    }
+
+    for (spec, value) in specs.iter().zip(values) {
+        spec.validate(value)?;
+    }
+    Ok(())
+}
+
+struct RawParams(Option<String>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        self.0.map(RawValue::from_string).transpose()
+    }
+}
+
+async fn call_validated(
+    methods: &Methods,
+    method: &'static str,
+    params: Params<'static>,
+) -> RpcResult<JsonValue> {
+    let raw_params = params.as_str().map(|raw| raw.to_string());
+    let result = match validate_params(method, raw_params.as_deref()) {
+        Ok(()) => methods
+            .call::<_, JsonValue>(method, RawParams(raw_params))
+            .await
+            .map_err(|e| match e {
+                MethodsError::JsonRpc(e) => e,
+                e => RpcSuibaseError::InternalError(e.to_string()).into(),
+            }),
+        Err(e) => Err(e.into()),
+    };
+    result.map_err(|e| with_method(e, method))
+}
+
+// Same methods, with their params validated first.
+pub fn validated_methods(methods: Methods) -> Methods {
+    let inner = Arc::new(methods);
+    let mut module = RpcModule::new(());
+    for method in inner.method_names() {
+        let inner = inner.clone();
+        let result = module.register_async_method(method, move |params, _| {
+            let inner = inner.clone();
+            async move { call_validated(&inner, method, params).await }
+        });
+        if let Err(e) = result {
+            log::error!("Error registering validated method {}: {}", method, e);
+        }
+    }
+    module.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::api::API_METHODS;
+
+    #[test]
+    fn test_api_params_table() {
+        // Same methods as API_METHODS.
+        let mut names: Vec<&str> = API_PARAMS.iter().map(|(name, _)| *name).collect();
+        let mut methods: Vec<&str> = API_METHODS.iter().map(|(name, _)| *name).collect();
+        names.sort();
+        methods.sort();
+        assert_eq!(names, methods);
+    }
+
+    #[test]
+    fn test_validate_params() {
+        let validate = |params: &str| validate_params("getProcessLog", Some(params));
+
+        assert!(validate(r#"{"workdir": "localnet", "process": "sui"}"#).is_ok());
+        assert!(validate(r#"{"workdir": "localnet", "process": "sui", "tailLines": 5}"#).is_ok());
+        assert!(validate(r#"["localnet", "sui", 5, null]"#).is_ok());
+        assert!(validate(r#"{"workdir": "localnet", "process": "sui", "grep": null}"#).is_ok());
+
+        let field = |params: &str| {
+            let e = validate(params).unwrap_err();
+            (e.code().code(), e.field().to_string())
+        };
+        assert_eq!(
+            field(r#"{"workdir": "localnet", "process": "sui", "tail_line": 5}"#),
+            (-32100, "tail_line".to_string())
+        );
+        assert_eq!(
+            field(r#"["localnet", "sui", 5, "x", 1]"#),
+            (-32100, "[4]".to_string())
+        );
+        assert_eq!(
+            field(r#"{"workdir": "localnet"}"#),
+            (-32101, "process".to_string())
+        );
+        assert_eq!(field(r#"{}"#), (-32101, "workdir".to_string()));
+        assert_eq!(
+            field(r#"{"workdir": "localnet", "process": "sui", "tail_lines": "5"}"#),
+            (-32102, "tail_lines".to_string())
+        );
+        assert_eq!(
+            field(r#"{"workdir": "localnet", "process": "sui", "tail_lines": -1}"#),
+            (-32102, "tail_lines".to_string())
+        );
+        assert_eq!(field(r#""localnet""#), (-32102, "params".to_string()));
+
+        let e = validate_params("mergeGasCoins", Some(r#"["localnet", null, 1000]"#)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "params max_coins_per_tx value 1000 is out of range [2..500]"
+        );
+        assert!(validate_params("mergeGasCoins", Some(r#"["localnet", null, 500]"#)).is_ok());
+        assert!(validate_params("getCapabilities", None).is_ok());
+        assert!(validate_params("getCapabilities", Some("[]")).is_ok());
+    }
+}
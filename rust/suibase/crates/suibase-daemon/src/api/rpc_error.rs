@@ -1,11 +1,26 @@
 // Convert various error type to a RpcError.
 //
-// All errors are map into one of the jsonrpsee "CallError" (e.g. InvalidParams, Failed, Custom).
+// Every error returned by the API has a code of the RpcErrorCode registry. The codes
+// are grouped by category, so a client can handle a whole category without knowing
+// every code:
 //
-// RpcInputError map to CallError::InvalidParams.
-// RpcServerError map to CallError::Failed.
+//   Validation  -32602 (JSON-RPC "Invalid params") and -32100..=-32199
+//   Not found   -32200..=-32299
+//   Busy        -32300..=-32399  Conflicts with the current state (retry later).
+//   Internal    -32603 (JSON-RPC "Internal error") and -32400..=-32499
+//
+// A code is never re-used for another meaning. A new code must be added to
+// RpcErrorCode::ALL.
+//
+// The "data" of the error is an object with the "method" (see params.rs) and, for the
+// validation errors, the "field" of the params.
+//
+// RpcInputError are the validation errors, RpcSuibaseError all the others.
+use std::ops::RangeInclusive;
 
+use common::basic_types::{JSONRPC_INTERNAL_ERROR, JSONRPC_INVALID_PARAMS};
 use jsonrpsee_types::ErrorObjectOwned as RpcError;
+use serde_json::{json, Map, Value as JsonValue};
 
 impl From<RpcInputError> for RpcError {
     fn from(e: RpcInputError) -> Self {
@@ -19,10 +34,119 @@ impl From<RpcSuibaseError> for RpcError {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorCategory {
+    Validation,
+    NotFound,
+    Busy,
+    Internal,
+}
+
+impl RpcErrorCategory {
+    // The codes specific to this daemon (the standard JSON-RPC code excluded).
+    pub fn range(self) -> RangeInclusive<i32> {
+        match self {
+            Self::Validation => -32199..=-32100,
+            Self::NotFound => -32299..=-32200,
+            Self::Busy => -32399..=-32300,
+            Self::Internal => -32499..=-32400,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorCode {
+    // Validation.
+    InvalidParams,
+    UnknownParam,
+    MissingParam,
+    InvalidParamType,
+    ParamOutOfRange,
+    // Not found.
+    NotFound,   // e.g. a snapshot, a job or the active address.
+    NotEnabled, // e.g. the proxy server of a workdir.
+    // Busy.
+    Busy,         // e.g. another job in progress.
+    Initializing, // The daemon did not yet retrieve the data.
+    OutdatedUUID, // The data changed since the caller got its UUID.
+    Conflict,     // e.g. the snapshot already exists.
+    // Internal.
+    InternalError,
+    FileAccessError,
+}
+
+impl RpcErrorCode {
+    pub const ALL: &'static [RpcErrorCode] = &[
+        Self::InvalidParams,
+        Self::UnknownParam,
+        Self::MissingParam,
+        Self::InvalidParamType,
+        Self::ParamOutOfRange,
+        Self::NotFound,
+        Self::NotEnabled,
+        Self::Busy,
+        Self::Initializing,
+        Self::OutdatedUUID,
+        Self::Conflict,
+        Self::InternalError,
+        Self::FileAccessError,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            Self::InvalidParams => JSONRPC_INVALID_PARAMS,
+            Self::UnknownParam => -32100,
+            Self::MissingParam => -32101,
+            Self::InvalidParamType => -32102,
+            Self::ParamOutOfRange => -32103,
+            Self::NotFound => -32200,
+            Self::NotEnabled => -32201,
+            Self::Busy => -32300,
+            Self::Initializing => -32301,
+            Self::OutdatedUUID => -32302,
+            Self::Conflict => -32303,
+            Self::InternalError => JSONRPC_INTERNAL_ERROR,
+            Self::FileAccessError => -32400,
+        }
+    }
+
+    pub fn category(self) -> RpcErrorCategory {
+        match self {
+            Self::InvalidParams
+            | Self::UnknownParam
+            | Self::MissingParam
+            | Self::InvalidParamType
+            | Self::ParamOutOfRange => RpcErrorCategory::Validation,
+            Self::NotFound | Self::NotEnabled => RpcErrorCategory::NotFound,
+            Self::Busy | Self::Initializing | Self::OutdatedUUID | Self::Conflict => {
+                RpcErrorCategory::Busy
+            }
+            Self::InternalError | Self::FileAccessError => RpcErrorCategory::Internal,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.code() == code)
+    }
+
+    pub fn rpc_error(self, message: String, field: Option<&str>) -> RpcError {
+        let data = field.map(|field| json!({ "field": field }));
+        RpcError::owned(self.code(), message, data)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RpcInputError {
     #[error("params {0} has invalid value '{1}'")]
     InvalidParams(String, String),
+    #[error("params {0} is unknown")]
+    UnknownParam(String),
+    #[error("params {0} is missing")]
+    MissingParam(String),
+    #[error("params {0} must be {1}")]
+    InvalidParamType(String, String),
+    #[error("params {0} value {1} is out of range [{2}]")]
+    ParamOutOfRange(String, String, String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -34,28 +158,130 @@ pub enum RpcSuibaseError {
     #[error("outdated uuid")]
     OutdatedUUID(),
     #[error("{0}")]
-    InfoError(String),
-
+    NotFound(String),
+    #[error("{0}")]
+    NotEnabled(String),
+    #[error("{0}")]
+    Busy(String),
+    #[error("Backend initializing. {0}")]
+    Initializing(String),
+    #[error("{0}")]
+    Conflict(String),
 }
 
 impl RpcInputError {
+    pub fn code(&self) -> RpcErrorCode {
+        match self {
+            Self::InvalidParams(..) => RpcErrorCode::InvalidParams,
+            Self::UnknownParam(..) => RpcErrorCode::UnknownParam,
+            Self::MissingParam(..) => RpcErrorCode::MissingParam,
+            Self::InvalidParamType(..) => RpcErrorCode::InvalidParamType,
+            Self::ParamOutOfRange(..) => RpcErrorCode::ParamOutOfRange,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        match self {
+            Self::InvalidParams(field, _)
+            | Self::UnknownParam(field)
+            | Self::MissingParam(field)
+            | Self::InvalidParamType(field, _)
+            | Self::ParamOutOfRange(field, ..) => field,
+        }
+    }
+
     pub fn rpc_error(self) -> RpcError {
-        let message = format!("{}", self);
-        jsonrpsee_types::ErrorObject::owned(
-            jsonrpsee_types::error::ErrorCode::InvalidParams.code(),
-            message,
-            None::<()>,
-        )
+        self.code()
+            .rpc_error(format!("{}", self), Some(self.field()))
     }
 }
 
 impl RpcSuibaseError {
+    pub fn code(&self) -> RpcErrorCode {
+        match self {
+            Self::InternalError(..) => RpcErrorCode::InternalError,
+            Self::FileAccessError(..) => RpcErrorCode::FileAccessError,
+            Self::OutdatedUUID() => RpcErrorCode::OutdatedUUID,
+            Self::NotFound(..) => RpcErrorCode::NotFound,
+            Self::NotEnabled(..) => RpcErrorCode::NotEnabled,
+            Self::Busy(..) => RpcErrorCode::Busy,
+            Self::Initializing(..) => RpcErrorCode::Initializing,
+            Self::Conflict(..) => RpcErrorCode::Conflict,
+        }
+    }
+
     pub fn rpc_error(self) -> RpcError {
-        let message = format!("{}", self);
-        jsonrpsee_types::ErrorObject::owned(
-            jsonrpsee_types::error::ErrorCode::InternalError.code(),
-            message,
-            None::<()>,
-        )
+        self.code().rpc_error(format!("{}", self), None)
+    }
+}
+
+// Adds the method to the data of the error.
+//
+// A code not in the registry (e.g. from jsonrpsee itself) becomes an InternalError,
+// with the original code and data kept in the "details".
+pub fn with_method(error: RpcError, method: &str) -> RpcError {
+    let data = error
+        .data()
+        .and_then(|data| serde_json::from_str::<JsonValue>(data.get()).ok());
+    let (code, mut data) = match (RpcErrorCode::from_code(error.code()), data) {
+        (Some(code), Some(JsonValue::Object(data))) => (code, data),
+        (Some(code), None) => (code, Map::new()),
+        (Some(code), Some(details)) => (code, Map::from_iter([("details".to_string(), details)])),
+        (None, details) => {
+            let details = json!({ "code": error.code(), "data": details });
+            (
+                RpcErrorCode::InternalError,
+                Map::from_iter([("details".to_string(), details)]),
+            )
+        }
+    };
+    data.insert("method".to_string(), json!(method));
+    RpcError::owned(
+        code.code(),
+        error.message().to_string(),
+        Some(JsonValue::Object(data)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_registry() {
+        let mut codes: Vec<i32> = RpcErrorCode::ALL.iter().map(|c| c.code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), RpcErrorCode::ALL.len());
+
+        // Within the range of its category (or the standard code).
+        for code in RpcErrorCode::ALL {
+            let value = code.code();
+            assert!(
+                code.category().range().contains(&value)
+                    || (value == JSONRPC_INVALID_PARAMS
+                        && code.category() == RpcErrorCategory::Validation)
+                    || (value == JSONRPC_INTERNAL_ERROR
+                        && code.category() == RpcErrorCategory::Internal),
+                "{:?}",
+                code
+            );
+            assert_eq!(RpcErrorCode::from_code(value), Some(*code));
+        }
+
+        let error = with_method(
+            RpcInputError::MissingParam("workdir".to_string()).into(),
+            "getLinks",
+        );
+        assert_eq!(error.code(), -32101);
+        assert_eq!(error.message(), "params workdir is missing");
+        let data: JsonValue = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(data, json!({ "field": "workdir", "method": "getLinks" }));
+
+        let error = with_method(RpcError::owned(-32000, "oops", Some("why")), "getLinks");
+        assert_eq!(error.code(), JSONRPC_INTERNAL_ERROR);
+        let data: JsonValue = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(data["method"], "getLinks");
+        assert_eq!(data["details"], json!({ "code": -32000, "data": "why" }));
     }
 }
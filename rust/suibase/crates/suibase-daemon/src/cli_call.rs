@@ -0,0 +1,229 @@
+// Client side of the "suibase-daemon call" subcommand (and its shorthands).
+//
+// Sends a single JSON-RPC request to the API of the daemon already running for this
+// user, and pretty-prints the result (or the error) as JSON:
+//
+//   suibase-daemon call getLinks --workdir testnet --param summary=false
+//   suibase-daemon links [testnet]     (getLinks, default is the active workdir)
+//   suibase-daemon status              (getWorkdirsStatus)
+//
+// The params are sent by name. A --param value of true/false, an integer or null is
+// sent as such, anything else as a string (quote it in JSON to force a string, e.g.
+// name='"123"').
+//
+// The API port is the one the daemon actually listens on (see ActivePorts), so this
+// works when the configured port was already in use.
+//
+// Exit codes (clap already uses 2 for a bad command line):
+//   0  Success.
+//   1  Daemon not reachable, invalid --param or invalid response.
+//   3  JSON-RPC error returned by the daemon (e.g. unknown method, invalid params).
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+
+use crate::shared_types::{read_active_workdir, ACTIVE_PORTS_FILENAME, WORKDIR_IDX_LOCALNET};
+use crate::shared_types::{GlobalsConfigST, WORKDIRS_KEYS};
+
+pub const EXIT_CALL_RPC_ERROR: i32 = 3;
+
+const CALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallOutput {
+    pub json: Value, // The "result", or the "error" object.
+    pub exit_code: i32,
+}
+
+// Same port as in workdirs/common/active-ports.yaml, otherwise the default.
+pub fn api_port(workdirs_path: &Path) -> u16 {
+    std::fs::read_to_string(workdirs_path.join("common").join(ACTIVE_PORTS_FILENAME))
+        .ok()
+        .and_then(|contents| serde_yaml::from_str::<serde_yaml::Value>(&contents).ok())
+        .and_then(|yaml| yaml["api_port"].as_u64())
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or_else(|| GlobalsConfigST::new().daemon_port)
+}
+
+// The workdir of the "links" shorthand when not specified.
+pub fn default_workdir(workdirs_path: &Path) -> String {
+    read_active_workdir(workdirs_path)
+        .filter(|workdir| WORKDIRS_KEYS.contains(&workdir.as_str()))
+        .unwrap_or_else(|| WORKDIRS_KEYS[WORKDIR_IDX_LOCALNET as usize].to_string())
+}
+
+pub fn parse_param_value(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" => Value::Null,
+        _ => {
+            if let Ok(n) = value.parse::<i64>() {
+                json!(n)
+            } else if let Ok(n) = value.parse::<u64>() {
+                json!(n)
+            } else if value.starts_with('"') {
+                serde_json::from_str(value).unwrap_or_else(|_| json!(value))
+            } else {
+                json!(value)
+            }
+        }
+    }
+}
+
+// The "key=value" of each --param, plus the workdir (when set).
+pub fn build_params(params: &[String], workdir: Option<&str>) -> Result<Value> {
+    let mut by_name = Map::new();
+    if let Some(workdir) = workdir {
+        by_name.insert("workdir".to_string(), json!(workdir));
+    }
+    for param in params {
+        let (key, value) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--param {} is not key=value", param))?;
+        if key.is_empty() {
+            bail!("--param {} has no key", param);
+        }
+        by_name.insert(key.to_string(), parse_param_value(value));
+    }
+    Ok(Value::Object(by_name))
+}
+
+pub async fn call(port: u16, method: &str, params: Value) -> Result<CallOutput> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let url = format!("http://127.0.0.1:{}", port);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .timeout(CALL_TIMEOUT)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| anyhow!("suibase-daemon not reachable at {} ({})", url, e))?;
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| anyhow!("invalid response from {} ({})", url, e))?;
+
+    if let Some(error) = body.get("error") {
+        return Ok(CallOutput {
+            json: error.clone(),
+            exit_code: EXIT_CALL_RPC_ERROR,
+        });
+    }
+    match body.get("result") {
+        Some(result) => Ok(CallOutput {
+            json: result.clone(),
+            exit_code: 0,
+        }),
+        None => bail!("invalid response from {} (no result)", url),
+    }
+}
+
+// Print the output, and returns the exit code.
+pub async fn execute(workdirs_path: &Path, method: &str, params: Value) -> Result<i32> {
+    let output = call(api_port(workdirs_path), method, params).await?;
+    let text = serde_json::to_string_pretty(&output.json)?;
+    if output.exit_code == 0 {
+        println!("{}", text);
+    } else {
+        eprintln!("{}", text);
+    }
+    Ok(output.exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::basic_types::MPSC_Q_SIZE;
+    use jsonrpsee::server::ServerBuilder;
+
+    use crate::api::build_api_methods;
+    use crate::shared_types::Globals;
+
+    #[test]
+    fn test_build_params() {
+        let params = vec![
+            "summary=false".to_string(),
+            "limit=10".to_string(),
+            "offset=-1".to_string(),
+            "month=2024-05".to_string(),
+            "name=\"123\"".to_string(),
+            "note=a=b".to_string(),
+            "since=null".to_string(),
+        ];
+        assert_eq!(
+            build_params(&params, Some("testnet")).unwrap(),
+            json!({
+                "workdir": "testnet",
+                "summary": false,
+                "limit": 10,
+                "offset": -1,
+                "month": "2024-05",
+                "name": "123",
+                "note": "a=b",
+                "since": null,
+            })
+        );
+        assert!(build_params(&["summary".to_string()], None).is_err());
+        assert!(build_params(&["=1".to_string()], None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let globals = Globals::new();
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+        let handle = server.start(build_api_methods(&globals, &admctrl_tx));
+
+        // The port is found in active-ports.yaml.
+        let dir = std::env::temp_dir().join(format!("sbsd-cli-call-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        std::fs::write(
+            dir.join("common").join(ACTIVE_PORTS_FILENAME),
+            format!("api_port: {}\n", port),
+        )
+        .unwrap();
+        assert_eq!(api_port(&dir), port);
+        assert_eq!(default_workdir(&dir), "localnet");
+
+        let output = call(api_port(&dir), "getWorkdirsStatus", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.json["header"]["method"], "getWorkdirsStatus");
+        assert!(output.json["workdirs"].is_array());
+
+        // Params by name.
+        let params = build_params(&[], Some("localnet")).unwrap();
+        let output = call(port, "getVersions", params).await.unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.json["header"]["key"], "localnet");
+
+        // Coerced to an integer, so only the workdir is rejected (no proxy yet).
+        let params = build_params(&["limit=1".to_string()], Some("localnet")).unwrap();
+        let output = call(port, "getConfigHistory", params).await.unwrap();
+        assert_eq!(output.exit_code, EXIT_CALL_RPC_ERROR);
+        assert_eq!(
+            output.json["message"],
+            "params workdir has invalid value 'localnet'"
+        );
+
+        let output = call(port, "noSuchMethod", json!({})).await.unwrap();
+        assert_eq!(output.exit_code, EXIT_CALL_RPC_ERROR);
+        assert_eq!(output.json["code"], -32601);
+
+        handle.stop().unwrap();
+        handle.stopped().await;
+        assert!(call(port, "getWorkdirsStatus", json!({})).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -9,6 +9,7 @@
 //     - NetworkMonitor: Maintains all remote server stats. Info coming from multiple sources (on a mpsc channel).
 //     - APIServer: Does "sandboxing" of the JSON-RPC server (auto-restart in case of panic).
 //     - ClockTrigger: Send periodic audit events to other threads.
+//     - WebhookWorker: POST events (e.g. link status change) to the user configured webhooks.
 //
 // Other tasks (not started here):
 //
@@ -43,15 +44,14 @@ use anyhow::Result;
 use api::APIServerParams;
 use clap::*;
 
-use clock_trigger::{ClockTrigger, ClockTriggerParams};
+use clock_trigger::NetmonClockHooks;
 use colored::Colorize;
-use common::basic_types::MPSC_Q_SIZE;
-use env_logger::{Builder, Env};
+use common::basic_types::{LogControl, MPSC_Q_SIZE};
+use common::workers::{ClockTrigger, ClockTriggerParams};
 
 mod admin_controller;
 mod api;
-mod app_error;
-
+mod cli_call;
 mod clock_trigger;
 mod network_monitor;
 mod proxy_server;
@@ -59,7 +59,7 @@ mod shared_types;
 mod workdirs_watcher;
 mod workers;
 
-use shared_types::Globals;
+use shared_types::{Globals, WebhookTx};
 use tokio::time::Duration;
 
 use crate::admin_controller::AdminController;
@@ -70,6 +70,7 @@ use tokio_graceful_shutdown::{
     SubsystemBuilder, Toplevel,
 };
 
+use workers::WebhookWorker;
 use workers::WebserverParams;
 use workers::WebserverWorker;
 
@@ -85,6 +86,29 @@ use workers::WebserverWorker;
 pub enum Command {
     #[clap(name = "run")]
     Run {},
+
+    /// Call a JSON-RPC method of the running daemon and print the JSON response.
+    #[clap(name = "call")]
+    Call {
+        /// e.g. getLinks
+        method: String,
+
+        /// Param by name, as key=value (repeat for each param).
+        #[clap(long = "param", short = 'p')]
+        params: Vec<String>,
+
+        /// Same as --param workdir=<WORKDIR>
+        #[clap(long)]
+        workdir: Option<String>,
+    },
+
+    /// Links of a workdir (getLinks). Default is the active workdir.
+    #[clap(name = "links")]
+    Links { workdir: Option<String> },
+
+    /// Status of all the workdirs (getWorkdirsStatus).
+    #[clap(name = "status")]
+    Status {},
 }
 
 impl Command {
@@ -99,8 +123,9 @@ impl Command {
                 let my_pid = std::process::id();
 
                 // Call the bash script "~/suibase/scripts/verify-suibase-daemon-lock $my_pid"
-                let home_dir = home::home_dir().expect("Failed to get home directory");
-                let script_path = home_dir.join("suibase/scripts/common/verify-suibase-daemon-lock.sh");
+                let suibase_home =
+                    common::utils::suibase_home().expect("Failed to get home directory");
+                let script_path = suibase_home.join("scripts/common/verify-suibase-daemon-lock.sh");
 
                 // Call the bash script "~/suibase/scripts/verify-suibase-daemon-lock $my_pid"
                 // Returns OK when the process is the one running under file lock ~/tmp/.suibase-daemon.lock
@@ -129,6 +154,7 @@ impl Command {
                 //
                 let (admctrl_tx, admctrl_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
                 let (netmon_tx, netmon_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+                let (webhook_tx, webhook_rx) = WebhookTx::channel(globals.webhook_stats.clone());
 
                 // Instantiate and connect all subsystems (while none is "running" yet).
                 let admctrl = AdminController::new(
@@ -136,16 +162,24 @@ impl Command {
                     admctrl_rx,
                     admctrl_tx.clone(),
                     netmon_tx.clone(),
+                    webhook_tx.clone(),
                 );
 
-                let netmon =
-                    NetworkMonitor::new(globals.proxy.clone(), netmon_rx, netmon_tx.clone());
+                let netmon = NetworkMonitor::new(
+                    globals.proxy.clone(),
+                    netmon_rx,
+                    netmon_tx.clone(),
+                    webhook_tx,
+                );
+
+                let webhook_worker = WebhookWorker::new(webhook_rx, globals.webhook_stats.clone());
 
                 let apiserver_params = APIServerParams::new(globals.clone(), admctrl_tx.clone());
                 let apiserver = APIServer::new(apiserver_params);
 
-                let clock_params = ClockTriggerParams::new(netmon_tx.clone(), admctrl_tx.clone());
-                let clock: ClockTrigger = ClockTrigger::new(clock_params);
+                let clock_hooks = NetmonClockHooks::new(netmon_tx.clone());
+                let clock_params = ClockTriggerParams::new(clock_hooks, admctrl_tx.clone());
+                let clock = ClockTrigger::new(clock_params);
 
                 let suiexplorer_params =
                     WebserverParams::new(globals.clone(), admctrl_tx.clone(), "sui-explorer");
@@ -155,6 +189,7 @@ impl Command {
                 let errors = Toplevel::new(|s| async move {
                     s.start(SubsystemBuilder::new("admctrl", |a| admctrl.run(a)));
                     s.start(SubsystemBuilder::new("netmon", |a| netmon.run(a)));
+                    s.start(SubsystemBuilder::new("webhooks", |a| webhook_worker.run(a)));
                     s.start(SubsystemBuilder::new("clock", |a| clock.run(a)));
                     s.start(SubsystemBuilder::new("suiexplorer", |a| suiexplorer.run(a)));
                     s.start(SubsystemBuilder::new("apiserver", |a| apiserver.run(a)));
@@ -193,7 +228,39 @@ impl Command {
                 }
                 Ok(errors?)
             } // end Command::Run
+            Command::Call {
+                method,
+                params,
+                workdir,
+            } => {
+                let params = cli_call::build_params(&params, workdir.as_deref())?;
+                Self::exit_with_call(&globals, &method, params).await
+            }
+            Command::Links { workdir } => {
+                let workdirs_path = globals.workdirs.read().await.path().to_path_buf();
+                let workdir = workdir.unwrap_or_else(|| cli_call::default_workdir(&workdirs_path));
+                let params = cli_call::build_params(&[], Some(&workdir))?;
+                Self::exit_with_call(&globals, "getLinks", params).await
+            }
+            Command::Status {} => {
+                let params = cli_call::build_params(&[], None)?;
+                Self::exit_with_call(&globals, "getWorkdirsStatus", params).await
+            }
+        }
+    }
+
+    // A non-zero exit code when the daemon returns a JSON-RPC error.
+    async fn exit_with_call(
+        globals: &Globals,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        let workdirs_path = globals.workdirs.read().await.path().to_path_buf();
+        let exit_code = cli_call::execute(&workdirs_path, method, params).await?;
+        if exit_code != 0 {
+            std::process::exit(exit_code);
         }
+        Ok(())
     }
 } // end of Command
 
@@ -214,9 +281,14 @@ async fn main() {
     #[cfg(windows)]
     colored::control::set_virtual_terminal(true).unwrap();
 
-    Builder::from_env(Env::default().default_filter_or("info"))
-        .filter(Some("jsonrpsee_server::server"), log::LevelFilter::Warn)
-        .init();
+    // Levels can be changed at runtime with the setLogLevel API. The output
+    // format is controlled by 'log_format' in the common suibase.yaml.
+    if let Err(e) = LogControl::init(
+        "info",
+        &[("jsonrpsee_server::server", log::LevelFilter::Warn)],
+    ) {
+        eprintln!("{}", e);
+    }
 
     let cmd: Command = Command::parse();
 
@@ -1,13 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use crate::shared_types::{InputPort, REQUEST_FAILED_NO_SERVER_AVAILABLE};
 use common::basic_types::*;
 
 use crate::shared_types::{
-    GlobalsProxyMT, RequestFailedReason, SendFailedReason, ServerStats, TargetServer,
+    checkpoint_timestamp_ms, estimate_time_skew_ms, unix_time_ms, usage_month, GlobalsProxyMT,
+    LinkClient, LinkRole, QuotaErrorRule, RequestFailedReason, SendFailedReason, ServerStats,
+    TargetServer, WarmUpProgress, WebhookEvent, WebhookEventType, WebhookTx,
     REQUEST_FAILED_BAD_REQUEST_HTTP, SEND_FAILED_RESP_HTTP_STATUS, SEND_FAILED_UNSPECIFIED_STATUS,
+    TIME_SKEW_PROBE_REQUEST_BODY,
 };
-use crate::workers::RequestWorker;
+
+use common::workers::{RequestWorker, ServerCheckMsg};
 
 use bitflags::bitflags;
 
@@ -16,8 +20,7 @@ use tokio_graceful_shutdown::{FutureExt, SubsystemBuilder, SubsystemHandle};
 
 use tokio::time::{Duration, Instant};
 
-pub const HEADER_SBSD_SERVER_IDX: &str = "X-SBSD-SERVER-IDX";
-pub const HEADER_SBSD_SERVER_HC: &str = "X-SBSD-SERVER-HC";
+pub use common::workers::{HEADER_SBSD_SERVER_HC, HEADER_SBSD_SERVER_IDX};
 
 pub struct NetmonMsg {
     // Internal messaging. Sent for every user request/response.
@@ -31,6 +34,7 @@ pub struct NetmonMsg {
     para32: [u32; 2],
     para8: [u8; 2],
     para16: [u16; 1],
+    para_i32: [i32; 1], // JSON-RPC error code (when JSONRPC_ERROR_SET, with category in para8[1]).
 }
 
 impl NetmonMsg {
@@ -44,6 +48,7 @@ impl NetmonMsg {
             para32: [0; 2],
             para8: [0; 2],
             para16: [0; 1],
+            para_i32: [0; 1],
         }
     }
     pub fn server_idx(&self) -> u8 {
@@ -58,6 +63,10 @@ impl NetmonMsg {
     pub fn para16(&self) -> &[u16; 1] {
         &self.para16
     }
+
+    pub fn is_proxy_tls(&self) -> bool {
+        self.flags.intersects(NetmonFlags::PROXY_TLS)
+    }
     /*
     pub fn para8(&self) -> &[u8; 2] {
         &self.para8
@@ -65,6 +74,22 @@ impl NetmonMsg {
     */
 }
 
+// For the RequestWorker (EVENT_DO_SERVER_HEALTH_CHECK).
+impl ServerCheckMsg for NetmonMsg {
+    // Inherent methods have priority (no recursion).
+    fn server_idx(&self) -> u8 {
+        self.server_idx()
+    }
+
+    fn proxy_port(&self) -> u16 {
+        self.para16()[0]
+    }
+
+    fn is_proxy_tls(&self) -> bool {
+        self.is_proxy_tls()
+    }
+}
+
 // Events ID.
 // See GenericChannelID for guidelines to set these values.
 pub type NetmonEvent = u8;
@@ -73,6 +98,10 @@ pub const EVENT_REPORT_TGT_REQ_RESP_OK: u8 = 129; // proxy_server reporting stat
 pub const EVENT_REPORT_TGT_REQ_RESP_ERR: u8 = 130; // proxy_server reporting stats on a response indicating an error.
 pub const EVENT_REPORT_TGT_SEND_FAILED: u8 = 131; // proxy_server reporting stats on a failed send attempt.
 pub const EVENT_DO_SERVER_HEALTH_CHECK: u8 = 132; // Start an async health check (a request/response test) for one server.
+pub const EVENT_SAMPLE_LOAD: u8 = 133; // Periodic sampling of the request rates of every server.
+pub const EVENT_REPORT_TGT_THROTTLED: u8 = 134; // proxy_server reporting a rate limited request (e.g. HTTP 429).
+pub const EVENT_REPORT_TGT_HEDGED: u8 = 135; // proxy_server reporting a request also sent to a second server.
+pub const EVENT_EVAL_MAINTENANCE: u8 = 136; // Periodic evaluation of the maintenance windows of every server.
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -81,6 +110,8 @@ bitflags! {
         const NEED_GLOBAL_READ_MUTEX = 0x02;
         const HEADER_SBSD_SERVER_IDX_SET = 0x04;
         const HEADER_SBSD_SERVER_HC_SET = 0x08;
+        const JSONRPC_ERROR_SET = 0x10;
+        const PROXY_TLS = 0x20; // Health check must use https (see EVENT_DO_SERVER_HEALTH_CHECK).
     }
 }
 
@@ -104,23 +135,192 @@ impl std::fmt::Debug for NetmonMsg {
 pub type NetMonTx = tokio::sync::mpsc::Sender<NetmonMsg>;
 pub type NetMonRx = tokio::sync::mpsc::Receiver<NetmonMsg>;
 
+// Window for the QPM (the QPS is over the interval between the two most recent samples).
+const LOAD_QPM_WINDOW: Duration = Duration::from_secs(60);
+
+// Window of the max_per_day quota (a UTC day, like most providers).
+const LOAD_DAY_SECS: u64 = 24 * 60 * 60;
+
+// Samples of the cumulative request count of a server, to derive its request rates.
+struct LoadSampler {
+    samples: VecDeque<(EpochTimestamp, u64)>,
+
+    // (day since the UNIX epoch, requests counted in that day).
+    //
+    // Requests are counted when sampled, so the ones done just before midnight may
+    // be counted in the next day (a small skew, the quota is only a soft limit).
+    day_window: Option<(u64, u64)>,
+    clock: SharedClock,
+}
+
+impl LoadSampler {
+    pub fn new() -> Self {
+        Self::new_with_clock(SharedClock::default())
+    }
+
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            day_window: None,
+            clock,
+        }
+    }
+
+    // Requests since 00:00 UTC (as of the most recent sample).
+    pub fn day_count(&self) -> u64 {
+        let today = self.clock.now_secs() / LOAD_DAY_SECS;
+        match self.day_window {
+            Some((day, count)) if day == today => count,
+            _ => 0,
+        }
+    }
+
+    fn count_in_day_window(&mut self, n_request: u64) {
+        let today = self.clock.now_secs() / LOAD_DAY_SECS;
+        self.day_window = match self.day_window {
+            Some((day, count)) if day == today => Some((day, count + n_request)),
+            _ => Some((today, n_request)),
+        };
+    }
+
+    fn rate(n_request: u64, duration: Duration) -> f64 {
+        let secs = duration.as_secs_f64();
+        if secs > 0.0 {
+            n_request as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    // Returns (qps, qpm).
+    pub fn sample(&mut self, now: EpochTimestamp, request_count: u64) -> (f64, f64) {
+        let prev = self.samples.back().copied();
+        let n_request = match prev {
+            Some((_, prev_count)) if request_count >= prev_count => request_count - prev_count,
+            // Stats were cleared (or first sample), all the requests are new.
+            _ => request_count,
+        };
+        // Unlike the rates, the day window survives a clear of the stats.
+        self.count_in_day_window(n_request);
+
+        if let Some((_, prev_count)) = prev {
+            if request_count < prev_count {
+                // Stats were cleared. Start over.
+                self.samples.clear();
+                self.samples.push_back((now, request_count));
+                return (0.0, 0.0);
+            }
+        }
+        self.samples.push_back((now, request_count));
+
+        // Keep the most recent sample that is at least LOAD_QPM_WINDOW old.
+        while self.samples.len() > 1 && now.duration_since(self.samples[1].0) >= LOAD_QPM_WINDOW {
+            self.samples.pop_front();
+        }
+
+        let qps = match prev {
+            Some((ts, count)) => Self::rate(request_count - count, now.duration_since(ts)),
+            None => 0.0,
+        };
+        let (oldest_ts, oldest_count) = self.samples[0];
+        let qpm = Self::rate(request_count - oldest_count, now.duration_since(oldest_ts)) * 60.0;
+        (qps, qpm)
+    }
+}
+
+// Interval between scrapes of the Prometheus "metrics" URL of a link.
+const METRICS_SCRAPE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Interval between requests for the latest checkpoint of a link, to measure the clock
+// skew (see time_skew.rs). Not done while the metrics scrape provides a fresher one.
+const TIME_SKEW_PROBE_INTERVAL: Duration = Duration::from_secs(600);
+
+// Interval between health checks of a link, and while in a maintenance window (only to
+// know its health at the end of the window).
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const MAINTENANCE_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Longest delay for the stats of a successful request to be visible in the globals
+// (e.g. getLinks). Any other message requiring the write lock applies them sooner.
+//
+// This batching is what keeps the write lock off the per-request path. The ServerStats
+// are not converted to atomics: the up/down scores and the quota windows are
+// order-dependent state machines, which batching keeps exact without a rewrite.
+const STATS_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+// Stats pending are applied right away past this count.
+const STATS_FLUSH_MAX: usize = 4096;
+
 struct MonitorData {
     most_recent_latency_test_attempted: Option<EpochTimestamp>,
+    most_recent_metrics_scrape: Option<EpochTimestamp>,
+    most_recent_time_skew_probe: Option<EpochTimestamp>,
+    load_sampler: LoadSampler,
 }
 
 impl MonitorData {
     pub fn new() -> Self {
         Self {
             most_recent_latency_test_attempted: None,
+            most_recent_metrics_scrape: None,
+            most_recent_time_skew_probe: None,
+            load_sampler: LoadSampler::new(),
         }
     }
 }
 
+// Sui node metrics of interest in a Prometheus text exposition.
+//
+// Returns (uptime, highest synced checkpoint, timestamp of the last executed checkpoint).
+fn parse_node_metrics(text: &str) -> (Option<u64>, Option<u64>, Option<u64>) {
+    let mut uptime_secs = None;
+    let mut checkpoint = None;
+    let mut checkpoint_timestamp_ms = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Format is "name{labels} value [timestamp]" (labels are optional).
+        let (name, rest) = match line.find('{') {
+            Some(pos) => match line.rfind('}') {
+                Some(end) => (&line[..pos], &line[end + 1..]),
+                None => continue,
+            },
+            None => match line.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, rest),
+                None => continue,
+            },
+        };
+        let value = rest
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value >= 0.0)
+            .map(|value| value as u64);
+        match name {
+            "uptime" => uptime_secs = value.or(uptime_secs),
+            "highest_synced_checkpoint" => checkpoint = value.or(checkpoint),
+            "last_executed_checkpoint_timestamp_ms" => {
+                checkpoint_timestamp_ms = value.or(checkpoint_timestamp_ms)
+            }
+            _ => {}
+        }
+    }
+    (uptime_secs, checkpoint, checkpoint_timestamp_ms)
+}
+
 pub struct NetworkMonitor {
     globals: GlobalsProxyMT,
     netmon_rx: NetMonRx,
     mon_map: HashMap<(InputPortIdx, TargetServerIdx), MonitorData>,
     init_time: EpochTimestamp,
+    webhook_tx: WebhookTx, // To notify link status changes.
+    netmon_tx: NetMonTx,   // To schedule the next health check of a link warm-up.
+
+    // Stats not yet applied to the globals (see is_deferrable).
+    pending_stats: Vec<NetmonMsg>,
+    pending_stats_since: Option<Instant>,
+
+    clock: SharedClock, // Wall-clock of the maintenance windows.
 }
 
 // This is how the ProxyHandler communicate with the NetworkMonitor.
@@ -150,23 +350,34 @@ impl<'a> ProxyHandlerReport<'a> {
         &mut self.flags
     }
 
+    // 'jsonrpc_error' is for a JSON-RPC error response (HTTP was successful,
+    // but the server reports an error within the JSON body).
     pub async fn req_resp_ok(
         &mut self,
         server_idx: TargetServerIdx,
         req_initiation_time: EpochTimestamp,
         resp_received: EpochTimestamp,
         retry_count: u8,
+        http_status: u16,
+        jsonrpc_error: Option<(i32, JsonRpcErrorCategory)>,
     ) -> Result<()> {
         let mut msg = NetmonMsg::new();
         msg.event_id = EVENT_REPORT_TGT_REQ_RESP_OK;
         self.flags.insert(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX);
-        msg.flags = self.flags;
+        if let Some((code, category)) = jsonrpc_error {
+            msg.para_i32[0] = code;
+            msg.para8[1] = category.as_u8();
+            msg.flags = self.flags | NetmonFlags::JSONRPC_ERROR_SET;
+        } else {
+            msg.flags = self.flags;
+        }
         msg.port_idx = self.port_idx;
         msg.server_idx = server_idx;
         msg.timestamp = req_initiation_time;
         msg.para32[0] = duration_to_micros(req_initiation_time - self.handler_start);
         msg.para32[1] = duration_to_micros(resp_received - req_initiation_time);
         msg.para8[0] = retry_count;
+        msg.para16[0] = http_status;
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -182,6 +393,7 @@ impl<'a> ProxyHandlerReport<'a> {
         resp_received: EpochTimestamp,
         retry_count: u8,
         reason: RequestFailedReason,
+        http_status: u16,
     ) -> Result<()> {
         let mut msg = NetmonMsg::new();
         msg.event_id = EVENT_REPORT_TGT_REQ_RESP_ERR;
@@ -194,6 +406,7 @@ impl<'a> ProxyHandlerReport<'a> {
         msg.para32[1] = duration_to_micros(resp_received - req_initiation_time);
         msg.para8[0] = retry_count;
         msg.para8[1] = reason;
+        msg.para16[0] = http_status;
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -203,17 +416,31 @@ impl<'a> ProxyHandlerReport<'a> {
     }
 
     pub async fn req_fail(&mut self, retry_count: u8, reason: RequestFailedReason) -> Result<()> {
+        self.req_fail_http_status(0, retry_count, reason, 0).await
+    }
+
+    // Same as req_fail, but also identify the server that responded with an HTTP status.
+    //
+    // A zero http_status means no server involved (server_idx is ignored).
+    pub async fn req_fail_http_status(
+        &mut self,
+        server_idx: TargetServerIdx,
+        retry_count: u8,
+        reason: RequestFailedReason,
+        http_status: u16,
+    ) -> Result<()> {
         let error_time = EpochTimestamp::now();
         let mut msg = NetmonMsg::new();
         msg.event_id = EVENT_REPORT_REQ_FAILED;
         self.flags.insert(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX);
         msg.flags = self.flags;
         msg.port_idx = self.port_idx;
-        msg.server_idx = 0; // Not used.
+        msg.server_idx = server_idx;
         msg.timestamp = error_time;
         msg.para32[0] = duration_to_micros(error_time - self.handler_start);
         msg.para8[0] = retry_count;
         msg.para8[1] = reason;
+        msg.para16[0] = http_status;
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -249,6 +476,55 @@ impl<'a> ProxyHandlerReport<'a> {
         })
     }
 
+    // The server rate limited the request (HTTP 429 or one of its throttle_codes).
+    //
+    // 'http_status' is zero when the response is returned to the user (and accounted
+    // for by req_resp_ok).
+    pub async fn throttled(
+        &mut self,
+        server_idx: TargetServerIdx,
+        req_initiation_time: EpochTimestamp,
+        retry_count: u8,
+        http_status: u16,
+        duration: Duration,
+    ) -> Result<()> {
+        let mut msg = NetmonMsg::new();
+        msg.event_id = EVENT_REPORT_TGT_THROTTLED;
+        self.flags.insert(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX);
+        msg.flags = self.flags;
+        msg.port_idx = self.port_idx;
+        msg.server_idx = server_idx;
+        msg.timestamp = req_initiation_time;
+        msg.para32[0] = duration.as_millis().min(u32::MAX as u128) as u32;
+        msg.para8[0] = retry_count;
+        msg.para16[0] = http_status;
+
+        // Send the message.
+        self.tx_channel.send(msg).await.map_err(|e| {
+            log::debug!("failed {}", e);
+            anyhow!("failed {}", e)
+        })
+    }
+
+    // The request was also sent to 'server_idx' (see ProxyHedgeConfig). 'won' is
+    // true when its response was the one returned to the user.
+    pub async fn hedged(&mut self, server_idx: TargetServerIdx, won: bool) -> Result<()> {
+        let mut msg = NetmonMsg::new();
+        msg.event_id = EVENT_REPORT_TGT_HEDGED;
+        self.flags.insert(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX);
+        msg.flags = self.flags;
+        msg.port_idx = self.port_idx;
+        msg.server_idx = server_idx;
+        msg.timestamp = EpochTimestamp::now();
+        msg.para8[0] = won as u8;
+
+        // Send the message.
+        self.tx_channel.send(msg).await.map_err(|e| {
+            log::debug!("failed {}", e);
+            anyhow!("failed {}", e)
+        })
+    }
+
     // Return true if the cause of the error is
     // the server and the request is likely
     // to succeed with another server.
@@ -273,7 +549,12 @@ impl<'a> ProxyHandlerReport<'a> {
                 | reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE => {
                     // These will not punish the server health score.
                     let _ = self
-                        .req_fail(retry_count, REQUEST_FAILED_BAD_REQUEST_HTTP)
+                        .req_fail_http_status(
+                            *server_idx,
+                            retry_count,
+                            REQUEST_FAILED_BAD_REQUEST_HTTP,
+                            status.as_u16(),
+                        )
                         .await;
                     // Do not try another server.
                     return false;
@@ -307,12 +588,38 @@ impl<'a> ProxyHandlerReport<'a> {
 }
 
 impl NetworkMonitor {
-    pub fn new(globals: GlobalsProxyMT, netmon_rx: NetMonRx, _netmon_tx: NetMonTx) -> Self {
+    pub fn new(
+        globals: GlobalsProxyMT,
+        netmon_rx: NetMonRx,
+        netmon_tx: NetMonTx,
+        webhook_tx: WebhookTx,
+    ) -> Self {
+        Self::new_with_clock(
+            globals,
+            netmon_rx,
+            netmon_tx,
+            webhook_tx,
+            SharedClock::default(),
+        )
+    }
+
+    pub fn new_with_clock(
+        globals: GlobalsProxyMT,
+        netmon_rx: NetMonRx,
+        netmon_tx: NetMonTx,
+        webhook_tx: WebhookTx,
+        clock: SharedClock,
+    ) -> Self {
         Self {
             globals,
             netmon_rx,
             mon_map: HashMap::new(),
             init_time: EpochTimestamp::now(),
+            webhook_tx,
+            netmon_tx,
+            pending_stats: Vec::new(),
+            pending_stats_since: None,
+            clock,
         }
     }
 
@@ -326,6 +633,26 @@ impl NetworkMonitor {
         })
     }
 
+    pub async fn send_event_sample_load(tx_channel: &NetMonTx) -> Result<()> {
+        let mut msg = NetmonMsg::new();
+        msg.event_id = EVENT_SAMPLE_LOAD;
+        msg.flags = NetmonFlags::NEED_GLOBAL_WRITE_MUTEX;
+        tx_channel.send(msg).await.map_err(|e| {
+            log::debug!("failed {}", e);
+            anyhow!("failed {}", e)
+        })
+    }
+
+    pub async fn send_event_eval_maintenance(tx_channel: &NetMonTx) -> Result<()> {
+        let mut msg = NetmonMsg::new();
+        msg.event_id = EVENT_EVAL_MAINTENANCE;
+        msg.flags = NetmonFlags::NEED_GLOBAL_WRITE_MUTEX;
+        tx_channel.send(msg).await.map_err(|e| {
+            log::debug!("failed {}", e);
+            anyhow!("failed {}", e)
+        })
+    }
+
     // Message that the NetworkManager sends to itself.
     //
     // A "ReadLock" section send this message to a "WriteLock" section.
@@ -335,6 +662,7 @@ impl NetworkMonitor {
         port_idx: InputPortIdx,
         server_idx: TargetServerIdx,
         port_number: u16,
+        proxy_tls: bool,
     ) -> Result<()> {
         // TODO: Bug protection against health check flooding!?
         let mut msg = NetmonMsg::new();
@@ -342,6 +670,9 @@ impl NetworkMonitor {
         msg.port_idx = port_idx;
         msg.server_idx = server_idx;
         msg.para16[0] = port_number;
+        if proxy_tls {
+            msg.flags.insert(NetmonFlags::PROXY_TLS);
+        }
 
         // Send the message.
         tx_channel.send(msg).await.map_err(|e| {
@@ -356,7 +687,9 @@ impl NetworkMonitor {
         port_idx: InputPortIdx,
         server_idx: TargetServerIdx,
         port_number: u16,
+        proxy_tls: bool,
         now: EpochTimestamp,
+        interval: Duration,
         force: bool,
     ) {
         let mon_data = mon_map
@@ -364,13 +697,14 @@ impl NetworkMonitor {
             .or_insert(MonitorData::new());
 
         let ts = &mon_data.most_recent_latency_test_attempted;
-        if force || ts.is_none() || (now - ts.unwrap()) > Duration::from_secs(15) {
+        if force || ts.is_none() || (now - ts.unwrap()) > interval {
             // Let the request worker take care of this.
             let _ = NetworkMonitor::send_do_server_health_check(
                 request_worker_tx,
                 port_idx,
                 server_idx,
                 port_number,
+                proxy_tls,
             )
             .await;
 
@@ -378,6 +712,151 @@ impl NetworkMonitor {
         }
     }
 
+    // Scrape the "metrics" URL of a link, at most once per METRICS_SCRAPE_INTERVAL.
+    //
+    // Done in its own task, because the scraped server may be slow to respond.
+    //
+    // Sent with the LinkClient of the port (same connections as the proxied traffic).
+    fn process_metrics_scrape_request(
+        mon_map: &mut HashMap<(u8, u8), MonitorData>,
+        globals: &GlobalsProxyMT,
+        link_client: LinkClient,
+        port_idx: InputPortIdx,
+        server_idx: TargetServerIdx,
+        alias: String,
+        url: String,
+        now: EpochTimestamp,
+    ) {
+        let mon_data = mon_map
+            .entry((port_idx, server_idx))
+            .or_insert(MonitorData::new());
+
+        let ts = &mon_data.most_recent_metrics_scrape;
+        if ts.is_some() && (now - ts.unwrap()) < METRICS_SCRAPE_INTERVAL {
+            return;
+        }
+        mon_data.most_recent_metrics_scrape = Some(now);
+
+        let globals = globals.clone();
+        tokio::spawn(async move {
+            let sent_ms = unix_time_ms();
+            let scraped = match link_client
+                .get(&url)
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => resp.text().await.ok(),
+                _ => None,
+            };
+            let received_ms = unix_time_ms();
+            let (uptime_secs, checkpoint, checkpoint_ts_ms) = match scraped {
+                Some(text) => parse_node_metrics(&text),
+                None => {
+                    log::debug!("{} metrics scrape failed ({})", alias, url);
+                    (None, None, None)
+                }
+            };
+            // Same response re-used for the clock skew (no probe needed, see time_skew.rs).
+            let skew_ms =
+                checkpoint_ts_ms.map(|ts_ms| estimate_time_skew_ms(sent_ms, received_ms, ts_ms));
+
+            let mut globals_write_guard = globals.write().await;
+            let globals = &mut *globals_write_guard;
+            if let Some(input_port) = globals.input_ports.get_mut(port_idx) {
+                if let Some(target_server) = input_port.target_servers.get_mut(server_idx) {
+                    // The link could have been replaced while scraping.
+                    if target_server.alias() == alias {
+                        target_server
+                            .stats
+                            .set_node_metrics(uptime_secs, checkpoint);
+                        if let Some(skew_ms) = skew_ms {
+                            target_server
+                                .stats
+                                .set_time_skew_ms(skew_ms, EpochTimestamp::now());
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // Request the latest checkpoint of a healthy link, at most once per
+    // TIME_SKEW_PROBE_INTERVAL, to measure the skew of the local clock.
+    //
+    // Skipped while a more recent measurement exists (e.g. from the metrics scrape).
+    //
+    // Never affects the health of the link (a failure is only logged).
+    fn process_time_skew_probe_request(
+        mon_map: &mut HashMap<(u8, u8), MonitorData>,
+        globals: &GlobalsProxyMT,
+        link_client: LinkClient,
+        port_idx: InputPortIdx,
+        server_idx: TargetServerIdx,
+        target_server: &TargetServer,
+        now: EpochTimestamp,
+    ) {
+        let rpc = target_server.rpc();
+        if rpc.is_empty() || !target_server.stats.is_healthy() {
+            return;
+        }
+        if let Some(measured_at) = target_server.stats.time_skew_measured_at() {
+            if now.saturating_duration_since(measured_at) < TIME_SKEW_PROBE_INTERVAL {
+                return;
+            }
+        }
+
+        let mon_data = mon_map
+            .entry((port_idx, server_idx))
+            .or_insert(MonitorData::new());
+
+        let ts = &mon_data.most_recent_time_skew_probe;
+        if ts.is_some() && (now - ts.unwrap()) < TIME_SKEW_PROBE_INTERVAL {
+            return;
+        }
+        mon_data.most_recent_time_skew_probe = Some(now);
+
+        let globals = globals.clone();
+        let alias = target_server.alias();
+        tokio::spawn(async move {
+            let sent_ms = unix_time_ms();
+            let response = match link_client
+                .post(&rpc)
+                .header("Content-Type", "application/json")
+                .body(TIME_SKEW_PROBE_REQUEST_BODY)
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => resp.text().await.ok(),
+                _ => None,
+            };
+            let received_ms = unix_time_ms();
+            let response: Option<serde_json::Value> =
+                response.and_then(|text| serde_json::from_str(&text).ok());
+            let skew_ms = match response.as_ref().and_then(checkpoint_timestamp_ms) {
+                Some(ts_ms) => estimate_time_skew_ms(sent_ms, received_ms, ts_ms),
+                None => {
+                    log::debug!("{} time skew probe failed", alias);
+                    return;
+                }
+            };
+
+            let mut globals_write_guard = globals.write().await;
+            let globals = &mut *globals_write_guard;
+            if let Some(input_port) = globals.input_ports.get_mut(port_idx) {
+                if let Some(target_server) = input_port.target_servers.get_mut(server_idx) {
+                    // The link could have been replaced while probing.
+                    if target_server.alias() == alias {
+                        target_server
+                            .stats
+                            .set_time_skew_ms(skew_ms, EpochTimestamp::now());
+                    }
+                }
+            }
+        });
+    }
+
     async fn process_read_only_globals(
         &mut self,
         msg: NetmonMsg,
@@ -393,6 +872,7 @@ impl NetworkMonitor {
         }
 
         let now = EpochTimestamp::now();
+        let globals_mt = self.globals.clone();
 
         {
             let globals_read_guard = self.globals.read().await;
@@ -408,16 +888,57 @@ impl NetworkMonitor {
                                 // Iterate every target_servers.
                                 for (_, target_server) in input_port.target_servers.iter() {
                                     if let Some(server_idx) = target_server.idx() {
+                                        // A health check would only extend the throttling.
+                                        if target_server.stats.is_throttled(&now) {
+                                            continue;
+                                        }
+
+                                        let interval = if target_server.stats.is_in_maintenance() {
+                                            MAINTENANCE_HEALTH_CHECK_INTERVAL
+                                        } else {
+                                            HEALTH_CHECK_INTERVAL
+                                        };
+
+                                        // A new link starts its warm-up right away.
                                         Self::process_latency_report_attempt_request(
                                             &mut self.mon_map,
                                             request_worker_tx,
                                             port_idx,
                                             server_idx,
-                                            input_port.port_number(),
+                                            input_port.listening_port_number(),
+                                            input_port.is_proxy_tls(),
                                             now,
-                                            false,
+                                            interval,
+                                            target_server.stats.is_warmup_pending(),
                                         )
                                         .await;
+
+                                        if target_server.role() == LinkRole::Metrics {
+                                            if let Some(url) = &target_server.get_config().metrics {
+                                                Self::process_metrics_scrape_request(
+                                                    &mut self.mon_map,
+                                                    &globals_mt,
+                                                    input_port.link_client(),
+                                                    port_idx,
+                                                    server_idx,
+                                                    target_server.alias(),
+                                                    url.clone(),
+                                                    now,
+                                                );
+                                            }
+                                        }
+
+                                        if !target_server.stats.is_in_maintenance() {
+                                            Self::process_time_skew_probe_request(
+                                                &mut self.mon_map,
+                                                &globals_mt,
+                                                input_port.link_client(),
+                                                port_idx,
+                                                server_idx,
+                                                target_server,
+                                                now,
+                                            );
+                                        }
                                     }
                                 }
                             }
@@ -490,12 +1011,242 @@ impl NetworkMonitor {
         None
     }
 
+    // Returns the rule only when the message is for a JSON-RPC error response.
+    fn get_quota_error_rule(
+        input_ports: &ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+    ) -> Option<QuotaErrorRule> {
+        if !msg.flags.intersects(NetmonFlags::JSONRPC_ERROR_SET) {
+            return None;
+        }
+        input_ports
+            .get(msg.port_idx)
+            .map(|input_port| input_port.quota_error_rule().clone())
+    }
+
+    fn get_target_server_health(
+        input_ports: &ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+    ) -> Option<bool> {
+        let input_port = input_ports.get(msg.port_idx)?;
+        let target_server = input_port.target_servers.get(msg.server_idx)?;
+        Some(target_server.stats.is_healthy())
+    }
+
+    // Notify the webhooks when a link became healthy/unhealthy.
+    //
+    // Not while in a maintenance window, since a downtime is expected then (see
+    // eval_maintenance for the end of the window).
+    fn report_link_status_change(
+        webhook_tx: &WebhookTx,
+        input_ports: &ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+        was_healthy: bool,
+    ) {
+        let input_port = match input_ports.get(msg.port_idx) {
+            Some(input_port) => input_port,
+            None => return,
+        };
+        let target_server = match input_port.target_servers.get(msg.server_idx) {
+            Some(target_server) => target_server,
+            None => return,
+        };
+        let is_healthy = target_server.stats.is_healthy();
+        if is_healthy == was_healthy || target_server.stats.is_in_maintenance() {
+            return;
+        }
+        let status = |healthy: bool| if healthy { "OK" } else { "DOWN" };
+        let data = serde_json::json!({
+            "alias": target_server.alias(),
+            "status": status(is_healthy),
+            "previous_status": status(was_healthy),
+            "error_info": target_server.stats.error_info(),
+        });
+        webhook_tx.send_event(WebhookEvent::new(
+            WebhookEventType::LinkStatusChange,
+            input_port.workdir_name(),
+            data,
+        ));
+    }
+
+    // Count a proxied request in the monthly usage of its link (see LinkUsage).
+    //
+    // The month is the one of the request time (not of when the stats are applied).
+    fn record_link_usage(
+        webhook_tx: &WebhookTx,
+        input_ports: &mut ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+    ) {
+        let input_port = match input_ports.get_mut(msg.port_idx) {
+            Some(input_port) => input_port,
+            None => return,
+        };
+        let (alias, budget) = match input_port.target_servers.get(msg.server_idx) {
+            Some(target_server) => (
+                target_server.alias(),
+                target_server.get_config().monthly_budget,
+            ),
+            None => return,
+        };
+        let age = chrono::Duration::from_std(msg.timestamp.elapsed()).unwrap_or_default();
+        let month = usage_month(chrono::Utc::now() - age);
+        let pct = match input_port.link_usage_mut().record(&alias, &month, budget) {
+            Some(pct) => pct,
+            None => return,
+        };
+
+        let requests = input_port.link_usage().get(&alias, &month).requests;
+        let budget = budget.unwrap_or_default();
+        log::warn!(
+            "link {} at {}% of its monthly_budget ({}/{} in {})",
+            alias,
+            pct,
+            requests,
+            budget,
+            month
+        );
+        let data = serde_json::json!({
+            "alias": alias,
+            "month": month,
+            "threshold_pct": pct,
+            "requests": requests,
+            "monthly_budget": budget,
+        });
+        webhook_tx.send_event(WebhookEvent::new(
+            WebhookEventType::LinkBudgetAlert,
+            input_port.workdir_name(),
+            data,
+        ));
+    }
+
+    // Account for a health check result of a probing link (see LinkWarmUpRule).
+    //
+    // The checks of a burst are chained: the next one is requested 'interval_ms'
+    // after the result of the previous one.
+    fn handle_warmup_check(
+        netmon_tx: &NetMonTx,
+        input_ports: &mut ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+    ) {
+        let input_port = match input_ports.get_mut(msg.port_idx) {
+            Some(input_port) => input_port,
+            None => return,
+        };
+        let port_number = input_port.listening_port_number();
+        let proxy_tls = input_port.is_proxy_tls();
+        let target_server = match input_port.target_servers.get_mut(msg.server_idx) {
+            Some(target_server) => target_server,
+            None => return,
+        };
+        let interval = target_server.stats.warmup_interval().unwrap_or_default();
+        let success = target_server.stats.is_healthy();
+        let progress = match target_server.stats.handle_warmup_check(success) {
+            Some(progress) => progress,
+            None => return, // Not probing.
+        };
+        let alias = target_server.alias();
+        let error_info = target_server.stats.error_info();
+
+        match progress {
+            WarmUpProgress::Probing => {
+                let netmon_tx = netmon_tx.clone();
+                let (port_idx, server_idx) = (msg.port_idx, msg.server_idx);
+                tokio::spawn(async move {
+                    tokio::time::sleep(interval).await;
+                    let _ = NetworkMonitor::send_do_server_health_check(
+                        &netmon_tx,
+                        port_idx,
+                        server_idx,
+                        port_number,
+                        proxy_tls,
+                    )
+                    .await;
+                });
+            }
+            WarmUpProgress::Passed => {
+                log::info!(
+                    "{} link {} warm-up passed",
+                    input_port.workdir_name(),
+                    alias
+                );
+                input_port.update_selection_vectors();
+            }
+            WarmUpProgress::Failed => {
+                log::warn!(
+                    "{} link {} warm-up failed ({})",
+                    input_port.workdir_name(),
+                    alias,
+                    error_info
+                );
+            }
+        }
+    }
+
+    // Enter/leave the maintenance windows of every link (see MaintenanceWindow).
+    //
+    // A link still down at the end of its window is notified as such.
+    fn eval_maintenance(&self, input_ports: &mut ManagedVec<InputPort>) {
+        let now_secs = self.clock.now_secs();
+        for (_, input_port) in input_ports.iter_mut() {
+            let workdir = input_port.workdir_name().to_string();
+            let mut changed = false;
+            for (_, target_server) in input_port.target_servers.iter_mut() {
+                let until = target_server.maintenance_until(now_secs);
+                if !target_server.stats.set_maintenance_until(until) {
+                    continue;
+                }
+                changed = true;
+                if until.is_some() {
+                    log::info!(
+                        "{} link {} maintenance started",
+                        workdir,
+                        target_server.alias()
+                    );
+                    continue;
+                }
+                log::info!(
+                    "{} link {} maintenance ended",
+                    workdir,
+                    target_server.alias()
+                );
+                if !target_server.stats.is_healthy() {
+                    let data = serde_json::json!({
+                        "alias": target_server.alias(),
+                        "status": "DOWN",
+                        "previous_status": "MAINTENANCE",
+                        "error_info": target_server.stats.error_info(),
+                    });
+                    self.webhook_tx.send_event(WebhookEvent::new(
+                        WebhookEventType::LinkStatusChange,
+                        &workdir,
+                        data,
+                    ));
+                }
+            }
+            if changed {
+                input_port.update_selection_vectors();
+            }
+        }
+    }
+
     fn update_selection_vectors(input_ports: &mut ManagedVec<InputPort>, msg: &NetmonMsg) {
         if let Some(input_port) = input_ports.get_mut(msg.port_idx) {
             input_port.update_selection_vectors();
         }
     }
 
+    // Stats of a successful user request that are not applied right away (see
+    // STATS_FLUSH_INTERVAL). Anything else may change the selection of the servers.
+    fn is_deferrable(msg: &NetmonMsg) -> bool {
+        match msg.event_id {
+            EVENT_REPORT_TGT_REQ_RESP_OK => !msg.flags.intersects(
+                NetmonFlags::HEADER_SBSD_SERVER_HC_SET | NetmonFlags::JSONRPC_ERROR_SET,
+            ),
+            EVENT_REPORT_TGT_HEDGED => true,
+            _ => false,
+        }
+    }
+
     async fn process_mut_globals(&mut self, msg: NetmonMsg) -> Option<NetmonMsg> {
         // Process messages that requires WRITE access to the globals.
         //
@@ -506,198 +1257,338 @@ impl NetworkMonitor {
             return Some(msg);
         }
 
-        {
-            let mut globals_write_guard = self.globals.write().await;
-            let globals = &mut *globals_write_guard;
-            let input_ports = &mut globals.input_ports;
+        // Under load, most messages are the stats of successful requests. These are
+        // accumulated and applied all at once, so the ProxyServer tasks are not
+        // contending with a write lock on every request.
+        //
+        // The flush deadline is also checked here: under steady traffic a message is
+        // always received before the timeout of the event_loop expires.
+        if Self::is_deferrable(&msg) {
+            let now = Instant::now();
+            let since = *self.pending_stats_since.get_or_insert(now);
+            self.pending_stats.push(msg);
+            if self.pending_stats.len() < STATS_FLUSH_MAX && now < since + STATS_FLUSH_INTERVAL {
+                return None;
+            }
+            return self.apply_mut_globals(None).await;
+        }
 
-            let mut cur_msg = msg;
-            loop {
-                match cur_msg.event_id {
-                    EVENT_REPORT_TGT_REQ_RESP_OK => {
-                        // Update the stats. Consume the message.
-                        if cur_msg
-                            .flags
-                            .intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
-                        {
-                            // This is for the "controlled" latency test.
-                            if let Some(target_server) =
-                                NetworkMonitor::get_mut_target_server(input_ports, &cur_msg)
-                            {
-                                target_server
-                                    .stats
-                                    .handle_latency_report(cur_msg.timestamp, cur_msg.para32[1]);
-
-                                // Always update the selection_vectors on a good latency_report. This is
-                                // the periodic "audit" opportunity to refresh things up.
-                                Self::update_selection_vectors(input_ports, &cur_msg);
-                            }
-                        } else {
-                            // This is for the user traffic.
-                            if let Some(stats) = crate::NetworkMonitor::get_mut_all_servers_stats(
-                                input_ports,
-                                &cur_msg,
-                            ) {
-                                stats.handle_resp_ok(
-                                    cur_msg.timestamp,
-                                    cur_msg.para8[0],
-                                    cur_msg.para32[0],
-                                    cur_msg.para32[1],
-                                );
-                            }
+        self.apply_mut_globals(Some(msg)).await
+    }
 
-                            if let Some(target_server) =
-                                NetworkMonitor::get_mut_target_server(input_ports, &cur_msg)
-                            {
-                                target_server.stats.handle_resp_ok(
-                                    cur_msg.timestamp,
-                                    cur_msg.para8[0],
-                                    cur_msg.para32[0],
-                                    cur_msg.para32[1],
-                                );
-                            }
+    // Apply the pending stats (in order received) and then 'msg' along with the
+    // consecutive messages also requiring WRITE access, all under one write lock.
+    async fn apply_mut_globals(&mut self, msg: Option<NetmonMsg>) -> Option<NetmonMsg> {
+        let globals = self.globals.clone();
+        let mut globals_write_guard = globals.write().await;
+        let globals = &mut *globals_write_guard;
+        let input_ports = &mut globals.input_ports;
+
+        self.pending_stats_since = None;
+        for pending_msg in std::mem::take(&mut self.pending_stats) {
+            self.apply_mut_msg(input_ports, &pending_msg);
+        }
+
+        let mut cur_msg = msg?;
+        loop {
+            self.apply_mut_msg(input_ports, &cur_msg);
+
+            // Check if more messages are available.
+            match self.netmon_rx.try_recv() {
+                Ok(next_msg) => {
+                    cur_msg = next_msg;
+                }
+                Err(_e) => {
+                    // No more messages.
+                    return None;
+                }
+            }
+
+            if !cur_msg
+                .flags
+                .intersects(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX)
+            {
+                // Does not requires a global mutex.
+                // Do not consume that message here.
+                return Some(cur_msg);
+            }
+        }
+    }
+
+    fn apply_mut_msg(&mut self, input_ports: &mut ManagedVec<InputPort>, msg: &NetmonMsg) {
+        // To detect a link status change (None when not for a specific server).
+        let was_healthy = Self::get_target_server_health(input_ports, msg);
+
+        match msg.event_id {
+            EVENT_REPORT_TGT_REQ_RESP_OK => {
+                // Update the stats. Consume the message.
+                if msg.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET) {
+                    // This is for the "controlled" latency test.
+                    let quota_error_rule = Self::get_quota_error_rule(input_ports, msg);
+                    if let Some(target_server) =
+                        NetworkMonitor::get_mut_target_server(input_ports, msg)
+                    {
+                        target_server.stats.record_http_status(msg.para16[0]);
+
+                        // A quota-type (or server-fault) error response is not a valid
+                        // latency report.
+                        let is_quota_error = match &quota_error_rule {
+                            Some(rule) => target_server.stats.handle_jsonrpc_error(
+                                msg.timestamp,
+                                msg.para_i32[0],
+                                JsonRpcErrorCategory::from_u8(msg.para8[1]),
+                                rule,
+                            ),
+                            None => false,
+                        };
+                        if !is_quota_error {
+                            target_server
+                                .stats
+                                .handle_latency_report(msg.timestamp, msg.para32[1]);
                         }
+
+                        // Always update the selection_vectors on a good latency_report. This is
+                        // the periodic "audit" opportunity to refresh things up.
+                        Self::update_selection_vectors(input_ports, msg);
+                    }
+                } else {
+                    // This is for the user traffic.
+                    let quota_error_rule = Self::get_quota_error_rule(input_ports, msg);
+                    let jsonrpc_error = quota_error_rule.as_ref().map(|rule| {
+                        (
+                            msg.para_i32[0],
+                            JsonRpcErrorCategory::from_u8(msg.para8[1]),
+                            rule,
+                        )
+                    });
+
+                    if let Some(stats) =
+                        crate::NetworkMonitor::get_mut_all_servers_stats(input_ports, msg)
+                    {
+                        stats.record_http_status(msg.para16[0]);
+                        stats.handle_resp_ok(
+                            msg.timestamp,
+                            msg.para8[0],
+                            msg.para32[0],
+                            msg.para32[1],
+                            jsonrpc_error,
+                        );
                     }
-                    EVENT_REPORT_TGT_REQ_RESP_ERR => {
-                        // Update the stats.
-                        if cur_msg
-                            .flags
-                            .intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
-                        {
-                            if let Some(target_server) =
-                                NetworkMonitor::get_mut_target_server(input_ports, &cur_msg)
-                            {
-                                let was_healthy = target_server.stats.is_healthy();
-
-                                // This is for the "controlled" latency test.
-                                // We do not want that failure to mix with the user
-                                // traffic stats so call report_req_failed_internal
-                                // instead.
-                                target_server.stats.handle_req_failed_internal(
-                                    cur_msg.timestamp,
-                                    cur_msg.para8[1],
-                                );
-
-                                // A bad latency report on a healthy target_server could affect
-                                // the selection of the target server.
-                                if was_healthy {
-                                    Self::update_selection_vectors(input_ports, &cur_msg);
-                                }
-                            }
-                        } else {
-                            // An error in the response for the user traffic.
-                            if let Some(stats) = crate::NetworkMonitor::get_mut_all_servers_stats(
-                                input_ports,
-                                &cur_msg,
-                            ) {
-                                stats.handle_resp_err(
-                                    cur_msg.timestamp,
-                                    cur_msg.para8[0],
-                                    cur_msg.para32[0],
-                                    cur_msg.para32[1],
-                                    cur_msg.para8[1],
-                                );
-                            }
 
-                            if let Some(target_server) =
-                                NetworkMonitor::get_mut_target_server(input_ports, &cur_msg)
-                            {
-                                target_server.stats.handle_resp_err(
-                                    cur_msg.timestamp,
-                                    cur_msg.para8[0],
-                                    cur_msg.para32[0],
-                                    cur_msg.para32[1],
-                                    cur_msg.para8[1],
-                                );
-                                // User traffic should not select that target again.
-                                // So always refresh the selection_vectors on every user
-                                // traffic error.
-                                Self::update_selection_vectors(input_ports, &cur_msg);
-                            }
+                    if let Some(target_server) =
+                        NetworkMonitor::get_mut_target_server(input_ports, msg)
+                    {
+                        let was_healthy = target_server.stats.is_healthy();
+                        target_server.stats.record_http_status(msg.para16[0]);
+                        target_server.stats.handle_resp_ok(
+                            msg.timestamp,
+                            msg.para8[0],
+                            msg.para32[0],
+                            msg.para32[1],
+                            jsonrpc_error,
+                        );
+                        // Shift the selection away when degraded by quota-type or
+                        // server-fault errors.
+                        if was_healthy && !target_server.stats.is_healthy() {
+                            Self::update_selection_vectors(input_ports, msg);
                         }
                     }
-                    EVENT_REPORT_TGT_SEND_FAILED => {
-                        // An error just sending a request.
-                        if let Some(target_server) =
-                            NetworkMonitor::get_mut_target_server(input_ports, &cur_msg)
-                        {
-                            let was_healthy = target_server.stats.is_healthy();
-
-                            target_server.stats.handle_send_failed(
-                                cur_msg.timestamp,
-                                cur_msg.para8[1],
-                                cur_msg.para16[0],
-                            );
-
-                            let update_selection_vectors = if cur_msg
-                                .flags
-                                .intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
-                            {
-                                was_healthy
-                            } else {
-                                true
-                            };
-
-                            if update_selection_vectors {
-                                Self::update_selection_vectors(input_ports, &cur_msg);
-                            }
+                    Self::record_link_usage(&self.webhook_tx, input_ports, msg);
+                }
+            }
+            EVENT_REPORT_TGT_REQ_RESP_ERR => {
+                // Update the stats.
+                if msg.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET) {
+                    if let Some(target_server) =
+                        NetworkMonitor::get_mut_target_server(input_ports, msg)
+                    {
+                        let was_healthy = target_server.stats.is_healthy();
+
+                        // This is for the "controlled" latency test.
+                        // We do not want that failure to mix with the user
+                        // traffic stats so call report_req_failed_internal
+                        // instead.
+                        target_server
+                            .stats
+                            .handle_req_failed_internal(msg.timestamp, msg.para8[1]);
+
+                        // A bad latency report on a healthy target_server could affect
+                        // the selection of the target server.
+                        if was_healthy {
+                            Self::update_selection_vectors(input_ports, msg);
                         }
                     }
-                    EVENT_REPORT_REQ_FAILED => {
-                        // Having no server available on startup is "normal". Ignore these for
-                        // first 15 seconds uptime of this task.
-                        if !(cur_msg.para8[1] == REQUEST_FAILED_NO_SERVER_AVAILABLE
-                            && self.init_time.elapsed() < Duration::from_secs(15))
-                        {
-                            // Update the stats. Not related to a specific target server
-                            // so update only the all_servers stats.
-                            if let Some(stats) = crate::NetworkMonitor::get_mut_all_servers_stats(
-                                input_ports,
-                                &cur_msg,
-                            ) {
-                                if cur_msg
-                                    .flags
-                                    .intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
-                                {
-                                    stats.handle_req_failed_internal(
-                                        cur_msg.timestamp,
-                                        cur_msg.para8[1],
-                                    );
-                                } else {
-                                    stats.handle_req_failed(cur_msg.timestamp, cur_msg.para8[1]);
-                                }
-                            }
-                        }
+                } else {
+                    // An error in the response for the user traffic.
+                    if let Some(stats) =
+                        crate::NetworkMonitor::get_mut_all_servers_stats(input_ports, msg)
+                    {
+                        stats.record_http_status(msg.para16[0]);
+                        stats.handle_resp_err(
+                            msg.timestamp,
+                            msg.para8[0],
+                            msg.para32[0],
+                            msg.para32[1],
+                            msg.para8[1],
+                        );
                     }
-                    _ => {
-                        log::error!(
-                            "process_mut_globals unexpected event id {}",
-                            cur_msg.event_id
+
+                    if let Some(target_server) =
+                        NetworkMonitor::get_mut_target_server(input_ports, msg)
+                    {
+                        target_server.stats.record_http_status(msg.para16[0]);
+                        target_server.stats.handle_resp_err(
+                            msg.timestamp,
+                            msg.para8[0],
+                            msg.para32[0],
+                            msg.para32[1],
+                            msg.para8[1],
                         );
-                        // Do nothing. Consume the bad message.
+                        // User traffic should not select that target again.
+                        // So always refresh the selection_vectors on every user
+                        // traffic error.
+                        Self::update_selection_vectors(input_ports, msg);
                     }
+                    // Answered by the provider, so likely billed.
+                    Self::record_link_usage(&self.webhook_tx, input_ports, msg);
                 }
+            }
+            EVENT_REPORT_TGT_SEND_FAILED => {
+                // An error just sending a request.
+                if let Some(target_server) = NetworkMonitor::get_mut_target_server(input_ports, msg)
+                {
+                    let was_healthy = target_server.stats.is_healthy();
 
-                // Check if more messages are available.
-                match self.netmon_rx.try_recv() {
-                    Ok(next_msg) => {
-                        cur_msg = next_msg;
+                    target_server.stats.handle_send_failed(
+                        msg.timestamp,
+                        msg.para8[1],
+                        msg.para16[0],
+                    );
+
+                    let update_selection_vectors =
+                        if msg.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET) {
+                            was_healthy
+                        } else {
+                            true
+                        };
+
+                    if update_selection_vectors {
+                        Self::update_selection_vectors(input_ports, msg);
                     }
-                    Err(_e) => {
-                        // No more messages.
-                        return None;
+                }
+            }
+            EVENT_REPORT_REQ_FAILED => {
+                // Having no server available on startup is "normal". Ignore these for
+                // first 15 seconds uptime of this task.
+                if !(msg.para8[1] == REQUEST_FAILED_NO_SERVER_AVAILABLE
+                    && self.init_time.elapsed() < Duration::from_secs(15))
+                {
+                    // Update the stats. Not related to a specific target server
+                    // so update only the all_servers stats.
+                    if let Some(stats) =
+                        crate::NetworkMonitor::get_mut_all_servers_stats(input_ports, msg)
+                    {
+                        if msg.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET) {
+                            stats.handle_req_failed_internal(msg.timestamp, msg.para8[1]);
+                        } else {
+                            stats.handle_req_failed(msg.timestamp, msg.para8[1]);
+                        }
                     }
                 }
 
-                if !cur_msg
-                    .flags
-                    .intersects(NetmonFlags::NEED_GLOBAL_WRITE_MUTEX)
+                // Failure caused by an HTTP response from a specific server (e.g. 4xx).
+                if msg.para16[0] != 0 {
+                    if let Some(target_server) =
+                        NetworkMonitor::get_mut_target_server(input_ports, msg)
+                    {
+                        target_server.stats.record_http_status(msg.para16[0]);
+                    }
+                }
+            }
+            EVENT_REPORT_TGT_THROTTLED => {
+                // The selection skips the link until the end of the window (see
+                // InputPort::get_best_target_servers), so the selection_vectors
+                // are left as is.
+                if let Some(target_server) = NetworkMonitor::get_mut_target_server(input_ports, msg)
                 {
-                    // Does not requires a global mutex.
-                    // Do not consume that message here.
-                    return Some(cur_msg);
+                    if msg.para16[0] != 0 {
+                        target_server.stats.record_http_status(msg.para16[0]);
+                    }
+                    let now = EpochTimestamp::now();
+                    let duration = Duration::from_millis(msg.para32[0] as u64);
+                    // Concurrent requests often report the same throttling.
+                    if !target_server.stats.is_throttled(&now) {
+                        log::info!(
+                            "link {} throttled for {:?}",
+                            target_server.alias(),
+                            duration
+                        );
+                    }
+                    target_server.stats.handle_throttled(now + duration);
                 }
             }
+            EVENT_REPORT_TGT_HEDGED => {
+                if let Some(target_server) = NetworkMonitor::get_mut_target_server(input_ports, msg)
+                {
+                    target_server.stats.handle_hedged(msg.para8[0] != 0);
+                }
+            }
+            EVENT_SAMPLE_LOAD => {
+                // Sample all servers at the same time, so the rates are
+                // consistent across links.
+                let now = EpochTimestamp::now();
+                for (_, input_port) in input_ports.iter_mut() {
+                    let port_idx = match input_port.idx() {
+                        Some(port_idx) => port_idx,
+                        None => continue,
+                    };
+                    for (_, target_server) in input_port.target_servers.iter_mut() {
+                        if let Some(server_idx) = target_server.idx() {
+                            let mon_data = self
+                                .mon_map
+                                .entry((port_idx, server_idx))
+                                .or_insert(MonitorData::new());
+                            let (qps, qpm) = mon_data
+                                .load_sampler
+                                .sample(now, target_server.stats.request_count());
+                            target_server.stats.set_load(qps, qpm);
+                            target_server
+                                .stats
+                                .set_day_count(mon_data.load_sampler.day_count());
+                        }
+                    }
+                    // The rate limits headroom changed with the load.
+                    input_port.update_selection_weights();
+                    input_port.update_links_status();
+                }
+            }
+            EVENT_EVAL_MAINTENANCE => {
+                self.eval_maintenance(input_ports);
+            }
+            _ => {
+                log::error!("process_mut_globals unexpected event id {}", msg.event_id);
+                // Do nothing. Consume the bad message.
+            }
+        }
+
+        if msg.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
+            && matches!(
+                msg.event_id,
+                EVENT_REPORT_TGT_REQ_RESP_OK
+                    | EVENT_REPORT_TGT_REQ_RESP_ERR
+                    | EVENT_REPORT_TGT_SEND_FAILED
+            )
+        {
+            Self::handle_warmup_check(&self.netmon_tx, input_ports, msg);
+        }
+
+        if let Some(was_healthy) = was_healthy {
+            Self::report_link_status_change(&self.webhook_tx, input_ports, msg, was_healthy);
+        }
+
+        // So the multi-link status change is timestamped when it happens (see getLinks).
+        if let Some(input_port) = input_ports.get_mut(msg.port_idx) {
+            input_port.update_links_status();
         }
     }
 
@@ -730,6 +1621,7 @@ impl NetworkMonitor {
                             cur_msg.port_idx,
                             cur_msg.server_idx,
                             cur_msg.para16[0],
+                            cur_msg.is_proxy_tls(),
                             EpochTimestamp::now(),
                             true,
                         )
@@ -767,8 +1659,20 @@ impl NetworkMonitor {
 
         while !subsys.is_shutdown_requested() {
             if cur_msg.is_none() {
-                // Wait for a message.
-                cur_msg = self.netmon_rx.recv().await;
+                // Wait for a message (or until the pending stats are due).
+                cur_msg = match self.pending_stats_since {
+                    Some(since) => {
+                        let deadline = since + STATS_FLUSH_INTERVAL;
+                        match tokio::time::timeout_at(deadline, self.netmon_rx.recv()).await {
+                            Ok(msg) => msg,
+                            Err(_elapsed) => {
+                                self.apply_mut_globals(None).await;
+                                continue;
+                            }
+                        }
+                    }
+                    None => self.netmon_rx.recv().await,
+                };
                 if cur_msg.is_none() || subsys.is_shutdown_requested() {
                     // Channel closed or shutdown requested.
                     return;
@@ -823,3 +1727,235 @@ impl NetworkMonitor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node_metrics() {
+        let text = "# HELP uptime uptime of the node service in seconds\n\
+                    # TYPE uptime counter\n\
+                    uptime{chain_identifier=\"4c78adac\",version=\"1.30.1\"} 3600\n\
+                    highest_known_checkpoint 1200\n\
+                    highest_synced_checkpoint 1187 1700000000000\n";
+        assert_eq!(parse_node_metrics(text), (Some(3600), Some(1187), None));
+
+        assert_eq!(parse_node_metrics("uptime 12.5\n"), (Some(12), None, None));
+        assert_eq!(
+            parse_node_metrics("uptime NaN\nbogus\n"),
+            (None, None, None)
+        );
+        assert_eq!(parse_node_metrics(""), (None, None, None));
+
+        // Re-used for the clock skew.
+        let text = "last_executed_checkpoint_timestamp_ms 1700000000000\n";
+        assert_eq!(
+            parse_node_metrics(text),
+            (None, None, Some(1_700_000_000_000))
+        );
+    }
+
+    #[test]
+    fn test_load_sampler() {
+        let mut sampler = LoadSampler::new();
+        let t0 = EpochTimestamp::now();
+        assert_eq!(sampler.sample(t0, 100), (0.0, 0.0));
+
+        // 10 requests per second, sampled every 5 seconds.
+        let mut count = 100;
+        let mut now = t0;
+        for _ in 0..24 {
+            now += Duration::from_secs(5);
+            count += 50;
+            let (qps, qpm) = sampler.sample(now, count);
+            assert_eq!(qps, 10.0);
+            assert_eq!(qpm, 600.0);
+        }
+        // Older samples are dropped.
+        assert_eq!(sampler.samples.len(), 13);
+
+        // Burst in the most recent interval only.
+        now += Duration::from_secs(5);
+        count += 500;
+        let (qps, qpm) = sampler.sample(now, count);
+        assert_eq!(qps, 100.0);
+        assert_eq!(qpm, 1050.0);
+
+        // Stats cleared.
+        now += Duration::from_secs(5);
+        assert_eq!(sampler.sample(now, 3), (0.0, 0.0));
+        assert_eq!(sampler.samples.len(), 1);
+    }
+
+    #[test]
+    fn test_load_sampler_day_window() {
+        let clock = MockClock::new();
+        let mut sampler = LoadSampler::new_with_clock(SharedClock::new(clock.clone()));
+        assert_eq!(sampler.day_count(), 0);
+
+        // Move to the start of a day, so the samples below are all in that day.
+        let secs_in_day = clock.now_secs() % LOAD_DAY_SECS;
+        clock.advance(Duration::from_secs(LOAD_DAY_SECS - secs_in_day));
+
+        let mut now = EpochTimestamp::now();
+        sampler.sample(now, 100);
+        assert_eq!(sampler.day_count(), 100);
+        for count in [150, 400, 1000] {
+            now += Duration::from_secs(5);
+            clock.advance(Duration::from_secs(5));
+            sampler.sample(now, count);
+        }
+        assert_eq!(sampler.day_count(), 1000);
+
+        // Stats cleared, the requests of the day are still counted.
+        clock.advance(Duration::from_secs(3600));
+        sampler.sample(now, 20);
+        assert_eq!(sampler.day_count(), 1020);
+
+        // The quota is refilled at 00:00 UTC, even before the next sample.
+        clock.advance(Duration::from_secs(LOAD_DAY_SECS));
+        assert_eq!(sampler.day_count(), 0);
+        sampler.sample(now, 50);
+        assert_eq!(sampler.day_count(), 30);
+    }
+
+    fn new_test_monitor() -> (NetworkMonitor, GlobalsProxyMT, TargetServerIdx) {
+        use crate::shared_types::{GlobalsProxyST, Link, WebhookStats, WorkdirUserConfig};
+        use std::sync::Arc;
+
+        let config = WorkdirUserConfig::new();
+        let mut input_port = InputPort::new(0, "localnet".to_string(), &config);
+        let link = Link::new("a".to_string(), "http://127.0.0.1:1".to_string());
+        assert!(input_port.upsert_target_server(&link));
+        input_port.update_selection_vectors();
+        let server_idx = input_port.target_servers.iter().next().unwrap().0;
+        let mut globals_st = GlobalsProxyST::new();
+        globals_st.input_ports.push(input_port).unwrap();
+        let globals: GlobalsProxyMT = Arc::new(tokio::sync::RwLock::new(globals_st));
+
+        let (netmon_tx, netmon_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let (webhook_tx, _webhook_rx) = WebhookTx::channel(Arc::new(WebhookStats::new()));
+        let netmon = NetworkMonitor::new(globals.clone(), netmon_rx, netmon_tx, webhook_tx);
+        (netmon, globals, server_idx)
+    }
+
+    // Same burst of user traffic reports, mostly successful requests.
+    fn burst(t0: EpochTimestamp, server_idx: TargetServerIdx) -> Vec<NetmonMsg> {
+        (0..1000u32)
+            .map(|i| {
+                let mut msg = NetmonMsg::new();
+                msg.flags = NetmonFlags::NEED_GLOBAL_WRITE_MUTEX;
+                msg.port_idx = 0;
+                msg.server_idx = server_idx;
+                msg.timestamp = t0 + Duration::from_micros(i as u64 + 1);
+                if i % 100 == 50 {
+                    msg.event_id = EVENT_REPORT_TGT_SEND_FAILED;
+                    msg.para8[1] = SEND_FAILED_RESP_HTTP_STATUS;
+                    msg.para16[0] = 503;
+                } else if i % 10 == 5 {
+                    msg.event_id = EVENT_REPORT_TGT_REQ_RESP_ERR;
+                    msg.para8[1] = REQUEST_FAILED_BAD_REQUEST_HTTP;
+                    msg.para16[0] = 400;
+                } else {
+                    msg.event_id = EVENT_REPORT_TGT_REQ_RESP_OK;
+                    msg.para8[0] = (i % 7 == 0) as u8; // Some success on retry.
+                    msg.para16[0] = 200;
+                }
+                msg
+            })
+            .collect()
+    }
+
+    // (request, success, bad_request, 2xx, 4xx, 5xx, is_healthy) of the link and of
+    // all the servers.
+    async fn totals(globals: &GlobalsProxyMT) -> Vec<(u64, u64, u64, u64, u64, u64, bool)> {
+        let globals_guard = globals.read().await;
+        let input_port = globals_guard.input_ports.get(0).unwrap();
+        let (_, target_server) = input_port.target_servers.iter().next().unwrap();
+        [&target_server.stats, &input_port.all_servers_stats]
+            .iter()
+            .map(|stats| {
+                let (mut n_request, mut n_success) = (0, 0);
+                stats.get_accum_stats(&mut n_request, &mut n_success);
+                let (mut network_down, mut bad_request, mut overload) = (0, 0, 0);
+                let (mut client_errors, mut others) = (0, 0);
+                stats.get_classified_failure(
+                    &mut network_down,
+                    &mut bad_request,
+                    &mut overload,
+                    &mut client_errors,
+                    &mut others,
+                );
+                (
+                    n_request,
+                    n_success,
+                    bad_request,
+                    stats.http_status_class_count(2),
+                    stats.http_status_class_count(4),
+                    stats.http_status_class_count(5),
+                    stats.is_healthy(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deferred_stats_totals() {
+        // Reference: every report applied as received.
+        let (mut netmon, globals, server_idx) = new_test_monitor();
+        let t0 = EpochTimestamp::now();
+        for msg in burst(t0, server_idx) {
+            assert!(netmon.apply_mut_globals(Some(msg)).await.is_none());
+        }
+        let expected = totals(&globals).await;
+        assert_eq!(expected[0], (990, 890, 100, 890, 100, 10, true));
+
+        // The successful requests are deferred until the next report that may
+        // change the selection (and then applied in order).
+        let (mut netmon, globals, server_idx) = new_test_monitor();
+        let mut msgs = burst(t0, server_idx).into_iter();
+        for msg in msgs.by_ref().take(5) {
+            assert!(netmon.process_mut_globals(msg).await.is_none());
+        }
+        assert_eq!(netmon.pending_stats.len(), 5);
+        assert_eq!(totals(&globals).await[0].0, 0);
+        for msg in msgs {
+            assert!(netmon.process_mut_globals(msg).await.is_none());
+        }
+        assert!(!netmon.pending_stats.is_empty());
+        assert_ne!(totals(&globals).await, expected);
+
+        // Applied on the tick.
+        netmon.apply_mut_globals(None).await;
+        assert!(netmon.pending_stats.is_empty());
+        assert!(netmon.pending_stats_since.is_none());
+        assert_eq!(totals(&globals).await, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deferred_stats_flushed_under_steady_traffic() {
+        // Only successful requests, one every 10ms: the event_loop timeout never
+        // expires, the deadline is checked on every report.
+        let (mut netmon, globals, server_idx) = new_test_monitor();
+        let t0 = EpochTimestamp::now();
+        let mut msgs = burst(t0, server_idx)
+            .into_iter()
+            .filter(NetworkMonitor::is_deferrable);
+        for msg in msgs.by_ref().take(5) {
+            assert!(netmon.process_mut_globals(msg).await.is_none());
+            tokio::time::advance(Duration::from_millis(10)).await;
+        }
+        assert_eq!(netmon.pending_stats.len(), 5);
+        assert_eq!(totals(&globals).await[0].0, 0);
+
+        // The report received past STATS_FLUSH_INTERVAL applies all of them.
+        assert!(netmon
+            .process_mut_globals(msgs.next().unwrap())
+            .await
+            .is_none());
+        assert!(netmon.pending_stats.is_empty());
+        assert!(netmon.pending_stats_since.is_none());
+        assert_eq!(totals(&globals).await[0].0, 6);
+    }
+}
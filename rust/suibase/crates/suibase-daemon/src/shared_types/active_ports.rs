@@ -0,0 +1,217 @@
+// Ports actually used by this daemon (may differ from the suibase.yaml config).
+//
+// Several users can run their own suibase on the same host, so the default ports
+// may already be taken by another daemon. When a configured port is in use, the
+// next free port within 'port_fallback_range' is used instead (unless the user
+// set 'strict_ports: true').
+//
+// The ports in use are written to ~/suibase/workdirs/common/active-ports.yaml for
+// the CLI scripts and the Helper:
+//
+//   api_port: 44399
+//   explorer_port: 44380
+//   proxy_ports:
+//     localnet: 44341
+//     testnet: 44342
+//
+// The same ports are also in daemon-endpoints.json, as URLs (see DaemonEndpoints).
+//
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{DaemonEndpoints, Globals, WorkdirUserConfig, DAEMON_ENDPOINTS_FILENAME};
+
+pub const ACTIVE_PORTS_FILENAME: &str = "active-ports.yaml";
+
+pub const DEFAULT_PORT_FALLBACK_RANGE: u16 = 10;
+
+// Webserver of the sui-explorer (see 'sui_explorer_port' in the common suibase.yaml).
+pub const DEFAULT_SUI_EXPLORER_PORT: u16 = 44380;
+
+// Probe done before this daemon binds the port, so a port "in use" is always
+// owned by another process (e.g. the daemon of another user).
+pub fn is_port_free(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok()
+}
+
+// The common suibase.yaml, for the settings shared by all the servers of this
+// daemon (e.g. strict_ports). Optional, so the defaults when not found.
+pub async fn load_common_config(globals: &Globals) -> WorkdirUserConfig {
+    let common_path = {
+        let workdirs_guard = globals.workdirs.read().await;
+        workdirs_guard.suibase_yaml_common().to_path_buf()
+    };
+    let mut common_config = WorkdirUserConfig::new();
+    if common_path.exists() {
+        if let Err(e) =
+            common_config.load_and_merge_from_common_file(&common_path.to_string_lossy())
+        {
+            log::error!("{}", e);
+        }
+    }
+    common_config
+}
+
+// Returns the configured port when free, otherwise the first free port among the
+// next 'range' ports. The error message is user facing (see getLinks).
+pub fn choose_port(
+    configured: u16,
+    range: u16,
+    strict: bool,
+    is_free: impl Fn(u16) -> bool,
+) -> Result<u16, String> {
+    if is_free(configured) {
+        return Ok(configured);
+    }
+    if strict {
+        return Err(format!("port {} already in use (strict_ports)", configured));
+    }
+    (1..=range)
+        .filter_map(|offset| configured.checked_add(offset))
+        .find(|port| is_free(*port))
+        .ok_or_else(|| {
+            format!(
+                "port {} already in use (no free port up to +{})",
+                configured, range
+            )
+        })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivePorts {
+    pub api_port: Option<u16>,
+    pub explorer_port: Option<u16>,
+    pub proxy_ports: BTreeMap<String, u16>, // Key is the workdir name.
+    pub proxy_tls: BTreeSet<String>,        // Workdirs with a HTTPS proxy (not in the yaml).
+}
+
+impl ActivePorts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Snapshot of the ports currently in use.
+    pub async fn from_globals(globals: &Globals) -> Self {
+        let mut active_ports = ActivePorts::new();
+        {
+            let config_guard = globals.config.read().await;
+            active_ports.api_port = config_guard.daemon_port_active;
+            active_ports.explorer_port = config_guard.explorer_port_active;
+        }
+        {
+            let proxy_guard = globals.proxy.read().await;
+            for (_, input_port) in proxy_guard.input_ports.iter() {
+                if let Some(port) = input_port.actual_port_number() {
+                    let workdir = input_port.workdir_name().to_string();
+                    if input_port.is_proxy_tls() {
+                        active_ports.proxy_tls.insert(workdir.clone());
+                    }
+                    active_ports.proxy_ports.insert(workdir, port);
+                }
+            }
+        }
+        active_ports
+    }
+
+    pub fn to_yaml(&self) -> String {
+        let mut yaml = String::from("# Generated by the suibase-daemon. Do not edit.\n");
+        if let Some(api_port) = self.api_port {
+            yaml.push_str(&format!("api_port: {}\n", api_port));
+        }
+        if let Some(explorer_port) = self.explorer_port {
+            yaml.push_str(&format!("explorer_port: {}\n", explorer_port));
+        }
+        if !self.proxy_ports.is_empty() {
+            yaml.push_str("proxy_ports:\n");
+            for (workdir, port) in &self.proxy_ports {
+                yaml.push_str(&format!("  {}: {}\n", workdir, port));
+            }
+        }
+        yaml
+    }
+
+    // Atomic write (temp file + rename), so a reader never sees a partial file.
+    pub fn write(&self, common_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(common_path)?;
+        let path = common_path.join(ACTIVE_PORTS_FILENAME);
+        let tmp_path = common_path.join(format!("{}.tmp", ACTIVE_PORTS_FILENAME));
+        std::fs::write(&tmp_path, self.to_yaml())?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    // Write the current snapshot in ~/suibase/workdirs/common (also as
+    // daemon-endpoints.json).
+    pub async fn save(globals: &Globals) {
+        let active_ports = Self::from_globals(globals).await;
+        let common_path = {
+            let workdirs_guard = globals.workdirs.read().await;
+            workdirs_guard.path().join("common")
+        };
+        let daemon_ip = globals.config.read().await.daemon_ip.clone();
+        if let Err(e) = active_ports.write(&common_path) {
+            log::error!("failed to write {}: {}", ACTIVE_PORTS_FILENAME, e);
+        }
+        let mut endpoints = DaemonEndpoints::from_active_ports(&active_ports, &daemon_ip);
+        if let Err(e) = endpoints.write(&common_path) {
+            log::error!("failed to write {}: {}", DAEMON_ENDPOINTS_FILENAME, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_port() {
+        let in_use = [44340u16, 44341, 44342];
+        let is_free = |port: u16| !in_use.contains(&port);
+
+        assert_eq!(choose_port(44343, 10, false, is_free), Ok(44343));
+        assert_eq!(choose_port(44340, 10, false, is_free), Ok(44343));
+        assert!(choose_port(44340, 10, true, is_free)
+            .unwrap_err()
+            .contains("strict_ports"));
+        assert!(choose_port(44340, 2, false, is_free).is_err());
+        assert!(choose_port(u16::MAX, 10, false, |_| false).is_err());
+    }
+
+    #[test]
+    fn test_is_port_free() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!is_port_free(port));
+        let chosen = choose_port(port, 10, false, is_port_free).unwrap();
+        assert_ne!(chosen, port);
+        drop(listener);
+    }
+
+    #[test]
+    fn test_write_active_ports() {
+        let dir = std::env::temp_dir().join(format!("sbsd-active-ports-{}", std::process::id()));
+        let mut active_ports = ActivePorts::new();
+        active_ports.api_port = Some(44399);
+        active_ports.explorer_port = Some(44381);
+        active_ports
+            .proxy_ports
+            .insert("testnet".to_string(), 44342);
+        active_ports
+            .proxy_ports
+            .insert("localnet".to_string(), 44341);
+        active_ports.write(&dir).unwrap();
+
+        let contents = std::fs::read_to_string(dir.join(ACTIVE_PORTS_FILENAME)).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&contents).unwrap();
+        assert_eq!(yaml["api_port"].as_u64(), Some(44399));
+        assert_eq!(yaml["explorer_port"].as_u64(), Some(44381));
+        assert_eq!(yaml["proxy_ports"]["localnet"].as_u64(), Some(44341));
+        assert_eq!(yaml["proxy_ports"]["testnet"].as_u64(), Some(44342));
+        assert!(!dir.join("active-ports.yaml.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,103 @@
+// The active workdir (asui) selection, done natively by the daemon (see setActiveWorkdir).
+//
+// Same state as the "<workdir> set-active" script command:
+//
+//   workdirs/active -> workdirs/{workdir}  (absolute symlink)
+//
+// The script replaces the symlink with "ln -nsf". Here a temporary symlink is renamed
+// over it instead, so a concurrent reader never finds the symlink missing.
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+pub const ACTIVE_WORKDIR_SYMLINK: &str = "active";
+
+// Same check as is_workdir_ok in __globals.sh (the "create" was done, the workdir is
+// not necessarily started).
+pub fn is_workdir_initialized(workdir_path: &Path) -> bool {
+    workdir_path.is_dir()
+        && workdir_path.join("sui-exec").is_file()
+        && workdir_path.join("workdir-exec").is_file()
+        && workdir_path
+            .join("config")
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+// Name of the workdir the symlink points to (None when there is no symlink).
+pub fn read_active_workdir(workdirs_path: &Path) -> Option<String> {
+    let target = std::fs::read_link(workdirs_path.join(ACTIVE_WORKDIR_SYMLINK)).ok()?;
+    let name = target.file_name()?.to_string_lossy().to_string();
+    Some(name)
+}
+
+// Point the symlink to the workdir. Returns false when it was already active.
+//
+// Not serialized here. The callers hold the asui selection lock (see Globals).
+pub fn write_active_workdir(workdirs_path: &Path, workdir: &str) -> Result<bool> {
+    let workdir_path = workdirs_path.join(workdir);
+    if !is_workdir_initialized(&workdir_path) {
+        bail!("{} workdir not initialized", workdir);
+    }
+    if read_active_workdir(workdirs_path).as_deref() == Some(workdir) {
+        return Ok(false);
+    }
+
+    let symlink_path = workdirs_path.join(ACTIVE_WORKDIR_SYMLINK);
+    let tmp_path = workdirs_path.join(format!(".{}.tmp", ACTIVE_WORKDIR_SYMLINK));
+    let _ = std::fs::remove_file(&tmp_path);
+    std::os::unix::fs::symlink(&workdir_path, &tmp_path)?;
+    if let Err(e) = std::fs::rename(&tmp_path, &symlink_path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(true)
+}
+
+// Minimal workdir, as left by "<workdir> create" (test fixture).
+#[cfg(test)]
+pub fn create_initialized_workdir(workdirs_path: &Path, workdir: &str) {
+    let workdir_path = workdirs_path.join(workdir);
+    std::fs::create_dir_all(workdir_path.join("config-default")).unwrap();
+    std::fs::write(workdir_path.join("sui-exec"), "").unwrap();
+    std::fs::write(workdir_path.join("workdir-exec"), "").unwrap();
+    std::os::unix::fs::symlink(
+        workdir_path.join("config-default"),
+        workdir_path.join("config"),
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_active_workdir() {
+        let dir = std::env::temp_dir().join(format!("sbsd-active-workdir-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        create_initialized_workdir(&dir, "localnet");
+        create_initialized_workdir(&dir, "testnet");
+        std::fs::create_dir_all(dir.join("devnet")).unwrap();
+
+        assert_eq!(read_active_workdir(&dir), None);
+        assert!(write_active_workdir(&dir, "localnet").unwrap());
+        assert_eq!(read_active_workdir(&dir).as_deref(), Some("localnet"));
+        assert!(!write_active_workdir(&dir, "localnet").unwrap());
+
+        assert!(write_active_workdir(&dir, "testnet").unwrap());
+        assert_eq!(read_active_workdir(&dir).as_deref(), Some("testnet"));
+        assert_eq!(
+            std::fs::read_link(dir.join(ACTIVE_WORKDIR_SYMLINK)).unwrap(),
+            dir.join("testnet")
+        );
+
+        // Not initialized (or missing) leaves the selection as-is.
+        assert!(write_active_workdir(&dir, "devnet").is_err());
+        assert!(write_active_workdir(&dir, "mainnet").is_err());
+        assert_eq!(read_active_workdir(&dir).as_deref(), Some("testnet"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
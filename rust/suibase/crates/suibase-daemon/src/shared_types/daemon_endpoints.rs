@@ -0,0 +1,202 @@
+// Discovery file of every endpoint this daemon listens on, for the external tools
+// (and the Helper) instead of guessing from suibase.yaml and the port fallbacks.
+//
+// Written to ~/suibase/workdirs/common/daemon-endpoints.json along with
+// active-ports.yaml (see ActivePorts::save), so whenever a binding changes:
+//
+//   {
+//     "version": 1,
+//     "pid": 1234,
+//     "generation": 7,
+//     "updated_at": "2024-05-01T12:00:00+00:00",
+//     "stale": false,
+//     "endpoints": [
+//       { "purpose": "api", "url": "http://localhost:44399", "port": 44399 },
+//       { "purpose": "explorer", "url": "http://localhost:44380", "port": 44380 },
+//       { "purpose": "proxy", "workdir": "localnet",
+//         "url": "http://localhost:44340", "port": 44340 }
+//     ]
+//   }
+//
+// 'generation' is incremented on every write (also across daemon restarts), so a tool
+// can detect a change without comparing the endpoints.
+//
+// On a graceful shutdown the file is kept, but with "stale": true. After a crash, the
+// readers detect that 'pid' is no longer running.
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{ActivePorts, Globals};
+
+pub const DAEMON_ENDPOINTS_FILENAME: &str = "daemon-endpoints.json";
+
+// Increment on any incompatible change of the file.
+pub const DAEMON_ENDPOINTS_VERSION: u32 = 1;
+
+pub const ENDPOINT_PURPOSE_API: &str = "api";
+pub const ENDPOINT_PURPOSE_EXPLORER: &str = "explorer";
+pub const ENDPOINT_PURPOSE_PROXY: &str = "proxy";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonEndpoint {
+    pub purpose: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub workdir: Option<String>, // None for the daemon-wide endpoints.
+    pub url: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonEndpoints {
+    pub version: u32,
+    pub pid: u32,
+    pub generation: u64,
+    pub updated_at: String, // RFC 3339
+    pub stale: bool,
+    pub endpoints: Vec<DaemonEndpoint>,
+}
+
+impl DaemonEndpoints {
+    pub fn from_active_ports(active_ports: &ActivePorts, daemon_ip: &str) -> Self {
+        let endpoint =
+            |purpose: &str, workdir: Option<&String>, scheme: &str, port: u16| DaemonEndpoint {
+                purpose: purpose.to_string(),
+                workdir: workdir.cloned(),
+                url: format!("{}://{}:{}", scheme, daemon_ip, port),
+                port,
+            };
+        let mut endpoints = Vec::new();
+        if let Some(port) = active_ports.api_port {
+            endpoints.push(endpoint(ENDPOINT_PURPOSE_API, None, "http", port));
+        }
+        if let Some(port) = active_ports.explorer_port {
+            endpoints.push(endpoint(ENDPOINT_PURPOSE_EXPLORER, None, "http", port));
+        }
+        for (workdir, port) in &active_ports.proxy_ports {
+            let scheme = if active_ports.proxy_tls.contains(workdir) {
+                "https"
+            } else {
+                "http"
+            };
+            endpoints.push(endpoint(
+                ENDPOINT_PURPOSE_PROXY,
+                Some(workdir),
+                scheme,
+                *port,
+            ));
+        }
+        Self {
+            version: DAEMON_ENDPOINTS_VERSION,
+            pid: std::process::id(),
+            generation: 0,
+            updated_at: Utc::now().to_rfc3339(),
+            stale: false,
+            endpoints,
+        }
+    }
+
+    // None when there is no file, or it is not usable.
+    pub fn load(common_path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(common_path.join(DAEMON_ENDPOINTS_FILENAME)).ok()?;
+        serde_json::from_str::<Self>(&contents)
+            .ok()
+            .filter(|endpoints| endpoints.version == DAEMON_ENDPOINTS_VERSION)
+    }
+
+    // Atomic write (temp file + rename), with the generation following the one of the
+    // previous file.
+    pub fn write(&mut self, common_path: &Path) -> Result<()> {
+        let previous_generation = Self::load(common_path).map_or(0, |previous| previous.generation);
+        self.generation = previous_generation + 1;
+        std::fs::create_dir_all(common_path)?;
+        let path = common_path.join(DAEMON_ENDPOINTS_FILENAME);
+        let tmp_path = common_path.join(format!("{}.tmp", DAEMON_ENDPOINTS_FILENAME));
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    // Done on a graceful shutdown. A file written by another daemon process is left as-is.
+    pub fn mark_stale(common_path: &Path) -> Result<()> {
+        match Self::load(common_path) {
+            Some(mut endpoints) if endpoints.pid == std::process::id() && !endpoints.stale => {
+                endpoints.stale = true;
+                endpoints.updated_at = Utc::now().to_rfc3339();
+                endpoints.write(common_path)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn save_stale(globals: &Globals) {
+        let common_path = {
+            let workdirs_guard = globals.workdirs.read().await;
+            workdirs_guard.path().join("common")
+        };
+        if let Err(e) = Self::mark_stale(&common_path) {
+            log::error!("failed to update {}: {}", DAEMON_ENDPOINTS_FILENAME, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daemon_endpoints_file() {
+        let dir = std::env::temp_dir().join(format!("sbsd-endpoints-{}", std::process::id()));
+        let mut active_ports = ActivePorts::new();
+        active_ports.api_port = Some(44399);
+        active_ports
+            .proxy_ports
+            .insert("localnet".to_string(), 44341);
+        active_ports
+            .proxy_ports
+            .insert("testnet".to_string(), 44342);
+        active_ports.proxy_tls.insert("testnet".to_string());
+
+        let mut endpoints = DaemonEndpoints::from_active_ports(&active_ports, "localhost");
+        endpoints.write(&dir).unwrap();
+        let loaded = DaemonEndpoints::load(&dir).unwrap();
+        assert_eq!(loaded.generation, 1);
+        assert_eq!(loaded.pid, std::process::id());
+        assert!(!loaded.stale);
+        let urls: Vec<(Option<&str>, &str)> = loaded
+            .endpoints
+            .iter()
+            .map(|endpoint| (endpoint.workdir.as_deref(), endpoint.url.as_str()))
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                (None, "http://localhost:44399"),
+                (Some("localnet"), "http://localhost:44341"),
+                (Some("testnet"), "https://localhost:44342"),
+            ]
+        );
+        assert!(!dir.join("daemon-endpoints.json.tmp").exists());
+
+        // Incremented on every change, and once more when marked stale.
+        active_ports.explorer_port = Some(44380);
+        let mut endpoints = DaemonEndpoints::from_active_ports(&active_ports, "localhost");
+        endpoints.write(&dir).unwrap();
+        assert_eq!(DaemonEndpoints::load(&dir).unwrap().generation, 2);
+        DaemonEndpoints::mark_stale(&dir).unwrap();
+        let loaded = DaemonEndpoints::load(&dir).unwrap();
+        assert!(loaded.stale);
+        assert_eq!(loaded.generation, 3);
+        assert_eq!(loaded.endpoints.len(), 4);
+
+        // Another schema version is not used.
+        let contents = std::fs::read_to_string(dir.join(DAEMON_ENDPOINTS_FILENAME)).unwrap();
+        let contents = contents.replace("\"version\": 1", "\"version\": 2");
+        std::fs::write(dir.join(DAEMON_ENDPOINTS_FILENAME), contents).unwrap();
+        assert!(DaemonEndpoints::load(&dir).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,230 @@
+// At-least-once delivery of the Sui events of the tracked packages (see getWorkdirPackages).
+//
+// The websocket subscription alone loses the events emitted while disconnected (and the
+// ones dropped by the server). The EventsWriterWorker closes these gaps with a backfill:
+//   - after every (re)subscription of a package and periodically on audit, the events
+//     more recent than the high-water mark are queried with suix_queryEvents.
+//   - the query is most recent first, until reaching the high-water mark (or the
+//     configured window). The events are then inserted oldest first.
+//   - everything goes through the same dedup than the subscription (EventsDedup), so an
+//     event is written once no matter how many times it is received.
+//
+// The high-water mark is advanced only by a completed backfill (never by the
+// subscription), so a gap can't be skipped by a more recent event being received.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use common::basic_types::WorkdirIdx;
+use serde_json::Value as JsonValue;
+
+use crate::api::PackageEventsSync;
+
+// Default of "events_backfill_window_secs" (suibase.yaml). 0 disables the backfill.
+pub const DEFAULT_EVENTS_BACKFILL_WINDOW_SECS: u64 = 3600;
+
+// Periodic backfill (on audit) even when no reconnection was detected.
+pub const EVENTS_BACKFILL_INTERVAL_SECS: u64 = 60;
+
+// suix_queryEvents paging. The window normally stops the query way before the max.
+pub const EVENTS_BACKFILL_PAGE_SIZE: u64 = 50;
+pub const EVENTS_BACKFILL_MAX_PAGES: usize = 100;
+
+// Most recent event IDs remembered per workdir for the dedup.
+pub const EVENTS_DEDUP_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EventId {
+    pub tx_digest: String,
+    pub event_seq: u64,
+}
+
+impl EventId {
+    // The "id" of an event, e.g. {"txDigest": "3Vua...ChrL", "eventSeq": "1"}.
+    pub fn from_event(event: &JsonValue) -> Option<Self> {
+        let id = event.get("id")?;
+        let tx_digest = id.get("txDigest")?.as_str()?.to_string();
+        let event_seq = match id.get("eventSeq")? {
+            JsonValue::String(value) => value.parse::<u64>().ok()?,
+            value => value.as_u64()?,
+        };
+        Some(Self {
+            tx_digest,
+            event_seq,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventsHighWaterMark {
+    // Latest checkpoint when the backfill started. All the events of the package up to
+    // this checkpoint were written (within the window).
+    pub checkpoint: u64,
+    // Most recent event written. None until the package emits one.
+    pub event_id: Option<EventId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackfillScan {
+    Continue,             // Every event of the page is more recent, query the next page.
+    ReachedHighWaterMark, // The gap is closed.
+    ReachedWindow,        // Older events are outside events_backfill_window_secs.
+    ReachedFirstEvent,    // No more page (the package has no older events).
+}
+
+// Process one page of suix_queryEvents (most recent first).
+//
+// Appends to 'events' the ones more recent than the high-water mark and within the
+// window ('oldest_ms' is the UNIX time in milliseconds of the window start).
+pub fn scan_backfill_page(
+    page: &[JsonValue],
+    has_next_page: bool,
+    high_water_mark: Option<&EventId>,
+    oldest_ms: u64,
+    events: &mut Vec<JsonValue>,
+) -> BackfillScan {
+    for event in page {
+        if high_water_mark.is_some() && EventId::from_event(event).as_ref() == high_water_mark {
+            return BackfillScan::ReachedHighWaterMark;
+        }
+        let timestamp_ms = event["timestampMs"]
+            .as_str()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(0);
+        if timestamp_ms < oldest_ms {
+            return BackfillScan::ReachedWindow;
+        }
+        events.push(event.clone());
+    }
+    if has_next_page {
+        BackfillScan::Continue
+    } else {
+        BackfillScan::ReachedFirstEvent
+    }
+}
+
+// Bounded set of the most recent event IDs written.
+#[derive(Debug, Default)]
+pub struct EventsDedup {
+    ids: HashSet<EventId>,
+    order: VecDeque<EventId>, // Oldest first, to forget when full.
+    capacity: usize,
+}
+
+impl EventsDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // Returns false when already seen (the event should not be written again).
+    pub fn insert(&mut self, event_id: &EventId) -> bool {
+        if self.ids.contains(event_id) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.ids.insert(event_id.clone());
+        self.order.push_back(event_id.clone());
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GlobalsEventsSyncST {
+    // From suibase.yaml (set by the AdminController).
+    backfill_window_secs: HashMap<WorkdirIdx, u64>,
+    // Published by each EventsWriterWorker on audit.
+    packages: HashMap<WorkdirIdx, Vec<PackageEventsSync>>,
+}
+
+impl GlobalsEventsSyncST {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn backfill_window_secs(&self, workdir_idx: WorkdirIdx) -> u64 {
+        self.backfill_window_secs
+            .get(&workdir_idx)
+            .copied()
+            .unwrap_or(DEFAULT_EVENTS_BACKFILL_WINDOW_SECS)
+    }
+
+    pub fn set_backfill_window_secs(&mut self, workdir_idx: WorkdirIdx, window_secs: u64) {
+        self.backfill_window_secs.insert(workdir_idx, window_secs);
+    }
+
+    pub fn set_packages(&mut self, workdir_idx: WorkdirIdx, packages: Vec<PackageEventsSync>) {
+        self.packages.insert(workdir_idx, packages);
+    }
+
+    pub fn get_packages(&self, workdir_idx: WorkdirIdx) -> Vec<PackageEventsSync> {
+        self.packages.get(&workdir_idx).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(tx_digest: &str, timestamp_ms: u64) -> JsonValue {
+        json!({
+            "id": { "txDigest": tx_digest, "eventSeq": "0" },
+            "type": "0x2a::counter::Changed",
+            "timestampMs": timestamp_ms.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_backfill_scan() {
+        let id = EventId::from_event(&event("tx2", 1000)).unwrap();
+        assert_eq!(id.tx_digest, "tx2");
+        assert_eq!(id.event_seq, 0);
+        assert_eq!(
+            EventId::from_event(&json!({ "id": { "txDigest": "x" } })),
+            None
+        );
+
+        // Most recent first, stops at the high-water mark.
+        let page = vec![event("tx4", 4000), event("tx3", 3000), event("tx2", 2000)];
+        let mut events = Vec::new();
+        let scan = scan_backfill_page(&page, true, Some(&id), 0, &mut events);
+        assert_eq!(scan, BackfillScan::ReachedHighWaterMark);
+        assert_eq!(events.len(), 2);
+
+        // Within the window, more pages.
+        let mut events = Vec::new();
+        let scan = scan_backfill_page(&page[..2], true, Some(&id), 0, &mut events);
+        assert_eq!(scan, BackfillScan::Continue);
+        let scan = scan_backfill_page(&[], false, Some(&id), 0, &mut events);
+        assert_eq!(scan, BackfillScan::ReachedFirstEvent);
+        assert_eq!(events.len(), 2);
+
+        // Truncated by the window.
+        let mut events = Vec::new();
+        let scan = scan_backfill_page(&page, true, None, 3000, &mut events);
+        assert_eq!(scan, BackfillScan::ReachedWindow);
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn test_events_dedup() {
+        let mut dedup = EventsDedup::new(2);
+        let ids: Vec<EventId> = ["a", "b", "c"]
+            .iter()
+            .map(|tx| EventId::from_event(&event(tx, 1)).unwrap())
+            .collect();
+        assert!(dedup.insert(&ids[0]));
+        assert!(!dedup.insert(&ids[0]));
+        assert!(dedup.insert(&ids[1]));
+        assert!(dedup.insert(&ids[2]));
+        // The oldest was forgotten.
+        assert!(dedup.insert(&ids[0]));
+        assert!(!dedup.insert(&ids[2]));
+    }
+}
@@ -0,0 +1,271 @@
+// Gas coins (SUI) inventory of an address.
+//
+// Coins are retrieved with suix_getCoins through the workdir proxy server (same
+// multi-link failover as any other client request).
+//
+// The daemon holds no key, so the merging of coins is done by the workdir sui
+// client (see mergeGasCoins). This module only decides what to merge and
+// parses the results.
+use anyhow::{anyhow, bail, Result};
+
+use crate::api::{GasCoinBucket, GasCoinInfo, GasInventoryResponse};
+
+pub const MIST_PER_SUI: u64 = 1_000_000_000;
+
+pub const GAS_INVENTORY_CACHE_DURATION: std::time::Duration = std::time::Duration::from_secs(10);
+
+// mergeGasCoins limits. Each transaction reduces the coin count by up to (max_coins_per_tx - 1).
+pub const MERGE_DEFAULT_COINS_PER_TX: u32 = 100;
+pub const MERGE_MAX_COINS_PER_TX: u32 = 500;
+pub const MERGE_MAX_TXS: usize = 20;
+pub const MERGE_GAS_BUDGET: u64 = MIST_PER_SUI / 20;
+
+// Suggest merging when an address has at least that many coins.
+const MERGE_SUGGESTED_COIN_COUNT: usize = 20;
+
+// suix_getCoins page size, and a protection against a runaway pagination.
+const COINS_PAGE_LIMIT: u32 = 50;
+const COINS_MAX_PAGES: u32 = 200;
+
+// Exclusive upper bound (in MIST) of every histogram bucket, except the last one.
+const HISTOGRAM_BUCKETS: [(u64, &str); 5] = [
+    (MIST_PER_SUI / 100, "<0.01"),
+    (MIST_PER_SUI / 10, "0.01-0.1"),
+    (MIST_PER_SUI, "0.1-1"),
+    (10 * MIST_PER_SUI, "1-10"),
+    (100 * MIST_PER_SUI, "10-100"),
+];
+const HISTOGRAM_LAST_BUCKET: &str = ">=100";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasCoin {
+    pub object_id: String,
+    pub balance: u64,
+}
+
+// Sui addresses and object IDs are "0x" followed by up to 64 hex digits.
+//
+// Also prevents shell injection, since these are used as CLI arguments.
+pub fn is_valid_sui_id(id: &str) -> bool {
+    match id.strip_prefix("0x") {
+        Some(hex) => {
+            !hex.is_empty() && hex.len() <= 64 && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+// Extract the address from the output of "sui client active-address".
+pub fn parse_active_address(cmd_response: &str) -> Option<String> {
+    let cmd = common::utils::remove_ascii_color_code(cmd_response);
+    cmd.lines()
+        .rev()
+        .map(|line| line.trim())
+        .find(|line| is_valid_sui_id(line))
+        .map(|line| line.to_string())
+}
+
+pub fn build_gas_inventory(address: &str, coins: &[GasCoin]) -> GasInventoryResponse {
+    let mut resp = GasInventoryResponse::new();
+    resp.address = address.to_string();
+    resp.coin_count = coins.len() as u64;
+
+    let total: u128 = coins.iter().map(|coin| coin.balance as u128).sum();
+    resp.total_balance = total.to_string();
+
+    resp.largest_coin = coins
+        .iter()
+        .max_by_key(|coin| coin.balance)
+        .map(|coin| GasCoinInfo {
+            coin_object_id: coin.object_id.clone(),
+            balance: coin.balance.to_string(),
+        });
+
+    let mut counts = [0u64; HISTOGRAM_BUCKETS.len() + 1];
+    for coin in coins {
+        let idx = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|(upper, _)| coin.balance < *upper)
+            .unwrap_or(HISTOGRAM_BUCKETS.len());
+        counts[idx] += 1;
+    }
+    let labels = HISTOGRAM_BUCKETS
+        .iter()
+        .map(|(_, label)| *label)
+        .chain(std::iter::once(HISTOGRAM_LAST_BUCKET));
+    resp.histogram = labels
+        .zip(counts)
+        .map(|(label, count)| GasCoinBucket {
+            label: label.to_string(),
+            count,
+        })
+        .collect();
+
+    if coins.len() >= MERGE_SUGGESTED_COIN_COUNT {
+        resp.merge_suggested = true;
+        resp.suggestion = Some(format!(
+            "{} coins. Merging them with mergeGasCoins will simplify gas selection",
+            coins.len()
+        ));
+    }
+    resp
+}
+
+// Coins to merge in the next transaction.
+//
+// Largest coins first, so the first one (used for gas) can always pay for it and
+// the result of a previous merge keeps accumulating the dust.
+pub fn next_merge_batch(coins: &[GasCoin], max_coins_per_tx: usize) -> Vec<String> {
+    if coins.len() < 2 || max_coins_per_tx < 2 {
+        return Vec::new();
+    }
+    let mut sorted: Vec<&GasCoin> = coins.iter().collect();
+    sorted.sort_by(|a, b| b.balance.cmp(&a.balance));
+    sorted
+        .into_iter()
+        .take(max_coins_per_tx)
+        .map(|coin| coin.object_id.clone())
+        .collect()
+}
+
+// Extract the transaction digest from the output of a sui client "--json" call.
+//
+// Error if the transaction was not successful.
+pub fn parse_tx_digest(cmd_response: &str) -> Result<String> {
+    // Warnings (stderr) may precede the JSON output.
+    let json_start = cmd_response
+        .find("\n{")
+        .map(|pos| pos + 1)
+        .or_else(|| cmd_response.starts_with('{').then_some(0))
+        .ok_or_else(|| anyhow!("no JSON in [{}]", cmd_response))?;
+    let json: serde_json::Value = serde_json::from_str(&cmd_response[json_start..])?;
+
+    if let Some(status) = json["effects"]["status"]["status"].as_str() {
+        if status != "success" {
+            bail!(
+                "transaction failed: {}",
+                json["effects"]["status"]["error"]
+                    .as_str()
+                    .unwrap_or(status)
+            );
+        }
+    }
+    match json["digest"].as_str() {
+        Some(digest) => Ok(digest.to_string()),
+        None => bail!("missing digest in [{}]", cmd_response),
+    }
+}
+
+// Get all the SUI coins of an address, through the proxy server at 'proxy_url'.
+pub async fn fetch_gas_coins(
+    client: &reqwest::Client,
+    proxy_url: &str,
+    address: &str,
+) -> Result<Vec<GasCoin>> {
+    let mut coins = Vec::new();
+    let mut cursor = serde_json::Value::Null;
+
+    for _ in 0..COINS_MAX_PAGES {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "suix_getCoins",
+            "params": [address, "0x2::sui::SUI", cursor, COINS_PAGE_LIMIT],
+        });
+        let resp: serde_json::Value = client
+            .post(proxy_url)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(err) = resp.get("error") {
+            bail!("suix_getCoins error: {}", err);
+        }
+        let result = &resp["result"];
+        let data = result["data"]
+            .as_array()
+            .ok_or_else(|| anyhow!("suix_getCoins unexpected response: {}", resp))?;
+        for coin in data {
+            let object_id = coin["coinObjectId"].as_str().unwrap_or_default();
+            let balance = coin["balance"].as_str().and_then(|b| b.parse::<u64>().ok());
+            match balance {
+                Some(balance) if !object_id.is_empty() => coins.push(GasCoin {
+                    object_id: object_id.to_string(),
+                    balance,
+                }),
+                _ => bail!("suix_getCoins unexpected coin: {}", coin),
+            }
+        }
+
+        if !result["hasNextPage"].as_bool().unwrap_or(false) || result["nextCursor"].is_null() {
+            return Ok(coins);
+        }
+        cursor = result["nextCursor"].clone();
+    }
+    bail!(
+        "suix_getCoins more than {} coins",
+        COINS_MAX_PAGES * COINS_PAGE_LIMIT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coin(id: &str, balance: u64) -> GasCoin {
+        GasCoin {
+            object_id: id.to_string(),
+            balance,
+        }
+    }
+
+    #[test]
+    fn test_build_gas_inventory() {
+        let coins = vec![
+            coin("0x1", 1_000),            // <0.01
+            coin("0x2", MIST_PER_SUI / 2), // 0.1-1
+            coin("0x3", 200 * MIST_PER_SUI),
+            coin("0x4", 5_000_000),
+        ];
+        let resp = build_gas_inventory("0xa", &coins);
+        assert_eq!(resp.coin_count, 4);
+        assert_eq!(resp.total_balance, "200505001000");
+        assert_eq!(resp.largest_coin.unwrap().coin_object_id, "0x3");
+        let counts: Vec<u64> = resp.histogram.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![2, 0, 1, 0, 0, 1]);
+        assert_eq!(resp.histogram[5].label, ">=100");
+        assert!(!resp.merge_suggested);
+
+        let many: Vec<GasCoin> = (0..MERGE_SUGGESTED_COIN_COUNT)
+            .map(|i| coin(&format!("0x{:x}", i + 1), 1))
+            .collect();
+        assert!(build_gas_inventory("0xa", &many).merge_suggested);
+    }
+
+    #[test]
+    fn test_next_merge_batch() {
+        let coins = vec![coin("0x1", 5), coin("0x2", 50), coin("0x3", 1)];
+        assert_eq!(next_merge_batch(&coins, 2), vec!["0x2", "0x1"]);
+        assert_eq!(next_merge_batch(&coins, 10), vec!["0x2", "0x1", "0x3"]);
+        assert!(next_merge_batch(&coins[..1], 10).is_empty());
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert!(is_valid_sui_id("0x2"));
+        assert!(!is_valid_sui_id("0x"));
+        assert!(!is_valid_sui_id("0x12;rm"));
+
+        let addr = format!("0x{}", "ab".repeat(32));
+        let resp = format!("[warning] version mismatch\n{}\n", addr);
+        assert_eq!(parse_active_address(&resp), Some(addr));
+
+        let out = "[warning] something\n{\"digest\": \"ABC\", \"effects\": {\"status\": {\"status\": \"success\"}}}";
+        assert_eq!(parse_tx_digest(out).unwrap(), "ABC");
+        let out = "{\"digest\": \"ABC\", \"effects\": {\"status\": {\"status\": \"failure\", \"error\": \"InsufficientGas\"}}}";
+        assert!(parse_tx_digest(out).is_err());
+    }
+}
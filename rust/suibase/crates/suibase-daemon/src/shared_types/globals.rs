@@ -11,11 +11,18 @@
 // Note: This app also uses message passing between threads to minimize sharing. See NetmonMsg as an example.
 use std::sync::Arc;
 
-use crate::api::{Versioned, VersionsResponse, WorkdirPackagesResponse, WorkdirStatusResponse};
+use crate::api::{
+    GasInventoryResponse, Versioned, VersionsResponse, WorkdirPackagesResponse,
+    WorkdirStatusResponse,
+};
 use crate::shared_types::InputPort;
 use common::basic_types::{ManagedVec, WorkdirIdx};
+use common::shared_types::{GlobalsEventsDataST, WorkdirStatus};
 
-use super::{workdirs, GlobalsEventsDataST, GlobalsWorkdirsST};
+use super::{
+    workdirs, GlobalsEventsSyncST, GlobalsJobsST, GlobalsSubscriptionsST, GlobalsWorkdirsST,
+    StartupStats, WebhookStats, WorkerRestarts, DEFAULT_SUI_EXPLORER_PORT,
+};
 
 #[derive(Debug)]
 pub struct GlobalsProxyST {
@@ -38,6 +45,13 @@ impl GlobalsProxyST {
         }
         None
     }
+
+    pub fn find_input_port_by_name_mut(&mut self, workdir_name: &str) -> Option<&mut InputPort> {
+        self.input_ports
+            .iter_mut()
+            .map(|(_, input_port)| input_port)
+            .find(|input_port| input_port.workdir_name() == workdir_name)
+    }
 }
 
 impl Default for GlobalsProxyST {
@@ -52,11 +66,17 @@ pub struct GlobalsWorkdirStatusST {
     // as the response of the GetWorkdirStatus API. That way,
     // the UI queries can be served very quickly.
     pub ui: Option<Versioned<WorkdirStatusResponse>>,
+
+    // Overall status with its last transition (also reflected in ui).
+    pub status: WorkdirStatus,
 }
 
 impl GlobalsWorkdirStatusST {
     pub fn new() -> Self {
-        Self { ui: None }
+        Self {
+            ui: None,
+            status: WorkdirStatus::new(),
+        }
     }
 }
 
@@ -111,6 +131,8 @@ pub struct GlobalsAPIMutexST {
     pub last_get_workdir_status_time: tokio::time::Instant,
     pub last_get_workdir_packages_time: tokio::time::Instant,
     pub last_versions_response: Option<Versioned<VersionsResponse>>,
+    // Cached getGasInventory response, with the requested address (None for the active one).
+    pub last_gas_inventory: Option<(tokio::time::Instant, Option<String>, GasInventoryResponse)>,
 }
 
 impl GlobalsAPIMutexST {
@@ -119,6 +141,7 @@ impl GlobalsAPIMutexST {
             last_get_workdir_status_time: tokio::time::Instant::now(),
             last_get_workdir_packages_time: tokio::time::Instant::now(),
             last_versions_response: None,
+            last_gas_inventory: None,
         }
     }
 }
@@ -135,7 +158,17 @@ pub struct GlobalsConfigST {
     // are controlled by the user (suibase.yaml
     // files, workdir CLI operations).
     pub daemon_ip: String,
-    pub daemon_port: u16,
+    pub daemon_port: u16,                // Configured.
+    pub daemon_port_active: Option<u16>, // Set once the API server is listening (see ActivePorts).
+
+    // Webserver of the sui-explorer (see getExplorerInfo).
+    pub explorer_port: u16,                  // Configured.
+    pub explorer_port_active: Option<u16>,   // Set once listening.
+    pub explorer_port_error: Option<String>, // Why not listening, or not on the configured port.
+
+    // Telemetry report as it would be sent (see getTelemetryPreview). None while disabled.
+    pub telemetry_preview: Option<serde_json::Value>,
+    pub telemetry_endpoint: Option<String>,
 }
 
 impl GlobalsConfigST {
@@ -143,6 +176,12 @@ impl GlobalsConfigST {
         Self {
             daemon_ip: "localhost".to_string(),
             daemon_port: 44399,
+            daemon_port_active: None,
+            explorer_port: DEFAULT_SUI_EXPLORER_PORT,
+            explorer_port_active: None,
+            explorer_port_error: None,
+            telemetry_preview: None,
+            telemetry_endpoint: None,
         }
     }
 }
@@ -168,6 +207,9 @@ pub type GlobalsWorkdirPackagesMT = Arc<tokio::sync::RwLock<GlobalsWorkdirPackag
 pub type GlobalsEventsDataMT = Arc<tokio::sync::RwLock<GlobalsEventsDataST>>;
 pub type GlobalsWorkdirsMT = Arc<tokio::sync::RwLock<GlobalsWorkdirsST>>;
 pub type GlobalsAPIMutexMT = Arc<tokio::sync::Mutex<GlobalsAPIMutexST>>;
+pub type GlobalsJobsMT = Arc<tokio::sync::RwLock<GlobalsJobsST>>;
+pub type GlobalsSubscriptionsMT = Arc<tokio::sync::RwLock<GlobalsSubscriptionsST>>;
+pub type GlobalsEventsSyncMT = Arc<tokio::sync::RwLock<GlobalsEventsSyncST>>;
 
 // A convenient way to refer to all globals at once.
 //
@@ -216,6 +258,24 @@ pub struct Globals {
     pub api_mutex_testnet: GlobalsAPIMutexMT,
     pub api_mutex_mainnet: GlobalsAPIMutexMT,
 
+    // Webhooks delivery stats (updated by the WebhookWorker, lock-free).
+    pub webhook_stats: Arc<WebhookStats>,
+
+    // Durations of the startup phases (see getDaemonStats).
+    pub startup: Arc<StartupStats>,
+
+    // Restarts of the workers of each workdir (see getDaemonStats).
+    pub worker_restarts: Arc<WorkerRestarts>,
+
+    // Status of the long running API operations (see getJobStatus).
+    pub jobs: GlobalsJobsMT,
+
+    // Delivery metrics of the Sui event subscriptions (see getSubscriptions).
+    pub subscriptions: GlobalsSubscriptionsMT,
+
+    // High-water marks and backfill of the Sui events (see getWorkdirPackages).
+    pub events_sync: GlobalsEventsSyncMT,
+
     asui_selection: Arc<tokio::sync::Mutex<Option<String>>>,
 }
 
@@ -241,6 +301,12 @@ impl Globals {
             api_mutex_devnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
             api_mutex_testnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
             api_mutex_mainnet: Arc::new(tokio::sync::Mutex::new(GlobalsAPIMutexST::new())),
+            webhook_stats: Arc::new(WebhookStats::new()),
+            startup: Arc::new(StartupStats::new()),
+            worker_restarts: Arc::new(WorkerRestarts::new()),
+            jobs: Arc::new(tokio::sync::RwLock::new(GlobalsJobsST::new())),
+            subscriptions: Arc::new(tokio::sync::RwLock::new(GlobalsSubscriptionsST::new())),
+            events_sync: Arc::new(tokio::sync::RwLock::new(GlobalsEventsSyncST::new())),
             asui_selection: Arc::new(tokio::sync::Mutex::new(None)),
         }
     }
@@ -308,6 +374,12 @@ impl Globals {
         let mut selection = self.asui_selection.lock().await;
         *selection = new_value;
     }
+
+    // Held while switching the active workdir (see setActiveWorkdir), so concurrent
+    // switches are serialized and the selection matches the workdirs/active symlink.
+    pub async fn lock_asui_selection(&self) -> tokio::sync::MutexGuard<'_, Option<String>> {
+        self.asui_selection.lock().await
+    }
 }
 
 impl Default for Globals {
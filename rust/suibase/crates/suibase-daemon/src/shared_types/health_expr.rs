@@ -0,0 +1,390 @@
+// User-defined health rule of the links of a workdir (see "health_score" in suibase.yaml).
+//
+// A comparison of two arithmetic expressions over a fixed set of metrics. Example:
+//
+//     health_score: "latency_p90_ms + 20 * error_rate_pct < 800"
+//
+// A link working normally is reported "DEGRADED" instead of "OK" when the comparison
+// is false. The left side is the score of the link (reported by getLinks).
+//
+// Grammar (usual precedence, '*' and '/' before '+' and '-'):
+//
+//     rule    := sum ('<' | '<=' | '>' | '>=' | '==' | '!=') sum
+//     sum     := product (('+' | '-') product)*
+//     product := unary (('*' | '/') unary)*
+//     unary   := '-' unary | primary
+//     primary := number | metric | '(' sum ')'
+//
+// Hand-rolled on purpose: nothing but these metrics and operators can be evaluated.
+use super::ServerStats;
+use common::basic_types::EpochTimestamp;
+
+// Bounds an expression from the config (no deep recursion on a crafted input).
+const HEALTH_EXPR_MAX_LEN: usize = 256;
+const HEALTH_EXPR_MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthMetric {
+    LatencyP90Ms,
+    ErrorRatePct,
+    CheckpointLag,
+    RateLimitHeadroom,
+}
+
+impl HealthMetric {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "latency_p90_ms" => Some(Self::LatencyP90Ms),
+            "error_rate_pct" => Some(Self::ErrorRatePct),
+            "checkpoint_lag" => Some(Self::CheckpointLag),
+            "rate_limit_headroom" => Some(Self::RateLimitHeadroom),
+            _ => None,
+        }
+    }
+}
+
+// The metrics of a link, at the time of the evaluation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthMetrics {
+    pub latency_p90_ms: f64,      // Of the most recent responses. 0 until known.
+    pub error_rate_pct: f64,      // Failed user requests (since the stats were last cleared).
+    pub checkpoint_lag: f64,      // Behind the most synced link (role "metrics" only, else 0).
+    pub rate_limit_headroom: f64, // % of the max_per_day left. 0 while throttled, else 100.
+}
+
+impl HealthMetrics {
+    // 'highest_checkpoint' is the highest among the links of the workdir.
+    pub fn from_stats(
+        stats: &ServerStats,
+        max_per_day: Option<u32>,
+        highest_checkpoint: Option<u64>,
+        now: &EpochTimestamp,
+    ) -> Self {
+        let checkpoint_lag = match (stats.highest_synced_checkpoint(), highest_checkpoint) {
+            (Some(checkpoint), Some(highest)) => highest.saturating_sub(checkpoint) as f64,
+            _ => 0.0,
+        };
+
+        let rate_limit_headroom = if stats.is_throttled(now) {
+            0.0
+        } else {
+            match max_per_day {
+                Some(max) if max > 0 => {
+                    let left = (max as u64).saturating_sub(stats.day_count());
+                    left as f64 * 100.0 / max as f64
+                }
+                _ => 100.0,
+            }
+        };
+
+        Self {
+            latency_p90_ms: stats.latency_p90_ms().unwrap_or(0.0),
+            error_rate_pct: stats.error_rate_pct(),
+            checkpoint_lag,
+            rate_limit_headroom,
+        }
+    }
+
+    fn get(&self, metric: HealthMetric) -> f64 {
+        match metric {
+            HealthMetric::LatencyP90Ms => self.latency_p90_ms,
+            HealthMetric::ErrorRatePct => self.error_rate_pct,
+            HealthMetric::CheckpointLag => self.checkpoint_lag,
+            HealthMetric::RateLimitHeadroom => self.rate_limit_headroom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Metric(HealthMetric),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>), // '+', '-', '*' or '/'
+}
+
+impl Expr {
+    // A division by zero gives an infinite (or NaN) value, and any comparison with
+    // NaN is false (so DEGRADED).
+    fn eval(&self, metrics: &HealthMetrics) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Metric(metric) => metrics.get(*metric),
+            Expr::Neg(expr) => -expr.eval(metrics),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(metrics), rhs.eval(metrics));
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    _ => lhs / rhs,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char), // + - * / ( )
+    Cmp(CmpOp),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{}'", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else if "<>=!".contains(c) {
+            let next_is_eq = chars.get(i + 1) == Some(&'=');
+            let op = match (c, next_is_eq) {
+                ('<', false) => CmpOp::Lt,
+                ('<', true) => CmpOp::Le,
+                ('>', false) => CmpOp::Gt,
+                ('>', true) => CmpOp::Ge,
+                ('=', true) => CmpOp::Eq,
+                ('!', true) => CmpOp::Ne,
+                _ => return Err(format!("invalid operator '{}'", c)),
+            };
+            tokens.push(Token::Cmp(op));
+            i += if next_is_eq { 2 } else { 1 };
+        } else {
+            return Err(format!("invalid character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > HEALTH_EXPR_MAX_DEPTH {
+            return Err("expression too deeply nested".to_string());
+        }
+        let expr = match self.advance() {
+            Some(Token::Op('-')) => Expr::Neg(Box::new(self.unary()?)),
+            Some(Token::Number(value)) => Expr::Number(value),
+            Some(Token::Ident(name)) => match HealthMetric::parse(&name) {
+                Some(metric) => Expr::Metric(metric),
+                None => return Err(format!("unknown metric '{}'", name)),
+            },
+            Some(Token::Op('(')) => {
+                let expr = self.sum()?;
+                if self.advance() != Some(Token::Op(')')) {
+                    return Err("missing ')'".to_string());
+                }
+                expr
+            }
+            Some(token) => return Err(format!("unexpected {:?}", token)),
+            None => return Err("unexpected end of expression".to_string()),
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+}
+
+// Equal when the source is the same.
+#[derive(Debug, Clone)]
+pub struct HealthRule {
+    source: String,
+    lhs: Expr,
+    op: CmpOp,
+    rhs: Expr,
+}
+
+impl HealthRule {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        if source.len() > HEALTH_EXPR_MAX_LEN {
+            return Err(format!("longer than {} characters", HEALTH_EXPR_MAX_LEN));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            depth: 0,
+        };
+        let lhs = parser.sum()?;
+        let op = match parser.advance() {
+            Some(Token::Cmp(op)) => op,
+            Some(token) => return Err(format!("unexpected {:?}", token)),
+            None => return Err("missing comparison to a threshold (e.g. '< 500')".to_string()),
+        };
+        let rhs = parser.sum()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {:?}", token));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            lhs,
+            op,
+            rhs,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // The left side of the comparison.
+    pub fn score(&self, metrics: &HealthMetrics) -> f64 {
+        self.lhs.eval(metrics)
+    }
+
+    pub fn is_ok(&self, metrics: &HealthMetrics) -> bool {
+        let (score, threshold) = (self.score(metrics), self.rhs.eval(metrics));
+        match self.op {
+            CmpOp::Lt => score < threshold,
+            CmpOp::Le => score <= threshold,
+            CmpOp::Gt => score > threshold,
+            CmpOp::Ge => score >= threshold,
+            CmpOp::Eq => score == threshold,
+            CmpOp::Ne => score != threshold,
+        }
+    }
+}
+
+impl PartialEq for HealthRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for HealthRule {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(latency_p90_ms: f64, error_rate_pct: f64) -> HealthMetrics {
+        HealthMetrics {
+            latency_p90_ms,
+            error_rate_pct,
+            checkpoint_lag: 0.0,
+            rate_limit_headroom: 100.0,
+        }
+    }
+
+    fn score(source: &str) -> f64 {
+        HealthRule::parse(source)
+            .unwrap()
+            .score(&metrics(300.0, 2.0))
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(score("1 + 2 * 3 < 0"), 7.0);
+        assert_eq!(score("(1 + 2) * 3 < 0"), 9.0);
+        assert_eq!(score("10 - 4 - 3 < 0"), 3.0);
+        assert_eq!(score("24 / 4 / 2 < 0"), 3.0);
+        assert_eq!(score("-2 * -3 < 0"), 6.0);
+        assert_eq!(score("latency_p90_ms + 10 * error_rate_pct < 0"), 320.0);
+        assert_eq!(score("latency_p90_ms / (error_rate_pct - 1.5) < 0"), 600.0);
+        assert!(score("1 / 0 < 0").is_infinite());
+    }
+
+    #[test]
+    fn test_comparison() {
+        let rule = HealthRule::parse("latency_p90_ms + 10 * error_rate_pct < 500").unwrap();
+        assert!(rule.is_ok(&metrics(300.0, 2.0)));
+        assert!(!rule.is_ok(&metrics(450.0, 6.0)));
+
+        let rule = HealthRule::parse("rate_limit_headroom >= 10").unwrap();
+        assert!(rule.is_ok(&metrics(0.0, 0.0)));
+        assert!(HealthRule::parse("checkpoint_lag == 0")
+            .unwrap()
+            .is_ok(&metrics(0.0, 0.0)));
+        assert!(!HealthRule::parse("0 / 0 <= 1")
+            .unwrap()
+            .is_ok(&metrics(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for source in [
+            "",
+            "latency_p90_ms",                // No threshold.
+            "latency_p50_ms < 100",          // Unknown metric.
+            "std::process::exit(1) < 1",     // Not an identifier.
+            "latency_p90_ms < 100 < 200",    // Only one comparison.
+            "(latency_p90_ms < 100",         // Unbalanced.
+            "latency_p90_ms + < 100",        // Missing operand.
+            "latency_p90_ms = 100",          // Not a comparison.
+            "1.2.3 < 4",                     // Invalid number.
+            "latency_p90_ms < 100 && 1 < 2", // No logical operators.
+        ] {
+            assert!(HealthRule::parse(source).is_err(), "{}", source);
+        }
+
+        let nested = format!("{}1{} < 2", "(".repeat(20), ")".repeat(20));
+        assert!(HealthRule::parse(&nested).is_err());
+        assert!(HealthRule::parse(&format!("{} < 1", "1 + ".repeat(100))).is_err());
+    }
+}
@@ -1,13 +1,41 @@
+use crate::api::{link_status, LINKS_REASON_ALL_LINKS_DOWN, LINKS_REASON_LINKS_DEGRADED};
 use crate::shared_types::Link;
 use crate::shared_types::TargetServer;
 use common::basic_types::*;
+use common::shared_types::{WorkdirState, WorkdirStatus};
 
-use super::{ServerStats, WorkdirUserConfig};
+use super::{
+    ConfigHistory, HealthRule, LinkClient, LinkRole, LinkUsage, LinkWarmUpRule, ProxyAllowlist,
+    ProxyCorsConfig, ProxyDistribution, ProxyHedgeConfig, ProxyShadowConfig, ProxyTimeouts,
+    ProxyTlsConfig, QuotaErrorRule, RateLimiter, RateLimits, RecentRequests, RecentRequestsMT,
+    ServerStats, ShadowAllowance, ShadowStats, ShadowStatsMT, ShadowTarget, SystemValues,
+    SystemValuesMT, WorkdirUserConfig,
+};
 
+use std::collections::BTreeMap;
 use std::hash::Hasher;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use twox_hash::XxHash32;
 
-#[derive(Debug)]
+// Maximum number of TargetServer attempted for one request.
+const RETRY_COUNT: usize = 4;
+
+// A link with less than this fraction of its rate limits available is not part
+// of the "weighted" distribution (see TargetServer::rate_limit_headroom).
+pub const RATE_LIMIT_MIN_HEADROOM: f64 = 0.1;
+
+// Latencies are floored to this for the "weighted" distribution (a very fast
+// local server would otherwise get all the traffic).
+const WEIGHTED_MIN_LATENCY_MS: f64 = 1.0;
+
+// Drives the "weighted" random pick of get_best_target_servers().
+static SELECTION_SEQ: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone)]
 pub struct InputPort {
     idx: Option<ManagedVecU8>,
 
@@ -20,6 +48,15 @@ pub struct InputPort {
     // TCP/UDP port number. Set once at construction.
     port_number: u16,
 
+    // Port the proxy_server listens on. Differs from port_number when the configured
+    // port was already in use (see ActivePorts). None until the proxy_server is started.
+    actual_port_number: Option<u16>,
+    strict_ports: bool,
+    port_fallback_range: u16,
+
+    // Last failure to find a port for the proxy_server (reported by getLinks).
+    proxy_port_error: Option<String>,
+
     // Request that processing on this port be abandon.
     //
     // This is a irreversible request.
@@ -34,6 +71,80 @@ pub struct InputPort {
     // Active Configuration.
     user_request_start: bool, // true when user_request == "start"
     proxy_enabled: bool,
+    quota_error_rule: QuotaErrorRule,
+    link_warmup: LinkWarmUpRule, // Applies to the links added by upsert_target_server.
+    time_skew_threshold_secs: u64, // See time_skew.rs (0 disables the warnings).
+    proxy_tls: Option<ProxyTlsConfig>,
+
+    // Last failure to load the proxy_tls cert/key (reported by getLinks).
+    // The proxy does not start (or keeps its previous cert) while set.
+    proxy_tls_error: Option<String>,
+
+    // Read once by the proxy_server on start (a change requires a restart).
+    proxy_cors: Option<ProxyCorsConfig>,
+
+    // Read by the proxy_server on every request.
+    proxy_hedge: Option<ProxyHedgeConfig>,
+    proxy_shadow: Option<ProxyShadowConfig>,
+    proxy_timeouts: ProxyTimeouts, // Each link may override them (see Link::timeouts).
+    proxy_allowlist: Option<ProxyAllowlist>, // None means any source IP.
+
+    // User-defined rule for "OK" vs "DEGRADED" of a working link (reported by getLinks).
+    health_rule: Option<HealthRule>,
+
+    // Name of the link_profiles entry used for the target_servers (reported by getLinks).
+    active_link_profile: Option<String>,
+
+    // Problems found while parsing suibase.yaml (reported by getLinks).
+    config_warnings: Vec<String>,
+
+    // Limit of concurrent upstream requests (load shedding).
+    //
+    // The proxy handler holds one permit for the duration of a request. A
+    // request waits at most 'proxy_queue_timeout' for a permit, then is rejected.
+    //
+    // A config change replaces the Semaphore. Requests in progress release their
+    // permit to the old one (harmless, it is dropped with its last user).
+    proxy_max_concurrency: u32,
+    proxy_queue_timeout: Duration,
+    proxy_permits: Arc<Semaphore>,
+
+    proxy_distribution: ProxyDistribution,
+
+    // Token buckets shared by the links with the same rate_group (see rate_limiter.rs).
+    rate_groups: BTreeMap<String, Arc<RateLimiter>>,
+
+    // Reference gas price and protocol config answered from a cache (see SystemValues).
+    proxy_serve_cached_system_values: bool,
+    system_values: SystemValuesMT,
+
+    // Where the cumulative stats are saved (see ProxyStatsFile). None when not persisted.
+    proxy_stats_file: Option<PathBuf>,
+
+    // Requests of each link per month (see LinkUsage), and where saved.
+    link_usage: LinkUsage,
+    link_usage_file: Option<PathBuf>,
+
+    // Last requests handled by the proxy_server (see getRecentRequests).
+    recent_requests: RecentRequestsMT,
+
+    // Comparisons with the shadow link (see getShadowReport).
+    shadow_stats: ShadowStatsMT,
+
+    // Toward the links of this port (see LinkClient).
+    link_client: LinkClient,
+
+    // Config changes applied by the AdminController (see getConfigHistory).
+    config_history: ConfigHistory,
+
+    // Multi-link RPC status, with its info and getLinks reason (see LINKS_REASON_*).
+    //
+    // Re-evaluated whenever the links or the proxy may have changed (see
+    // update_links_status), so its 'since' is when the change happened, not when
+    // getLinks was called.
+    links_status: WorkdirStatus,
+    links_info: String,
+    links_reason: Option<&'static str>,
 
     // Maintained by the AdminController such that the runtime idx remain the
     // same for a given alias ("forever", even when deleted from file config).
@@ -58,6 +169,15 @@ pub struct InputPort {
     // not in OK state (could be fine right now, but not yet known). These are the
     // fallback attempts on initialization or hard recovery (least worst first).
     pub selection_worst: Vec<TargetServerIdx>,
+
+    // For the "weighted" ProxyDistribution only (otherwise empty).
+    //
+    // Probability of each link to be the first attempt of a request (adds up to 1.0).
+    // Recomputed on every NetworkMonitor load sample and selection_vectors update.
+    pub selection_weights: Vec<(TargetServerIdx, f64)>,
+
+    // Healthy links with a canary_pct (see Link), with their percentage of the requests.
+    pub selection_canaries: Vec<(TargetServerIdx, u8)>,
 }
 
 impl InputPort {
@@ -66,20 +186,64 @@ impl InputPort {
         workdir_name: String,
         workdir_config: &WorkdirUserConfig,
     ) -> Self {
-        Self {
+        let mut input_port = Self {
             idx: None,
             workdir_name,
             workdir_idx,
             port_number: workdir_config.proxy_port_number(),
+            actual_port_number: None,
+            strict_ports: workdir_config.is_strict_ports(),
+            port_fallback_range: workdir_config.port_fallback_range(),
+            proxy_port_error: None,
             deactivate_request: false,
             proxy_server_running: false,
             user_request_start: workdir_config.is_user_request_start(),
             proxy_enabled: workdir_config.is_proxy_enabled(),
+            quota_error_rule: workdir_config.quota_error_rule().clone(),
+            link_warmup: workdir_config.link_warmup().clone(),
+            time_skew_threshold_secs: workdir_config.time_skew_threshold_secs(),
+            proxy_tls: workdir_config.proxy_tls().cloned(),
+            proxy_tls_error: None,
+            proxy_cors: workdir_config.proxy_cors().cloned(),
+            proxy_hedge: workdir_config.proxy_hedge().cloned(),
+            proxy_shadow: workdir_config.proxy_shadow(),
+            proxy_timeouts: *workdir_config.proxy_timeouts(),
+            proxy_allowlist: workdir_config.proxy_allowlist(),
+            health_rule: workdir_config.health_score().cloned(),
+            active_link_profile: workdir_config.active_link_profile().cloned(),
+            config_warnings: workdir_config.warnings().to_vec(),
+            proxy_max_concurrency: workdir_config.proxy_max_concurrency(),
+            proxy_queue_timeout: Duration::from_millis(workdir_config.proxy_queue_timeout_ms()),
+            proxy_permits: Arc::new(Semaphore::new(
+                workdir_config.proxy_max_concurrency() as usize
+            )),
+            proxy_distribution: workdir_config.proxy_distribution(),
+            rate_groups: workdir_config
+                .rate_groups()
+                .iter()
+                .map(|(group, limits)| (group.clone(), Arc::new(RateLimiter::new(*limits))))
+                .collect(),
+            proxy_serve_cached_system_values: workdir_config.is_proxy_serve_cached_system_values(),
+            system_values: SystemValues::new_mt(),
+            proxy_stats_file: None,
+            link_usage: LinkUsage::new(),
+            link_usage_file: None,
+            recent_requests: RecentRequests::new_mt(),
+            shadow_stats: ShadowStats::new_mt(),
+            link_client: LinkClient::new(),
+            config_history: ConfigHistory::default(),
+            links_status: WorkdirStatus::new(),
+            links_info: String::new(),
+            links_reason: None,
             target_servers: ManagedVec::new(),
             all_servers_stats: ServerStats::new("all".to_string()),
             selection_vectors: Vec::new(),
             selection_worst: Vec::new(),
-        }
+            selection_weights: Vec::new(),
+            selection_canaries: Vec::new(),
+        };
+        input_port.update_links_status();
+        input_port
     }
 
     pub fn add_target_server(&mut self, config: &Link) {
@@ -106,6 +270,7 @@ impl InputPort {
                         );
                         target_server.set_rpc(rpc.clone());
                         target_server.stats_clear();
+                        target_server.stats.start_warmup(&self.link_warmup);
                         at_least_one_change = true;
                     }
 
@@ -127,9 +292,11 @@ impl InputPort {
                 return at_least_one_change;
             }
         }
-        // Does not exists... add it.
+        // Does not exists... add it. Not selectable until its warm-up passed.
         log::info!("{} adding server {}", self.workdir_name, config.alias);
-        self.add_target_server(config);
+        let mut target_server = TargetServer::new(config.clone());
+        target_server.stats.start_warmup(&self.link_warmup);
+        self.target_servers.push(target_server);
         true
     }
 
@@ -163,10 +330,62 @@ impl InputPort {
 
     pub fn set_user_request_start(&mut self, value: bool) {
         self.user_request_start = value;
+        self.update_links_status();
     }
 
     pub fn set_proxy_enabled(&mut self, value: bool) {
         self.proxy_enabled = value;
+        self.update_links_status();
+    }
+
+    pub fn links_status(&self) -> &WorkdirStatus {
+        &self.links_status
+    }
+
+    // Human-friendly details of the links_status (e.g. "protected, load-balanced").
+    pub fn links_info(&self) -> &str {
+        &self.links_info
+    }
+
+    pub fn links_reason(&self) -> Option<&'static str> {
+        self.links_reason
+    }
+
+    pub fn quota_error_rule(&self) -> &QuotaErrorRule {
+        &self.quota_error_rule
+    }
+
+    pub fn set_quota_error_rule(&mut self, rule: QuotaErrorRule) {
+        self.quota_error_rule = rule;
+    }
+
+    pub fn link_warmup(&self) -> &LinkWarmUpRule {
+        &self.link_warmup
+    }
+
+    pub fn set_link_warmup(&mut self, rule: LinkWarmUpRule) {
+        self.link_warmup = rule;
+    }
+
+    pub fn time_skew_threshold_secs(&self) -> u64 {
+        self.time_skew_threshold_secs
+    }
+
+    pub fn set_time_skew_threshold_secs(&mut self, threshold_secs: u64) {
+        self.time_skew_threshold_secs = threshold_secs;
+    }
+
+    // (alias, skew) of every link with a measured clock skew (see time_skew.rs).
+    pub fn time_skews(&self) -> Vec<(String, i64)> {
+        self.target_servers
+            .iter()
+            .filter_map(|(_, target_server)| {
+                target_server
+                    .stats
+                    .time_skew_ms()
+                    .map(|skew_ms| (target_server.alias(), skew_ms))
+            })
+            .collect()
     }
 
     pub fn report_proxy_server_starting(&mut self) {
@@ -177,22 +396,323 @@ impl InputPort {
         self.proxy_server_running = false;
     }
 
+    pub fn is_proxy_server_running(&self) -> bool {
+        self.proxy_server_running
+    }
+
+    // Port to use for a request to this proxy.
+    pub fn listening_port_number(&self) -> u16 {
+        self.actual_port_number.unwrap_or(self.port_number)
+    }
+
+    pub fn actual_port_number(&self) -> Option<u16> {
+        self.actual_port_number
+    }
+
+    pub fn set_actual_port_number(&mut self, value: Option<u16>) {
+        self.actual_port_number = value;
+    }
+
+    pub fn is_strict_ports(&self) -> bool {
+        self.strict_ports
+    }
+
+    pub fn port_fallback_range(&self) -> u16 {
+        self.port_fallback_range
+    }
+
+    pub fn set_port_fallback(&mut self, strict_ports: bool, port_fallback_range: u16) {
+        self.strict_ports = strict_ports;
+        self.port_fallback_range = port_fallback_range;
+    }
+
+    pub fn proxy_port_error(&self) -> Option<&String> {
+        self.proxy_port_error.as_ref()
+    }
+
+    pub fn set_proxy_port_error(&mut self, value: Option<String>) {
+        self.proxy_port_error = value;
+        self.update_links_status();
+    }
+
+    pub fn proxy_tls(&self) -> Option<&ProxyTlsConfig> {
+        self.proxy_tls.as_ref()
+    }
+
+    pub fn is_proxy_tls(&self) -> bool {
+        self.proxy_tls.is_some()
+    }
+
+    pub fn set_proxy_tls(&mut self, value: Option<ProxyTlsConfig>) {
+        self.proxy_tls = value;
+    }
+
+    pub fn proxy_tls_error(&self) -> Option<&String> {
+        self.proxy_tls_error.as_ref()
+    }
 
+    pub fn set_proxy_tls_error(&mut self, value: Option<String>) {
+        self.proxy_tls_error = value;
+        self.update_links_status();
+    }
 
+    pub fn proxy_cors(&self) -> Option<&ProxyCorsConfig> {
+        self.proxy_cors.as_ref()
+    }
+
+    pub fn set_proxy_cors(&mut self, value: Option<ProxyCorsConfig>) {
+        self.proxy_cors = value;
+    }
+
+    pub fn proxy_hedge(&self) -> Option<&ProxyHedgeConfig> {
+        self.proxy_hedge.as_ref()
+    }
+
+    pub fn set_proxy_hedge(&mut self, value: Option<ProxyHedgeConfig>) {
+        self.proxy_hedge = value;
+    }
+
+    pub fn proxy_timeouts(&self) -> &ProxyTimeouts {
+        &self.proxy_timeouts
+    }
+
+    pub fn set_proxy_timeouts(&mut self, value: ProxyTimeouts) {
+        self.proxy_timeouts = value;
+    }
+
+    pub fn proxy_shadow(&self) -> Option<&ProxyShadowConfig> {
+        self.proxy_shadow.as_ref()
+    }
+
+    // The comparisons restart from zero when the shadow link changes.
+    pub fn set_proxy_shadow(&mut self, value: Option<ProxyShadowConfig>) {
+        let link = |config: &Option<ProxyShadowConfig>| config.as_ref().map(|c| c.link.clone());
+        if link(&self.proxy_shadow) != link(&value) {
+            self.shadow_stats = ShadowStats::new_mt();
+        }
+        self.proxy_shadow = value;
+    }
+
+    pub fn proxy_allowlist(&self) -> Option<&ProxyAllowlist> {
+        self.proxy_allowlist.as_ref()
+    }
+
+    pub fn set_proxy_allowlist(&mut self, value: Option<ProxyAllowlist>) {
+        self.proxy_allowlist = value;
+    }
+
+    pub fn health_rule(&self) -> Option<&HealthRule> {
+        self.health_rule.as_ref()
+    }
+
+    pub fn set_health_rule(&mut self, value: Option<HealthRule>) {
+        self.health_rule = value;
+    }
+
+    pub fn active_link_profile(&self) -> Option<&String> {
+        self.active_link_profile.as_ref()
+    }
+
+    pub fn set_active_link_profile(&mut self, value: Option<String>) {
+        self.active_link_profile = value;
+    }
+
+    pub fn config_warnings(&self) -> &[String] {
+        &self.config_warnings
+    }
+
+    pub fn set_config_warnings(&mut self, value: Vec<String>) {
+        self.config_warnings = value;
+    }
+
+    pub fn proxy_max_concurrency(&self) -> u32 {
+        self.proxy_max_concurrency
+    }
+
+    pub fn proxy_queue_timeout(&self) -> Duration {
+        self.proxy_queue_timeout
+    }
+
+    pub fn proxy_permits(&self) -> Arc<Semaphore> {
+        self.proxy_permits.clone()
+    }
+
+    pub fn proxy_distribution(&self) -> ProxyDistribution {
+        self.proxy_distribution
+    }
+
+    pub fn set_proxy_distribution(&mut self, value: ProxyDistribution) {
+        self.proxy_distribution = value;
+    }
+
+    pub fn rate_groups(&self) -> &BTreeMap<String, Arc<RateLimiter>> {
+        &self.rate_groups
+    }
+
+    // The tokens already taken from a group are kept, unless its limits changed.
+    pub fn set_rate_groups(&mut self, value: &BTreeMap<String, RateLimits>) {
+        let mut rate_groups = BTreeMap::new();
+        for (group, limits) in value {
+            let limiter = match self.rate_groups.remove(group) {
+                Some(limiter) if limiter.limits() == *limits => limiter,
+                _ => Arc::new(RateLimiter::new(*limits)),
+            };
+            rate_groups.insert(group.clone(), limiter);
+        }
+        self.rate_groups = rate_groups;
+    }
+
+    // The token buckets to acquire before sending a request to the link, in order:
+    // its rate group first, then the link itself (see try_acquire_all).
+    pub fn rate_limiters(&self, server_idx: TargetServerIdx) -> Vec<Arc<RateLimiter>> {
+        let target_server = match self.target_servers.get(server_idx) {
+            Some(target_server) => target_server,
+            None => return Vec::new(),
+        };
+        let group = target_server
+            .get_config()
+            .rate_group
+            .as_ref()
+            .and_then(|group| self.rate_groups.get(group));
+        group
+            .into_iter()
+            .chain(target_server.rate_limiter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_proxy_serve_cached_system_values(&self) -> bool {
+        self.proxy_serve_cached_system_values
+    }
+
+    pub fn set_proxy_serve_cached_system_values(&mut self, value: bool) {
+        self.proxy_serve_cached_system_values = value;
+    }
+
+    // All the stats restart from zero (see resetServerStats).
+    pub fn clear_stats(&mut self) {
+        for (_, target_server) in self.target_servers.iter_mut() {
+            target_server.stats_clear();
+        }
+        self.all_servers_stats.clear();
+        self.update_selection_vectors();
+    }
+
+    pub fn proxy_stats_file(&self) -> Option<&PathBuf> {
+        self.proxy_stats_file.as_ref()
+    }
+
+    pub fn set_proxy_stats_file(&mut self, value: Option<PathBuf>) {
+        self.proxy_stats_file = value;
+    }
+
+    pub fn link_usage(&self) -> &LinkUsage {
+        &self.link_usage
+    }
+
+    pub fn link_usage_mut(&mut self) -> &mut LinkUsage {
+        &mut self.link_usage
+    }
+
+    pub fn link_usage_file(&self) -> Option<&PathBuf> {
+        self.link_usage_file.as_ref()
+    }
+
+    pub fn set_link_usage_file(&mut self, value: Option<PathBuf>) {
+        self.link_usage_file = value;
+    }
+
+    pub fn system_values(&self) -> SystemValuesMT {
+        self.system_values.clone()
+    }
+
+    pub fn recent_requests(&self) -> RecentRequestsMT {
+        self.recent_requests.clone()
+    }
+
+    pub fn shadow_stats(&self) -> ShadowStatsMT {
+        self.shadow_stats.clone()
+    }
+
+    // Where to send the copy of a request (see shadow.rs). None when the shadow mode
+    // is disabled, or the shadow link has no rpc.
+    pub fn shadow_target(&self, now: &EpochTimestamp) -> Option<ShadowTarget> {
+        let config = self.proxy_shadow.as_ref()?;
+        let (server_idx, target_server) = self
+            .target_servers
+            .iter()
+            .find(|(_, target_server)| target_server.alias() == config.link)?;
+        let uri = target_server.rpc();
+        if uri.is_empty() {
+            return None;
+        }
+        Some(ShadowTarget {
+            config: config.clone(),
+            server_idx,
+            uri,
+            allowance: ShadowAllowance::new(target_server, now),
+            stats: self.shadow_stats.clone(),
+        })
+    }
+
+    pub fn link_client(&self) -> LinkClient {
+        self.link_client.clone()
+    }
+
+    pub fn config_history(&self) -> &ConfigHistory {
+        &self.config_history
+    }
+
+    pub fn config_history_mut(&mut self) -> &mut ConfigHistory {
+        &mut self.config_history
+    }
+
+    // Returns true on any change.
+    pub fn set_proxy_concurrency(&mut self, max_concurrency: u32, queue_timeout_ms: u64) -> bool {
+        let queue_timeout = Duration::from_millis(queue_timeout_ms);
+        let max_concurrency = max_concurrency.max(1);
+        if max_concurrency == self.proxy_max_concurrency
+            && queue_timeout == self.proxy_queue_timeout
+        {
+            return false;
+        }
+        if max_concurrency != self.proxy_max_concurrency {
+            self.proxy_max_concurrency = max_concurrency;
+            self.proxy_permits = Arc::new(Semaphore::new(max_concurrency as usize));
+        }
+        self.proxy_queue_timeout = queue_timeout;
+        true
+    }
+
+    // 'request_id' drives the canary selection (None to never pick a canary first,
+    // e.g. for the daemon own requests).
     pub fn get_best_target_servers(
         &self,
         target_servers: &mut Vec<(TargetServerIdx, String)>,
         handler_start: &EpochTimestamp,
+        request_id: Option<&str>,
+    ) {
+        self.get_selected_target_servers(target_servers, handler_start);
+        self.apply_canaries(target_servers, handler_start, request_id);
+    }
+
+    fn get_selected_target_servers(
+        &self,
+        target_servers: &mut Vec<(TargetServerIdx, String)>,
+        handler_start: &EpochTimestamp,
     ) {
         // Just leave target_servers untouch if there is any problem.
 
+        if self.get_weighted_target_servers(target_servers, handler_start) {
+            return;
+        }
+
         if !self.selection_vectors.is_empty() {
             // Select up to 'RETRY_COUNT' healthy (when available).
             //
             // Get the first 'RETRY_COUNT' TargetServerIdx stored in self.selection_vectors[x][y] by incrementing x first then y.
             //
             // This allows to group TargetServer for load balancing and distribute evenly over a selection_vector[x].
-            const RETRY_COUNT: usize = 4;
             let mut count = 0;
             let mut vector_idx: usize = 0;
 
@@ -212,7 +732,7 @@ impl InputPort {
                 let rng = hasher.finish() as usize;
                 for i in 0..vector.len() {
                     let idx = vector[(i + rng) % vector.len()];
-                    if let Some(uri) = self.uri(idx) {
+                    if let Some(uri) = self.selectable_uri(idx, handler_start) {
                         target_servers.push((idx, uri));
                         count += 1;
                         if count == RETRY_COUNT {
@@ -225,7 +745,7 @@ impl InputPort {
             // Select sequentially from this point on.
             for vector in &self.selection_vectors[vector_idx..] {
                 for &idx in vector {
-                    if let Some(uri) = self.uri(idx) {
+                    if let Some(uri) = self.selectable_uri(idx, handler_start) {
                         target_servers.push((idx, uri));
                         count += 1;
                         if count == RETRY_COUNT {
@@ -239,7 +759,7 @@ impl InputPort {
             // worst selections.
             // Note: This can normally happen on initialization or hard recovery.
             for &idx in &self.selection_worst {
-                if let Some(uri) = self.uri(idx) {
+                if let Some(uri) = self.selectable_uri(idx, handler_start) {
                     target_servers.push((idx, uri));
                     count += 1;
                     if count == RETRY_COUNT {
@@ -253,9 +773,9 @@ impl InputPort {
         // that may rely more on the config user priority.
         if target_servers.is_empty() {
             for (_, target_server) in self.target_servers.iter() {
-                if target_server.is_selectable() {
+                if target_server.is_selectable() && !target_server.stats.is_probing() {
                     if let Some(idx) = target_server.idx() {
-                        if let Some(uri) = self.uri(idx) {
+                        if let Some(uri) = self.selectable_uri(idx, handler_start) {
                             target_servers.push((idx, uri));
                         }
                     }
@@ -284,10 +804,189 @@ impl InputPort {
         }
     }
 
+    // First attempt picked at random according to the selection_weights. The retries
+    // are in the usual order (best first).
+    //
+    // Returns false when not using the "weighted" distribution (or no link has a weight).
+    fn get_weighted_target_servers(
+        &self,
+        target_servers: &mut Vec<(TargetServerIdx, String)>,
+        handler_start: &EpochTimestamp,
+    ) -> bool {
+        if self.selection_weights.is_empty() {
+            return false;
+        }
+
+        // Uniform in [0.0, 1.0).
+        let mut hasher = XxHash32::with_seed(0);
+        hasher.write_u32(SELECTION_SEQ.fetch_add(1, Ordering::Relaxed));
+        let point = (hasher.finish() as u32) as f64 / (u32::MAX as f64 + 1.0);
+
+        let mut picked = self.selection_weights.last().unwrap().0;
+        let mut cumulative = 0.0;
+        for &(idx, weight) in &self.selection_weights {
+            cumulative += weight;
+            if point < cumulative {
+                picked = idx;
+                break;
+            }
+        }
+
+        let retries = self
+            .selection_vectors
+            .iter()
+            .flatten()
+            .chain(self.selection_worst.iter())
+            .filter(|&&idx| idx != picked);
+        let mut count = 0;
+        for &idx in std::iter::once(&picked).chain(retries) {
+            if let Some(uri) = self.selectable_uri(idx, handler_start) {
+                target_servers.push((idx, uri));
+                count += 1;
+                if count == RETRY_COUNT {
+                    break;
+                }
+            }
+        }
+        count != 0
+    }
+
+    fn is_canary(&self, server_idx: TargetServerIdx) -> bool {
+        self.target_servers
+            .get(server_idx)
+            .is_some_and(|ts| ts.get_config().canary_pct.is_some())
+    }
+
+    // A canary link gets about its canary_pct of the requests, picked by a hash of the
+    // request id (a request id always gives the same pick). Otherwise, a canary is
+    // attempted only after all the other links (e.g. all DOWN).
+    fn apply_canaries(
+        &self,
+        target_servers: &mut Vec<(TargetServerIdx, String)>,
+        handler_start: &EpochTimestamp,
+        request_id: Option<&str>,
+    ) {
+        if !target_servers.iter().any(|(idx, _)| self.is_canary(*idx))
+            && self.selection_canaries.is_empty()
+        {
+            return;
+        }
+        let (mut ordered, canaries): (Vec<_>, Vec<_>) = target_servers
+            .drain(..)
+            .partition(|(idx, _)| !self.is_canary(*idx));
+        ordered.extend(canaries);
+
+        if let Some(picked) = request_id.and_then(|id| self.pick_canary(id, handler_start)) {
+            ordered.retain(|(idx, _)| *idx != picked.0);
+            ordered.insert(0, picked);
+            ordered.truncate(RETRY_COUNT);
+        }
+        *target_servers = ordered;
+    }
+
+    fn pick_canary(
+        &self,
+        request_id: &str,
+        handler_start: &EpochTimestamp,
+    ) -> Option<(TargetServerIdx, String)> {
+        // Uniform in [0.0, 100.0).
+        let mut hasher = XxHash32::with_seed(0);
+        hasher.write(request_id.as_bytes());
+        let point = (hasher.finish() % 10_000) as f64 / 100.0;
+
+        let mut cumulative = 0.0;
+        for &(idx, pct) in &self.selection_canaries {
+            cumulative += pct as f64;
+            if point < cumulative {
+                // The rate limits still apply (the normal selection is then used).
+                let target_server = self.target_servers.get(idx)?;
+                if target_server.rate_limit_headroom() < RATE_LIMIT_MIN_HEADROOM {
+                    return None;
+                }
+                return self
+                    .selectable_uri(idx, handler_start)
+                    .map(|uri| (idx, uri));
+            }
+        }
+        None
+    }
+
+    // Canaries for the selection (see selection_canaries).
+    fn update_selection_canaries(&mut self) {
+        self.selection_canaries = self
+            .selection_vectors
+            .iter()
+            .flatten()
+            .filter_map(|&idx| {
+                let canary_pct = self.target_servers.get(idx)?.get_config().canary_pct?;
+                Some((idx, canary_pct))
+            })
+            .filter(|(_, canary_pct)| *canary_pct > 0)
+            .collect();
+    }
+
+    // Weights for the "weighted" distribution (see selection_weights).
+    //
+    // Every healthy link (in the selection_vectors), except the canaries, gets a weight
+    // inversely proportional to its smoothed latency, except when too close to its rate
+    // limits. A link without latency measurement yet counts as the slowest one.
+    pub fn update_selection_weights(&mut self) {
+        self.selection_weights.clear();
+        if self.proxy_distribution != ProxyDistribution::Weighted {
+            return;
+        }
+
+        let mut candidates: Vec<(TargetServerIdx, Option<f64>)> = Vec::new();
+        for &idx in self.selection_vectors.iter().flatten() {
+            if let Some(target_server) = self.target_servers.get(idx) {
+                // A canary gets its share otherwise (see apply_canaries).
+                if target_server.rate_limit_headroom() < RATE_LIMIT_MIN_HEADROOM
+                    || target_server.get_config().canary_pct.is_some()
+                {
+                    continue;
+                }
+                let latency = target_server.stats.avg_latency_ms();
+                let latency = if latency.is_finite() && latency != f64::MAX {
+                    Some(latency.max(WEIGHTED_MIN_LATENCY_MS))
+                } else {
+                    None
+                };
+                candidates.push((idx, latency));
+            }
+        }
+
+        let slowest = candidates
+            .iter()
+            .filter_map(|(_, latency)| *latency)
+            .fold(WEIGHTED_MIN_LATENCY_MS, f64::max);
+        let inverses: Vec<(TargetServerIdx, f64)> = candidates
+            .into_iter()
+            .map(|(idx, latency)| (idx, 1.0 / latency.unwrap_or(slowest)))
+            .collect();
+        let total: f64 = inverses.iter().map(|(_, inverse)| inverse).sum();
+        if total > 0.0 {
+            self.selection_weights = inverses
+                .into_iter()
+                .map(|(idx, inverse)| (idx, inverse / total))
+                .collect();
+        }
+    }
+
     pub fn uri(&self, server_idx: TargetServerIdx) -> Option<String> {
         self.target_servers.get(server_idx).map(|ts| ts.rpc())
     }
 
+    // Same as uri(), but None while the link is throttled by its provider.
+    //
+    // Checked on every request (instead of by update_selection_vectors), so the link
+    // is selected again as soon as its throttling window ends.
+    fn selectable_uri(&self, server_idx: TargetServerIdx, now: &EpochTimestamp) -> Option<String> {
+        self.target_servers
+            .get(server_idx)
+            .filter(|ts| !ts.stats.is_throttled(now))
+            .map(|ts| ts.rpc())
+    }
+
     pub fn update_selection_vectors(&mut self) {
         let target_servers = &mut self.target_servers;
 
@@ -297,10 +996,18 @@ impl InputPort {
         // Build a vector of idx() of the elements of target_servers.
         // At same time, find one currently OK with the best latency_avg().
         // Isolate immediately all down target servers in selection_worst.
+        // A server still probing (see LinkWarmUpRule), in a maintenance window or not
+        // selectable (e.g. a "monitor-only" link) is in neither.
         let mut ok_idx_vec: Vec<TargetServerIdx> = Vec::new();
         let mut best_latency_avg: f64 = f64::MAX;
         let mut best_latency_avg_idx: Option<TargetServerIdx> = None;
         for (_, target_server) in target_servers.iter() {
+            if !target_server.is_selectable()
+                || target_server.stats.is_probing()
+                || target_server.stats.is_in_maintenance()
+            {
+                continue;
+            }
             if let Some(idx) = target_server.idx() {
                 if target_server.stats.is_healthy() {
                     if best_latency_avg_idx.is_none()
@@ -386,6 +1093,101 @@ impl InputPort {
                 }
             });
         }
+
+        self.update_selection_canaries();
+        self.update_selection_weights();
+        self.update_links_status();
+    }
+
+    // Re-evaluate the multi-link RPC status from the proxy config and the current
+    // health of the links. The transition (if any) is timestamped now.
+    pub fn update_links_status(&mut self) {
+        let (state, info, reason) = self.eval_links_status();
+        let cause = if info.is_empty() {
+            format!("multi-link RPC {}", state)
+        } else {
+            info.clone()
+        };
+        self.links_status.transition(state, &cause);
+        self.links_info = info;
+        self.links_reason = reason;
+    }
+
+    fn eval_links_status(&self) -> (WorkdirState, String, Option<&'static str>) {
+        let mut server_count: usize = 0;
+        let mut healthy_server_count: usize = 0;
+        let mut neutral_health_count: usize = 0;
+        let mut probing_count: usize = 0; // Not selectable until their warm-up passed.
+        let mut monitor_only_count: usize = 0; // Never selectable.
+        let mut maintenance_count: usize = 0; // Not selectable until their window ends.
+        for (_, target_server) in self.target_servers.iter() {
+            // A "monitor-only" link does not contribute to the multi-link status.
+            let stats = &target_server.stats;
+            if target_server.role() == LinkRole::MonitorOnly {
+                monitor_only_count += 1;
+                continue;
+            }
+            server_count += 1;
+            let health_score = stats.health_score();
+            if stats.is_in_maintenance() {
+                maintenance_count += 1;
+            } else if stats.is_probing() {
+                probing_count += 1;
+            } else if health_score.is_normal() && health_score.is_sign_positive() {
+                healthy_server_count += 1;
+            }
+            if link_status(stats).is_empty() {
+                neutral_health_count += 1;
+            }
+        }
+
+        // With weights, the load is distributed on the links having one.
+        let load_distribution_depth = if !self.selection_weights.is_empty() {
+            self.selection_weights.len()
+        } else {
+            self.selection_vectors.first().map_or(0, |v| v.len())
+        };
+        let load_balance_str = if load_distribution_depth > 1 {
+            ", load-balanced"
+        } else {
+            ""
+        };
+
+        let warm_server_count = server_count - probing_count - maintenance_count;
+        if !self.proxy_enabled {
+            (WorkdirState::Down, "proxy not enabled".to_string(), None)
+        } else if let Some(proxy_tls_error) = &self.proxy_tls_error {
+            (WorkdirState::Down, proxy_tls_error.clone(), None)
+        } else if let Some(proxy_port_error) = &self.proxy_port_error {
+            (WorkdirState::Down, proxy_port_error.clone(), None)
+        } else if !self.user_request_start {
+            let info = format!("{} not started", self.workdir_name);
+            (WorkdirState::Down, info, None)
+        } else if server_count == 0 && monitor_only_count > 0 {
+            let info = "only monitor-only links".to_string();
+            (WorkdirState::Down, info, None)
+        } else if server_count == 0 {
+            let info = "no links in suibase.yaml".to_string();
+            (WorkdirState::Down, info, None)
+        } else if warm_server_count == 0 && maintenance_count > 0 {
+            let info = "links in maintenance".to_string();
+            (WorkdirState::Down, info, None)
+        } else if neutral_health_count == warm_server_count {
+            (WorkdirState::Down, "initializing".to_string(), None)
+        } else if healthy_server_count == 0 {
+            let info = "no servers available".to_string();
+            (WorkdirState::Down, info, Some(LINKS_REASON_ALL_LINKS_DOWN))
+        } else if healthy_server_count * 100 / warm_server_count > 50 {
+            let info = if self.workdir_name == "localnet" {
+                load_balance_str.to_string()
+            } else {
+                format!("protected{}", load_balance_str)
+            };
+            (WorkdirState::Ok, info, None)
+        } else {
+            let info = format!(">50% degraded{}", load_balance_str);
+            (WorkdirState::Ok, info, Some(LINKS_REASON_LINKS_DEGRADED))
+        }
     }
 }
 
@@ -398,3 +1200,479 @@ impl ManagedElement for InputPort {
         self.idx = index;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_types::WarmUpProgress;
+    use std::collections::HashMap;
+
+    // Port with two links where "a" is faster than "b".
+    fn new_port_a_faster_than_b(rule: QuotaErrorRule) -> (InputPort, EpochTimestamp) {
+        let config = WorkdirUserConfig::new();
+        let mut input_port = InputPort::new(0, "localnet".to_string(), &config);
+        input_port.set_quota_error_rule(rule);
+        input_port.add_target_server(&Link::new("a".to_string(), "http://a".to_string()));
+        input_port.add_target_server(&Link::new("b".to_string(), "http://b".to_string()));
+
+        let t0 = EpochTimestamp::now() + Duration::from_millis(1);
+        for (_, target_server) in input_port.target_servers.iter_mut() {
+            let latency_microsecs = if target_server.alias() == "a" {
+                1000
+            } else {
+                3000
+            };
+            target_server
+                .stats
+                .handle_latency_report(t0, latency_microsecs);
+        }
+        input_port.update_selection_vectors();
+        (input_port, t0)
+    }
+
+    fn best_alias(input_port: &InputPort) -> String {
+        let mut targets = Vec::new();
+        input_port.get_best_target_servers(&mut targets, &EpochTimestamp::now(), None);
+        let (idx, _) = targets.first().unwrap();
+        input_port.target_servers.get(*idx).unwrap().alias()
+    }
+
+    fn get_idx(input_port: &InputPort, alias: &str) -> TargetServerIdx {
+        input_port
+            .target_servers
+            .iter()
+            .find(|(_, ts)| ts.alias() == alias)
+            .and_then(|(_, ts)| ts.idx())
+            .unwrap()
+    }
+
+    // Same as done by the NetworkMonitor for a response to user traffic.
+    fn resp_ok(input_port: &mut InputPort, alias: &str, t: EpochTimestamp, code: Option<i32>) {
+        resp_ok_for_method(input_port, alias, t, JsonRpcMethodKind::Read, code);
+    }
+
+    fn resp_ok_for_method(
+        input_port: &mut InputPort,
+        alias: &str,
+        t: EpochTimestamp,
+        kind: JsonRpcMethodKind,
+        code: Option<i32>,
+    ) {
+        let rule = input_port.quota_error_rule().clone();
+        let idx = get_idx(input_port, alias);
+        let target_server = input_port.target_servers.get_mut(idx).unwrap();
+        let was_healthy = target_server.stats.is_healthy();
+        target_server.stats.record_http_status(200);
+        let jsonrpc_error = code.map(|code| (code, jsonrpc_error_category(kind, code), &rule));
+        target_server
+            .stats
+            .handle_resp_ok(t, 0, 0, 0, jsonrpc_error);
+        if was_healthy && !target_server.stats.is_healthy() {
+            input_port.update_selection_vectors();
+        }
+    }
+
+    #[test]
+    fn test_consecutive_quota_errors_shift_selection() {
+        let (mut input_port, t0) = new_port_a_faster_than_b(QuotaErrorRule::new());
+        assert_eq!(best_alias(&input_port), "a");
+
+        // Below the threshold, "a" remains the preferred link.
+        for i in 1..5 {
+            let t = t0 + Duration::from_millis(i);
+            resp_ok(&mut input_port, "a", t, Some(-32000));
+            assert_eq!(best_alias(&input_port), "a");
+        }
+
+        // 5th consecutive quota error degrades "a".
+        resp_ok(
+            &mut input_port,
+            "a",
+            t0 + Duration::from_millis(5),
+            Some(-32000),
+        );
+        let a_idx = get_idx(&input_port, "a");
+        let a_stats = &input_port.target_servers.get(a_idx).unwrap().stats;
+        assert!(!a_stats.is_healthy());
+        assert_eq!(a_stats.error_info(), "Quota errors (-32000)");
+        assert_eq!(a_stats.http_status_class_count(2), 5);
+        assert_eq!(input_port.selection_worst, vec![a_idx]);
+        assert_eq!(best_alias(&input_port), "b");
+    }
+
+    #[test]
+    fn test_non_quota_errors_do_not_shift_selection() {
+        let (mut input_port, t0) = new_port_a_faster_than_b(QuotaErrorRule::new());
+
+        // An error not configured as quota-type (e.g. invalid params) is only counted.
+        for i in 1..10 {
+            let t = t0 + Duration::from_millis(i);
+            resp_ok(&mut input_port, "a", t, Some(-32602));
+        }
+        assert_eq!(best_alias(&input_port), "a");
+
+        let a_idx = get_idx(&input_port, "a");
+        let a_stats = &input_port.target_servers.get(a_idx).unwrap().stats;
+        assert!(a_stats.is_healthy());
+        assert_eq!(a_stats.top_jsonrpc_error_codes(3), vec![(-32602, 9)]);
+    }
+
+    #[test]
+    fn test_execute_errors_do_not_shift_selection() {
+        let (mut input_port, t0) = new_port_a_faster_than_b(QuotaErrorRule::new());
+
+        // Invalid transactions of the user, even with a quota-type code.
+        for i in 1..10 {
+            let t = t0 + Duration::from_millis(i);
+            let code = if i % 2 == 0 { -32602 } else { -32000 };
+            resp_ok_for_method(
+                &mut input_port,
+                "a",
+                t,
+                JsonRpcMethodKind::Execute,
+                Some(code),
+            );
+        }
+        assert_eq!(best_alias(&input_port), "a");
+        let a_idx = get_idx(&input_port, "a");
+        assert!(input_port
+            .target_servers
+            .get(a_idx)
+            .unwrap()
+            .stats
+            .is_healthy());
+
+        // A server-fault error on a read degrades on the first occurrence.
+        resp_ok(
+            &mut input_port,
+            "a",
+            t0 + Duration::from_millis(10),
+            Some(JSONRPC_INTERNAL_ERROR),
+        );
+        let a_stats = &input_port.target_servers.get(a_idx).unwrap().stats;
+        assert!(!a_stats.is_healthy());
+        assert_eq!(a_stats.error_info(), "JSON-RPC server error (-32603)");
+        assert_eq!(best_alias(&input_port), "b");
+    }
+
+    #[test]
+    fn test_pct_quota_errors_shift_selection() {
+        let rule = QuotaErrorRule {
+            codes: vec![-32000, -32099],
+            consecutive: 0,
+            pct: 50,
+            min_samples: 10,
+        };
+        let (mut input_port, t0) = new_port_a_faster_than_b(rule);
+
+        // Alternate quota errors with normal responses. Never more than one
+        // consecutive, but 50% of the responses once min_samples is reached.
+        for i in 1..=9u64 {
+            let code = if i % 2 == 1 { Some(-32000) } else { None };
+            resp_ok(&mut input_port, "a", t0 + Duration::from_millis(i), code);
+            assert_eq!(best_alias(&input_port), "a");
+        }
+        resp_ok(&mut input_port, "a", t0 + Duration::from_millis(10), None);
+        assert_eq!(best_alias(&input_port), "a");
+        resp_ok(
+            &mut input_port,
+            "a",
+            t0 + Duration::from_millis(11),
+            Some(-32099),
+        );
+        assert_eq!(best_alias(&input_port), "b");
+
+        let a_idx = get_idx(&input_port, "a");
+        let a_stats = &input_port.target_servers.get(a_idx).unwrap().stats;
+        assert_eq!(
+            a_stats.top_jsonrpc_error_codes(3),
+            vec![(-32000, 5), (-32099, 1)]
+        );
+    }
+
+    #[test]
+    fn test_link_warmup() {
+        let (mut input_port, t0) = new_port_a_faster_than_b(QuotaErrorRule::new());
+        input_port.set_link_warmup(LinkWarmUpRule {
+            checks: 3,
+            min_success: 2,
+            interval_ms: 10,
+        });
+        let is_ranked = |input_port: &InputPort, alias: &str| -> bool {
+            let idx = get_idx(input_port, alias);
+            input_port
+                .selection_vectors
+                .iter()
+                .flatten()
+                .any(|i| *i == idx)
+                || input_port.selection_worst.contains(&idx)
+        };
+
+        // A link added while running is never selected while probing, even when
+        // it is faster than all others.
+        assert!(
+            input_port.upsert_target_server(&Link::new("c".to_string(), "http://c".to_string()))
+        );
+        let c_idx = get_idx(&input_port, "c");
+        let c = input_port.target_servers.get_mut(c_idx).unwrap();
+        assert!(c.stats.is_warmup_pending());
+        c.stats
+            .handle_latency_report(t0 + Duration::from_millis(1), 100);
+        input_port.update_selection_vectors();
+        assert!(!is_ranked(&input_port, "c"));
+        assert_eq!(best_alias(&input_port), "a");
+
+        // Two failures out of three checks fail the warm-up.
+        let c = input_port.target_servers.get_mut(c_idx).unwrap();
+        assert_eq!(
+            c.stats.handle_warmup_check(false),
+            Some(WarmUpProgress::Probing)
+        );
+        assert!(!c.stats.is_warmup_pending());
+        assert_eq!(
+            c.stats.handle_warmup_check(true),
+            Some(WarmUpProgress::Probing)
+        );
+        assert_eq!(
+            c.stats.handle_warmup_check(false),
+            Some(WarmUpProgress::Failed)
+        );
+        assert!(c.stats.is_warmup_failed());
+        input_port.update_selection_vectors();
+        assert!(!is_ranked(&input_port, "c"));
+
+        // The next check starts a new burst.
+        let c = input_port.target_servers.get_mut(c_idx).unwrap();
+        assert_eq!(
+            c.stats.handle_warmup_check(true),
+            Some(WarmUpProgress::Probing)
+        );
+        assert!(!c.stats.is_warmup_failed());
+        assert_eq!(
+            c.stats.handle_warmup_check(true),
+            Some(WarmUpProgress::Passed)
+        );
+        assert!(!c.stats.is_probing());
+        assert_eq!(c.stats.handle_warmup_check(true), None);
+        input_port.update_selection_vectors();
+        assert_eq!(best_alias(&input_port), "c");
+
+        // A rpc change restarts the warm-up.
+        assert!(
+            input_port.upsert_target_server(&Link::new("c".to_string(), "http://c2".to_string()))
+        );
+        input_port.update_selection_vectors();
+        assert!(!is_ranked(&input_port, "c"));
+        assert_eq!(best_alias(&input_port), "a");
+
+        // No warm-up when disabled.
+        input_port.set_link_warmup(LinkWarmUpRule {
+            checks: 0,
+            min_success: 0,
+            interval_ms: 10,
+        });
+        assert!(
+            input_port.upsert_target_server(&Link::new("d".to_string(), "http://d".to_string()))
+        );
+        let d_idx = get_idx(&input_port, "d");
+        assert!(!input_port
+            .target_servers
+            .get(d_idx)
+            .unwrap()
+            .stats
+            .is_probing());
+        input_port.update_selection_vectors();
+        assert!(is_ranked(&input_port, "d"));
+    }
+
+    #[test]
+    fn test_links_status_recorded_on_change() {
+        let (mut input_port, _) = new_port_a_faster_than_b(QuotaErrorRule::new());
+        input_port.set_proxy_enabled(true);
+        input_port.set_user_request_start(false);
+        let status = input_port.links_status().clone();
+        assert_eq!(status.state(), WorkdirState::Down);
+        assert_eq!(status.cause(), "localnet not started");
+
+        // Re-evaluated without change, so still since the same time.
+        std::thread::sleep(Duration::from_millis(5));
+        input_port.update_links_status();
+        assert_eq!(input_port.links_status(), &status);
+
+        // Recorded when started (not when the status is read).
+        input_port.set_user_request_start(true);
+        assert_eq!(input_port.links_status().state(), WorkdirState::Ok);
+        assert!(input_port.links_status().since() > status.since());
+        assert_eq!(input_port.links_reason(), None);
+    }
+
+    #[test]
+    fn test_weighted_distribution() {
+        let config = WorkdirUserConfig::new();
+        let mut input_port = InputPort::new(0, "localnet".to_string(), &config);
+        let t0 = EpochTimestamp::now() + Duration::from_millis(1);
+        for (alias, latency_ms) in [("fast", 10), ("medium", 40), ("slow", 120)] {
+            let mut link = Link::new(alias.to_string(), format!("http://{}", alias));
+            link.max_per_secs = Some(100);
+            input_port.add_target_server(&link);
+            let idx = get_idx(&input_port, alias);
+            let stats = &mut input_port.target_servers.get_mut(idx).unwrap().stats;
+            stats.handle_latency_report(t0, latency_ms * 1000);
+        }
+
+        // "best" is the default (no weights).
+        input_port.update_selection_vectors();
+        assert!(input_port.selection_weights.is_empty());
+
+        input_port.set_proxy_distribution(ProxyDistribution::Weighted);
+        input_port.update_selection_vectors();
+        let weight = |input_port: &InputPort, alias: &str| -> Option<f64> {
+            let idx = get_idx(input_port, alias);
+            input_port
+                .selection_weights
+                .iter()
+                .find(|(i, _)| *i == idx)
+                .map(|(_, weight)| *weight)
+        };
+        let fast = weight(&input_port, "fast").unwrap();
+        let medium = weight(&input_port, "medium").unwrap();
+        let slow = weight(&input_port, "slow").unwrap();
+        assert!(fast > medium && medium > slow);
+        assert!((fast + medium + slow - 1.0).abs() < 1e-9);
+        assert!((fast / slow - 12.0).abs() < 1e-9);
+
+        // Every link gets some of the traffic.
+        let mut first_attempts = HashMap::new();
+        for _ in 0..300 {
+            let mut targets = Vec::new();
+            input_port.get_best_target_servers(&mut targets, &EpochTimestamp::now(), None);
+            assert_eq!(targets.len(), 3);
+            *first_attempts.entry(targets[0].0).or_insert(0) += 1;
+        }
+        assert_eq!(first_attempts.len(), 3);
+
+        // A link near its rate limit is avoided until its load goes down.
+        let fast_idx = get_idx(&input_port, "fast");
+        let fast_server = input_port.target_servers.get_mut(fast_idx).unwrap();
+        fast_server.stats.set_load(95.0, 95.0 * 60.0);
+        assert!(fast_server.rate_limit_headroom() < RATE_LIMIT_MIN_HEADROOM);
+        input_port.update_selection_weights();
+        assert_eq!(weight(&input_port, "fast"), None);
+        assert!(
+            (weight(&input_port, "medium").unwrap() / weight(&input_port, "slow").unwrap() - 3.0)
+                .abs()
+                < 1e-9
+        );
+
+        let fast_server = input_port.target_servers.get_mut(fast_idx).unwrap();
+        fast_server.stats.set_load(50.0, 50.0 * 60.0);
+        input_port.update_selection_weights();
+        assert!(weight(&input_port, "fast").is_some());
+
+        // Same when most of its daily quota is used.
+        let fast_server = input_port.target_servers.get_mut(fast_idx).unwrap();
+        let mut link = fast_server.get_config().clone();
+        link.max_per_day = Some(100_000);
+        fast_server.set_config(link);
+        fast_server.stats.set_day_count(95_000);
+        input_port.update_selection_weights();
+        assert_eq!(weight(&input_port, "fast"), None);
+    }
+
+    #[test]
+    fn test_throttled_link_not_selected() {
+        let (mut input_port, _) = new_port_a_faster_than_b(QuotaErrorRule::new());
+        let now = EpochTimestamp::now();
+        let a_idx = get_idx(&input_port, "a");
+        input_port
+            .target_servers
+            .get_mut(a_idx)
+            .unwrap()
+            .stats
+            .handle_throttled(now + Duration::from_secs(2));
+
+        // Still healthy and in the selection_vectors, but skipped until the window ends.
+        let a_stats = &input_port.target_servers.get(a_idx).unwrap().stats;
+        assert!(a_stats.is_healthy());
+        assert_eq!(a_stats.throttle_count(), 1);
+        assert!(input_port
+            .selection_vectors
+            .iter()
+            .flatten()
+            .any(|&idx| idx == a_idx));
+        let mut targets = Vec::new();
+        input_port.get_best_target_servers(&mut targets, &now, None);
+        let aliases: Vec<String> = targets
+            .iter()
+            .map(|(idx, _)| input_port.target_servers.get(*idx).unwrap().alias())
+            .collect();
+        assert_eq!(aliases, vec!["b".to_string()]);
+
+        let mut targets = Vec::new();
+        let later = now + Duration::from_secs(3);
+        input_port.get_best_target_servers(&mut targets, &later, None);
+        assert_eq!(targets.first().map(|(idx, _)| *idx), Some(a_idx));
+
+        // A shorter window reported afterward does not shorten it.
+        let a_stats = &mut input_port.target_servers.get_mut(a_idx).unwrap().stats;
+        a_stats.handle_throttled(now + Duration::from_secs(1));
+        assert_eq!(
+            a_stats.throttled_until(&now),
+            Some(now + Duration::from_secs(2))
+        );
+        assert_eq!(a_stats.throttled_until(&later), None);
+    }
+
+    #[test]
+    fn test_canary_selection() {
+        let (mut input_port, t0) = new_port_a_faster_than_b(QuotaErrorRule::new());
+        // The fastest link, but a canary.
+        let mut canary = Link::new("c".to_string(), "http://c".to_string());
+        canary.canary_pct = Some(10);
+        input_port.add_target_server(&canary);
+        let c_idx = get_idx(&input_port, "c");
+        input_port
+            .target_servers
+            .get_mut(c_idx)
+            .unwrap()
+            .stats
+            .handle_latency_report(t0, 500);
+        input_port.update_selection_vectors();
+        assert_eq!(input_port.selection_canaries, vec![(c_idx, 10)]);
+
+        let now = EpochTimestamp::now();
+        let select = |input_port: &InputPort, request_id: Option<&str>| {
+            let mut targets = Vec::new();
+            input_port.get_best_target_servers(&mut targets, &now, request_id);
+            targets
+                .iter()
+                .map(|(idx, _)| input_port.target_servers.get(*idx).unwrap().alias())
+                .collect::<Vec<String>>()
+        };
+
+        // About 10% of the requests, the others on the normal selection.
+        let mut canary_count = 0;
+        for i in 0..1000 {
+            let request_id = format!("req-{}", i);
+            let aliases = select(&input_port, Some(&request_id));
+            if aliases[0] == "c" {
+                canary_count += 1;
+                assert_eq!(aliases, ["c", "a", "b"]);
+            } else {
+                assert_eq!(aliases, ["a", "b", "c"]);
+            }
+            // Same pick for the same request id.
+            assert_eq!(select(&input_port, Some(&request_id)), aliases);
+        }
+        assert!((70..=130).contains(&canary_count), "{}", canary_count);
+        assert_eq!(select(&input_port, None), ["a", "b", "c"]);
+
+        // The only link available.
+        for alias in ["a", "b"] {
+            let idx = get_idx(&input_port, alias);
+            let stats = &mut input_port.target_servers.get_mut(idx).unwrap().stats;
+            stats.handle_throttled(now + Duration::from_secs(2));
+        }
+        assert_eq!(select(&input_port, None), ["c"]);
+    }
+}
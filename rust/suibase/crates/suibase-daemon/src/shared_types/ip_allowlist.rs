@@ -0,0 +1,291 @@
+// Source IP allowlist of a proxy port (see "proxy_allowed_ips" in suibase.yaml).
+//
+// Each entry is a CIDR ("10.0.0.0/8", "fd00::/8") or a single address (same as
+// a /32 or /128). IPv4-mapped IPv6 addresses ("::ffff:10.1.2.3") are matched as
+// their IPv4 equivalent.
+//
+// A loopback peer is always allowed: the daemon sends its own health checks to
+// the proxy on localhost.
+//
+// The X-Forwarded-For header is ignored unless trust_forwarded is set, and then
+// only when the peer is a trusted reverse proxy (loopback or in the allowlist).
+// Only its rightmost address is used: it is appended by that proxy, all the
+// others are given by the client and trivially spoofed.
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::HeaderMap;
+
+pub const HEADER_FORWARDED_FOR: &str = "x-forwarded-for";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr, // Host bits cleared.
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (addr_str, prefix_str) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = addr_str
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid IP address '{}'", addr_str))?;
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match prefix_str {
+            Some(prefix) => match prefix.parse::<u8>() {
+                Ok(len) if len <= max_len => len,
+                _ => return Err(format!("invalid prefix length '/{}'", prefix)),
+            },
+            None => max_len,
+        };
+        // An IPv4-mapped network is kept as its IPv4 equivalent.
+        let (addr, prefix_len) = match normalize(addr) {
+            IpAddr::V4(v4) if addr.is_ipv6() => {
+                if prefix_len < 96 {
+                    return Err(format!("invalid prefix length for '{}'", addr_str));
+                }
+                (IpAddr::V4(v4), prefix_len - 96)
+            }
+            normalized => (normalized, prefix_len),
+        };
+        Ok(Self {
+            addr: mask(addr, prefix_len),
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = normalize(*ip);
+        match (self.addr, ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                mask(ip, self.prefix_len) == self.addr
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl std::fmt::Debug for IpCidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => ip,
+        },
+        IpAddr::V4(_) => ip,
+    }
+}
+
+fn mask(ip: IpAddr, prefix_len: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4);
+            let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+            IpAddr::V4((bits & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+            IpAddr::V6((bits & mask).into())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyAllowlist {
+    cidrs: Vec<IpCidr>,
+    trust_forwarded: bool,
+}
+
+impl ProxyAllowlist {
+    pub fn new(cidrs: Vec<IpCidr>, trust_forwarded: bool) -> Self {
+        Self {
+            cidrs,
+            trust_forwarded,
+        }
+    }
+
+    pub fn cidrs(&self) -> &[IpCidr] {
+        &self.cidrs
+    }
+
+    pub fn trust_forwarded(&self) -> bool {
+        self.trust_forwarded
+    }
+
+    // The address to check for a request received from 'peer' (the forwarded
+    // one when there is a valid one from a trusted proxy).
+    pub fn client_ip(&self, peer: &SocketAddr, headers: &HeaderMap) -> IpAddr {
+        self.forwarded_ip(peer, headers).unwrap_or(peer.ip())
+    }
+
+    // The loopback exemption applies to the peer only, never to a forwarded address.
+    pub fn is_allowed(&self, peer: &SocketAddr, headers: &HeaderMap) -> bool {
+        match self.forwarded_ip(peer, headers) {
+            Some(ip) => self.contains(&ip),
+            None => normalize(peer.ip()).is_loopback() || self.contains(&peer.ip()),
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    // The rightmost X-Forwarded-For address, when trust_forwarded and the peer is
+    // a trusted proxy.
+    fn forwarded_ip(&self, peer: &SocketAddr, headers: &HeaderMap) -> Option<IpAddr> {
+        let peer_ip = peer.ip();
+        if !self.trust_forwarded || !(normalize(peer_ip).is_loopback() || self.contains(&peer_ip)) {
+            return None;
+        }
+        headers
+            .get_all(HEADER_FORWARDED_FOR)
+            .iter()
+            .last()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|last| last.trim().parse::<IpAddr>().ok())
+    }
+}
+
+impl std::fmt::Display for ProxyAllowlist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cidrs: Vec<String> = self.cidrs.iter().map(|c| c.to_string()).collect();
+        write!(
+            f,
+            "[{}] (trust_forwarded: {})",
+            cidrs.join(", "),
+            self.trust_forwarded
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn allowlist(cidrs: &[&str], trust_forwarded: bool) -> ProxyAllowlist {
+        let cidrs = cidrs.iter().map(|s| IpCidr::parse(s).unwrap()).collect();
+        ProxyAllowlist::new(cidrs, trust_forwarded)
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn peer(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn forwarded_for(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_FORWARDED_FOR, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert_eq!(
+            IpCidr::parse("10.1.2.3/8").unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(
+            IpCidr::parse(" 192.168.1.5 ").unwrap().to_string(),
+            "192.168.1.5/32"
+        );
+        assert_eq!(IpCidr::parse("0.0.0.0/0").unwrap().to_string(), "0.0.0.0/0");
+        assert_eq!(
+            IpCidr::parse("fd00:1::1/16").unwrap().to_string(),
+            "fd00::/16"
+        );
+        assert_eq!(IpCidr::parse("::1").unwrap().to_string(), "::1/128");
+        assert_eq!(
+            IpCidr::parse("::ffff:10.1.2.3/120").unwrap().to_string(),
+            "10.1.2.0/24"
+        );
+
+        assert!(IpCidr::parse("10.0.0.0/33").is_err());
+        assert!(IpCidr::parse("fd00::/129").is_err());
+        assert!(IpCidr::parse("10.0.0/8").is_err());
+        assert!(IpCidr::parse("10.0.0.0/").is_err());
+        assert!(IpCidr::parse("::ffff:10.1.2.3/64").is_err());
+        assert!(IpCidr::parse("localhost").is_err());
+    }
+
+    #[test]
+    fn test_allowlist_match() {
+        let list = allowlist(&["10.0.0.0/8", "192.168.1.5", "fd00::/8"], false);
+        assert!(list.contains(&ip("10.255.0.1")));
+        assert!(list.contains(&ip("192.168.1.5")));
+        assert!(list.contains(&ip("fd12:3456::1")));
+        assert!(list.contains(&ip("::ffff:10.1.2.3")));
+        assert!(!list.contains(&ip("11.0.0.1")));
+        assert!(!list.contains(&ip("192.168.1.6")));
+        assert!(!list.contains(&ip("fe80::1")));
+
+        // Loopback peer always allowed, even with an empty list.
+        let empty = allowlist(&[], false);
+        let headers = HeaderMap::new();
+        assert!(empty.is_allowed(&peer("127.0.0.1:4000"), &headers));
+        assert!(empty.is_allowed(&peer("[::1]:4000"), &headers));
+        assert!(!empty.is_allowed(&peer("10.0.0.1:4000"), &headers));
+    }
+
+    #[test]
+    fn test_client_ip_forwarded() {
+        let proxy = peer("127.0.0.1:4000");
+        let headers = forwarded_for("192.168.1.5, 10.0.0.1");
+
+        // Ignored when not trusted.
+        let list = allowlist(&["10.0.0.0/8"], false);
+        assert_eq!(list.client_ip(&proxy, &headers), ip("127.0.0.1"));
+
+        // The rightmost address is the one appended by the proxy.
+        let list = allowlist(&["10.0.0.0/8"], true);
+        assert_eq!(list.client_ip(&proxy, &headers), ip("10.0.0.1"));
+        assert!(list.is_allowed(&proxy, &headers));
+        let headers = forwarded_for("10.0.0.1, 192.168.1.5");
+        assert_eq!(list.client_ip(&proxy, &headers), ip("192.168.1.5"));
+        assert!(!list.is_allowed(&proxy, &headers));
+
+        let headers = forwarded_for("garbage");
+        assert_eq!(list.client_ip(&proxy, &headers), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_for() {
+        let list = allowlist(&["10.0.0.0/8"], true);
+
+        // A remote client cannot claim to be localhost or an allowed address.
+        let remote = peer("203.0.113.7:4000");
+        for value in ["127.0.0.1", "::1", "10.0.0.1", "127.0.0.1, 10.0.0.1"] {
+            let headers = forwarded_for(value);
+            assert_eq!(list.client_ip(&remote, &headers), ip("203.0.113.7"));
+            assert!(!list.is_allowed(&remote, &headers));
+        }
+
+        // Nor through a trusted proxy: the loopback exemption is for the peer only.
+        let proxy = peer("10.0.0.2:4000");
+        assert!(!list.is_allowed(&proxy, &forwarded_for("127.0.0.1")));
+        assert!(!list.is_allowed(&peer("127.0.0.1:4000"), &forwarded_for("::1")));
+        assert!(list.is_allowed(&proxy, &HeaderMap::new()));
+    }
+}
@@ -0,0 +1,138 @@
+// Long running operations started through the JSON-RPC API.
+//
+// The method returns right away with the status of the new job (e.g. snapshotLocalnet)
+// and the caller polls getJobStatus until the state is no longer "RUNNING".
+//
+// Only the MAX_JOBS_KEPT most recent jobs are kept (in memory only).
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+
+use crate::api::JobStatus;
+
+pub const MAX_JOBS_KEPT: usize = 20;
+
+pub const JOB_STATE_RUNNING: &str = "RUNNING";
+pub const JOB_STATE_DONE: &str = "DONE";
+pub const JOB_STATE_FAILED: &str = "FAILED";
+
+#[derive(Debug, Default)]
+pub struct GlobalsJobsST {
+    jobs: BTreeMap<u64, JobStatus>, // Key is the job_id (increasing).
+    last_job_id: u64,
+}
+
+impl GlobalsJobsST {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // None when a job is already running for this workdir (one at the time).
+    pub fn start(&mut self, method: &str, workdir: &str) -> Option<JobStatus> {
+        if self
+            .jobs
+            .values()
+            .any(|job| job.workdir == workdir && job.state == JOB_STATE_RUNNING)
+        {
+            return None;
+        }
+
+        self.last_job_id += 1;
+        let job = JobStatus {
+            job_id: self.last_job_id,
+            method: method.to_string(),
+            workdir: workdir.to_string(),
+            state: JOB_STATE_RUNNING.to_string(),
+            progress: "Starting".to_string(),
+            info: None,
+            started_at: Utc::now().to_rfc3339(),
+            ended_at: None,
+        };
+        self.jobs.insert(job.job_id, job.clone());
+
+        // Forget the oldest completed jobs.
+        while self.jobs.len() > MAX_JOBS_KEPT {
+            let oldest = self
+                .jobs
+                .values()
+                .find(|job| job.state != JOB_STATE_RUNNING)
+                .map(|job| job.job_id);
+            match oldest {
+                Some(job_id) => self.jobs.remove(&job_id),
+                None => break,
+            };
+        }
+        Some(job)
+    }
+
+    pub fn set_progress(&mut self, job_id: u64, progress: &str) {
+        if let Some(job) = self.jobs.get_mut(&job_id) {
+            job.progress = progress.to_string();
+        }
+    }
+
+    // The Err string is the user facing reason of the failure.
+    pub fn finish(&mut self, job_id: u64, result: Result<String, String>) {
+        if let Some(job) = self.jobs.get_mut(&job_id) {
+            match result {
+                Ok(info) => {
+                    job.state = JOB_STATE_DONE.to_string();
+                    job.info = Some(info);
+                }
+                Err(e) => {
+                    job.state = JOB_STATE_FAILED.to_string();
+                    job.info = Some(e);
+                }
+            }
+            job.ended_at = Some(Utc::now().to_rfc3339());
+        }
+    }
+
+    pub fn get(&self, job_id: u64) -> Option<JobStatus> {
+        self.jobs.get(&job_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_lifecycle() {
+        let mut jobs = GlobalsJobsST::new();
+        let job = jobs.start("snapshotLocalnet", "localnet").unwrap();
+        assert_eq!(job.state, JOB_STATE_RUNNING);
+
+        // One running job per workdir.
+        assert!(jobs.start("restoreLocalnet", "localnet").is_none());
+        assert!(jobs.start("mergeGasCoins", "testnet").is_some());
+
+        jobs.set_progress(job.job_id, "Copying");
+        assert_eq!(jobs.get(job.job_id).unwrap().progress, "Copying");
+
+        jobs.finish(job.job_id, Err("disk full".to_string()));
+        let failed = jobs.get(job.job_id).unwrap();
+        assert_eq!(failed.state, JOB_STATE_FAILED);
+        assert_eq!(failed.info.as_deref(), Some("disk full"));
+        assert!(failed.ended_at.is_some());
+
+        let next = jobs.start("restoreLocalnet", "localnet").unwrap();
+        assert!(next.job_id > job.job_id);
+        jobs.finish(next.job_id, Ok("Restored".to_string()));
+        assert_eq!(jobs.get(next.job_id).unwrap().state, JOB_STATE_DONE);
+        assert!(jobs.get(12345).is_none());
+    }
+
+    #[test]
+    fn test_jobs_max_kept() {
+        let mut jobs = GlobalsJobsST::new();
+        let first = jobs.start("snapshotLocalnet", "localnet").unwrap();
+        jobs.finish(first.job_id, Ok(String::new()));
+        for _ in 0..MAX_JOBS_KEPT {
+            let job = jobs.start("snapshotLocalnet", "localnet").unwrap();
+            jobs.finish(job.job_id, Ok(String::new()));
+        }
+        assert!(jobs.get(first.job_id).is_none());
+        assert!(jobs.get(first.job_id + 1).is_some());
+    }
+}
@@ -0,0 +1,83 @@
+// HTTP client toward the links of an input port.
+//
+// One per InputPort, shared by everything sending to its links:
+//   - the ProxyServer, for the user traffic.
+//   - the health checks, which the RequestWorker sends to the ProxyServer itself (with
+//     the X-SBSD-SERVER-HC marker), so they are built and sent like any user request.
+//   - the NetworkMonitor metrics scrapes and clock skew probes.
+//
+// Sharing the client (cheap to clone) also shares its connection pool, so a link
+// reported OK was reached with the same TLS/ALPN settings and connections as the
+// user traffic.
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap, HeaderValue, Method};
+use hyper::body::Bytes;
+
+// Trace id of a request. Taken from the client (or generated), forwarded to the
+// RPC server and returned to the client in the response.
+pub const HEADER_REQUEST_ID: &str = "x-request-id";
+
+// The proxy sets its own per request (see proxy_timeouts.rs).
+const LINK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct LinkClient {
+    client: reqwest::Client,
+}
+
+impl LinkClient {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(LINK_REQUEST_TIMEOUT)
+            .no_proxy()
+            .connection_verbose(true)
+            .build()
+            .unwrap_or_else(|e| {
+                log::error!("link client build failed ({}), using defaults", e);
+                reqwest::Client::new()
+            });
+        Self { client }
+    }
+
+    // Headers sent to a link, from the ones received by the proxy (the X-SBSD-* ones
+    // already consumed). 'request_id' is the same toward every link attempted.
+    //
+    // The client always advertises gzip/deflate to the link and decompresses the
+    // response, so the Accept-Encoding of the proxy client is not forwarded.
+    pub fn upstream_headers(mut headers: HeaderMap, request_id: &str) -> HeaderMap {
+        headers.remove(header::HOST); // Replaced with the one of the link.
+        headers.remove(header::ACCEPT_ENCODING);
+        if let Ok(request_id) = HeaderValue::from_str(request_id) {
+            headers.insert(HEADER_REQUEST_ID, request_id);
+        }
+        headers
+    }
+
+    pub fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, uri)
+            .headers(headers.clone())
+            .body(body.clone())
+    }
+
+    pub fn get(&self, uri: &str) -> reqwest::RequestBuilder {
+        self.client.get(uri)
+    }
+
+    pub fn post(&self, uri: &str) -> reqwest::RequestBuilder {
+        self.client.post(uri)
+    }
+}
+
+impl Default for LinkClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
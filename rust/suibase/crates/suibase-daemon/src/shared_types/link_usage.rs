@@ -0,0 +1,246 @@
+// Monthly request count of each link, for the RPC plans billed monthly (see
+// "monthly_budget" in suibase.yaml and getUsageReport).
+//
+// Counted by the NetworkMonitor for every proxied request (not the health checks),
+// in the UTC month of the request time. A month is never missed nor merged with the
+// next one, even when the daemon was down at the boundary.
+//
+// Saved to <workdir>/.state/link-usage.json with the proxy stats (every
+// PROXY_STATS_SAVE_INTERVAL and on a graceful shutdown), and loaded when the InputPort
+// of the workdir is created. Unlike the proxy stats, always persisted.
+//
+// A log warning and a "link_budget_alert" webhook are sent once per threshold per
+// month. The thresholds alerted are saved, so a restart does not repeat them.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::GlobalsProxyMT;
+
+pub const LINK_USAGE_FILENAME: &str = "link-usage.json";
+
+// % of the monthly_budget, in increasing order.
+pub const BUDGET_ALERT_THRESHOLDS_PCT: [u32; 2] = [80, 100];
+
+// Months kept by link (the older ones are dropped).
+const LINK_USAGE_MONTHS_KEPT: usize = 13;
+
+// Increment on any incompatible change of the file.
+const LINK_USAGE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkMonthUsage {
+    pub requests: u64,
+    // Highest of the BUDGET_ALERT_THRESHOLDS_PCT alerted in this month (0 when none).
+    pub alerted_pct: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LinkUsageFile {
+    version: u32,
+    saved_at: String, // RFC 3339
+    links: BTreeMap<String, BTreeMap<String, LinkMonthUsage>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkUsage {
+    // Alias -> "YYYY-MM" -> usage.
+    links: BTreeMap<String, BTreeMap<String, LinkMonthUsage>>,
+}
+
+// e.g. "2024-05"
+pub fn usage_month(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m").to_string()
+}
+
+pub fn is_valid_usage_month(month: &str) -> bool {
+    chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_ok()
+        && month.len() == 7
+}
+
+// Highest of the BUDGET_ALERT_THRESHOLDS_PCT reached (None when below all).
+pub fn budget_threshold_reached(requests: u64, budget: u64) -> Option<u32> {
+    BUDGET_ALERT_THRESHOLDS_PCT
+        .iter()
+        .rev()
+        .find(|pct| requests.saturating_mul(100) >= budget.saturating_mul(**pct as u64))
+        .copied()
+}
+
+impl LinkUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Count one request. Returns the threshold to alert, when one is newly reached.
+    pub fn record(&mut self, alias: &str, month: &str, budget: Option<u64>) -> Option<u32> {
+        let months = self.links.entry(alias.to_string()).or_default();
+        if !months.contains_key(month) {
+            months.insert(month.to_string(), LinkMonthUsage::default());
+            while months.len() > LINK_USAGE_MONTHS_KEPT {
+                months.pop_first();
+            }
+        }
+        let usage = months.get_mut(month)?;
+        usage.requests += 1;
+
+        let reached = budget_threshold_reached(usage.requests, budget?)?;
+        if reached <= usage.alerted_pct {
+            return None;
+        }
+        usage.alerted_pct = reached;
+        Some(reached)
+    }
+
+    pub fn get(&self, alias: &str, month: &str) -> LinkMonthUsage {
+        self.links
+            .get(alias)
+            .and_then(|months| months.get(month))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Every month having a count, most recent first.
+    pub fn months(&self) -> Vec<String> {
+        let mut months: Vec<String> = self
+            .links
+            .values()
+            .flat_map(|months| months.keys().cloned())
+            .collect();
+        months.sort_unstable_by(|a, b| b.cmp(a));
+        months.dedup();
+        months
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &String> {
+        self.links.keys()
+    }
+
+    // None when there is no file. Also None (with a warning) when not usable.
+    pub fn load_from_file(path: &Path) -> Option<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("{:?} ignored ({})", path, e);
+                return None;
+            }
+        };
+        match serde_json::from_str::<LinkUsageFile>(&contents) {
+            Ok(file) if file.version == LINK_USAGE_VERSION => Some(Self { links: file.links }),
+            Ok(file) => {
+                log::warn!(
+                    "{:?} ignored (version {}, expected {})",
+                    path,
+                    file.version,
+                    LINK_USAGE_VERSION
+                );
+                None
+            }
+            Err(e) => {
+                log::warn!("{:?} ignored ({})", path, e);
+                None
+            }
+        }
+    }
+
+    // Written to a temporary file first, so a crash never leaves a truncated file.
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = LinkUsageFile {
+            version: LINK_USAGE_VERSION,
+            saved_at: Utc::now().to_rfc3339(),
+            links: self.links.clone(),
+        };
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(&file)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+// Save the usage of every InputPort having a link_usage_file. The files are written
+// after releasing the globals lock.
+pub async fn save_link_usage(globals: &GlobalsProxyMT) {
+    let to_save: Vec<(PathBuf, LinkUsage)> = {
+        let globals_guard = globals.read().await;
+        globals_guard
+            .input_ports
+            .iter()
+            .filter_map(|(_, input_port)| {
+                let path = input_port.link_usage_file()?;
+                Some((path.clone(), input_port.link_usage().clone()))
+            })
+            .collect()
+    };
+    for (path, usage) in to_save {
+        if let Err(e) = usage.save_to_file(&path) {
+            log::warn!("{:?} not saved ({})", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_alerts() {
+        let mut usage = LinkUsage::new();
+
+        // Budget of 5: alerts at the 4th and 5th request, only once per threshold.
+        let alerts: Vec<Option<u32>> = (0..7)
+            .map(|_| usage.record("paid", "2024-05", Some(5)))
+            .collect();
+        assert_eq!(
+            alerts,
+            vec![None, None, None, Some(80), Some(100), None, None]
+        );
+        assert_eq!(usage.get("paid", "2024-05").requests, 7);
+        assert_eq!(usage.get("paid", "2024-05").alerted_pct, 100);
+
+        // A new month starts over (even when the previous was never "closed").
+        assert_eq!(usage.record("paid", "2024-06", Some(5)), None);
+        assert_eq!(usage.get("paid", "2024-06").requests, 1);
+        assert_eq!(usage.get("paid", "2024-05").requests, 7);
+
+        // No budget, no alert. A budget crossed in one step alerts only the highest.
+        assert_eq!(usage.record("free", "2024-06", None), None);
+        assert_eq!(usage.record("tiny", "2024-06", Some(1)), Some(100));
+        assert_eq!(usage.months(), vec!["2024-06", "2024-05"]);
+
+        // The alerted thresholds survive a restart.
+        let dir = std::env::temp_dir().join(format!("sbsd-link-usage-{}", std::process::id()));
+        let path = dir.join(LINK_USAGE_FILENAME);
+        assert!(LinkUsage::load_from_file(&path).is_none());
+        usage.save_to_file(&path).unwrap();
+        let mut loaded = LinkUsage::load_from_file(&path).unwrap();
+        assert_eq!(loaded, usage);
+        assert_eq!(loaded.record("tiny", "2024-06", Some(1)), None);
+        std::fs::write(&path, "{\"version\":1,").unwrap();
+        assert!(LinkUsage::load_from_file(&path).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // Only the most recent months are kept.
+        for month in 1..=12 {
+            usage.record("paid", &format!("2025-{:02}", month), None);
+        }
+        assert_eq!(usage.get("paid", "2024-05").requests, 0);
+        assert_eq!(usage.get("paid", "2024-06").requests, 1);
+    }
+
+    #[test]
+    fn test_usage_month() {
+        let time = DateTime::parse_from_rfc3339("2024-01-31T23:59:59Z").unwrap();
+        assert_eq!(usage_month(time.with_timezone(&Utc)), "2024-01");
+        assert!(is_valid_usage_month("2024-12"));
+        assert!(!is_valid_usage_month("2024-13"));
+        assert!(!is_valid_usage_month("2024-1"));
+        assert!(!is_valid_usage_month("latest"));
+    }
+}
@@ -0,0 +1,276 @@
+// Named copies of the localnet chain state (see snapshotLocalnet and restoreLocalnet).
+//
+// Stored in workdirs/localnet/snapshots/{name}:
+//
+//   snapshot.yaml      Metadata (sui version, creation time).
+//   config/            Copy of the 'config' symlink target (network config and databases).
+//   published-data/    Copy of the publication records, when present.
+//
+// The copies are done only while the localnet is stopped (the databases are
+// not consistent while the sui process runs).
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+
+use crate::api::LocalnetSnapshotInfo;
+
+pub const SNAPSHOTS_DIRNAME: &str = "snapshots";
+pub const SNAPSHOT_METADATA_FILENAME: &str = "snapshot.yaml";
+
+const SNAPSHOT_CONFIG_DIR: &str = "config";
+const SNAPSHOT_PUBLISHED_DATA_DIR: &str = "published-data";
+
+// Names are used as a directory name, so keep them simple.
+pub fn is_valid_snapshot_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// "sui 1.30.1-abc123" -> "1.30.1-abc123" (output of "lsui --version").
+pub fn parse_sui_version_output(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("sui"), Some(version)) => Some(version.to_string()),
+            _ => None,
+        }
+    })
+}
+
+// The error message is user facing (see restoreLocalnet).
+pub fn check_restore_version(
+    snapshot_version: Option<&str>,
+    current_version: Option<&str>,
+    force: bool,
+) -> Result<(), String> {
+    if force || snapshot_version == current_version {
+        return Ok(());
+    }
+    Err(format!(
+        "snapshot created with sui {} but localnet is now sui {} (use force to restore anyway)",
+        snapshot_version.unwrap_or("unknown"),
+        current_version.unwrap_or("unknown")
+    ))
+}
+
+pub fn snapshots_path(workdir_path: &Path) -> PathBuf {
+    workdir_path.join(SNAPSHOTS_DIRNAME)
+}
+
+fn metadata_to_yaml(info: &LocalnetSnapshotInfo) -> String {
+    let mut yaml = String::from("# Generated by the suibase-daemon. Do not edit.\n");
+    yaml.push_str(&format!("name: {}\n", info.name));
+    if let Some(sui_version) = &info.sui_version {
+        yaml.push_str(&format!("sui_version: \"{}\"\n", sui_version));
+    }
+    yaml.push_str(&format!("created_at: \"{}\"\n", info.created_at));
+    yaml
+}
+
+fn read_metadata(snapshot_path: &Path) -> Result<LocalnetSnapshotInfo> {
+    let contents = std::fs::read_to_string(snapshot_path.join(SNAPSHOT_METADATA_FILENAME))?;
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&contents)?;
+    let name = yaml["name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing name in {}", SNAPSHOT_METADATA_FILENAME))?;
+    Ok(LocalnetSnapshotInfo {
+        name: name.to_string(),
+        sui_version: yaml["sui_version"].as_str().map(|v| v.to_string()),
+        created_at: yaml["created_at"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+// Recursive copy. Symlinks are re-created as-is (not followed).
+pub fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let dst_path = dst.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else if file_type.is_symlink() {
+            let target = std::fs::read_link(entry.path())?;
+            std::os::unix::fs::symlink(target, &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), &dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+// Where the 'config' symlink of the workdir points to (e.g. config-default).
+fn config_target_path(workdir_path: &Path) -> Result<PathBuf> {
+    let config_path = workdir_path.join(SNAPSHOT_CONFIG_DIR);
+    std::fs::canonicalize(&config_path)
+        .map_err(|e| anyhow!("{} not found: {}", config_path.display(), e))
+}
+
+pub fn get_snapshot(workdir_path: &Path, name: &str) -> Result<LocalnetSnapshotInfo> {
+    let snapshot_path = snapshots_path(workdir_path).join(name);
+    if !snapshot_path.is_dir() {
+        bail!("snapshot [{}] not found", name);
+    }
+    read_metadata(&snapshot_path)
+}
+
+pub fn list_snapshots(workdir_path: &Path) -> Vec<LocalnetSnapshotInfo> {
+    let mut snapshots = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(snapshots_path(workdir_path)) {
+        for entry in entries.flatten() {
+            // Skip the incomplete ones (e.g. "{name}.tmp" of a snapshot in progress).
+            let is_named = entry
+                .file_name()
+                .to_str()
+                .is_some_and(is_valid_snapshot_name);
+            if is_named && entry.path().is_dir() {
+                if let Ok(info) = read_metadata(&entry.path()) {
+                    snapshots.push(info);
+                }
+            }
+        }
+    }
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    snapshots
+}
+
+// Copy the chain state into a new snapshot. The localnet must be stopped.
+//
+// Written in "{name}.tmp" then renamed, so an interrupted copy is never listed.
+pub fn create_snapshot(
+    workdir_path: &Path,
+    name: &str,
+    sui_version: Option<String>,
+) -> Result<LocalnetSnapshotInfo> {
+    let snapshots_path = snapshots_path(workdir_path);
+    let snapshot_path = snapshots_path.join(name);
+    if snapshot_path.exists() {
+        bail!("snapshot [{}] already exists", name);
+    }
+    let tmp_path = snapshots_path.join(format!("{}.tmp", name));
+    if tmp_path.exists() {
+        std::fs::remove_dir_all(&tmp_path)?;
+    }
+
+    let info = LocalnetSnapshotInfo {
+        name: name.to_string(),
+        sui_version,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let result = (|| -> Result<()> {
+        copy_dir_all(
+            &config_target_path(workdir_path)?,
+            &tmp_path.join(SNAPSHOT_CONFIG_DIR),
+        )?;
+        let published_data_path = workdir_path.join(SNAPSHOT_PUBLISHED_DATA_DIR);
+        if published_data_path.is_dir() {
+            copy_dir_all(
+                &published_data_path,
+                &tmp_path.join(SNAPSHOT_PUBLISHED_DATA_DIR),
+            )?;
+        }
+        std::fs::write(
+            tmp_path.join(SNAPSHOT_METADATA_FILENAME),
+            metadata_to_yaml(&info),
+        )?;
+        std::fs::rename(&tmp_path, &snapshot_path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&tmp_path);
+    }
+    result.map(|_| info)
+}
+
+// Replace the chain state with the snapshot. The localnet must be stopped.
+pub fn restore_snapshot(workdir_path: &Path, name: &str) -> Result<()> {
+    let snapshot_path = snapshots_path(workdir_path).join(name);
+    read_metadata(&snapshot_path)?;
+
+    let config_path = config_target_path(workdir_path)?;
+    std::fs::remove_dir_all(&config_path)?;
+    copy_dir_all(&snapshot_path.join(SNAPSHOT_CONFIG_DIR), &config_path)?;
+
+    // Publications done after the snapshot are for packages that no longer exist.
+    let published_data_path = workdir_path.join(SNAPSHOT_PUBLISHED_DATA_DIR);
+    if published_data_path.exists() {
+        std::fs::remove_dir_all(&published_data_path)?;
+    }
+    let snapshot_published_data = snapshot_path.join(SNAPSHOT_PUBLISHED_DATA_DIR);
+    if snapshot_published_data.is_dir() {
+        copy_dir_all(&snapshot_published_data, &published_data_path)?;
+    }
+    Ok(())
+}
+
+pub fn delete_snapshot(workdir_path: &Path, name: &str) -> Result<()> {
+    let snapshot_path = snapshots_path(workdir_path).join(name);
+    if !snapshot_path.is_dir() {
+        bail!("snapshot [{}] not found", name);
+    }
+    std::fs::remove_dir_all(&snapshot_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_name_and_version() {
+        assert!(is_valid_snapshot_name("after-publish_2"));
+        assert!(!is_valid_snapshot_name(""));
+        assert!(!is_valid_snapshot_name("../localnet"));
+        assert!(!is_valid_snapshot_name("a.tmp"));
+        assert!(!is_valid_snapshot_name(&"a".repeat(65)));
+
+        assert_eq!(
+            parse_sui_version_output("sui 1.30.1-abc123\n"),
+            Some("1.30.1-abc123".to_string())
+        );
+        assert_eq!(parse_sui_version_output("Error: not found"), None);
+
+        assert!(check_restore_version(Some("1.30.1"), Some("1.30.1"), false).is_ok());
+        assert!(check_restore_version(Some("1.30.1"), Some("1.31.0"), false)
+            .unwrap_err()
+            .contains("force"));
+        assert!(check_restore_version(Some("1.30.1"), None, false).is_err());
+        assert!(check_restore_version(Some("1.30.1"), Some("1.31.0"), true).is_ok());
+    }
+
+    #[test]
+    fn test_snapshot_create_restore_delete() {
+        let workdir = std::env::temp_dir().join(format!("sbsd-snapshots-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&workdir);
+        let config_default = workdir.join("config-default");
+        std::fs::create_dir_all(config_default.join("authorities_db")).unwrap();
+        std::fs::write(config_default.join("authorities_db/state"), "v1").unwrap();
+        std::os::unix::fs::symlink(&config_default, workdir.join("config")).unwrap();
+
+        let info = create_snapshot(&workdir, "s1", Some("1.30.1".to_string())).unwrap();
+        assert!(create_snapshot(&workdir, "s1", None).is_err());
+        assert_eq!(list_snapshots(&workdir), vec![info.clone()]);
+        assert_eq!(get_snapshot(&workdir, "s1").unwrap(), info);
+
+        // Chain moves on, and a package gets published.
+        std::fs::write(config_default.join("authorities_db/state"), "v2").unwrap();
+        std::fs::create_dir_all(workdir.join("published-data/demo")).unwrap();
+
+        restore_snapshot(&workdir, "s1").unwrap();
+        let state = std::fs::read_to_string(config_default.join("authorities_db/state"));
+        assert_eq!(state.unwrap(), "v1");
+        assert!(workdir.join("config").is_symlink());
+        assert!(!workdir.join("published-data").exists());
+
+        delete_snapshot(&workdir, "s1").unwrap();
+        assert!(list_snapshots(&workdir).is_empty());
+        assert!(delete_snapshot(&workdir, "s1").is_err());
+        assert!(restore_snapshot(&workdir, "s1").is_err());
+
+        let _ = std::fs::remove_dir_all(&workdir);
+    }
+}
@@ -0,0 +1,300 @@
+// Scheduled maintenance windows of a link (see "maintenance" in suibase.yaml).
+//
+// A window starts at every minute matching its cron expression, and lasts
+// duration_mins. While in a window, the link is not selected and its failures
+// are not notified (the health checks continue, but less often).
+//
+// Supported cron subset (always UTC):
+//
+//     minute (0-59)  hour (0-23)  day-of-month (1-31)  month (1-12)  day-of-week (0-7)
+//
+// Each field is '*', a value, a range 'a-b', a step '*/n' (or 'a-b/n', 'a/n') or a
+// comma separated list of these. Months (JAN-DEC) and days of week (SUN-SAT) can be
+// named, Sunday is either 0 or 7.
+//
+// Like cron, when both day-of-month and day-of-week are restricted (not '*'), a day
+// matching either one is a match.
+use chrono::{DateTime, Datelike, Timelike};
+
+// Longest window supported. Bounds the search of the start of the current window.
+pub const MAINTENANCE_MAX_DURATION_MINS: u32 = 7 * 24 * 60;
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const DAY_OF_WEEK_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    spec: String,
+    // Bit 'n' set when the value 'n' matches.
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64, // Sunday is bit 0 only.
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    // The error describes the first problem found.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("has {} fields (expecting 5)", fields.len()));
+        }
+        let minutes = Self::parse_field(fields[0], "minute", 0, 59, &[])?;
+        let hours = Self::parse_field(fields[1], "hour", 0, 23, &[])?;
+        let days_of_month = Self::parse_field(fields[2], "day-of-month", 1, 31, &[])?;
+        let months = Self::parse_field(fields[3], "month", 1, 12, &MONTH_NAMES)?;
+        let mut days_of_week =
+            Self::parse_field(fields[4], "day-of-week", 0, 7, &DAY_OF_WEEK_NAMES)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            spec: fields.join(" "),
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            days_of_month_restricted: !fields[2].starts_with('*'),
+            days_of_week_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn parse_field(
+        field: &str,
+        name: &str,
+        min: u32,
+        max: u32,
+        names: &[&str],
+    ) -> Result<u64, String> {
+        let parse_value = |value: &str| -> Result<u32, String> {
+            let parsed = match value.parse::<u32>() {
+                Ok(parsed) => Some(parsed),
+                Err(_) => names
+                    .iter()
+                    .position(|name| name.eq_ignore_ascii_case(value))
+                    .map(|position| min + position as u32),
+            };
+            match parsed {
+                Some(parsed) if parsed >= min && parsed <= max => Ok(parsed),
+                _ => Err(format!(
+                    "{} {} not valid (expecting {} to {})",
+                    name, value, min, max
+                )),
+            }
+        };
+
+        let mut mask = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (range, Some(step)),
+                    _ => return Err(format!("{} step {} not valid", name, step)),
+                },
+                None => (part, None),
+            };
+            let (first, last) = if range == "*" {
+                (min, max)
+            } else if let Some((first, last)) = range.split_once('-') {
+                let (first, last) = (parse_value(first)?, parse_value(last)?);
+                if first > last {
+                    return Err(format!("{} range {} is reversed", name, range));
+                }
+                (first, last)
+            } else {
+                let first = parse_value(range)?;
+                // "a/n" is from 'a' to the max of the field.
+                (first, if step.is_some() { max } else { first })
+            };
+            for value in (first..=last).step_by(step.unwrap_or(1) as usize) {
+                mask |= 1 << value;
+            }
+        }
+        Ok(mask)
+    }
+
+    // True when the minute starting at 'secs' (since the UNIX epoch) matches.
+    pub fn matches(&self, secs: u64) -> bool {
+        let datetime = match DateTime::from_timestamp(secs as i64, 0) {
+            Some(datetime) => datetime,
+            None => return false,
+        };
+        let is_set = |mask: u64, value: u32| mask & (1 << value) != 0;
+        if !is_set(self.minutes, datetime.minute())
+            || !is_set(self.hours, datetime.hour())
+            || !is_set(self.months, datetime.month())
+        {
+            return false;
+        }
+        let day_of_month = is_set(self.days_of_month, datetime.day());
+        let day_of_week = is_set(self.days_of_week, datetime.weekday().num_days_from_sunday());
+        if self.days_of_month_restricted && self.days_of_week_restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub cron: CronSchedule,
+    pub duration_mins: u32, // 1 to MAINTENANCE_MAX_DURATION_MINS.
+}
+
+impl MaintenanceWindow {
+    // End (secs since the UNIX epoch) of the window in which 'now_secs' is, if any.
+    //
+    // When windows overlap, this is the end of the one that started last.
+    pub fn active_until(&self, now_secs: u64) -> Option<u64> {
+        let now_minute = now_secs - now_secs % 60;
+        for minutes_ago in 0..self.duration_mins as u64 {
+            let start = now_minute.checked_sub(minutes_ago * 60)?;
+            if self.cron.matches(start) {
+                return Some(start + self.duration_mins as u64 * 60);
+            }
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} mins)", self.cron, self.duration_mins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sunday 2023-01-01 00:00:00 UTC.
+    const SUNDAY: u64 = 1_672_531_200;
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+
+    fn window(cron: &str, duration_mins: u32) -> MaintenanceWindow {
+        MaintenanceWindow {
+            cron: CronSchedule::parse(cron).unwrap(),
+            duration_mins,
+        }
+    }
+
+    #[test]
+    fn test_cron_parse() {
+        let cron = CronSchedule::parse("0 2 * * SUN").unwrap();
+        assert_eq!(cron.minutes, 1);
+        assert_eq!(cron.hours, 1 << 2);
+        assert_eq!(cron.days_of_week, 1);
+        assert_eq!(cron.to_string(), "0 2 * * SUN");
+
+        let cron = CronSchedule::parse(" */15  1-3,22 1 jan-mar/2 0,7 ").unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.hours, 1 << 1 | 1 << 2 | 1 << 3 | 1 << 22);
+        assert_eq!(cron.days_of_month, 1 << 1);
+        assert_eq!(cron.months, 1 << 1 | 1 << 3);
+        assert_eq!(cron.days_of_week, 1);
+        assert_eq!(cron.to_string(), "*/15 1-3,22 1 jan-mar/2 0,7");
+
+        let cron = CronSchedule::parse("50/5 * * * MON-FRI").unwrap();
+        assert_eq!(cron.minutes, 1 << 50 | 1 << 55);
+        assert_eq!(cron.days_of_week, 0b0111110);
+
+        for bad in [
+            "",
+            "0 2 * *",
+            "0 2 * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "* * * * SUNDAY",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "*/x * * * *",
+            "1,,2 * * * *",
+        ] {
+            assert!(CronSchedule::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_cron_matches() {
+        let cron = CronSchedule::parse("0 2 * * SUN").unwrap();
+        assert!(cron.matches(SUNDAY + 2 * HOUR));
+        assert!(!cron.matches(SUNDAY + 2 * HOUR + 60));
+        assert!(!cron.matches(SUNDAY + DAY + 2 * HOUR));
+        assert!(cron.matches(SUNDAY + 7 * DAY + 2 * HOUR));
+
+        // Day-of-month OR day-of-week when both are restricted, AND otherwise.
+        let cron = CronSchedule::parse("0 0 15 * MON").unwrap();
+        assert!(cron.matches(SUNDAY + DAY)); // Monday January 2.
+        assert!(cron.matches(SUNDAY + 14 * DAY)); // Sunday January 15.
+        assert!(!cron.matches(SUNDAY + 2 * DAY));
+        let cron = CronSchedule::parse("0 0 */2 * *").unwrap();
+        assert!(cron.matches(SUNDAY)); // January 1.
+        assert!(!cron.matches(SUNDAY + DAY));
+
+        // February 29 of a leap year (2024), and the last day of the year.
+        let cron = CronSchedule::parse("30 23 29 FEB *").unwrap();
+        let feb_29 = 1_709_164_800; // 2024-02-29 00:00:00 UTC.
+        assert!(cron.matches(feb_29 + 23 * HOUR + 30 * 60));
+        assert!(!cron.matches(feb_29 - 365 * DAY + 23 * HOUR + 30 * 60));
+        let cron = CronSchedule::parse("59 23 31 12 *").unwrap();
+        assert!(cron.matches(SUNDAY - 60));
+    }
+
+    #[test]
+    fn test_window_active_until() {
+        let maintenance = window("0 2 * * SUN", 60);
+        let start = SUNDAY + 2 * HOUR;
+        assert_eq!(maintenance.active_until(start - 1), None);
+        assert_eq!(maintenance.active_until(start), Some(start + HOUR));
+        assert_eq!(
+            maintenance.active_until(start + 59 * 60 + 59),
+            Some(start + HOUR)
+        );
+        assert_eq!(maintenance.active_until(start + HOUR), None);
+        assert_eq!(maintenance.active_until(start + DAY), None);
+
+        // Across midnight and a month boundary (Tuesday 2023-01-31 23:30).
+        let maintenance = window("30 23 * * TUE", 90);
+        let start = SUNDAY + 30 * DAY + 23 * HOUR + 30 * 60;
+        assert_eq!(
+            maintenance.active_until(start + HOUR),
+            Some(start + 90 * 60)
+        );
+        assert_eq!(maintenance.active_until(start + 90 * 60), None);
+
+        // Overlapping starts: the window is extended by the most recent one.
+        let maintenance = window("*/10 * * * *", 15);
+        assert_eq!(
+            maintenance.active_until(SUNDAY + 12 * 60),
+            Some(SUNDAY + 25 * 60)
+        );
+        let maintenance = window("*/30 * * * *", 10);
+        assert_eq!(maintenance.active_until(SUNDAY + 12 * 60), None);
+
+        // A window of a week always finds its start.
+        let maintenance = window("0 0 * * MON", MAINTENANCE_MAX_DURATION_MINS);
+        let monday = SUNDAY + DAY;
+        assert_eq!(maintenance.active_until(monday - 60), Some(monday));
+        assert_eq!(maintenance.active_until(monday), Some(monday + 7 * DAY));
+
+        // Near the epoch, there is nothing before.
+        assert_eq!(window("0 0 1 1 *", 60).active_until(0), Some(HOUR));
+        assert_eq!(window("1 0 1 1 *", 60).active_until(0), None);
+    }
+}
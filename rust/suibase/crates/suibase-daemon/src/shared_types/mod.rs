@@ -3,18 +3,76 @@
 // This is a submodule specific to suibase-daemon.
 //
 // flatten everything under "shared_type" module.
-pub(crate) use self::events::*;
+pub(crate) use self::active_ports::*;
+pub(crate) use self::active_workdir::*;
+pub(crate) use self::config_history::*;
+pub(crate) use self::daemon_endpoints::*;
+pub(crate) use self::events_sync::*;
+pub(crate) use self::gas_inventory::*;
 pub(crate) use self::globals::*;
+pub(crate) use self::health_expr::*;
 pub(crate) use self::input_port::*;
+pub(crate) use self::ip_allowlist::*;
+pub(crate) use self::jobs::*;
+pub(crate) use self::link_client::*;
+pub(crate) use self::link_usage::*;
+pub(crate) use self::localnet_snapshots::*;
+pub(crate) use self::maintenance::*;
 pub(crate) use self::packages::*;
+pub(crate) use self::process_log::*;
+pub(crate) use self::proxy_stats::*;
+pub(crate) use self::proxy_timeouts::*;
+pub(crate) use self::rate_limiter::*;
+pub(crate) use self::recent_requests::*;
 pub(crate) use self::server_stats::*;
+pub(crate) use self::shadow::*;
+pub(crate) use self::startup::*;
+pub(crate) use self::subscriptions::*;
+pub(crate) use self::sui_binary::*;
+pub(crate) use self::system_check::*;
+pub(crate) use self::system_values::*;
 pub(crate) use self::target_server::*;
+pub(crate) use self::telemetry::*;
+pub(crate) use self::time_skew::*;
+pub(crate) use self::webhooks::*;
+pub(crate) use self::worker_restarts::*;
+pub(crate) use self::workdir_processes::*;
+pub(crate) use self::workdir_profile::*;
 pub(crate) use self::workdirs::*;
 
-mod events;
+mod active_ports;
+mod active_workdir;
+mod config_history;
+mod daemon_endpoints;
+mod events_sync;
+mod gas_inventory;
 mod globals;
+mod health_expr;
 mod input_port;
+mod ip_allowlist;
+mod jobs;
+mod link_client;
+mod link_usage;
+mod localnet_snapshots;
+mod maintenance;
 mod packages;
+mod process_log;
+mod proxy_stats;
+mod proxy_timeouts;
+mod rate_limiter;
+mod recent_requests;
 mod server_stats;
+mod shadow;
+mod startup;
+mod subscriptions;
+mod sui_binary;
+mod system_check;
+mod system_values;
 mod target_server;
+mod telemetry;
+mod time_skew;
+mod webhooks;
+mod worker_restarts;
+mod workdir_processes;
+mod workdir_profile;
 mod workdirs;
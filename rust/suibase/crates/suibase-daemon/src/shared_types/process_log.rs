@@ -0,0 +1,315 @@
+// Rotation and tail of the logs of the workdir processes (see "process_log_max_mb"
+// in suibase.yaml and getProcessLog).
+//
+// The scripts redirect the processes output to:
+//
+//   workdirs/{workdir}/config/sui-process.log
+//   workdirs/{workdir}/config/sui-faucet-process.log
+//
+// Rotated with copy-truncate, because the process keeps the file open:
+//   - The archives are shifted (.1.gz becomes .2.gz ...), the oldest is deleted.
+//   - The log is compressed into .1.gz, reading up to its end (including what is
+//     written meanwhile), then truncated right away.
+//
+// Only what the process writes between the last read and the truncate (a few
+// microseconds) can be lost. The scripts open the logs in append mode, so the
+// process continues at the start of the truncated file.
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use super::WorkdirProcessKind;
+
+pub const PROCESS_LOG_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+pub const DEFAULT_PROCESS_LOG_MAX_MB: u64 = 100;
+pub const DEFAULT_PROCESS_LOG_KEEP: u32 = 5;
+
+pub const DEFAULT_PROCESS_LOG_TAIL_LINES: u32 = 100;
+pub const MAX_PROCESS_LOG_TAIL_LINES: u32 = 10000;
+
+// Read from the end of the log by chunks of this size.
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessLogConfig {
+    pub max_mb: u64, // 0 disables the rotation.
+    pub keep: u32,   // Compressed archives kept.
+}
+
+impl ProcessLogConfig {
+    pub fn new() -> Self {
+        Self {
+            max_mb: DEFAULT_PROCESS_LOG_MAX_MB,
+            keep: DEFAULT_PROCESS_LOG_KEEP,
+        }
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+impl Default for ProcessLogConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn process_log_path(workdir_path: &Path, kind: WorkdirProcessKind) -> PathBuf {
+    workdir_path.join("config").join(kind.log_filename())
+}
+
+// e.g. "sui-process.log.1.gz" (most recent is 1).
+pub fn process_log_archive_path(log_path: &Path, n: u32) -> PathBuf {
+    let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.gz", n));
+    log_path.with_file_name(name)
+}
+
+// The archives of a log, most recent first.
+pub fn process_log_archives(log_path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|n| process_log_archive_path(log_path, n))
+        .take_while(|path| path.is_file())
+        .collect()
+}
+
+// Rotate the log when larger than 'max_bytes'. Returns true when rotated.
+//
+// A missing log is not an error (e.g. the process never started).
+pub fn rotate_process_log(log_path: &Path, max_bytes: u64, keep: u32) -> Result<bool> {
+    let size = match std::fs::metadata(log_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    if max_bytes == 0 || size <= max_bytes {
+        return Ok(false);
+    }
+
+    // Make room for the new .1.gz (keep of 0 still rotates, into a single archive).
+    // Also deletes the archives beyond 'keep' (e.g. after lowering it in suibase.yaml).
+    let keep = keep.max(1);
+    for stale in process_log_archives(log_path)
+        .iter()
+        .skip(keep as usize - 1)
+    {
+        std::fs::remove_file(stale)?;
+    }
+    for n in (1..keep).rev() {
+        let from = process_log_archive_path(log_path, n);
+        if from.is_file() {
+            std::fs::rename(&from, process_log_archive_path(log_path, n + 1))?;
+        }
+    }
+
+    let archive_path = process_log_archive_path(log_path, 1);
+    let tmp_path = archive_path.with_extension("gz.tmp");
+    let mut log = OpenOptions::new().read(true).write(true).open(log_path)?;
+    let mut encoder = GzEncoder::new(File::create(&tmp_path)?, flate2::Compression::fast());
+    std::io::copy(&mut log, &mut encoder)?;
+    // Truncate as soon as the end is reached, to minimize what can be missed.
+    log.set_len(0)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::rename(&tmp_path, &archive_path)?;
+    Ok(true)
+}
+
+// Keep the last 'n_lines' pushed.
+struct LastLines<'a> {
+    n_lines: usize,
+    grep: Option<&'a str>,
+    lines: VecDeque<String>,
+}
+
+impl<'a> LastLines<'a> {
+    fn new(n_lines: usize, grep: Option<&'a str>) -> Self {
+        Self {
+            n_lines,
+            grep,
+            lines: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, line: &str) {
+        if self.grep.is_some_and(|grep| !line.contains(grep)) {
+            return;
+        }
+        if self.lines.len() == self.n_lines {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+}
+
+// The last lines of the live log, read backward from its end.
+fn tail_file(log_path: &Path, n_lines: usize, grep: Option<&str>) -> Result<Vec<String>> {
+    let mut file = match File::open(log_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut pos = file.seek(SeekFrom::End(0))?;
+
+    // Lines found, newest first. 'partial' is the start of the line cut by the chunk.
+    let mut found: Vec<String> = Vec::new();
+    let mut partial: Vec<u8> = Vec::new();
+    let mut is_last_line = true;
+    while pos > 0 && found.len() < n_lines {
+        let chunk_size = TAIL_CHUNK_SIZE.min(pos);
+        pos -= chunk_size;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0u8; chunk_size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&partial);
+
+        let mut segments: Vec<&[u8]> = chunk.split(|b| *b == b'\n').collect();
+        // The first segment may continue in the previous chunk.
+        partial = segments.remove(0).to_vec();
+        for segment in segments.into_iter().rev() {
+            // The trailing newline of the file is not an empty line.
+            if is_last_line {
+                is_last_line = false;
+                if segment.is_empty() {
+                    continue;
+                }
+            }
+            push_found(&mut found, segment, grep);
+            if found.len() == n_lines {
+                break;
+            }
+        }
+    }
+    if pos == 0 && found.len() < n_lines && !(partial.is_empty() && is_last_line) {
+        push_found(&mut found, &partial, grep);
+    }
+    found.reverse();
+    Ok(found)
+}
+
+fn push_found(found: &mut Vec<String>, segment: &[u8], grep: Option<&str>) {
+    // A process writing at its own offset after a truncate leaves NULs at the start.
+    let line = String::from_utf8_lossy(segment);
+    let line = line.trim_start_matches('\0');
+    if grep.is_some_and(|grep| !line.contains(grep)) {
+        return;
+    }
+    found.push(line.to_string());
+}
+
+// The last 'n_lines' of a process log (only those containing 'grep' when set).
+//
+// Completed from the most recent archive when the log was just rotated.
+pub fn tail_process_log(log_path: &Path, n_lines: u32, grep: Option<&str>) -> Result<Vec<String>> {
+    let n_lines = n_lines as usize;
+    let mut lines = tail_file(log_path, n_lines, grep)?;
+    if lines.len() >= n_lines {
+        return Ok(lines);
+    }
+
+    let archive_path = process_log_archive_path(log_path, 1);
+    let archive = match File::open(&archive_path) {
+        Ok(archive) => archive,
+        Err(_) => return Ok(lines),
+    };
+    let mut last = LastLines::new(n_lines - lines.len(), grep);
+    for line in BufReader::new(GzDecoder::new(archive)).split(b'\n') {
+        last.push(String::from_utf8_lossy(&line?).trim_start_matches('\0'));
+    }
+    let mut all: Vec<String> = last.lines.into();
+    all.append(&mut lines);
+    Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Mimic the output of a process.
+    fn append_lines(log_path: &Path, lines: impl Iterator<Item = String>) {
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)
+            .unwrap();
+        for line in lines {
+            writeln!(log, "{}", line).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rotate_and_tail() {
+        let dir = std::env::temp_dir().join(format!("sbsd-process-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let log_path = dir.join("sui-process.log");
+        let max_bytes = 64 * 1024;
+
+        // Nothing to do while missing or small.
+        assert!(!rotate_process_log(&log_path, max_bytes, 2).unwrap());
+        append_lines(&log_path, (0..10).map(|i| format!("line {}", i)));
+        assert!(!rotate_process_log(&log_path, max_bytes, 2).unwrap());
+        assert_eq!(
+            tail_process_log(&log_path, 3, None).unwrap(),
+            vec!["line 7", "line 8", "line 9"]
+        );
+
+        // Above the limit (several tail chunks).
+        append_lines(&log_path, (10..20000).map(|i| format!("line {}", i)));
+        let tail = tail_process_log(&log_path, 10000, None).unwrap();
+        assert_eq!(tail.len(), 10000);
+        assert_eq!(tail[0], "line 10000");
+        assert_eq!(tail[9999], "line 19999");
+        assert!(rotate_process_log(&log_path, max_bytes, 2).unwrap());
+        assert_eq!(std::fs::metadata(&log_path).unwrap().len(), 0);
+        assert_eq!(process_log_archives(&log_path).len(), 1);
+
+        // The newest lines, completed from the archive.
+        append_lines(&log_path, (20000..20002).map(|i| format!("line {}", i)));
+        assert_eq!(
+            tail_process_log(&log_path, 4, None).unwrap(),
+            vec!["line 19998", "line 19999", "line 20000", "line 20001"]
+        );
+        assert_eq!(
+            tail_process_log(&log_path, 3, Some("line 1999")).unwrap(),
+            vec!["line 19997", "line 19998", "line 19999"]
+        );
+
+        // Only 'keep' archives, the most recent first.
+        for round in 0..3 {
+            append_lines(
+                &log_path,
+                (0..20000).map(|i| format!("round {} {}", round, i)),
+            );
+            assert!(rotate_process_log(&log_path, max_bytes, 2).unwrap());
+        }
+        let archives = process_log_archives(&log_path);
+        assert_eq!(
+            archives,
+            vec![
+                dir.join("sui-process.log.1.gz"),
+                dir.join("sui-process.log.2.gz")
+            ]
+        );
+        assert_eq!(
+            tail_process_log(&log_path, 1, None).unwrap(),
+            vec!["round 2 19999"]
+        );
+
+        // NULs left by a process not writing in append mode.
+        std::fs::write(&log_path, b"\0\0\0\0after truncate\n").unwrap();
+        assert_eq!(
+            tail_process_log(&log_path, 1, None).unwrap(),
+            vec!["after truncate"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
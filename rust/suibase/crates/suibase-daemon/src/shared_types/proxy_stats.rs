@@ -0,0 +1,185 @@
+// Cumulative proxy statistics kept across daemon restarts (see "proxy_stats_persist"
+// in suibase.yaml).
+//
+// Saved to <workdir>/.state/proxy-stats.json by the AdminController (every
+// PROXY_STATS_SAVE_INTERVAL and on a graceful shutdown), and merged into the fresh
+// ServerStats when the InputPort of the workdir is created.
+//
+// Only the cumulative counters are kept (requests, failures, latency sum, rate
+// limiting...). The windowed values (health, latency average, request rates,
+// throttling, warm-up) always start clean.
+//
+// The links are matched by alias. A file not readable, or from another
+// PROXY_STATS_VERSION, is ignored with a warning.
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{GlobalsProxyMT, InputPort};
+
+pub const PROXY_STATS_FILENAME: &str = "proxy-stats.json";
+pub const PROXY_STATS_SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+// Increment on any incompatible change of the file (e.g. a new failure reason).
+const PROXY_STATS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersistedServerStats {
+    pub success_on_first_attempt: u64,
+    pub success_on_retry: u64,
+    pub retry_count: u64,
+    pub req_failure_reasons: Vec<u64>,
+    pub req_unknown_reason: u64,
+    pub send_failure_reasons: Vec<u64>,
+    pub send_unknown_reason: u64,
+    pub req_failure_internal: u64,
+    pub latency_report_count: u64,
+    pub latency_report_sum_ms: f64,
+    pub http_status_classes: Vec<u64>,
+    pub jsonrpc_error_codes: HashMap<i32, u64>,
+    pub jsonrpc_error_other: u64,
+    pub throttle_count: u64,
+    pub hedge_count: u64,
+    pub hedge_won_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyStatsFile {
+    pub version: u32,
+    pub saved_at: String, // RFC 3339
+    pub all_servers: PersistedServerStats,
+    pub links: BTreeMap<String, PersistedServerStats>, // Key is the alias.
+}
+
+impl ProxyStatsFile {
+    pub fn from_input_port(input_port: &InputPort) -> Self {
+        Self {
+            version: PROXY_STATS_VERSION,
+            saved_at: Utc::now().to_rfc3339(),
+            all_servers: input_port.all_servers_stats.to_persisted(),
+            links: input_port
+                .target_servers
+                .iter()
+                .map(|(_, target_server)| {
+                    (target_server.alias(), target_server.stats.to_persisted())
+                })
+                .collect(),
+        }
+    }
+
+    // None when there is no file. Also None (with a warning) when not usable.
+    pub fn load_from_file(path: &Path) -> Option<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("{:?} ignored ({})", path, e);
+                return None;
+            }
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(file) if file.version == PROXY_STATS_VERSION => Some(file),
+            Ok(file) => {
+                log::warn!(
+                    "{:?} ignored (version {}, expected {})",
+                    path,
+                    file.version,
+                    PROXY_STATS_VERSION
+                );
+                None
+            }
+            Err(e) => {
+                log::warn!("{:?} ignored ({})", path, e);
+                None
+            }
+        }
+    }
+
+    // Written to a temporary file first, so a crash never leaves a truncated file.
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    // The links not in the file (or gone from the config) are left as-is.
+    pub fn restore_into(&self, input_port: &mut InputPort) {
+        input_port
+            .all_servers_stats
+            .merge_persisted(&self.all_servers);
+        for (_, target_server) in input_port.target_servers.iter_mut() {
+            if let Some(persisted) = self.links.get(&target_server.alias()) {
+                target_server.stats.merge_persisted(persisted);
+            }
+        }
+    }
+}
+
+// Save the stats of every InputPort having a proxy_stats_file. The files are
+// written after releasing the globals lock.
+pub async fn save_proxy_stats(globals: &GlobalsProxyMT) {
+    let to_save: Vec<(PathBuf, ProxyStatsFile)> = {
+        let globals_guard = globals.read().await;
+        globals_guard
+            .input_ports
+            .iter()
+            .filter_map(|(_, input_port)| {
+                let path = input_port.proxy_stats_file()?;
+                Some((path.clone(), ProxyStatsFile::from_input_port(input_port)))
+            })
+            .collect()
+    };
+    for (path, file) in to_save {
+        if let Err(e) = file.save_to_file(&path) {
+            log::warn!("{:?} not saved ({})", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_ignored() {
+        let dir = std::env::temp_dir().join(format!("sbsd-proxy-stats-{}", std::process::id()));
+        let path = dir.join(PROXY_STATS_FILENAME);
+        assert!(ProxyStatsFile::load_from_file(&path).is_none());
+
+        let mut file = ProxyStatsFile {
+            version: PROXY_STATS_VERSION,
+            saved_at: Utc::now().to_rfc3339(),
+            all_servers: PersistedServerStats::default(),
+            links: BTreeMap::new(),
+        };
+        file.links.insert(
+            "main".to_string(),
+            PersistedServerStats {
+                success_on_first_attempt: 5,
+                jsonrpc_error_codes: HashMap::from([(-32000, 2)]),
+                ..Default::default()
+            },
+        );
+        file.save_to_file(&path).unwrap();
+        assert_eq!(ProxyStatsFile::load_from_file(&path), Some(file.clone()));
+
+        // Another version.
+        file.version = PROXY_STATS_VERSION + 1;
+        file.save_to_file(&path).unwrap();
+        assert!(ProxyStatsFile::load_from_file(&path).is_none());
+
+        // Corrupted.
+        std::fs::write(&path, "{\"version\":1,").unwrap();
+        assert!(ProxyStatsFile::load_from_file(&path).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,173 @@
+// Upstream timeout of the proxy requests, by class of method (see jsonrpc_method_kind):
+//   read_ms    : Read methods (e.g. sui_getObject).
+//   execute_ms : Build, dry-run or execute a transaction. WaitForLocalExecution can
+//                legitimately take several seconds.
+//   default_ms : Any other method (and the batches).
+//
+// Set per workdir with "proxy_timeouts". Each field can be overridden per link (the
+// "timeouts" of a link in suibase.yaml).
+//
+// A timed out request is an upstream failure of the link (then tried with another
+// one), except for sui_executeTransactionBlock: the transaction may have been executed
+// anyway, so the client gets an "outcome unknown" error (with the transaction digest)
+// instead.
+use std::time::Duration;
+
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use common::basic_types::{jsonrpc_method_kind, JsonRpcMethodKind};
+use serde_json::Value as JsonValue;
+
+pub const DEFAULT_PROXY_READ_TIMEOUT_MS: u64 = 2000;
+pub const DEFAULT_PROXY_EXECUTE_TIMEOUT_MS: u64 = 30000;
+pub const DEFAULT_PROXY_TIMEOUT_MS: u64 = 10000;
+
+// Fields of "proxy_timeouts" (and of the "timeouts" of a link).
+pub const PROXY_TIMEOUTS_FIELDS: [&str; 3] = ["read_ms", "execute_ms", "default_ms"];
+
+// Only method for which a timeout does not tell if the request had an effect.
+const OUTCOME_UNKNOWN_METHOD: &str = "sui_executeTransactionBlock";
+
+// Hashed before the BCS of a TransactionData (its type name).
+const TRANSACTION_DATA_DIGEST_PREFIX: &[u8] = b"TransactionData::";
+
+// None is the default (for a link: the value of the workdir).
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct ProxyTimeouts {
+    pub read_ms: Option<u64>,
+    pub execute_ms: Option<u64>,
+    pub default_ms: Option<u64>,
+}
+
+impl ProxyTimeouts {
+    // 'link' overrides these, field by field.
+    pub fn timeout(&self, method: &str, link: &ProxyTimeouts) -> Duration {
+        let (link_ms, workdir_ms, default_ms) = match jsonrpc_method_kind(method) {
+            JsonRpcMethodKind::Read => (link.read_ms, self.read_ms, DEFAULT_PROXY_READ_TIMEOUT_MS),
+            JsonRpcMethodKind::Execute => (
+                link.execute_ms,
+                self.execute_ms,
+                DEFAULT_PROXY_EXECUTE_TIMEOUT_MS,
+            ),
+            JsonRpcMethodKind::Unknown => {
+                (link.default_ms, self.default_ms, DEFAULT_PROXY_TIMEOUT_MS)
+            }
+        };
+        Duration::from_millis(link_ms.or(workdir_ms).unwrap_or(default_ms))
+    }
+
+    pub fn set(&mut self, field: &str, value_ms: Option<u64>) {
+        match field {
+            "read_ms" => self.read_ms = value_ms,
+            "execute_ms" => self.execute_ms = value_ms,
+            "default_ms" => self.default_ms = value_ms,
+            _ => {}
+        }
+    }
+}
+
+// e.g. "read_ms=1000, execute_ms=60000" (only the fields set).
+impl std::fmt::Display for ProxyTimeouts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = PROXY_TIMEOUTS_FIELDS
+            .iter()
+            .zip([self.read_ms, self.execute_ms, self.default_ms])
+            .filter_map(|(field, value)| value.map(|value| format!("{}={}", field, value)))
+            .collect();
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+pub fn is_outcome_unknown_on_timeout(method: &str) -> bool {
+    method == OUTCOME_UNKNOWN_METHOD
+}
+
+// Digest of the transaction of a sui_executeTransactionBlock request (Base58, as shown
+// by the explorers). None for any other request, or when tx_bytes is not valid Base64.
+pub fn request_transaction_digest(request: &[u8]) -> Option<String> {
+    let request = serde_json::from_slice::<JsonValue>(request).ok()?;
+    if request["method"].as_str() != Some(OUTCOME_UNKNOWN_METHOD) {
+        return None;
+    }
+    let params = &request["params"];
+    let tx_bytes = params
+        .get(0)
+        .or_else(|| params.get("tx_bytes"))
+        .and_then(JsonValue::as_str)?;
+    let tx_bytes = data_encoding::BASE64.decode(tx_bytes.as_bytes()).ok()?;
+    Some(transaction_digest(&tx_bytes))
+}
+
+// Same as the TransactionDigest of Sui: Blake2b-256 of the BCS of the TransactionData,
+// prefixed with its type name.
+pub fn transaction_digest(tx_bytes: &[u8]) -> String {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(TRANSACTION_DATA_DIGEST_PREFIX);
+    hasher.update(tx_bytes);
+    bs58::encode(hasher.finalize()).into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_timeouts() {
+        let workdir = ProxyTimeouts {
+            read_ms: Some(1000),
+            execute_ms: None,
+            default_ms: Some(5000),
+        };
+        let no_override = ProxyTimeouts::default();
+        let ms = |timeouts: &ProxyTimeouts, method: &str, link: &ProxyTimeouts| {
+            timeouts.timeout(method, link).as_millis() as u64
+        };
+        assert_eq!(ms(&workdir, "sui_getObject", &no_override), 1000);
+        assert_eq!(
+            ms(&workdir, "sui_executeTransactionBlock", &no_override),
+            DEFAULT_PROXY_EXECUTE_TIMEOUT_MS
+        );
+        assert_eq!(ms(&workdir, "unsafe_moveCall", &no_override), 30000);
+        assert_eq!(ms(&workdir, "batch", &no_override), 5000);
+        assert_eq!(
+            ms(&no_override, "suix_getBalance", &no_override),
+            DEFAULT_PROXY_READ_TIMEOUT_MS
+        );
+
+        // Overridden per link, field by field.
+        let mut link = ProxyTimeouts::default();
+        link.set("read_ms", Some(250));
+        assert_eq!(ms(&workdir, "sui_getObject", &link), 250);
+        assert_eq!(ms(&workdir, "custom_method", &link), 5000);
+
+        assert_eq!(workdir.to_string(), "read_ms=1000, default_ms=5000");
+        assert_eq!(no_override.to_string(), "");
+    }
+
+    #[test]
+    fn test_request_transaction_digest() {
+        assert_eq!(
+            transaction_digest(b"\x00\x01\x02"),
+            "472GebxRV9PPfLw1Ekt2zM746Q33u5C862KU9wPrVe13"
+        );
+        let request = |method: &str, tx_bytes: &str| {
+            format!(
+                "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"{}\",\"params\":[\"{}\",[]]}}",
+                method, tx_bytes
+            )
+        };
+        assert_eq!(
+            request_transaction_digest(request("sui_executeTransactionBlock", "AAEC").as_bytes()),
+            Some("472GebxRV9PPfLw1Ekt2zM746Q33u5C862KU9wPrVe13".to_string())
+        );
+        assert_eq!(
+            request_transaction_digest(request("sui_executeTransactionBlock", "A?").as_bytes()),
+            None
+        );
+        assert_eq!(
+            request_transaction_digest(request("sui_dryRunTransactionBlock", "AAEC").as_bytes()),
+            None
+        );
+        assert!(is_outcome_unknown_on_timeout("sui_executeTransactionBlock"));
+        assert!(!is_outcome_unknown_on_timeout("sui_dryRunTransactionBlock"));
+    }
+}
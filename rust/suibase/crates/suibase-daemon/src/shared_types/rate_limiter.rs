@@ -0,0 +1,274 @@
+// Token buckets for the max_per_secs, max_per_min and max_per_day of a link, or of
+// a rate group shared by the links of a same provider (see "rate_groups" in
+// suibase.yaml).
+//
+// The proxy_server takes a token of the group first, then of the link, before every
+// request sent to the link (see try_acquire_all). A link without a token is skipped
+// for that request, without affecting its health.
+//
+// A bucket holds at most its limit and is refilled continuously (max_per_min is
+// refilled at max_per_min/60 per second), so even a burst stays within the limits.
+//
+// The max_per_day bucket is instead refilled all at once at 00:00 UTC (a quota, like
+// most providers). It is not kept across a daemon restart.
+//
+// Used by the proxy_server under the read lock of the globals, so the buckets have
+// their own std Mutex (held very briefly).
+use std::sync::{Arc, Mutex};
+
+use common::basic_types::SharedClock;
+use tokio::time::Instant;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct RateLimits {
+    pub max_per_secs: Option<u32>,
+    pub max_per_min: Option<u32>,
+    pub max_per_day: Option<u32>,
+}
+
+impl RateLimits {
+    pub fn is_limited(&self) -> bool {
+        self.max_per_secs.is_some() || self.max_per_min.is_some() || self.max_per_day.is_some()
+    }
+}
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+// Tokens granted and denied since the limiter was created (see getLinks).
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct RateLimiterUsage {
+    pub granted: u64,
+    pub denied: u64,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    secs_tokens: f64,
+    min_tokens: f64,
+    refilled_at: Instant,
+    day_tokens: u64,
+    day: u64, // Day since the UNIX epoch of the day_tokens.
+    usage: RateLimiterUsage,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+    clock: SharedClock,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self::new_with_clock(limits, SharedClock::default())
+    }
+
+    // The buckets start full.
+    pub fn new_with_clock(limits: RateLimits, clock: SharedClock) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(Buckets {
+                secs_tokens: limits.max_per_secs.unwrap_or(0) as f64,
+                min_tokens: limits.max_per_min.unwrap_or(0) as f64,
+                refilled_at: clock.now_instant(),
+                day_tokens: limits.max_per_day.unwrap_or(0) as u64,
+                day: clock.now_secs() / DAY_SECS,
+                usage: RateLimiterUsage::default(),
+            }),
+            clock,
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    pub fn usage(&self) -> RateLimiterUsage {
+        self.buckets.lock().unwrap().usage
+    }
+
+    // A token is taken from all the buckets, or from none.
+    pub fn try_acquire(&self) -> bool {
+        let now = self.clock.now_instant();
+        let today = self.clock.now_secs() / DAY_SECS;
+        let mut buckets = self.buckets.lock().unwrap();
+        let elapsed = now
+            .saturating_duration_since(buckets.refilled_at)
+            .as_secs_f64();
+        buckets.refilled_at = buckets.refilled_at.max(now);
+        if let Some(limit) = self.limits.max_per_secs {
+            buckets.secs_tokens = (buckets.secs_tokens + elapsed * limit as f64).min(limit as f64);
+        }
+        if let Some(limit) = self.limits.max_per_min {
+            let refill = elapsed * limit as f64 / 60.0;
+            buckets.min_tokens = (buckets.min_tokens + refill).min(limit as f64);
+        }
+        if let Some(limit) = self.limits.max_per_day {
+            if buckets.day != today {
+                buckets.day = today;
+                buckets.day_tokens = limit as u64;
+            }
+        }
+
+        let available = |tokens: f64, limit: Option<u32>| limit.is_none() || tokens >= 1.0;
+        if !available(buckets.secs_tokens, self.limits.max_per_secs)
+            || !available(buckets.min_tokens, self.limits.max_per_min)
+            || !available(buckets.day_tokens as f64, self.limits.max_per_day)
+        {
+            buckets.usage.denied += 1;
+            return false;
+        }
+        if self.limits.max_per_secs.is_some() {
+            buckets.secs_tokens -= 1.0;
+        }
+        if self.limits.max_per_min.is_some() {
+            buckets.min_tokens -= 1.0;
+        }
+        if self.limits.max_per_day.is_some() {
+            buckets.day_tokens -= 1;
+        }
+        buckets.usage.granted += 1;
+        true
+    }
+
+    // Give back a token not used for a request (e.g. the link denied it after its
+    // group granted it).
+    pub fn refund_token(&self) {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(limit) = self.limits.max_per_secs {
+            buckets.secs_tokens = (buckets.secs_tokens + 1.0).min(limit as f64);
+        }
+        if let Some(limit) = self.limits.max_per_min {
+            buckets.min_tokens = (buckets.min_tokens + 1.0).min(limit as f64);
+        }
+        if let Some(limit) = self.limits.max_per_day {
+            buckets.day_tokens = (buckets.day_tokens + 1).min(limit as u64);
+        }
+        buckets.usage.granted = buckets.usage.granted.saturating_sub(1);
+    }
+}
+
+// Take a token of every limiter, in order (the group before the link). When one
+// denies, the tokens already taken are refunded and false is returned.
+pub fn try_acquire_all(limiters: &[Arc<RateLimiter>]) -> bool {
+    for (pos, limiter) in limiters.iter().enumerate() {
+        if !limiter.try_acquire() {
+            for acquired in &limiters[..pos] {
+                acquired.refund_token();
+            }
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::basic_types::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_limiter_refill() {
+        let clock = MockClock::new();
+        let limiter = RateLimiter::new_with_clock(
+            RateLimits {
+                max_per_secs: Some(2),
+                max_per_min: Some(3),
+                max_per_day: None,
+            },
+            SharedClock::new(clock.clone()),
+        );
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        // The per-second bucket is refilled, the per-minute one only by 1/60 per second.
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+        clock.advance(Duration::from_secs(20));
+        assert!(limiter.try_acquire());
+        assert_eq!(
+            limiter.usage(),
+            RateLimiterUsage {
+                granted: 4,
+                denied: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_acquire_all_refunds_group() {
+        // The time does not move, so no token is refilled.
+        let clock = SharedClock::new(MockClock::new());
+        let new_limiter = |max_per_secs: Option<u32>| {
+            let limits = RateLimits {
+                max_per_secs,
+                ..Default::default()
+            };
+            Arc::new(RateLimiter::new_with_clock(limits, clock.clone()))
+        };
+        let group = new_limiter(Some(2));
+        let link = new_limiter(Some(1));
+        let other_link = new_limiter(None);
+
+        assert!(try_acquire_all(&[group.clone(), link.clone()]));
+        // The link denies, so the token of the group is given back...
+        assert!(!try_acquire_all(&[group.clone(), link.clone()]));
+        assert_eq!(group.usage().granted, 1);
+        // ...and is still available to the other link of the group.
+        assert!(try_acquire_all(&[group.clone(), other_link.clone()]));
+        assert!(!try_acquire_all(&[group.clone(), other_link]));
+        assert_eq!(
+            group.usage(),
+            RateLimiterUsage {
+                granted: 2,
+                denied: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_day_quota() {
+        let clock = MockClock::new();
+        let shared_clock = SharedClock::new(clock.clone());
+        let limits = RateLimits {
+            max_per_day: Some(3),
+            ..Default::default()
+        };
+        let link = Arc::new(RateLimiter::new_with_clock(limits, shared_clock.clone()));
+        let group = Arc::new(RateLimiter::new_with_clock(
+            RateLimits {
+                max_per_secs: Some(10),
+                ..Default::default()
+            },
+            shared_clock,
+        ));
+        assert!(limits.is_limited());
+
+        // Move to the start of a day, so the quota is not refilled below.
+        let secs_in_day = clock.now_secs() % DAY_SECS;
+        clock.advance(Duration::from_secs(DAY_SECS - secs_in_day));
+
+        for _ in 0..3 {
+            assert!(try_acquire_all(&[group.clone(), link.clone()]));
+        }
+        // Not refilled with the time, and the group token is given back on deny.
+        clock.advance(Duration::from_secs(3600));
+        assert!(!try_acquire_all(&[group.clone(), link.clone()]));
+        assert_eq!(group.usage().granted, 3);
+
+        // A token refunded to the link is available again.
+        link.refund_token();
+        assert!(link.try_acquire());
+        assert!(!link.try_acquire());
+
+        // Refilled at 00:00 UTC.
+        clock.advance(Duration::from_secs(DAY_SECS));
+        for _ in 0..3 {
+            assert!(link.try_acquire());
+        }
+        assert!(!link.try_acquire());
+    }
+}
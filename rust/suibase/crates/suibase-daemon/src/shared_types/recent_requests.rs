@@ -0,0 +1,80 @@
+// Last requests handled by a proxy port (see getRecentRequests).
+//
+// Debugging aid to correlate a client request (by its trace id) with the link
+// that served it. In memory only, the oldest entries are dropped.
+//
+// Written by the proxy_server on every request, so it has its own std Mutex
+// (held very briefly) instead of requiring a write lock on the globals.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::basic_types::TargetServerIdx;
+
+pub const RECENT_REQUESTS_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentRequest {
+    pub request_id: String,
+    pub method: String, // JSON-RPC method ("batch" for an array of requests).
+    pub server_idx: Option<TargetServerIdx>, // Last link attempted, if any.
+    pub latency: Duration,
+    pub http_status: u16, // Status returned to the client.
+}
+
+#[derive(Debug)]
+pub struct RecentRequests {
+    entries: VecDeque<RecentRequest>,
+    capacity: usize,
+}
+
+pub type RecentRequestsMT = Arc<Mutex<RecentRequests>>;
+
+impl RecentRequests {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn new_mt() -> RecentRequestsMT {
+        Arc::new(Mutex::new(Self::new(RECENT_REQUESTS_CAPACITY)))
+    }
+
+    pub fn push(&mut self, entry: RecentRequest) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    // Most recent first.
+    pub fn latest(&self, limit: usize) -> Vec<RecentRequest> {
+        self.entries.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_requests_ring() {
+        let entry = |i: usize| RecentRequest {
+            request_id: format!("req-{}", i),
+            method: "sui_getObject".to_string(),
+            server_idx: Some(0),
+            latency: Duration::from_millis(i as u64),
+            http_status: 200,
+        };
+        let mut ring = RecentRequests::new(3);
+        assert!(ring.latest(10).is_empty());
+        for i in 0..5 {
+            ring.push(entry(i));
+        }
+        let ids: Vec<String> = ring.latest(10).into_iter().map(|e| e.request_id).collect();
+        assert_eq!(ids, vec!["req-4", "req-3", "req-2"]);
+        assert_eq!(ring.latest(1), vec![entry(4)]);
+    }
+}
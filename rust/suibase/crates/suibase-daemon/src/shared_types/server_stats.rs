@@ -1,9 +1,14 @@
 // Maintains stats/health of a server (IP:Port).
 
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
 use hyper::http;
 
 use common::basic_types::*;
 
+use super::PersistedServerStats;
+
 type UpScoreBonus = f64;
 const NORMAL_SCORE_UP: UpScoreBonus = 1.15;
 const WEAK_SCORE_UP: UpScoreBonus = 1.01;
@@ -22,9 +27,14 @@ pub const REQUEST_FAILED_NETWORK_DOWN: u8 = 5; // Not implemented yet.
 pub const REQUEST_FAILED_BAD_REQUEST_HTTP: u8 = 6; // Got HTTP Bad Request (400), Bad Method (405), etc.
 pub const REQUEST_FAILED_CONFIG_DISABLED: u8 = 7;
 pub const REQUEST_FAILED_NOT_STARTED: u8 = 8;
+pub const REQUEST_FAILED_OVERLOAD: u8 = 9; // Shed by the proxy (proxy_max_concurrency reached).
+pub const REQUEST_FAILED_INVALID_REQUEST: u8 = 10; // Malformed JSON-RPC, never sent upstream.
+pub const REQUEST_FAILED_IP_DENIED: u8 = 11; // Source IP not in proxy_allowed_ips.
+pub const REQUEST_FAILED_OUTCOME_UNKNOWN: u8 = 12; // Transaction timed out, may be executed.
+pub const REQUEST_FAILED_RATE_LIMITED: u8 = 13; // No token of the links (see rate_limiter.rs).
 
 // !!! Update the following whenever you append a new reason above.
-pub const REQUEST_FAILED_LAST_REASON: u8 = REQUEST_FAILED_NOT_STARTED;
+pub const REQUEST_FAILED_LAST_REASON: u8 = REQUEST_FAILED_RATE_LIMITED;
 
 // Do not touch this.
 pub const REQUEST_FAILED_VEC_SIZE: usize = REQUEST_FAILED_LAST_REASON as usize + 1;
@@ -42,6 +52,110 @@ pub const SEND_FAILED_LAST_REASON: u8 = SEND_FAILED_UNSPECIFIED_STATUS;
 // Do not touch this.
 pub const SEND_FAILED_VEC_SIZE: usize = SEND_FAILED_LAST_REASON as usize + 1;
 
+// Limit on distinct JSON-RPC error codes tracked per server. Codes beyond
+// this limit are counted in 'jsonrpc_error_other'.
+const JSONRPC_ERROR_CODES_MAX: usize = 32;
+
+// A link responding with HTTP 429 (or one of its throttle_codes) is not selected for the
+// duration of its Retry-After header, or THROTTLE_DEFAULT_SECS when not specified. Longer
+// durations are capped (a misbehaving provider should not remove a link for hours).
+pub const THROTTLE_DEFAULT_SECS: u64 = 10;
+pub const THROTTLE_MAX_SECS: u64 = 600;
+
+// Number of most recent responses considered for QuotaErrorRule::pct.
+const QUOTA_ERROR_WINDOW: u32 = 64;
+
+// Most recent latency reports kept for the percentiles (see latency_p90_ms).
+const LATENCY_SAMPLES_WINDOW: usize = 50;
+
+// Rule to degrade a server health when it responds with too many "quota-type"
+// JSON-RPC errors (e.g. -32000 "quota exceeded" within an HTTP 200 response).
+//
+// The server is degraded on either:
+//   - 'consecutive' quota-type errors (0 disables this check).
+//   - 'pct' percent or more of the most recent responses being quota-type errors (0 disables
+//     this check). Applies only after 'min_samples' responses were observed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaErrorRule {
+    pub codes: Vec<i32>,
+    pub consecutive: u32,
+    pub pct: u8,
+    pub min_samples: u8,
+}
+
+impl QuotaErrorRule {
+    pub fn new() -> Self {
+        Self {
+            codes: vec![-32000],
+            consecutive: 5,
+            pct: 50,
+            min_samples: 20,
+        }
+    }
+
+    pub fn is_quota_code(&self, code: i32) -> bool {
+        self.codes.contains(&code)
+    }
+}
+
+impl Default for QuotaErrorRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Rule to "warm-up" a link added (or modified) while the proxy is running.
+//
+// The link is not selectable until it answers 'min_success' out of a burst of
+// 'checks' health checks, done every 'interval_ms'. A link failing its warm-up
+// stays out of the selection until a later burst passes (the next one is
+// started by the periodic audit). 'checks: 0' disables the warm-up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkWarmUpRule {
+    pub checks: u8,
+    pub min_success: u8,
+    pub interval_ms: u64,
+}
+
+impl LinkWarmUpRule {
+    pub fn new() -> Self {
+        Self {
+            checks: 3,
+            min_success: 3,
+            interval_ms: 500,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.checks > 0
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+impl Default for LinkWarmUpRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmUpProgress {
+    Probing, // More checks needed.
+    Passed,  // The link can now be selected.
+    Failed,  // Too many failures for this burst.
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WarmUp {
+    rule: LinkWarmUpRule,
+    checks: u8,
+    successes: u8,
+    failed: bool, // Most recent burst failed. Next check starts a new burst.
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServerStats {
     // Keep a copy of the server alias here because it is very
@@ -66,6 +180,8 @@ pub struct ServerStats {
     latency_report_avg: f64,
     latency_report_most_recent: Option<EpochTimestamp>,
     latency_report_count: u64,
+    latency_report_sum_ms: f64, // With latency_report_count, for a long-term average.
+    latency_samples: VecDeque<u32>, // Microseconds, oldest first.
 
     success_on_first_attempt: u64,
     success_on_retry: u64,
@@ -96,6 +212,52 @@ pub struct ServerStats {
     down_score: f64, // Value from 0 to 100
 
     error_info: Option<String>, // Info on most recent failure.
+
+    // Count of HTTP responses per status class. Index is status / 100 (e.g. [2] is 2xx).
+    http_status_classes: [u64; 6],
+
+    // Count of JSON-RPC error per code (from responses that were otherwise successful).
+    jsonrpc_error_codes: HashMap<i32, u64>,
+    jsonrpc_error_other: u64,
+
+    // Tracking for the QuotaErrorRule.
+    //
+    // quota_error_window is a bitmask of the most recent responses (bit set for
+    // a quota-type error). quota_error_samples saturates at QUOTA_ERROR_WINDOW.
+    quota_error_consecutive: u32,
+    quota_error_window: u64,
+    quota_error_samples: u32,
+
+    // Request rates, periodically sampled by the NetworkMonitor.
+    qps: f64,
+    qpm: f64,
+
+    // Requests since 00:00 UTC, for the max_per_day quota. Also sampled by the
+    // NetworkMonitor, which keeps counting across a clear().
+    day_count: u64,
+
+    // Scraped from the Prometheus "metrics" URL of the link (role "metrics" only).
+    uptime_secs: Option<u64>,
+    highest_synced_checkpoint: Option<u64>,
+
+    // Local clock minus the clock of the link, from its latest checkpoint (see time_skew.rs).
+    time_skew_ms: Option<i64>,
+    time_skew_measured_at: Option<EpochTimestamp>,
+
+    // Set while the link is probed before entering the selection (see LinkWarmUpRule).
+    warmup: Option<WarmUp>,
+
+    // Rate limited by the provider. Not selected until this time (see THROTTLE_DEFAULT_SECS).
+    throttled_until: Option<EpochTimestamp>,
+    throttle_count: u64,
+
+    // Requests also sent to this link while the best link was slow (see ProxyHedgeConfig).
+    hedge_count: u64,
+    hedge_won_count: u64,
+
+    // End (secs since the UNIX epoch) of the maintenance window the link is in. Not
+    // selected until then (see MaintenanceWindow).
+    maintenance_until: Option<u64>,
 }
 
 impl ServerStats {
@@ -111,6 +273,8 @@ impl ServerStats {
             latency_report_most_recent: None,
 
             latency_report_count: 0,
+            latency_report_sum_ms: 0.0,
+            latency_samples: VecDeque::with_capacity(LATENCY_SAMPLES_WINDOW),
             success_on_first_attempt: 0,
             success_on_retry: 0,
             retry_count: 0,
@@ -127,11 +291,44 @@ impl ServerStats {
             down_score: 0.0,
 
             error_info: None,
+
+            http_status_classes: [0; 6],
+
+            jsonrpc_error_codes: HashMap::new(),
+            jsonrpc_error_other: 0,
+
+            quota_error_consecutive: 0,
+            quota_error_window: 0,
+            quota_error_samples: 0,
+
+            qps: 0.0,
+            qpm: 0.0,
+
+            day_count: 0,
+
+            uptime_secs: None,
+            highest_synced_checkpoint: None,
+
+            time_skew_ms: None,
+            time_skew_measured_at: None,
+
+            warmup: None,
+
+            throttled_until: None,
+            throttle_count: 0,
+
+            hedge_count: 0,
+            hedge_won_count: 0,
+
+            maintenance_until: None,
         }
     }
 
+    // The maintenance window is scheduled, so still applies after a clear.
     pub fn clear(&mut self) {
+        let maintenance_until = self.maintenance_until;
         *self = Self::new(self.alias.clone());
+        self.maintenance_until = maintenance_until;
     }
 
     pub fn alias(&self) -> String {
@@ -150,10 +347,148 @@ impl ServerStats {
         self.is_healthy
     }
 
+    pub fn start_warmup(&mut self, rule: &LinkWarmUpRule) {
+        self.warmup = if rule.is_enabled() {
+            Some(WarmUp {
+                rule: rule.clone(),
+                checks: 0,
+                successes: 0,
+                failed: false,
+            })
+        } else {
+            None
+        };
+    }
+
+    // True until the warm-up passed. A probing link must not be selected.
+    pub fn is_probing(&self) -> bool {
+        self.warmup.is_some()
+    }
+
+    pub fn is_warmup_failed(&self) -> bool {
+        self.warmup.as_ref().map_or(false, |warmup| warmup.failed)
+    }
+
+    // True when the next health check should be done right away (burst not started).
+    pub fn is_warmup_pending(&self) -> bool {
+        self.warmup
+            .as_ref()
+            .map_or(false, |warmup| warmup.checks == 0 && !warmup.failed)
+    }
+
+    pub fn warmup_interval(&self) -> Option<Duration> {
+        self.warmup.as_ref().map(|warmup| warmup.rule.interval())
+    }
+
+    // Account for a health check result while probing. Returns None when not probing.
+    pub fn handle_warmup_check(&mut self, success: bool) -> Option<WarmUpProgress> {
+        let warmup = self.warmup.as_mut()?;
+        if warmup.failed {
+            // Previous burst failed, this check starts a new one.
+            warmup.checks = 0;
+            warmup.successes = 0;
+            warmup.failed = false;
+        }
+        warmup.checks += 1;
+        if success {
+            warmup.successes += 1;
+        }
+
+        let min_success = warmup.rule.min_success.min(warmup.rule.checks);
+        let max_failures = warmup.rule.checks - min_success;
+        if warmup.successes >= min_success {
+            self.warmup = None;
+            Some(WarmUpProgress::Passed)
+        } else if warmup.checks - warmup.successes > max_failures {
+            warmup.failed = true;
+            Some(WarmUpProgress::Failed)
+        } else {
+            Some(WarmUpProgress::Probing)
+        }
+    }
+
+    pub fn is_throttled(&self, now: &EpochTimestamp) -> bool {
+        self.throttled_until.map_or(false, |until| until > *now)
+    }
+
+    // None when not throttled at 'now'.
+    pub fn throttled_until(&self, now: &EpochTimestamp) -> Option<EpochTimestamp> {
+        self.throttled_until.filter(|until| until > now)
+    }
+
+    pub fn throttle_count(&self) -> u64 {
+        self.throttle_count
+    }
+
+    // The provider asked to slow down. This is not a health problem, so the
+    // health score is unchanged.
+    //
+    // Concurrent requests may report the same throttling, so the window is
+    // extended but never shortened.
+    pub fn handle_throttled(&mut self, until: EpochTimestamp) {
+        self.throttle_count += 1;
+        if self.throttled_until.map_or(true, |current| until > current) {
+            self.throttled_until = Some(until);
+        }
+    }
+
+    pub fn hedge_count(&self) -> u64 {
+        self.hedge_count
+    }
+
+    pub fn hedge_won_count(&self) -> u64 {
+        self.hedge_won_count
+    }
+
+    // Only counted. The response (if any) is reported like any other.
+    pub fn handle_hedged(&mut self, won: bool) {
+        self.hedge_count += 1;
+        if won {
+            self.hedge_won_count += 1;
+        }
+    }
+
+    pub fn is_in_maintenance(&self) -> bool {
+        self.maintenance_until.is_some()
+    }
+
+    pub fn maintenance_until(&self) -> Option<u64> {
+        self.maintenance_until
+    }
+
+    // Returns true when the link entered or left a maintenance window.
+    pub fn set_maintenance_until(&mut self, until: Option<u64>) -> bool {
+        let changed = until.is_some() != self.maintenance_until.is_some();
+        self.maintenance_until = until;
+        changed
+    }
+
     pub fn avg_latency_ms(&self) -> f64 {
         self.latency_report_avg
     }
 
+    // 90th percentile of the most recent latency reports. None until the first report.
+    pub fn latency_p90_ms(&self) -> Option<f64> {
+        if self.latency_samples.is_empty() {
+            return None;
+        }
+        let mut samples: Vec<u32> = self.latency_samples.iter().copied().collect();
+        samples.sort_unstable();
+        let idx = (samples.len() * 9).div_ceil(10) - 1;
+        Some(samples[idx] as f64 / 1000.0)
+    }
+
+    // Failed user requests (since the stats were last cleared). Health checks not included.
+    pub fn error_rate_pct(&self) -> f64 {
+        let mut sum_request = 0;
+        let mut sum_success = 0;
+        self.get_accum_stats(&mut sum_request, &mut sum_success);
+        if sum_request == 0 {
+            return 0.0;
+        }
+        (sum_request - sum_success) as f64 * 100.0 / sum_request as f64
+    }
+
     pub fn success_on_first_attempt(&self) -> u64 {
         self.success_on_first_attempt
     }
@@ -162,6 +497,60 @@ impl ServerStats {
         self.success_on_retry
     }
 
+    // Requests (success or failure) since the stats were last cleared.
+    pub fn request_count(&self) -> u64 {
+        self.success_on_first_attempt + self.success_on_retry + self.get_accum_failure()
+    }
+
+    pub fn qps(&self) -> f64 {
+        self.qps
+    }
+
+    pub fn qpm(&self) -> f64 {
+        self.qpm
+    }
+
+    pub fn set_load(&mut self, qps: f64, qpm: f64) {
+        self.qps = qps;
+        self.qpm = qpm;
+    }
+
+    pub fn day_count(&self) -> u64 {
+        self.day_count
+    }
+
+    pub fn set_day_count(&mut self, day_count: u64) {
+        self.day_count = day_count;
+    }
+
+    pub fn uptime_secs(&self) -> Option<u64> {
+        self.uptime_secs
+    }
+
+    pub fn highest_synced_checkpoint(&self) -> Option<u64> {
+        self.highest_synced_checkpoint
+    }
+
+    // A failed scrape clears the values (stale data is worse than none).
+    pub fn set_node_metrics(&mut self, uptime_secs: Option<u64>, checkpoint: Option<u64>) {
+        self.uptime_secs = uptime_secs;
+        self.highest_synced_checkpoint = checkpoint;
+    }
+
+    pub fn time_skew_ms(&self) -> Option<i64> {
+        self.time_skew_ms
+    }
+
+    pub fn time_skew_measured_at(&self) -> Option<EpochTimestamp> {
+        self.time_skew_measured_at
+    }
+
+    // A failed measurement keeps the previous value (a clock does not drift that fast).
+    pub fn set_time_skew_ms(&mut self, skew_ms: i64, now: EpochTimestamp) {
+        self.time_skew_ms = Some(skew_ms);
+        self.time_skew_measured_at = Some(now);
+    }
+
     fn get_accum_failure(&self) -> u64 {
         let mut total = 0;
         for i in 0..REQUEST_FAILED_VEC_SIZE {
@@ -181,6 +570,8 @@ impl ServerStats {
         &self,
         network_down: &mut u64,
         bad_request: &mut u64,
+        overload: &mut u64,
+        client_errors: &mut u64,
         other_failures: &mut u64,
     ) {
         // Sum all the request failures.
@@ -189,32 +580,235 @@ impl ServerStats {
         // Now isolate a few notable one for the caller.
         *network_down = self.req_failure_reasons[REQUEST_FAILED_NETWORK_DOWN as usize];
         *bad_request = self.req_failure_reasons[REQUEST_FAILED_BAD_REQUEST_HTTP as usize];
-        *other_failures = total - (*network_down + *bad_request);
+        *overload = self.req_failure_reasons[REQUEST_FAILED_OVERLOAD as usize];
+        *client_errors = self.req_failure_reasons[REQUEST_FAILED_INVALID_REQUEST as usize];
+        let ip_denied = self.ip_denied_count();
+        *other_failures =
+            total - (*network_down + *bad_request + *overload + *client_errors + ip_denied);
+    }
+
+    // Requests rejected because of proxy_allowed_ips (never counted in the others).
+    pub fn ip_denied_count(&self) -> u64 {
+        self.req_failure_reasons[REQUEST_FAILED_IP_DENIED as usize]
+    }
+
+    // Count of HTTP responses for a status class (e.g. 2 for all 2xx).
+    pub fn http_status_class_count(&self, class: u16) -> u64 {
+        self.http_status_classes
+            .get(class as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // The 'n' most frequent JSON-RPC error codes as (code, count), most frequent first.
+    pub fn top_jsonrpc_error_codes(&self, n: usize) -> Vec<(i32, u64)> {
+        let mut codes: Vec<(i32, u64)> = self
+            .jsonrpc_error_codes
+            .iter()
+            .map(|(code, count)| (*code, *count))
+            .collect();
+        // Sort by descending count, then code for a stable output.
+        codes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        codes.truncate(n);
+        codes
+    }
+
+    pub fn record_http_status(&mut self, status: u16) {
+        let class = (status / 100) as usize;
+        if class < self.http_status_classes.len() {
+            self.http_status_classes[class] += 1;
+        } else {
+            self.http_status_classes[0] += 1; // Invalid status, count as "other".
+        }
     }
 
     pub fn latency_report_most_recent(&self) -> Option<EpochTimestamp> {
         self.latency_report_most_recent
     }
 
+    // Average of all the latency reports, including the ones before a daemon restart.
+    pub fn lifetime_avg_latency_ms(&self) -> Option<f64> {
+        if self.latency_report_count == 0 {
+            return None;
+        }
+        Some(self.latency_report_sum_ms / self.latency_report_count as f64)
+    }
+
+    // The cumulative counters (see ProxyStatsFile).
+    pub fn to_persisted(&self) -> PersistedServerStats {
+        PersistedServerStats {
+            success_on_first_attempt: self.success_on_first_attempt,
+            success_on_retry: self.success_on_retry,
+            retry_count: self.retry_count,
+            req_failure_reasons: self.req_failure_reasons.to_vec(),
+            req_unknown_reason: self.req_unknown_reason,
+            send_failure_reasons: self.send_failure_reasons.to_vec(),
+            send_unknown_reason: self.send_unknown_reason,
+            req_failure_internal: self.req_failure_internal,
+            latency_report_count: self.latency_report_count,
+            latency_report_sum_ms: self.latency_report_sum_ms,
+            http_status_classes: self.http_status_classes.to_vec(),
+            jsonrpc_error_codes: self.jsonrpc_error_codes.clone(),
+            jsonrpc_error_other: self.jsonrpc_error_other,
+            throttle_count: self.throttle_count,
+            hedge_count: self.hedge_count,
+            hedge_won_count: self.hedge_won_count,
+        }
+    }
+
+    // Added to the counters (also works on stats already counting since the start).
+    pub fn merge_persisted(&mut self, persisted: &PersistedServerStats) {
+        let add = |counters: &mut [u64], persisted: &[u64]| {
+            for (counter, value) in counters.iter_mut().zip(persisted) {
+                *counter += value;
+            }
+        };
+        self.success_on_first_attempt += persisted.success_on_first_attempt;
+        self.success_on_retry += persisted.success_on_retry;
+        self.retry_count += persisted.retry_count;
+        add(
+            &mut self.req_failure_reasons,
+            &persisted.req_failure_reasons,
+        );
+        self.req_unknown_reason += persisted.req_unknown_reason;
+        add(
+            &mut self.send_failure_reasons,
+            &persisted.send_failure_reasons,
+        );
+        self.send_unknown_reason += persisted.send_unknown_reason;
+        self.req_failure_internal += persisted.req_failure_internal;
+        self.latency_report_count += persisted.latency_report_count;
+        self.latency_report_sum_ms += persisted.latency_report_sum_ms;
+        add(
+            &mut self.http_status_classes,
+            &persisted.http_status_classes,
+        );
+        for (code, count) in &persisted.jsonrpc_error_codes {
+            if let Some(current) = self.jsonrpc_error_codes.get_mut(code) {
+                *current += count;
+            } else if self.jsonrpc_error_codes.len() < JSONRPC_ERROR_CODES_MAX {
+                self.jsonrpc_error_codes.insert(*code, *count);
+            } else {
+                self.jsonrpc_error_other += count;
+            }
+        }
+        self.jsonrpc_error_other += persisted.jsonrpc_error_other;
+        self.throttle_count += persisted.throttle_count;
+        self.hedge_count += persisted.hedge_count;
+        self.hedge_won_count += persisted.hedge_won_count;
+    }
+
     fn is_client_fault(reason: RequestFailedReason) -> bool {
         // Identify reason for which the failure can be
         // attributed to the client doing a bad request.
-        matches!(reason, REQUEST_FAILED_BAD_REQUEST_HTTP)
+        //
+        // Load shedding, the configured rate limits and denied source IPs are not a fault
+        // of the servers either, nor a transaction of unknown outcome (see proxy_timeouts.rs).
+        matches!(
+            reason,
+            REQUEST_FAILED_BAD_REQUEST_HTTP
+                | REQUEST_FAILED_OVERLOAD
+                | REQUEST_FAILED_INVALID_REQUEST
+                | REQUEST_FAILED_IP_DENIED
+                | REQUEST_FAILED_OUTCOME_UNKNOWN
+                | REQUEST_FAILED_RATE_LIMITED
+        )
     }
 
+    // 'jsonrpc_error' is set when the response is a JSON-RPC error (with its category
+    // and the rule to apply for quota-type errors).
     pub fn handle_resp_ok(
         &mut self,
         initiation_time: EpochTimestamp,
         retry_count: u8,
         _prep_microsecs: u32,
         _latency_microsecs: u32,
+        jsonrpc_error: Option<(i32, JsonRpcErrorCategory, &QuotaErrorRule)>,
     ) {
-        self.inc_up_score(initiation_time, NORMAL_SCORE_UP);
         if retry_count == 0 {
             self.success_on_first_attempt += 1;
         } else {
             self.success_on_retry += 1;
         }
+
+        if let Some((code, category, rule)) = jsonrpc_error {
+            if self.handle_jsonrpc_error(initiation_time, code, category, rule) {
+                // A quota-type or server-fault error is not a sign of good health.
+                return;
+            }
+        } else {
+            self.track_quota_error(false);
+        }
+        self.inc_up_score(initiation_time, NORMAL_SCORE_UP);
+    }
+
+    // Count the JSON-RPC error code and apply the QuotaErrorRule.
+    //
+    // Returns true if the code is a quota-type or a server-fault error (in which
+    // case the health may have been degraded).
+    //
+    // A client-fault error (e.g. an invalid transaction) is never held against the
+    // server, even when its code is configured as quota-type.
+    pub fn handle_jsonrpc_error(
+        &mut self,
+        initiation_time: EpochTimestamp,
+        code: i32,
+        category: JsonRpcErrorCategory,
+        rule: &QuotaErrorRule,
+    ) -> bool {
+        if let Some(count) = self.jsonrpc_error_codes.get_mut(&code) {
+            *count += 1;
+        } else if self.jsonrpc_error_codes.len() < JSONRPC_ERROR_CODES_MAX {
+            self.jsonrpc_error_codes.insert(code, 1);
+        } else {
+            self.jsonrpc_error_other += 1;
+        }
+
+        if category == JsonRpcErrorCategory::ClientFault {
+            self.track_quota_error(false);
+            return false;
+        }
+
+        if !rule.is_quota_code(code) {
+            self.track_quota_error(false);
+            if category == JsonRpcErrorCategory::ServerFault {
+                self.inc_down_score(initiation_time);
+                self.error_info = Some(format!("JSON-RPC server error ({})", code));
+                return true;
+            }
+            return false;
+        }
+
+        self.track_quota_error(true);
+
+        let consecutive_triggered =
+            rule.consecutive != 0 && self.quota_error_consecutive >= rule.consecutive;
+
+        let pct_triggered =
+            if rule.pct != 0 && self.quota_error_samples >= (rule.min_samples as u32).max(1) {
+                let n_errors = self.quota_error_window.count_ones() * 100;
+                n_errors >= (rule.pct as u32) * self.quota_error_samples
+            } else {
+                false
+            };
+
+        if consecutive_triggered || pct_triggered {
+            self.inc_down_score(initiation_time);
+            self.error_info = Some(format!("Quota errors ({})", code));
+        }
+        true
+    }
+
+    fn track_quota_error(&mut self, is_quota_error: bool) {
+        if is_quota_error {
+            self.quota_error_consecutive = self.quota_error_consecutive.saturating_add(1);
+        } else {
+            self.quota_error_consecutive = 0;
+        }
+        self.quota_error_window = (self.quota_error_window << 1) | (is_quota_error as u64);
+        if self.quota_error_samples < QUOTA_ERROR_WINDOW {
+            self.quota_error_samples += 1;
+        }
     }
 
     pub fn handle_resp_err(
@@ -283,6 +877,7 @@ impl ServerStats {
                     self.error_info = Some("Server Unreachable".to_string())
                 }
                 SEND_FAILED_RESP_HTTP_STATUS => {
+                    self.record_http_status(status);
                     let status_code = http::StatusCode::from_u16(status);
                     match status_code {
                         Ok(status_code) => {
@@ -320,6 +915,11 @@ impl ServerStats {
             log::error!("ServerStats::report_latency() clamped");
         }
 
+        if self.latency_samples.len() == LATENCY_SAMPLES_WINDOW {
+            self.latency_samples.pop_front();
+        }
+        self.latency_samples.push_back(latency_microsecs);
+
         let bonus = if latency_microsecs >= SLOW_LATENCY_LIMIT_MICROSECONDS {
             WEAK_SCORE_UP
         } else {
@@ -330,7 +930,8 @@ impl ServerStats {
             // One-time initialization
             self.latency_report_most_recent = Some(initiation_time);
             self.latency_report_avg = latency_microsecs as f64 / 1000.0; // to milliseconds.
-            self.latency_report_count = 1;
+            self.latency_report_count += 1; // Not always 0 (see merge_persisted).
+            self.latency_report_sum_ms += self.latency_report_avg;
             // Reflect that the server is healthy, but do not give too
             // much of a bonus if extremely slow (>4 secs).
             self.inc_up_score(initiation_time, bonus);
@@ -346,6 +947,7 @@ impl ServerStats {
         // This is a valid latency report.
         self.latency_report_most_recent = Some(initiation_time);
         self.latency_report_count += 1;
+        self.latency_report_sum_ms += latency_microsecs as f64 / 1000.0;
 
         // Reflect that the server was healthy (at least at the moment the request was initiated).
         self.inc_up_score(initiation_time, bonus);
@@ -0,0 +1,404 @@
+// Shadow mode: a share of the read requests also sent to a second link, to validate
+// a provider against the one in use (see getShadowReport).
+//
+// The proxy_server sends the copy in the background, after the response is returned
+// to the client (never affecting its response or latency). The two responses are
+// then compared structurally, except for the fields that legitimately differ (e.g.
+// the request "id", see SHADOW_IGNORED_PATHS).
+//
+// The shadow traffic is not reported to the NetworkMonitor, so it never affects the
+// health, stats or selection of the links. It respects the rate limits of the shadow
+// link instead: a copy is dropped while the link is throttled, or when the proxied
+// load plus the shadow load would leave less than RATE_LIMIT_MIN_HEADROOM.
+//
+// Written from background tasks, so it has its own std Mutex (held very briefly)
+// instead of requiring a write lock on the globals.
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::hash::Hasher;
+use std::sync::{Arc, Mutex};
+
+use common::basic_types::{
+    jsonrpc_method_kind, EpochTimestamp, JsonRpcMethodKind, TargetServerIdx,
+};
+use serde_json::Value as JsonValue;
+use twox_hash::XxHash32;
+
+use super::{TargetServer, RATE_LIMIT_MIN_HEADROOM};
+
+// Default of "shadow_pct" (suibase.yaml).
+pub const DEFAULT_SHADOW_PCT: u8 = 10;
+
+// Last mismatches kept (see getShadowReport).
+pub const SHADOW_DIFFS_CAPACITY: usize = 10;
+
+// Differences kept per mismatch, and max length of a value displayed in one.
+pub const SHADOW_DIFF_MAX_PATHS: usize = 10;
+const SHADOW_DIFF_MAX_VALUE_LEN: usize = 80;
+
+// Never compared (differ between any two responses).
+pub const SHADOW_IGNORED_PATHS: &[&str] = &["id", "jsonrpc", "error.data"];
+
+// Not the seed of the canary pick, so the two samples are independent.
+const SHADOW_SAMPLING_SEED: u32 = 1;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ProxyShadowConfig {
+    pub link: String,               // Alias of the shadow link.
+    pub pct: u8,                    // 1 to 100. Share of the read requests also sent to it.
+    pub ignore_fields: Vec<String>, // Field names not compared, at any depth.
+}
+
+impl ProxyShadowConfig {
+    // Only read methods (a copy must never change the state twice).
+    //
+    // The decision is from the trace id, so the same for every attempt of a request.
+    pub fn is_sampled(&self, method: &str, request_id: &str) -> bool {
+        if jsonrpc_method_kind(method) != JsonRpcMethodKind::Read {
+            return false;
+        }
+        let mut hasher = XxHash32::with_seed(SHADOW_SAMPLING_SEED);
+        hasher.write(request_id.as_bytes());
+        hasher.finish() % 100 < self.pct as u64
+    }
+}
+
+// Requests the shadow traffic may still send to a link in the current second, minute
+// and UTC day. None when the link has no such limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShadowAllowance {
+    pub per_secs: Option<f64>,
+    pub per_min: Option<f64>,
+    pub per_day: Option<f64>,
+}
+
+impl ShadowAllowance {
+    pub fn new(target_server: &TargetServer, now: &EpochTimestamp) -> Self {
+        if target_server.stats.is_throttled(now) {
+            return Self {
+                per_secs: Some(0.0),
+                per_min: Some(0.0),
+                per_day: Some(0.0),
+            };
+        }
+        let config = target_server.get_config();
+        let remaining = |load: f64, limit: Option<u32>| {
+            limit.map(|limit| (limit as f64 * (1.0 - RATE_LIMIT_MIN_HEADROOM) - load).max(0.0))
+        };
+        Self {
+            per_secs: remaining(target_server.stats.qps(), config.max_per_secs),
+            per_min: remaining(target_server.stats.qpm(), config.max_per_min),
+            per_day: remaining(target_server.stats.day_count() as f64, config.max_per_day),
+        }
+    }
+}
+
+// Resolved by the proxy_server for a request (under the globals read lock).
+#[derive(Debug, Clone)]
+pub struct ShadowTarget {
+    pub config: ProxyShadowConfig,
+    pub server_idx: TargetServerIdx,
+    pub uri: String,
+    pub allowance: ShadowAllowance,
+    pub stats: ShadowStatsMT,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShadowMethodStats {
+    pub compared: u64,
+    pub mismatches: u64,
+    pub failed: u64, // No usable response from the shadow link.
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowDiff {
+    pub request_id: String,
+    pub method: String,
+    pub server_idx: TargetServerIdx, // Link that answered the client.
+    pub unix_time_ms: u64,
+    // e.g. "result.version: \"12\" != \"13\"" (the value of the link first, then
+    // the one of the shadow link).
+    pub differences: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ShadowStats {
+    methods: BTreeMap<String, ShadowMethodStats>,
+    dropped: u64, // Copies not sent because of the rate limits of the shadow link.
+    diffs: VecDeque<ShadowDiff>,
+    // Copies sent in the current second, minute and UTC day: (window, count).
+    windows: [(u64, u64); 3],
+}
+
+pub type ShadowStatsMT = Arc<Mutex<ShadowStats>>;
+
+impl ShadowStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_mt() -> ShadowStatsMT {
+        Arc::new(Mutex::new(Self::new()))
+    }
+
+    // Counts a copy against the allowance of the shadow link. Returns false when
+    // the copy must be dropped.
+    pub fn try_send(&mut self, allowance: &ShadowAllowance, now_ms: u64) -> bool {
+        let now_secs = now_ms / 1000;
+        let limits = [
+            (now_secs, allowance.per_secs),
+            (now_secs / 60, allowance.per_min),
+            (now_secs / 86_400, allowance.per_day),
+        ];
+        let mut allowed = true;
+        for (slot, (window, limit)) in self.windows.iter_mut().zip(limits) {
+            if slot.0 != window {
+                *slot = (window, 0);
+            }
+            if matches!(limit, Some(limit) if (slot.1 + 1) as f64 > limit) {
+                allowed = false;
+            }
+        }
+        if allowed {
+            for slot in self.windows.iter_mut() {
+                slot.1 += 1;
+            }
+        } else {
+            self.dropped += 1;
+        }
+        allowed
+    }
+
+    pub fn record_failed(&mut self, method: &str) {
+        self.methods.entry(method.to_string()).or_default().failed += 1;
+    }
+
+    // 'diff' is None when the two responses matched.
+    pub fn record_compared(&mut self, method: &str, diff: Option<ShadowDiff>) {
+        let stats = self.methods.entry(method.to_string()).or_default();
+        stats.compared += 1;
+        if let Some(diff) = diff {
+            stats.mismatches += 1;
+            if self.diffs.len() >= SHADOW_DIFFS_CAPACITY {
+                self.diffs.pop_front();
+            }
+            self.diffs.push_back(diff);
+        }
+    }
+
+    pub fn methods(&self) -> &BTreeMap<String, ShadowMethodStats> {
+        &self.methods
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // Most recent first.
+    pub fn latest_diffs(&self) -> Vec<ShadowDiff> {
+        self.diffs.iter().rev().cloned().collect()
+    }
+}
+
+// Differences between the response of the link and the one of the shadow link.
+// Empty when they match.
+pub fn compare_responses(
+    response: &JsonValue,
+    shadow: &JsonValue,
+    ignore_fields: &[String],
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    diff_json("", response, shadow, ignore_fields, &mut differences);
+    differences
+}
+
+fn diff_json(
+    path: &str,
+    response: &JsonValue,
+    shadow: &JsonValue,
+    ignore_fields: &[String],
+    differences: &mut Vec<String>,
+) {
+    if differences.len() >= SHADOW_DIFF_MAX_PATHS {
+        return;
+    }
+    match (response, shadow) {
+        (JsonValue::Object(response), JsonValue::Object(shadow)) => {
+            let keys: BTreeSet<&String> = response.keys().chain(shadow.keys()).collect();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                if SHADOW_IGNORED_PATHS.contains(&child.as_str())
+                    || ignore_fields.iter().any(|field| field == key)
+                {
+                    continue;
+                }
+                match (response.get(key), shadow.get(key)) {
+                    (Some(response), Some(shadow)) => {
+                        diff_json(&child, response, shadow, ignore_fields, differences)
+                    }
+                    (response, shadow) => {
+                        push_difference(&child, response, shadow, differences);
+                    }
+                }
+            }
+        }
+        (JsonValue::Array(response), JsonValue::Array(shadow)) => {
+            for (i, (response, shadow)) in response.iter().zip(shadow.iter()).enumerate() {
+                let child = format!("{}[{}]", path, i);
+                diff_json(&child, response, shadow, ignore_fields, differences);
+            }
+            if response.len() != shadow.len() && differences.len() < SHADOW_DIFF_MAX_PATHS {
+                differences.push(format!(
+                    "{}: {} items != {} items",
+                    display_path(path),
+                    response.len(),
+                    shadow.len()
+                ));
+            }
+        }
+        (response, shadow) => {
+            if response != shadow {
+                push_difference(path, Some(response), Some(shadow), differences);
+            }
+        }
+    }
+}
+
+fn push_difference(
+    path: &str,
+    response: Option<&JsonValue>,
+    shadow: Option<&JsonValue>,
+    differences: &mut Vec<String>,
+) {
+    if differences.len() < SHADOW_DIFF_MAX_PATHS {
+        differences.push(format!(
+            "{}: {} != {}",
+            display_path(path),
+            display_value(response),
+            display_value(shadow)
+        ));
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "(response)"
+    } else {
+        path
+    }
+}
+
+fn display_value(value: Option<&JsonValue>) -> String {
+    let value = match value {
+        Some(value) => value.to_string(),
+        None => return "(missing)".to_string(),
+    };
+    if value.chars().count() > SHADOW_DIFF_MAX_VALUE_LEN {
+        let truncated: String = value.chars().take(SHADOW_DIFF_MAX_VALUE_LEN).collect();
+        format!("{}...", truncated)
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shadow_compare_responses() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "version": "12", "digest": "abc", "owners": ["0x1", "0x2"] }
+        });
+        let same = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "result": { "owners": ["0x1", "0x2"], "digest": "abc", "version": "12" }
+        });
+        assert!(compare_responses(&response, &same, &[]).is_empty());
+
+        let shadow = json!({
+            "id": 1,
+            "result": { "version": "13", "owners": ["0x1"], "extra": true }
+        });
+        assert_eq!(
+            compare_responses(&response, &shadow, &[]),
+            vec![
+                "result.digest: \"abc\" != (missing)",
+                "result.extra: (missing) != true",
+                "result.owners: 2 items != 1 items",
+                "result.version: \"12\" != \"13\"",
+            ]
+        );
+        let ignore_fields = vec!["version".to_string(), "digest".to_string()];
+        assert_eq!(
+            compare_responses(&response, &shadow, &ignore_fields).len(),
+            2
+        );
+
+        // The error data is added by each proxy/provider.
+        let error = json!({ "error": { "code": -32602, "data": "link a" } });
+        let other = json!({ "error": { "code": -32602, "data": "link b" } });
+        assert!(compare_responses(&error, &other, &[]).is_empty());
+        assert_eq!(
+            compare_responses(&error, &json!("x"), &[]),
+            vec![format!("(response): {} != \"x\"", error)]
+        );
+    }
+
+    #[test]
+    fn test_shadow_stats() {
+        let config = ProxyShadowConfig {
+            link: "shadow".to_string(),
+            pct: 100,
+            ignore_fields: Vec::new(),
+        };
+        assert!(config.is_sampled("sui_getObject", "req-1"));
+        assert!(!config.is_sampled("sui_executeTransactionBlock", "req-1"));
+        assert!(!config.is_sampled("batch", "req-1"));
+        let config = ProxyShadowConfig { pct: 0, ..config };
+        assert!(!config.is_sampled("sui_getObject", "req-1"));
+
+        // 2 copies per second at most.
+        let allowance = ShadowAllowance {
+            per_secs: Some(2.5),
+            ..Default::default()
+        };
+        let mut stats = ShadowStats::new();
+        assert!(stats.try_send(&allowance, 1_000));
+        assert!(stats.try_send(&allowance, 1_500));
+        assert!(!stats.try_send(&allowance, 1_999));
+        assert!(stats.try_send(&allowance, 2_000));
+        assert_eq!(stats.dropped(), 1);
+        assert!(stats.try_send(&ShadowAllowance::default(), 2_000));
+
+        for i in 0..SHADOW_DIFFS_CAPACITY + 2 {
+            let diff = ShadowDiff {
+                request_id: format!("req-{}", i),
+                method: "sui_getObject".to_string(),
+                server_idx: 0,
+                unix_time_ms: i as u64,
+                differences: vec!["result: 1 != 2".to_string()],
+            };
+            stats.record_compared("sui_getObject", Some(diff));
+        }
+        stats.record_compared("sui_getObject", None);
+        stats.record_failed("sui_getObject");
+        let method_stats = &stats.methods()["sui_getObject"];
+        assert_eq!(method_stats.compared, SHADOW_DIFFS_CAPACITY as u64 + 3);
+        assert_eq!(method_stats.mismatches, SHADOW_DIFFS_CAPACITY as u64 + 2);
+        assert_eq!(method_stats.failed, 1);
+        let diffs = stats.latest_diffs();
+        assert_eq!(diffs.len(), SHADOW_DIFFS_CAPACITY);
+        assert_eq!(
+            diffs[0].request_id,
+            format!("req-{}", SHADOW_DIFFS_CAPACITY + 1)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// Durations of the startup phases (see getDaemonStats).
+//
+// The APIServer answers as soon as bound, while every workdir initializes in the
+// background, concurrently:
+//   - config_loaded: first load of its suibase.yaml (AdminController).
+//   - status_known:  first "<workdir> status" (CliPoller).
+//
+// Until its status is known, a workdir is INITIALIZING in getWorkdirsStatus, and the
+// API calls needing it return an Initializing error (instead of waiting for it).
+//
+// All durations are in milliseconds since the start of the daemon.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use common::basic_types::WorkdirIdx;
+
+use super::WORKDIRS_KEYS;
+
+// Test hook: delays the first status of every workdir (e.g. "3000").
+pub const STARTUP_INIT_DELAY_ENV: &str = "SUIBASE_DAEMON_INIT_DELAY_MS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupPhase {
+    ApiReady,
+    ConfigLoaded(WorkdirIdx),
+    StatusKnown(WorkdirIdx),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkdirStartup {
+    pub config_loaded_ms: Option<u64>,
+    pub status_known_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartupPhases {
+    pub api_ready_ms: Option<u64>,
+    pub workdirs: Vec<WorkdirStartup>, // Order of WORKDIRS_KEYS.
+}
+
+impl StartupPhases {
+    // Once the status of every workdir is known.
+    pub fn initialized_ms(&self) -> Option<u64> {
+        self.workdirs
+            .iter()
+            .map(|workdir| workdir.status_known_ms)
+            .try_fold(0, |max, ms| ms.map(|ms| max.max(ms)))
+    }
+}
+
+#[derive(Debug)]
+pub struct StartupStats {
+    started_at: Instant,
+    init_delay: Duration,
+    phases: Mutex<StartupPhases>,
+}
+
+impl StartupStats {
+    pub fn new() -> Self {
+        let init_delay = std::env::var(STARTUP_INIT_DELAY_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        Self::with_init_delay(Duration::from_millis(init_delay))
+    }
+
+    pub fn with_init_delay(init_delay: Duration) -> Self {
+        Self {
+            started_at: Instant::now(),
+            init_delay,
+            phases: Mutex::new(StartupPhases {
+                api_ready_ms: None,
+                workdirs: vec![WorkdirStartup::default(); WORKDIRS_KEYS.len()],
+            }),
+        }
+    }
+
+    pub fn init_delay(&self) -> Duration {
+        self.init_delay
+    }
+
+    // Only the first time of each phase is kept (e.g. not the later config changes).
+    pub fn record(&self, phase: StartupPhase) {
+        let elapsed_ms = self.started_at.elapsed().as_millis() as u64;
+        let mut phases = self.phases.lock().unwrap();
+        let slot = match phase {
+            StartupPhase::ApiReady => &mut phases.api_ready_ms,
+            StartupPhase::ConfigLoaded(workdir_idx) => {
+                match phases.workdirs.get_mut(workdir_idx as usize) {
+                    Some(workdir) => &mut workdir.config_loaded_ms,
+                    None => return,
+                }
+            }
+            StartupPhase::StatusKnown(workdir_idx) => {
+                match phases.workdirs.get_mut(workdir_idx as usize) {
+                    Some(workdir) => &mut workdir.status_known_ms,
+                    None => return,
+                }
+            }
+        };
+        if slot.is_none() {
+            *slot = Some(elapsed_ms);
+            log::info!("startup {:?} after {}ms", phase, elapsed_ms);
+        }
+    }
+
+    pub fn snapshot(&self) -> StartupPhases {
+        self.phases.lock().unwrap().clone()
+    }
+}
+
+impl Default for StartupStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_phases() {
+        let stats = StartupStats::with_init_delay(Duration::ZERO);
+        assert_eq!(stats.init_delay(), Duration::ZERO);
+        let phases = stats.snapshot();
+        assert_eq!(phases.api_ready_ms, None);
+        assert_eq!(phases.workdirs.len(), WORKDIRS_KEYS.len());
+        assert_eq!(phases.initialized_ms(), None);
+
+        stats.record(StartupPhase::ApiReady);
+        for workdir_idx in 0..WORKDIRS_KEYS.len() as WorkdirIdx {
+            stats.record(StartupPhase::ConfigLoaded(workdir_idx));
+            if workdir_idx != 0 {
+                stats.record(StartupPhase::StatusKnown(workdir_idx));
+            }
+        }
+        let phases = stats.snapshot();
+        assert!(phases.api_ready_ms.is_some());
+        assert!(phases.workdirs[0].config_loaded_ms.is_some());
+        assert_eq!(phases.initialized_ms(), None);
+
+        std::thread::sleep(Duration::from_millis(5));
+        stats.record(StartupPhase::StatusKnown(0));
+        stats.record(StartupPhase::StatusKnown(99)); // Ignored.
+        let phases = stats.snapshot();
+        let last = phases.workdirs[0].status_known_ms.unwrap();
+        assert_eq!(phases.initialized_ms(), Some(last));
+        assert!(last >= phases.workdirs[1].status_known_ms.unwrap() + 5);
+
+        // Only the first time.
+        stats.record(StartupPhase::ApiReady);
+        assert_eq!(stats.snapshot().api_ready_ms, phases.api_ready_ms);
+    }
+}
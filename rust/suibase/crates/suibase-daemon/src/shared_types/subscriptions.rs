@@ -0,0 +1,54 @@
+// Delivery metrics of the Sui event subscriptions of each workdir (see
+// getSubscriptions).
+//
+// Each WebSocketWorker publishes the metrics of all its subscriptions on every audit,
+// replacing the previous snapshot of its workdir. The metrics themselves are kept by
+// the SubscriptionTracking (never reset on a reconnect).
+use std::collections::HashMap;
+
+use common::basic_types::WorkdirIdx;
+
+use crate::api::SubscriptionInfo;
+
+#[derive(Debug, Default)]
+pub struct GlobalsSubscriptionsST {
+    by_workdir: HashMap<WorkdirIdx, Vec<SubscriptionInfo>>,
+}
+
+impl GlobalsSubscriptionsST {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, workdir_idx: WorkdirIdx, subscriptions: Vec<SubscriptionInfo>) {
+        self.by_workdir.insert(workdir_idx, subscriptions);
+    }
+
+    pub fn get(&self, workdir_idx: WorkdirIdx) -> Vec<SubscriptionInfo> {
+        self.by_workdir
+            .get(&workdir_idx)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Of all the workdirs.
+    pub fn count(&self) -> usize {
+        self.by_workdir
+            .values()
+            .map(|subscriptions| subscriptions.len())
+            .sum()
+    }
+
+    // e.g. "Counter: 10 events dropped in the last 60 secs"
+    pub fn degraded_reasons(&self, workdir_idx: WorkdirIdx) -> Vec<String> {
+        self.by_workdir
+            .get(&workdir_idx)
+            .into_iter()
+            .flatten()
+            .filter_map(|subscription| {
+                let reason = subscription.degraded_reason.as_ref()?;
+                Some(format!("{}: {}", subscription.package_name, reason))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,141 @@
+// Provenance of the sui binary used by a workdir.
+//
+// Derived from the files maintained by the scripts:
+//
+//   workdirs/{workdir}/sui-repo              Symlink. Toward a user repo after set-sui-repo,
+//                                            otherwise toward sui-repo-default.
+//   workdirs/{workdir}/.state/precompiled    Exists when the binaries were downloaded (path
+//                                            of the download).
+//   workdirs/{workdir}/sui-repo/target/debug/sui  The binary (also when downloaded).
+//
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::api::SuiBinaryInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuiBinaryOrigin {
+    RepoBuild,    // Built from a user repo (set-sui-repo).
+    Downloaded,   // Precompiled release downloaded by suibase.
+    DefaultBuild, // Built by suibase from its default repo.
+}
+
+impl SuiBinaryOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuiBinaryOrigin::RepoBuild => "repo-build",
+            SuiBinaryOrigin::Downloaded => "downloaded",
+            SuiBinaryOrigin::DefaultBuild => "default-build",
+        }
+    }
+}
+
+impl std::fmt::Display for SuiBinaryOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// The set-sui-repo path, None when using the default repo.
+pub fn sui_repo_override(workdir_path: &Path) -> Option<String> {
+    let target = std::fs::read_link(workdir_path.join("sui-repo")).ok()?;
+    if target == workdir_path.join("sui-repo-default") {
+        return None;
+    }
+    Some(target.to_string_lossy().to_string())
+}
+
+// None when there is no sui binary (e.g. workdir not initialized).
+//
+// 'version' is the client version already known by the caller (see CliPoller).
+pub fn probe_sui_binary(workdir_path: &Path, version: Option<String>) -> Option<SuiBinaryInfo> {
+    let binary = workdir_path.join("sui-repo/target/debug/sui");
+    let metadata = std::fs::metadata(binary).ok()?;
+    let binary_mtime = metadata
+        .modified()
+        .ok()
+        .map(|mtime| DateTime::<Utc>::from(mtime).to_rfc3339());
+
+    let sui_repo_path = sui_repo_override(workdir_path);
+    let origin = if sui_repo_path.is_some() {
+        SuiBinaryOrigin::RepoBuild
+    } else if std::fs::read_to_string(workdir_path.join(".state/precompiled"))
+        .is_ok_and(|precompiled| !precompiled.trim().is_empty())
+    {
+        SuiBinaryOrigin::Downloaded
+    } else {
+        SuiBinaryOrigin::DefaultBuild
+    };
+
+    Some(SuiBinaryInfo {
+        origin: origin.to_string(),
+        sui_repo_path,
+        binary_mtime,
+        version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same layout as created by the scripts.
+    fn fixture_workdir(name: &str) -> std::path::PathBuf {
+        let workdir = std::env::temp_dir()
+            .join(format!("sbsd-sui-binary-{}-{}", name, std::process::id()))
+            .join("testnet");
+        let _ = std::fs::remove_dir_all(&workdir);
+        std::fs::create_dir_all(workdir.join("sui-repo-default/target/debug")).unwrap();
+        std::fs::create_dir_all(workdir.join(".state")).unwrap();
+        std::os::unix::fs::symlink(workdir.join("sui-repo-default"), workdir.join("sui-repo"))
+            .unwrap();
+        workdir
+    }
+
+    #[test]
+    fn test_probe_downloaded() {
+        let workdir = fixture_workdir("downloaded");
+        assert_eq!(probe_sui_binary(&workdir, None), None);
+
+        std::fs::write(workdir.join("sui-repo/target/debug/sui"), "bin").unwrap();
+        let info = probe_sui_binary(&workdir, None).unwrap();
+        assert_eq!(info.origin, "default-build");
+
+        let precompiled = workdir.join(".cache/precompiled/testnet-v1.30.1");
+        std::fs::write(
+            workdir.join(".state/precompiled"),
+            precompiled.to_string_lossy().as_bytes(),
+        )
+        .unwrap();
+        let info = probe_sui_binary(&workdir, Some("1.30.1-abc".to_string())).unwrap();
+        assert_eq!(info.origin, "downloaded");
+        assert_eq!(info.sui_repo_path, None);
+        assert_eq!(info.version.as_deref(), Some("1.30.1-abc"));
+        assert!(info.binary_mtime.is_some());
+
+        let _ = std::fs::remove_dir_all(workdir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_probe_repo_build() {
+        let workdir = fixture_workdir("repo");
+        std::fs::write(workdir.join(".state/precompiled"), "/stale/download").unwrap();
+
+        // set-sui-repo toward a local repo (the binary is built there).
+        let user_repo = workdir.parent().unwrap().join("my-sui");
+        std::fs::create_dir_all(user_repo.join("target/debug")).unwrap();
+        std::fs::write(user_repo.join("target/debug/sui"), "bin").unwrap();
+        std::fs::remove_file(workdir.join("sui-repo")).unwrap();
+        std::os::unix::fs::symlink(&user_repo, workdir.join("sui-repo")).unwrap();
+
+        let info = probe_sui_binary(&workdir, None).unwrap();
+        assert_eq!(info.origin, "repo-build");
+        assert_eq!(
+            info.sui_repo_path,
+            Some(user_repo.to_string_lossy().to_string())
+        );
+
+        let _ = std::fs::remove_dir_all(workdir.parent().unwrap());
+    }
+}
@@ -0,0 +1,548 @@
+// Self-diagnostic of the installation (see getSystemCheck).
+//
+// Each check is independent and quick. They run concurrently, each with its own
+// timeout (SYSTEM_CHECK_TIMEOUT), so the whole report takes a few seconds at most.
+//
+// Every "warn" and "fail" comes with a short remediation hint (user facing).
+use std::future::Future;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::SystemCheckItem;
+
+use super::{find_workdir_processes, time_skew_warnings, WorkdirProcess};
+
+pub const SYSTEM_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub const CHECK_PASS: &str = "pass";
+pub const CHECK_WARN: &str = "warn";
+pub const CHECK_FAIL: &str = "fail";
+
+// Free space under ~/suibase (in KB, as reported by df).
+const DISK_SPACE_WARN_KB: u64 = 5 * 1024 * 1024;
+const DISK_SPACE_FAIL_KB: u64 = 1024 * 1024;
+
+// Trivial request answered by any sui node.
+const CHECK_RPC_REQUEST_BODY: &str =
+    "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"sui_getChainIdentifier\",\"params\":[]}";
+
+fn pass(name: &str, message: String) -> SystemCheckItem {
+    SystemCheckItem {
+        name: name.to_string(),
+        status: CHECK_PASS.to_string(),
+        message,
+        hint: None,
+    }
+}
+
+fn not_pass(name: &str, status: &str, message: String, hint: String) -> SystemCheckItem {
+    SystemCheckItem {
+        name: name.to_string(),
+        status: status.to_string(),
+        message,
+        hint: Some(hint),
+    }
+}
+
+// A check taking longer than 'timeout' (normally SYSTEM_CHECK_TIMEOUT) is reported as failed.
+pub async fn with_check_timeout(
+    name: String,
+    timeout: Duration,
+    check: impl Future<Output = SystemCheckItem>,
+) -> SystemCheckItem {
+    match tokio::time::timeout(timeout, check).await {
+        Ok(item) => item,
+        Err(_) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("no result within {}ms", timeout.as_millis()),
+            "retry, then check the daemon logs".to_string(),
+        ),
+    }
+}
+
+// "fail" over "warn" over "pass".
+pub fn worst_status(items: &[SystemCheckItem]) -> String {
+    let has = |status: &str| items.iter().any(|item| item.status == status);
+    if has(CHECK_FAIL) {
+        CHECK_FAIL.to_string()
+    } else if has(CHECK_WARN) {
+        CHECK_WARN.to_string()
+    } else {
+        CHECK_PASS.to_string()
+    }
+}
+
+// 'export SUIBASE_VERSION="0.1.7"' in scripts/common/__globals.sh
+pub fn parse_suibase_version(globals_sh: &str) -> Option<String> {
+    globals_sh.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("export SUIBASE_VERSION=")?;
+        let version = value.trim_matches('"');
+        (!version.is_empty()).then(|| version.to_string())
+    })
+}
+
+pub fn check_scripts(suibase_path: &Path) -> SystemCheckItem {
+    const NAME: &str = "scripts";
+    let globals_sh = suibase_path.join("scripts/common/__globals.sh");
+    match std::fs::read_to_string(&globals_sh)
+        .ok()
+        .as_deref()
+        .and_then(parse_suibase_version)
+    {
+        Some(version) => pass(NAME, format!("suibase {}", version)),
+        None => not_pass(
+            NAME,
+            CHECK_FAIL,
+            format!("{} missing or invalid", globals_sh.display()),
+            "re-install with ~/suibase/install (or ~/suibase/repair)".to_string(),
+        ),
+    }
+}
+
+pub fn check_workdir_state(workdir: &str, workdir_path: &Path) -> SystemCheckItem {
+    let name = format!("{}.state", workdir);
+    let state_path = workdir_path.join(".state");
+    if !state_path.is_dir() {
+        return not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} missing", state_path.display()),
+            "run ~/suibase/repair".to_string(),
+        );
+    }
+    match std::fs::read_to_string(state_path.join("user_request")) {
+        Ok(user_request) if matches!(user_request.trim(), "start" | "stop") => {
+            pass(&name, format!("user_request is {}", user_request.trim()))
+        }
+        Ok(user_request) => not_pass(
+            &name,
+            CHECK_WARN,
+            format!("unexpected user_request [{}]", user_request.trim()),
+            format!("run '{} start' or '{} stop'", workdir, workdir),
+        ),
+        Err(_) => not_pass(
+            &name,
+            CHECK_WARN,
+            "no user_request".to_string(),
+            format!("run '{} start'", workdir),
+        ),
+    }
+}
+
+pub fn check_sui_binary(workdir: &str, workdir_path: &Path) -> SystemCheckItem {
+    let name = format!("{}.sui_binary", workdir);
+    let binary = workdir_path.join("sui-repo/target/debug/sui");
+    match std::fs::metadata(&binary) {
+        Ok(metadata) if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 => {
+            pass(&name, binary.display().to_string())
+        }
+        Ok(_) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} not executable", binary.display()),
+            format!("run '{} update'", workdir),
+        ),
+        Err(_) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} not found", binary.display()),
+            format!("run '{} update'", workdir),
+        ),
+    }
+}
+
+pub fn check_keystore(workdir: &str, workdir_path: &Path) -> SystemCheckItem {
+    let name = format!("{}.keystore", workdir);
+    let keystore = workdir_path.join("config/sui.keystore");
+    let contents = match std::fs::read_to_string(&keystore) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return not_pass(
+                &name,
+                CHECK_WARN,
+                format!("{} not found", keystore.display()),
+                format!("run '{} start' once to create it", workdir),
+            )
+        }
+        Err(e) => {
+            return not_pass(
+                &name,
+                CHECK_FAIL,
+                format!("{} not readable: {}", keystore.display(), e),
+                format!("check the permissions of {}", keystore.display()),
+            )
+        }
+    };
+    match serde_json::from_str::<Vec<String>>(&contents) {
+        Ok(keys) => pass(&name, format!("{} key(s)", keys.len())),
+        Err(e) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} invalid: {}", keystore.display(), e),
+            "restore the keystore from a backup".to_string(),
+        ),
+    }
+}
+
+// Detection only (see cleanupWorkdirProcesses to terminate the orphans).
+pub fn workdir_processes_item(workdir: &str, processes: &[WorkdirProcess]) -> SystemCheckItem {
+    let name = format!("{}.processes", workdir);
+    let orphans: Vec<String> = processes
+        .iter()
+        .filter_map(|process| {
+            let reason = process.orphan_reason()?;
+            Some(format!(
+                "{} pid {} ({})",
+                process.kind.as_str(),
+                process.pid,
+                reason
+            ))
+        })
+        .collect();
+    if orphans.is_empty() {
+        return pass(&name, format!("{} sui/faucet process(es)", processes.len()));
+    }
+    not_pass(
+        &name,
+        CHECK_WARN,
+        format!("orphaned {}", orphans.join(", ")),
+        format!(
+            "run '{} start' or call cleanupWorkdirProcesses to terminate them",
+            workdir
+        ),
+    )
+}
+
+pub fn check_workdir_processes(workdir: &str, workdir_path: &Path) -> SystemCheckItem {
+    workdir_processes_item(workdir, &find_workdir_processes(workdir_path))
+}
+
+// 'bound_by_us' when the proxy_server of the workdir is listening on the port.
+pub fn check_proxy_port(
+    workdir: &str,
+    port: u16,
+    bound_by_us: bool,
+    port_error: Option<&String>,
+    is_free: impl Fn(u16) -> bool,
+) -> SystemCheckItem {
+    let name = format!("{}.port", workdir);
+    if let Some(port_error) = port_error {
+        return not_pass(
+            &name,
+            CHECK_FAIL,
+            port_error.clone(),
+            "change proxy_port_number in suibase.yaml, or allow a fallback port".to_string(),
+        );
+    }
+    if bound_by_us {
+        pass(&name, format!("{} bound by this daemon", port))
+    } else if is_free(port) {
+        pass(&name, format!("{} available", port))
+    } else {
+        not_pass(
+            &name,
+            CHECK_WARN,
+            format!("{} in use by another process", port),
+            "change proxy_port_number in suibase.yaml".to_string(),
+        )
+    }
+}
+
+// Clock of this host compared to the links of the workdir (see time_skew.rs).
+pub fn check_time_skew(
+    workdir: &str,
+    skews: &[(String, i64)],
+    threshold_secs: u64,
+) -> SystemCheckItem {
+    let name = format!("{}.clock", workdir);
+    let warnings = time_skew_warnings(skews, threshold_secs);
+    if !warnings.is_empty() {
+        return not_pass(
+            &name,
+            CHECK_WARN,
+            warnings.join(", "),
+            "synchronize the clock of this host (e.g. enable NTP)".to_string(),
+        );
+    }
+    match skews
+        .iter()
+        .map(|(_, skew_ms)| skew_ms.unsigned_abs())
+        .max()
+    {
+        Some(max_ms) => pass(
+            &name,
+            format!(
+                "within {:.1}s of {} link(s)",
+                max_ms as f64 / 1000.0,
+                skews.len()
+            ),
+        ),
+        None => pass(&name, "not measured yet".to_string()),
+    }
+}
+
+// Output of "df -Pk {path}". The 4th column of the 2nd line is the available KB.
+pub fn parse_df_available_kb(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()
+}
+
+pub fn disk_space_item(available_kb: Option<u64>) -> SystemCheckItem {
+    const NAME: &str = "disk_space";
+    let hint = "free space under ~/suibase (e.g. 'localnet regen' or delete old snapshots)";
+    match available_kb {
+        None => not_pass(
+            NAME,
+            CHECK_WARN,
+            "free space unknown".to_string(),
+            "check with 'df -h ~/suibase'".to_string(),
+        ),
+        Some(kb) => {
+            let message = format!("{} MB available", kb / 1024);
+            if kb < DISK_SPACE_FAIL_KB {
+                not_pass(NAME, CHECK_FAIL, message, hint.to_string())
+            } else if kb < DISK_SPACE_WARN_KB {
+                not_pass(NAME, CHECK_WARN, message, hint.to_string())
+            } else {
+                pass(NAME, message)
+            }
+        }
+    }
+}
+
+pub async fn check_disk_space(suibase_path: PathBuf) -> SystemCheckItem {
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(&suibase_path)
+        .output()
+        .await;
+    let available_kb = output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| parse_df_available_kb(&String::from_utf8_lossy(&output.stdout)));
+    disk_space_item(available_kb)
+}
+
+// Same verification as done by the daemon on startup (see main.rs).
+pub async fn check_daemon_lock(suibase_path: PathBuf) -> SystemCheckItem {
+    const NAME: &str = "daemon_lock";
+    let script = suibase_path.join("scripts/common/verify-suibase-daemon-lock.sh");
+    let output = tokio::process::Command::new("/bin/bash")
+        .arg("-c")
+        .arg(format!("{} {}", script.display(), std::process::id()))
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() && output.stdout.starts_with(b"OK") => {
+            pass(NAME, format!("pid {} owns the lock", std::process::id()))
+        }
+        Ok(output) => not_pass(
+            NAME,
+            CHECK_FAIL,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            "restart the daemon with ~/suibase/restart".to_string(),
+        ),
+        Err(e) => not_pass(
+            NAME,
+            CHECK_FAIL,
+            e.to_string(),
+            "restart the daemon with ~/suibase/restart".to_string(),
+        ),
+    }
+}
+
+pub async fn check_proxy_rpc(
+    client: reqwest::Client,
+    workdir: String,
+    proxy_url: String,
+) -> SystemCheckItem {
+    let name = format!("{}.proxy", workdir);
+    let hint = format!(
+        "run '{} status', then getLinks for the links health",
+        workdir
+    );
+    let resp = client
+        .post(&proxy_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(CHECK_RPC_REQUEST_BODY)
+        .timeout(SYSTEM_CHECK_TIMEOUT)
+        .send()
+        .await;
+    let json = match resp {
+        Ok(resp) => resp.json::<serde_json::Value>().await,
+        Err(e) => {
+            let message = format!("{} not reachable: {}", proxy_url, e.without_url());
+            return not_pass(&name, CHECK_FAIL, message, hint);
+        }
+    };
+    match json {
+        Ok(json) if json.get("result").is_some() => pass(&name, format!("{} answering", proxy_url)),
+        Ok(json) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} unexpected answer: {}", proxy_url, json),
+            hint,
+        ),
+        Err(e) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} invalid answer: {}", proxy_url, e.without_url()),
+            hint,
+        ),
+    }
+}
+
+pub async fn check_websocket(workdir: String, url: String) -> SystemCheckItem {
+    let name = format!("{}.websocket", workdir);
+    match tokio_tungstenite::connect_async(url.as_str()).await {
+        Ok((mut ws_stream, _response)) => {
+            let _ = ws_stream.close(None).await;
+            pass(&name, format!("{} connectable", url))
+        }
+        Err(e) => not_pass(
+            &name,
+            CHECK_FAIL,
+            format!("{} not connectable: {}", url, e),
+            format!(
+                "run '{} status' (events are not updated meanwhile)",
+                workdir
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_types::WorkdirProcessKind;
+
+    #[test]
+    fn test_parsing_and_status() {
+        let globals_sh = "#!/bin/bash\nexport SUIBASE_VERSION=\"0.1.7\"\n";
+        assert_eq!(parse_suibase_version(globals_sh), Some("0.1.7".to_string()));
+        assert_eq!(parse_suibase_version("SUIBASE_VERSION=\"\""), None);
+
+        let df = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                  /dev/sda1 100000000 40000000 60000000 40% /\n";
+        assert_eq!(parse_df_available_kb(df), Some(60000000));
+        assert_eq!(parse_df_available_kb("garbage"), None);
+        assert_eq!(disk_space_item(Some(60000000)).status, CHECK_PASS);
+        assert_eq!(disk_space_item(Some(2 * 1024 * 1024)).status, CHECK_WARN);
+        assert_eq!(disk_space_item(Some(1024)).status, CHECK_FAIL);
+
+        let port_error = "port 44340 already in use (strict_ports)".to_string();
+        let item = check_proxy_port("localnet", 44340, false, Some(&port_error), |_| true);
+        assert_eq!(item.status, CHECK_FAIL);
+        assert!(item.hint.is_some());
+        let item = check_proxy_port("localnet", 44340, true, None, |_| false);
+        assert_eq!(item.status, CHECK_PASS);
+        let item = check_proxy_port("localnet", 44340, false, None, |_| false);
+        assert_eq!(item.status, CHECK_WARN);
+
+        let skews = vec![("sui.io".to_string(), -45_000), ("local".to_string(), 200)];
+        let item = check_time_skew("testnet", &skews, 30);
+        assert_eq!(item.name, "testnet.clock");
+        assert_eq!(item.status, CHECK_WARN);
+        assert_eq!(item.message, "local clock 45.0s behind sui.io");
+        assert_eq!(check_time_skew("testnet", &skews, 60).status, CHECK_PASS);
+        assert_eq!(check_time_skew("testnet", &[], 30).status, CHECK_PASS);
+
+        let mut process = WorkdirProcess {
+            pid: 1234,
+            kind: WorkdirProcessKind::Node,
+            cmd: "sui start".to_string(),
+            recorded: true,
+            running: true,
+        };
+        let item = workdir_processes_item("localnet", &[process.clone()]);
+        assert_eq!(item.status, CHECK_PASS);
+        assert_eq!(item.name, "localnet.processes");
+        process.recorded = false;
+        let item = workdir_processes_item("localnet", &[process]);
+        assert_eq!(item.status, CHECK_WARN);
+        assert!(item.message.contains("sui pid 1234"));
+        assert!(item.hint.unwrap().contains("cleanupWorkdirProcesses"));
+
+        let items = vec![
+            pass("a", String::new()),
+            not_pass("b", CHECK_WARN, String::new(), String::new()),
+        ];
+        assert_eq!(worst_status(&items), CHECK_WARN);
+        assert_eq!(worst_status(&items[..1]), CHECK_PASS);
+    }
+
+    #[tokio::test]
+    async fn test_workdir_checks() {
+        let root = std::env::temp_dir().join(format!("sbsd-syscheck-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let workdir_path = root.join("workdirs/localnet");
+
+        // Nothing installed.
+        assert_eq!(check_scripts(&root).status, CHECK_FAIL);
+        assert_eq!(
+            check_workdir_state("localnet", &workdir_path).status,
+            CHECK_FAIL
+        );
+        assert_eq!(
+            check_sui_binary("localnet", &workdir_path).status,
+            CHECK_FAIL
+        );
+        assert_eq!(check_keystore("localnet", &workdir_path).status, CHECK_WARN);
+
+        std::fs::create_dir_all(root.join("scripts/common")).unwrap();
+        std::fs::write(
+            root.join("scripts/common/__globals.sh"),
+            "export SUIBASE_VERSION=\"0.1.7\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(workdir_path.join(".state")).unwrap();
+        std::fs::write(workdir_path.join(".state/user_request"), "start").unwrap();
+        let binary = workdir_path.join("sui-repo/target/debug/sui");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, "#!/bin/sh\n").unwrap();
+        std::fs::create_dir_all(workdir_path.join("config")).unwrap();
+        std::fs::write(workdir_path.join("config/sui.keystore"), "[\"AAA\"]").unwrap();
+
+        let item = check_scripts(&root);
+        assert_eq!(item.status, CHECK_PASS);
+        assert_eq!(item.message, "suibase 0.1.7");
+        assert_eq!(
+            check_workdir_state("localnet", &workdir_path).status,
+            CHECK_PASS
+        );
+        assert_eq!(check_keystore("localnet", &workdir_path).status, CHECK_PASS);
+
+        // Present, but not executable.
+        assert_eq!(
+            check_sui_binary("localnet", &workdir_path).status,
+            CHECK_FAIL
+        );
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            check_sui_binary("localnet", &workdir_path).status,
+            CHECK_PASS
+        );
+
+        std::fs::write(workdir_path.join("config/sui.keystore"), "{").unwrap();
+        assert_eq!(check_keystore("localnet", &workdir_path).status, CHECK_FAIL);
+
+        // A check never answering does not block the report.
+        let timeout = Duration::from_millis(50);
+        let item = with_check_timeout("slow".to_string(), timeout, async {
+            tokio::time::sleep(SYSTEM_CHECK_TIMEOUT).await;
+            pass("slow", String::new())
+        })
+        .await;
+        assert_eq!(item.status, CHECK_FAIL);
+        assert_eq!(item.name, "slow");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,145 @@
+// Values of a network that change only on epoch boundaries (reference gas price,
+// protocol config). Requested very often by the SDKs, and answered by the proxy
+// from this cache without an upstream round trip while fresh.
+//
+// Populated by the proxy_server (successful upstream responses) and by the
+// SystemValuesPoller (periodic prefetch), which also detects the epoch changes.
+//
+// Written by the proxy_server, so it has its own std Mutex (held very briefly)
+// instead of requiring a write lock on the globals.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::basic_types::EpochTimestamp;
+
+// JSON-RPC methods served from the cache (only when called without params).
+pub const SYSTEM_VALUES_METHODS: [&str; 2] = ["suix_getReferenceGasPrice", "sui_getProtocolConfig"];
+
+// A value older than this is not served (and is refreshed by the poller).
+pub const SYSTEM_VALUES_MAX_AGE: Duration = Duration::from_secs(300);
+
+// Response header identifying a response served from the cache.
+pub const HEADER_SBSD_CACHE: &str = "x-suibase-cache";
+pub const HEADER_SBSD_CACHE_HIT: &str = "hit";
+
+#[derive(Debug, Default)]
+pub struct SystemValues {
+    // Epoch of the cached values (None until first detected).
+    epoch: Option<u64>,
+    // method -> (JSON-RPC "result", when it was retrieved).
+    values: HashMap<String, (serde_json::Value, EpochTimestamp)>,
+}
+
+// Also locked by the proxy_server for every cached method request. A poisoned lock
+// is recovered instead of panicking: the updates (insert, clear) cannot be left half
+// done by a panic, so the values are still consistent.
+pub type SystemValuesMT = Arc<Mutex<SystemValues>>;
+
+impl SystemValues {
+    pub fn new_mt() -> SystemValuesMT {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    pub fn is_cached_method(method: &str) -> bool {
+        SYSTEM_VALUES_METHODS.contains(&method)
+    }
+
+    // The cached result, only when still fresh.
+    pub fn get(&self, method: &str, now: &EpochTimestamp) -> Option<&serde_json::Value> {
+        match self.values.get(method) {
+            Some((result, retrieved))
+                if now.saturating_duration_since(*retrieved) < SYSTEM_VALUES_MAX_AGE =>
+            {
+                Some(result)
+            }
+            _ => None,
+        }
+    }
+
+    // True when the poller should fetch the value again (before it becomes stale).
+    pub fn is_refresh_due(&self, method: &str, now: &EpochTimestamp) -> bool {
+        match self.values.get(method) {
+            Some((_, retrieved)) => {
+                now.saturating_duration_since(*retrieved) >= SYSTEM_VALUES_MAX_AGE / 2
+            }
+            None => true,
+        }
+    }
+
+    pub fn set(&mut self, method: &str, result: serde_json::Value, now: EpochTimestamp) {
+        if Self::is_cached_method(method) {
+            self.values.insert(method.to_string(), (result, now));
+        }
+    }
+
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    // Returns true when the epoch changed (all the values are then invalidated).
+    pub fn set_epoch(&mut self, epoch: u64) -> bool {
+        if self.epoch == Some(epoch) {
+            return false;
+        }
+        let changed = self.epoch.is_some();
+        self.epoch = Some(epoch);
+        if changed {
+            self.values.clear();
+        }
+        changed
+    }
+
+    pub fn clear(&mut self) {
+        self.epoch = None;
+        self.values.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_values() {
+        let mut cache = SystemValues::default();
+        let now = EpochTimestamp::now();
+        let gas_price = serde_json::json!("750");
+        assert!(cache.get("suix_getReferenceGasPrice", &now).is_none());
+
+        cache.set("suix_getReferenceGasPrice", gas_price.clone(), now);
+        cache.set("sui_getObject", serde_json::json!({}), now); // Not a system value.
+        assert_eq!(
+            cache.get("suix_getReferenceGasPrice", &now),
+            Some(&gas_price)
+        );
+        assert!(cache.get("sui_getObject", &now).is_none());
+        assert!(!cache.is_refresh_due("suix_getReferenceGasPrice", &now));
+        assert!(cache.is_refresh_due("sui_getProtocolConfig", &now));
+        let half = now + SYSTEM_VALUES_MAX_AGE / 2;
+        assert!(cache.is_refresh_due("suix_getReferenceGasPrice", &half));
+        assert!(cache.get("suix_getReferenceGasPrice", &half).is_some());
+
+        // Stale.
+        let later = now + SYSTEM_VALUES_MAX_AGE;
+        assert!(cache.get("suix_getReferenceGasPrice", &later).is_none());
+
+        // First epoch detected: nothing invalidated.
+        assert!(!cache.set_epoch(10));
+        assert!(!cache.set_epoch(10));
+        assert_eq!(
+            cache.get("suix_getReferenceGasPrice", &now),
+            Some(&gas_price)
+        );
+
+        // Epoch change.
+        assert!(cache.set_epoch(11));
+        assert_eq!(cache.epoch(), Some(11));
+        assert!(cache.get("suix_getReferenceGasPrice", &now).is_none());
+
+        cache.set("sui_getProtocolConfig", serde_json::json!({}), now);
+        cache.clear();
+        assert!(cache.epoch().is_none());
+        assert!(cache.get("sui_getProtocolConfig", &now).is_none());
+    }
+}
@@ -1,26 +1,51 @@
 use common::basic_types::*;
 
-use crate::shared_types::Link;
+use std::sync::Arc;
+
 use crate::shared_types::ServerStats;
+use crate::shared_types::{Link, LinkRole, RateLimiter, RateLimits};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TargetServer {
     idx: Option<ManagedVecU8>,
     config: Link,
     pub stats: ServerStats,
+    // Token buckets of the max_per_secs/min/day of the config. None when not limited.
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl TargetServer {
     pub fn new(config: Link) -> Self {
         // alias is the 'key' and can't be changed after construction.
         let alias = config.alias.clone();
+        let rate_limiter = Self::new_rate_limiter(&config);
         Self {
             idx: None,
             config,
             stats: ServerStats::new(alias),
+            rate_limiter,
         }
     }
 
+    fn rate_limits(config: &Link) -> RateLimits {
+        RateLimits {
+            max_per_secs: config.max_per_secs,
+            max_per_min: config.max_per_min,
+            max_per_day: config.max_per_day,
+        }
+    }
+
+    fn new_rate_limiter(config: &Link) -> Option<Arc<RateLimiter>> {
+        let limits = Self::rate_limits(config);
+        limits
+            .is_limited()
+            .then(|| Arc::new(RateLimiter::new(limits)))
+    }
+
+    pub fn rate_limiter(&self) -> Option<&Arc<RateLimiter>> {
+        self.rate_limiter.as_ref()
+    }
+
     pub fn alias(&self) -> String {
         self.config.alias.clone()
     }
@@ -40,6 +65,10 @@ impl TargetServer {
         self.config.rpc = Some(rpc);
     }
 
+    pub fn role(&self) -> LinkRole {
+        self.config.role
+    }
+
     pub fn is_selectable(&self) -> bool {
         self.config.selectable
     }
@@ -48,6 +77,35 @@ impl TargetServer {
         self.config.monitored
     }
 
+    pub fn is_throttle_code(&self, code: i32) -> bool {
+        self.config.throttle_codes.contains(&code)
+    }
+
+    // End of the latest of the maintenance windows in which 'now_secs' is, if any.
+    pub fn maintenance_until(&self, now_secs: u64) -> Option<u64> {
+        self.config
+            .maintenance
+            .iter()
+            .filter_map(|window| window.active_until(now_secs))
+            .max()
+    }
+
+    // Fraction (0.0 to 1.0) of the configured rate limits still available at the
+    // most recently sampled load (and of the daily quota not yet used today).
+    // 1.0 when the link has no rate limit.
+    pub fn rate_limit_headroom(&self) -> f64 {
+        let headroom = |rate: f64, limit: Option<u32>| match limit {
+            Some(limit) => (1.0 - rate / limit as f64).max(0.0),
+            None => 1.0,
+        };
+        headroom(self.stats.qps(), self.config.max_per_secs)
+            .min(headroom(self.stats.qpm(), self.config.max_per_min))
+            .min(headroom(
+                self.stats.day_count() as f64,
+                self.config.max_per_day,
+            ))
+    }
+
     pub fn stats_clear(&mut self) {
         self.stats.clear();
     }
@@ -56,7 +114,11 @@ impl TargetServer {
         &self.config
     }
 
+    // The tokens already taken are kept, unless the limits changed.
     pub fn set_config(&mut self, config: Link) {
+        if Self::rate_limits(&config) != Self::rate_limits(&self.config) {
+            self.rate_limiter = Self::new_rate_limiter(&config);
+        }
         self.config = config
     }
 }
@@ -0,0 +1,401 @@
+// Anonymized usage counters, strictly opt-in (telemetry_enabled in the common
+// suibase.yaml, disabled by default).
+//
+// Nothing of this module runs while disabled: the AdminController creates a Telemetry
+// only once enabled, and drops it when disabled again.
+//
+// The counters are kept in ~/suibase/workdirs/common/telemetry.json, which the user can
+// inspect at any time (also see getTelemetryPreview). Once a day, when telemetry_endpoint
+// is configured, the payload is posted there:
+//
+//   {
+//     "schema": 1,
+//     "install_id": "0b6c3bd6-...",   # Random, generated on the first enable.
+//     "daemon_version": "0.0.1",
+//     "counters": {
+//       "daemon_starts": 2, "crashes": 0, "uptime_secs": 86400,
+//       "proxy_requests": 1234, "proxy_workdirs": 2,
+//       "event_subscriptions": 1, "webhooks": 0
+//     }
+//   }
+//
+// Only numbers: never an address, hostname, URL or package name. The counters are for
+// the period since the last report, then restart from zero.
+//
+// A crash is detected on the next start, from the file still marked "running" (it is
+// cleared on a graceful shutdown).
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+pub const TELEMETRY_FILENAME: &str = "telemetry.json";
+
+// Increment on any change of the payload.
+pub const TELEMETRY_SCHEMA_VERSION: u32 = 1;
+
+pub const TELEMETRY_REPORT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+pub const TELEMETRY_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const TELEMETRY_SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+pub const TELEMETRY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryCounters {
+    // Accumulated over the period.
+    pub daemon_starts: u64,
+    pub crashes: u64,
+    pub uptime_secs: u64,
+    pub proxy_requests: u64,
+    // Most seen at once during the period.
+    pub proxy_workdirs: u64,
+    pub event_subscriptions: u64,
+    pub webhooks: u64,
+}
+
+impl TelemetryCounters {
+    // Once reported, what was accumulated meanwhile (e.g. while posting) is kept.
+    fn report_done(&mut self, reported: &TelemetryCounters) {
+        self.daemon_starts = self.daemon_starts.saturating_sub(reported.daemon_starts);
+        self.crashes = self.crashes.saturating_sub(reported.crashes);
+        self.uptime_secs = self.uptime_secs.saturating_sub(reported.uptime_secs);
+        self.proxy_requests = self.proxy_requests.saturating_sub(reported.proxy_requests);
+        self.proxy_workdirs = 0;
+        self.event_subscriptions = 0;
+        self.webhooks = 0;
+    }
+}
+
+// What is observed on an audit (see AdminController).
+#[derive(Debug, Clone, Default)]
+pub struct TelemetrySample {
+    pub proxy_requests: u64, // Total of all proxy servers, since their stats were cleared.
+    pub proxy_workdirs: u64,
+    pub event_subscriptions: u64,
+    pub webhooks: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryFile {
+    pub schema: u32,
+    pub install_id: String,
+    pub running: bool,
+    pub period_start: String, // RFC 3339. Local only (not in the payload).
+    pub counters: TelemetryCounters,
+}
+
+impl TelemetryFile {
+    fn new() -> Self {
+        Self {
+            schema: TELEMETRY_SCHEMA_VERSION,
+            install_id: uuid::Uuid::new_v4().to_string(),
+            running: false,
+            period_start: Utc::now().to_rfc3339(),
+            counters: TelemetryCounters::default(),
+        }
+    }
+
+    // None when there is no file, or it is not usable.
+    pub fn load(common_path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(common_path.join(TELEMETRY_FILENAME)).ok()?;
+        serde_json::from_str::<Self>(&contents)
+            .ok()
+            .filter(|file| file.schema == TELEMETRY_SCHEMA_VERSION)
+    }
+
+    // Atomic write (temp file + rename).
+    pub fn write(&self, common_path: &Path) -> Result<()> {
+        std::fs::create_dir_all(common_path)?;
+        let path = common_path.join(TELEMETRY_FILENAME);
+        let tmp_path = common_path.join(format!("{}.tmp", TELEMETRY_FILENAME));
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    // Everything that would be sent (nothing else ever is).
+    pub fn payload(&self) -> JsonValue {
+        json!({
+            "schema": self.schema,
+            "install_id": self.install_id,
+            "daemon_version": env!("CARGO_PKG_VERSION"),
+            "counters": self.counters,
+        })
+    }
+}
+
+// A report being posted, with the counters it includes.
+struct TelemetryReport {
+    handle: tokio::task::JoinHandle<bool>,
+    counters: TelemetryCounters,
+}
+
+pub struct Telemetry {
+    common_path: PathBuf,
+    endpoint: Option<String>,
+    file: TelemetryFile,
+    last_proxy_requests: Option<u64>,
+    sampled_at: tokio::time::Instant,
+    saved_at: tokio::time::Instant,
+    report: Option<TelemetryReport>,
+    retry_at: Option<tokio::time::Instant>,
+}
+
+impl Telemetry {
+    // Done once enabled. Loads (or creates) the file, and counts this start.
+    pub fn start(common_path: &Path, endpoint: Option<String>) -> Self {
+        let mut file = TelemetryFile::load(common_path).unwrap_or_else(TelemetryFile::new);
+        if file.running {
+            file.counters.crashes += 1;
+        }
+        file.counters.daemon_starts += 1;
+        file.running = true;
+        let mut telemetry = Self {
+            common_path: common_path.to_path_buf(),
+            endpoint,
+            file,
+            last_proxy_requests: None,
+            sampled_at: tokio::time::Instant::now(),
+            saved_at: tokio::time::Instant::now(),
+            report: None,
+            retry_at: None,
+        };
+        telemetry.save();
+        telemetry
+    }
+
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    pub fn set_endpoint(&mut self, endpoint: Option<String>) {
+        self.endpoint = endpoint;
+    }
+
+    pub fn payload(&self) -> JsonValue {
+        self.file.payload()
+    }
+
+    pub fn sample(&mut self, sample: &TelemetrySample) {
+        let elapsed_secs = self.sampled_at.elapsed().as_secs();
+        self.sampled_at += Duration::from_secs(elapsed_secs);
+        let counters = &mut self.file.counters;
+        counters.uptime_secs += elapsed_secs;
+
+        // The proxy stats can be cleared (e.g. resetServerStats).
+        counters.proxy_requests += match self.last_proxy_requests {
+            Some(last) if sample.proxy_requests >= last => sample.proxy_requests - last,
+            Some(_) => sample.proxy_requests,
+            None => 0,
+        };
+        self.last_proxy_requests = Some(sample.proxy_requests);
+
+        counters.proxy_workdirs = counters.proxy_workdirs.max(sample.proxy_workdirs);
+        counters.event_subscriptions = counters.event_subscriptions.max(sample.event_subscriptions);
+        counters.webhooks = counters.webhooks.max(sample.webhooks);
+    }
+
+    // Called on every audit. Saves the file periodically, and posts the daily report.
+    pub async fn audit(&mut self) {
+        let report_done = self
+            .report
+            .as_ref()
+            .is_some_and(|report| report.handle.is_finished());
+        if report_done {
+            let report = self.report.take().unwrap();
+            if report.handle.await.unwrap_or(false) {
+                self.file.counters.report_done(&report.counters);
+                self.file.period_start = Utc::now().to_rfc3339();
+                self.retry_at = None;
+                self.save();
+            } else {
+                self.retry_at = Some(tokio::time::Instant::now() + TELEMETRY_RETRY_INTERVAL);
+            }
+        }
+
+        if self.is_report_due() {
+            self.start_report();
+        }
+
+        if self.saved_at.elapsed() >= TELEMETRY_SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    fn is_report_due(&self) -> bool {
+        if self.endpoint.is_none() || self.report.is_some() {
+            return false;
+        }
+        if self
+            .retry_at
+            .is_some_and(|retry_at| tokio::time::Instant::now() < retry_at)
+        {
+            return false;
+        }
+        let period_start = match DateTime::parse_from_rfc3339(&self.file.period_start) {
+            Ok(period_start) => period_start.with_timezone(&Utc),
+            Err(_) => return true,
+        };
+        Utc::now()
+            .signed_duration_since(period_start)
+            .to_std()
+            .unwrap_or_default()
+            >= TELEMETRY_REPORT_INTERVAL
+    }
+
+    fn start_report(&mut self) {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => return,
+        };
+        let payload = self.payload();
+        let handle = tokio::spawn(async move {
+            let client = reqwest::Client::builder()
+                .timeout(TELEMETRY_TIMEOUT)
+                .build()
+                .unwrap_or_default();
+            match client.post(&endpoint).json(&payload).send().await {
+                Ok(resp) if resp.status().is_success() => true,
+                Ok(resp) => {
+                    log::warn!("telemetry report failed: status {}", resp.status());
+                    false
+                }
+                Err(e) => {
+                    log::warn!("telemetry report failed: {}", e);
+                    false
+                }
+            }
+        });
+        self.report = Some(TelemetryReport {
+            handle,
+            counters: self.file.counters.clone(),
+        });
+    }
+
+    fn save(&mut self) {
+        if let Err(e) = self.file.write(&self.common_path) {
+            log::error!("failed to write {}: {}", TELEMETRY_FILENAME, e);
+        }
+        self.saved_at = tokio::time::Instant::now();
+    }
+
+    // Done on a graceful shutdown, or when disabled.
+    pub fn stop(&mut self) {
+        if let Some(report) = self.report.take() {
+            report.handle.abort();
+        }
+        self.file.running = false;
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sbsd-telemetry-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_telemetry_payload_schema() {
+        let mut file = TelemetryFile::new();
+        file.counters.proxy_requests = 42;
+        let payload = file.payload();
+
+        let mut keys: Vec<&str> = payload
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|key| key.as_str())
+            .collect();
+        keys.sort();
+        assert_eq!(keys, ["counters", "daemon_version", "install_id", "schema"]);
+        assert_eq!(payload["schema"], TELEMETRY_SCHEMA_VERSION);
+        assert!(uuid::Uuid::parse_str(payload["install_id"].as_str().unwrap()).is_ok());
+
+        // Only numbers in the counters.
+        let counters = payload["counters"].as_object().unwrap();
+        assert_eq!(counters.len(), 7);
+        assert!(counters.values().all(|value| value.is_u64()));
+        assert_eq!(counters["proxy_requests"], 42);
+
+        // The period is not sent.
+        assert!(!payload.to_string().contains(&file.period_start));
+    }
+
+    #[tokio::test]
+    async fn test_telemetry_preview() {
+        let dir = test_dir("preview");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut telemetry = Telemetry::start(&dir, None);
+        let file = TelemetryFile::load(&dir).unwrap();
+        assert!(file.running);
+        assert_eq!(file.counters.daemon_starts, 1);
+
+        let mut sample = TelemetrySample {
+            proxy_requests: 100,
+            proxy_workdirs: 2,
+            event_subscriptions: 1,
+            webhooks: 0,
+        };
+        telemetry.sample(&sample);
+        sample.proxy_requests = 130;
+        sample.proxy_workdirs = 1;
+        telemetry.sample(&sample);
+        // Stats cleared.
+        sample.proxy_requests = 5;
+        telemetry.sample(&sample);
+
+        let payload = telemetry.payload();
+        assert_eq!(payload["install_id"], file.install_id);
+        assert_eq!(payload["counters"]["proxy_requests"], 35);
+        assert_eq!(payload["counters"]["proxy_workdirs"], 2);
+        assert_eq!(payload["counters"]["event_subscriptions"], 1);
+
+        // Without an endpoint, never a report.
+        assert!(!telemetry.is_report_due());
+        telemetry.audit().await;
+        assert!(telemetry.report.is_none());
+
+        // Graceful shutdown, then a restart.
+        telemetry.stop();
+        assert!(!TelemetryFile::load(&dir).unwrap().running);
+        let mut telemetry = Telemetry::start(&dir, None);
+        assert_eq!(telemetry.payload()["install_id"], file.install_id);
+        assert_eq!(telemetry.payload()["counters"]["daemon_starts"], 2);
+        assert_eq!(telemetry.payload()["counters"]["crashes"], 0);
+
+        // Not stopped (a crash): counted on the next start.
+        drop(telemetry);
+        let telemetry = Telemetry::start(&dir, None);
+        assert_eq!(telemetry.payload()["counters"]["crashes"], 1);
+        assert!(!dir.join("telemetry.json.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_telemetry_report_done() {
+        let mut counters = TelemetryCounters {
+            daemon_starts: 2,
+            proxy_requests: 50,
+            proxy_workdirs: 3,
+            ..Default::default()
+        };
+        let reported = TelemetryCounters {
+            daemon_starts: 2,
+            proxy_requests: 40,
+            proxy_workdirs: 3,
+            ..Default::default()
+        };
+        counters.report_done(&reported);
+        assert_eq!(counters.daemon_starts, 0);
+        assert_eq!(counters.proxy_requests, 10);
+        assert_eq!(counters.proxy_workdirs, 0);
+    }
+}
@@ -0,0 +1,144 @@
+// Skew between the clock of this host and the clock of the links (see getLinks and
+// getSystemCheck).
+//
+// The remote time is the "timestampMs" of the latest checkpoint known by the link. It
+// is compared with the middle of the local request/response interval, to compensate
+// for the network latency.
+//
+// A positive skew means the local clock is ahead of the link.
+//
+// Only informative: the skew never affects the selection of a link.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value as JsonValue;
+
+// Default of "time_skew_threshold_secs" (suibase.yaml). 0 disables the warnings.
+pub const DEFAULT_TIME_SKEW_THRESHOLD_SECS: u64 = 30;
+
+// Request for the latest checkpoint of a link (when no other response has a timestamp).
+pub const TIME_SKEW_PROBE_REQUEST_BODY: &str = "{\"jsonrpc\":\"2.0\",\"id\":1,\
+    \"method\":\"sui_getCheckpoints\",\"params\":[null,\"1\",true]}";
+
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+// Local clock minus the remote clock, in milliseconds.
+//
+// 'sent_ms' and 'received_ms' are the local time (UNIX epoch) of the request and of
+// its response.
+pub fn estimate_time_skew_ms(sent_ms: u64, received_ms: u64, remote_ms: u64) -> i64 {
+    let received_ms = received_ms.max(sent_ms);
+    let local_ms = sent_ms + (received_ms - sent_ms) / 2;
+    (local_ms as i128 - remote_ms as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+// "timestampMs" of a checkpoint, either in a JSON-RPC response of sui_getCheckpoint or
+// the most recent of a sui_getCheckpoints page.
+//
+// Sui returns it as a string (u64 does not fit in a JSON number for all clients).
+pub fn checkpoint_timestamp_ms(response: &JsonValue) -> Option<u64> {
+    let result = response.get("result").unwrap_or(response);
+    let parse = |checkpoint: &JsonValue| match &checkpoint["timestampMs"] {
+        JsonValue::String(value) => value.parse::<u64>().ok(),
+        value => value.as_u64(),
+    };
+    match result.get("data").and_then(JsonValue::as_array) {
+        Some(page) => page.iter().filter_map(parse).max(),
+        None => parse(result),
+    }
+}
+
+pub fn is_time_skew_exceeded(skew_ms: i64, threshold_secs: u64) -> bool {
+    threshold_secs != 0 && skew_ms.unsigned_abs() > threshold_secs.saturating_mul(1000)
+}
+
+// e.g. "local clock 42.5s ahead of sui.io"
+pub fn format_time_skew(alias: &str, skew_ms: i64) -> String {
+    let direction = if skew_ms >= 0 { "ahead of" } else { "behind" };
+    format!(
+        "local clock {:.1}s {} {}",
+        skew_ms.unsigned_abs() as f64 / 1000.0,
+        direction,
+        alias
+    )
+}
+
+// One warning per link above the threshold.
+pub fn time_skew_warnings(skews: &[(String, i64)], threshold_secs: u64) -> Vec<String> {
+    skews
+        .iter()
+        .filter(|(_, skew_ms)| is_time_skew_exceeded(*skew_ms, threshold_secs))
+        .map(|(alias, skew_ms)| format_time_skew(alias, *skew_ms))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn probe_response(timestamp_ms: u64) -> JsonValue {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "data": [{ "sequenceNumber": "1187", "timestampMs": timestamp_ms.to_string() }],
+                "nextCursor": "1186",
+                "hasNextPage": true
+            }
+        })
+    }
+
+    #[test]
+    fn test_time_skew_estimate() {
+        // Same clocks, 400ms round-trip: the remote time is the middle of it.
+        let remote = checkpoint_timestamp_ms(&probe_response(NOW_MS + 200)).unwrap();
+        assert_eq!(estimate_time_skew_ms(NOW_MS, NOW_MS + 400, remote), 0);
+
+        // Local clock 45s ahead, then 45s behind.
+        let remote = checkpoint_timestamp_ms(&probe_response(NOW_MS - 45_000)).unwrap();
+        assert_eq!(estimate_time_skew_ms(NOW_MS, NOW_MS, remote), 45_000);
+        let remote = checkpoint_timestamp_ms(&probe_response(NOW_MS + 45_100)).unwrap();
+        assert_eq!(estimate_time_skew_ms(NOW_MS, NOW_MS + 200, remote), -45_000);
+
+        // Single checkpoint, and a numeric timestampMs.
+        let response = json!({ "result": { "timestampMs": 1234 } });
+        assert_eq!(checkpoint_timestamp_ms(&response), Some(1234));
+        assert_eq!(
+            checkpoint_timestamp_ms(&json!({ "result": { "data": [] } })),
+            None
+        );
+        assert_eq!(
+            checkpoint_timestamp_ms(&json!({ "error": { "code": -32000 } })),
+            None
+        );
+    }
+
+    #[test]
+    fn test_time_skew_threshold() {
+        let threshold = DEFAULT_TIME_SKEW_THRESHOLD_SECS;
+        assert!(!is_time_skew_exceeded(30_000, threshold));
+        assert!(is_time_skew_exceeded(30_001, threshold));
+        assert!(is_time_skew_exceeded(-45_000, threshold));
+        assert!(!is_time_skew_exceeded(-45_000, 0));
+
+        let skews = vec![
+            ("sui.io".to_string(), 42_500),
+            ("local".to_string(), 150),
+            ("other".to_string(), -31_000),
+        ];
+        assert_eq!(
+            time_skew_warnings(&skews, threshold),
+            vec![
+                "local clock 42.5s ahead of sui.io".to_string(),
+                "local clock 31.0s behind other".to_string()
+            ]
+        );
+        assert!(time_skew_warnings(&skews, 60).is_empty());
+    }
+}
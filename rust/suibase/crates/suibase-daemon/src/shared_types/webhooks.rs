@@ -0,0 +1,222 @@
+// Notifications of some state transitions with an HTTP POST (e.g. for CI integration).
+//
+// Configured in the common suibase.yaml (see WorkdirUserConfig for the syntax).
+//
+// The events are created where the transitions are detected:
+//   link_status_change    NetworkMonitor (a link becomes healthy/unhealthy)
+//   link_budget_alert     NetworkMonitor (80% and 100% of a link monthly_budget)
+//   workdir_status_change CliPoller (e.g. localnet OK -> DOWN)
+//   package_published     PackagesPoller (new package found in published-data)
+//
+// ...and are delivered by the WebhookWorker. Sending an event never blocks
+// (the event is dropped and counted when the queue is full).
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use common::basic_types::MPSC_Q_SIZE;
+
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Suibase-Event";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Suibase-Signature"; // "sha256=<hex HMAC of the body>"
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEventType {
+    LinkBudgetAlert,
+    LinkStatusChange,
+    PackagePublished,
+    WorkdirStatusChange,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 4] = [
+        WebhookEventType::LinkBudgetAlert,
+        WebhookEventType::LinkStatusChange,
+        WebhookEventType::PackagePublished,
+        WebhookEventType::WorkdirStatusChange,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::LinkBudgetAlert => "link_budget_alert",
+            WebhookEventType::LinkStatusChange => "link_status_change",
+            WebhookEventType::PackagePublished => "package_published",
+            WebhookEventType::WorkdirStatusChange => "workdir_status_change",
+        }
+    }
+}
+
+impl std::fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WebhookEventType::ALL
+            .into_iter()
+            .find(|event_type| event_type.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown webhook event [{}]", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: Option<String>, // Payloads are signed only when set.
+    pub events: Vec<WebhookEventType>,
+}
+
+impl WebhookConfig {
+    pub fn accepts(&self, event_type: WebhookEventType) -> bool {
+        self.events.contains(&event_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    pub event_type: WebhookEventType,
+    pub workdir: String,
+    pub timestamp: DateTime<Utc>,
+    pub data: serde_json::Value, // Specific to the event_type.
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: WebhookEventType, workdir: &str, data: serde_json::Value) -> Self {
+        Self {
+            event_type,
+            workdir: workdir.to_string(),
+            timestamp: Utc::now(),
+            data,
+        }
+    }
+
+    // The JSON body of the POST.
+    pub fn payload(&self) -> serde_json::Value {
+        serde_json::json!({
+            "event": self.event_type.as_str(),
+            "workdir": self.workdir,
+            "timestamp": self.timestamp.to_rfc3339(),
+            "data": self.data,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WebhookStats {
+    delivered: AtomicU64,
+    retried: AtomicU64,
+    dropped: AtomicU64, // Queue full or all attempts failed.
+}
+
+impl WebhookStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::Relaxed)
+    }
+
+    pub fn retried(&self) -> u64 {
+        self.retried.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_delivered(&self) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_retried(&self) {
+        self.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub enum WebhookMsg {
+    Config(Vec<WebhookConfig>), // Replaces the whole config.
+    Event(WebhookEvent),
+}
+
+pub type WebhookRx = tokio::sync::mpsc::Receiver<WebhookMsg>;
+
+#[derive(Debug, Clone)]
+pub struct WebhookTx {
+    tx: tokio::sync::mpsc::Sender<WebhookMsg>,
+    stats: Arc<WebhookStats>,
+}
+
+impl WebhookTx {
+    pub fn channel(stats: Arc<WebhookStats>) -> (WebhookTx, WebhookRx) {
+        let (tx, rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        (WebhookTx { tx, stats }, rx)
+    }
+
+    pub fn send_event(&self, event: WebhookEvent) {
+        match self.tx.try_send(WebhookMsg::Event(event)) {
+            Ok(()) => {}
+            Err(tokio::sync::mpsc::error::TrySendError::Full(msg)) => {
+                self.stats.inc_dropped();
+                log::warn!("webhook queue full, dropping {:?}", msg);
+            }
+            Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+                // No WebhookWorker (e.g. some tests).
+            }
+        }
+    }
+
+    pub async fn send_config(&self, config: Vec<WebhookConfig>) {
+        if let Err(e) = self.tx.send(WebhookMsg::Config(config)).await {
+            log::error!("send webhooks config failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_wire_format() {
+        for event_type in WebhookEventType::ALL {
+            assert_eq!(
+                event_type.as_str().parse::<WebhookEventType>().unwrap(),
+                event_type
+            );
+        }
+        assert!("link_down".parse::<WebhookEventType>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_event_queue_full() {
+        let stats = Arc::new(WebhookStats::new());
+        let (tx, mut rx) = WebhookTx::channel(stats.clone());
+        let data = serde_json::json!({ "alias": "localnet", "status": "DOWN" });
+        for _ in 0..MPSC_Q_SIZE + 2 {
+            let event =
+                WebhookEvent::new(WebhookEventType::LinkStatusChange, "localnet", data.clone());
+            tx.send_event(event);
+        }
+        assert_eq!(stats.dropped(), 2);
+
+        match rx.recv().await {
+            Some(WebhookMsg::Event(event)) => {
+                let payload = event.payload();
+                assert_eq!(payload["event"], "link_status_change");
+                assert_eq!(payload["workdir"], "localnet");
+                assert_eq!(payload["data"]["status"], "DOWN");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,482 @@
+// Orphaned sui/faucet processes of a workdir (see cleanupWorkdirProcesses).
+//
+// A previous sui process that did not fully exit (e.g. after a crash) may still hold
+// the ports or the database lock, and the next "localnet start" then fails in
+// confusing ways (e.g. "Segmentation fault").
+//
+// A process belongs to a workdir when it is:
+//   - "sui start" with its --network.config in the workdir (or, without that option,
+//     started from the workdir binaries: workdirs/{workdir}/sui-repo/target/debug).
+//   - "sui-faucet" started from the workdir binaries.
+//
+// The scripts record the pid of the processes they start:
+//
+//   workdirs/{workdir}/.state/sui-process.pid
+//   workdirs/{workdir}/.state/sui-faucet-process.pid
+//
+// A recorded and running process is adopted (left alone). So is the only process of
+// its kind when there is no pid file (started by scripts older than the pid files), and
+// its pid file is then created. Any other is terminated with SIGTERM, then SIGKILL when
+// still alive after PROCESS_TERM_TIMEOUT.
+//
+// Processes are found with sysinfo (no shell).
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, Signal, System, UpdateKind};
+
+use crate::api::WorkdirProcessAction;
+
+pub const PROCESS_TERM_TIMEOUT: Duration = Duration::from_secs(10);
+
+// After a SIGKILL. Only a process stuck in the kernel (e.g. disk sleep) takes that long.
+const PROCESS_KILL_TIMEOUT: Duration = Duration::from_secs(3);
+
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub const PROCESS_ACTION_ADOPTED: &str = "adopted";
+pub const PROCESS_ACTION_TERMINATED: &str = "terminated"; // Exited on SIGTERM.
+pub const PROCESS_ACTION_KILLED: &str = "killed"; // Needed a SIGKILL.
+pub const PROCESS_ACTION_FAILED: &str = "failed"; // Still running.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkdirProcessKind {
+    Node,
+    Faucet,
+}
+
+impl WorkdirProcessKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkdirProcessKind::Node => "sui",
+            WorkdirProcessKind::Faucet => "sui-faucet",
+        }
+    }
+
+    pub fn pid_filename(&self) -> &'static str {
+        match self {
+            WorkdirProcessKind::Node => "sui-process.pid",
+            WorkdirProcessKind::Faucet => "sui-faucet-process.pid",
+        }
+    }
+
+    // In workdirs/{workdir}/config (see process_log.rs).
+    pub fn log_filename(&self) -> &'static str {
+        match self {
+            WorkdirProcessKind::Node => "sui-process.log",
+            WorkdirProcessKind::Faucet => "sui-faucet-process.log",
+        }
+    }
+
+    // Inverse of as_str().
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sui" => Some(WorkdirProcessKind::Node),
+            "sui-faucet" => Some(WorkdirProcessKind::Faucet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkdirProcess {
+    pub pid: u32,
+    pub kind: WorkdirProcessKind,
+    pub cmd: String,
+    pub recorded: bool, // pid in its .state pid file (or no pid file, see above).
+    pub running: bool,  // false when stopped (SIGSTOP) or being traced.
+}
+
+impl WorkdirProcess {
+    // None when the process should be adopted, otherwise why it is an orphan.
+    pub fn orphan_reason(&self) -> Option<String> {
+        let pid_file = format!(".state/{}", self.kind.pid_filename());
+        match (self.recorded, self.running) {
+            (true, true) => None,
+            (true, false) => Some(format!("in {} but not running", pid_file)),
+            (false, _) => Some(format!("not in {}", pid_file)),
+        }
+    }
+}
+
+fn canonical(path: &Path) -> Option<PathBuf> {
+    std::fs::canonicalize(path).ok()
+}
+
+// Value of "--network.config <path>" (or "--network.config=<path>").
+fn network_config_arg(args: &[String]) -> Option<&str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--network.config" {
+            return iter.next().map(String::as_str);
+        }
+        if let Some(value) = arg.strip_prefix("--network.config=") {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// The kind of process when it belongs to the workdir, from its executable and command line.
+pub fn workdir_process_kind(
+    workdir_path: &Path,
+    exe: Option<&Path>,
+    cmd: &[String],
+) -> Option<WorkdirProcessKind> {
+    let program = cmd.first().map(Path::new).or(exe)?;
+    let name = program.file_name()?.to_str()?;
+    if name != "sui" && name != "sui-faucet" {
+        return None;
+    }
+
+    // A binary of the workdir (sui-repo is a symlink, so compare resolved paths).
+    let from_workdir_bin = || {
+        let bin_dir = match canonical(&workdir_path.join("sui-repo/target/debug")) {
+            Some(bin_dir) => bin_dir,
+            None => return false,
+        };
+        [exe, Some(program)]
+            .into_iter()
+            .flatten()
+            .filter_map(canonical)
+            .any(|path| path.parent() == Some(bin_dir.as_path()))
+    };
+
+    let args = cmd.get(1..).unwrap_or_default();
+    if name == "sui-faucet" {
+        return from_workdir_bin().then_some(WorkdirProcessKind::Faucet);
+    }
+    // Not the other sui subcommands (e.g. "sui client", commonly used meanwhile).
+    if !args.iter().any(|arg| arg == "start") {
+        return None;
+    }
+    let belongs = match network_config_arg(args) {
+        Some(config) => match (canonical(Path::new(config)), canonical(workdir_path)) {
+            (Some(config), Some(workdir)) => config.starts_with(workdir),
+            _ => false,
+        },
+        None => from_workdir_bin(),
+    };
+    belongs.then_some(WorkdirProcessKind::Node)
+}
+
+// The pid recorded by the scripts (None when the file does not exist or is invalid).
+pub fn read_pid_file(workdir_path: &Path, kind: WorkdirProcessKind) -> Option<u32> {
+    let pathname = workdir_path.join(".state").join(kind.pid_filename());
+    std::fs::read_to_string(pathname).ok()?.trim().parse().ok()
+}
+
+fn refresh_processes(sys: &mut System) {
+    sys.refresh_processes_specifics(
+        ProcessRefreshKind::new()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet),
+    );
+}
+
+fn find_in(sys: &System, workdir_path: &Path) -> Vec<WorkdirProcess> {
+    let mut processes: Vec<WorkdirProcess> = sys
+        .processes()
+        .iter()
+        .filter_map(|(pid, process)| {
+            // Already exited (a zombie holds no port nor lock).
+            if matches!(
+                process.status(),
+                ProcessStatus::Zombie | ProcessStatus::Dead
+            ) {
+                return None;
+            }
+            let kind = workdir_process_kind(workdir_path, process.exe(), process.cmd())?;
+            Some(WorkdirProcess {
+                pid: pid.as_u32(),
+                kind,
+                cmd: process.cmd().join(" "),
+                recorded: false, // Set below.
+                running: !matches!(
+                    process.status(),
+                    ProcessStatus::Stop | ProcessStatus::Tracing
+                ),
+            })
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+
+    for kind in [WorkdirProcessKind::Node, WorkdirProcessKind::Faucet] {
+        let count = processes.iter().filter(|p| p.kind == kind).count();
+        let recorded_pid = read_pid_file(workdir_path, kind);
+        for process in processes.iter_mut().filter(|p| p.kind == kind) {
+            process.recorded = match recorded_pid {
+                Some(recorded_pid) => recorded_pid == process.pid,
+                None => count == 1,
+            };
+        }
+    }
+    processes
+}
+
+// The sui/faucet processes of the workdir (sorted by pid).
+pub fn find_workdir_processes(workdir_path: &Path) -> Vec<WorkdirProcess> {
+    let mut sys = System::new();
+    refresh_processes(&mut sys);
+    find_in(&sys, workdir_path)
+}
+
+// True once the process is gone (or only a zombie waiting to be reaped by its parent).
+fn is_exited(sys: &mut System, pid: Pid) -> bool {
+    if !sys.refresh_process_specifics(pid, ProcessRefreshKind::new()) {
+        return true;
+    }
+    match sys.process(pid) {
+        Some(process) => matches!(
+            process.status(),
+            ProcessStatus::Zombie | ProcessStatus::Dead
+        ),
+        None => true,
+    }
+}
+
+async fn wait_exited(sys: &mut System, pid: Pid, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if is_exited(sys, pid) {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(PROCESS_POLL_INTERVAL).await;
+    }
+}
+
+// SIGTERM, then SIGKILL after 'term_timeout'. Returns the action done.
+async fn terminate(sys: &mut System, pid: u32, term_timeout: Duration) -> &'static str {
+    let pid = Pid::from_u32(pid);
+    if let Some(process) = sys.process(pid) {
+        // None when SIGTERM is not supported by the platform.
+        if process.kill_with(Signal::Term).is_none() {
+            process.kill();
+        }
+    }
+    if wait_exited(sys, pid, term_timeout).await {
+        return PROCESS_ACTION_TERMINATED;
+    }
+    if let Some(process) = sys.process(pid) {
+        process.kill();
+    }
+    if wait_exited(sys, pid, PROCESS_KILL_TIMEOUT).await {
+        PROCESS_ACTION_KILLED
+    } else {
+        PROCESS_ACTION_FAILED
+    }
+}
+
+// Adopt or terminate every sui/faucet process of the workdir. A pid file not matching
+// any running process is removed (the pid could otherwise be re-used by another process).
+//
+// The caller must prevent a concurrent start/stop of the workdir (see get_api_mutex).
+pub async fn cleanup_workdir_processes(
+    workdir_path: &Path,
+    term_timeout: Duration,
+) -> Vec<WorkdirProcessAction> {
+    let mut sys = System::new();
+    refresh_processes(&mut sys);
+    let processes = find_in(&sys, workdir_path);
+
+    let mut actions = Vec::new();
+    for process in processes.iter() {
+        let (action, reason) = match process.orphan_reason() {
+            None if read_pid_file(workdir_path, process.kind).is_none() => {
+                let pid_file = workdir_path
+                    .join(".state")
+                    .join(process.kind.pid_filename());
+                let _ = std::fs::write(pid_file, format!("{}\n", process.pid));
+                (
+                    PROCESS_ACTION_ADOPTED,
+                    "running, pid file created".to_string(),
+                )
+            }
+            None => (
+                PROCESS_ACTION_ADOPTED,
+                "running and in its pid file".to_string(),
+            ),
+            Some(reason) => (terminate(&mut sys, process.pid, term_timeout).await, reason),
+        };
+        if action == PROCESS_ACTION_FAILED {
+            log::error!(
+                "{} pid {} still running after SIGKILL",
+                process.kind.as_str(),
+                process.pid
+            );
+        } else if action != PROCESS_ACTION_ADOPTED {
+            log::warn!(
+                "{} pid {} {} ({})",
+                process.kind.as_str(),
+                process.pid,
+                action,
+                reason
+            );
+        }
+        actions.push(WorkdirProcessAction {
+            pid: process.pid,
+            process: process.kind.as_str().to_string(),
+            cmd: process.cmd.clone(),
+            action: action.to_string(),
+            reason,
+        });
+    }
+
+    for kind in [WorkdirProcessKind::Node, WorkdirProcessKind::Faucet] {
+        let adopted = processes
+            .iter()
+            .any(|p| p.kind == kind && p.orphan_reason().is_none());
+        if !adopted && read_pid_file(workdir_path, kind).is_some() {
+            let _ = std::fs::remove_file(workdir_path.join(".state").join(kind.pid_filename()));
+        }
+    }
+    actions
+}
+
+// One line for the user, e.g. "sui pid 1234 terminated (not in .state/sui-process.pid)".
+pub fn process_actions_summary(actions: &[WorkdirProcessAction]) -> String {
+    actions
+        .iter()
+        .map(|a| format!("{} pid {} {} ({})", a.process, a.pid, a.action, a.reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    // A workdir with its sui binaries directory, under a temp dir unique to the test.
+    fn create_workdir(test: &str) -> PathBuf {
+        let workdir = std::env::temp_dir().join(format!("sbsd-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&workdir);
+        std::fs::create_dir_all(workdir.join("sui-repo-default/target/debug")).unwrap();
+        std::os::unix::fs::symlink(workdir.join("sui-repo-default"), workdir.join("sui-repo"))
+            .unwrap();
+        std::fs::create_dir_all(workdir.join("config")).unwrap();
+        std::fs::write(workdir.join("config/network.yaml"), "").unwrap();
+        std::fs::create_dir_all(workdir.join(".state")).unwrap();
+        workdir
+    }
+
+    #[test]
+    fn test_workdir_process_kind() {
+        let workdir = create_workdir("process-kind");
+        let bin = workdir.join("sui-repo/target/debug");
+        std::fs::write(bin.join("sui"), "").unwrap();
+        std::fs::write(bin.join("sui-faucet"), "").unwrap();
+        let sui = bin.join("sui").to_string_lossy().to_string();
+        let sui = sui.as_str();
+        let faucet = bin.join("sui-faucet").to_string_lossy().to_string();
+        let faucet = faucet.as_str();
+        let config = workdir.join("config/network.yaml");
+        let config = config.to_string_lossy().to_string();
+        let config = config.as_str();
+        let kind = |cmd: &[&str]| workdir_process_kind(&workdir, None, &args(cmd));
+
+        assert_eq!(
+            kind(&[sui, "start", "--network.config", config]),
+            Some(WorkdirProcessKind::Node)
+        );
+        // Another sui binary, but on the network config of the workdir.
+        let other_config = format!("--network.config={}", config);
+        assert_eq!(
+            kind(&["/usr/bin/sui", "start", other_config.as_str()]),
+            Some(WorkdirProcessKind::Node)
+        );
+        assert_eq!(kind(&[sui, "start"]), Some(WorkdirProcessKind::Node));
+        assert_eq!(
+            kind(&[faucet, "--port", "9123"]),
+            Some(WorkdirProcessKind::Faucet)
+        );
+
+        // Not a node, or not of this workdir.
+        assert_eq!(kind(&[sui, "client", "gas"]), None);
+        assert_eq!(kind(&["/usr/bin/sui", "start"]), None);
+        assert_eq!(
+            kind(&[sui, "start", "--network.config", "/tmp/other/network.yaml"]),
+            None
+        );
+        assert_eq!(kind(&["/usr/bin/sui-faucet"]), None);
+        assert_eq!(kind(&["/bin/sleep", "start"]), None);
+        assert_eq!(kind(&[]), None);
+        let _ = std::fs::remove_dir_all(&workdir);
+    }
+
+    #[test]
+    fn test_orphan_reason() {
+        let mut process = WorkdirProcess {
+            pid: 42,
+            kind: WorkdirProcessKind::Faucet,
+            cmd: String::new(),
+            recorded: true,
+            running: true,
+        };
+        assert_eq!(process.orphan_reason(), None);
+        process.running = false;
+        assert!(process.orphan_reason().unwrap().contains("not running"));
+        process.recorded = false;
+        assert_eq!(
+            process.orphan_reason().unwrap(),
+            "not in .state/sui-faucet-process.pid"
+        );
+    }
+
+    // A dummy "sui-faucet" (a copy of sleep) of the workdir.
+    fn spawn_dummy_faucet(workdir: &Path) -> std::process::Child {
+        let faucet = workdir.join("sui-repo/target/debug/sui-faucet");
+        std::fs::copy("/bin/sleep", &faucet).unwrap();
+        std::process::Command::new(&faucet)
+            .arg("30")
+            .spawn()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_workdir_processes() {
+        let workdir = create_workdir("process-cleanup");
+        let pid_file = workdir.join(".state/sui-faucet-process.pid");
+
+        // Only one, without pid file: adopted and recorded.
+        let mut adopted = spawn_dummy_faucet(&workdir);
+        let actions = cleanup_workdir_processes(&workdir, PROCESS_TERM_TIMEOUT).await;
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].pid, adopted.id());
+        assert_eq!(actions[0].action, PROCESS_ACTION_ADOPTED);
+        assert_eq!(
+            read_pid_file(&workdir, WorkdirProcessKind::Faucet),
+            Some(adopted.id())
+        );
+
+        // Recorded: adopted again.
+        let actions = cleanup_workdir_processes(&workdir, PROCESS_TERM_TIMEOUT).await;
+        assert_eq!(actions[0].action, PROCESS_ACTION_ADOPTED);
+        assert!(adopted.try_wait().unwrap().is_none());
+
+        // The pid file now names another process: the dummy is an orphan.
+        std::fs::write(&pid_file, "999999999").unwrap();
+        let processes = find_workdir_processes(&workdir);
+        assert_eq!(processes.len(), 1);
+        assert!(!processes[0].recorded);
+        let actions = cleanup_workdir_processes(&workdir, PROCESS_TERM_TIMEOUT).await;
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].action, PROCESS_ACTION_TERMINATED);
+        assert_eq!(actions[0].process, "sui-faucet");
+        assert!(process_actions_summary(&actions).contains("not in .state"));
+        assert!(adopted.wait().is_ok());
+        assert!(!pid_file.exists()); // Stale.
+
+        assert!(find_workdir_processes(&workdir).is_empty());
+        assert!(cleanup_workdir_processes(&workdir, PROCESS_TERM_TIMEOUT)
+            .await
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&workdir);
+    }
+}
@@ -0,0 +1,817 @@
+// Bundle of a workdir setup, to reproduce it on another machine (e.g. onboarding a
+// teammate). See exportWorkdirProfile and importWorkdirProfile.
+//
+// A single JSON document (WorkdirProfile) with:
+//   - the user suibase.yaml of the workdir.
+//   - the publication records (published-data), each file in Base64.
+//   - the keystore entries selected on export. None by default, and imported only
+//     when asked for (these are private keys).
+//   - a manifest of the environment it comes from (versions, platform, packages and
+//     named addresses). Not applied, only used to report the package conflicts.
+//
+// The symlinks of published-data (e.g. "most-recent") are exported relative to
+// published-data, then re-created with an absolute target (same as the publish script).
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use chrono::Utc;
+use data_encoding::{BASE64, HEXLOWER};
+use serde_json::Value as JsonValue;
+
+use crate::api::{
+    ImportWorkdirProfileResponse, WorkdirProfile, WorkdirProfileAddress, WorkdirProfileConflict,
+    WorkdirProfileFile, WorkdirProfileKey, WorkdirProfileManifest, WorkdirProfilePackage,
+};
+
+use super::{Link, WorkdirUserConfig};
+
+pub const WORKDIR_PROFILE_FORMAT: &str = "suibase-workdir-profile";
+pub const WORKDIR_PROFILE_VERSION: u32 = 1;
+
+// Relative to the workdir.
+const PUBLISHED_DATA_DIR: &str = "published-data";
+const USER_YAML_FILENAME: &str = "suibase.yaml";
+const KEYSTORE_PATH: &str = "config/sui.keystore";
+const ALIASES_PATH: &str = "config/sui.aliases";
+
+// Relative to the directory of a package in published-data.
+const MOST_RECENT_PACKAGE_ID: &str = "most-recent/package-id.json";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfileImportOptions {
+    pub dry_run: bool,     // Only report the changes and conflicts.
+    pub force: bool,       // Apply despite the link and package conflicts.
+    pub import_keys: bool, // Without it, the keys of the profile are ignored.
+}
+
+// The manifest fields not known from the files (suibase_version and sui_version) are
+// left to the caller.
+//
+// 'keys' are the aliases or addresses of the keystore entries to include.
+pub fn export_workdir_profile(
+    workdir: &str,
+    workdir_path: &Path,
+    keys: &[String],
+) -> Result<(WorkdirProfile, Vec<String>)> {
+    let mut warnings = Vec::new();
+
+    let user_yaml = workdir_path.join(USER_YAML_FILENAME);
+    let suibase_yaml = match std::fs::read_to_string(&user_yaml) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let published_path = workdir_path.join(PUBLISHED_DATA_DIR);
+    let mut published_data = Vec::new();
+    if published_path.is_dir() {
+        let root = std::fs::canonicalize(&published_path)?;
+        export_published_dir(
+            &published_path,
+            &root,
+            &published_path,
+            &mut published_data,
+            &mut warnings,
+        )?;
+    }
+
+    let named_keys = read_named_keys(workdir_path);
+    let mut selected_keys: Vec<WorkdirProfileKey> = Vec::new();
+    for key in keys {
+        let named_key = named_keys
+            .iter()
+            .find(|named_key| named_key.alias == *key || named_key.address == key.to_lowercase())
+            .ok_or_else(|| anyhow!("key {} not found in {}", key, ALIASES_PATH))?;
+        if named_key.private_key.is_empty() {
+            bail!(
+                "{} does not match {} (key {} not exported)",
+                KEYSTORE_PATH,
+                ALIASES_PATH,
+                key
+            );
+        }
+        if !selected_keys.contains(named_key) {
+            selected_keys.push(named_key.clone());
+        }
+    }
+
+    let manifest = WorkdirProfileManifest {
+        workdir: workdir.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+        suibase_version: None,
+        sui_version: None,
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        packages: read_packages(&published_path),
+        addresses: named_keys
+            .iter()
+            .map(|named_key| WorkdirProfileAddress {
+                alias: named_key.alias.clone(),
+                address: named_key.address.clone(),
+            })
+            .collect(),
+    };
+    let profile = WorkdirProfile {
+        format: WORKDIR_PROFILE_FORMAT.to_string(),
+        version: WORKDIR_PROFILE_VERSION,
+        manifest,
+        suibase_yaml,
+        published_data,
+        keys: selected_keys,
+    };
+    Ok((profile, warnings))
+}
+
+// Files and symlinks under 'dir', sorted by path. 'root' is the canonical path of
+// published-data (the symlinks are resolved, so both relative and absolute work).
+fn export_published_dir(
+    published_path: &Path,
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<WorkdirProfileFile>,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let rel_path = path
+            .strip_prefix(published_path)?
+            .to_string_lossy()
+            .to_string();
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let target = std::fs::canonicalize(&path)
+                .ok()
+                .and_then(|target| target.strip_prefix(root).ok().map(Path::to_path_buf))
+                .filter(|target| !target.as_os_str().is_empty());
+            match target {
+                Some(target) => files.push(WorkdirProfileFile {
+                    path: rel_path,
+                    symlink: Some(target.to_string_lossy().to_string()),
+                    contents: None,
+                }),
+                None => warnings.push(format!(
+                    "{}/{} not exported (symlink target not within {})",
+                    PUBLISHED_DATA_DIR, rel_path, PUBLISHED_DATA_DIR
+                )),
+            }
+        } else if file_type.is_dir() {
+            export_published_dir(published_path, root, &path, files, warnings)?;
+        } else {
+            files.push(WorkdirProfileFile {
+                path: rel_path,
+                symlink: None,
+                contents: Some(BASE64.encode(&std::fs::read(&path)?)),
+            });
+        }
+    }
+    Ok(())
+}
+
+// The published packages, sorted by name.
+fn read_packages(published_path: &Path) -> Vec<WorkdirProfilePackage> {
+    let mut packages: Vec<WorkdirProfilePackage> = std::fs::read_dir(published_path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            Some(WorkdirProfilePackage {
+                package_id: read_package_id(&entry.path()),
+                name,
+            })
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+// e.g. ["0x5c8d...1c2d"]
+fn read_package_id(package_path: &Path) -> Option<String> {
+    let contents = std::fs::read_to_string(package_path.join(MOST_RECENT_PACKAGE_ID)).ok()?;
+    let ids: Vec<String> = serde_json::from_str(&contents).ok()?;
+    ids.into_iter().next()
+}
+
+fn read_json_array(path: &Path) -> Vec<JsonValue> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+// The named addresses of sui.aliases, each with its keystore entry.
+//
+// The sui client writes both files in the same order, so the entries match by
+// position. private_key is empty when the keystore does not match the aliases.
+fn read_named_keys(workdir_path: &Path) -> Vec<WorkdirProfileKey> {
+    let aliases = read_json_array(&workdir_path.join(ALIASES_PATH));
+    let keystore = read_json_array(&workdir_path.join(KEYSTORE_PATH));
+    let is_matching = aliases.len() == keystore.len();
+    aliases
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let public_key_base64 = entry["public_key_base64"].as_str()?;
+            let private_key = keystore
+                .get(i)
+                .filter(|_| is_matching)
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default();
+            Some(WorkdirProfileKey {
+                alias: entry["alias"].as_str()?.to_string(),
+                address: sui_address(public_key_base64)?,
+                public_key_base64: public_key_base64.to_string(),
+                private_key: private_key.to_string(),
+            })
+        })
+        .collect()
+}
+
+// Address of a public key as in sui.aliases (Base64 of flag || public key).
+fn sui_address(public_key_base64: &str) -> Option<String> {
+    let public_key = BASE64.decode(public_key_base64.as_bytes()).ok()?;
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(&public_key);
+    Some(format!("0x{}", HEXLOWER.encode(&hasher.finalize())))
+}
+
+// Relative, without any "..", so always within the directory it is joined to.
+fn is_contained_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Everything is checked before writing anything.
+fn check_profile(profile: &WorkdirProfile) -> Result<()> {
+    if profile.format != WORKDIR_PROFILE_FORMAT {
+        bail!("not a workdir profile (format {})", profile.format);
+    }
+    if profile.version != WORKDIR_PROFILE_VERSION {
+        bail!(
+            "profile version {} not supported (expected {})",
+            profile.version,
+            WORKDIR_PROFILE_VERSION
+        );
+    }
+    for file in &profile.published_data {
+        let is_valid = is_contained_path(&file.path)
+            && match (&file.symlink, &file.contents) {
+                (Some(target), None) => is_contained_path(target),
+                (None, Some(contents)) => BASE64.decode(contents.as_bytes()).is_ok(),
+                _ => false,
+            };
+        if !is_valid {
+            bail!("invalid {} entry {}", PUBLISHED_DATA_DIR, file.path);
+        }
+    }
+    for key in &profile.keys {
+        if key.private_key.is_empty()
+            || sui_address(&key.public_key_base64).as_deref() != Some(key.address.as_str())
+        {
+            bail!("invalid key {}", key.alias);
+        }
+    }
+    Ok(())
+}
+
+// The links defined in both, but not the same way.
+fn link_conflicts(
+    current: &HashMap<String, Link>,
+    imported: &HashMap<String, Link>,
+) -> Vec<WorkdirProfileConflict> {
+    let mut conflicts: Vec<WorkdirProfileConflict> = imported
+        .iter()
+        .filter_map(|(alias, link)| {
+            let current_fields = current.get(alias)?.fields();
+            let fields: Vec<&str> = current_fields
+                .iter()
+                .zip(link.fields().iter())
+                .filter(|(current, imported)| current.1 != imported.1)
+                .map(|(current, _)| current.0)
+                .collect();
+            (!fields.is_empty()).then(|| WorkdirProfileConflict {
+                kind: "link".to_string(),
+                name: alias.clone(),
+                detail: format!("not the same in the workdir: {}", fields.join(", ")),
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+    conflicts
+}
+
+fn write_replace(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    std::fs::write(&tmp_path, contents)?;
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+// Appended to the keystore (and to the aliases, when the file exists). A copy of each
+// file prior to the import is kept as "<file>.bak".
+fn append_keys(workdir_path: &Path, keys: &[&WorkdirProfileKey]) -> Result<()> {
+    let keystore_entries: Vec<JsonValue> = keys
+        .iter()
+        .map(|key| JsonValue::from(key.private_key.as_str()))
+        .collect();
+    let aliases_entries: Vec<JsonValue> = keys
+        .iter()
+        .map(|key| {
+            serde_json::json!({
+                "alias": key.alias,
+                "public_key_base64": key.public_key_base64,
+            })
+        })
+        .collect();
+    for (path, entries) in [
+        (KEYSTORE_PATH, keystore_entries),
+        (ALIASES_PATH, aliases_entries),
+    ] {
+        let path = workdir_path.join(path);
+        if !path.exists() {
+            continue;
+        }
+        let mut array: Vec<JsonValue> = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        array.extend(entries);
+        let mut bak_path = path.as_os_str().to_owned();
+        bak_path.push(".bak");
+        std::fs::copy(&path, PathBuf::from(bak_path))?;
+        write_replace(&path, serde_json::to_string_pretty(&array)?.as_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn import_workdir_profile(
+    workdir_path: &Path,
+    profile: &WorkdirProfile,
+    options: ProfileImportOptions,
+) -> Result<ImportWorkdirProfileResponse> {
+    check_profile(profile)?;
+    let mut resp = ImportWorkdirProfileResponse::new();
+    resp.dry_run = options.dry_run;
+
+    // The user suibase.yaml is replaced.
+    let user_yaml = workdir_path.join(USER_YAML_FILENAME);
+    let mut write_yaml: Option<&str> = None;
+    if let Some(yaml) = &profile.suibase_yaml {
+        let mut config = WorkdirUserConfig::new();
+        config
+            .load_and_merge_from_str(yaml, "profile")
+            .map_err(|e| anyhow!("invalid {}: {}", USER_YAML_FILENAME, e))?;
+        resp.warnings.extend(config.warnings().iter().cloned());
+        match std::fs::read_to_string(&user_yaml) {
+            Ok(current) if current == *yaml => {}
+            Ok(current) => {
+                let mut current_config = WorkdirUserConfig::new();
+                if current_config
+                    .load_and_merge_from_str(&current, USER_YAML_FILENAME)
+                    .is_ok()
+                {
+                    resp.conflicts
+                        .extend(link_conflicts(current_config.links(), config.links()));
+                }
+                resp.changes
+                    .push(format!("{} replaced", USER_YAML_FILENAME));
+                write_yaml = Some(yaml);
+            }
+            Err(_) => {
+                resp.changes.push(format!("{} created", USER_YAML_FILENAME));
+                write_yaml = Some(yaml);
+            }
+        }
+    }
+
+    // A package already published with the same id is left as-is.
+    let published_path = workdir_path.join(PUBLISHED_DATA_DIR);
+    let packages: BTreeSet<&str> = profile
+        .published_data
+        .iter()
+        .filter_map(|file| file.path.split('/').next())
+        .collect();
+    let mut write_packages: Vec<&str> = Vec::new();
+    for name in packages {
+        let package_id = profile
+            .manifest
+            .packages
+            .iter()
+            .find(|package| package.name == name)
+            .and_then(|package| package.package_id.clone());
+        let package_path = published_path.join(name);
+        if !package_path.exists() {
+            resp.changes.push(format!("package {} added", name));
+            write_packages.push(name);
+            continue;
+        }
+        let current_id = read_package_id(&package_path);
+        if current_id == package_id {
+            continue;
+        }
+        resp.conflicts.push(WorkdirProfileConflict {
+            kind: "package".to_string(),
+            name: name.to_string(),
+            detail: format!(
+                "published as {} in the workdir, {} in the profile",
+                current_id.as_deref().unwrap_or("unknown"),
+                package_id.as_deref().unwrap_or("unknown")
+            ),
+        });
+        resp.changes.push(format!("package {} replaced", name));
+        write_packages.push(name);
+    }
+
+    // Never over an existing alias, even when forced.
+    let mut write_keys: Vec<&WorkdirProfileKey> = Vec::new();
+    if !profile.keys.is_empty() {
+        if !options.import_keys {
+            resp.warnings.push(format!(
+                "{} key(s) of the profile not imported (import_keys not set)",
+                profile.keys.len()
+            ));
+        } else if !workdir_path.join(KEYSTORE_PATH).exists() {
+            resp.warnings.push(format!(
+                "keys not imported, {} not found (start the workdir once to create it)",
+                KEYSTORE_PATH
+            ));
+        } else {
+            let named_keys = read_named_keys(workdir_path);
+            for key in &profile.keys {
+                if let Some(named_key) = named_keys.iter().find(|k| k.alias == key.alias) {
+                    if named_key.public_key_base64 != key.public_key_base64 {
+                        resp.conflicts.push(WorkdirProfileConflict {
+                            kind: "key".to_string(),
+                            name: key.alias.clone(),
+                            detail: format!(
+                                "alias of {} in the workdir (key not imported)",
+                                named_key.address
+                            ),
+                        });
+                    }
+                    continue;
+                }
+                if named_keys.iter().any(|k| k.address == key.address) {
+                    continue; // Same key, another alias.
+                }
+                resp.changes
+                    .push(format!("key {} ({}) added", key.alias, key.address));
+                write_keys.push(key);
+            }
+        }
+    }
+
+    let is_blocked = !options.force && resp.conflicts.iter().any(|c| c.kind != "key");
+    if options.dry_run || is_blocked {
+        return Ok(resp);
+    }
+
+    if let Some(yaml) = write_yaml {
+        write_replace(&user_yaml, yaml.as_bytes())?;
+    }
+    for name in &write_packages {
+        let package_path = published_path.join(name);
+        if package_path.exists() {
+            std::fs::remove_dir_all(&package_path)?;
+        }
+    }
+    for file in &profile.published_data {
+        let is_written = file
+            .path
+            .split('/')
+            .next()
+            .is_some_and(|name| write_packages.contains(&name));
+        if !is_written {
+            continue;
+        }
+        let path = published_path.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match (&file.symlink, &file.contents) {
+            (Some(target), _) => std::os::unix::fs::symlink(published_path.join(target), &path)?,
+            (None, Some(contents)) => std::fs::write(&path, BASE64.decode(contents.as_bytes())?)?,
+            (None, None) => {} // Refused by check_profile.
+        }
+    }
+    if !write_keys.is_empty() {
+        append_keys(workdir_path, &write_keys)?;
+    }
+    resp.applied = true;
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ed25519 flag, then a (fake) public key.
+    const PUBLIC_KEY_1: &str = "AAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEB";
+    const PUBLIC_KEY_2: &str = "AAICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgIC";
+
+    const USER_YAML: &str = "proxy_enabled: true\n\
+                             links:\n\
+                             \x20 - alias: \"team-rpc\"\n\
+                             \x20   rpc: \"http://10.0.0.5:9000\"\n";
+
+    // A started workdir with a published package (same layout as the publish script).
+    fn fixture_workdir(name: &str) -> PathBuf {
+        let workdir =
+            std::env::temp_dir().join(format!("sbsd-profile-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&workdir);
+        std::fs::create_dir_all(workdir.join("config")).unwrap();
+        std::fs::write(workdir.join(USER_YAML_FILENAME), USER_YAML).unwrap();
+
+        let package = workdir.join("published-data/demo");
+        let publication = package.join("UUID1/1727712345678");
+        std::fs::create_dir_all(publication.join("build/demo/bytecode_modules")).unwrap();
+        std::fs::write(publication.join("package-id.json"), "[\"0x5c8d\"]").unwrap();
+        std::fs::write(
+            publication.join("created-objects.json"),
+            "[{\"type\":\"0x5c8d::counter::Counter\",\"objectId\":\"0x7b3e\"}]",
+        )
+        .unwrap();
+        std::fs::write(
+            publication.join("build/demo/bytecode_modules/counter.mv"),
+            [0xa1, 0x1c, 0xeb, 0x0b, 0x00, 0xff],
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(&publication, package.join("most-recent")).unwrap();
+        std::os::unix::fs::symlink(
+            "./1727712345678",
+            package.join("UUID1/most-recent-timestamp"),
+        )
+        .unwrap();
+
+        let keystore = serde_json::json!(["AAEprivate1", "AAEprivate2"]);
+        let aliases = serde_json::json!([
+            { "alias": "admin", "public_key_base64": PUBLIC_KEY_1 },
+            { "alias": "user", "public_key_base64": PUBLIC_KEY_2 },
+        ]);
+        std::fs::write(workdir.join(KEYSTORE_PATH), keystore.to_string()).unwrap();
+        std::fs::write(workdir.join(ALIASES_PATH), aliases.to_string()).unwrap();
+        workdir
+    }
+
+    // An initialized workdir, never started.
+    fn fresh_workdir(name: &str) -> PathBuf {
+        let workdir =
+            std::env::temp_dir().join(format!("sbsd-profile-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&workdir);
+        std::fs::create_dir_all(&workdir).unwrap();
+        workdir
+    }
+
+    // Relative path -> file contents or symlink target (resolved).
+    fn published_tree(workdir: &Path) -> Vec<(String, String)> {
+        let published_path = workdir.join(PUBLISHED_DATA_DIR);
+        let root = std::fs::canonicalize(&published_path).unwrap();
+        let mut files = Vec::new();
+        let mut warnings = Vec::new();
+        export_published_dir(
+            &published_path,
+            &root,
+            &published_path,
+            &mut files,
+            &mut warnings,
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        files
+            .into_iter()
+            .map(|file| (file.path, file.symlink.or(file.contents).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_export_import_profile() {
+        let source = fixture_workdir("source");
+        let (profile, warnings) = export_workdir_profile("localnet", &source, &[]).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(profile.suibase_yaml.as_deref(), Some(USER_YAML));
+        assert!(profile.keys.is_empty());
+        assert_eq!(
+            profile.manifest.packages,
+            vec![WorkdirProfilePackage {
+                name: "demo".to_string(),
+                package_id: Some("0x5c8d".to_string()),
+            }]
+        );
+        let addresses: Vec<&str> = profile
+            .manifest
+            .addresses
+            .iter()
+            .map(|address| address.alias.as_str())
+            .collect();
+        assert_eq!(addresses, vec!["admin", "user"]);
+        assert_eq!(
+            profile.manifest.addresses[0].address,
+            sui_address(PUBLIC_KEY_1).unwrap()
+        );
+        let symlinks: Vec<(&str, &str)> = profile
+            .published_data
+            .iter()
+            .filter_map(|file| Some((file.path.as_str(), file.symlink.as_deref()?)))
+            .collect();
+        assert_eq!(
+            symlinks,
+            vec![
+                (
+                    "demo/UUID1/most-recent-timestamp",
+                    "demo/UUID1/1727712345678"
+                ),
+                ("demo/most-recent", "demo/UUID1/1727712345678"),
+            ]
+        );
+
+        // Survives the JSON round trip of the API.
+        let profile: WorkdirProfile =
+            serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+
+        // Nothing written on a dry run.
+        let target = fresh_workdir("target");
+        let dry_run = ProfileImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let resp = import_workdir_profile(&target, &profile, dry_run).unwrap();
+        assert!(!resp.applied);
+        assert_eq!(
+            resp.changes,
+            vec!["suibase.yaml created", "package demo added"]
+        );
+        assert!(resp.conflicts.is_empty());
+        assert!(!target.join(USER_YAML_FILENAME).exists());
+        assert!(!target.join(PUBLISHED_DATA_DIR).exists());
+
+        let resp =
+            import_workdir_profile(&target, &profile, ProfileImportOptions::default()).unwrap();
+        assert!(resp.applied);
+        assert_eq!(
+            std::fs::read_to_string(target.join(USER_YAML_FILENAME)).unwrap(),
+            USER_YAML
+        );
+        let config = |workdir: &Path| {
+            let mut config = WorkdirUserConfig::new();
+            config
+                .load_and_merge_from_file(workdir.join(USER_YAML_FILENAME).to_str().unwrap())
+                .unwrap();
+            config
+        };
+        assert_eq!(config(&target).links(), config(&source).links());
+        assert_eq!(published_tree(&target), published_tree(&source));
+        assert_eq!(
+            read_package_id(&target.join("published-data/demo")).as_deref(),
+            Some("0x5c8d")
+        );
+        // The most-recent symlink is absolute, within the target.
+        let most_recent = std::fs::read_link(target.join("published-data/demo/most-recent"));
+        assert!(most_recent.unwrap().starts_with(&target));
+
+        // Same profile again: nothing to do.
+        let resp =
+            import_workdir_profile(&target, &profile, ProfileImportOptions::default()).unwrap();
+        assert!(resp.applied);
+        assert!(resp.changes.is_empty());
+        assert!(resp.conflicts.is_empty());
+
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn test_import_profile_conflicts() {
+        let source = fixture_workdir("conflicts-source");
+        let (profile, _) = export_workdir_profile("localnet", &source, &[]).unwrap();
+
+        // Same link alias elsewhere, and another publication of the package.
+        let target = fixture_workdir("conflicts-target");
+        std::fs::write(
+            target.join(USER_YAML_FILENAME),
+            USER_YAML.replace("10.0.0.5", "10.0.0.6"),
+        )
+        .unwrap();
+        std::fs::write(
+            target.join("published-data/demo/most-recent/package-id.json"),
+            "[\"0x9999\"]",
+        )
+        .unwrap();
+
+        let resp =
+            import_workdir_profile(&target, &profile, ProfileImportOptions::default()).unwrap();
+        assert!(!resp.applied);
+        let conflicts: Vec<(&str, &str)> = resp
+            .conflicts
+            .iter()
+            .map(|c| (c.kind.as_str(), c.name.as_str()))
+            .collect();
+        assert_eq!(conflicts, vec![("link", "team-rpc"), ("package", "demo")]);
+        assert_eq!(resp.conflicts[0].detail, "not the same in the workdir: rpc");
+        assert_eq!(
+            resp.conflicts[1].detail,
+            "published as 0x9999 in the workdir, 0x5c8d in the profile"
+        );
+        assert!(std::fs::read_to_string(target.join(USER_YAML_FILENAME))
+            .unwrap()
+            .contains("10.0.0.6"));
+
+        let force = ProfileImportOptions {
+            force: true,
+            ..Default::default()
+        };
+        let resp = import_workdir_profile(&target, &profile, force).unwrap();
+        assert!(resp.applied);
+        assert_eq!(published_tree(&target), published_tree(&source));
+        assert_eq!(
+            std::fs::read_to_string(target.join(USER_YAML_FILENAME)).unwrap(),
+            USER_YAML
+        );
+
+        // Not a profile, or escaping published-data.
+        let mut invalid = profile.clone();
+        invalid.version = 2;
+        assert!(import_workdir_profile(&target, &invalid, force).is_err());
+        let mut invalid = profile.clone();
+        invalid.published_data[0].path = "../../.bashrc".to_string();
+        assert!(import_workdir_profile(&target, &invalid, force).is_err());
+
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn test_profile_keys() {
+        let source = fixture_workdir("keys-source");
+        assert!(export_workdir_profile("localnet", &source, &["unknown".to_string()]).is_err());
+
+        // Selected by alias or address.
+        let address_2 = sui_address(PUBLIC_KEY_2).unwrap();
+        let keys = vec![
+            "admin".to_string(),
+            address_2.to_uppercase().replace("0X", "0x"),
+        ];
+        let (profile, _) = export_workdir_profile("localnet", &source, &keys).unwrap();
+        let exported: Vec<(&str, &str)> = profile
+            .keys
+            .iter()
+            .map(|key| (key.alias.as_str(), key.private_key.as_str()))
+            .collect();
+        assert_eq!(
+            exported,
+            vec![("admin", "AAEprivate1"), ("user", "AAEprivate2")]
+        );
+
+        // Ignored unless import_keys.
+        let target = fresh_workdir("keys-target");
+        std::fs::create_dir_all(target.join("config")).unwrap();
+        std::fs::write(
+            target.join(KEYSTORE_PATH),
+            serde_json::json!(["AAEother"]).to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            target.join(ALIASES_PATH),
+            serde_json::json!([{ "alias": "user", "public_key_base64": "AAMDAwM=" }]).to_string(),
+        )
+        .unwrap();
+        let resp =
+            import_workdir_profile(&target, &profile, ProfileImportOptions::default()).unwrap();
+        assert!(resp.applied);
+        assert_eq!(
+            resp.warnings,
+            vec!["2 key(s) of the profile not imported (import_keys not set)"]
+        );
+        assert_eq!(read_json_array(&target.join(KEYSTORE_PATH)).len(), 1);
+
+        // The "user" alias is already used by another key.
+        let import_keys = ProfileImportOptions {
+            import_keys: true,
+            ..Default::default()
+        };
+        let resp = import_workdir_profile(&target, &profile, import_keys).unwrap();
+        assert!(resp.applied);
+        assert_eq!(resp.conflicts.len(), 1);
+        assert_eq!(resp.conflicts[0].kind, "key");
+        assert_eq!(resp.conflicts[0].name, "user");
+        assert_eq!(
+            read_json_array(&target.join(KEYSTORE_PATH)),
+            vec![JsonValue::from("AAEother"), JsonValue::from("AAEprivate1")]
+        );
+        let named_keys = read_named_keys(&target);
+        assert_eq!(named_keys[1].alias, "admin");
+        assert_eq!(named_keys[1].address, profile.keys[0].address);
+        assert!(target.join("config/sui.keystore.bak").exists());
+
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+}
@@ -6,15 +6,23 @@
 // the user filesystem (for relevant files only).
 //
 use home::home_dir;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use common::basic_types::*;
+use common::utils::suibase_home;
 
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 
-use super::Globals;
+use super::{
+    CronSchedule, Globals, HealthRule, IpCidr, LinkWarmUpRule, MaintenanceWindow, ProcessLogConfig,
+    ProxyAllowlist, ProxyShadowConfig, ProxyTimeouts, QuotaErrorRule, RateLimits, WebhookConfig,
+    WebhookEventType, CONFIG_HISTORY_FILENAME, DEFAULT_EVENTS_BACKFILL_WINDOW_SECS,
+    DEFAULT_PORT_FALLBACK_RANGE, DEFAULT_SHADOW_PCT, DEFAULT_SUI_EXPLORER_PORT,
+    DEFAULT_TIME_SKEW_THRESHOLD_SECS, LINK_USAGE_FILENAME, MAINTENANCE_MAX_DURATION_MINS,
+    PROXY_STATS_FILENAME, PROXY_TIMEOUTS_FIELDS,
+};
 
 // workdir_idx are hard coded for performance.
 pub const WORKDIR_IDX_MAINNET: WorkdirIdx = 0;
@@ -26,30 +34,211 @@ pub const WORKDIR_IDX_LOCALNET: WorkdirIdx = 3;
 // The order is important since the position match the WORKDIR_IDX_* constants.
 pub const WORKDIRS_KEYS: [&str; 4] = ["mainnet", "testnet", "devnet", "localnet"];
 
+// Sui client script of each workdir (same order as WORKDIRS_KEYS).
+pub const WORKDIRS_SUI_SCRIPTS: [&str; 4] = ["msui", "tsui", "dsui", "lsui"];
+
+// Default limit of concurrent upstream requests per proxy port, and how long a
+// request waits for a slot before being shed (0 means shed immediately).
+pub const DEFAULT_PROXY_MAX_CONCURRENCY: u32 = 512;
+pub const DEFAULT_PROXY_QUEUE_TIMEOUT_MS: u64 = 200;
+
+// What a link is used for (the "role" field of a link in suibase.yaml).
+//
+//   rpc          : Selected for user traffic (default).
+//   metrics      : Same as rpc, and its "metrics" URL (Prometheus) is also scraped.
+//   monitor-only : Health-checked and reported, but never selected for user traffic.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum LinkRole {
+    #[default]
+    Rpc,
+    Metrics,
+    MonitorOnly,
+}
+
+impl LinkRole {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rpc" => Some(Self::Rpc),
+            "metrics" => Some(Self::Metrics),
+            "monitor-only" => Some(Self::MonitorOnly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rpc => "rpc",
+            Self::Metrics => "metrics",
+            Self::MonitorOnly => "monitor-only",
+        }
+    }
+}
+
+// How the proxy spreads the user traffic among its healthy links (the
+// "proxy_distribution" of a workdir in suibase.yaml).
+//
+//   best     : Mostly the lowest latency links (default).
+//   weighted : All healthy links, with a probability inversely proportional to their
+//              latency. Links near their rate limits (max_per_secs/max_per_min/max_per_day)
+//              are avoided.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub enum ProxyDistribution {
+    #[default]
+    Best,
+    Weighted,
+}
+
+impl ProxyDistribution {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "best" => Some(Self::Best),
+            "weighted" => Some(Self::Weighted),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            Self::Weighted => "weighted",
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Link {
     // A link in a suibase.yaml file.
     pub alias: String,
+    pub role: LinkRole,
     pub selectable: bool,
     pub monitored: bool,
     pub rpc: Option<String>,
     pub metrics: Option<String>,
     pub ws: Option<String>,
     pub priority: u8,
+    pub max_per_secs: Option<u32>, // Rate limit of the provider (e.g. a paid plan quota).
+    pub max_per_min: Option<u32>,
+    pub max_per_day: Option<u32>, // Daily quota, the day being 00:00 to 24:00 UTC.
+    // Name in the rate_groups of the workdir, for limits shared with other links
+    // (e.g. an account-wide quota of the provider).
+    pub rate_group: Option<String>,
+    // Requests per UTC month of the plan (alerts only, the link is still selected).
+    pub monthly_budget: Option<u64>,
+    // JSON-RPC error codes by which the provider signals a rate limit (like an HTTP 429).
+    pub throttle_codes: Vec<i32>,
+    // Scheduled windows during which the link is not selected (see MaintenanceWindow).
+    pub maintenance: Vec<MaintenanceWindow>,
+    // Percentage of the requests sent to this link to evaluate it (see InputPort
+    // apply_canaries). Never the fallback of another link while any other is up.
+    pub canary_pct: Option<u8>,
+    // Overrides of the proxy_timeouts of the workdir for this link.
+    pub timeouts: ProxyTimeouts,
 }
 
 impl Link {
     pub fn new(alias: String, rpc: String) -> Self {
         Self {
             alias,
+            role: LinkRole::Rpc,
             selectable: true,
             monitored: true,
             rpc: Some(rpc),
             metrics: None,
             ws: None,
             priority: u8::MAX,
+            max_per_secs: None,
+            max_per_min: None,
+            max_per_day: None,
+            rate_group: None,
+            monthly_budget: None,
+            throttle_codes: Vec::new(),
+            maintenance: Vec::new(),
+            canary_pct: None,
+            timeouts: ProxyTimeouts::default(),
         }
     }
+
+    // The user visible fields, as compared by previewConfig and getConfigHistory.
+    pub fn fields(&self) -> [(&'static str, String); 15] {
+        let fmt = |value: &Option<String>| value.clone().unwrap_or_default();
+        let fmt_limit = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+        let fmt_budget = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let fmt_codes = |codes: &Vec<i32>| {
+            let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+            codes.join(",")
+        };
+        let fmt_windows = |windows: &Vec<MaintenanceWindow>| {
+            let windows: Vec<String> = windows.iter().map(|window| window.to_string()).collect();
+            windows.join(", ")
+        };
+        [
+            ("rpc", fmt(&self.rpc)),
+            ("ws", fmt(&self.ws)),
+            ("metrics", fmt(&self.metrics)),
+            ("priority", self.priority.to_string()),
+            ("role", self.role.as_str().to_string()),
+            ("enabled", self.monitored.to_string()),
+            ("max_per_secs", fmt_limit(self.max_per_secs)),
+            ("max_per_min", fmt_limit(self.max_per_min)),
+            ("max_per_day", fmt_limit(self.max_per_day)),
+            ("rate_group", fmt(&self.rate_group)),
+            ("monthly_budget", fmt_budget(self.monthly_budget)),
+            ("throttle_codes", fmt_codes(&self.throttle_codes)),
+            ("maintenance", fmt_windows(&self.maintenance)),
+            (
+                "canary_pct",
+                self.canary_pct.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+            ("timeouts", self.timeouts.to_string()),
+        ]
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ProxyTlsConfig {
+    // TLS termination for the proxy port (PEM files).
+    pub cert: String,
+    pub key: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ProxyCorsConfig {
+    // Cross-origin requests to the proxy port (e.g. from a browser dapp).
+    pub allowed_origins: Vec<String>, // "*" allows any origin.
+    pub allowed_headers: Vec<String>, // "*" allows any header.
+}
+
+// Bounds of the "auto" hedging delay (see ProxyHedgeConfig::delay).
+const HEDGE_AUTO_MIN_MS: f64 = 20.0;
+const HEDGE_AUTO_MAX_MS: f64 = 1000.0;
+// The tail latency of a link is approximated from its average latency.
+const HEDGE_AUTO_FACTOR: f64 = 2.0;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ProxyHedgeConfig {
+    // A read request not answered by the best link within the delay is also
+    // sent to the second best link. The first response is returned.
+    pub delay_ms: Option<u64>, // None is "auto" (from the latency of the best link).
+    pub methods: Vec<String>,  // Empty for any read method.
+}
+
+impl ProxyHedgeConfig {
+    // A method that may change the state (or unknown) is never hedged.
+    pub fn is_hedged_method(&self, method: &str) -> bool {
+        jsonrpc_method_kind(method) == JsonRpcMethodKind::Read
+            && (self.methods.is_empty() || self.methods.iter().any(|m| m == method))
+    }
+
+    // 'avg_latency_ms' of the best link (f64::MAX when not yet measured).
+    pub fn delay(&self, avg_latency_ms: f64) -> std::time::Duration {
+        let delay_ms = match self.delay_ms {
+            Some(delay_ms) => delay_ms as f64,
+            None => {
+                (avg_latency_ms * HEDGE_AUTO_FACTOR).clamp(HEDGE_AUTO_MIN_MS, HEDGE_AUTO_MAX_MS)
+            }
+        };
+        std::time::Duration::from_millis(delay_ms as u64)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -60,8 +249,39 @@ pub struct WorkdirUserConfig {
     user_request_start: bool, // true when user_request == "start"
     proxy_enabled: bool,
     proxy_port_number: u16,
+    proxy_tls: Option<ProxyTlsConfig>, // None means plain HTTP (the default).
+    proxy_cors: Option<ProxyCorsConfig>, // None means no CORS headers (the default).
+    proxy_hedge: Option<ProxyHedgeConfig>, // None means no hedged requests (the default).
+    shadow_link: Option<String>,       // None means no shadow mode (the default).
+    shadow_pct: u8,
+    shadow_ignore_fields: Vec<String>,
+    proxy_allowed_ips: Option<Vec<IpCidr>>, // None means any source IP (the default).
+    trust_forwarded: bool, // Check X-Forwarded-For of a trusted proxy with proxy_allowed_ips.
+    proxy_max_concurrency: u32,
+    proxy_queue_timeout_ms: u64,
+    proxy_timeouts: ProxyTimeouts, // Upstream timeouts by class of method.
+    proxy_distribution: ProxyDistribution,
+    proxy_serve_cached_system_values: bool,
+    proxy_stats_persist: bool, // Keep the cumulative link stats across daemon restarts.
+    time_skew_threshold_secs: u64, // 0 disables the clock skew warnings.
+    events_backfill_window_secs: u64, // 0 disables the backfill of the Sui events.
+    strict_ports: bool,        // true: never use another port than configured.
+    port_fallback_range: u16,
+    sui_explorer_port: u16, // Daemon-wide, only from the common suibase.yaml.
     links_overrides: bool,
     links: HashMap<String, Link>,
+    link_profiles: BTreeMap<String, HashMap<String, Link>>, // Each replaces 'links' when active.
+    rate_groups: BTreeMap<String, RateLimits>, // Limits shared by the links of a group.
+    active_link_profile: Option<String>,
+    log_format: Option<LogFormat>, // Daemon-wide, only from the common suibase.yaml.
+    webhooks: Vec<WebhookConfig>,  // Daemon-wide, only from the common suibase.yaml.
+    telemetry_enabled: bool,       // Daemon-wide, only from the common suibase.yaml.
+    telemetry_endpoint: Option<String>, // Daemon-wide, only from the common suibase.yaml.
+    quota_error_rule: QuotaErrorRule,
+    link_warmup: LinkWarmUpRule,
+    health_score: Option<HealthRule>, // None means only the built-in scoring (the default).
+    process_log: ProcessLogConfig,
+    warnings: Vec<String>, // Problems found while parsing (the value is ignored or adjusted).
 }
 
 impl WorkdirUserConfig {
@@ -71,8 +291,39 @@ impl WorkdirUserConfig {
             user_request_start: false,
             proxy_enabled: false,
             proxy_port_number: 0,
+            proxy_tls: None,
+            proxy_cors: None,
+            proxy_hedge: None,
+            shadow_link: None,
+            shadow_pct: DEFAULT_SHADOW_PCT,
+            shadow_ignore_fields: Vec::new(),
+            proxy_allowed_ips: None,
+            trust_forwarded: false,
+            proxy_max_concurrency: DEFAULT_PROXY_MAX_CONCURRENCY,
+            proxy_queue_timeout_ms: DEFAULT_PROXY_QUEUE_TIMEOUT_MS,
+            proxy_timeouts: ProxyTimeouts::default(),
+            proxy_distribution: ProxyDistribution::Best,
+            proxy_serve_cached_system_values: true,
+            proxy_stats_persist: false,
+            time_skew_threshold_secs: DEFAULT_TIME_SKEW_THRESHOLD_SECS,
+            events_backfill_window_secs: DEFAULT_EVENTS_BACKFILL_WINDOW_SECS,
+            strict_ports: false,
+            port_fallback_range: DEFAULT_PORT_FALLBACK_RANGE,
+            sui_explorer_port: DEFAULT_SUI_EXPLORER_PORT,
             links_overrides: false,
             links: HashMap::new(),
+            link_profiles: BTreeMap::new(),
+            rate_groups: BTreeMap::new(),
+            active_link_profile: None,
+            log_format: None,
+            webhooks: Vec::new(),
+            telemetry_enabled: false,
+            telemetry_endpoint: None,
+            quota_error_rule: QuotaErrorRule::new(),
+            link_warmup: LinkWarmUpRule::new(),
+            health_score: None,
+            process_log: ProcessLogConfig::new(),
+            warnings: Vec::new(),
         }
     }
 
@@ -92,12 +343,180 @@ impl WorkdirUserConfig {
         self.proxy_port_number
     }
 
+    pub fn proxy_tls(&self) -> Option<&ProxyTlsConfig> {
+        self.proxy_tls.as_ref()
+    }
+
+    pub fn proxy_cors(&self) -> Option<&ProxyCorsConfig> {
+        self.proxy_cors.as_ref()
+    }
+
+    pub fn proxy_hedge(&self) -> Option<&ProxyHedgeConfig> {
+        self.proxy_hedge.as_ref()
+    }
+
+    // None when shadow_link is not set, or is not one of the links.
+    pub fn proxy_shadow(&self) -> Option<ProxyShadowConfig> {
+        let link = self
+            .shadow_link
+            .as_ref()
+            .filter(|alias| self.links().contains_key(*alias))?;
+        Some(ProxyShadowConfig {
+            link: link.clone(),
+            pct: self.shadow_pct,
+            ignore_fields: self.shadow_ignore_fields.clone(),
+        })
+    }
+
+    pub fn proxy_allowlist(&self) -> Option<ProxyAllowlist> {
+        self.proxy_allowed_ips
+            .as_ref()
+            .map(|cidrs| ProxyAllowlist::new(cidrs.clone(), self.trust_forwarded))
+    }
+
+    pub fn health_score(&self) -> Option<&HealthRule> {
+        self.health_score.as_ref()
+    }
+
+    pub fn proxy_max_concurrency(&self) -> u32 {
+        self.proxy_max_concurrency
+    }
+
+    pub fn proxy_queue_timeout_ms(&self) -> u64 {
+        self.proxy_queue_timeout_ms
+    }
+
+    pub fn proxy_timeouts(&self) -> &ProxyTimeouts {
+        &self.proxy_timeouts
+    }
+
+    pub fn proxy_distribution(&self) -> ProxyDistribution {
+        self.proxy_distribution
+    }
+
+    pub fn is_proxy_serve_cached_system_values(&self) -> bool {
+        self.proxy_serve_cached_system_values
+    }
+
+    pub fn is_proxy_stats_persist(&self) -> bool {
+        self.proxy_stats_persist
+    }
+
+    pub fn time_skew_threshold_secs(&self) -> u64 {
+        self.time_skew_threshold_secs
+    }
+
+    pub fn events_backfill_window_secs(&self) -> u64 {
+        self.events_backfill_window_secs
+    }
+
+    pub fn is_strict_ports(&self) -> bool {
+        self.strict_ports
+    }
+
+    pub fn process_log(&self) -> ProcessLogConfig {
+        self.process_log
+    }
+
+    pub fn port_fallback_range(&self) -> u16 {
+        self.port_fallback_range
+    }
+
+    pub fn sui_explorer_port(&self) -> u16 {
+        self.sui_explorer_port
+    }
+
     pub fn links_overrides(&self) -> bool {
         self.links_overrides
     }
 
+    // The links of the active profile, otherwise the 'links' section.
+    //
+    // An undefined active profile falls back to the 'links' section (see
+    // check_link_profiles).
     pub fn links(&self) -> &HashMap<String, Link> {
-        &self.links
+        self.active_link_profile
+            .as_ref()
+            .and_then(|profile| self.link_profiles.get(profile))
+            .unwrap_or(&self.links)
+    }
+
+    pub fn link_profiles(&self) -> &BTreeMap<String, HashMap<String, Link>> {
+        &self.link_profiles
+    }
+
+    pub fn rate_groups(&self) -> &BTreeMap<String, RateLimits> {
+        &self.rate_groups
+    }
+
+    // None when the configured profile is not defined (not in use).
+    pub fn active_link_profile(&self) -> Option<&String> {
+        self.active_link_profile
+            .as_ref()
+            .filter(|profile| self.link_profiles.contains_key(*profile))
+    }
+
+    // To be called once all the files are merged (a profile can be defined
+    // in another file than the one activating it). Same for the shadow_link
+    // and the rate_group of the links.
+    pub fn check_link_profiles(&mut self) {
+        if let Some(profile) = &self.active_link_profile {
+            if !self.link_profiles.contains_key(profile) {
+                self.warnings.push(format!(
+                    "active_link_profile {} not defined in link_profiles (using links)",
+                    profile
+                ));
+            }
+        }
+        if let Some(alias) = &self.shadow_link {
+            if !self.links().contains_key(alias) {
+                let warning = format!("shadow_link {} not defined in the links (ignored)", alias);
+                self.warnings.push(warning);
+            }
+        }
+        let mut undefined_groups: Vec<String> = self
+            .links()
+            .values()
+            .filter_map(|link| link.rate_group.as_ref())
+            .filter(|group| !self.rate_groups.contains_key(*group))
+            .cloned()
+            .collect();
+        undefined_groups.sort();
+        undefined_groups.dedup();
+        for group in undefined_groups {
+            self.warnings.push(format!(
+                "rate_group {} not defined in rate_groups (no group limit)",
+                group
+            ));
+        }
+    }
+
+    pub fn log_format(&self) -> Option<LogFormat> {
+        self.log_format
+    }
+
+    pub fn webhooks(&self) -> &[WebhookConfig] {
+        &self.webhooks
+    }
+
+    pub fn is_telemetry_enabled(&self) -> bool {
+        self.telemetry_enabled
+    }
+
+    pub fn telemetry_endpoint(&self) -> Option<&str> {
+        self.telemetry_endpoint.as_deref()
+    }
+
+    pub fn quota_error_rule(&self) -> &QuotaErrorRule {
+        &self.quota_error_rule
+    }
+
+    pub fn link_warmup(&self) -> &LinkWarmUpRule {
+        &self.link_warmup
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
     pub fn load_state_file(&mut self, path: &str) -> Result<()> {
@@ -122,7 +541,17 @@ impl WorkdirUserConfig {
         self.load_and_merge_from_file_internal(path, true)
     }
 
+    // Merge a suibase.yaml content not (yet) in a file (e.g. previewConfig).
+    pub fn load_and_merge_from_str(&mut self, contents: &str, origin: &str) -> Result<()> {
+        self.load_and_merge_internal(contents, origin, false)
+    }
+
     fn load_and_merge_from_file_internal(&mut self, path: &str, common: bool) -> Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        self.load_and_merge_internal(&contents, path, common)
+    }
+
+    fn load_and_merge_internal(&mut self, contents: &str, path: &str, common: bool) -> Result<()> {
         // This merge the config of the file with the current
         // configuration.
         //
@@ -137,16 +566,109 @@ impl WorkdirUserConfig {
         //
         // proxy_enabled: false
         //
+        // proxy_tls:
+        //   cert: "~/certs/localhost.pem"
+        //   key: "~/certs/localhost-key.pem"
+        //
+        // proxy_cors:
+        //   enabled: true
+        //   allowed_origins: [ "http://localhost:3000" ] # "*" for any.
+        //   allowed_headers: [ "content-type" ]          # Optional. This is the default.
+        //
+        // proxy_hedge:
+        //   enabled: true
+        //   delay_ms: auto                # Or milliseconds. "auto" is from the link latency.
+        //   methods: [ "sui_getObject" ]  # Optional. Default is any read method.
+        //
+        // shadow_link: "provider-b"  # Optional. Read requests also sent to it (getShadowReport).
+        // shadow_pct: 10              # Share of the read requests copied, 1 to 100.
+        // shadow_ignore_fields: [ "timestampMs" ]  # Optional. Not compared, at any depth.
+        //
+        // proxy_allowed_ips: [ "10.0.0.0/8", "fd00::/8" ]  # Optional. Localhost always allowed.
+        // trust_forwarded: false  # When true, X-Forwarded-For of a trusted proxy is checked.
+        //
+        // proxy_max_concurrency: 512
+        // proxy_queue_timeout_ms: 200
+        //
+        // proxy_timeouts:      # Upstream timeouts by class of method. Each is optional.
+        //   read_ms: 2000      # e.g. sui_getObject
+        //   execute_ms: 30000  # Build, dry-run and execute of transactions.
+        //   default_ms: 10000  # Any other method.
+        //
+        // health_score: "latency_p90_ms + 20 * error_rate_pct < 800"  # Else DEGRADED (getLinks).
+        //
+        // proxy_distribution: "best"  # "weighted" spreads the traffic on all healthy links.
+        //
+        // proxy_serve_cached_system_values: true  # Reference gas price and protocol config.
+        //
+        // proxy_stats_persist: false  # When true, link stats are kept across daemon restarts.
+        //
+        // time_skew_threshold_secs: 30  # Warn when the local clock is off. 0 disables.
+        //
+        // events_backfill_window_secs: 3600  # Max age of the Sui events recovered. 0 disables.
+        //
+        // strict_ports: false      # When true, fail instead of using another free port.
+        // port_fallback_range: 10  # How many ports to try after one already in use.
+        //
+        // sui_explorer_port: 44380 # Only in the common suibase.yaml
+        //
+        // webhooks:              # Only in the common suibase.yaml
+        //   - url: "http://localhost:8080/suibase"
+        //     secret: "my-secret" # Optional. Signs the payloads.
+        //     events: [ "link_status_change", "workdir_status_change" ] # Optional. Default is all.
+        //
+        // telemetry_enabled: false # Only in the common suibase.yaml. Anonymized counters.
+        // telemetry_endpoint: "https://example.com/telemetry" # Optional. Daily report.
+        //
+        // quota_errors:
+        //   codes: [ -32000 ]
+        //   consecutive: 5
+        //   pct: 50
+        //   min_samples: 20
+        //
+        // link_warmup:           # Probing of a link added while running. checks: 0 disables.
+        //   checks: 3
+        //   min_success: 3
+        //   interval_ms: 500
+        //
+        // process_log_max_mb: 100  # Rotate the sui/faucet process logs above this. 0 disables.
+        // process_log_keep: 5      # Compressed archives kept (e.g. sui-process.log.1.gz).
+        //
         // links:
         //  - alias: "localnet"
         //    rpc: "http://localhost:9000"
         //    ws: "ws://localhost:9000"
         //    priority: 12
+        //    max_per_secs: 100    # Optional rate limits (see proxy_distribution).
+        //    max_per_min: 5000
+        //    max_per_day: 100000  # Quota reset at 00:00 UTC.
+        //    rate_group: "provider-a"  # Optional, also limited by this entry of rate_groups.
+        //    monthly_budget: 3000000  # Optional, requests per UTC month (see getUsageReport).
+        //    throttle_codes: [ -32029 ]  # Optional, handled like an HTTP 429 (see ServerStats).
+        //    maintenance:         # Optional, not selected in these windows (cron is UTC).
+        //      - cron: "0 2 * * SUN"
+        //        duration_mins: 60
+        //    canary_pct: 10       # Optional, 0 to 100. Share of the requests to evaluate the link.
+        //    timeouts: { read_ms: 5000 }  # Optional, overrides of proxy_timeouts.
         //  - alias: "localnet"
         //    enabled: false
         //    rpc: "http://localhost:9000"
-        let contents = std::fs::read_to_string(path)?;
-        let yaml: serde_yaml::Value = serde_yaml::from_str(&contents)?;
+        //
+        // rate_groups:          # Limits shared by all the links of a group (e.g. of a
+        //   provider-a:          # provider with an account-wide quota). Each is optional.
+        //     max_per_secs: 100
+        //     max_per_min: 3000
+        //
+        // active_link_profile: "paid"  # Optional. Its links are used instead of 'links'.
+        //
+        // link_profiles:               # Each profile is a full links list.
+        //   paid:
+        //     - alias: "provider-a"
+        //       rpc: "https://rpc.provider-a.io"
+        //   public:
+        //     - alias: "sui.io"
+        //       rpc: "https://fullnode.testnet.sui.io:443"
+        let yaml: serde_yaml::Value = serde_yaml::from_str(contents)?;
 
         // TODO: Lots of robustness could be added here...
 
@@ -167,9 +689,84 @@ impl WorkdirUserConfig {
             self.links_overrides = links_overrides;
         }
 
+        // Each quota_errors field is optional (keep current value when not specified).
+        let quota_errors = &yaml["quota_errors"];
+        if let Some(codes) = quota_errors["codes"].as_sequence() {
+            self.quota_error_rule.codes = codes
+                .iter()
+                .filter_map(|code| code.as_i64())
+                .filter_map(|code| i32::try_from(code).ok())
+                .collect();
+        }
+        if let Some(consecutive) = quota_errors["consecutive"].as_u64() {
+            self.quota_error_rule.consecutive = consecutive.min(u32::MAX as u64) as u32;
+        }
+        if let Some(pct) = quota_errors["pct"].as_u64() {
+            self.quota_error_rule.pct = pct.min(100) as u8;
+        }
+        if let Some(min_samples) = quota_errors["min_samples"].as_u64() {
+            self.quota_error_rule.min_samples = min_samples.min(u8::MAX as u64) as u8;
+        }
+
+        // Same for each link_warmup field.
+        let link_warmup = &yaml["link_warmup"];
+        if let Some(checks) = link_warmup["checks"].as_u64() {
+            self.link_warmup.checks = checks.min(u8::MAX as u64) as u8;
+        }
+        if let Some(min_success) = link_warmup["min_success"].as_u64() {
+            self.link_warmup.min_success = min_success.min(u8::MAX as u64) as u8;
+        }
+        if let Some(interval_ms) = link_warmup["interval_ms"].as_u64() {
+            self.link_warmup.interval_ms = interval_ms;
+        }
+        if self.link_warmup.min_success > self.link_warmup.checks {
+            self.link_warmup.min_success = self.link_warmup.checks;
+        }
+
+        // Ports already in use (e.g. by the daemon of another user on the same host).
+        if let Some(strict_ports) = yaml["strict_ports"].as_bool() {
+            self.strict_ports = strict_ports;
+        }
+        if let Some(range) = yaml["port_fallback_range"].as_u64() {
+            self.port_fallback_range = range.min(u16::MAX as u64) as u16;
+        }
+
+        // Logs of the workdir processes (see process_log.rs).
+        if let Some(max_mb) = yaml["process_log_max_mb"].as_u64() {
+            self.process_log.max_mb = max_mb;
+        }
+        if let Some(keep) = yaml["process_log_keep"].as_u64() {
+            self.process_log.keep = keep.clamp(1, 100) as u32;
+        }
+
         // Remaining variables do not make sense in common files, so ignore them.
         // TODO: Implement warning user for bad usage...
         if common {
+            // log_format can be "text" (default) or "json".
+            if let Some(log_format) = yaml["log_format"].as_str() {
+                match log_format.parse::<LogFormat>() {
+                    Ok(log_format) => self.log_format = Some(log_format),
+                    Err(e) => log::warn!("{} in {}", e, path),
+                }
+            }
+            if let Some(port) = yaml["sui_explorer_port"].as_u64() {
+                match u16::try_from(port) {
+                    Ok(port) if port != 0 => self.sui_explorer_port = port,
+                    _ => log::warn!("invalid sui_explorer_port {} in {}", port, path),
+                }
+            }
+            if let Some(webhooks) = yaml["webhooks"].as_sequence() {
+                self.webhooks = webhooks
+                    .iter()
+                    .filter_map(|webhook| Self::parse_webhook(webhook, path))
+                    .collect();
+            }
+            if let Some(enabled) = yaml["telemetry_enabled"].as_bool() {
+                self.telemetry_enabled = enabled;
+            }
+            if let Some(endpoint) = yaml["telemetry_endpoint"].as_str() {
+                self.telemetry_endpoint = Some(endpoint.to_string()).filter(|s| !s.is_empty());
+            }
             return Ok(());
         }
 
@@ -177,39 +774,659 @@ impl WorkdirUserConfig {
             self.proxy_port_number = proxy_port_number as u16;
         }
 
+        // Requests above proxy_max_concurrency wait up to proxy_queue_timeout_ms
+        // for a slot, then are rejected with an "overloaded" JSON-RPC error.
+        if let Some(max) = yaml["proxy_max_concurrency"].as_u64() {
+            self.proxy_max_concurrency = max.clamp(1, u32::MAX as u64) as u32;
+        }
+        if let Some(timeout_ms) = yaml["proxy_queue_timeout_ms"].as_u64() {
+            self.proxy_queue_timeout_ms = timeout_ms;
+        }
+
+        // Merged field by field. "proxy_timeouts: ~" goes back to the defaults.
+        let proxy_timeouts = &yaml["proxy_timeouts"];
+        if proxy_timeouts.is_mapping() {
+            let mut timeouts = self.proxy_timeouts;
+            self.parse_timeouts(proxy_timeouts, &mut timeouts, "proxy_timeouts", path);
+            self.proxy_timeouts = timeouts;
+        } else if proxy_timeouts.is_null() && yaml.get("proxy_timeouts").is_some() {
+            self.proxy_timeouts = ProxyTimeouts::default();
+        }
+
+        if let Some(value) = yaml["proxy_distribution"].as_str() {
+            match ProxyDistribution::parse(value) {
+                Some(distribution) => self.proxy_distribution = distribution,
+                None => self.warnings.push(format!(
+                    "{}: proxy_distribution {} not supported (using {})",
+                    path,
+                    value,
+                    self.proxy_distribution.as_str()
+                )),
+            }
+        }
+
+        if let Some(value) = yaml["proxy_serve_cached_system_values"].as_bool() {
+            self.proxy_serve_cached_system_values = value;
+        }
+
+        if let Some(value) = yaml["proxy_stats_persist"].as_bool() {
+            self.proxy_stats_persist = value;
+        }
+
+        // Clock of this host compared to the links (see time_skew.rs).
+        if let Some(value) = yaml["time_skew_threshold_secs"].as_u64() {
+            self.time_skew_threshold_secs = value;
+        }
+
+        // Recovery of the Sui events missed by the subscriptions (see events_sync.rs).
+        if let Some(value) = yaml["events_backfill_window_secs"].as_u64() {
+            self.events_backfill_window_secs = value;
+        }
+
+        // Both cert and key are needed. A partial config is kept as-is, so the
+        // error is reported when the proxy starts (instead of silently using HTTP).
+        let proxy_tls = &yaml["proxy_tls"];
+        if proxy_tls.is_mapping() {
+            self.proxy_tls = Some(ProxyTlsConfig {
+                cert: Self::expand_home(proxy_tls["cert"].as_str().unwrap_or_default()),
+                key: Self::expand_home(proxy_tls["key"].as_str().unwrap_or_default()),
+            });
+        } else if proxy_tls.is_null() && yaml.get("proxy_tls").is_some() {
+            // "proxy_tls: ~" in the user file disables a TLS config from the defaults.
+            self.proxy_tls = None;
+        }
+
+        // Closed by default. A later file can disable with "enabled: false" or "proxy_cors: ~".
+        let proxy_cors = &yaml["proxy_cors"];
+        if proxy_cors.is_mapping() {
+            self.proxy_cors = if proxy_cors["enabled"].as_bool().unwrap_or(false) {
+                let strings = |field: &str| -> Option<Vec<String>> {
+                    proxy_cors[field].as_sequence().map(|values| {
+                        values
+                            .iter()
+                            .filter_map(|value| value.as_str())
+                            .map(|value| value.trim().to_string())
+                            .collect()
+                    })
+                };
+                Some(ProxyCorsConfig {
+                    allowed_origins: strings("allowed_origins").unwrap_or_default(),
+                    allowed_headers: strings("allowed_headers")
+                        .unwrap_or_else(|| vec!["content-type".to_string()]),
+                })
+            } else {
+                None
+            };
+        } else if proxy_cors.is_null() && yaml.get("proxy_cors").is_some() {
+            self.proxy_cors = None;
+        }
+
+        // Same as proxy_cors, disabled by default.
+        let proxy_hedge = &yaml["proxy_hedge"];
+        if proxy_hedge.is_mapping() {
+            self.proxy_hedge = if proxy_hedge["enabled"].as_bool().unwrap_or(false) {
+                let delay_ms = match &proxy_hedge["delay_ms"] {
+                    serde_yaml::Value::Number(delay_ms) => delay_ms.as_u64(),
+                    serde_yaml::Value::String(delay_ms) if delay_ms.trim() == "auto" => None,
+                    serde_yaml::Value::Null => None,
+                    delay_ms => {
+                        self.warnings.push(format!(
+                            "{}: proxy_hedge delay_ms {:?} not supported (using auto)",
+                            path, delay_ms
+                        ));
+                        None
+                    }
+                };
+                let mut methods = Vec::new();
+                if let Some(values) = proxy_hedge["methods"].as_sequence() {
+                    for method in values.iter().filter_map(|value| value.as_str()) {
+                        let method = method.trim();
+                        if jsonrpc_method_kind(method) == JsonRpcMethodKind::Read {
+                            methods.push(method.to_string());
+                        } else {
+                            self.warnings.push(format!(
+                                "{}: proxy_hedge method {} ignored (not a read method)",
+                                path, method
+                            ));
+                        }
+                    }
+                }
+                Some(ProxyHedgeConfig { delay_ms, methods })
+            } else {
+                None
+            };
+        } else if proxy_hedge.is_null() && yaml.get("proxy_hedge").is_some() {
+            self.proxy_hedge = None;
+        }
+
+        // Comparison of the responses with another link (see shadow.rs). A null or
+        // empty shadow_link disables it.
+        if let Some(value) = yaml.get("shadow_link") {
+            match value {
+                serde_yaml::Value::String(alias) if !alias.trim().is_empty() => {
+                    self.shadow_link = Some(alias.trim().to_string());
+                }
+                serde_yaml::Value::String(_) | serde_yaml::Value::Null => self.shadow_link = None,
+                value => {
+                    let value = serde_yaml::to_string(value).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "{}: shadow_link {} ignored (not an alias)",
+                        path,
+                        value.trim()
+                    ));
+                }
+            }
+        }
+        if let Some(value) = yaml.get("shadow_pct") {
+            match value.as_u64() {
+                Some(pct) if (1..=100).contains(&pct) => self.shadow_pct = pct as u8,
+                _ => {
+                    let value = serde_yaml::to_string(value).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "{}: shadow_pct {} not an integer from 1 to 100 (using {})",
+                        path,
+                        value.trim(),
+                        self.shadow_pct
+                    ));
+                }
+            }
+        }
+        if let Some(values) = yaml["shadow_ignore_fields"].as_sequence() {
+            self.shadow_ignore_fields = values
+                .iter()
+                .filter_map(|value| value.as_str())
+                .map(|field| field.trim().to_string())
+                .filter(|field| !field.is_empty())
+                .collect();
+        }
+
+        // An invalid entry is ignored (with a warning). When none is valid, only
+        // localhost is allowed (never open to all because of a typo).
+        let proxy_allowed_ips = &yaml["proxy_allowed_ips"];
+        if let Some(values) = proxy_allowed_ips.as_sequence() {
+            let mut cidrs = Vec::new();
+            for value in values {
+                match value.as_str().map(IpCidr::parse) {
+                    Some(Ok(cidr)) => cidrs.push(cidr),
+                    Some(Err(e)) => self
+                        .warnings
+                        .push(format!("{}: proxy_allowed_ips entry ignored ({})", path, e)),
+                    None => self.warnings.push(format!(
+                        "{}: proxy_allowed_ips entry {:?} ignored (not a string)",
+                        path, value
+                    )),
+                }
+            }
+            self.proxy_allowed_ips = Some(cidrs);
+        } else if proxy_allowed_ips.is_null() && yaml.get("proxy_allowed_ips").is_some() {
+            self.proxy_allowed_ips = None;
+        }
+
+        if let Some(value) = yaml["trust_forwarded"].as_bool() {
+            self.trust_forwarded = value;
+        }
+
+        // An invalid expression is ignored (with a warning), so the built-in scoring
+        // (or the rule from a previous file) still applies.
+        let health_score = &yaml["health_score"];
+        if let Some(source) = health_score.as_str() {
+            match HealthRule::parse(source) {
+                Ok(rule) => self.health_score = Some(rule),
+                Err(e) => self.warnings.push(format!(
+                    "{}: health_score {:?} ignored ({})",
+                    path, source, e
+                )),
+            }
+        } else if health_score.is_null() && yaml.get("health_score").is_some() {
+            self.health_score = None;
+        } else if !health_score.is_null() {
+            self.warnings
+                .push(format!("{}: health_score ignored (not a string)", path));
+        }
+
         if let Some(links) = yaml["links"].as_sequence() {
             for link in links {
-                if let Some(alias) = link["alias"].as_str() {
-                    // TODO: Consider implementing link level member merging.
-
-                    // Default of "enabled" is true. Allow the user to disable a single link.
-                    //
-                    // May allow later user finer control with "selectable" and "monitored".
-                    let enabled = link["enabled"].as_bool().unwrap_or(true);
-                    let selectable = enabled;
-                    let monitored = enabled;
-
-                    let rpc = link["rpc"].as_str().map(|s| s.to_string()); // Optional
-                    let metrics = link["metrics"].as_str().map(|s| s.to_string()); // Optional
-                    let ws = link["ws"].as_str().map(|s| s.to_string()); // Optional
-                    let priority = link["priority"].as_u64().unwrap_or(u64::MAX) as u8;
-                    let link = Link {
-                        alias: alias.to_string(),
-                        selectable,
-                        monitored,
-                        rpc,
-                        metrics,
-                        ws,
-                        priority,
-                    };
+                if let Some(link) = self.parse_link(link, path) {
                     // Replace if already present.
-                    self.links.insert(alias.to_string(), link);
+                    self.links.insert(link.alias.clone(), link);
+                }
+            }
+        }
+
+        // A group defined again (e.g. in the user file) is replaced as a whole.
+        if let Some(rate_groups) = yaml["rate_groups"].as_mapping() {
+            for (group, limits) in rate_groups {
+                let group = match group.as_str() {
+                    Some(group) => group.to_string(),
+                    None => continue,
+                };
+                let what = format!("rate_group {}", group);
+                let limits = RateLimits {
+                    max_per_secs: self.parse_rate_limit(limits, "max_per_secs", &what, path),
+                    max_per_min: self.parse_rate_limit(limits, "max_per_min", &what, path),
+                    max_per_day: None,
+                };
+                if !limits.is_limited() {
+                    self.warnings
+                        .push(format!("{}: {} without limit", path, what));
+                }
+                self.rate_groups.insert(group, limits);
+            }
+        }
+
+        // A profile defined again (e.g. in the user file) is replaced as a whole.
+        if let Some(link_profiles) = yaml["link_profiles"].as_mapping() {
+            for (profile, links) in link_profiles {
+                let profile = match profile.as_str() {
+                    Some(profile) => profile.to_string(),
+                    None => continue,
+                };
+                let origin = format!("{} (link_profiles {})", path, profile);
+                let mut profile_links = HashMap::new();
+                for link in links.as_sequence().into_iter().flatten() {
+                    if let Some(link) = self.parse_link(link, &origin) {
+                        profile_links.insert(link.alias.clone(), link);
+                    }
                 }
+                self.link_profiles.insert(profile, profile_links);
             }
         }
 
+        // "active_link_profile: ~" goes back to the 'links' section.
+        let active_link_profile = &yaml["active_link_profile"];
+        if let Some(profile) = active_link_profile.as_str() {
+            self.active_link_profile = Some(profile.to_string()).filter(|p| !p.is_empty());
+        } else if active_link_profile.is_null() && yaml.get("active_link_profile").is_some() {
+            self.active_link_profile = None;
+        }
+
         Ok(())
     }
+
+    fn parse_link(&mut self, link: &serde_yaml::Value, path: &str) -> Option<Link> {
+        let alias = match link["alias"].as_str() {
+            Some(alias) => alias,
+            None => {
+                self.warnings
+                    .push(format!("{}: link without alias ignored", path));
+                return None;
+            }
+        };
+        self.check_link_fields(link, alias, path);
+
+        // TODO: Consider implementing link level member merging.
+
+        let role = match link["role"].as_str() {
+            Some(value) => LinkRole::parse(value).unwrap_or_else(|| {
+                self.warnings.push(format!(
+                    "{}: link {} role {} not supported (using rpc)",
+                    path, alias, value
+                ));
+                LinkRole::Rpc
+            }),
+            None => LinkRole::Rpc,
+        };
+
+        // Default of "enabled" is true. Allow the user to disable a single link.
+        //
+        // A "monitor-only" link is still monitored, but never selected.
+        let enabled = link["enabled"].as_bool().unwrap_or(true);
+        let selectable = enabled && role != LinkRole::MonitorOnly;
+        let monitored = enabled;
+
+        let rpc = link["rpc"].as_str().map(|s| s.to_string()); // Optional
+        let metrics = link["metrics"].as_str().map(|s| s.to_string()); // Optional
+        let ws = link["ws"].as_str().map(|s| s.to_string()); // Optional
+        let priority = match link["priority"].as_u64() {
+            Some(priority) if priority > u8::MAX as u64 => {
+                self.warnings.push(format!(
+                    "{}: link {} priority {} above {} (using {})",
+                    path,
+                    alias,
+                    priority,
+                    u8::MAX,
+                    u8::MAX
+                ));
+                u8::MAX
+            }
+            Some(priority) => priority as u8,
+            None => u8::MAX,
+        };
+        let what = format!("link {}", alias);
+        let max_per_secs = self.parse_rate_limit(link, "max_per_secs", &what, path);
+        let max_per_min = self.parse_rate_limit(link, "max_per_min", &what, path);
+        let max_per_day = self.parse_rate_limit(link, "max_per_day", &what, path);
+        let rate_group = link["rate_group"]
+            .as_str()
+            .map(|group| group.trim().to_string())
+            .filter(|group| !group.is_empty());
+        let monthly_budget = self.parse_link_monthly_budget(link, alias, path);
+        let throttle_codes = self.parse_link_throttle_codes(link, alias, path);
+        let maintenance = self.parse_link_maintenance(link, alias, path);
+        let canary_pct = self.parse_link_canary_pct(link, alias, path);
+        let mut timeouts = ProxyTimeouts::default();
+        if link["timeouts"].is_mapping() {
+            let what = format!("link {} timeouts", alias);
+            self.parse_timeouts(&link["timeouts"], &mut timeouts, &what, path);
+        }
+        if role == LinkRole::Metrics && metrics.is_none() {
+            self.warnings.push(format!(
+                "{}: link {} role metrics without metrics URL (nothing scraped)",
+                path, alias
+            ));
+        }
+        Some(Link {
+            alias: alias.to_string(),
+            role,
+            selectable,
+            monitored,
+            rpc,
+            metrics,
+            ws,
+            priority,
+            max_per_secs,
+            max_per_min,
+            max_per_day,
+            rate_group,
+            monthly_budget,
+            throttle_codes,
+            maintenance,
+            canary_pct,
+            timeouts,
+        })
+    }
+
+    // 'what' is for the warnings (e.g. "proxy_timeouts"). An invalid field keeps the
+    // value in 'timeouts'.
+    fn parse_timeouts(
+        &mut self,
+        value: &serde_yaml::Value,
+        timeouts: &mut ProxyTimeouts,
+        what: &str,
+        path: &str,
+    ) {
+        let fields = match value.as_mapping() {
+            Some(fields) => fields,
+            None => return,
+        };
+        for (field, value) in fields {
+            let field = field.as_str().unwrap_or_default();
+            if !PROXY_TIMEOUTS_FIELDS.contains(&field) {
+                self.warnings.push(format!(
+                    "{}: {} field {} not supported (ignored)",
+                    path, what, field
+                ));
+                continue;
+            }
+            match value.as_u64() {
+                Some(value_ms) if value_ms > 0 => timeouts.set(field, Some(value_ms)),
+                _ if value.is_null() => timeouts.set(field, None),
+                _ => {
+                    let value = serde_yaml::to_string(value).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "{}: {} {} {} not a positive integer (ignored)",
+                        path,
+                        what,
+                        field,
+                        value.trim()
+                    ));
+                }
+            }
+        }
+    }
+
+    // None (not a canary) when not specified or invalid.
+    fn parse_link_canary_pct(
+        &mut self,
+        link: &serde_yaml::Value,
+        alias: &str,
+        path: &str,
+    ) -> Option<u8> {
+        let value = link.get("canary_pct")?;
+        match value.as_u64() {
+            Some(pct) if pct <= 100 => Some(pct as u8),
+            _ => {
+                let value = serde_yaml::to_string(value).unwrap_or_default();
+                self.warnings.push(format!(
+                    "{}: link {} canary_pct {} not an integer from 0 to 100 (not a canary)",
+                    path,
+                    alias,
+                    value.trim()
+                ));
+                None
+            }
+        }
+    }
+
+    // None (no budget) when not specified. Zero is not a valid budget.
+    fn parse_link_monthly_budget(
+        &mut self,
+        link: &serde_yaml::Value,
+        alias: &str,
+        path: &str,
+    ) -> Option<u64> {
+        let value = link.get("monthly_budget")?;
+        match value.as_u64() {
+            Some(budget) if budget > 0 => Some(budget),
+            _ => {
+                let value = serde_yaml::to_string(value).unwrap_or_default();
+                self.warnings.push(format!(
+                    "{}: link {} monthly_budget {} not a positive integer (no budget)",
+                    path,
+                    alias,
+                    value.trim()
+                ));
+                None
+            }
+        }
+    }
+
+    // None (no limit) when not specified. Zero is not a valid limit.
+    //
+    // 'what' is for the warnings (e.g. "link localnet").
+    fn parse_rate_limit(
+        &mut self,
+        yaml: &serde_yaml::Value,
+        field: &str,
+        what: &str,
+        path: &str,
+    ) -> Option<u32> {
+        let value = yaml.get(field)?;
+        match value.as_u64() {
+            Some(limit) if limit > 0 => Some(limit.min(u32::MAX as u64) as u32),
+            _ => {
+                let value = serde_yaml::to_string(value).unwrap_or_default();
+                self.warnings.push(format!(
+                    "{}: {} {} {} not a positive integer (no limit)",
+                    path,
+                    what,
+                    field,
+                    value.trim()
+                ));
+                None
+            }
+        }
+    }
+
+    // Codes that are not an i32 are ignored.
+    fn parse_link_throttle_codes(
+        &mut self,
+        link: &serde_yaml::Value,
+        alias: &str,
+        path: &str,
+    ) -> Vec<i32> {
+        let codes = match link.get("throttle_codes") {
+            Some(codes) => codes,
+            None => return Vec::new(),
+        };
+        let codes = match codes.as_sequence() {
+            Some(codes) => codes,
+            None => {
+                self.warnings.push(format!(
+                    "{}: link {} throttle_codes not a list (ignored)",
+                    path, alias
+                ));
+                return Vec::new();
+            }
+        };
+        let mut throttle_codes = Vec::new();
+        for code in codes {
+            match code.as_i64().and_then(|code| i32::try_from(code).ok()) {
+                Some(code) => throttle_codes.push(code),
+                None => {
+                    let code = serde_yaml::to_string(code).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "{}: link {} throttle code {} not a JSON-RPC error code (ignored)",
+                        path,
+                        alias,
+                        code.trim()
+                    ));
+                }
+            }
+        }
+        throttle_codes
+    }
+
+    // A window with an invalid cron or duration is ignored.
+    fn parse_link_maintenance(
+        &mut self,
+        link: &serde_yaml::Value,
+        alias: &str,
+        path: &str,
+    ) -> Vec<MaintenanceWindow> {
+        let windows = match link.get("maintenance") {
+            Some(windows) => windows,
+            None => return Vec::new(),
+        };
+        let windows = match windows.as_sequence() {
+            Some(windows) => windows,
+            None => {
+                self.warnings.push(format!(
+                    "{}: link {} maintenance not a list (ignored)",
+                    path, alias
+                ));
+                return Vec::new();
+            }
+        };
+        let mut maintenance = Vec::new();
+        for window in windows {
+            let cron = match window["cron"].as_str() {
+                Some(cron) => cron,
+                None => {
+                    self.warnings.push(format!(
+                        "{}: link {} maintenance window without cron (ignored)",
+                        path, alias
+                    ));
+                    continue;
+                }
+            };
+            let cron = match CronSchedule::parse(cron) {
+                Ok(cron) => cron,
+                Err(e) => {
+                    self.warnings.push(format!(
+                        "{}: link {} maintenance cron \"{}\" {} (ignored)",
+                        path, alias, cron, e
+                    ));
+                    continue;
+                }
+            };
+            match window["duration_mins"].as_u64() {
+                Some(duration_mins)
+                    if duration_mins > 0
+                        && duration_mins <= MAINTENANCE_MAX_DURATION_MINS as u64 =>
+                {
+                    maintenance.push(MaintenanceWindow {
+                        cron,
+                        duration_mins: duration_mins as u32,
+                    });
+                }
+                _ => {
+                    let value = serde_yaml::to_string(&window["duration_mins"]).unwrap_or_default();
+                    self.warnings.push(format!(
+                        "{}: link {} maintenance duration_mins {} not 1 to {} (ignored)",
+                        path,
+                        alias,
+                        value.trim(),
+                        MAINTENANCE_MAX_DURATION_MINS
+                    ));
+                }
+            }
+        }
+        maintenance
+    }
+
+    // Unknown fields are ignored by the parser. Report them, since this is most
+    // often a typo or a setting not supported by this version.
+    fn check_link_fields(&mut self, link: &serde_yaml::Value, alias: &str, path: &str) {
+        const LINK_FIELDS: [&str; 16] = [
+            "alias",
+            "enabled",
+            "role",
+            "rpc",
+            "metrics",
+            "ws",
+            "priority",
+            "max_per_secs",
+            "max_per_min",
+            "max_per_day",
+            "rate_group",
+            "monthly_budget",
+            "throttle_codes",
+            "maintenance",
+            "canary_pct",
+            "timeouts",
+        ];
+        if let Some(fields) = link.as_mapping() {
+            for field in fields.keys().filter_map(|field| field.as_str()) {
+                if !LINK_FIELDS.contains(&field) {
+                    self.warnings.push(format!(
+                        "{}: link {} field {} not supported (ignored)",
+                        path, alias, field
+                    ));
+                }
+            }
+        }
+        if link["rpc"].as_str().is_none() {
+            self.warnings
+                .push(format!("{}: link {} without rpc ignored", path, alias));
+        }
+    }
+
+    fn parse_webhook(webhook: &serde_yaml::Value, path: &str) -> Option<WebhookConfig> {
+        let url = match webhook["url"].as_str() {
+            Some(url) if !url.is_empty() => url.to_string(),
+            _ => {
+                log::warn!("webhook without url ignored in {}", path);
+                return None;
+            }
+        };
+        let secret = webhook["secret"].as_str().map(|s| s.to_string()); // Optional
+        let events = match webhook["events"].as_sequence() {
+            Some(events) => events
+                .iter()
+                .filter_map(|event| event.as_str())
+                .filter_map(|event| match event.parse::<WebhookEventType>() {
+                    Ok(event_type) => Some(event_type),
+                    Err(e) => {
+                        log::warn!("{} in {}", e, path);
+                        None
+                    }
+                })
+                .collect(),
+            None => WebhookEventType::ALL.to_vec(),
+        };
+        Some(WebhookConfig {
+            url,
+            secret,
+            events,
+        })
+    }
+
+    fn expand_home(path: &str) -> String {
+        common::utils::expand_home(path, home_dir().as_deref())
+            .to_string_lossy()
+            .to_string()
+    }
 }
 
 impl Default for WorkdirUserConfig {
@@ -218,6 +1435,45 @@ impl Default for WorkdirUserConfig {
     }
 }
 
+// Set a top-level 'key: value' in a suibase.yaml (e.g. active_link_profile).
+//
+// The line of the key is replaced (or appended), so the comments and the rest
+// of the file are preserved. The value must already be valid YAML.
+//
+// Atomic write (temp file + rename), so the WorkdirsWatcher never reloads a
+// partial file.
+pub fn write_suibase_yaml_key(path: &Path, key: &str, value: &str) -> Result<()> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let prefix = format!("{}:", key);
+    let new_line = format!("{}: {}", key, value);
+    let mut found = false;
+    let mut lines: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            if !found && line.starts_with(&prefix) {
+                found = true;
+                new_line.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        lines.push(new_line);
+    }
+    let mut new_contents = lines.join("\n");
+    new_contents.push('\n');
+
+    let tmp_path = path.with_extension("yaml.tmp");
+    std::fs::write(&tmp_path, new_contents)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Workdir {
     idx: Option<ManagedVecU8>,
@@ -275,6 +1531,18 @@ impl Workdir {
     pub fn suibase_yaml_default(&self) -> &Path {
         &self.suibase_yaml_default
     }
+
+    pub fn config_history_file(&self) -> PathBuf {
+        self.path.join("logs").join(CONFIG_HISTORY_FILENAME)
+    }
+
+    pub fn proxy_stats_file(&self) -> PathBuf {
+        self.state_path.join(PROXY_STATS_FILENAME)
+    }
+
+    pub fn link_usage_file(&self) -> PathBuf {
+        self.state_path.join(LINK_USAGE_FILENAME)
+    }
 }
 
 #[derive(Debug)]
@@ -287,16 +1555,20 @@ pub struct GlobalsWorkdirsST {
 
 impl GlobalsWorkdirsST {
     pub fn new() -> Self {
-        let home_dir = if let Some(home_dir) = home_dir() {
-            home_dir
+        // ~/suibase, unless overridden with SUIBASE_HOME (see common::utils::suibase_home).
+        let suibase_home = if let Some(suibase_home) = suibase_home() {
+            suibase_home
         } else {
             // The program will likely fail to further initialize, so pointing to /tmp
             // in meantime is a reasonable default/fallback safe thing to do...
-            PathBuf::from("/tmp")
+            PathBuf::from("/tmp/suibase")
         };
 
-        let suibase_home = home_dir.join("suibase");
+        Self::with_suibase_home(&suibase_home)
+    }
 
+    // Same as new(), for a given location (e.g. tests).
+    pub fn with_suibase_home(suibase_home: &Path) -> Self {
         // Generate all the suibase paths for state and config files of each WORKDIRS_KEYS.
         let mut workdirs = ManagedVec::new();
 
@@ -0,0 +1,104 @@
+// Restarts of the workers started for a workdir (e.g. the EventsWriter of localnet).
+//
+// AUTO_THREAD_STATS has the same by thread name only, which is shared by the instances
+// of all workdirs. Recorded by the owner of the AutoThread with its OnRestart callback
+// (see getDaemonStats).
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use common::basic_types::{OnRestart, RestartAction, RestartEvent, WorkdirIdx};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerRestart {
+    pub name: String,
+    pub workdir_idx: WorkdirIdx,
+    pub restart_count: u64,
+    pub last_error: String,
+    pub last_restart: DateTime<Utc>,
+    pub failed: bool, // Not restarted anymore (see RestartPolicy::give_up).
+}
+
+#[derive(Debug, Default)]
+pub struct WorkerRestarts {
+    restarts: Mutex<Vec<WorkerRestart>>,
+}
+
+impl WorkerRestarts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, workdir_idx: WorkdirIdx, event: &RestartEvent) {
+        let mut restarts = self.restarts.lock().unwrap();
+        let failed = event.action == RestartAction::GiveUp;
+        match restarts
+            .iter_mut()
+            .find(|restart| restart.name == event.name && restart.workdir_idx == workdir_idx)
+        {
+            Some(restart) => {
+                restart.restart_count += 1;
+                restart.last_error = event.error.clone();
+                restart.last_restart = Utc::now();
+                restart.failed = failed;
+            }
+            None => restarts.push(WorkerRestart {
+                name: event.name.clone(),
+                workdir_idx,
+                restart_count: 1,
+                last_error: event.error.clone(),
+                last_restart: Utc::now(),
+                failed,
+            }),
+        }
+    }
+
+    // Callback for AutoThread::with_on_restart.
+    pub fn on_restart(self: &Arc<Self>, workdir_idx: WorkdirIdx) -> OnRestart {
+        let restarts = self.clone();
+        Arc::new(move |event: &RestartEvent| restarts.record(workdir_idx, event))
+    }
+
+    // Sorted by name, then workdir.
+    pub fn snapshot(&self) -> Vec<WorkerRestart> {
+        let mut restarts = self.restarts.lock().unwrap().clone();
+        restarts.sort_by(|a, b| (&a.name, a.workdir_idx).cmp(&(&b.name, b.workdir_idx)));
+        restarts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_worker_restarts() {
+        let restarts = Arc::new(WorkerRestarts::new());
+        let event = |error: &str, action: RestartAction| RestartEvent {
+            name: "EventsWriter".to_string(),
+            restart_count: 0,
+            error: error.to_string(),
+            action,
+        };
+        let on_restart = restarts.on_restart(2);
+        on_restart(&event(
+            "panic: a",
+            RestartAction::Restart(Duration::from_secs(2)),
+        ));
+        restarts.record(
+            0,
+            &event("panic: b", RestartAction::Restart(Duration::from_secs(2))),
+        );
+        on_restart(&event("panic: c", RestartAction::GiveUp));
+
+        let snapshot = restarts.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].workdir_idx, 0);
+        assert_eq!(snapshot[0].restart_count, 1);
+        assert!(!snapshot[0].failed);
+        assert_eq!(snapshot[1].workdir_idx, 2);
+        assert_eq!(snapshot[1].restart_count, 2);
+        assert_eq!(snapshot[1].last_error, "panic: c");
+        assert!(snapshot[1].failed);
+    }
+}
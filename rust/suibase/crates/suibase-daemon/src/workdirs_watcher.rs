@@ -1,4 +1,11 @@
-use common::basic_types::{AdminControllerMsg, AdminControllerTx, AutoSizeVec, MPSC_Q_SIZE};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use common::basic_types::{
+    AdminControllerMsg, AdminControllerTx, AutoSizeVec, GenericChannelMsg, GenericRx, WorkdirIdx,
+    EVENT_UPDATE, MPSC_Q_SIZE,
+};
 
 use anyhow::Result;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
@@ -11,7 +18,9 @@ use notify::{PollWatcher, Watcher};
 pub struct WorkdirsWatcher {
     workdirs: GlobalsWorkdirsMT,
     admctrl_tx: AdminControllerTx,
+    watcher_rx: GenericRx, // From the AdminController (files to watch outside the workdirs).
     tracking: AutoSizeVec<WorkdirTracking>,
+    files: WatchedFiles,
 }
 
 #[derive(Default)]
@@ -19,14 +28,186 @@ struct WorkdirTracking {
     // Data private to the WorkdirsWatcher. One tracking per WorkdirIdx.
     is_workdir_watched: bool,
     is_state_watched: bool,
+    tls_files: Vec<PathBuf>, // proxy_tls cert and key files.
+}
+
+// Delay without any new event before a changed file is notified.
+//
+// An atomic save (e.g. vim writes a new file, then renames it over the old one) is a
+// storm of create/modify/rename events, in two directories for a symlinked file. It
+// must cause a single reload.
+const COALESCE_DELAY: Duration = Duration::from_millis(250);
+
+// What to send to the AdminController for a changed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileNotif {
+    Config,
+    Tls(WorkdirIdx),
+}
+
+// Files are watched through their parent directory, so the watch survives the
+// file being replaced (the inode of the file would be lost).
+//
+// A symlinked file (e.g. into a dotfiles repo) is also watched at its target.
+// The target is resolved again on every change of the link itself.
+#[derive(Default)]
+struct WatchedFiles {
+    dirs: HashMap<PathBuf, usize>, // Watched directory -> count of users.
+    files: HashMap<PathBuf, (PathBuf, FileNotif)>, // File or symlink target -> path notified.
+    links: HashMap<PathBuf, PathBuf>, // Symlink -> its target currently watched.
+
+    // Changes not yet notified (see COALESCE_DELAY).
+    pending: BTreeMap<PathBuf, FileNotif>,
+    pending_deadline: Option<Instant>,
+}
+
+impl WatchedFiles {
+    fn watch_dir(&mut self, poll_watcher: &mut PollWatcher, dir: &Path) {
+        let count = self.dirs.entry(dir.to_path_buf()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            log::info!("watching {}", dir.display());
+            if let Err(e) = poll_watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("can't watch {} ({})", dir.display(), e);
+            }
+        }
+    }
+
+    fn unwatch_dir(&mut self, poll_watcher: &mut PollWatcher, dir: &Path) {
+        if let Some(count) = self.dirs.get_mut(dir) {
+            *count -= 1;
+            if *count == 0 {
+                self.dirs.remove(dir);
+                log::info!("unwatching {}", dir.display());
+                let _ = poll_watcher.unwatch(dir);
+            }
+        }
+    }
+
+    // A directory re-created after being removed (or renamed) is not watched
+    // anymore by the PollWatcher, even when still needed.
+    fn rewatch_dir(&mut self, poll_watcher: &mut PollWatcher, dir: &Path) {
+        if self.dirs.contains_key(dir) {
+            log::info!("watching again {}", dir.display());
+            let _ = poll_watcher.unwatch(dir);
+            if let Err(e) = poll_watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("can't watch {} ({})", dir.display(), e);
+            }
+        }
+    }
+
+    fn add_file(&mut self, poll_watcher: &mut PollWatcher, path: &Path, notif: FileNotif) {
+        if self.files.contains_key(path) {
+            return;
+        }
+        self.files
+            .insert(path.to_path_buf(), (path.to_path_buf(), notif));
+        if let Some(dir) = path.parent() {
+            self.watch_dir(poll_watcher, dir);
+        }
+        self.resolve_link(poll_watcher, path);
+    }
+
+    fn remove_file(&mut self, poll_watcher: &mut PollWatcher, path: &Path) {
+        if self.files.remove(path).is_none() {
+            return;
+        }
+        if let Some(dir) = path.parent() {
+            self.unwatch_dir(poll_watcher, dir);
+        }
+        if let Some(target) = self.links.remove(path) {
+            self.remove_target(poll_watcher, &target);
+        }
+        self.pending.remove(path);
+    }
+
+    fn remove_target(&mut self, poll_watcher: &mut PollWatcher, target: &Path) {
+        self.files.remove(target);
+        if let Some(dir) = target.parent() {
+            self.unwatch_dir(poll_watcher, dir);
+        }
+    }
+
+    // Watch the target of 'path' when it is a symlink (a dangling one has no target).
+    //
+    // Returns true if the target changed.
+    fn resolve_link(&mut self, poll_watcher: &mut PollWatcher, path: &Path) -> bool {
+        let notif = match self.files.get(path) {
+            Some((_, notif)) => *notif,
+            None => return false,
+        };
+        let is_link = std::fs::symlink_metadata(path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        let target = if is_link {
+            std::fs::canonicalize(path).ok()
+        } else {
+            None
+        };
+        if self.links.get(path) == target.as_ref() {
+            return false;
+        }
+
+        if let Some(old_target) = self.links.remove(path) {
+            self.remove_target(poll_watcher, &old_target);
+        }
+        if let Some(target) = target {
+            log::info!("{} is a link to {}", path.display(), target.display());
+            if let Some(dir) = target.parent() {
+                self.watch_dir(poll_watcher, dir);
+            }
+            self.files
+                .insert(target.clone(), (path.to_path_buf(), notif));
+            self.links.insert(path.to_path_buf(), target);
+        }
+        true
+    }
+
+    // Process an event for 'path' (any file of a watched directory).
+    //
+    // Returns true if 'path' is a watched file (its change is then pending).
+    fn on_event(&mut self, poll_watcher: &mut PollWatcher, path: &Path, now: Instant) -> bool {
+        let (notified, notif) = match self.files.get(path) {
+            Some(file) => file.clone(),
+            None => return false,
+        };
+        if notified == path {
+            // The link itself may have been replaced (or the file by a link).
+            self.resolve_link(poll_watcher, path);
+        }
+        self.pending.insert(notified, notif);
+        self.pending_deadline = Some(now + COALESCE_DELAY);
+        true
+    }
+
+    fn pending_deadline(&self) -> Option<Instant> {
+        self.pending_deadline
+    }
+
+    // The files changed, once no event for COALESCE_DELAY.
+    fn take_pending(&mut self, now: Instant) -> Vec<(PathBuf, FileNotif)> {
+        match self.pending_deadline {
+            Some(deadline) if now >= deadline => {
+                self.pending_deadline = None;
+                std::mem::take(&mut self.pending).into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl WorkdirsWatcher {
-    pub fn new(workdirs: GlobalsWorkdirsMT, admctrl_tx: AdminControllerTx) -> Self {
+    pub fn new(
+        workdirs: GlobalsWorkdirsMT,
+        admctrl_tx: AdminControllerTx,
+        watcher_rx: GenericRx,
+    ) -> Self {
         Self {
             workdirs,
             admctrl_tx,
+            watcher_rx,
             tracking: AutoSizeVec::new(),
+            files: WatchedFiles::default(),
         }
     }
 
@@ -40,9 +221,56 @@ impl WorkdirsWatcher {
         });
     }
 
+    async fn send_notif_tls_file_change(&self, workdir_idx: WorkdirIdx, path: String) {
+        log::info!("Sending notif {}", path);
+        let mut msg = AdminControllerMsg::new();
+        msg.event_id = common::basic_types::EVENT_NOTIF_TLS_FILE_CHANGE;
+        msg.workdir_idx = Some(workdir_idx);
+        msg.data_string = Some(path);
+        let _ = self.admctrl_tx.send(msg).await.map_err(|e| {
+            log::debug!("failed {}", e);
+        });
+    }
+
+    // The AdminController sends an EVENT_UPDATE with the proxy_tls cert and key
+    // paths of a workdir (empty params when TLS is not configured).
+    fn update_tls_files_watch(
+        tracking: &mut AutoSizeVec<WorkdirTracking>,
+        files: &mut WatchedFiles,
+        poll_watcher: &mut PollWatcher,
+        msg: GenericChannelMsg,
+    ) {
+        if msg.event_id != EVENT_UPDATE {
+            log::error!("Unexpected event_id {:?}", msg.event_id);
+            return;
+        }
+        let workdir_idx = match msg.workdir_idx {
+            Some(workdir_idx) => workdir_idx,
+            None => {
+                log::error!("EVENT_UPDATE missing workdir_idx");
+                return;
+            }
+        };
+        let tracking = tracking.get_mut(workdir_idx);
+        let new_files: Vec<PathBuf> = msg.params.iter().map(PathBuf::from).collect();
+
+        for path in &tracking.tls_files {
+            if !new_files.contains(path) {
+                files.remove_file(poll_watcher, path);
+            }
+        }
+        for path in &new_files {
+            if !tracking.tls_files.contains(path) {
+                files.add_file(poll_watcher, path, FileNotif::Tls(workdir_idx));
+            }
+        }
+        tracking.tls_files = new_files;
+    }
+
     // Return true if something newly watched/unwatched.
     fn update_workdir_watch(
         tracking: &mut AutoSizeVec<WorkdirTracking>,
+        files: &mut WatchedFiles,
         poll_watcher: &mut PollWatcher,
         workdir: &Workdir,
         target_path: &str,
@@ -69,8 +297,8 @@ impl WorkdirsWatcher {
         if !path.exists() {
             // If the path does not exist, then remove the watch.
             if tracking.is_workdir_watched {
-                log::info!("unwatching {}", workdir.path().display());
-                let _ = poll_watcher.unwatch(path);
+                files.remove_file(poll_watcher, workdir.suibase_yaml_user());
+                files.unwatch_dir(poll_watcher, path);
                 tracking.is_workdir_watched = false;
                 at_least_one_modif = true;
             }
@@ -78,8 +306,8 @@ impl WorkdirsWatcher {
             // The path exists, so add the watch (if not already done).
             // TODO Enhance this with FD tracking.
             if !tracking.is_workdir_watched {
-                log::info!("watching {}", workdir.path().display());
-                let _ = poll_watcher.watch(path, RecursiveMode::NonRecursive);
+                files.watch_dir(poll_watcher, path);
+                files.add_file(poll_watcher, workdir.suibase_yaml_user(), FileNotif::Config);
                 tracking.is_workdir_watched = true;
                 at_least_one_modif = true;
             }
@@ -88,8 +316,8 @@ impl WorkdirsWatcher {
         if !state_path.exists() {
             // If the path does not exist, then remove the watch.
             if tracking.is_state_watched {
-                log::info!("unwatching {}", workdir.state_path().display());
-                let _ = poll_watcher.unwatch(state_path);
+                files.remove_file(poll_watcher, workdir.suibase_state_file());
+                files.unwatch_dir(poll_watcher, state_path);
                 tracking.is_state_watched = false;
                 at_least_one_modif = true;
             }
@@ -97,8 +325,12 @@ impl WorkdirsWatcher {
             // The path exists, so add the watch (if not already done).
             // TODO Enhance this with FD tracking?
             if !tracking.is_state_watched {
-                log::info!("watching {}", workdir.state_path().display());
-                let _ = poll_watcher.watch(state_path, RecursiveMode::NonRecursive);
+                files.watch_dir(poll_watcher, state_path);
+                files.add_file(
+                    poll_watcher,
+                    workdir.suibase_state_file(),
+                    FileNotif::Config,
+                );
                 tracking.is_state_watched = true;
                 at_least_one_modif = true;
             }
@@ -109,6 +341,7 @@ impl WorkdirsWatcher {
 
     fn remove_workdir_watch(
         tracking: &mut AutoSizeVec<WorkdirTracking>,
+        files: &mut WatchedFiles,
         poll_watcher: &mut PollWatcher,
         workdir: &Workdir,
         target_path: &str,
@@ -135,8 +368,8 @@ impl WorkdirsWatcher {
         if !path.exists() {
             // If the path does not exist, then remove the watch.
             if tracking.is_workdir_watched {
-                log::info!("unwatching {}", workdir.path().display());
-                let _ = poll_watcher.unwatch(path);
+                files.remove_file(poll_watcher, workdir.suibase_yaml_user());
+                files.unwatch_dir(poll_watcher, path);
                 tracking.is_workdir_watched = false;
                 at_least_one_modif = true;
             }
@@ -145,8 +378,8 @@ impl WorkdirsWatcher {
         if !state_path.exists() {
             // If the path does not exist, then remove the watch.
             if tracking.is_state_watched {
-                log::info!("unwatching {}", workdir.state_path().display());
-                let _ = poll_watcher.unwatch(state_path);
+                files.remove_file(poll_watcher, workdir.suibase_state_file());
+                files.unwatch_dir(poll_watcher, state_path);
                 tracking.is_state_watched = false;
                 at_least_one_modif = true;
             }
@@ -155,6 +388,18 @@ impl WorkdirsWatcher {
         at_least_one_modif
     }
 
+    async fn send_pending_notifs(&mut self) {
+        for (path, notif) in self.files.take_pending(Instant::now()) {
+            let path = path.to_string_lossy().to_string();
+            match notif {
+                FileNotif::Config => self.send_notif_config_file_change(path).await,
+                FileNotif::Tls(workdir_idx) => {
+                    self.send_notif_tls_file_change(workdir_idx, path).await
+                }
+            }
+        }
+    }
+
     async fn watch_loop(
         &mut self,
         subsys: &SubsystemHandle,
@@ -162,8 +407,28 @@ impl WorkdirsWatcher {
         mut local_rx: tokio::sync::mpsc::Receiver<notify::event::Event>,
     ) {
         while !subsys.is_shutdown_requested() {
-            // Wait for a message.
-            if let Some(msg) = local_rx.recv().await {
+            // Wait for a message (or the end of the coalescing of the pending changes).
+            let pending_deadline = self.files.pending_deadline();
+            let flush_at = tokio::time::Instant::from_std(
+                pending_deadline.unwrap_or_else(|| Instant::now() + COALESCE_DELAY),
+            );
+            let msg = tokio::select! {
+                msg = local_rx.recv() => msg,
+                Some(watcher_msg) = self.watcher_rx.recv() => {
+                    Self::update_tls_files_watch(
+                        &mut self.tracking,
+                        &mut self.files,
+                        &mut poll_watcher,
+                        watcher_msg,
+                    );
+                    continue;
+                }
+                _ = tokio::time::sleep_until(flush_at), if pending_deadline.is_some() => {
+                    self.send_pending_notifs().await;
+                    continue;
+                }
+            };
+            if let Some(msg) = msg {
                 common::mpsc_q_check!(local_rx);
                 if msg.need_rescan() {
                     // TODO Implement rescan of all workdirs (assume events were missed).
@@ -172,18 +437,19 @@ impl WorkdirsWatcher {
 
                 // Process the event from notify-rs
                 //log::info!("watch_loop() msg {:?}", msg);
+
+                // A change of any watched file (user_request, suibase.yaml, TLS files) is
+                // notified after COALESCE_DELAY. Whatever the kind of event, because an
+                // atomic save may be reported as a create, a modify or a rename.
+                if msg.kind.is_create() || msg.kind.is_modify() || msg.kind.is_remove() {
+                    let now = Instant::now();
+                    for path in &msg.paths {
+                        self.files.on_event(&mut poll_watcher, path, now);
+                    }
+                }
+
                 // Iterate the msg.paths and find the workdir string (using Workdirs::find_workdir) and filename portion for each.
                 match msg.kind {
-                    notify::event::EventKind::Modify(_) => {
-                        for path in msg.paths {
-                            // Ignore everything except for user_request and suibase.yaml files.
-                            if !path.ends_with("user_request") && !path.ends_with("suibase.yaml") {
-                                continue;
-                            }
-                            self.send_notif_config_file_change(path.to_string_lossy().to_string())
-                                .await
-                        }
-                    }
                     // notify::event::EventKind::Any()
 
                     // Meta-events about notifier itself (can be ignored).
@@ -198,10 +464,14 @@ impl WorkdirsWatcher {
                             let workdirs = &*workdirs_guard;
                             log::info!("CreateKind {:?}", msg);
                             for path in msg.paths {
+                                // Re-created after a remove or rename (e.g. a symlink target).
+                                self.files.rewatch_dir(&mut poll_watcher, &path);
+
                                 let path = &path.to_string_lossy();
                                 if let Some((_, workdir)) = workdirs.find_workdir(path) {
                                     if Self::update_workdir_watch(
                                         &mut self.tracking,
+                                        &mut self.files,
                                         &mut poll_watcher,
                                         workdir,
                                         path,
@@ -228,6 +498,7 @@ impl WorkdirsWatcher {
                                 {
                                     if Self::remove_workdir_watch(
                                         &mut self.tracking,
+                                        &mut self.files,
                                         &mut poll_watcher,
                                         workdir,
                                         &path.to_string_lossy(),
@@ -268,6 +539,10 @@ impl WorkdirsWatcher {
             .build()
             .unwrap();
 
+        // The modification times are compared with a one second resolution, and only
+        // a newer one is a change. The contents are also compared to not miss a save
+        // within the same second, or a symlink swapped to an older file. The watches
+        // are non-recursive, so only a few small files are read on each poll.
         let poll_watcher_config = notify::Config::default();
 
         let mut poll_watcher = PollWatcher::new(
@@ -286,7 +561,9 @@ impl WorkdirsWatcher {
                     log::warn!("{:?}", e);
                 }
             },
-            poll_watcher_config.with_poll_interval(std::time::Duration::from_secs(15)),
+            poll_watcher_config
+                .with_poll_interval(std::time::Duration::from_secs(15))
+                .with_compare_contents(true),
         )?;
 
         {
@@ -297,7 +574,7 @@ impl WorkdirsWatcher {
             // TODO if suibase is deleted... then need to find a solution to recover gracefully (exit?).
             let path = workdirs.path();
             if path.exists() {
-                let _ = poll_watcher.watch(workdirs.path(), RecursiveMode::NonRecursive);
+                self.files.watch_dir(&mut poll_watcher, workdirs.path());
             } else {
                 log::error!("implement watching above ~/suibase/workdirs for bad installation!");
             }
@@ -305,6 +582,7 @@ impl WorkdirsWatcher {
             for (_workdir_idx, workdir) in workdirs.workdirs.iter() {
                 if Self::update_workdir_watch(
                     &mut self.tracking,
+                    &mut self.files,
                     &mut poll_watcher,
                     workdir,
                     &workdir.path().to_string_lossy(),
@@ -334,3 +612,137 @@ impl WorkdirsWatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    // Same as the daemon, except for a much faster polling.
+    fn poll_watcher() -> (PollWatcher, mpsc::Receiver<notify::Event>) {
+        let (tx, rx) = mpsc::channel();
+        let config = notify::Config::default()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_compare_contents(true);
+        let poll_watcher = PollWatcher::new(
+            move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = res {
+                    let _ = tx.send(event);
+                }
+            },
+            config,
+        )
+        .unwrap();
+        (poll_watcher, rx)
+    }
+
+    // Process the events like the watch_loop for 'duration'. Returns the paths notified.
+    fn run_for(
+        files: &mut WatchedFiles,
+        poll_watcher: &mut PollWatcher,
+        rx: &mpsc::Receiver<notify::Event>,
+        duration: Duration,
+    ) -> Vec<PathBuf> {
+        let start = Instant::now();
+        let mut notified = Vec::new();
+        while start.elapsed() < duration {
+            if let Ok(event) = rx.recv_timeout(Duration::from_millis(10)) {
+                if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+                    for path in &event.paths {
+                        files.on_event(poll_watcher, path, Instant::now());
+                    }
+                }
+            }
+            let pending = files.take_pending(Instant::now());
+            notified.extend(pending.into_iter().map(|(path, _)| path));
+        }
+        notified
+    }
+
+    // Like vim: write a new file, then rename it over the original.
+    fn atomic_save(path: &Path, contents: &str) {
+        let tmp = path.with_file_name("4913");
+        fs::write(&tmp, contents).unwrap();
+        fs::rename(&tmp, path).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_save() {
+        let dir = std::env::temp_dir().join(format!("sbsd-watch-save-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let yaml = dir.join("suibase.yaml");
+        fs::write(&yaml, "proxy_enabled: true\n").unwrap();
+
+        let (mut poll_watcher, rx) = poll_watcher();
+        let mut files = WatchedFiles::default();
+        files.add_file(&mut poll_watcher, &yaml, FileNotif::Config);
+        let wait = Duration::from_millis(600);
+        assert!(run_for(&mut files, &mut poll_watcher, &rx, wait).is_empty());
+
+        // Still watched after the file was replaced.
+        for i in 0..3 {
+            atomic_save(&yaml, &format!("proxy_port_number: {}\n", 44340 + i));
+            assert_eq!(
+                run_for(&mut files, &mut poll_watcher, &rx, wait),
+                vec![yaml.clone()]
+            );
+        }
+
+        // Other files of the directory are ignored.
+        fs::write(dir.join(".suibase.yaml.swp"), "swap").unwrap();
+        assert!(run_for(&mut files, &mut poll_watcher, &rx, wait).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_symlink_target_swap() {
+        let dir = std::env::temp_dir().join(format!("sbsd-watch-link-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let workdir = dir.join("workdir");
+        let dotfiles = dir.join("dotfiles");
+        fs::create_dir_all(&workdir).unwrap();
+        fs::create_dir_all(&dotfiles).unwrap();
+        let target_a = dotfiles.join("a.yaml");
+        let target_b = dotfiles.join("b.yaml");
+        fs::write(&target_a, "proxy_enabled: true\n").unwrap();
+        fs::write(&target_b, "proxy_enabled: false\n").unwrap();
+        let link = workdir.join("suibase.yaml");
+        std::os::unix::fs::symlink(&target_a, &link).unwrap();
+
+        let (mut poll_watcher, rx) = poll_watcher();
+        let mut files = WatchedFiles::default();
+        files.add_file(&mut poll_watcher, &link, FileNotif::Config);
+        let wait = Duration::from_millis(600);
+        assert!(run_for(&mut files, &mut poll_watcher, &rx, wait).is_empty());
+
+        // Saving the target changes both the target and the link (once).
+        atomic_save(&target_a, "proxy_port_number: 44340\n");
+        assert_eq!(
+            run_for(&mut files, &mut poll_watcher, &rx, wait),
+            vec![link.clone()]
+        );
+
+        // Swap the link to another target (like "ln -sfn").
+        let tmp_link = workdir.join("suibase.yaml.tmp");
+        std::os::unix::fs::symlink(&target_b, &tmp_link).unwrap();
+        fs::rename(&tmp_link, &link).unwrap();
+        assert_eq!(
+            run_for(&mut files, &mut poll_watcher, &rx, wait),
+            vec![link.clone()]
+        );
+
+        // Only the new target is watched.
+        atomic_save(&target_b, "proxy_port_number: 44341\n");
+        assert_eq!(
+            run_for(&mut files, &mut poll_watcher, &rx, wait),
+            vec![link.clone()]
+        );
+        atomic_save(&target_a, "proxy_port_number: 44342\n");
+        assert!(run_for(&mut files, &mut poll_watcher, &rx, wait).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
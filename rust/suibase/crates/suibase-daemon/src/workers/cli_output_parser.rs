@@ -0,0 +1,676 @@
+// Parsing of the "<workdir> status" output (used by the CliPoller).
+//
+// The output is from the suibase scripts, but it embeds what the sui binary prints,
+// and sui releases have changed it before (e.g. sui 1.2.x interleaving log lines
+// on stdout).
+//
+// Each known output format family has its own CliOutputParser. The parser is
+// selected from the sui client version detected on a previous poll. The other
+// parsers are tried as fallbacks.
+//
+// Output that no parser understands is "quarantined": the status becomes UNKNOWN
+// (with a raw snippet in the 'debug' field) instead of flipping the workdir to DOWN.
+//
+// Same for an output that is not trusted to be parsed: truncated by the ShellWorker,
+// larger than PARSE_INPUT_MAX_BYTES, mostly binary, or taking more than
+// PARSE_TIMEOUT to parse (see parse_status_output_timeboxed).
+
+use std::time::Duration;
+
+use common::{shared_types::WorkdirState, workers::SHELL_OUTPUT_TRUNCATED_MARKER};
+
+use crate::{
+    api::{StatusService, WorkdirStatusResponse},
+    shared_types::WORKDIRS_KEYS,
+};
+
+// Max number of characters of the raw output kept for debugging.
+const QUARANTINE_SNIPPET_MAX: usize = 512;
+
+// Larger outputs are quarantined without being parsed.
+const PARSE_INPUT_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+// Output with more than 1/BINARY_CHARS_RATIO of control characters (other than
+// whitespaces and escape sequences) or invalid UTF8 is considered binary.
+const BINARY_CHARS_RATIO: usize = 100;
+
+pub(crate) const PARSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Service status words in the text output.
+const SERVICE_STATUS_WORDS: [&str; 3] = ["OK", "DOWN", "DEGRADED"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CliParseOutcome {
+    // Status (and services) were parsed into the response.
+    Parsed { asui_selection: Option<String> },
+    // The output was understood and reports a problem (e.g. workdir not initialized).
+    Problem,
+    // The output is not understood by this parser (with the reason).
+    Unparseable(String),
+}
+
+pub(crate) trait CliOutputParser: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Parse 'cmd' (already without color codes) into 'resp'.
+    //
+    // 'resp' content is undefined when Unparseable is returned.
+    fn parse(
+        &self,
+        cmd: &str,
+        workdir_name: &str,
+        resp: &mut WorkdirStatusResponse,
+    ) -> CliParseOutcome;
+}
+
+// Text output, as displayed to the user (the default).
+pub(crate) struct TextStatusParser;
+
+// Text output with log lines interleaved on stdout (sui 1.2.x).
+pub(crate) struct InterleavedLogStatusParser;
+
+static TEXT_PARSER: TextStatusParser = TextStatusParser;
+static INTERLEAVED_LOG_PARSER: InterleavedLogStatusParser = InterleavedLogStatusParser;
+
+// Extract (major, minor, patch) from a version string like "sui 1.2.0-c8f2ec0".
+pub(crate) fn parse_sui_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.trim().trim_start_matches("sui ").trim();
+    let version = version.split(['-', ' ']).next()?;
+    let mut numbers = version.split('.').map(|n| n.parse::<u32>().ok());
+    Some((numbers.next()??, numbers.next()??, numbers.next()??))
+}
+
+// sui versions known to interleave log lines with their stdout output.
+fn is_interleaved_log_version(client_version: Option<&str>) -> bool {
+    matches!(client_version.and_then(parse_sui_version), Some((1, 2, _)))
+}
+
+// Parsers to try, most likely first.
+pub(crate) fn select_parsers(client_version: Option<&str>) -> [&'static dyn CliOutputParser; 2] {
+    if is_interleaved_log_version(client_version) {
+        [&INTERLEAVED_LOG_PARSER, &TEXT_PARSER]
+    } else {
+        [&TEXT_PARSER, &INTERLEAVED_LOG_PARSER]
+    }
+}
+
+fn snippet(cmd: &str) -> String {
+    cmd.chars().take(QUARANTINE_SNIPPET_MAX).collect()
+}
+
+fn is_binary_output(cmd_response: &str) -> bool {
+    let mut n_chars: usize = 0;
+    let mut n_binary: usize = 0;
+    for c in cmd_response.chars() {
+        n_chars += 1;
+        if c == char::REPLACEMENT_CHARACTER
+            || (c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x1b'))
+        {
+            n_binary += 1;
+        }
+    }
+    n_binary > 0 && n_binary >= n_chars / BINARY_CHARS_RATIO
+}
+
+fn quarantine(
+    resp: &mut WorkdirStatusResponse,
+    workdir_name: &str,
+    status_info: String,
+    reason: &str,
+    cmd: &str,
+) {
+    let debug = snippet(cmd);
+    log::warn!(
+        "'{} status' output not recognized ({}) [{}]",
+        workdir_name,
+        reason,
+        debug
+    );
+    resp.status = Some(WorkdirState::Unknown.to_string());
+    resp.status_info = Some(status_info);
+    resp.services = None;
+    resp.debug = Some(debug);
+}
+
+// Parse the "<workdir> status" output into 'resp' with the most appropriate parser.
+//
+// Returns the asui selection when the output could be parsed.
+//
+// When no parser succeeds, the status is UNKNOWN and a snippet of the output is
+// stored in resp.debug.
+pub(crate) fn parse_status_output(
+    cmd_response: &str,
+    workdir_name: &str,
+    client_version: Option<&str>,
+    resp: &mut WorkdirStatusResponse,
+) -> Option<String> {
+    let untrusted = if cmd_response.len() > PARSE_INPUT_MAX_BYTES {
+        Some(format!("is too large ({} bytes)", cmd_response.len()))
+    } else if cmd_response.contains(SHELL_OUTPUT_TRUNCATED_MARKER) {
+        Some("was truncated".to_string())
+    } else if is_binary_output(cmd_response) {
+        Some("is binary".to_string())
+    } else {
+        None
+    };
+    if let Some(untrusted) = untrusted {
+        let status_info = format!("'{} status' output {}", workdir_name, untrusted);
+        quarantine(resp, workdir_name, status_info, &untrusted, cmd_response);
+        return None;
+    }
+
+    let cmd = common::utils::strip_ansi_escapes(cmd_response);
+
+    let mut reasons: Vec<String> = Vec::new();
+    for parser in select_parsers(client_version) {
+        let mut candidate = resp.clone();
+        match parser.parse(&cmd, workdir_name, &mut candidate) {
+            CliParseOutcome::Parsed { asui_selection } => {
+                *resp = candidate;
+                return asui_selection;
+            }
+            CliParseOutcome::Problem => {
+                *resp = candidate;
+                return None;
+            }
+            CliParseOutcome::Unparseable(reason) => {
+                reasons.push(format!("{}: {}", parser.name(), reason));
+            }
+        }
+    }
+
+    let status_info = format!("unrecognized '{} status' output", workdir_name);
+    quarantine(resp, workdir_name, status_info, &reasons.join("; "), &cmd);
+    None
+}
+
+// parse_status_output() on a blocking thread, for up to 'timeout'.
+//
+// Returns the updated 'resp' and the asui selection.
+pub(crate) async fn parse_status_output_timeboxed(
+    cmd_response: String,
+    workdir_name: &str,
+    client_version: Option<String>,
+    resp: WorkdirStatusResponse,
+    timeout: Duration,
+) -> (WorkdirStatusResponse, Option<String>) {
+    let debug = snippet(&cmd_response);
+    let mut fallback_resp = resp.clone();
+    let workdir = workdir_name.to_string();
+    let parse = tokio::task::spawn_blocking(move || {
+        let mut resp = resp;
+        let asui_selection = parse_status_output(
+            &cmd_response,
+            &workdir,
+            client_version.as_deref(),
+            &mut resp,
+        );
+        (resp, asui_selection)
+    });
+    let reason = match tokio::time::timeout(timeout, parse).await {
+        Ok(Ok(parsed)) => return parsed,
+        Ok(Err(e)) => format!("parser failed ({})", e),
+        Err(_) => format!("parse timed out after {:?}", timeout),
+    };
+    let status_info = format!("'{} status' {}", workdir_name, reason);
+    quarantine(
+        &mut fallback_resp,
+        workdir_name,
+        status_info,
+        &reason,
+        &debug,
+    );
+    (fallback_resp, None)
+}
+
+impl CliOutputParser for TextStatusParser {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn parse(
+        &self,
+        cmd: &str,
+        workdir_name: &str,
+        resp: &mut WorkdirStatusResponse,
+    ) -> CliParseOutcome {
+        // First line is two words, first should match the workdir name followed by the status word.
+        let mut first_line_parsed = false;
+        let mut asui_selection: Option<String> = None;
+
+        // Iterate every lines of cmd.
+        let mut line_number = 0;
+        let mut error_detected = false;
+
+        for line in cmd.lines() {
+            let line = line.trim();
+            // Ignore empty lines or "---" divider.
+            if line.is_empty() || line.starts_with("---") {
+                continue;
+            }
+
+            if line.starts_with("Error:") {
+                error_detected = true;
+            }
+
+            line_number += 1;
+
+            // Detect into the first two lines for a hint of a problem.
+            if line_number <= 2 {
+                let line_lc = line.to_lowercase();
+                // Detect Suibase not installed.
+                if line_lc.contains("not initialized")
+                    || line_lc.contains("not found")
+                    || line_lc.contains("no such")
+                    || line_lc.contains("no command")
+                {
+                    resp.status = Some(WorkdirState::Disabled.to_string());
+                    let status_info = format!("{0} not initialized. Do '{0} start'", workdir_name);
+                    resp.status_info = Some(status_info);
+                    return CliParseOutcome::Problem;
+                }
+            }
+
+            if error_detected {
+                if line_number == 2 {
+                    // Error detected but not sure what the problem is.
+                    break;
+                }
+                continue;
+            }
+
+            // Split the line into words.
+            let mut words = line.split_whitespace();
+
+            if line_number == 1 {
+                // Get the very first word.
+                if let Some(word) = words.next() {
+                    if word != workdir_name {
+                        return CliParseOutcome::Unparseable(format!("first word is [{}]", word));
+                    }
+                    // The first word matches the workdir name, so the next word is the status.
+                    // (but skip "services" which is present only for remote network workdirs).
+                    let status = match words.next() {
+                        Some("services") => words.next(),
+                        status => status,
+                    };
+                    match status {
+                        Some(status) if status.parse::<WorkdirState>().is_ok() => {
+                            resp.status = Some(status.to_string());
+                            first_line_parsed = true;
+                        }
+                        _ => {
+                            return CliParseOutcome::Unparseable(format!(
+                                "missing status in [{}]",
+                                line
+                            ))
+                        }
+                    }
+                }
+                continue; // Done with parsing first line
+            }
+            // Use first word in words to decide how to parse the remaining words.
+            let first_word = words.next();
+
+            match first_word {
+                Some("Localnet") | Some("Faucet") | Some("Multi-link") | Some("Proxy") => {
+                    // Get the 4th word in words.
+                    let mut service_status = words.nth(2).unwrap_or("").to_string();
+
+                    // Valid service_status are "OK", "DOWN", "DEGRADED" or "NOT RUNNING"
+                    let status_is_valid = if SERVICE_STATUS_WORDS.contains(&service_status.as_str())
+                    {
+                        true
+                    } else if service_status == "NOT" {
+                        // Special case for two words "NOT RUNNING" status.
+                        let mut ret_value = false;
+                        if let Some(next_word) = words.next() {
+                            if next_word == "RUNNING" {
+                                ret_value = true;
+                                service_status = "NOT RUNNING".to_string();
+                            } else {
+                                service_status = format!("NOT {}", next_word);
+                            }
+                        }
+                        ret_value
+                    } else {
+                        false
+                    };
+
+                    if !status_is_valid {
+                        return CliParseOutcome::Unparseable(format!(
+                            "missing [{}] service status in [{}] service_status=[{}]",
+                            first_word.unwrap(),
+                            line,
+                            service_status,
+                        ));
+                    }
+
+                    // service label is everything before the ":" on the line.
+                    let service_label = line.split(':').next().unwrap_or("").trim().to_string();
+                    if service_label.is_empty() {
+                        continue;
+                    }
+
+                    // Add the service, unless already in resp.services.
+                    let services = resp.services.get_or_insert_with(Vec::new);
+                    if !services
+                        .iter()
+                        .any(|service| service.label == service_label)
+                    {
+                        let mut new_service = StatusService::new(service_label);
+                        new_service.status = Some(service_status);
+                        services.push(new_service);
+                    }
+                }
+                Some("client") => {
+                    // Parse client line.
+                    let sui_version = line.split(':').nth(1).unwrap_or("").trim();
+                    if !sui_version.is_empty() {
+                        // Remove leading "sui " from sui_version.
+                        let sui_version = sui_version.trim_start_matches("sui ").to_string();
+                        resp.client_version = Some(sui_version);
+                    }
+                }
+                Some("asui") => {
+                    // Parse asui selection line. Isolate what is between [] on that line
+                    let candidate = line.split('[').nth(1).unwrap_or("");
+                    let candidate = candidate.trim_end_matches(']').trim();
+                    // Validate that it is one of the known workdir key.
+                    if WORKDIRS_KEYS.contains(&candidate) {
+                        asui_selection = Some(candidate.to_string());
+                    }
+                }
+                _ => {
+                    // Unknown line, so ignore it.
+                }
+            }
+        }
+
+        if error_detected {
+            let cmd = snippet(cmd);
+            resp.status = Some(WorkdirState::Down.to_string());
+            resp.status_info = Some(format!("Error detected [{}]", cmd));
+            log::error!("Workdir status error detected [{}]", cmd);
+            return CliParseOutcome::Problem;
+        }
+
+        if !first_line_parsed {
+            return CliParseOutcome::Unparseable("empty output".to_string());
+        }
+        CliParseOutcome::Parsed { asui_selection }
+    }
+}
+
+impl InterleavedLogStatusParser {
+    // Log lines look like either:
+    //   "[warning] Client/Server api version mismatch, ..."
+    //   "2023-06-14T16:20:31.123456Z  WARN sui_sdk: ..."
+    fn is_log_line(line: &str) -> bool {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[') {
+            let tag = tag.split(']').next().unwrap_or("").to_lowercase();
+            return ["warn", "warning", "info", "debug", "trace", "error"].contains(&tag.as_str());
+        }
+
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(timestamp), Some(level)) => {
+                chrono::DateTime::parse_from_rfc3339(timestamp).is_ok()
+                    && ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"].contains(&level)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl CliOutputParser for InterleavedLogStatusParser {
+    fn name(&self) -> &'static str {
+        "interleaved-log"
+    }
+
+    fn parse(
+        &self,
+        cmd: &str,
+        workdir_name: &str,
+        resp: &mut WorkdirStatusResponse,
+    ) -> CliParseOutcome {
+        let filtered: Vec<&str> = cmd
+            .lines()
+            .filter(|line| !Self::is_log_line(line))
+            .collect();
+        TEXT_PARSER.parse(&filtered.join("\n"), workdir_name, resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(cmd: &str, workdir: &str, client_version: Option<&str>) -> WorkdirStatusResponse {
+        let mut resp = WorkdirStatusResponse::new();
+        parse_status_output(cmd, workdir, client_version, &mut resp);
+        resp
+    }
+
+    fn service_status(resp: &WorkdirStatusResponse, label: &str) -> Option<String> {
+        resp.services
+            .as_ref()?
+            .iter()
+            .find(|service| service.label == label)?
+            .status
+            .clone()
+    }
+
+    // Current format (with color codes).
+    const FIXTURE_LOCALNET: &str = "\x1b[1;32mlocalnet \x1b[0m\x1b[1;34mOK\x1b[0m\n\
+        ---\n\
+        Localnet process : \x1b[1;34mOK\x1b[0m ( pid 1234 ) http://0.0.0.0:9000\n\
+        Faucet process   : \x1b[1;34mOK\x1b[0m ( pid 1235 ) http://0.0.0.0:9123\n\
+        Proxy server     : \x1b[1;34mOK\x1b[0m ( pid 1236 ) http://localhost:44340\n\
+        Multi-link RPC   : \x1b[1;34mOK\x1b[0m\n\
+        ---\n\
+        client version: \x1b[1;34msui 1.30.1-2a3b4c5\x1b[0m\n\
+        asui selection: [ \x1b[1;34mlocalnet\x1b[0m ]\n";
+
+    // Remote network workdir, stopped.
+    const FIXTURE_REMOTE_STOPPED: &str = "devnet services STOPPED\n\
+        ---\n\
+        client version: sui 1.28.0-abcdef\n\
+        asui selection: [ testnet ]\n";
+
+    // sui 1.2.x interleaving its logs on stdout (including before the first line).
+    const FIXTURE_INTERLEAVED: &str =
+        "[warning] Client/Server api version mismatch, client api version : 1.2.0\n\
+        localnet DOWN\n\
+        ---\n\
+        Localnet process : NOT RUNNING\n\
+        2023-06-14T16:20:31.123456Z  WARN sui_sdk: Server api version mismatch\n\
+        Faucet process   : NOT RUNNING\n\
+        ---\n\
+        client version: sui 1.2.0-c8f2ec0\n\
+        asui selection: [ localnet ]\n";
+
+    #[test]
+    fn test_text_formats() {
+        let resp = parse(FIXTURE_LOCALNET, "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("OK"));
+        assert_eq!(resp.client_version.as_deref(), Some("1.30.1-2a3b4c5"));
+        assert_eq!(resp.services.as_ref().unwrap().len(), 4);
+        assert_eq!(
+            service_status(&resp, "Multi-link RPC").as_deref(),
+            Some("OK")
+        );
+        assert!(resp.debug.is_none());
+
+        let mut resp = WorkdirStatusResponse::new();
+        let asui = parse_status_output(FIXTURE_REMOTE_STOPPED, "devnet", None, &mut resp);
+        assert_eq!(resp.status.as_deref(), Some("STOPPED"));
+        assert_eq!(asui.as_deref(), Some("testnet"));
+    }
+
+    #[test]
+    fn test_interleaved_log_format() {
+        // Selected from the version detected on a previous poll...
+        assert_eq!(
+            select_parsers(Some("1.2.0-c8f2ec0"))[0].name(),
+            "interleaved-log"
+        );
+        let resp = parse(FIXTURE_INTERLEAVED, "localnet", Some("1.2.0-c8f2ec0"));
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert_eq!(
+            service_status(&resp, "Faucet process").as_deref(),
+            Some("NOT RUNNING")
+        );
+        assert_eq!(resp.client_version.as_deref(), Some("1.2.0-c8f2ec0"));
+
+        // ...and as a fallback when the version is not known yet.
+        let resp = parse(FIXTURE_INTERLEAVED, "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert!(resp.debug.is_none());
+    }
+
+    #[test]
+    fn test_problems_and_quarantine() {
+        let resp = parse("localnet not initialized\n", "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("DISABLED"));
+        assert!(resp.debug.is_none());
+
+        let resp = parse("Error: shell exec failed", "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+
+        // Not understood by any parser.
+        let resp = parse("localnet is doing fine\nwhatever", "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("UNKNOWN"));
+        assert!(resp.services.is_none());
+        assert_eq!(
+            resp.debug.as_deref(),
+            Some("localnet is doing fine\nwhatever")
+        );
+
+        let long = format!("localnet {}", "x".repeat(2 * QUARANTINE_SNIPPET_MAX));
+        let resp = parse(&long, "localnet", None);
+        assert_eq!(resp.debug.unwrap().len(), QUARANTINE_SNIPPET_MAX);
+    }
+
+    // Valid output buried in ~3MB of interleaved logs (with all sort of escapes).
+    fn multi_mb_interleaved() -> String {
+        let mut cmd = String::new();
+        for (i, line) in FIXTURE_INTERLEAVED.lines().enumerate() {
+            cmd.push_str(line);
+            cmd.push('\n');
+            if i > 0 {
+                for _ in 0..5000 {
+                    cmd.push_str(
+                        "2023-06-14T16:20:31.123456Z  WARN sui_sdk: \x1b[2K\x1b]0;t\x1b\\ \
+                        Server api version mismatch\r\n",
+                    );
+                }
+            }
+        }
+        cmd
+    }
+
+    // Deterministic pseudo-random bytes.
+    fn binary_blob(n_bytes: usize) -> Vec<u8> {
+        let mut seed: u32 = 12345;
+        (0..n_bytes)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn assert_bounded_unknown(resp: &WorkdirStatusResponse) {
+        assert_eq!(resp.status.as_deref(), Some("UNKNOWN"));
+        assert!(resp.services.is_none());
+        assert!(resp.debug.as_ref().unwrap().chars().count() <= QUARANTINE_SNIPPET_MAX);
+        assert!(resp.status_info.as_ref().unwrap().len() < 100);
+    }
+
+    #[test]
+    fn test_enormous_or_binary_output() {
+        let cmd = multi_mb_interleaved();
+        assert!(cmd.len() > 3 * 1024 * 1024 && cmd.len() < PARSE_INPUT_MAX_BYTES);
+        let resp = parse(&cmd, "localnet", Some("1.2.0-c8f2ec0"));
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert_eq!(
+            service_status(&resp, "Faucet process").as_deref(),
+            Some("NOT RUNNING")
+        );
+
+        // Not parsed when above the limit.
+        let mut cmd = cmd;
+        cmd.push_str(&"x".repeat(PARSE_INPUT_MAX_BYTES));
+        let resp = parse(&cmd, "localnet", None);
+        assert_bounded_unknown(&resp);
+        assert!(resp.status_info.unwrap().contains("too large"));
+
+        // Binary, alone or after a valid first line (lossy decoded as the ShellWorker does).
+        let blob = binary_blob(2 * 1024 * 1024);
+        let binary = String::from_utf8_lossy(&blob).to_string();
+        let resp = parse(&binary, "localnet", None);
+        assert_bounded_unknown(&resp);
+        assert_eq!(
+            resp.status_info.as_deref(),
+            Some("'localnet status' output is binary")
+        );
+        let resp = parse(&format!("localnet OK\n{}", binary), "localnet", None);
+        assert_bounded_unknown(&resp);
+        let resp = parse(&format!("Error: {}", binary), "localnet", None);
+        assert_bounded_unknown(&resp);
+
+        // A failed command with a large output is DOWN, with only a snippet kept.
+        let error = format!("Error: failed\n{}", "y".repeat(1024 * 1024));
+        let resp = parse(&error, "localnet", None);
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert!(resp.status_info.unwrap().len() < 2 * QUARANTINE_SNIPPET_MAX);
+
+        // Truncated by the ShellWorker.
+        let fixture = FIXTURE_LOCALNET.as_bytes();
+        let truncated = common::workers::capped_output_to_string(fixture, 5_000_000);
+        let resp = parse(&truncated, "localnet", None);
+        assert_bounded_unknown(&resp);
+        assert_eq!(
+            resp.status_info.as_deref(),
+            Some("'localnet status' output was truncated")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_timeboxed() {
+        let cmd = multi_mb_interleaved();
+        let (resp, asui) = parse_status_output_timeboxed(
+            cmd.clone(),
+            "localnet",
+            None,
+            WorkdirStatusResponse::new(),
+            PARSE_TIMEOUT,
+        )
+        .await;
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert_eq!(asui.as_deref(), Some("localnet"));
+
+        let (resp, asui) = parse_status_output_timeboxed(
+            cmd,
+            "localnet",
+            None,
+            WorkdirStatusResponse::new(),
+            Duration::ZERO,
+        )
+        .await;
+        assert_bounded_unknown(&resp);
+        assert!(resp.status_info.unwrap().contains("parse timed out"));
+        assert!(asui.is_none());
+    }
+
+    #[test]
+    fn test_parse_sui_version() {
+        assert_eq!(parse_sui_version("sui 1.2.0-c8f2ec0"), Some((1, 2, 0)));
+        assert_eq!(parse_sui_version("1.30.1"), Some((1, 30, 1)));
+        assert_eq!(parse_sui_version("sui"), None);
+        assert!(is_interleaved_log_version(Some("1.2.3")));
+        assert!(!is_interleaved_log_version(Some("1.20.3")));
+    }
+}
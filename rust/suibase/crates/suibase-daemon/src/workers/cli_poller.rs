@@ -14,24 +14,32 @@
 
 use crate::{
     admin_controller::AdminController,
-    api::{StatusService, Versioned, WorkdirStatusResponse},
-    shared_types::{Globals, WORKDIRS_KEYS},
+    api::{Versioned, WorkdirStatusResponse},
+    shared_types::{
+        probe_sui_binary, Globals, GlobalsWorkdirsST, StartupPhase, WebhookEvent, WebhookEventType,
+        WebhookTx, WORKDIRS_KEYS,
+    },
 };
 
+use super::cli_output_parser::{parse_status_output_timeboxed, PARSE_TIMEOUT};
+
 use axum::async_trait;
 use common::{
     basic_types::{AdminControllerTx, GenericTx, Instantiable, WorkdirContext, WorkdirIdx},
-    workers::PollerWorker,
+    shared_types::{WorkdirState, WorkdirStatus},
+    workers::{PollerSchedule, PollerWorker},
 };
 
 use common::workers::PollingTrait;
 
+use tokio::time::Duration;
 use tokio_graceful_shutdown::SubsystemHandle;
 
 #[derive(Clone)]
 pub struct CliPollerParams {
     globals: Globals,
     admctrl_tx: AdminControllerTx, // For exec shell messages
+    webhook_tx: WebhookTx,         // To notify status changes.
     workdir_idx: WorkdirIdx,
 }
 
@@ -42,10 +50,16 @@ impl WorkdirContext for CliPollerParams {
 }
 
 impl CliPollerParams {
-    pub fn new(globals: Globals, admctrl_tx: AdminControllerTx, workdir_idx: WorkdirIdx) -> Self {
+    pub fn new(
+        globals: Globals,
+        admctrl_tx: AdminControllerTx,
+        webhook_tx: WebhookTx,
+        workdir_idx: WorkdirIdx,
+    ) -> Self {
         Self {
             globals,
             admctrl_tx,
+            webhook_tx,
             workdir_idx,
         }
     }
@@ -59,6 +73,12 @@ pub struct CliPoller {
 
 pub struct PollingTraitObject {
     params: CliPollerParams,
+
+    // sui client version from the most recent parsed output. Drives the parser selection.
+    client_version: Option<String>,
+
+    // false until the first poll.
+    initialized: bool,
 }
 
 #[async_trait]
@@ -72,14 +92,23 @@ impl PollingTrait for PollingTraitObject {
 // This allow the PollerWorker to instantiate the PollingTraitObject.
 impl Instantiable<CliPollerParams> for PollingTraitObject {
     fn new(params: CliPollerParams) -> Self {
-        Self { params }
+        Self {
+            params,
+            client_version: None,
+            initialized: false,
+        }
     }
 }
 
 impl CliPoller {
     pub fn new(params: CliPollerParams, subsys: &SubsystemHandle) -> Self {
-        let poller =
-            PollerWorker::<PollingTraitObject, CliPollerParams>::new(params.clone(), subsys);
+        // Self-scheduled every ~5 seconds (jitter avoids all workdirs polling at the same time).
+        let schedule = PollerSchedule::new(Duration::from_secs(5), 20, true);
+        let poller = PollerWorker::<PollingTraitObject, CliPollerParams>::new(
+            params.clone(),
+            Some(schedule),
+            subsys,
+        );
         Self { poller }
     }
 
@@ -89,215 +118,59 @@ impl CliPoller {
 }
 
 impl PollingTraitObject {
-    fn convert_status_cmd_resp_to_status_response(
-        cmd_response: String,
-        workdir_name: String,
+    // Apply the parsed resp.status to the workdir status state machine, then reflect
+    // the resulting status (with since/cause) into the response.
+    //
+    // Returns true if the status changed.
+    fn apply_status_transition(
+        workdir: &str,
+        status: &mut WorkdirStatus,
         resp: &mut WorkdirStatusResponse,
-    ) -> (bool, Option<String>) {
-        // First line is two words, first should match the workdir name followed by the status word.
-        // If the workdir name does not match, then the resp.status is set to "DOWN" else the status word is stores in resp.status.
-        let mut first_line_parsed = false;
-        let mut asui_selection: Option<String> = None;
-
-        // Iterate every lines of cmd.
-        let mut line_number = 0;
-        let mut error_detected = false;
-
-        let cmd = common::utils::remove_ascii_color_code(&cmd_response);
-        for line in cmd.lines() {
-            let line = line.trim();
-            // Ignore empty lines or "---" divider.
-            if line.is_empty() || line.starts_with("---") {
-                continue;
-            }
-
-            if line.starts_with("Error:") {
-                error_detected = true;
-            }
-
-            line_number += 1;
-
-            // Detect into the first two lines for a hint of a problem.
-            if line_number <= 2 {
-                let line_lc = line.to_lowercase();
-                // Detect Suibase not installed.
-                if line_lc.contains("not initialized")
-                    || line_lc.contains("not found")
-                    || line_lc.contains("no such")
-                    || line_lc.contains("no command")
-                {
-                    resp.status = Some("DISABLED".to_string());
-                    let status_info = format!("{0} not initialized. Do '{0} start'", workdir_name);
-                    resp.status_info = Some(status_info);
-                    return (false, None);
-                }
-            }
-
-            if error_detected {
-                if line_number == 2 {
-                    // Error detected but not sure what the problem is.
-                    resp.status = Some("DOWN".to_string());
-                    resp.status_info = Some(format!("Error detected [{}]", cmd_response));
-                    log::error!("Workdir status error detected [{}]", cmd_response);
-                    return (false, None);
-                }
-                continue;
-            }
-
-            // Split the line into words.
-            let mut words = line.split_whitespace();
-
-            if line_number == 1 {
-                // Get the very first word.
-                if let Some(word) = words.next() {
-                    if word == workdir_name {
-                        // The first word matches the workdir name, so the next word is the status.
-                        // (but skip if next word is "services" which is present only for remote network workdirs).
-                        if let Some(status) = words.next() {
-                            if status != "services" {
-                                resp.status = Some(status.to_string());
-                                first_line_parsed = true;
-                            } else if let Some(status) = words.next() {
-                                resp.status = Some(status.to_string());
-                                first_line_parsed = true;
-                            }
-                        }
-                    } else {
-                        resp.status = Some("DOWN".to_string());
-                        resp.status_info = Some(format!(
-                            "Missing status in [{}] first word is [{}]",
-                            cmd, word
-                        ));
-                        return (false, None);
-                    }
-                }
-
-                if resp.status.is_none() {
-                    // Something is not right.
-                    resp.status = Some("DOWN".to_string());
-                    resp.status_info = Some(format!("Missing status in {}", cmd));
-                    return (false, None);
-                }
-
-                continue; // Done with parsing first line
-            }
-            // Use first word in words to decide how to parse the remaining words.
-            let first_word = words.next();
-
-            match first_word {
-                Some("Localnet") | Some("Faucet") | Some("Multi-link") | Some("Proxy") => {
-                    // Get the 4th word in words.
-                    let mut service_status = words.nth(2).unwrap_or("").to_string();
-
-                    // Validate if service_status is one of substring "OK", "DOWN", "DEGRADED" or "NOT RUNNING"
-                    let status_is_valid = if service_status == "OK"
-                        || service_status == "DOWN"
-                        || service_status == "DEGRADED"
-                    {
-                        true
-                    } else if service_status == "NOT" {
-                        // Special case for two words "NOT RUNNING" status.
-                        let mut ret_value = false;
-                        if let Some(next_word) = words.next() {
-                            if next_word == "RUNNING" {
-                                ret_value = true;
-                                service_status = "NOT RUNNING".to_string();
-                            } else {
-                                service_status = format!("NOT {}", next_word);
-                            }
-                        }
-                        ret_value
-                    } else {
-                        false
-                    };
-                    let service_status = service_status; // Make service_status immutable.
-
-                    if !status_is_valid {
-                        // Something is not right.
-                        resp.status = Some("DOWN".to_string());
-                        resp.status_info = Some(format!(
-                            "Missing [{}] service status in [{}] service_status=[{}]",
-                            first_word.unwrap(),
-                            cmd,
-                            service_status,
-                        ));
-                        return (false, None);
-                    }
-
-                    // Valid service status found, make sure the response has the services array initialized.
-                    if resp.services.is_none() {
-                        resp.services = Some(Vec::new());
-                    }
-
-                    // service label is everything before the ":" on the line.
-                    let service_label = line.split(':').next().unwrap_or("").trim().to_string();
-                    if service_label.is_empty() {
-                        continue;
-                    }
-
-                    // Lookup if the service is already in resp.services. If not then create it, else
-                    // just ignore this line.
-                    let services = resp.services.as_mut().unwrap();
-                    let mut found = false;
-                    for service in services.iter_mut() {
-                        if service.label == service_label {
-                            found = true;
-                            break;
-                        }
-                    }
-                    if !found {
-                        // Add a new service to resp.services.
-                        let mut new_service = StatusService::new(service_label);
-                        new_service.status = Some(service_status);
-                        services.push(new_service);
-                    }
-                }
-                Some("client") => {
-                    // Parse client line.
-                    let mut sui_version = line.split(':').nth(1).unwrap_or("").trim().to_string();
-                    if !sui_version.is_empty() {
-                        // Remove leading "sui " from sui_version.
-                        sui_version = sui_version.trim_start_matches("sui ").to_string();
-                        resp.client_version = Some(sui_version);
-                    }
-                }
-                Some("asui") => {
-                    // Parse asui selection line. Isolate what is between [] on that line
-                    let mut asui_selection_candidate =
-                        line.split('[').nth(1).unwrap_or("").trim().to_string();
-                    if !asui_selection_candidate.is_empty() {
-                        // Remove trailing "]" from asui_selection.
-                        asui_selection_candidate =
-                            asui_selection_candidate.trim_end_matches(']').to_string();
-                        // Trim spaces
-                        asui_selection_candidate = asui_selection_candidate.trim().to_string();
-                        // Validate that it is one of the known workdir key.
-                        if WORKDIRS_KEYS.contains(&asui_selection_candidate.as_str()) {
-                            // All good.
-                            asui_selection = Some(asui_selection_candidate);
-                        }
-                    }
-                }
-                _ => {
-                    // Unknown line, so ignore it.
-                }
+    ) -> bool {
+        // Default to DOWN if could not identify the status.
+        let (new_state, cause) = match resp.status.as_deref().map(str::parse::<WorkdirState>) {
+            Some(Ok(state)) => {
+                let cause = resp
+                    .status_info
+                    .clone()
+                    .unwrap_or_else(|| format!("'{} status' reported {}", workdir, state));
+                (state, cause)
             }
-        }
+            Some(Err(e)) => (WorkdirState::Down, e.to_string()),
+            None => (
+                WorkdirState::Down,
+                resp.status_info
+                    .clone()
+                    .unwrap_or_else(|| "status not identified".to_string()),
+            ),
+        };
 
-        (first_line_parsed, asui_selection)
+        let changed = status.transition(new_state, &cause);
+        resp.status = Some(status.state().to_string());
+        resp.status_since = Some(status.since().to_rfc3339());
+        resp.status_cause = Some(status.cause().to_string());
+        changed
     }
 
     async fn update_globals_workdir_status(&mut self) {
         let workdir_idx = self.params.workdir_idx;
         let workdir = WORKDIRS_KEYS[workdir_idx as usize].to_string();
 
+        // Test hook (see STARTUP_INIT_DELAY_ENV). Each workdir waits on its own.
+        if !self.initialized {
+            self.initialized = true;
+            let init_delay = self.params.globals.startup.init_delay();
+            if !init_delay.is_zero() {
+                tokio::time::sleep(init_delay).await;
+            }
+        }
+
         // Try to refresh the globals and return the latest UUID.
         let mut resp = WorkdirStatusResponse::new();
         resp.header.method = "getWorkdirStatus".to_string();
         resp.header.key = Some(workdir.clone());
 
         // Get an update with a "<workdir> status" shell call.
-        // Map it into the resp.
         let cmd_resp = match AdminController::send_shell_exec(
             &self.params.admctrl_tx,
             workdir_idx,
@@ -312,15 +185,26 @@ impl PollingTraitObject {
         // Do not assumes that if shell_exec returns OK that the command was successful.
         // Parse the command response to figure out if really successful.
         resp.status = None;
-        let (is_successful, asui_selection) =
-            Self::convert_status_cmd_resp_to_status_response(cmd_resp, workdir.clone(), &mut resp);
+        let (mut resp, asui_selection) = parse_status_output_timeboxed(
+            cmd_resp,
+            &workdir,
+            self.client_version.clone(),
+            resp,
+            PARSE_TIMEOUT,
+        )
+        .await;
+        if resp.client_version.is_some() {
+            self.client_version = resp.client_version.clone();
+        }
 
-        // Default to DOWN if could not identify the status.
-        if resp.status.is_none() {
-            resp.status = Some("DOWN".to_string());
+        // Provenance of the sui binary (e.g. after a set-sui-repo or an update).
+        if let Some(workdir_obj) =
+            GlobalsWorkdirsST::get_workdir_by_idx(&self.params.globals, workdir_idx).await
+        {
+            resp.sui_binary = probe_sui_binary(workdir_obj.path(), resp.client_version.clone());
         }
 
-        if is_successful && asui_selection.is_some() {
+        if asui_selection.is_some() {
             self.params.globals.set_asui_selection(asui_selection).await;
         }
 
@@ -328,6 +212,21 @@ impl PollingTraitObject {
             // Update the globals with this potentially new response.
             let mut globals_write_guard = self.params.globals.get_status(workdir_idx).write().await;
             let globals = &mut *globals_write_guard;
+
+            let previous_state = globals.status.state();
+            if Self::apply_status_transition(&workdir, &mut globals.status, &mut resp) {
+                log::info!("{} {}", workdir, globals.status.summary_string());
+                let data = serde_json::json!({
+                    "status": globals.status.state().as_str(),
+                    "previous_status": previous_state.as_str(),
+                    "since": globals.status.since().to_rfc3339(),
+                    "cause": globals.status.cause(),
+                });
+                let event =
+                    WebhookEvent::new(WebhookEventType::WorkdirStatusChange, &workdir, data);
+                self.params.webhook_tx.send_event(event);
+            }
+
             if let Some(ui) = &mut globals.ui {
                 // Update globals.ui with resp if different. This will update the uuid_data accordingly.
                 let _was_updated = ui.take_if_not_equal(resp.clone());
@@ -343,7 +242,108 @@ impl PollingTraitObject {
                 // Copy the newly created UUID in the inner response header (so the caller can use these also).
                 //new_versioned_resp.write_uuids_into_header_param(&mut resp.header);
                 globals.ui = Some(new_versioned_resp);
+                self.params
+                    .globals
+                    .startup
+                    .record(StartupPhase::StatusKnown(workdir_idx));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workers::cli_output_parser::parse_status_output;
+
+    #[test]
+    fn test_status_cause_propagates_to_response() {
+        let mut status = WorkdirStatus::new();
+
+        let mut resp = WorkdirStatusResponse::new();
+        let cmd = "localnet DOWN\n\nLocalnet process : NOT RUNNING\n";
+        parse_status_output(cmd, "localnet", None, &mut resp);
+        assert!(PollingTraitObject::apply_status_transition(
+            "localnet",
+            &mut status,
+            &mut resp
+        ));
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert_eq!(
+            resp.status_cause.as_deref(),
+            Some("'localnet status' reported DOWN")
+        );
+        let since = resp.status_since.clone();
+        assert!(since.is_some());
+
+        // Parsing problem: UNKNOWN (quarantined) instead of DOWN, with the cause.
+        let mut resp = WorkdirStatusResponse::new();
+        parse_status_output("devnet OK", "localnet", None, &mut resp);
+        assert!(PollingTraitObject::apply_status_transition(
+            "localnet",
+            &mut status,
+            &mut resp
+        ));
+        assert_eq!(resp.status.as_deref(), Some("UNKNOWN"));
+        assert_eq!(
+            resp.status_cause.as_deref(),
+            Some("unrecognized 'localnet status' output")
+        );
+        assert_eq!(resp.debug.as_deref(), Some("devnet OK"));
+
+        // Unknown status word.
+        let mut resp = WorkdirStatusResponse::new();
+        resp.status = Some("BOGUS".to_string());
+        PollingTraitObject::apply_status_transition("localnet", &mut status, &mut resp);
+        assert_eq!(resp.status.as_deref(), Some("DOWN"));
+        assert!(resp.status_cause.unwrap().contains("BOGUS"));
+    }
+    #[tokio::test]
+    async fn test_dead_process_reported_by_get_links() {
+        use crate::api::{ProxyApiImpl, ProxyApiServer, LINKS_REASON_NODE_PROCESS_DEAD};
+        use crate::shared_types::{InputPort, WorkdirUserConfig};
+
+        // The faucet died while the localnet is started.
+        const FIXTURE_FAUCET_DEAD: &str = "localnet DEGRADED\n\
+            ---\n\
+            Localnet process : OK ( pid 1234 ) http://0.0.0.0:9000\n\
+            Faucet process   : DOWN\n\
+            Proxy server     : OK ( pid 1236 ) http://localhost:44340\n\
+            Multi-link RPC   : OK\n";
+
+        let globals = Globals::new();
+        {
+            let mut config = WorkdirUserConfig::new();
+            config
+                .load_and_merge_from_str("proxy_enabled: true\n", "snippet")
+                .unwrap();
+            let mut input_port = InputPort::new(0, "localnet".to_string(), &config);
+            input_port.set_user_request_start(true);
+            let mut proxy_guard = globals.proxy.write().await;
+            proxy_guard.input_ports.push(input_port).unwrap();
+        }
+        {
+            let mut status_guard = globals.get_status(0).write().await;
+            let status = &mut *status_guard;
+            let mut resp = WorkdirStatusResponse::new();
+            parse_status_output(FIXTURE_FAUCET_DEAD, "localnet", None, &mut resp);
+            PollingTraitObject::apply_status_transition("localnet", &mut status.status, &mut resp);
+            status.ui = Some(Versioned::new(resp));
+        }
+
+        let (admctrl_tx, _admctrl_rx) = tokio::sync::mpsc::channel(10);
+        let api =
+            ProxyApiImpl::new(globals.proxy.clone(), admctrl_tx).with_workdirs_status(&globals);
+        let resp = api
+            .get_links("localnet".to_string(), None, None, None, None, None)
+            .await
+            .unwrap();
+        let reasons = resp.summary.unwrap().reasons.unwrap();
+        let reason = reasons
+            .iter()
+            .find(|reason| reason.kind == LINKS_REASON_NODE_PROCESS_DEAD)
+            .expect("no node_process_dead reason");
+        assert_eq!(reason.detail, "Faucet process not running");
+        assert!(reason.since.is_some());
+    }
+}
@@ -424,6 +424,158 @@ impl SuiEvent {
     }
 }
 
+// Server-side filtering of the stored events (see getWorkdirEvents).
+//
+// A query is one filter or an array of filters (all must match):
+//   {"field": "sender", "equals": "0x..."}
+//   [{"field": "type", "equals": "0x...::m::Ev"}, {"field": "owner.id", "equals": "0x..."}]
+//
+// The EVENT_TOP_LEVEL_FIELDS are matched against the event itself, any other field
+// is looked up in its parsedJson (dotted name for nested objects). The comparison is
+// typed (e.g. Sui u64 are JSON strings, so "equals": "5" and not 5).
+pub const EVENTS_QUERY_MAX_RESULTS: usize = 500;
+pub const EVENTS_QUERY_MAX_FILTERS: usize = 8;
+const EVENT_TOP_LEVEL_FIELDS: [&str; 4] = ["sender", "type", "packageId", "transactionModule"];
+const EVENT_INDEXED_FIELDS: [&str; 2] = ["sender", "type"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFilter {
+    path: String, // JSON path in event_json (e.g. "$.parsedJson.owner").
+    value: rusqlite::types::Value,
+}
+
+impl EventFilter {
+    // The errors are user facing.
+    pub fn parse(filter: &serde_json::Value) -> Result<Self, String> {
+        let field = filter
+            .get("field")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("filter {} missing \"field\"", filter))?;
+        let valid_field = field
+            .split('.')
+            .all(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        if !valid_field {
+            return Err(format!("invalid filter field [{}]", field));
+        }
+        let path = if EVENT_TOP_LEVEL_FIELDS.contains(&field) {
+            format!("$.{}", field)
+        } else {
+            format!("$.parsedJson.{}", field)
+        };
+
+        let value = match filter.get("equals") {
+            Some(serde_json::Value::String(s)) => rusqlite::types::Value::Text(s.clone()),
+            // json_extract() returns 1/0 for true/false.
+            Some(serde_json::Value::Bool(b)) => rusqlite::types::Value::Integer(*b as i64),
+            Some(serde_json::Value::Number(n)) => {
+                if let Some(i) = n.as_i64() {
+                    rusqlite::types::Value::Integer(i)
+                } else if let Some(f) = n.as_f64() {
+                    rusqlite::types::Value::Real(f)
+                } else {
+                    return Err(format!("filter [{}] number out of range", field));
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "filter [{}] \"equals\" must be a string, number or bool",
+                    field
+                ))
+            }
+        };
+        Ok(Self { path, value })
+    }
+
+    // Accepts a single filter object or an array of them.
+    pub fn parse_filters(filters: &serde_json::Value) -> Result<Vec<Self>, String> {
+        let filters = match filters {
+            serde_json::Value::Array(filters) => filters.iter().collect::<Vec<_>>(),
+            filter => vec![filter],
+        };
+        if filters.is_empty() {
+            return Err("at least one filter is required".to_string());
+        }
+        if filters.len() > EVENTS_QUERY_MAX_FILTERS {
+            return Err(format!(
+                "too many filters (max {})",
+                EVENTS_QUERY_MAX_FILTERS
+            ));
+        }
+        filters.into_iter().map(Self::parse).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventQueryResult {
+    pub id: u64,
+    pub timestamp_ms: u64,
+    pub event_json: serde_json::Value,
+}
+
+impl SuiEvent {
+    // Indexes for the most common filters (must be the same expression as in query_events).
+    pub fn create_indexes(conn: &Connection, table_name: &str) -> rusqlite::Result<()> {
+        for field in EVENT_INDEXED_FIELDS {
+            let sql = format!(
+                "CREATE INDEX IF NOT EXISTS {0}_{1} ON {0} (json_extract(event_json, '$.{1}'))",
+                table_name, field
+            );
+            conn.execute(&sql, [])?;
+        }
+        Ok(())
+    }
+
+    // Events matching all the filters, in insertion order.
+    //
+    // Rejects a query matching more than EVENTS_QUERY_MAX_RESULTS events instead of
+    // truncating it (the caller would miss events without knowing).
+    pub fn query_events(
+        conn: &Connection,
+        table_name: &str,
+        filters: &[EventFilter],
+    ) -> Result<Vec<EventQueryResult>, String> {
+        if filters.is_empty() {
+            return Err("at least one filter is required".to_string());
+        }
+        // The paths are inlined (validated by EventFilter::parse) so that SQLite can
+        // match the expression of the indexes.
+        let conditions = filters
+            .iter()
+            .enumerate()
+            .map(|(i, f)| format!("json_extract(event_json, '{}') = ?{}", f.path, i + 1))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let sql = format!(
+            "SELECT id, timestamp, event_json FROM {} WHERE {} ORDER BY id LIMIT {}",
+            table_name,
+            conditions,
+            EVENTS_QUERY_MAX_RESULTS + 1
+        );
+        let query = || -> rusqlite::Result<Vec<(u64, u64, String)>> {
+            let mut stmt = conn.prepare(&sql)?;
+            let params = rusqlite::params_from_iter(filters.iter().map(|f| &f.value));
+            let rows = stmt.query_map(params, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect()
+        };
+        let rows = query().map_err(|e| format!("events query failed: {}", e))?;
+
+        if rows.len() > EVENTS_QUERY_MAX_RESULTS {
+            return Err(format!(
+                "more than {} events match, add filters to narrow the query (e.g. sender or type)",
+                EVENTS_QUERY_MAX_RESULTS
+            ));
+        }
+        Ok(rows
+            .into_iter()
+            .map(|(id, timestamp_ms, event_json)| EventQueryResult {
+                id,
+                timestamp_ms,
+                event_json: serde_json::from_str(&event_json).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
 // Schema: global variables.
 // This table have a single entry.
 const SCHEMA_VERSION: &str = "0.0.1";
@@ -806,17 +958,8 @@ impl DBWorkerThread {
                 "message": event_message,
             })
         } else {
-            let res = serde_json::to_string(&result_json);
-            let message = if let Ok(res) = res {
-                res
-            } else {
-                format!("Failed to stringify result_json {:?}", result_json)
-            };
-            // TODO Find a way to resolve the sender.
-            serde_json::json!({
-                "sender": "unknown",
-                "message": message,
-            })
+            // Stored as-is for the queries on its fields (see SuiEvent::query_events).
+            serde_json::Value::Object(result_json.clone())
         };
         // Stringify event_json and insert it in DB.
         let event_json = serde_json::to_string(&event_json);
@@ -837,6 +980,44 @@ impl DBWorkerThread {
         // TODO Broadcast the sequence number increment of this sui_event object to websocket users.
     }
 
+    // Response (a JSON string) is either {"events": [...]} or {"error": "..."}.
+    async fn process_query_sui_events(&mut self, msg: GenericChannelMsg) {
+        let resp_channel = if let Some(resp_channel) = msg.resp_channel {
+            resp_channel
+        } else {
+            log::error!("query_sui_events missing response channel");
+            return;
+        };
+
+        let result = match (&self.db.conn, &msg.data_json) {
+            (None, _) => Err("events database not opened".to_string()),
+            (_, None) => Err("at least one filter is required".to_string()),
+            (Some(conn), Some(filters)) => {
+                EventFilter::parse_filters(filters).and_then(|filters| {
+                    let table_name = format!("{}_sui_event_user_0", self.params.workdir_name);
+                    SuiEvent::query_events(conn, &table_name, &filters)
+                })
+            }
+        };
+        let resp = match result {
+            Ok(events) => {
+                let events = events
+                    .into_iter()
+                    .map(|event| {
+                        serde_json::json!({
+                            "id": event.id,
+                            "timestampMs": event.timestamp_ms,
+                            "event": event.event_json,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({ "events": events })
+            }
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        let _ = resp_channel.send(resp.to_string());
+    }
+
     async fn open_db(&mut self) -> bool {
         // Get copy of latest workdir info from globals.
         let workdir =
@@ -937,6 +1118,15 @@ impl DBWorkerThread {
                 log::error!("Failed to create {} user event table {:?}", workdir_name, e);
                 return false;
             }
+            let table_name = format!("{}_sui_event_{}", workdir_name, name_suffix);
+            if let Err(e) = SuiEvent::create_indexes(&conn, &table_name) {
+                log::error!(
+                    "Failed to create {} user event indexes {:?}",
+                    workdir_name,
+                    e
+                );
+                return false;
+            }
         }
 
         // All success. This is a good DB connection.
@@ -987,6 +1177,8 @@ impl DBWorkerThread {
                                 if let Some(command) = msg.command() {
                                     if command == "add_sui_event" {
                                         self.process_add_sui_event(msg).await;
+                                    } else if command == "query_sui_events" {
+                                        self.process_query_sui_events(msg).await;
                                     } else {
                                         log::error!("Received a EVENT_EXEC message with unexpected command {}", command);
                                     }
@@ -1009,3 +1201,181 @@ impl DBWorkerThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENDERS: [&str; 3] = ["0xa11ce", "0xb0b", "0xca201"];
+    const TYPES: [&str; 2] = ["0x2a::market::Listed", "0x2a::market::Sold"];
+
+    // 300 events with the fields cycling at different rates.
+    fn synthetic_events_table() -> (Connection, String) {
+        let conn = Connection::open_in_memory().unwrap();
+        SuiEvent::create_table(
+            &conn,
+            "localnet".to_string(),
+            None,
+            Some("user_0".to_string()),
+        )
+        .unwrap();
+        let table_name = "localnet_sui_event_user_0".to_string();
+        SuiEvent::create_indexes(&conn, &table_name).unwrap();
+
+        for i in 0..300u64 {
+            let event_json = serde_json::json!({
+                "packageId": "0x2a",
+                "transactionModule": "market",
+                "sender": SENDERS[(i % 3) as usize],
+                "type": TYPES[(i % 2) as usize],
+                "parsedJson": {
+                    "item": { "id": format!("0x{:x}", i % 10) },
+                    "price": (i % 7).to_string(),
+                    "quantity": i % 5,
+                    "is_auction": i % 4 == 0,
+                },
+                "timestampMs": (1_700_000_000_000 + i).to_string(),
+            });
+            conn.execute(
+                &format!(
+                    "INSERT INTO {} (package_instance_id, timestamp, event_json)
+                    VALUES (1, ?1, ?2)",
+                    table_name
+                ),
+                rusqlite::params![1_700_000_000_000 + i, event_json.to_string()],
+            )
+            .unwrap();
+        }
+        (conn, table_name)
+    }
+
+    fn query_ids(conn: &Connection, table_name: &str, filters: serde_json::Value) -> Vec<u64> {
+        let filters = EventFilter::parse_filters(&filters).unwrap();
+        SuiEvent::query_events(conn, table_name, &filters)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect()
+    }
+
+    // ids are 1-based (AUTOINCREMENT), event i has the id i+1.
+    fn expected_ids(matches: impl Fn(u64) -> bool) -> Vec<u64> {
+        (0..300u64).filter(|i| matches(*i)).map(|i| i + 1).collect()
+    }
+
+    #[test]
+    fn test_query_events() {
+        let (conn, table_name) = synthetic_events_table();
+
+        // Top-level fields.
+        let ids = query_ids(
+            &conn,
+            &table_name,
+            serde_json::json!({"field": "sender", "equals": "0xb0b"}),
+        );
+        assert_eq!(ids, expected_ids(|i| i % 3 == 1));
+
+        // ANDed filters, on parsedJson (nested and typed fields).
+        let ids = query_ids(
+            &conn,
+            &table_name,
+            serde_json::json!([
+                {"field": "type", "equals": "0x2a::market::Sold"},
+                {"field": "item.id", "equals": "0x3"},
+            ]),
+        );
+        assert_eq!(ids, expected_ids(|i| i % 2 == 1 && i % 10 == 3));
+
+        let ids = query_ids(
+            &conn,
+            &table_name,
+            serde_json::json!([
+                {"field": "price", "equals": "6"},
+                {"field": "quantity", "equals": 2},
+                {"field": "is_auction", "equals": true},
+            ]),
+        );
+        assert_eq!(
+            ids,
+            expected_ids(|i| i % 7 == 6 && i % 5 == 2 && i % 4 == 0)
+        );
+        assert!(!ids.is_empty());
+
+        // Typed comparison: the price is a string.
+        let ids = query_ids(
+            &conn,
+            &table_name,
+            serde_json::json!({"field": "price", "equals": 6}),
+        );
+        assert!(ids.is_empty());
+
+        // Unknown field.
+        let ids = query_ids(
+            &conn,
+            &table_name,
+            serde_json::json!({"field": "seller", "equals": "0xb0b"}),
+        );
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn test_query_events_too_broad() {
+        let (conn, table_name) = synthetic_events_table();
+        let filters = EventFilter::parse_filters(
+            &serde_json::json!({"field": "packageId", "equals": "0x2a"}),
+        )
+        .unwrap();
+        let err = SuiEvent::query_events(&conn, &table_name, &filters).unwrap_err();
+        assert!(err.contains("narrow"), "{}", err);
+
+        assert!(SuiEvent::query_events(&conn, &table_name, &[]).is_err());
+    }
+
+    #[test]
+    fn test_query_events_uses_index() {
+        let (conn, table_name) = synthetic_events_table();
+        let sql = format!(
+            "EXPLAIN QUERY PLAN SELECT id FROM {} WHERE json_extract(event_json, '$.sender') = ?1",
+            table_name
+        );
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let plan: Vec<String> = stmt
+            .query_map(["0xb0b"], |row| row.get(3))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(
+            plan.iter()
+                .any(|step| step.contains(&format!("{}_sender", table_name))),
+            "{:?}",
+            plan
+        );
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        for filters in [
+            serde_json::json!([]),
+            serde_json::json!({"equals": "0x1"}),
+            serde_json::json!({"field": "sender"}),
+            serde_json::json!({"field": "sender", "equals": null}),
+            serde_json::json!({"field": "sender", "equals": {"a": 1}}),
+            serde_json::json!({"field": "a..b", "equals": "x"}),
+            serde_json::json!({"field": "a') OR 1=1 --", "equals": "x"}),
+            serde_json::Value::Array(vec![
+                serde_json::json!({"field": "sender", "equals": "0x1"});
+                EVENTS_QUERY_MAX_FILTERS + 1
+            ]),
+        ] {
+            assert!(EventFilter::parse_filters(&filters).is_err(), "{}", filters);
+        }
+
+        let filters = EventFilter::parse_filters(&serde_json::json!([
+            {"field": "sender", "equals": "0x1"},
+            {"field": "owner.id", "equals": "0x2"},
+        ]))
+        .unwrap();
+        assert_eq!(filters[0].path, "$.sender");
+        assert_eq!(filters[1].path, "$.parsedJson.owner.id");
+    }
+}
@@ -6,23 +6,103 @@
 //
 // The events_worker is responsible to subscribe/unsubscribe events, filter them
 // and forward the validated data to this events_writer_worker parent.
+//
+// The events missed by the subscription (e.g. emitted while reconnecting) are recovered
+// here with a backfill from the high-water mark of each package (see events_sync.rs).
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
-    shared_types::{self, Globals},
-    workers::{DBWorker, DBWorkerParams, WebSocketWorker, WebSocketWorkerParams},
+    api::PackageEventsSync,
+    shared_types::{
+        self, scan_backfill_page, unix_time_ms, BackfillScan, EventId, EventsDedup,
+        EventsHighWaterMark, Globals, EVENTS_BACKFILL_INTERVAL_SECS, EVENTS_BACKFILL_MAX_PAGES,
+        EVENTS_BACKFILL_PAGE_SIZE, EVENTS_DEDUP_CAPACITY, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET,
+        WORKDIR_IDX_MAINNET, WORKDIR_IDX_TESTNET,
+    },
+    workers::{websocket_url, DBWorker, DBWorkerParams, WebSocketWorker, WebSocketWorkerParams},
 };
 
-use common::basic_types::{
-    self, AutoThread, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx, MPSC_Q_SIZE,
+use common::{
+    basic_types::{
+        self, AutoThread, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx,
+        MPSC_Q_SIZE,
+    },
+    log_safe_warn,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use axum::async_trait;
+use serde_json::Value as JsonValue;
 
 use tokio::sync::{mpsc::Sender, Mutex};
 use tokio_graceful_shutdown::{FutureExt, SubsystemBuilder, SubsystemHandle};
 
+const BACKFILL_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// JSON-RPC server queried for the backfill (same fullnode as websocket_url).
+pub fn rpc_url(workdir_idx: WorkdirIdx) -> Option<&'static str> {
+    match workdir_idx {
+        WORKDIR_IDX_LOCALNET => Some("http://localhost:9000"),
+        WORKDIR_IDX_DEVNET => Some("https://fullnode.devnet.sui.io:443"),
+        WORKDIR_IDX_TESTNET => Some("https://fullnode.testnet.sui.io:443"),
+        WORKDIR_IDX_MAINNET => Some("https://fullnode.mainnet.sui.io:443"),
+        _ => None,
+    }
+}
+
+// Delivery state of one package, kept across the restarts of the thread.
+#[derive(Debug, Clone, Default)]
+struct PackageEventsTracking {
+    package_uuid: String,
+    package_name: String,
+    high_water_mark: Option<EventsHighWaterMark>,
+    latest_checkpoint: Option<u64>,
+    backfill_pending: bool,
+    last_backfill: Option<tokio::time::Instant>,
+    last_backfill_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_backfill_recovered: u64,
+    last_backfill_truncated: bool,
+    last_backfill_error: Option<String>,
+    events_recovered: u64,
+    duplicates_dropped: u64,
+}
+
+impl PackageEventsTracking {
+    fn is_backfill_due(&self) -> bool {
+        match self.last_backfill {
+            Some(last) if !self.backfill_pending => {
+                last.elapsed() >= Duration::from_secs(EVENTS_BACKFILL_INTERVAL_SECS)
+            }
+            _ => true,
+        }
+    }
+
+    fn to_events_sync(&self, package_id: &str) -> PackageEventsSync {
+        let hwm = self.high_water_mark.as_ref();
+        let hwm_event_id = hwm.and_then(|hwm| hwm.event_id.as_ref());
+        PackageEventsSync {
+            package_id: format!("0x{}", package_id),
+            package_name: self.package_name.clone(),
+            hwm_checkpoint: hwm.map(|hwm| hwm.checkpoint),
+            hwm_tx_digest: hwm_event_id.map(|id| id.tx_digest.clone()),
+            hwm_event_seq: hwm_event_id.map(|id| id.event_seq),
+            lag_checkpoints: self
+                .latest_checkpoint
+                .zip(hwm)
+                .map(|(latest, hwm)| latest.saturating_sub(hwm.checkpoint)),
+            backfill_pending: self.backfill_pending,
+            last_backfill_at: self.last_backfill_at.map(|time| time.to_rfc3339()),
+            last_backfill_recovered: self.last_backfill_recovered,
+            last_backfill_truncated: self.last_backfill_truncated,
+            last_backfill_error: self.last_backfill_error.clone(),
+            events_recovered: self.events_recovered,
+            duplicates_dropped: self.duplicates_dropped,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EventsWriterWorkerParams {
     globals: Globals,
@@ -30,6 +110,12 @@ pub struct EventsWriterWorkerParams {
     event_tx: GenericTx,
     workdir_idx: WorkdirIdx,
     workdir_name: String,
+    // rpc_url() and websocket_url(), except for the tests (local servers).
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    // Key is the package_id (no 0x).
+    packages: Arc<Mutex<HashMap<String, PackageEventsTracking>>>,
+    dedup: Arc<Mutex<EventsDedup>>,
 }
 
 impl EventsWriterWorkerParams {
@@ -48,6 +134,10 @@ impl EventsWriterWorkerParams {
             event_tx,
             workdir_idx,
             workdir_name,
+            rpc_url: rpc_url(workdir_idx).map(str::to_string),
+            ws_url: websocket_url(workdir_idx).map(str::to_string),
+            packages: Arc::new(Mutex::new(HashMap::new())),
+            dedup: Arc::new(Mutex::new(EventsDedup::new(EVENTS_DEDUP_CAPACITY))),
         }
     }
 }
@@ -58,8 +148,14 @@ pub struct EventsWriterWorker {
 
 impl EventsWriterWorker {
     pub fn new(params: EventsWriterWorkerParams) -> Self {
+        // Restarts are also reported for the workdir (see getDaemonStats).
+        let on_restart = params
+            .globals
+            .worker_restarts
+            .on_restart(params.workdir_idx);
         Self {
-            auto_thread: AutoThread::new("EventsWriter".to_string(), params),
+            auto_thread: AutoThread::new("EventsWriter".to_string(), params)
+                .with_on_restart(on_restart),
         }
     }
 
@@ -73,6 +169,7 @@ struct EventsWriterThread {
     params: EventsWriterWorkerParams,
     ws_workers_channel: Vec<Sender<GenericChannelMsg>>,
     db_worker_channel: Option<Sender<GenericChannelMsg>>,
+    client: reqwest::Client,
 }
 
 #[async_trait]
@@ -83,6 +180,7 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
             params,
             ws_workers_channel: Vec::new(),
             db_worker_channel: None,
+            client: reqwest::Client::new(),
         }
     }
 
@@ -101,6 +199,7 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
             worker_tx.clone(),
             self.params.event_tx.clone(),
             self.params.workdir_idx,
+            self.params.ws_url.clone(),
         );
         let ws_worker = WebSocketWorker::new(ws_worker_params);
         subsys.start(SubsystemBuilder::new("ws-worker", |a| ws_worker.run(a)));
@@ -159,7 +258,7 @@ impl EventsWriterThread {
                 params: msg.params,
                 data_json: msg.data_json,
                 workdir_idx: msg.workdir_idx,
-                resp_channel: None,
+                resp_channel: msg.resp_channel,
             };
             let _ = tx.send(forward_msg).await;
         }
@@ -167,6 +266,7 @@ impl EventsWriterThread {
 
     async fn process_audit_msg(&mut self, msg: GenericChannelMsg) {
         self.forward_to_children(msg).await;
+        self.audit_events_sync().await;
     }
 
     async fn process_update_msg(&mut self, msg: GenericChannelMsg) {
@@ -174,9 +274,328 @@ impl EventsWriterThread {
     }
 
     async fn process_add_sui_event(&mut self, msg: GenericChannelMsg) {
-        // TODO Dedup logic to be done here, for now just forward everything since
-        //      suibase currently support only a single websocket worker per workdir.
+        self.write_sui_event(msg).await;
+    }
+
+    // Forward to the DBWorker, unless already written. Returns true when forwarded.
+    //
+    // Events from the subscription and from the backfill all go through here.
+    async fn write_sui_event(&mut self, msg: GenericChannelMsg) -> bool {
+        let result = msg
+            .data_json
+            .as_ref()
+            .map(|data_json| &data_json["params"]["result"]);
+        if let Some(event_id) = result.and_then(EventId::from_event) {
+            if !self.params.dedup.lock().await.insert(&event_id) {
+                let package_id = result
+                    .and_then(|result| result["packageId"].as_str())
+                    .unwrap_or_default()
+                    .trim_start_matches("0x");
+                if let Some(tracking) = self.params.packages.lock().await.get_mut(package_id) {
+                    tracking.duplicates_dropped += 1;
+                }
+                return false;
+            }
+        }
+        // Without an ID, let the DBWorker validate (and report) it.
         self.forward_to_db_worker(msg).await;
+        true
+    }
+
+    // A (re)subscription to a package by the WebSocketWorker.
+    async fn process_sui_events_subscribed(&mut self, msg: GenericChannelMsg) {
+        let (package_uuid, package_name, package_id) = match (
+            msg.params(0),
+            msg.params(1),
+            msg.params(2).filter(|package_id| !package_id.is_empty()),
+        ) {
+            (Some(package_uuid), Some(package_name), Some(package_id)) => {
+                (package_uuid, package_name, package_id)
+            }
+            _ => {
+                log::error!("sui_events_subscribed missing params {:?}", msg);
+                return;
+            }
+        };
+        {
+            let mut packages = self.params.packages.lock().await;
+            let tracking = packages.entry(package_id.clone()).or_default();
+            tracking.package_uuid = package_uuid;
+            tracking.package_name = package_name;
+            // Anything emitted while not subscribed is missing.
+            tracking.backfill_pending = true;
+        }
+        self.backfill(&package_id).await;
+        self.publish_events_sync().await;
+    }
+
+    async fn audit_events_sync(&mut self) {
+        // Forget the packages no longer the most recent (same as the subscriptions).
+        let most_recent: Option<HashSet<String>> = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_packages(self.params.workdir_idx)
+                .read()
+                .await;
+            globals_read_guard.ui.as_ref().map(|ui| {
+                ui.get_data()
+                    .iter_most_recent_package_instance()
+                    .map(|latest| latest.get_package_id().to_string())
+                    .collect()
+            })
+        };
+        let due = {
+            let mut packages = self.params.packages.lock().await;
+            if let Some(most_recent) = most_recent {
+                packages.retain(|package_id, _| most_recent.contains(package_id));
+            }
+            packages
+                .iter()
+                .filter(|(_, tracking)| tracking.is_backfill_due())
+                .map(|(package_id, _)| package_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        if !due.is_empty() && self.backfill_window_secs().await != 0 {
+            for package_id in due {
+                self.backfill(&package_id).await;
+            }
+        } else if !self.params.packages.lock().await.is_empty() {
+            // Still update the lag.
+            if let Ok(latest_checkpoint) = self.latest_checkpoint().await {
+                for tracking in self.params.packages.lock().await.values_mut() {
+                    tracking.latest_checkpoint = Some(latest_checkpoint);
+                }
+            }
+        }
+        self.publish_events_sync().await;
+    }
+
+    async fn backfill_window_secs(&self) -> u64 {
+        self.params
+            .globals
+            .events_sync
+            .read()
+            .await
+            .backfill_window_secs(self.params.workdir_idx)
+    }
+
+    // Write the events more recent than the high-water mark of the package, and then
+    // move the high-water mark.
+    async fn backfill(&mut self, package_id: &str) {
+        let window_secs = self.backfill_window_secs().await;
+        if window_secs == 0 {
+            return;
+        }
+        let (package_uuid, package_name, hwm_event_id) = {
+            let packages = self.params.packages.lock().await;
+            match packages.get(package_id) {
+                Some(tracking) => (
+                    tracking.package_uuid.clone(),
+                    tracking.package_name.clone(),
+                    tracking
+                        .high_water_mark
+                        .as_ref()
+                        .and_then(|hwm| hwm.event_id.clone()),
+                ),
+                None => return,
+            }
+        };
+
+        let queried = self
+            .query_backfill(package_id, hwm_event_id.as_ref(), window_secs)
+            .await;
+
+        let (checkpoint, events, truncated) = match queried {
+            Ok(queried) => queried,
+            Err(e) => {
+                log_safe_warn!(
+                    "{} backfill of {} failed: {}",
+                    self.params.workdir_name,
+                    package_name,
+                    e
+                );
+                let mut packages = self.params.packages.lock().await;
+                if let Some(tracking) = packages.get_mut(package_id) {
+                    tracking.last_backfill = Some(tokio::time::Instant::now());
+                    tracking.last_backfill_error = Some(e.to_string());
+                }
+                return;
+            }
+        };
+
+        // Oldest first.
+        let newest_event_id = events.first().and_then(EventId::from_event);
+        let mut recovered = 0;
+        for event in events.into_iter().rev() {
+            let msg = Self::add_sui_event_msg(
+                self.params.workdir_idx,
+                &package_uuid,
+                &package_name,
+                event,
+            );
+            if self.write_sui_event(msg).await {
+                recovered += 1;
+            }
+        }
+        if recovered != 0 {
+            log::info!(
+                "{} recovered {} events of {} missed by the subscription",
+                self.params.workdir_name,
+                recovered,
+                package_name
+            );
+        }
+        if truncated {
+            log::warn!(
+                "{} backfill of {} limited to the last {} secs",
+                self.params.workdir_name,
+                package_name,
+                window_secs
+            );
+        }
+
+        let mut packages = self.params.packages.lock().await;
+        if let Some(tracking) = packages.get_mut(package_id) {
+            let event_id = newest_event_id.or_else(|| {
+                tracking
+                    .high_water_mark
+                    .as_ref()
+                    .and_then(|hwm| hwm.event_id.clone())
+            });
+            tracking.high_water_mark = Some(EventsHighWaterMark {
+                checkpoint,
+                event_id,
+            });
+            tracking.latest_checkpoint = Some(checkpoint);
+            tracking.backfill_pending = false;
+            tracking.last_backfill = Some(tokio::time::Instant::now());
+            tracking.last_backfill_at = Some(chrono::Utc::now());
+            tracking.last_backfill_recovered = recovered;
+            tracking.last_backfill_truncated = truncated;
+            tracking.last_backfill_error = None;
+            tracking.events_recovered += recovered;
+        }
+    }
+
+    // The latest checkpoint, and the events (most recent first) more recent than the
+    // high-water mark. The bool is set when stopped by the window.
+    async fn query_backfill(
+        &self,
+        package_id: &str,
+        hwm_event_id: Option<&EventId>,
+        window_secs: u64,
+    ) -> Result<(u64, Vec<JsonValue>, bool)> {
+        // Before the events, so nothing is missed up to that checkpoint.
+        let checkpoint = self.latest_checkpoint().await?;
+        let oldest_ms = unix_time_ms().saturating_sub(window_secs.saturating_mul(1000));
+        let filter = serde_json::json!({ "Package": format!("0x{}", package_id) });
+        let mut cursor = JsonValue::Null;
+        let mut events = Vec::new();
+        for _ in 0..EVENTS_BACKFILL_MAX_PAGES {
+            let params = serde_json::json!([filter, cursor, EVENTS_BACKFILL_PAGE_SIZE, true]);
+            let page = self.call("suix_queryEvents", params).await?;
+            let data = page["data"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let has_next_page =
+                page["hasNextPage"].as_bool().unwrap_or(false) && !page["nextCursor"].is_null();
+            match scan_backfill_page(data, has_next_page, hwm_event_id, oldest_ms, &mut events) {
+                BackfillScan::Continue => cursor = page["nextCursor"].clone(),
+                BackfillScan::ReachedWindow => return Ok((checkpoint, events, true)),
+                BackfillScan::ReachedHighWaterMark | BackfillScan::ReachedFirstEvent => {
+                    return Ok((checkpoint, events, false))
+                }
+            }
+        }
+        Ok((checkpoint, events, true))
+    }
+
+    async fn latest_checkpoint(&self) -> Result<u64> {
+        let result = self
+            .call(
+                "sui_getLatestCheckpointSequenceNumber",
+                serde_json::json!([]),
+            )
+            .await?;
+        match &result {
+            JsonValue::String(value) => value.parse::<u64>().ok(),
+            value => value.as_u64(),
+        }
+        .ok_or_else(|| anyhow!("unexpected checkpoint {}", result))
+    }
+
+    // The "result" of a JSON-RPC call.
+    async fn call(&self, method: &str, params: JsonValue) -> Result<JsonValue> {
+        let rpc_url = self
+            .params
+            .rpc_url
+            .as_deref()
+            .ok_or_else(|| anyhow!("no RPC server for {}", self.params.workdir_name))?;
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let resp: JsonValue = self
+            .client
+            .post(rpc_url)
+            .timeout(BACKFILL_REQUEST_TIMEOUT)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        match resp.get("result") {
+            Some(result) if !result.is_null() => Ok(result.clone()),
+            _ => Err(anyhow!("{} unexpected response: {}", method, resp)),
+        }
+    }
+
+    // Same message as an event received from the subscription.
+    fn add_sui_event_msg(
+        workdir_idx: WorkdirIdx,
+        package_uuid: &str,
+        package_name: &str,
+        event: JsonValue,
+    ) -> GenericChannelMsg {
+        let data_json = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "suix_subscribeEvent",
+            "params": { "subscription": 0, "result": event },
+        });
+        GenericChannelMsg {
+            event_id: basic_types::EVENT_EXEC,
+            command: Some("add_sui_event".to_string()),
+            params: vec![package_uuid.to_string(), package_name.to_string()],
+            data_json: Some(data_json),
+            workdir_idx: Some(workdir_idx),
+            resp_channel: None,
+        }
+    }
+
+    async fn publish_events_sync(&mut self) {
+        let mut events_sync = self
+            .params
+            .packages
+            .lock()
+            .await
+            .iter()
+            .map(|(package_id, tracking)| tracking.to_events_sync(package_id))
+            .collect::<Vec<_>>();
+        events_sync.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+        self.params
+            .globals
+            .events_sync
+            .write()
+            .await
+            .set_packages(self.params.workdir_idx, events_sync);
     }
 
     async fn event_loop(&mut self, subsys: &SubsystemHandle) {
@@ -200,6 +619,11 @@ impl EventsWriterThread {
                         if let Some(command) = msg.command() {
                             if command == "add_sui_event" {
                                 self.process_add_sui_event(msg).await;
+                            } else if command == "sui_events_subscribed" {
+                                self.process_sui_events_subscribed(msg).await;
+                            } else if command == "query_sui_events" {
+                                // Answered by the DBWorker (through the resp_channel).
+                                self.forward_to_db_worker(msg).await;
                             } else {
                                 log::error!(
                                     "Received a EVENT_EXEC message with unexpected command {}",
@@ -223,3 +647,279 @@ impl EventsWriterThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{extract::State, routing::post, Json, Router};
+    use futures::{SinkExt, StreamExt};
+    use serde_json::json;
+    use tokio_graceful_shutdown::Toplevel;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::api::{PackageInstance, Versioned, WorkdirPackagesResponse};
+    use crate::shared_types::{GlobalsWorkdirsST, PackagePath};
+
+    const PACKAGE_ID: &str = "e0654f522ae3cb1a364174f740275d57f5a87b430d669c5a0554b975af683b08";
+    const SUBSCRIPTION_NUMBER: u64 = 42;
+
+    static SUBSCRIBE_COUNT: AtomicU32 = AtomicU32::new(0);
+
+    // Events of the package on the "chain", oldest first.
+    type Chain = Arc<std::sync::Mutex<Vec<JsonValue>>>;
+
+    fn event_type() -> String {
+        format!("0x{}::counter::Changed", PACKAGE_ID)
+    }
+
+    fn sui_event(n: u64) -> JsonValue {
+        json!({
+            "id": { "txDigest": format!("tx{}", n), "eventSeq": "0" },
+            "packageId": format!("0x{}", PACKAGE_ID),
+            "transactionModule": "counter",
+            "sender": "0xf7ae",
+            "type": event_type(),
+            "parsedJson": { "count": n.to_string() },
+            "timestampMs": unix_time_ms().to_string()
+        })
+    }
+
+    // Fullnode JSON-RPC stub (suix_queryEvents is most recent first).
+    async fn rpc_stub(State(chain): State<Chain>, Json(req): Json<JsonValue>) -> Json<JsonValue> {
+        let chain = chain.lock().unwrap().clone();
+        let result = match req["method"].as_str() {
+            Some("sui_getLatestCheckpointSequenceNumber") => {
+                json!((1000 + chain.len()).to_string())
+            }
+            Some("suix_queryEvents") => {
+                let (cursor, limit) = (&req["params"][1], req["params"][2].as_u64().unwrap());
+                let mut events: Vec<JsonValue> = chain.into_iter().rev().collect();
+                if !cursor.is_null() {
+                    let start = events.iter().position(|e| &e["id"] == cursor).unwrap() + 1;
+                    events.drain(..start);
+                }
+                let has_next_page = events.len() > limit as usize;
+                events.truncate(limit as usize);
+                let next_cursor = events.last().map(|e| e["id"].clone());
+                json!({ "data": events, "nextCursor": next_cursor, "hasNextPage": has_next_page })
+            }
+            _ => JsonValue::Null,
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": req["id"], "result": result }))
+    }
+
+    // Fake websocket server: confirms the subscriptions and pushes the live events.
+    async fn ws_server(
+        listener: tokio::net::TcpListener,
+        live: tokio::sync::broadcast::Sender<JsonValue>,
+    ) {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut ws = match tokio_tungstenite::accept_async(stream).await {
+                Ok(ws) => ws,
+                Err(_) => continue,
+            };
+            let mut live_rx = live.subscribe();
+            loop {
+                tokio::select! {
+                    msg = ws.next() => match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            let req: JsonValue = serde_json::from_str(&text).unwrap();
+                            if req["method"] == "suix_subscribeEvent" {
+                                SUBSCRIBE_COUNT.fetch_add(1, Ordering::SeqCst);
+                                let resp = json!({
+                                    "jsonrpc": "2.0", "id": req["id"], "result": SUBSCRIPTION_NUMBER
+                                });
+                                let _ = ws.send(Message::Text(resp.to_string())).await;
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                        _ => break,
+                    },
+                    event = live_rx.recv() => match event {
+                        Ok(event) => {
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": "suix_subscribeEvent",
+                                "params": { "subscription": SUBSCRIPTION_NUMBER, "result": event }
+                            });
+                            let _ = ws.send(Message::Text(notification.to_string())).await;
+                        }
+                        Err(_) => break,
+                    },
+                }
+            }
+        }
+    }
+
+    // As done periodically by the AdminController.
+    async fn audit(events_tx: &GenericTx) {
+        let mut msg = GenericChannelMsg::new();
+        msg.event_id = basic_types::EVENT_AUDIT;
+        msg.workdir_idx = Some(WORKDIR_IDX_LOCALNET);
+        events_tx.send(msg).await.unwrap();
+    }
+
+    // txDigest of the events in the DB, in the order written.
+    async fn stored_events(events_tx: &GenericTx) -> Vec<String> {
+        let (resp_tx, resp_rx) = tokio::sync::oneshot::channel();
+        let mut msg = GenericChannelMsg::new();
+        msg.event_id = basic_types::EVENT_EXEC;
+        msg.command = Some("query_sui_events".to_string());
+        msg.data_json = Some(json!({ "field": "type", "equals": event_type() }));
+        msg.workdir_idx = Some(WORKDIR_IDX_LOCALNET);
+        msg.resp_channel = Some(resp_tx);
+        events_tx.send(msg).await.unwrap();
+        let resp: JsonValue = serde_json::from_str(&resp_rx.await.unwrap()).unwrap();
+        resp["events"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|event| {
+                event["event"]["id"]["txDigest"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    async fn audit_until<F: std::future::Future<Output = bool>>(
+        events_tx: &GenericTx,
+        condition: impl Fn() -> F,
+    ) -> bool {
+        for _ in 0..200 {
+            if condition().await {
+                return true;
+            }
+            audit(events_tx).await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_backfill_after_reconnect() {
+        let dir = std::env::temp_dir().join(format!("sbsd-events-backfill-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let globals = Globals::new();
+        *globals.workdirs.write().await = GlobalsWorkdirsST::with_suibase_home(&dir);
+        {
+            let mut resp = WorkdirPackagesResponse::new();
+            let path = PackagePath::new(
+                "demo".to_string(),
+                "DEMOUUID".to_string(),
+                "1703895010111".to_string(),
+            );
+            resp.add_package_instance(PackageInstance::new(PACKAGE_ID.to_string(), path), None);
+            let mut packages_guard = globals.get_packages(WORKDIR_IDX_LOCALNET).write().await;
+            packages_guard.ui = Some(Versioned::new(resp));
+        }
+
+        let chain: Chain = Arc::default();
+        let app = Router::new()
+            .route("/", post(rpc_stub))
+            .with_state(chain.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let rpc_addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let (live_tx, _) = tokio::sync::broadcast::channel(16);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_addr = listener.local_addr().unwrap();
+        let ws_task = tokio::spawn(ws_server(listener, live_tx.clone()));
+
+        let (events_tx, events_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let mut params = EventsWriterWorkerParams::new(
+            globals.clone(),
+            events_rx,
+            events_tx.clone(),
+            WORKDIR_IDX_LOCALNET,
+        );
+        params.rpc_url = Some(format!("http://{}", rpc_addr));
+        params.ws_url = Some(format!("ws://{}", ws_addr));
+        let worker = EventsWriterWorker::new(params);
+        let toplevel = tokio::spawn(
+            Toplevel::new(move |s| async move {
+                s.start(SubsystemBuilder::new("events-writer", |a| worker.run(a)));
+            })
+            .handle_shutdown_requests(Duration::from_millis(1000)),
+        );
+
+        // Subscribed, with a first high-water mark (nothing emitted yet).
+        let events_sync = || async {
+            globals
+                .events_sync
+                .read()
+                .await
+                .get_packages(WORKDIR_IDX_LOCALNET)
+        };
+        let subscribed = audit_until(&events_tx, || async {
+            events_sync().await.first().map(|sync| sync.hwm_checkpoint) == Some(Some(1000))
+        })
+        .await;
+        assert!(subscribed);
+
+        // Live events.
+        for n in 1..=3 {
+            chain.lock().unwrap().push(sui_event(n));
+            live_tx.send(sui_event(n)).unwrap();
+        }
+        let delivered = audit_until(&events_tx, || async {
+            stored_events(&events_tx).await.len() == 3
+        })
+        .await;
+        assert!(delivered);
+
+        // Outage of the websocket, while more events are emitted.
+        ws_task.abort();
+        let _ = ws_task.await;
+        for n in 4..=6 {
+            chain.lock().unwrap().push(sui_event(n));
+        }
+        let subscribe_count = SUBSCRIBE_COUNT.load(Ordering::SeqCst);
+        let listener = tokio::net::TcpListener::bind(ws_addr).await.unwrap();
+        let ws_task = tokio::spawn(ws_server(listener, live_tx.clone()));
+
+        // Recovered after the reconnection, and a new live event.
+        let recovered = audit_until(&events_tx, || async {
+            SUBSCRIBE_COUNT.load(Ordering::SeqCst) > subscribe_count
+                && stored_events(&events_tx).await.len() >= 6
+        })
+        .await;
+        assert!(recovered);
+        chain.lock().unwrap().push(sui_event(7));
+        live_tx.send(sui_event(7)).unwrap();
+        let delivered = audit_until(&events_tx, || async {
+            stored_events(&events_tx).await.len() >= 7
+        })
+        .await;
+        assert!(delivered);
+
+        // Each exactly once.
+        audit(&events_tx).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let expected: Vec<String> = (1..=7).map(|n| format!("tx{}", n)).collect();
+        assert_eq!(stored_events(&events_tx).await, expected);
+
+        let sync = events_sync().await;
+        assert_eq!(sync.len(), 1);
+        assert_eq!(sync[0].package_id, format!("0x{}", PACKAGE_ID));
+        assert_eq!(sync[0].hwm_checkpoint, Some(1006));
+        assert_eq!(sync[0].hwm_tx_digest.as_deref(), Some("tx6"));
+        assert_eq!(sync[0].last_backfill_recovered, 3);
+        assert_eq!(sync[0].events_recovered, 3);
+        assert_eq!(sync[0].duplicates_dropped, 3);
+        assert!(!sync[0].backfill_pending);
+
+        toplevel.abort();
+        ws_task.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -16,15 +16,18 @@ pub(crate) use self::cli_poller::*;
 pub(crate) use self::db_worker::*;
 pub(crate) use self::events_writer_worker::*;
 pub(crate) use self::packages_poller::*;
-pub(crate) use self::request_worker::*;
+pub(crate) use self::system_values_poller::*;
+pub(crate) use self::webhook_worker::*;
 pub(crate) use self::webserver::*;
 pub(crate) use self::websocket_worker::*;
 
+mod cli_output_parser;
 mod cli_poller;
 mod db_worker;
 mod events_writer_worker;
 mod log_worker;
 mod packages_poller;
-mod request_worker;
+mod system_values_poller;
+mod webhook_worker;
 mod webserver;
 mod websocket_worker;
@@ -13,14 +13,14 @@ use common::{
     basic_types::{Instantiable, WorkdirContext, WorkdirIdx},
     log_safe,
     shared_types::WORKDIRS_KEYS,
-    workers::{PollerWorker, PollingTrait},
+    workers::{PollerSchedule, PollerWorker, PollingTrait},
 };
 use std::collections::HashSet;
 use std::path::PathBuf;
 
 use crate::{
     api::{PackageInstance, SuiObjectInstance, SuiObjectType},
-    shared_types::{Globals, PackagePath},
+    shared_types::{Globals, PackagePath, WebhookEvent, WebhookEventType, WebhookTx},
 };
 
 use anyhow::Result;
@@ -28,12 +28,14 @@ use anyhow::Result;
 use axum::async_trait;
 use common::basic_types::{self, GenericChannelMsg, GenericTx};
 
+use tokio::time::Duration;
 use tokio_graceful_shutdown::SubsystemHandle;
 
 #[derive(Clone)]
 pub struct PackagesPollerParams {
     globals: Globals,
     sui_events_worker_tx: Option<GenericTx>, // To send messages to related Sui event worker.
+    webhook_tx: WebhookTx,                   // To notify newly published packages.
     workdir_idx: WorkdirIdx,
 }
 
@@ -47,11 +49,13 @@ impl PackagesPollerParams {
     pub fn new(
         globals: Globals,
         sui_events_worker_tx: Option<GenericTx>,
+        webhook_tx: WebhookTx,
         workdir_idx: WorkdirIdx,
     ) -> Self {
         Self {
             globals,
             sui_events_worker_tx,
+            webhook_tx,
             workdir_idx,
         }
     }
@@ -84,8 +88,13 @@ impl Instantiable<PackagesPollerParams> for PollingTraitObject {
 
 impl PackagesPoller {
     pub fn new(params: PackagesPollerParams, subsys: &SubsystemHandle) -> Self {
-        let poller =
-            PollerWorker::<PollingTraitObject, PackagesPollerParams>::new(params.clone(), subsys);
+        // Self-scheduled every ~5 seconds (jitter avoids all workdirs polling at the same time).
+        let schedule = PollerSchedule::new(Duration::from_secs(5), 20, true);
+        let poller = PollerWorker::<PollingTraitObject, PackagesPollerParams>::new(
+            params.clone(),
+            Some(schedule),
+            subsys,
+        );
         Self { poller }
     }
 
@@ -330,8 +339,17 @@ impl PollingTraitObject {
                 if !to_be_added_packages.is_empty() {
                     let wp_resp = ui.get_mut_data();
                     for package_instance in to_be_added_packages {
+                        // The packages found on the first scan were not just published.
+                        let published_event = if no_change_resp_header {
+                            Some(Self::package_published_event(&workdir, &package_instance))
+                        } else {
+                            None
+                        };
                         if wp_resp.add_package_instance(package_instance, None) {
                             at_least_one_ui_change = true;
+                            if let Some(event) = published_event {
+                                self.params.webhook_tx.send_event(event);
+                            }
                         }
                     }
                 }
@@ -359,6 +377,16 @@ impl PollingTraitObject {
         }
     }
 
+    fn package_published_event(workdir: &str, package_instance: &PackageInstance) -> WebhookEvent {
+        let data = serde_json::json!({
+            "package_id": format!("0x{}", package_instance.get_package_id()),
+            "package_name": package_instance.get_package_name(),
+            "package_uuid": package_instance.get_package_uuid(),
+            "package_timestamp": package_instance.get_package_timestamp(),
+        });
+        WebhookEvent::new(WebhookEventType::PackagePublished, workdir, data)
+    }
+
     async fn get_published_data_path(&self, workdir_idx: WorkdirIdx) -> Result<PathBuf> {
         let workdir_path = {
             let workdirs_guard = self.params.globals.workdirs.read().await;
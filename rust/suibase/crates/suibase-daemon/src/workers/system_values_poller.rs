@@ -0,0 +1,229 @@
+// Child task of admin_controller
+//
+// One instance per workdir.
+//
+// Responsible to:
+//  - Periodically detect the epoch of the network (latest checkpoint).
+//  - Prefetch the values cached by the proxy (see SystemValues), so a client
+//    rarely has to wait for an upstream round trip for these.
+//
+// The requests go directly to the links (best first), not through the proxy
+// server (which would answer from the cache being refreshed).
+//
+// The task is auto-restart in case of panic.
+
+use crate::shared_types::{Globals, SystemValuesMT, SYSTEM_VALUES_METHODS, WORKDIRS_KEYS};
+
+use anyhow::{anyhow, bail, Result};
+use axum::async_trait;
+use common::{
+    basic_types::{EpochTimestamp, GenericTx, Instantiable, WorkdirContext, WorkdirIdx},
+    workers::{PollerSchedule, PollerWorker, PollingTrait},
+};
+
+use tokio::time::Duration;
+use tokio_graceful_shutdown::SubsystemHandle;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct SystemValuesPollerParams {
+    globals: Globals,
+    workdir_idx: WorkdirIdx,
+}
+
+impl WorkdirContext for SystemValuesPollerParams {
+    fn workdir_idx(&self) -> WorkdirIdx {
+        self.workdir_idx
+    }
+}
+
+impl SystemValuesPollerParams {
+    pub fn new(globals: Globals, workdir_idx: WorkdirIdx) -> Self {
+        Self {
+            globals,
+            workdir_idx,
+        }
+    }
+}
+
+pub struct SystemValuesPoller {
+    // "Glue" the specialized PollingTraitObject with its parameters.
+    // The worker does all the background task/events handling.
+    poller: PollerWorker<PollingTraitObject, SystemValuesPollerParams>,
+}
+
+pub struct PollingTraitObject {
+    params: SystemValuesPollerParams,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl PollingTrait for PollingTraitObject {
+    // This is called by the PollerWorker task.
+    async fn update(&mut self) {
+        self.update_system_values().await;
+    }
+}
+
+// This allow the PollerWorker to instantiate the PollingTraitObject.
+impl Instantiable<SystemValuesPollerParams> for PollingTraitObject {
+    fn new(params: SystemValuesPollerParams) -> Self {
+        Self {
+            params,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl SystemValuesPoller {
+    pub fn new(params: SystemValuesPollerParams, subsys: &SubsystemHandle) -> Self {
+        // Self-scheduled every ~30 seconds (an epoch change is noticed within that delay).
+        let schedule = PollerSchedule::new(Duration::from_secs(30), 20, true);
+        let poller = PollerWorker::<PollingTraitObject, SystemValuesPollerParams>::new(
+            params.clone(),
+            Some(schedule),
+            subsys,
+        );
+        Self { poller }
+    }
+
+    pub fn get_tx_channel(&self) -> GenericTx {
+        self.poller.get_tx_channel()
+    }
+}
+
+impl PollingTraitObject {
+    // The cache and the links to use, or None when the cache is not in use.
+    async fn cache_and_targets(&self) -> Option<(SystemValuesMT, Vec<String>)> {
+        let workdir_name = WORKDIRS_KEYS.get(self.params.workdir_idx as usize)?;
+        let globals = self.params.globals.proxy.read().await;
+        let input_port = globals.find_input_port_by_name(workdir_name)?;
+        if !input_port.is_proxy_enabled() || !input_port.is_proxy_serve_cached_system_values() {
+            return None;
+        }
+        let mut targets = Vec::new();
+        input_port.get_best_target_servers(&mut targets, &EpochTimestamp::now(), None);
+        if targets.is_empty() {
+            return None;
+        }
+        let targets = targets.into_iter().map(|(_, uri)| uri).collect();
+        Some((input_port.system_values(), targets))
+    }
+
+    // The "result" of a JSON-RPC call from the first link that succeeds.
+    async fn call(
+        &self,
+        targets: &[String],
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let mut last_error = anyhow!("no link");
+        for target_uri in targets {
+            let resp = self
+                .client
+                .post(target_uri)
+                .timeout(REQUEST_TIMEOUT)
+                .json(&body)
+                .send()
+                .await;
+            let resp: serde_json::Value = match resp {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(resp) => match resp.json().await {
+                        Ok(json) => json,
+                        Err(e) => {
+                            last_error = e.without_url().into();
+                            continue;
+                        }
+                    },
+                    Err(e) => {
+                        last_error = e.without_url().into();
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    last_error = e.without_url().into();
+                    continue;
+                }
+            };
+            match resp.get("result") {
+                Some(result) if !result.is_null() => return Ok(result.clone()),
+                _ => last_error = anyhow!("{} unexpected response: {}", method, resp),
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn fetch_epoch(&self, targets: &[String]) -> Result<u64> {
+        let seq = self
+            .call(
+                targets,
+                "sui_getLatestCheckpointSequenceNumber",
+                serde_json::json!([]),
+            )
+            .await?;
+        let checkpoint = self
+            .call(targets, "sui_getCheckpoint", serde_json::json!([seq]))
+            .await?;
+        match checkpoint["epoch"].as_str().and_then(|e| e.parse().ok()) {
+            Some(epoch) => Ok(epoch),
+            None => bail!(
+                "sui_getCheckpoint unexpected epoch: {}",
+                checkpoint["epoch"]
+            ),
+        }
+    }
+
+    async fn update_system_values(&self) {
+        let (cache, targets) = match self.cache_and_targets().await {
+            Some(cache_and_targets) => cache_and_targets,
+            None => return,
+        };
+        let workdir_name = WORKDIRS_KEYS[self.params.workdir_idx as usize];
+
+        match self.fetch_epoch(&targets).await {
+            Ok(epoch) => {
+                let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+                let previous = cache.epoch();
+                if cache.set_epoch(epoch) {
+                    // Also a localnet regen (the epoch goes back to 0).
+                    log::info!(
+                        "{} epoch {} -> {} (cached system values invalidated)",
+                        workdir_name,
+                        previous.unwrap_or_default(),
+                        epoch
+                    );
+                }
+            }
+            Err(e) => {
+                // Keep the values until they become stale on their own.
+                log::debug!("{} epoch not detected: {}", workdir_name, e);
+                return;
+            }
+        }
+
+        for method in SYSTEM_VALUES_METHODS {
+            if !cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_refresh_due(method, &EpochTimestamp::now())
+            {
+                continue;
+            }
+            match self.call(&targets, method, serde_json::json!([])).await {
+                Ok(result) => cache.lock().unwrap_or_else(|e| e.into_inner()).set(
+                    method,
+                    result,
+                    EpochTimestamp::now(),
+                ),
+                Err(e) => log::debug!("{} {} prefetch failed: {}", workdir_name, method, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+// Top level task (one instance for the daemon).
+//
+// Responsible to POST the webhook events (see shared_types/webhooks.rs) to every
+// configured webhook accepting the event type.
+//
+// A failed delivery is retried with an exponential backoff, without delaying the
+// delivery of the other events. It is dropped (and counted) after WEBHOOK_MAX_ATTEMPTS.
+//
+// The config is sent by the AdminController on every change of the common suibase.yaml.
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::time::{Duration, Instant};
+use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+
+use crate::shared_types::{
+    WebhookConfig, WebhookEvent, WebhookMsg, WebhookRx, WebhookStats, WEBHOOK_EVENT_HEADER,
+    WEBHOOK_SIGNATURE_HEADER,
+};
+
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_BACKOFF_INITIAL: Duration = Duration::from_millis(500);
+const WEBHOOK_BACKOFF_MAX: Duration = Duration::from_secs(30);
+const WEBHOOK_MAX_PENDING: usize = 1000; // Deliveries waiting for a retry.
+
+// Value of the WEBHOOK_SIGNATURE_HEADER.
+pub fn sign_webhook_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    let signature = mac.finalize().into_bytes();
+    format!("sha256={}", data_encoding::HEXLOWER.encode(&signature))
+}
+
+// Delay before the next attempt, after 'attempts' failed attempts.
+fn backoff(attempts: u32) -> Duration {
+    let factor = 1u32 << attempts.saturating_sub(1).min(16);
+    WEBHOOK_BACKOFF_INITIAL
+        .saturating_mul(factor)
+        .min(WEBHOOK_BACKOFF_MAX)
+}
+
+struct Delivery {
+    url: String,
+    event_name: &'static str,
+    body: String,
+    signature: Option<String>,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+pub struct WebhookWorker {
+    webhook_rx: WebhookRx,
+    stats: Arc<WebhookStats>,
+    config: Vec<WebhookConfig>,
+    pending: Vec<Delivery>,
+    client: reqwest::Client,
+}
+
+impl WebhookWorker {
+    pub fn new(webhook_rx: WebhookRx, stats: Arc<WebhookStats>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            webhook_rx,
+            stats,
+            config: Vec::new(),
+            pending: Vec::new(),
+            client,
+        }
+    }
+
+    async fn post(&self, delivery: &Delivery) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_EVENT_HEADER, delivery.event_name)
+            .body(delivery.body.clone());
+        if let Some(signature) = &delivery.signature {
+            request = request.header(WEBHOOK_SIGNATURE_HEADER, signature);
+        }
+        let resp = request.send().await.map_err(|e| e.to_string())?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("HTTP {}", resp.status()))
+        }
+    }
+
+    async fn attempt(&mut self, mut delivery: Delivery) {
+        delivery.attempts += 1;
+        match self.post(&delivery).await {
+            Ok(()) => self.stats.inc_delivered(),
+            Err(e) => {
+                if delivery.attempts >= WEBHOOK_MAX_ATTEMPTS
+                    || self.pending.len() >= WEBHOOK_MAX_PENDING
+                {
+                    self.stats.inc_dropped();
+                    log::warn!(
+                        "webhook {} {} dropped after {} attempt(s): {}",
+                        delivery.url,
+                        delivery.event_name,
+                        delivery.attempts,
+                        e
+                    );
+                } else {
+                    self.stats.inc_retried();
+                    log::debug!(
+                        "webhook {} {} failed: {}",
+                        delivery.url,
+                        delivery.event_name,
+                        e
+                    );
+                    delivery.next_attempt = Instant::now() + backoff(delivery.attempts);
+                    self.pending.push(delivery);
+                }
+            }
+        }
+    }
+
+    async fn dispatch(&mut self, event: WebhookEvent) {
+        let body = event.payload().to_string();
+        let now = Instant::now();
+        let deliveries: Vec<Delivery> = self
+            .config
+            .iter()
+            .filter(|config| config.accepts(event.event_type))
+            .map(|config| Delivery {
+                url: config.url.clone(),
+                event_name: event.event_type.as_str(),
+                body: body.clone(),
+                signature: config
+                    .secret
+                    .as_ref()
+                    .map(|secret| sign_webhook_payload(secret, body.as_bytes())),
+                attempts: 0,
+                next_attempt: now,
+            })
+            .collect();
+        for delivery in deliveries {
+            self.attempt(delivery).await;
+        }
+    }
+
+    async fn retry_pending(&mut self) {
+        let now = Instant::now();
+        let (due, not_due): (Vec<Delivery>, Vec<Delivery>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|delivery| delivery.next_attempt <= now);
+        self.pending = not_due;
+        for delivery in due {
+            self.attempt(delivery).await;
+        }
+    }
+
+    async fn event_loop(&mut self, subsys: &SubsystemHandle) {
+        while !subsys.is_shutdown_requested() {
+            let next_attempt = self
+                .pending
+                .iter()
+                .map(|delivery| delivery.next_attempt)
+                .min();
+            let msg = tokio::select! {
+                msg = self.webhook_rx.recv() => msg,
+                _ = tokio::time::sleep_until(next_attempt.unwrap_or_else(Instant::now)),
+                    if next_attempt.is_some() =>
+                {
+                    self.retry_pending().await;
+                    continue;
+                }
+            };
+            match msg {
+                Some(WebhookMsg::Config(config)) => {
+                    // Deliveries pending for a removed webhook are still attempted.
+                    self.config = config;
+                }
+                Some(WebhookMsg::Event(event)) => {
+                    common::mpsc_q_check!(self.webhook_rx);
+                    self.dispatch(event).await;
+                }
+                None => return, // Channel closed.
+            }
+        }
+    }
+
+    pub async fn run(mut self, subsys: SubsystemHandle) -> anyhow::Result<()> {
+        log::info!("started");
+
+        match self.event_loop(&subsys).cancel_on_shutdown(&subsys).await {
+            Ok(()) => {
+                log::info!("normal thread exit (2)");
+                Ok(())
+            }
+            Err(_cancelled_by_shutdown) => {
+                log::info!("normal thread exit (1)");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use common::basic_types::{EpochTimestamp, MPSC_Q_SIZE};
+    use tokio_graceful_shutdown::{SubsystemBuilder, Toplevel};
+
+    use crate::network_monitor::{NetworkMonitor, ProxyHandlerReport};
+    use crate::shared_types::{
+        GlobalsProxyMT, GlobalsProxyST, InputPort, WebhookEventType, WebhookTx, WorkdirUserConfig,
+        SEND_FAILED_UNSPECIFIED_ERROR,
+    };
+
+    type Received = tokio::sync::mpsc::Sender<(HeaderMap, String)>;
+
+    async fn receive(State(tx): State<Received>, headers: HeaderMap, body: String) -> StatusCode {
+        let _ = tx.send((headers, body)).await;
+        StatusCode::OK
+    }
+
+    #[test]
+    fn test_sign_and_backoff() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            sign_webhook_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(backoff(1), WEBHOOK_BACKOFF_INITIAL);
+        assert_eq!(backoff(2), WEBHOOK_BACKOFF_INITIAL * 2);
+        assert_eq!(backoff(40), WEBHOOK_BACKOFF_MAX);
+    }
+
+    #[tokio::test]
+    async fn test_link_down_webhook() {
+        // Local receiver of the webhooks.
+        let (received_tx, mut received_rx) = tokio::sync::mpsc::channel(10);
+        let app = Router::new()
+            .route("/ci", post(receive))
+            .with_state(received_tx);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let receiver_port = listener.local_addr().unwrap().port();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        // One workdir with a single (mock) link.
+        let dir = std::env::temp_dir().join(format!("sbsd-webhook-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let yaml = dir.join("suibase.yaml");
+        std::fs::write(
+            &yaml,
+            "links:\n  - alias: \"mock\"\n    rpc: \"http://127.0.0.1:1\"\n",
+        )
+        .unwrap();
+        let mut config = WorkdirUserConfig::new();
+        config
+            .load_and_merge_from_file(yaml.to_str().unwrap())
+            .unwrap();
+        let mut input_port = InputPort::new(0, "localnet".to_string(), &config);
+        for link in config.links().values() {
+            input_port.add_target_server(link);
+        }
+        let server_idx = input_port.target_servers.iter().next().unwrap().0;
+        let mut globals_st = GlobalsProxyST::new();
+        let port_idx = globals_st.input_ports.push(input_port).unwrap();
+        let globals: GlobalsProxyMT = Arc::new(tokio::sync::RwLock::new(globals_st));
+
+        let stats = Arc::new(WebhookStats::new());
+        let (webhook_tx, webhook_rx) = WebhookTx::channel(stats.clone());
+        webhook_tx
+            .send_config(vec![WebhookConfig {
+                url: format!("http://127.0.0.1:{}/ci", receiver_port),
+                secret: Some("s3cret".to_string()),
+                events: vec![WebhookEventType::LinkStatusChange],
+            }])
+            .await;
+
+        let (netmon_tx, netmon_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let netmon = NetworkMonitor::new(globals, netmon_rx, netmon_tx.clone(), webhook_tx);
+        let webhook_worker = WebhookWorker::new(webhook_rx, stats.clone());
+        let toplevel = tokio::spawn(
+            Toplevel::new(move |s| async move {
+                s.start(SubsystemBuilder::new("netmon", |a| netmon.run(a)));
+                s.start(SubsystemBuilder::new("webhooks", |a| webhook_worker.run(a)));
+            })
+            .handle_shutdown_requests(Duration::from_millis(1000)),
+        );
+
+        // Flip the link to OK and then to DOWN.
+        let mut report = ProxyHandlerReport::new(&netmon_tx, port_idx, EpochTimestamp::now());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let now = EpochTimestamp::now();
+        report
+            .req_resp_ok(server_idx, now, now, 0, 200, None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        report
+            .send_failed(
+                server_idx,
+                EpochTimestamp::now(),
+                SEND_FAILED_UNSPECIFIED_ERROR,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let mut statuses = Vec::new();
+        while statuses.len() < 2 {
+            let (headers, body) = tokio::time::timeout(Duration::from_secs(5), received_rx.recv())
+                .await
+                .expect("webhook not received")
+                .unwrap();
+            assert_eq!(headers[WEBHOOK_EVENT_HEADER], "link_status_change");
+            assert_eq!(
+                headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap(),
+                sign_webhook_payload("s3cret", body.as_bytes())
+            );
+            let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(payload["workdir"], "localnet");
+            assert_eq!(payload["data"]["alias"], "mock");
+            statuses.push(payload["data"]["status"].as_str().unwrap().to_string());
+        }
+        assert_eq!(statuses, vec!["OK", "DOWN"]);
+        assert_eq!(stats.delivered(), 2);
+        assert_eq!(stats.dropped(), 0);
+
+        toplevel.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_drop_after_max_attempts() {
+        // Nothing listening on that port.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let stats = Arc::new(WebhookStats::new());
+        let (_webhook_tx, webhook_rx) = WebhookTx::channel(stats.clone());
+        let mut worker = WebhookWorker::new(webhook_rx, stats.clone());
+        worker.config = vec![WebhookConfig {
+            url: format!("http://127.0.0.1:{}", port),
+            secret: None,
+            events: WebhookEventType::ALL.to_vec(),
+        }];
+
+        let data = serde_json::json!({ "package_name": "demo" });
+        let event = WebhookEvent::new(WebhookEventType::PackagePublished, "localnet", data);
+        worker.dispatch(event).await;
+        assert_eq!(worker.pending.len(), 1);
+
+        // Retry without waiting for the backoff.
+        for _ in 1..WEBHOOK_MAX_ATTEMPTS {
+            worker.pending[0].next_attempt = Instant::now();
+            worker.retry_pending().await;
+        }
+        assert!(worker.pending.is_empty());
+        assert_eq!(stats.retried(), (WEBHOOK_MAX_ATTEMPTS - 1) as u64);
+        assert_eq!(stats.dropped(), 1);
+        assert_eq!(stats.delivered(), 0);
+    }
+}
@@ -1,8 +1,12 @@
 // Run a webserver to serve all files in a specified directory.
 //
 // The tokio task is auto-restart in case of panic.
+//
+// Listens on 'sui_explorer_port' (common suibase.yaml), or a nearby port when
+// already in use. The port actually used is in the globals (see getExplorerInfo)
+// and in active-ports.yaml.
 
-use crate::shared_types::Globals;
+use crate::shared_types::{choose_port, is_port_free, load_common_config, ActivePorts, Globals};
 
 use anyhow::Result;
 use axum::async_trait;
@@ -96,52 +100,52 @@ impl WebserverTask {
             // Serve whatever is at "~/suibase/typescript/website_name"
             format!("{}/{}", self.websites_root, self.params.website_name)
         };
-
-        let index_html_fallback = format!("{}/index.html", static_files_path);
-
-        // Use tower to handle the serving of the static files + index.html
-        let tower_srvc = tower_http::services::ServeDir::new(static_files_path)
-            .append_index_html_on_directories(true)
-            .not_found_service(tower_http::services::ServeFile::new(index_html_fallback));
-
-        // CORS to accept requests from any origin
-        /*let cors = tower_http::cors::CorsLayer::new()
-                    .allow_origin(tower_http::cors::AllowOrigin::list(vec![
-                        axum::http::HeaderValue::from_static("http://localhost:9000"),
-                    ]))
-                    .allow_methods(tower_http::cors::Any)
-                    .allow_headers(tower_http::cors::Any);
-        */
-        let cors = tower_http::cors::CorsLayer::new()
-            .allow_origin(tower_http::cors::AllowOrigin::any())
-            .allow_methods(tower_http::cors::Any)
-            .allow_headers(tower_http::cors::Any);
-
-        // Setup the router to always use the tower service.
-        //
-        // There is no caching, the files are purposely read and served on each request.
-        //
-        // Why? The Suibase webserver favor KISS over performance (modifying the files
-        // update the "website" on next request/refresh).
-        let app = axum::Router::new()
-            .fallback(
-                axum::routing::get_service(tower_srvc).handle_error(|error| async move {
-                    (
-                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Unhandled internal error: {}", error),
-                    )
-                }),
-            )
-            .layer(cors);
-
-        // Define the address to serve on
-        //
-        // TODO Get this from the yaml configuration.
-        let addr = std::net::SocketAddr::from(([0, 0, 0, 0], 44380));
-        log::info!("{} listening on {}", self.task_name, addr);
+        let app = router(&static_files_path);
+
+        // Another daemon (e.g. of another user on the same host) may already listen on
+        // the configured port. Fallback to a nearby port (see ActivePorts).
+        let globals = &self.params.globals;
+        let common_config = load_common_config(globals).await;
+        let configured_port = common_config.sui_explorer_port();
+        let listener = choose_port(
+            configured_port,
+            common_config.port_fallback_range(),
+            common_config.is_strict_ports(),
+            is_port_free,
+        )
+        .and_then(bind_listener);
+
+        {
+            let mut config_guard = globals.config.write().await;
+            config_guard.explorer_port = configured_port;
+            match &listener {
+                Ok((_, port)) => {
+                    config_guard.explorer_port_active = Some(*port);
+                    config_guard.explorer_port_error = (*port != configured_port).then(|| {
+                        format!("port {} already in use (using {})", configured_port, port)
+                    });
+                }
+                Err(e) => {
+                    config_guard.explorer_port_active = None;
+                    config_guard.explorer_port_error = Some(e.clone());
+                }
+            }
+        }
+        ActivePorts::save(globals).await;
+
+        let (listener, port) = listener.map_err(anyhow::Error::msg)?;
+        if port != configured_port {
+            log::warn!(
+                "{} port {} already in use, listening on port {}",
+                self.task_name,
+                configured_port,
+                port
+            );
+        }
+        log::info!("{} listening on port {}", self.task_name, port);
 
         // Run the server
-        axum::Server::bind(&addr)
+        axum::Server::from_tcp(listener)?
             .serve(app.into_make_service())
             .await
             .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
@@ -149,3 +153,91 @@ impl WebserverTask {
         Ok(())
     }
 }
+
+// Router serving the static files, with index.html for any other path (the explorer
+// is a single page app, so a deep link like "/object/0x2" must also get the app).
+fn router(static_files_path: &str) -> axum::Router {
+    let index_html_fallback = format!("{}/index.html", static_files_path);
+
+    // Use tower to handle the serving of the static files + index.html
+    let tower_srvc = tower_http::services::ServeDir::new(static_files_path)
+        .append_index_html_on_directories(true)
+        .fallback(tower_http::services::ServeFile::new(index_html_fallback));
+
+    // CORS to accept requests from any origin
+    /*let cors = tower_http::cors::CorsLayer::new()
+                .allow_origin(tower_http::cors::AllowOrigin::list(vec![
+                    axum::http::HeaderValue::from_static("http://localhost:9000"),
+                ]))
+                .allow_methods(tower_http::cors::Any)
+                .allow_headers(tower_http::cors::Any);
+    */
+    let cors = tower_http::cors::CorsLayer::new()
+        .allow_origin(tower_http::cors::AllowOrigin::any())
+        .allow_methods(tower_http::cors::Any)
+        .allow_headers(tower_http::cors::Any);
+
+    // Setup the router to always use the tower service.
+    //
+    // There is no caching, the files are purposely read and served on each request.
+    //
+    // Why? The Suibase webserver favor KISS over performance (modifying the files
+    // update the "website" on next request/refresh).
+    axum::Router::new()
+        .fallback(
+            axum::routing::get_service(tower_srvc).handle_error(|error| async move {
+                (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Unhandled internal error: {}", error),
+                )
+            }),
+        )
+        .layer(cors)
+}
+
+// The error message is user facing (see getExplorerInfo).
+fn bind_listener(port: u16) -> Result<(std::net::TcpListener, u16), String> {
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+    std::net::TcpListener::bind(addr)
+        .map(|listener| (listener, port))
+        .map_err(|e| format!("port {} bind failed: {}", port, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serve_on_fallback_port() {
+        let dir = std::env::temp_dir().join(format!("sbsd-webserver-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), "<html>sui-explorer</html>").unwrap();
+
+        // The configured port is already in use.
+        let in_use = std::net::TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let configured_port = in_use.local_addr().unwrap().port();
+        let (listener, port) = choose_port(configured_port, 10, false, is_port_free)
+            .and_then(bind_listener)
+            .unwrap();
+        assert_ne!(port, configured_port);
+
+        let app = router(&dir.to_string_lossy());
+        let server = tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        // Deep links get the single page app.
+        for path in ["/", "/object/0x2?network=local"] {
+            let response = reqwest::get(format!("http://localhost:{}{}", port, path))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK, "{}", path);
+            assert!(response.text().await.unwrap().contains("sui-explorer"));
+        }
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -5,11 +5,14 @@
 //   - keep alive the connection with Ping
 //   - subscribe/unsubscribe to Sui events, filter and forward the
 //     validated data to its parent thread.
+//   - publish the delivery metrics of the subscriptions (see getSubscriptions).
 //
-// The thread is auto-restart in case of panic.
+// The thread is auto-restart in case of panic (and on a connection loss). The delivery
+// stats of the subscriptions are kept in the params to survive these restarts.
 
 use std::{collections::HashMap, sync::Arc};
 
+use crate::api::SubscriptionInfo;
 use crate::shared_types::{
     Globals, WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET, WORKDIR_IDX_MAINNET,
     WORKDIR_IDX_TESTNET,
@@ -22,7 +25,7 @@ use common::{
     basic_types::{
         self, AutoThread, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx,
     },
-    log_safe, mpsc_q_check,
+    log_safe_error, log_safe_warn, mpsc_q_check,
 };
 
 use futures::{
@@ -34,7 +37,21 @@ use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
 use common::basic_types::remove_generic_event_dups;
-use common::workers::{SubscriptionTracking, SubscriptionTrackingState};
+use common::workers::{SubscriptionDeliveryStats, SubscriptionTracking, SubscriptionTrackingState};
+
+// Websocket server used for a workdir (also see getSystemCheck).
+//
+// TODO Change this to the actual server URL from the config.
+// For now, use hard coded Mysten Labs servers...
+pub fn websocket_url(workdir_idx: WorkdirIdx) -> Option<&'static str> {
+    match workdir_idx {
+        WORKDIR_IDX_LOCALNET => Some("ws://localhost:9000"),
+        WORKDIR_IDX_DEVNET => Some("wss://fullnode.devnet.sui.io:443"),
+        WORKDIR_IDX_TESTNET => Some("wss://fullnode.testnet.sui.io:443"),
+        WORKDIR_IDX_MAINNET => Some("wss://fullnode.mainnet.sui.io:443"),
+        _ => None,
+    }
+}
 
 #[derive(Clone)]
 pub struct WebSocketWorkerParams {
@@ -44,6 +61,9 @@ pub struct WebSocketWorkerParams {
     events_writer_tx: GenericTx, // To send message to parent EventsWriterWorker.
     workdir_idx: WorkdirIdx,
     workdir_name: String,
+    ws_url: Option<String>, // Normally websocket_url() (a local server for the tests).
+    // Key is the package_id. Updated on every audit.
+    delivery_stats: Arc<Mutex<HashMap<String, SubscriptionDeliveryStats>>>,
 }
 
 impl WebSocketWorkerParams {
@@ -53,6 +73,7 @@ impl WebSocketWorkerParams {
         event_tx: GenericTx,
         events_writer_tx: GenericTx,
         workdir_idx: WorkdirIdx,
+        ws_url: Option<String>,
     ) -> Self {
         Self {
             globals,
@@ -61,6 +82,8 @@ impl WebSocketWorkerParams {
             events_writer_tx,
             workdir_idx,
             workdir_name: WORKDIRS_KEYS[workdir_idx as usize].to_string(),
+            ws_url,
+            delivery_stats: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
@@ -188,6 +211,7 @@ impl WebSocketWorkerThread {
         // Check for expected response (correlate using the JSON-RPC id).
         let mut trig_audit_event = false;
         let mut correlated_msg = false;
+        let mut subscribed: Option<Vec<String>> = None;
         if msg_seq_number != 0 {
             for package in self.package_subs.values_mut() {
                 let state = package.state();
@@ -212,6 +236,11 @@ impl WebSocketWorkerThread {
                         }
                         let unsubscribe_id = result.unwrap();
                         package.report_subscribing_response(unsubscribe_id.to_string());
+                        subscribed = Some(vec![
+                            package.uuid().clone(),
+                            package.name().clone(),
+                            package.package_filter().cloned().unwrap_or_default(),
+                        ]);
                         trig_audit_event = true;
                         break;
                     }
@@ -381,7 +410,19 @@ impl WebSocketWorkerThread {
                 workdir_idx: Some(self.params.workdir_idx),
                 resp_channel: None,
             };
-            if self.params.events_writer_tx.send(msg).await.is_err() {
+            let is_delivered = self.params.events_writer_tx.send(msg).await.is_ok();
+            if let Some(tracker) = self
+                .package_subs
+                .values_mut()
+                .find(|tracker| tracker.subscription_number() == subscription_number)
+            {
+                if is_delivered {
+                    tracker.report_event_delivered();
+                } else {
+                    tracker.report_event_dropped();
+                }
+            }
+            if !is_delivered {
                 log::error!(
                     "Failed to add_sui_event for workdir_idx={}",
                     self.params.workdir_idx
@@ -389,6 +430,25 @@ impl WebSocketWorkerThread {
             }
         }
 
+        if let Some(params) = subscribed {
+            // The events emitted while not subscribed are recovered by the parent (see
+            // events_sync.rs).
+            let msg = GenericChannelMsg {
+                event_id: basic_types::EVENT_EXEC,
+                command: Some("sui_events_subscribed".to_string()),
+                params,
+                data_json: None,
+                workdir_idx: Some(self.params.workdir_idx),
+                resp_channel: None,
+            };
+            if self.params.events_writer_tx.send(msg).await.is_err() {
+                log::error!(
+                    "Failed to send sui_events_subscribed for workdir_idx={}",
+                    self.params.workdir_idx
+                );
+            }
+        }
+
         if trig_audit_event {
             let msg = GenericChannelMsg {
                 event_id: basic_types::EVENT_AUDIT,
@@ -438,6 +498,8 @@ impl WebSocketWorkerThread {
         // log::info!("Received an audit message: {:?}", msg);
         let mut state_change = false;
         {
+            let delivery_stats = self.params.delivery_stats.lock().await;
+
             // Get a reader lock on the globals packages_config.
             let globals_read_guard = self.params.globals.get_packages(workdir_idx).read().await;
             let globals = &globals_read_guard;
@@ -454,12 +516,15 @@ impl WebSocketWorkerThread {
                     // Check if the package is already in the packages HashMap.
                     if !self.package_subs.contains_key(latest.get_package_id()) {
                         // Create a new PackagesTracking.
-                        let package_tracking = SubscriptionTracking::new_for_managed_package(
+                        let mut package_tracking = SubscriptionTracking::new_for_managed_package(
                             latest.get_package_name().to_string(),
                             latest.get_package_uuid().to_string(),
                             latest.get_package_timestamp().to_string(),
                             latest.get_package_id().to_string(),
                         );
+                        if let Some(stats) = delivery_stats.get(latest.get_package_id()) {
+                            package_tracking.set_delivery_stats(stats.clone());
+                        }
                         // Add the PackagesTracking to the packages HashMap.
                         self.package_subs
                             .insert(latest.get_package_id().to_string(), package_tracking);
@@ -545,6 +610,39 @@ impl WebSocketWorkerThread {
                 );
             }
         }
+
+        self.publish_subscriptions().await;
+    }
+
+    // Save the delivery stats (for the next thread, see delivery_stats in the params) and
+    // publish the metrics of every subscription for getSubscriptions.
+    async fn publish_subscriptions(&mut self) {
+        let mut subscriptions = Vec::new();
+        {
+            let mut delivery_stats = self.params.delivery_stats.lock().await;
+            delivery_stats.clear(); // Forget the packages no longer tracked.
+            for (package_id, tracker) in self.package_subs.iter_mut() {
+                delivery_stats.insert(package_id.clone(), tracker.delivery_stats().clone());
+                let metrics = tracker.metrics();
+                subscriptions.push(SubscriptionInfo {
+                    package_id: format!("0x{}", package_id),
+                    package_name: tracker.name().clone(),
+                    state: tracker.state().to_string(),
+                    events_delivered: metrics.events_delivered,
+                    events_dropped: metrics.events_dropped,
+                    resubscribe_count: metrics.resubscribe_count,
+                    last_event_at: metrics
+                        .last_event_secs
+                        .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+                        .map(|time| time.to_rfc3339()),
+                    degraded_reason: metrics.degraded_reason,
+                });
+            }
+        }
+        subscriptions.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+
+        let mut globals_write_guard = self.params.globals.subscriptions.write().await;
+        globals_write_guard.set(self.params.workdir_idx, subscriptions);
     }
 
     async fn process_update_msg(&mut self, msg: GenericChannelMsg) {
@@ -578,6 +676,8 @@ impl WebSocketWorkerThread {
 
         let mut trig_audit = false;
         {
+            let delivery_stats = self.params.delivery_stats.lock().await;
+
             // Get a writer lock on the globals ui.
             let mut globals_write_guard =
                 self.params.globals.get_packages(workdir_idx).write().await;
@@ -594,12 +694,15 @@ impl WebSocketWorkerThread {
                     // Check if the package is already in the packages HashMap.
                     if !self.package_subs.contains_key(latest.get_package_id()) {
                         // Create a new PackagesTracking.
-                        let package_tracking = SubscriptionTracking::new_for_managed_package(
+                        let mut package_tracking = SubscriptionTracking::new_for_managed_package(
                             latest.get_package_name().to_string(),
                             latest.get_package_uuid().to_string(),
                             latest.get_package_timestamp().to_string(),
                             latest.get_package_id().to_string(),
                         );
+                        if let Some(stats) = delivery_stats.get(latest.get_package_id()) {
+                            package_tracking.set_delivery_stats(stats.clone());
+                        }
                         // Add the PackagesTracking to the packages HashMap.
                         self.package_subs
                             .insert(latest.get_package_id().to_string(), package_tracking);
@@ -693,7 +796,7 @@ impl WebSocketWorkerThread {
             if let Some(ref mut write) = websocket.write {
                 log::info!("Sending subscribe message: {:?}", msg);
                 if let Err(e) = write.send(msg).await {
-                    log::error!("subscribe write.send error: {:?}", e);
+                    log_safe_error!("subscribe write.send error: {:?}", e);
                 } else {
                     log::info!("subscribe write.send success");
                 }
@@ -794,7 +897,7 @@ impl WebSocketWorkerThread {
             if let Some(ref mut write) = websocket.write {
                 log::info!("Sending unsubscribe message: {:?}", msg);
                 if let Err(e) = write.send(msg).await {
-                    log::error!("unsubscribe write.send error: {:?}", e);
+                    log_safe_error!("unsubscribe write.send error: {:?}", e);
                 } else {
                     log::info!("unsubscribe write.send success");
                 }
@@ -807,14 +910,9 @@ impl WebSocketWorkerThread {
     async fn open_websocket(&mut self) -> bool {
         // Open a websocket connection to the server for this workdir.
 
-        // TODO Change this to the actual server URL from the config.
-        // For now, use hard coded Mysten Labs servers...
-        let socket_url = match self.params.workdir_idx {
-            WORKDIR_IDX_LOCALNET => "ws://localhost:9000",
-            WORKDIR_IDX_DEVNET => "wss://fullnode.devnet.sui.io:443",
-            WORKDIR_IDX_TESTNET => "wss://fullnode.testnet.sui.io:443",
-            WORKDIR_IDX_MAINNET => "wss://fullnode.mainnet.sui.io:443",
-            _ => {
+        let socket_url = match self.params.ws_url.as_deref() {
+            Some(socket_url) => socket_url,
+            None => {
                 log::error!("Unexpected workdir_idx {:?}", self.params.workdir_idx);
                 return false;
             }
@@ -830,7 +928,7 @@ impl WebSocketWorkerThread {
                 if !e.to_string().contains("Connection refused") {
                     // "Connection refused" is annoying when localnet is not running, so ignore it.
                     // TODO Make this more "aware" about if localnet should be running or not.
-                    log_safe!(format!("connect_async error: {:?}", e));
+                    log_safe_warn!("connect_async error: {:?}", e);
                 }
                 self.websocket.write = None;
                 self.websocket.read = None;
@@ -1,5 +1,11 @@
 // These integration tests assume:
 //  - localnet is already installed
+//  - the suibase-daemon is running for the current user
+//
+// The API port is found in workdirs/common/active-ports.yaml of the Suibase
+// location (see common::utils::suibase_home), the daemon may use another port
+// than 44399 when already taken. SUIBASE_API_PORT overrides it (e.g. in a CI
+// container).
 
 use log;
 use serde_json::json;
@@ -11,9 +17,27 @@ fn init() {
         .try_init();
 }
 
+const DEFAULT_API_PORT: u16 = 44399;
+
+fn api_port() -> u16 {
+    if let Some(port) = std::env::var("SUIBASE_API_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+    {
+        return port;
+    }
+    common::utils::suibase_home()
+        .map(|suibase_home| suibase_home.join("workdirs/common/active-ports.yaml"))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_yaml::from_str::<serde_yaml::Value>(&contents).ok())
+        .and_then(|yaml| yaml["api_port"].as_u64())
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or(DEFAULT_API_PORT)
+}
+
 async fn api_request(method: &str) -> serde_json::Value {
     let client = reqwest::Client::new();
-    let request_url = "http://localhost:44399";
+    let request_url = format!("http://localhost:{}", api_port());
     let request_body = json!({
         "jsonrpc": "2.0",
         "id": 1,
@@ -21,7 +45,7 @@ async fn api_request(method: &str) -> serde_json::Value {
         "params": ["localnet"]
     });
 
-    let response = match client.post(request_url).json(&request_body).send().await {
+    let response = match client.post(&request_url).json(&request_body).send().await {
         Ok(response) => response,
         Err(e) => {
             log::error!("api_request error: {:?}", e);
@@ -61,3 +85,161 @@ async fn test_sanity_api() {
     log::info!("response_body: {}", response);
     assert_eq!(response["result"]["status"].as_str().unwrap(), "OK");
 }
+
+async fn api_call(method: &str, params: serde_json::Value) -> serde_json::Value {
+    let client = reqwest::Client::new();
+    let request_url = format!("http://localhost:{}", api_port());
+    let request_body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    let response = client
+        .post(&request_url)
+        .json(&request_body)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    response.json().await.unwrap()
+}
+
+// Poll getJobStatus until the job is no longer running.
+async fn wait_job_done(job_id: u64) -> serde_json::Value {
+    for _ in 0..300 {
+        let response = api_call("getJobStatus", json!([job_id])).await;
+        let job = response["result"]["job"].clone();
+        if job["state"] != "RUNNING" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    panic!("job {} still running", job_id);
+}
+
+async fn localnet_snapshot_op(method: &str, params: serde_json::Value) {
+    let response = api_call(method, params).await;
+    assert!(response["error"].is_null(), "{}", response);
+    let job_id = response["result"]["job"]["jobId"].as_u64().unwrap();
+    let job = wait_job_done(job_id).await;
+    log::info!("{}: {}", method, job);
+    assert_eq!(job["state"].as_str().unwrap(), "DONE");
+}
+
+#[tokio::test]
+async fn test_system_check() {
+    init();
+    let started = std::time::Instant::now();
+    let response = api_call("getSystemCheck", json!([])).await;
+    log::info!("getSystemCheck: {}", response);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    let result = &response["result"];
+    assert!(["pass", "warn", "fail"].contains(&result["status"].as_str().unwrap()));
+    let checks = result["checks"].as_array().unwrap();
+    for check in checks {
+        assert!(check["name"].is_string());
+        assert!(check["message"].is_string());
+        let status = check["status"].as_str().unwrap();
+        assert!(["pass", "warn", "fail"].contains(&status));
+        if status != "pass" {
+            assert!(check["hint"].is_string(), "{}", check);
+        }
+    }
+
+    // True whenever this daemon answers.
+    let status_of = |name: &str| {
+        checks
+            .iter()
+            .find(|check| check["name"] == name)
+            .map(|check| check["status"].as_str().unwrap().to_string())
+    };
+    assert_eq!(status_of("scripts").as_deref(), Some("pass"));
+    assert_eq!(status_of("daemon_lock").as_deref(), Some("pass"));
+    assert_eq!(status_of("localnet.state").as_deref(), Some("pass"));
+}
+
+#[tokio::test]
+async fn test_explorer_info() {
+    init();
+    let response = api_call("getExplorerInfo", json!([])).await;
+    log::info!("getExplorerInfo: {}", response);
+    let result = &response["result"];
+    assert!(result["configuredPort"].as_u64().unwrap() > 0);
+    let url = result["url"].as_str().unwrap();
+    let port = result["port"].as_u64().unwrap();
+    assert!(url.ends_with(&format!(":{}", port)));
+    if port != result["configuredPort"].as_u64().unwrap() {
+        assert!(result["info"].is_string());
+    }
+
+    // The explorer app is served only once built.
+    let index_html = common::utils::suibase_home()
+        .unwrap()
+        .join("typescript/sui-explorer/apps/explorer/build/index.html");
+    if !index_html.exists() {
+        log::warn!("sui-explorer not built, skipping the page check");
+        return;
+    }
+    let expected = std::fs::read_to_string(&index_html).unwrap();
+    for path in ["/", "/object/0x2?network=local"] {
+        let response = reqwest::get(format!("{}{}", url, path)).await.unwrap();
+        assert!(response.status().is_success(), "{}", path);
+        assert_eq!(response.text().await.unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires a running localnet (stops and restarts it)"]
+async fn test_localnet_snapshot_restore() {
+    init();
+    let suibase_path = common::utils::suibase_home().unwrap();
+    let snapshot_name = "integration-test";
+    let _ = api_call("deleteLocalnetSnapshot", json!([snapshot_name])).await;
+
+    localnet_snapshot_op("snapshotLocalnet", json!([snapshot_name])).await;
+    let response = api_call("listLocalnetSnapshots", json!([])).await;
+    let snapshots = response["result"]["snapshots"].as_array().unwrap();
+    assert!(snapshots.iter().any(|s| s["name"] == snapshot_name));
+
+    // Publish the demo package after the snapshot.
+    let status = std::process::Command::new(suibase_path.join("scripts/localnet"))
+        .arg("publish")
+        .current_dir(suibase_path.join("rust/demo-app"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let package_id_path =
+        suibase_path.join("workdirs/localnet/published-data/demo/most-recent/package-id.json");
+    let package_ids: Vec<String> =
+        serde_json::from_str(&std::fs::read_to_string(package_id_path).unwrap()).unwrap();
+    let package_id = package_ids[0].clone();
+
+    localnet_snapshot_op("restoreLocalnet", json!([snapshot_name, false])).await;
+
+    // The package was published after the snapshot, so no longer exists.
+    let response: serde_json::Value = reqwest::Client::new()
+        .post("http://localhost:9000")
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sui_getObject",
+            "params": [package_id, {}]
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        response["result"]["error"]["code"].as_str(),
+        Some("notExists"),
+        "{}",
+        response
+    );
+
+    let response = api_call("deleteLocalnetSnapshot", json!([snapshot_name])).await;
+    assert_eq!(response["result"]["result"].as_bool(), Some(true));
+}
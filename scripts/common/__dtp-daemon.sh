@@ -152,7 +152,7 @@ start_dtp_daemon() {
     # All errors will be visible through the dtp-services own logs or by observing
     # which PID owns the flock file. So all output of the script (if any) can
     # safely be ignored to /dev/null.
-    nohup "$HOME/suibase/scripts/common/run-daemon.sh" dtp >/dev/null 2>&1 &
+    nohup "$SUIBASE_DIR/scripts/common/run-daemon.sh" dtp >/dev/null 2>&1 &
 
     while [ $SECONDS -lt $end ]; do
       if is_dtp_daemon_running; then
@@ -42,7 +42,10 @@ if [[ "$SCRIPT_PATH" = *"sui-base"* ]]; then
 fi
 
 # Two key directories location.
-export SUIBASE_DIR="$HOME/suibase"
+#
+# SUIBASE_HOME overrides the default ~/suibase. The suibase-daemon sets it for the
+# scripts it runs, to its own location (which can also be from ~/.suibase-location).
+export SUIBASE_DIR="${SUIBASE_HOME:-$HOME/suibase}"
 export WORKDIRS="$SUIBASE_DIR/workdirs"
 
 # Some other commonly used locations.
@@ -690,6 +693,20 @@ update_suibase_yaml() {
   if [ -f "$YAML_FILE" ]; then
     eval "$(parse_yaml "$YAML_FILE" "CFG_")"
   fi
+
+  # The suibase-daemon may listen on other ports than configured (e.g. when
+  # another user on the same host already uses them). Use the ports it reports.
+  YAML_FILE="$WORKDIRS/common/active-ports.yaml"
+  if [ -f "$YAML_FILE" ]; then
+    eval "$(parse_yaml "$YAML_FILE" "ACTIVE_PORTS_")"
+    if [ -n "${ACTIVE_PORTS_api_port:-}" ]; then
+      CFG_suibase_api_port_number="$ACTIVE_PORTS_api_port"
+    fi
+    local _ACTIVE_PROXY_PORT_VAR="ACTIVE_PORTS_proxy_ports_${_WORKDIR}"
+    if [ -n "${!_ACTIVE_PROXY_PORT_VAR:-}" ]; then
+      CFG_proxy_port_number="${!_ACTIVE_PROXY_PORT_VAR}"
+    fi
+  fi
 }
 export -f update_suibase_yaml
 
@@ -2119,6 +2136,7 @@ stop_sui_process() {
     if [ -n "$SUI_PROCESS_PID" ]; then
       setup_error "Sui process pid=$SUI_PROCESS_PID still running. Try again, or stop (kill) the sui process yourself before proceeding."
     fi
+    rm -f "$WORKDIRS/$WORKDIR/.state/sui-process.pid"
   fi
 }
 export -f stop_sui_process
@@ -2136,9 +2154,13 @@ start_sui_process() {
     if $SUI_BASE_NET_MOCK; then
       SUI_PROCESS_PID=$SUI_BASE_NET_MOCK_PID
     else
-      nohup env SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE=1 SUI_PROTOCOL_CONFIG_OVERRIDE_min_checkpoint_interval_ms=1000 RUST_LOG="error" "$SUI_BIN_DIR/sui" start --network.config "$NETWORK_CONFIG" >"$CONFIG_DATA_DIR/sui-process.log" 2>&1 &
+      # Append mode, so the suibase-daemon can rotate the log while the process runs.
+      rm -f "$CONFIG_DATA_DIR/sui-process.log" >/dev/null 2>&1
+      nohup env SUI_PROTOCOL_CONFIG_OVERRIDE_ENABLE=1 SUI_PROTOCOL_CONFIG_OVERRIDE_min_checkpoint_interval_ms=1000 RUST_LOG="error" "$SUI_BIN_DIR/sui" start --network.config "$NETWORK_CONFIG" >>"$CONFIG_DATA_DIR/sui-process.log" 2>&1 &
+      # Recorded for the suibase-daemon to tell this process from an orphan (same pid
+      # as "sui", because nohup and env exec it).
+      echo "$!" >"$WORKDIRS/$WORKDIR/.state/sui-process.pid"
     fi
-    #NEW_PID=$!
 
     # Loop until "sui client" confirms being able to talk to the sui process, or exit
     # if that takes too long.
@@ -2201,6 +2223,7 @@ publish_clear_output() {
   rm -f "$_DIR/publish-output.json" >/dev/null 2>&1
   rm -f "$_DIR/created-objects.json" >/dev/null 2>&1
   rm -f "$_DIR/package-id.json" >/dev/null 2>&1
+  rm -f "$_DIR/build-metadata.json" >/dev/null 2>&1
 }
 export -f publish_clear_output
 
@@ -68,6 +68,8 @@ publish_all() {
   eval "$_CMD"
   #  TODO Investigate problem with exit status here...
 
+  record_build_metadata "$INSTALL_DIR"
+
   # Create the created_objects.json file.
   update_SUI_PUBLISH_TXDIGEST "$INSTALL_DIR"
   if [ -n "$SUI_PUBLISH_TXDIGEST" ]; then
@@ -180,6 +182,36 @@ publish_all() {
 }
 export -f publish_all
 
+json_escape() {
+  # Escape a string to be a JSON string value (without the surrounding quotes).
+  # Other control characters than newline, carriage return and tab are removed.
+  local _STR="$1"
+  _STR="${_STR//\\/\\\\}"
+  _STR="${_STR//\"/\\\"}"
+  _STR="${_STR//$'\n'/\\n}"
+  _STR="${_STR//$'\r'/\\r}"
+  _STR="${_STR//$'\t'/\\t}"
+  printf '%s' "$_STR" | tr -d '\000-\037'
+}
+export -f json_escape
+
+record_build_metadata() {
+  # Best-effort record of what produced the build artifacts (read by the
+  # rust helper package_build_info). Empty values when unknown.
+  local _INSTALL_DIR="$1"
+  local _SUI_VERSION _GIT_COMMIT
+  _SUI_VERSION=$($SUI_EXEC --version 2>/dev/null | head -n 1)
+  _GIT_COMMIT=$(git -C "$MOVE_TOML_DIR" rev-parse HEAD 2>/dev/null)
+  cat >"$_INSTALL_DIR/build-metadata.json" <<EOF
+{
+  "sui_version": "$(json_escape "$_SUI_VERSION")",
+  "source_path": "$(json_escape "$MOVE_TOML_DIR")",
+  "git_commit": "$(json_escape "$_GIT_COMMIT")"
+}
+EOF
+}
+export -f record_build_metadata
+
 export SUI_PUBLISH_TXDIGEST=""
 update_SUI_PUBLISH_TXDIGEST() {
   local _INSTALL_DIR="$1"
@@ -29,6 +29,7 @@ start_sui_faucet_process() {
     if $SUI_BASE_NET_MOCK; then
       export SUI_FAUCET_PROCESS_PID=$SUI_BASE_NET_MOCK_PID
     else
+      # Append mode, so the suibase-daemon can rotate the log (see start_sui_process).
       rm -f "$CONFIG_DATA_DIR/sui-faucet-process.log" >/dev/null 2>&1
       # sui-faucet does not support "localhost", so translate it to 127.0.0.1
       local _HOST_IP=${CFG_sui_faucet_host_ip:?}
@@ -43,7 +44,9 @@ start_sui_faucet_process() {
         --port "${CFG_sui_faucet_port:?}" \
         --request-buffer-size "${CFG_sui_faucet_request_buffer_size:?}" \
         --wallet-client-timeout-secs "${CFG_sui_faucet_client_timeout_secs:?}" \
-        --write-ahead-log "$CONFIG_DATA_DIR/faucet.wal" >"$CONFIG_DATA_DIR/sui-faucet-process.log" 2>&1 &
+        --write-ahead-log "$CONFIG_DATA_DIR/faucet.wal" >>"$CONFIG_DATA_DIR/sui-faucet-process.log" 2>&1 &
+      # Recorded for the suibase-daemon (see start_sui_process).
+      echo "$!" >"$WORKDIRS/$WORKDIR/.state/sui-faucet-process.pid"
     fi
 
     # Loop until confirms can connect, or exit if takes too much time.
@@ -134,6 +137,7 @@ stop_sui_faucet_process() {
     if [ -n "$SUI_FAUCET_PROCESS_PID" ]; then
       setup_error "sui-faucet process pid=$SUI_FAUCET_PROCESS_PID still running. Try again, or stop (kill) the process yourself before proceeding."
     fi
+    rm -f "$WORKDIRS/$WORKDIR/.state/sui-faucet-process.pid"
   fi
 }
 export -f stop_sui_faucet_process
@@ -848,6 +848,8 @@ workdir_exec() {
       rm -rf "$WORKDIRS/$WORKDIR/logs/sui.log" >/dev/null 2>&1
       rm -rf "$WORKDIRS/$WORKDIR/config/sui-process.log" >/dev/null 2>&1
       rm -rf "$WORKDIRS/$WORKDIR/config/sui-faucet-process.log" >/dev/null 2>&1
+      # Rotated by the suibase-daemon (e.g. sui-process.log.1.gz).
+      rm -f "$WORKDIRS/$WORKDIR/config/"sui-*process.log.*.gz >/dev/null 2>&1
       if [ -d "$WORKDIRS/$WORKDIR/sui-repo-default" ]; then
         rm -rf "$WORKDIRS/$WORKDIR/sui-repo-default" >/dev/null 2>&1
         info_exit "Logs, default repo and artifacts deleted. sui.keystore and client.yaml are NEVER deleted for '$WORKDIR'."
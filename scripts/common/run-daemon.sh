@@ -15,7 +15,7 @@ PARAM_NAME="$1"
 PARAM_CMD="$2"
 
 # Source '__globals.sh'.
-SUIBASE_DIR="$HOME/suibase"
+SUIBASE_DIR="${SUIBASE_HOME:-$HOME/suibase}"
 SCRIPT_COMMON_CALLER="$(readlink -f "$0")"
 WORKDIR="localnet"
 
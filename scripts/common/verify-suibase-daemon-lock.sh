@@ -1,7 +1,7 @@
 #!/bin/bash
 
 # Source '__globals.sh'.
-SUIBASE_DIR="$HOME/suibase"
+SUIBASE_DIR="${SUIBASE_HOME:-$HOME/suibase}"
 SCRIPT_COMMON_CALLER="$(readlink -f "$0")"
 WORKDIR="none"
 